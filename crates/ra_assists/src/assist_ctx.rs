@@ -1,6 +1,6 @@
 //! This module defines `AssistCtx` -- the API surface that is exposed to assists.
 use hir::Semantics;
-use ra_db::FileRange;
+use ra_db::{FileId, FileRange};
 use ra_fmt::{leading_indent, reindent};
 use ra_ide_db::RootDatabase;
 use ra_syntax::{
@@ -8,9 +8,13 @@ use ra_syntax::{
     AstNode, SourceFile, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextUnit,
     TokenAtOffset,
 };
-use ra_text_edit::TextEditBuilder;
+use ra_text_edit::{TextEdit, TextEditBuilder};
+use rustc_hash::FxHashMap;
 
-use crate::{AssistAction, AssistId, AssistLabel, GroupLabel, ResolvedAssist};
+use crate::{
+    utils::escape_snippet, AssistAction, AssistConfig, AssistFile, AssistId, AssistLabel,
+    GroupLabel, ResolvedAssist,
+};
 use algo::SyntaxRewriter;
 
 #[derive(Clone, Debug)]
@@ -80,6 +84,7 @@ pub(crate) struct AssistCtx<'a> {
     pub(crate) sema: &'a Semantics<'a, RootDatabase>,
     pub(crate) db: &'a RootDatabase,
     pub(crate) frange: FileRange,
+    pub(crate) config: &'a AssistConfig,
     source_file: SourceFile,
     should_compute_edit: bool,
 }
@@ -88,10 +93,11 @@ impl<'a> AssistCtx<'a> {
     pub fn new(
         sema: &'a Semantics<'a, RootDatabase>,
         frange: FileRange,
+        config: &'a AssistConfig,
         should_compute_edit: bool,
     ) -> AssistCtx<'a> {
         let source_file = sema.parse(frange.file_id);
-        AssistCtx { sema, db: sema.db, frange, source_file, should_compute_edit }
+        AssistCtx { sema, db: sema.db, frange, config, source_file, should_compute_edit }
     }
 
     pub(crate) fn add_assist(
@@ -180,6 +186,10 @@ pub(crate) struct ActionBuilder {
     edit: TextEditBuilder,
     cursor_position: Option<TextUnit>,
     target: Option<TextRange>,
+    /// The ranges replaced by snippets, to escape the other edits.
+    snippet_ranges: Vec<TextRange>,
+    file: AssistFile,
+    other_edits: FxHashMap<FileId, TextEditBuilder>,
 }
 
 impl ActionBuilder {
@@ -214,6 +224,19 @@ impl ActionBuilder {
         self.edit.insert(offset, text.into())
     }
 
+    /// Inserts a snippet, in which `$0` or `${0:placeholder}` marks where
+    /// the cursor ends up. Clients which don't support snippets get the plain
+    /// text and a cursor position instead.
+    pub(crate) fn insert_snippet(&mut self, offset: TextUnit, snippet: impl Into<String>) {
+        self.replace_snippet(TextRange::offset_len(offset, 0.into()), snippet)
+    }
+
+    /// Replaces specified `range` of text with a snippet, see `insert_snippet`.
+    pub(crate) fn replace_snippet(&mut self, range: TextRange, snippet: impl Into<String>) {
+        self.snippet_ranges.push(range);
+        self.replace(range, snippet)
+    }
+
     /// Specify desired position of the cursor after the assist is applied.
     pub(crate) fn set_cursor(&mut self, offset: TextUnit) {
         self.cursor_position = Some(offset)
@@ -227,11 +250,22 @@ impl ActionBuilder {
         self.target = Some(target)
     }
 
+    /// Makes the edits apply to another file than the current one.
+    pub(crate) fn set_file(&mut self, assist_file: AssistFile) {
+        self.file = assist_file
+    }
+
     /// Get access to the raw `TextEditBuilder`.
     pub(crate) fn text_edit_builder(&mut self) -> &mut TextEditBuilder {
         &mut self.edit
     }
 
+    /// Get access to the `TextEditBuilder` of `file_id`, which must be another
+    /// file than the one the other edits apply to.
+    pub(crate) fn other_file_edit_builder(&mut self, file_id: FileId) -> &mut TextEditBuilder {
+        self.other_edits.entry(file_id).or_default()
+    }
+
     pub(crate) fn replace_ast<N: AstNode>(&mut self, old: N, new: N) {
         algo::diff(old.syntax(), new.syntax()).into_text_edit(&mut self.edit)
     }
@@ -242,10 +276,39 @@ impl ActionBuilder {
     }
 
     fn build(self) -> AssistAction {
+        let mut edit = self.edit.finish();
+        let mut other_edits: Vec<(FileId, TextEdit)> =
+            self.other_edits.into_iter().map(|(file_id, it)| (file_id, it.finish())).collect();
+        other_edits.sort_by_key(|(file_id, _)| *file_id);
+        let is_snippet = !self.snippet_ranges.is_empty();
+        if is_snippet {
+            // The whole edit is interpreted as a snippet, so the text of the
+            // plain edits must not expand.
+            edit = escape_plain_edits(&edit, &self.snippet_ranges);
+            for (_, edit) in other_edits.iter_mut() {
+                *edit = escape_plain_edits(edit, &[]);
+            }
+        }
         AssistAction {
-            edit: self.edit.finish(),
+            edit,
             cursor_position: self.cursor_position,
+            is_snippet,
             target: self.target,
+            file: self.file,
+            other_edits,
         }
     }
 }
+
+fn escape_plain_edits(edit: &TextEdit, snippet_ranges: &[TextRange]) -> TextEdit {
+    let mut builder = TextEditBuilder::default();
+    for atom in edit.as_atoms() {
+        let insert = if snippet_ranges.contains(&atom.delete) {
+            atom.insert.clone()
+        } else {
+            escape_snippet(&atom.insert)
+        };
+        builder.replace(atom.delete, insert);
+    }
+    builder.finish()
+}
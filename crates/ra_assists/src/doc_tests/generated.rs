@@ -41,6 +41,25 @@ struct Point {
     )
 }
 
+#[test]
+fn doctest_add_enum_variant() {
+    check(
+        "add_enum_variant",
+        r#####"
+enum Color { Red }
+fn main() {
+    let c = Color::Teal<|>(92);
+}
+"#####,
+        r#####"
+enum Color { Red, Teal(i32) }
+fn main() {
+    let c = Color::Teal(92);
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_add_explicit_type() {
     check(
@@ -77,8 +96,8 @@ fn foo() {
      bar("", baz());
 }
 
-fn bar(arg: &str, baz: Baz) {
-    unimplemented!()
+fn bar(arg: &str, baz: Baz) ${0:-> _} {
+    todo!()
 }
 
 "#####,
@@ -187,6 +206,31 @@ impl Trait<u32> for () {
     )
 }
 
+#[test]
+fn doctest_add_method() {
+    check(
+        "add_method",
+        r#####"
+struct Foo;
+impl Foo {}
+fn foo(x: Foo) {
+    x.bar<|>(92);
+}
+"#####,
+        r#####"
+struct Foo;
+impl Foo {
+    fn bar(&self, arg: i32) ${0:-> _} {
+        todo!()
+    }
+}
+fn foo(x: Foo) {
+    x.bar(92);
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_add_new() {
     check(
@@ -209,6 +253,43 @@ impl<T: Clone> Ctx<T> {
     )
 }
 
+#[test]
+fn doctest_add_struct() {
+    check(
+        "add_struct",
+        r#####"
+fn main() {
+    let p = Point<|> { x: 1, y: 2.0 };
+}
+"#####,
+        r#####"
+fn main() {
+    let p = Point { x: 1, y: 2.0 };
+}
+
+struct Point {
+    x: i32,
+    y: f64,
+}
+"#####,
+    )
+}
+
+#[test]
+fn doctest_add_type_alias() {
+    check(
+        "add_type_alias",
+        r#####"
+fn frobnicate(x: Frobnicator<|>) {}
+"#####,
+        r#####"
+fn frobnicate(x: Frobnicator) {}
+
+type Frobnicator = ${0:()};
+"#####,
+    )
+}
+
 #[test]
 fn doctest_apply_demorgan() {
     check(
@@ -284,6 +365,94 @@ fn main() {
     )
 }
 
+#[test]
+fn doctest_demote_field_to_local() {
+    check(
+        "demote_field_to_local",
+        r#####"
+struct Counter { count: u32, <|>step: u32 }
+impl Counter {
+    fn new() -> Counter { Counter { count: 0, step: 2 } }
+    fn tick(&mut self) {
+        self.count += self.step;
+    }
+}
+"#####,
+        r#####"
+struct Counter { count: u32 }
+impl Counter {
+    fn new() -> Counter { Counter { count: 0 } }
+    fn tick(&mut self) {
+        let step: u32 = 2;
+        self.count += step;
+    }
+}
+"#####,
+    )
+}
+
+#[test]
+fn doctest_extract_module() {
+    check(
+        "extract_module",
+        r#####"
+struct Point { x: i32, y: i32 }
+
+<|>fn norm(p: &Point) -> i32 {
+    p.x.abs() + p.y.abs()
+}<|>
+
+fn main() {
+    norm(&Point { x: 1, y: 2 });
+}
+"#####,
+        r#####"
+struct Point { x: i32, y: i32 }
+
+mod ${1:modname} {
+    use super::Point;
+
+    pub(crate) fn norm(p: &Point) -> i32 {
+        p.x.abs() + p.y.abs()
+    }
+}
+
+use $1::norm;
+
+fn main() {
+    norm(&Point { x: 1, y: 2 });
+}
+"#####,
+    )
+}
+
+#[test]
+fn doctest_extract_struct_from_enum_variant() {
+    check(
+        "extract_struct_from_enum_variant",
+        r#####"
+enum A { <|>One { a: u32, b: u32 } }
+
+fn f(a: A) -> u32 {
+    match a {
+        A::One { a, .. } => a,
+    }
+}
+"#####,
+        r#####"
+struct One { a: u32, b: u32 }
+
+enum A { One(One) }
+
+fn f(a: A) -> u32 {
+    match a {
+        A::One(One { a, .. }) => a,
+    }
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_fill_match_arms() {
     check(
@@ -375,6 +544,19 @@ fn main() {
     )
 }
 
+#[test]
+fn doctest_introduce_named_lifetime() {
+    check(
+        "introduce_named_lifetime",
+        r#####"
+fn first(s: &<|>str) -> &str { s }
+"#####,
+        r#####"
+fn first<'a>(s: &'a str) -> &'a str { s }
+"#####,
+    )
+}
+
 #[test]
 fn doctest_introduce_variable() {
     check(
@@ -555,6 +737,32 @@ fn handle(action: Action) {
     )
 }
 
+#[test]
+fn doctest_promote_local_to_field() {
+    check(
+        "promote_local_to_field",
+        r#####"
+struct Counter { count: u32 }
+impl Counter {
+    fn new() -> Counter { Counter { count: 0 } }
+    fn tick(&mut self) {
+        let <|>step: u32 = 2;
+        self.count += step;
+    }
+}
+"#####,
+        r#####"
+struct Counter { count: u32, step: u32 }
+impl Counter {
+    fn new() -> Counter { Counter { count: 0, step: 2 } }
+    fn tick(&mut self) {
+        self.count += self.step;
+    }
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_remove_dbg() {
     check(
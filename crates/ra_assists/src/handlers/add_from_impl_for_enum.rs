@@ -1,5 +1,6 @@
+use hir::{PathResolution, SemanticsScope};
 use ra_syntax::{
-    ast::{self, AstNode, NameOwner},
+    ast::{self, make, AstNode, NameOwner},
     TextUnit,
 };
 use stdx::format_to;
@@ -44,6 +45,13 @@ pub(crate) fn add_from_impl_for_enum(ctx: AssistCtx) -> Option<Assist> {
     if already_has_from_impl(ctx.sema, &variant) {
         return None;
     }
+    // The `From` in scope may be another trait, which the impl would
+    // implement instead.
+    let scope = ctx.sema.scope(variant.syntax());
+    let from_path = match resolve_trait(&scope, "std::convert::From") {
+        Some(it) if resolve_trait(&scope, "From") != Some(it) => "std::convert::From",
+        _ => "From",
+    };
 
     ctx.add_assist(
         AssistId("add_from_impl_for_enum"),
@@ -55,14 +63,15 @@ pub(crate) fn add_from_impl_for_enum(ctx: AssistCtx) -> Option<Assist> {
                 buf,
                 r#"
 
-impl From<{0}> for {1} {{
+impl {3}<{0}> for {1} {{
     fn from(v: {0}) -> Self {{
         {1}::{2}(v)
     }}
 }}"#,
                 path.syntax(),
                 enum_name,
-                variant_name
+                variant_name,
+                from_path
             );
             edit.insert(start_offset, buf);
             edit.set_cursor(start_offset + TextUnit::of_str("\n\n"));
@@ -70,22 +79,18 @@ impl From<{0}> for {1} {{
     )
 }
 
+fn resolve_trait(scope: &SemanticsScope<'_, RootDatabase>, path: &str) -> Option<hir::Trait> {
+    let path = hir::Path::from_ast(make::path_from_text(path))?;
+    match scope.resolve_hir_path(&path)? {
+        PathResolution::Def(hir::ModuleDef::Trait(it)) => Some(it),
+        _ => None,
+    }
+}
+
 fn already_has_from_impl(
     sema: &'_ hir::Semantics<'_, RootDatabase>,
     variant: &ast::EnumVariant,
 ) -> bool {
-    let scope = sema.scope(&variant.syntax());
-
-    let from_path = ast::make::path_from_text("From");
-    let from_hir_path = match hir::Path::from_ast(from_path) {
-        Some(p) => p,
-        None => return false,
-    };
-    let from_trait = match scope.resolve_hir_path(&from_hir_path) {
-        Some(hir::PathResolution::Def(hir::ModuleDef::Trait(t))) => t,
-        _ => return false,
-    };
-
     let e: hir::Enum = match sema.to_def(&variant.parent_enum()) {
         Some(e) => e,
         None => return false,
@@ -98,7 +103,7 @@ fn already_has_from_impl(
     };
     let var_ty = hir_enum_var.fields(sema.db)[0].signature_ty(sema.db);
 
-    e_ty.impls_trait(sema.db, from_trait, &[var_ty.clone()])
+    e_ty.impls_from(sema.db, &var_ty)
 }
 
 #[cfg(test)]
@@ -155,7 +160,35 @@ mod tests {
     fn test_add_from_impl_already_exists() {
         check_assist_not_applicable(
             add_from_impl_for_enum,
-            r#"enum A { <|>One(u32), }
+            r#"
+//- /main.rs crate:main deps:std
+use std::convert::From;
+
+enum A { <|>One(u32), }
+
+impl From<u32> for A {
+    fn from(v: u32) -> Self {
+        A::One(v)
+    }
+}
+
+//- /std/lib.rs crate:std
+pub mod convert {
+    pub trait From<T> {
+        fn from(t: T) -> Self;
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_add_from_impl_other_from_trait_exists() {
+        check_assist(
+            add_from_impl_for_enum,
+            r#"
+//- /main.rs crate:main deps:std
+enum A { <|>One(u32), }
 
 impl From<u32> for A {
     fn from(v: u32) -> Self {
@@ -164,8 +197,35 @@ impl From<u32> for A {
 }
 
 pub trait From<T> {
-    fn from(T) -> Self;
-}"#,
+    fn from(t: T) -> Self;
+}
+
+//- /std/lib.rs crate:std
+pub mod convert {
+    pub trait From<T> {
+        fn from(t: T) -> Self;
+    }
+}
+"#,
+            r#"enum A { One(u32), }
+
+<|>impl std::convert::From<u32> for A {
+    fn from(v: u32) -> Self {
+        A::One(v)
+    }
+}
+
+impl From<u32> for A {
+    fn from(v: u32) -> Self {
+        A::One(v)
+    }
+}
+
+pub trait From<T> {
+    fn from(t: T) -> Self;
+}
+
+"#,
         );
     }
 
@@ -173,7 +233,11 @@ pub trait From<T> {
     fn test_add_from_impl_different_variant_impl_exists() {
         check_assist(
             add_from_impl_for_enum,
-            r#"enum A { <|>One(u32), Two(String), }
+            r#"
+//- /main.rs crate:main deps:std
+use std::convert::From;
+
+enum A { <|>One(u32), Two(String), }
 
 impl From<String> for A {
     fn from(v: String) -> Self {
@@ -181,10 +245,16 @@ impl From<String> for A {
     }
 }
 
-pub trait From<T> {
-    fn from(T) -> Self;
-}"#,
-            r#"enum A { One(u32), Two(String), }
+//- /std/lib.rs crate:std
+pub mod convert {
+    pub trait From<T> {
+        fn from(t: T) -> Self;
+    }
+}
+"#,
+            r#"use std::convert::From;
+
+enum A { One(u32), Two(String), }
 
 <|>impl From<u32> for A {
     fn from(v: u32) -> Self {
@@ -198,9 +268,7 @@ impl From<String> for A {
     }
 }
 
-pub trait From<T> {
-    fn from(T) -> Self;
-}"#,
+"#,
         );
     }
 }
@@ -1,12 +1,19 @@
+use std::iter::successors;
+
+use hir::{Adt, HasSource, InFile};
+use ra_db::FileId;
 use ra_syntax::{
-    ast::{self, AstNode},
-    SyntaxKind, SyntaxNode, TextUnit,
+    ast::{self, edit::IndentLevel, ArgListOwner, AstNode, NameOwner, TypeParamsOwner},
+    SyntaxNode, TextRange,
 };
-
-use crate::{Assist, AssistCtx, AssistId};
-use ast::{edit::IndentLevel, ArgListOwner, CallExpr, Expr};
-use hir::HirDisplay;
 use rustc_hash::{FxHashMap, FxHashSet};
+use stdx::{format_to, SepBy};
+
+use crate::{
+    assist_ctx::ActionBuilder,
+    utils::{escape_snippet, indent_text, next_space_for_item, original_node, render_type_of_expr},
+    Assist, AssistCtx, AssistFile, AssistId,
+};
 
 // Assist: add_function
 //
@@ -28,8 +35,8 @@ use rustc_hash::{FxHashMap, FxHashSet};
 //      bar("", baz());
 // }
 //
-// fn bar(arg: &str, baz: Baz) {
-//     unimplemented!()
+// fn bar(arg: &str, baz: Baz) ${0:-> _} {
+//     todo!()
 // }
 //
 // ```
@@ -47,66 +54,245 @@ pub(crate) fn add_function(ctx: AssistCtx) -> Option<Assist> {
         return None;
     }
 
-    let function_builder = FunctionBuilder::from_call(&ctx, &call)?;
+    let fn_name = ast::make::name(path.segment()?.name_ref()?.text());
+    let target = GeneratedFunctionTarget::BehindItem(next_space_for_item(call.syntax())?);
+    let function_builder = FunctionBuilder::new(&ctx, fn_name, &call, target, None)?;
 
     ctx.add_assist(AssistId("add_function"), "Add function", |edit| {
         edit.target(call.syntax().text_range());
+        function_builder.render(edit);
+    })
+}
+
+// Assist: add_method
+//
+// Adds a stub method with a signature matching the method call under the
+// cursor to an impl block of the receiver's type.
+//
+// ```
+// struct Foo;
+// impl Foo {}
+// fn foo(x: Foo) {
+//     x.bar<|>(92);
+// }
+// ```
+// ->
+// ```
+// struct Foo;
+// impl Foo {
+//     fn bar(&self, arg: i32) ${0:-> _} {
+//         todo!()
+//     }
+// }
+// fn foo(x: Foo) {
+//     x.bar(92);
+// }
+// ```
+pub(crate) fn add_method(ctx: AssistCtx) -> Option<Assist> {
+    let call: ast::MethodCallExpr = ctx.find_node_at_offset()?;
+    let name_ref = call.name_ref()?;
+    if let Some(arg_list) = call.arg_list() {
+        // The cursor is on one of the arguments, not on this call.
+        let range = arg_list.syntax().text_range();
+        let offset = ctx.frange.range.start();
+        if range.start() < offset && offset < range.end() {
+            return None;
+        }
+    }
+
+    if ctx.sema.resolve_method_call(&call).is_some() {
+        return None;
+    }
 
-        if let Some(function_template) = function_builder.render() {
-            edit.set_cursor(function_template.cursor_offset);
-            edit.insert(function_template.insert_offset, function_template.fn_def.to_string());
+    let receiver_ty = ctx.sema.type_of_expr(&call.expr()?)?;
+    let adt = receiver_ty.autoderef(ctx.db).find_map(|it| it.as_adt())?;
+    // Only types of the current crate can have inherent impls here.
+    let krate = ctx.sema.scope(call.syntax()).module()?.krate();
+    if adt.module(ctx.db).krate() != krate {
+        return None;
+    }
+
+    let inherent_impl = hir::ImplDef::all_in_crate(ctx.db, krate)
+        .into_iter()
+        .find(|it| it.target_trait(ctx.db).is_none() && it.target_ty(ctx.db).as_adt() == Some(adt));
+    // Private methods are visible in the module of the impl and its
+    // descendants.
+    let impl_module = inherent_impl.as_ref().map_or(adt.module(ctx.db), |it| it.module(ctx.db));
+    let current_module = ctx.sema.scope(call.syntax()).module()?;
+    let is_visible =
+        successors(Some(current_module), |it| it.parent(ctx.db)).any(|it| it == impl_module);
+    let visibility = if is_visible { "" } else { "pub(crate) " };
+    let (file_id, target) = match inherent_impl {
+        Some(impl_def) => {
+            let (file_id, impl_def) = original_node(ctx.db, impl_def.source(ctx.db))?;
+            (file_id, GeneratedFunctionTarget::InImpl(impl_def))
         }
+        None => {
+            let (file_id, adt) = original_node(ctx.db, adt_source(&ctx, adt))?;
+            (file_id, GeneratedFunctionTarget::NewImpl(adt))
+        }
+    };
+    let file = if file_id == ctx.frange.file_id { None } else { Some(file_id) };
+
+    let fn_name = ast::make::name(name_ref.text());
+    let function_builder =
+        FunctionBuilder::new(&ctx, fn_name, &call, target, file)?.visibility(visibility);
+
+    ctx.add_assist(AssistId("add_method"), "Add method", |edit| {
+        edit.target(call.syntax().text_range());
+        function_builder.render(edit);
     })
 }
 
-struct FunctionTemplate {
-    insert_offset: TextUnit,
-    cursor_offset: TextUnit,
-    fn_def: ast::SourceFile,
+/// Where the generated function goes.
+enum GeneratedFunctionTarget {
+    /// Behind the item containing the call, in the same module.
+    BehindItem(SyntaxNode),
+    /// At the end of an inherent impl of the receiver's type.
+    InImpl(ast::ImplDef),
+    /// Into a new impl block behind the definition of the receiver's type.
+    NewImpl(ast::NominalDef),
 }
 
 struct FunctionBuilder {
-    append_fn_at: SyntaxNode,
+    target: GeneratedFunctionTarget,
+    /// The file of the target, if it's not the current one.
+    file: Option<FileId>,
+    visibility: &'static str,
     fn_name: ast::Name,
     type_params: Option<ast::TypeParamList>,
     params: ast::ParamList,
 }
 
 impl FunctionBuilder {
-    fn from_call(ctx: &AssistCtx, call: &ast::CallExpr) -> Option<Self> {
-        let append_fn_at = next_space_for_fn(&call)?;
-        let fn_name = fn_name(&call)?;
-        let (type_params, params) = fn_args(ctx, &call)?;
-        Some(Self { append_fn_at, fn_name, type_params, params })
-    }
-    fn render(self) -> Option<FunctionTemplate> {
-        let placeholder_expr = ast::make::expr_unimplemented();
+    fn new(
+        ctx: &AssistCtx,
+        fn_name: ast::Name,
+        call: &impl ArgListOwner,
+        target: GeneratedFunctionTarget,
+        file: Option<FileId>,
+    ) -> Option<Self> {
+        let self_param = match target {
+            GeneratedFunctionTarget::BehindItem(_) => None,
+            GeneratedFunctionTarget::InImpl(_) | GeneratedFunctionTarget::NewImpl(_) => {
+                Some(ast::make::self_param())
+            }
+        };
+        let (type_params, params) = fn_args(ctx, call, self_param)?;
+        Some(Self { target, file, visibility: "", fn_name, type_params, params })
+    }
+
+    fn visibility(mut self, visibility: &'static str) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    fn render(self, edit: &mut ActionBuilder) {
+        let placeholder_expr = ast::make::expr_todo();
         let fn_body = ast::make::block_expr(vec![], Some(placeholder_expr));
         let fn_def = ast::make::fn_def(self.fn_name, self.type_params, self.params, fn_body);
-        let fn_def = ast::make::add_newlines(2, fn_def);
-        let fn_def = IndentLevel::from_node(&self.append_fn_at).increase_indent(fn_def);
-        let insert_offset = self.append_fn_at.text_range().end();
-        let cursor_offset_from_fn_start = fn_def
-            .syntax()
-            .descendants()
-            .find_map(ast::MacroCall::cast)?
-            .syntax()
-            .text_range()
-            .start();
-        let cursor_offset = insert_offset + cursor_offset_from_fn_start;
-        Some(FunctionTemplate { insert_offset, cursor_offset, fn_def })
+
+        let (range, indent, prefix, suffix) = match &self.target {
+            GeneratedFunctionTarget::BehindItem(item) => {
+                let indent = IndentLevel::from_node(item);
+                let offset = item.text_range().end();
+                (TextRange::offset_len(offset, 0.into()), indent, "\n\n".to_string(), String::new())
+            }
+            GeneratedFunctionTarget::InImpl(impl_def) => {
+                let indent = IndentLevel::from_node(impl_def.syntax());
+                let item_list = match impl_def.item_list() {
+                    Some(it) => it,
+                    None => return,
+                };
+                match item_list.impl_items().last() {
+                    Some(last_item) => {
+                        let offset = last_item.syntax().text_range().end();
+                        let range = TextRange::offset_len(offset, 0.into());
+                        (range, IndentLevel(indent.0 + 1), "\n\n".to_string(), String::new())
+                    }
+                    // Replace the whole `{}`, as there might not be a line
+                    // break between the braces.
+                    None => {
+                        let suffix = format!("\n{}}}", indent_text(indent));
+                        let range = item_list.syntax().text_range();
+                        (range, IndentLevel(indent.0 + 1), "{\n".to_string(), suffix)
+                    }
+                }
+            }
+            GeneratedFunctionTarget::NewImpl(adt) => {
+                let indent = IndentLevel::from_node(adt.syntax());
+                let offset = adt.syntax().text_range().end();
+                let mut prefix = format!("\n\n{}", indent_text(indent));
+                match impl_header(adt) {
+                    Some(header) => format_to!(prefix, "{} {{\n", header),
+                    None => return,
+                }
+                let suffix = format!("\n{}}}", indent_text(indent));
+                (TextRange::offset_len(offset, 0.into()), IndentLevel(indent.0 + 1), prefix, suffix)
+            }
+        };
+        let fn_def = indent.increase_indent(fn_def);
+        let body_start = match fn_def.body() {
+            Some(it) => it.syntax().text_range().start(),
+            None => return,
+        };
+
+        // The return type is unknown, so let the user fill it in first.
+        let fn_text = fn_def.syntax().to_string();
+        let snippet = format!(
+            "{}{}{}{}${{0:-> _}} {}{}",
+            escape_snippet(&prefix),
+            indent_text(indent),
+            self.visibility,
+            escape_snippet(&fn_text[..body_start.to_usize()]),
+            escape_snippet(&fn_text[body_start.to_usize()..]),
+            escape_snippet(&suffix),
+        );
+        if let Some(file_id) = self.file {
+            edit.set_file(AssistFile::TargetFile(file_id));
+        }
+        edit.replace_snippet(range, snippet);
+    }
+}
+
+/// Renders `impl<T> Foo<T>` for `struct Foo<T>`.
+fn impl_header(adt: &ast::NominalDef) -> Option<String> {
+    let mut buf = String::from("impl");
+    let name = adt.name()?;
+    match adt.type_param_list() {
+        Some(type_params) => {
+            let lifetime_params = type_params
+                .lifetime_params()
+                .filter_map(|it| it.lifetime_token())
+                .map(|it| it.text().clone());
+            let type_param_names =
+                type_params.type_params().filter_map(|it| it.name()).map(|it| it.text().clone());
+            format_to!(
+                buf,
+                "{} {}<{}>",
+                type_params.syntax(),
+                name,
+                lifetime_params.chain(type_param_names).sep_by(", ")
+            );
+        }
+        None => format_to!(buf, " {}", name),
     }
+    Some(buf)
 }
 
-fn fn_name(call: &CallExpr) -> Option<ast::Name> {
-    let name = call.expr()?.syntax().to_string();
-    Some(ast::make::name(&name))
+fn adt_source(ctx: &AssistCtx, adt: Adt) -> InFile<ast::NominalDef> {
+    match adt {
+        Adt::Struct(it) => it.source(ctx.db).map(ast::NominalDef::from),
+        Adt::Union(it) => it.source(ctx.db).map(ast::NominalDef::from),
+        Adt::Enum(it) => it.source(ctx.db).map(ast::NominalDef::from),
+    }
 }
 
 /// Computes the type variables and arguments required for the generated function
 fn fn_args(
     ctx: &AssistCtx,
-    call: &CallExpr,
+    call: &impl ArgListOwner,
+    self_param: Option<ast::SelfParam>,
 ) -> Option<(Option<ast::TypeParamList>, ast::ParamList)> {
     let mut arg_names = Vec::new();
     let mut arg_types = Vec::new();
@@ -116,14 +302,14 @@ fn fn_args(
             None => String::from("arg"),
         };
         arg_names.push(arg_name);
-        arg_types.push(match fn_arg_type(ctx, &arg) {
+        arg_types.push(match render_type_of_expr(ctx.sema, &arg) {
             Some(ty) => ty,
             None => String::from("()"),
         });
     }
     deduplicate_arg_names(&mut arg_names);
     let params = arg_names.into_iter().zip(arg_types).map(|(name, ty)| ast::make::param(name, ty));
-    Some((None, ast::make::param_list(params)))
+    Some((None, ast::make::param_list(self_param, params)))
 }
 
 /// Makes duplicate argument names unique by appending incrementing numbers.
@@ -158,9 +344,9 @@ fn deduplicate_arg_names(arg_names: &mut Vec<String>) {
     }
 }
 
-fn fn_arg_name(fn_arg: &Expr) -> Option<String> {
+fn fn_arg_name(fn_arg: &ast::Expr) -> Option<String> {
     match fn_arg {
-        Expr::CastExpr(cast_expr) => fn_arg_name(&cast_expr.expr()?),
+        ast::Expr::CastExpr(cast_expr) => fn_arg_name(&cast_expr.expr()?),
         _ => Some(
             fn_arg
                 .syntax()
@@ -172,38 +358,6 @@ fn fn_arg_name(fn_arg: &Expr) -> Option<String> {
     }
 }
 
-fn fn_arg_type(ctx: &AssistCtx, fn_arg: &Expr) -> Option<String> {
-    let ty = ctx.sema.type_of_expr(fn_arg)?;
-    if ty.is_unknown() {
-        return None;
-    }
-    Some(ty.display(ctx.sema.db).to_string())
-}
-
-/// Returns the position inside the current mod or file
-/// directly after the current block
-/// We want to write the generated function directly after
-/// fns, impls or macro calls, but inside mods
-fn next_space_for_fn(expr: &CallExpr) -> Option<SyntaxNode> {
-    let mut ancestors = expr.syntax().ancestors().peekable();
-    let mut last_ancestor: Option<SyntaxNode> = None;
-    while let Some(next_ancestor) = ancestors.next() {
-        match next_ancestor.kind() {
-            SyntaxKind::SOURCE_FILE => {
-                break;
-            }
-            SyntaxKind::ITEM_LIST => {
-                if ancestors.peek().map(|a| a.kind()) == Some(SyntaxKind::MODULE) {
-                    break;
-                }
-            }
-            _ => {}
-        }
-        last_ancestor = Some(next_ancestor);
-    }
-    last_ancestor
-}
-
 #[cfg(test)]
 mod tests {
     use crate::helpers::{check_assist, check_assist_not_applicable};
@@ -224,8 +378,8 @@ fn foo() {
     bar();
 }
 
-fn bar() {
-    <|>unimplemented!()
+fn bar() ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -251,8 +405,8 @@ impl Foo {
     }
 }
 
-fn bar() {
-    <|>unimplemented!()
+fn bar() ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -275,8 +429,8 @@ fn foo1() {
     bar();
 }
 
-fn bar() {
-    <|>unimplemented!()
+fn bar() ${0:-> _} {
+    todo!()
 }
 
 fn foo2() {}
@@ -301,8 +455,8 @@ mod baz {
         bar();
     }
 
-    fn bar() {
-        <|>unimplemented!()
+    fn bar() ${0:-> _} {
+        todo!()
     }
 }
 ",
@@ -327,8 +481,8 @@ fn foo() {
     bar(baz());
 }
 
-fn bar(baz: Baz) {
-    <|>unimplemented!()
+fn bar(baz: Baz) ${0:-> _} {
+    todo!()
 }
 ",
         );
@@ -360,8 +514,8 @@ impl Baz {
     }
 }
 
-fn bar(baz: Baz) {
-    <|>unimplemented!()
+fn bar(baz: Baz) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -381,8 +535,8 @@ fn foo() {
     bar("bar")
 }
 
-fn bar(arg: &str) {
-    <|>unimplemented!()
+fn bar(arg: &str) ${0:-> _} {
+    todo!()
 }
 "#,
         )
@@ -402,8 +556,8 @@ fn foo() {
     bar('x')
 }
 
-fn bar(arg: char) {
-    <|>unimplemented!()
+fn bar(arg: char) ${0:-> _} {
+    todo!()
 }
 "#,
         )
@@ -423,8 +577,8 @@ fn foo() {
     bar(42)
 }
 
-fn bar(arg: i32) {
-    <|>unimplemented!()
+fn bar(arg: i32) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -444,8 +598,8 @@ fn foo() {
     bar(42 as u8)
 }
 
-fn bar(arg: u8) {
-    <|>unimplemented!()
+fn bar(arg: u8) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -469,8 +623,8 @@ fn foo() {
     bar(x as u8)
 }
 
-fn bar(x: u8) {
-    <|>unimplemented!()
+fn bar(x: u8) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -492,8 +646,8 @@ fn foo() {
     bar(worble)
 }
 
-fn bar(worble: ()) {
-    <|>unimplemented!()
+fn bar(worble: ()) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -521,8 +675,8 @@ fn baz() {
     bar(foo())
 }
 
-fn bar(foo: impl Foo) {
-    <|>unimplemented!()
+fn bar(foo: impl Foo) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -555,8 +709,8 @@ mod Foo {
         bar(super::Baz::baz())
     }
 
-    fn bar(baz: super::Baz::Bof) {
-        <|>unimplemented!()
+    fn bar(baz: super::Baz::Bof) ${0:-> _} {
+        todo!()
     }
 }
 ",
@@ -579,8 +733,8 @@ fn foo<T>(t: T) {
     bar(t)
 }
 
-fn bar<T>(t: T) {
-    <|>unimplemented!()
+fn bar<T>(t: T) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -610,8 +764,8 @@ fn foo() {
     bar(Baz::new);
 }
 
-fn bar(arg: fn() -> Baz) {
-    <|>unimplemented!()
+fn bar(arg: fn() -> Baz) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -635,8 +789,8 @@ fn foo() {
     bar(closure)
 }
 
-fn bar(closure: impl Fn(i64) -> i64) {
-    <|>unimplemented!()
+fn bar(closure: impl Fn(i64) -> i64) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -656,8 +810,8 @@ fn foo() {
     bar(baz)
 }
 
-fn bar(baz: ()) {
-    <|>unimplemented!()
+fn bar(baz: ()) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -681,8 +835,8 @@ fn foo() {
     bar(baz(), baz())
 }
 
-fn bar(baz_1: Baz, baz_2: Baz) {
-    <|>unimplemented!()
+fn bar(baz_1: Baz, baz_2: Baz) ${0:-> _} {
+    todo!()
 }
 ",
         )
@@ -706,8 +860,8 @@ fn foo() {
     bar(baz(), baz(), "foo", "bar")
 }
 
-fn bar(baz_1: Baz, baz_2: Baz, arg_1: &str, arg_2: &str) {
-    <|>unimplemented!()
+fn bar(baz_1: Baz, baz_2: Baz, arg_1: &str, arg_2: &str) ${0:-> _} {
+    todo!()
 }
 "#,
         )
@@ -760,10 +914,9 @@ fn foo() {
     }
 
     #[test]
-    #[ignore]
-    fn create_method_with_no_args() {
+    fn add_method_with_no_args() {
         check_assist(
-            add_function,
+            add_method,
             r"
 struct Foo;
 impl Foo {
@@ -771,18 +924,126 @@ impl Foo {
         self.bar()<|>;
     }
 }
-        ",
+",
             r"
 struct Foo;
 impl Foo {
     fn foo(&self) {
         self.bar();
     }
-    fn bar(&self) {
-        unimplemented!();
+
+    fn bar(&self) ${0:-> _} {
+        todo!()
     }
 }
-        ",
+",
+        )
+    }
+
+    #[test]
+    fn add_method_into_empty_impl() {
+        check_assist(
+            add_method,
+            r"
+struct Foo;
+impl Foo {}
+fn foo(x: &Foo) {
+    x.bar<|>(92, x);
+}
+",
+            r"
+struct Foo;
+impl Foo {
+    fn bar(&self, arg: i32, x: &Foo) ${0:-> _} {
+        todo!()
+    }
+}
+fn foo(x: &Foo) {
+    x.bar(92, x);
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_method_into_new_impl() {
+        check_assist(
+            add_method,
+            r"
+mod foo {
+    pub struct Foo<'a, T> { t: &'a T }
+}
+fn foo(x: foo::Foo<u32>) {
+    x.bar<|>();
+}
+",
+            r"
+mod foo {
+    pub struct Foo<'a, T> { t: &'a T }
+
+    impl<'a, T> Foo<'a, T> {
+        pub(crate) fn bar(&self) ${0:-> _} {
+            todo!()
+        }
+    }
+}
+fn foo(x: foo::Foo<u32>) {
+    x.bar();
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_method_in_other_file() {
+        check_assist(
+            add_method,
+            r"
+//- /main.rs
+mod foo;
+fn main() {
+    foo::Foo.bar<|>();
+}
+//- /foo.rs
+pub struct Foo;
+",
+            r"pub struct Foo;
+
+impl Foo {
+    pub(crate) fn bar(&self) ${0:-> _} {
+        todo!()
+    }
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_method_not_applicable_if_method_exists() {
+        check_assist_not_applicable(
+            add_method,
+            r"
+struct Foo;
+impl Foo {
+    fn bar(&self) {}
+}
+fn foo(x: Foo) {
+    x.bar<|>();
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_method_not_applicable_on_argument() {
+        check_assist_not_applicable(
+            add_method,
+            r"
+struct Foo;
+fn foo(x: Foo) {
+    x.bar(<|>92);
+}
+",
         )
     }
 }
@@ -0,0 +1,657 @@
+use std::iter::successors;
+
+use hir::{Adt, HasSource, ModuleDef, ModuleSource, PathResolution};
+use ra_syntax::{
+    ast::{self, edit::IndentLevel, ArgListOwner, AstNode, ModuleItemOwner},
+    Direction, SyntaxNode, TextRange, T,
+};
+use stdx::SepBy;
+
+use crate::{
+    assist_ctx::ActionBuilder,
+    utils::{indent_text, next_space_for_item, original_node, render_type_of_expr},
+    Assist, AssistCtx, AssistFile, AssistId,
+};
+
+// Assist: add_enum_variant
+//
+// Adds a variant matching the unresolved enum variant under the cursor.
+//
+// ```
+// enum Color { Red }
+// fn main() {
+//     let c = Color::Teal<|>(92);
+// }
+// ```
+// ->
+// ```
+// enum Color { Red, Teal(i32) }
+// fn main() {
+//     let c = Color::Teal(92);
+// }
+// ```
+pub(crate) fn add_enum_variant(ctx: AssistCtx) -> Option<Assist> {
+    let path: ast::Path = ctx.find_node_at_offset()?;
+    let name_ref = path.segment()?.name_ref()?;
+    // `Color::new()` is much more likely to be a missing function.
+    if !name_ref.text().starts_with(char::is_uppercase) {
+        return None;
+    }
+    if ctx.sema.resolve_path(&path).is_some() {
+        return None;
+    }
+    let enum_ = match ctx.sema.resolve_path(&path.qualifier()?)? {
+        PathResolution::Def(ModuleDef::Adt(Adt::Enum(it))) => it,
+        _ => return None,
+    };
+    if enum_.module(ctx.db).krate() != ctx.sema.scope(path.syntax()).module()?.krate() {
+        return None;
+    }
+
+    let fields = Fields::from_usage(&ctx, &path)?;
+    let (file_id, enum_def) = original_node(ctx.db, enum_.source(ctx.db))?;
+    let variant_list = enum_def.variant_list()?;
+    let file = if file_id == ctx.frange.file_id {
+        AssistFile::CurrentFile
+    } else {
+        AssistFile::TargetFile(file_id)
+    };
+
+    ctx.add_assist(AssistId("add_enum_variant"), "Add enum variant", |edit| {
+        edit.target(path.syntax().text_range());
+        let variant = format!("{}{}", name_ref, fields.render_variant());
+        let (range, text) = add_variant(&variant_list, &variant);
+        edit.set_file(file);
+        edit.replace(range, text);
+    })
+}
+
+// Assist: add_struct
+//
+// Adds a struct for the unresolved record literal under the cursor, with the
+// types of the fields inferred from the literal.
+//
+// ```
+// fn main() {
+//     let p = Point<|> { x: 1, y: 2.0 };
+// }
+// ```
+// ->
+// ```
+// fn main() {
+//     let p = Point { x: 1, y: 2.0 };
+// }
+//
+// struct Point {
+//     x: i32,
+//     y: f64,
+// }
+// ```
+pub(crate) fn add_struct(ctx: AssistCtx) -> Option<Assist> {
+    let lit: ast::RecordLit = ctx.find_node_at_offset()?;
+    let path = lit.path()?;
+    if !path.syntax().text_range().contains_inclusive(ctx.frange.range.start()) {
+        return None;
+    }
+    let name_ref = path.segment()?.name_ref()?;
+    if ctx.sema.resolve_path(&path).is_some() {
+        return None;
+    }
+    let fields = record_fields(&ctx, &lit)?;
+    let target = ItemTarget::for_path(&ctx, &path)?;
+
+    ctx.add_assist(AssistId("add_struct"), "Add struct", |edit| {
+        edit.target(path.syntax().text_range());
+        let vis = target.visibility;
+        let text = if fields.is_empty() {
+            format!("{}struct {} {{}}", vis, name_ref)
+        } else {
+            let fields = fields
+                .iter()
+                .map(|(name, ty)| format!("\n    {}{}: {},", vis, name, ty))
+                .collect::<String>();
+            format!("{}struct {} {{{}\n}}", vis, name_ref, fields)
+        };
+        target.insert(edit, &text);
+    })
+}
+
+// Assist: add_type_alias
+//
+// Adds a type alias for the unresolved type under the cursor.
+//
+// ```
+// fn frobnicate(x: Frobnicator<|>) {}
+// ```
+// ->
+// ```
+// fn frobnicate(x: Frobnicator) {}
+//
+// type Frobnicator = ${0:()};
+// ```
+pub(crate) fn add_type_alias(ctx: AssistCtx) -> Option<Assist> {
+    let path_type: ast::PathType = ctx.find_node_at_offset()?;
+    let path = path_type.path()?;
+    let segment = path.segment()?;
+    // The generic parameters of the alias can't be guessed.
+    if segment.type_arg_list().is_some() {
+        return None;
+    }
+    let name_ref = segment.name_ref()?;
+    if ctx.sema.resolve_path(&path).is_some() {
+        return None;
+    }
+    let target = ItemTarget::for_path(&ctx, &path)?;
+
+    ctx.add_assist(AssistId("add_type_alias"), "Add type alias", |edit| {
+        edit.target(path.syntax().text_range());
+        let text = format!("{}type {} = ${{0:()}};", target.visibility, name_ref);
+        target.insert_snippet(edit, &text);
+    })
+}
+
+/// The fields of a struct or variant, as inferred from its usage.
+enum Fields {
+    Unit,
+    Tuple(Vec<String>),
+    Record(Vec<(String, String)>),
+}
+
+impl Fields {
+    fn from_usage(ctx: &AssistCtx, path: &ast::Path) -> Option<Fields> {
+        let parent = path.syntax().parent()?;
+        if let Some(lit) = ast::RecordLit::cast(parent.clone()) {
+            return record_fields(ctx, &lit).map(Fields::Record);
+        }
+        // Paths in patterns and types are not handled.
+        let path_expr = ast::PathExpr::cast(parent)?;
+        let call = path_expr
+            .syntax()
+            .parent()
+            .and_then(ast::CallExpr::cast)
+            .filter(|it| it.expr().map_or(false, |it| it.syntax() == path_expr.syntax()));
+        let res = match call {
+            Some(call) => {
+                Fields::Tuple(call.arg_list()?.args().map(|it| field_type(ctx, &it)).collect())
+            }
+            None => Fields::Unit,
+        };
+        Some(res)
+    }
+
+    fn render_variant(&self) -> String {
+        match self {
+            Fields::Unit => String::new(),
+            Fields::Tuple(types) => format!("({})", types.iter().sep_by(", ")),
+            Fields::Record(fields) => {
+                let fields = fields.iter().map(|(name, ty)| format!("{}: {}", name, ty));
+                format!(" {{ {} }}", fields.sep_by(", "))
+            }
+        }
+    }
+}
+
+/// Returns the names and types of the fields of a record literal.
+fn record_fields(ctx: &AssistCtx, lit: &ast::RecordLit) -> Option<Vec<(String, String)>> {
+    let mut res = Vec::new();
+    for field in lit.record_field_list()?.fields() {
+        // `Foo { a }` is short for `Foo { a: a }`.
+        let name = field.field_name()?.text().to_string();
+        let expr = field.expr()?;
+        res.push((name, field_type(ctx, &expr)));
+    }
+    Some(res)
+}
+
+fn field_type(ctx: &AssistCtx, expr: &ast::Expr) -> String {
+    render_type_of_expr(ctx.sema, expr).unwrap_or_else(|| "()".to_string())
+}
+
+/// Computes the edit adding `variant` behind the last variant of `list`,
+/// keeping it on a single line if the list is.
+fn add_variant(list: &ast::EnumVariantList, variant: &str) -> (TextRange, String) {
+    let is_multiline = list.syntax().text().contains_char('\n');
+    let last_variant = match list.variants().last() {
+        Some(it) => it,
+        // Replace the whole `{}`, as there might not be a line break between
+        // the braces.
+        None => {
+            let text = if is_multiline {
+                let indent = IndentLevel::from_node(list.syntax());
+                format!(
+                    "{{\n{}{},\n{}}}",
+                    indent_text(IndentLevel(indent.0 + 1)),
+                    variant,
+                    indent_text(indent)
+                )
+            } else {
+                format!("{{ {} }}", variant)
+            };
+            return (list.syntax().text_range(), text);
+        }
+    };
+    let separator = if is_multiline {
+        format!("\n{}", indent_text(IndentLevel::from_node(last_variant.syntax())))
+    } else {
+        " ".to_string()
+    };
+    let trailing_comma = last_variant
+        .syntax()
+        .siblings_with_tokens(Direction::Next)
+        .skip(1)
+        .find(|it| !it.kind().is_trivia())
+        .filter(|it| it.kind() == T![,]);
+    match trailing_comma {
+        Some(comma) => {
+            let offset = comma.text_range().end();
+            (TextRange::offset_len(offset, 0.into()), format!("{}{},", separator, variant))
+        }
+        None => {
+            let offset = last_variant.syntax().text_range().end();
+            (TextRange::offset_len(offset, 0.into()), format!(",{}{}", separator, variant))
+        }
+    }
+}
+
+/// Where a generated item goes.
+struct ItemTarget {
+    file: AssistFile,
+    /// Empty, unless the item goes into an empty inline module.
+    range: TextRange,
+    prefix: String,
+    suffix: String,
+    indent: IndentLevel,
+    /// The item is generated outside of the modules which can see private
+    /// items of the target module.
+    visibility: &'static str,
+}
+
+impl ItemTarget {
+    /// Puts the item named by `path` behind the current item if the path is
+    /// unqualified, and at the end of the module named by the qualifier
+    /// otherwise.
+    fn for_path(ctx: &AssistCtx, path: &ast::Path) -> Option<ItemTarget> {
+        let qualifier = match path.qualifier() {
+            Some(it) => it,
+            None => {
+                let item = next_space_for_item(path.syntax())?;
+                let indent = IndentLevel::from_node(&item);
+                return Some(ItemTarget {
+                    file: AssistFile::CurrentFile,
+                    range: TextRange::offset_len(item.text_range().end(), 0.into()),
+                    prefix: format!("\n\n{}", indent_text(indent)),
+                    suffix: String::new(),
+                    indent,
+                    visibility: "",
+                });
+            }
+        };
+        let module = match ctx.sema.resolve_path(&qualifier)? {
+            PathResolution::Def(ModuleDef::Module(it)) => it,
+            _ => return None,
+        };
+        let current_module = ctx.sema.scope(path.syntax()).module()?;
+        if module.krate() != current_module.krate() {
+            return None;
+        }
+        // Private items are visible in their module and its descendants.
+        let is_visible =
+            successors(Some(current_module), |it| it.parent(ctx.db)).any(|it| it == module);
+        let visibility = if is_visible { "" } else { "pub(crate) " };
+
+        let (file_id, source) = original_node(ctx.db, module.definition_source(ctx.db))?;
+        let file = if file_id == ctx.frange.file_id {
+            AssistFile::CurrentFile
+        } else {
+            AssistFile::TargetFile(file_id)
+        };
+        let behind = |item: &SyntaxNode| {
+            let indent = IndentLevel::from_node(item);
+            let range = TextRange::offset_len(item.text_range().end(), 0.into());
+            (range, format!("\n\n{}", indent_text(indent)), String::new(), indent)
+        };
+        let (range, prefix, suffix, indent) = match source {
+            ModuleSource::SourceFile(source_file) => match source_file.items().last() {
+                Some(last_item) => behind(last_item.syntax()),
+                None => {
+                    let range =
+                        TextRange::offset_len(source_file.syntax().text_range().end(), 0.into());
+                    (range, String::new(), "\n".to_string(), IndentLevel(0))
+                }
+            },
+            ModuleSource::Module(module) => {
+                let item_list = module.item_list()?;
+                match item_list.items().last() {
+                    Some(last_item) => behind(last_item.syntax()),
+                    None => {
+                        let indent = IndentLevel::from_node(module.syntax());
+                        let item_indent = IndentLevel(indent.0 + 1);
+                        let prefix = format!("{{\n{}", indent_text(item_indent));
+                        let suffix = format!("\n{}}}", indent_text(indent));
+                        (item_list.syntax().text_range(), prefix, suffix, item_indent)
+                    }
+                }
+            }
+        };
+        Some(ItemTarget { file, range, prefix, suffix, indent, visibility })
+    }
+
+    fn insert(&self, edit: &mut ActionBuilder, item: &str) {
+        edit.set_file(self.file);
+        edit.replace(self.range, self.render(item));
+    }
+
+    fn insert_snippet(&self, edit: &mut ActionBuilder, item: &str) {
+        edit.set_file(self.file);
+        edit.replace_snippet(self.range, self.render(item));
+    }
+
+    fn render(&self, item: &str) -> String {
+        let item = item.replace('\n', &format!("\n{}", indent_text(self.indent)));
+        format!("{}{}{}", self.prefix, item, self.suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn add_unit_variant() {
+        check_assist(
+            add_enum_variant,
+            r"
+enum Color {
+    Red,
+    Green,
+}
+fn main() {
+    let c = Color::Teal<|>;
+}
+",
+            r"
+enum Color {
+    Red,
+    Green,
+    Teal,
+}
+fn main() {
+    let c = Color::Teal<|>;
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_tuple_variant() {
+        check_assist(
+            add_enum_variant,
+            r"
+enum Color { Red }
+fn main() {
+    let c = Color::Rgb<|>(1u8, 2u8, 3u8);
+}
+",
+            r"
+enum Color { Red, Rgb(u8, u8, u8) }
+fn main() {
+    let c = Color::Rgb<|>(1u8, 2u8, 3u8);
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_record_variant() {
+        check_assist(
+            add_enum_variant,
+            r"
+enum Color {
+    Red
+}
+fn main() {
+    let lightness = 0.5;
+    let c = Color::Gray<|> { lightness, alpha: 1u8 };
+}
+",
+            r"
+enum Color {
+    Red,
+    Gray { lightness: f64, alpha: u8 }
+}
+fn main() {
+    let lightness = 0.5;
+    let c = Color::Gray<|> { lightness, alpha: 1u8 };
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_variant_to_empty_enum() {
+        check_assist(
+            add_enum_variant,
+            r"
+enum Color {}
+fn main() {
+    let c = Color::Teal<|>;
+}
+",
+            r"
+enum Color { Teal }
+fn main() {
+    let c = Color::Teal<|>;
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_variant_in_other_file() {
+        check_assist(
+            add_enum_variant,
+            r"
+//- /main.rs
+mod color;
+fn main() {
+    let c = color::Color::Teal<|>;
+}
+//- /color.rs
+pub enum Color {
+    Red,
+}
+",
+            r"pub enum Color {
+    Red,
+    Teal,
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_variant_not_applicable() {
+        check_assist_not_applicable(
+            add_enum_variant,
+            r"
+enum Color { Red }
+fn main() {
+    let c = Color::Red<|>;
+}
+",
+        );
+        check_assist_not_applicable(
+            add_enum_variant,
+            r"
+enum Color { Red }
+fn main() {
+    let c = Color::new<|>();
+}
+",
+        );
+        check_assist_not_applicable(
+            add_enum_variant,
+            r"
+struct Color;
+fn main() {
+    let c = Color::Teal<|>;
+}
+",
+        );
+    }
+
+    #[test]
+    fn add_struct_behind_current_item() {
+        check_assist(
+            add_struct,
+            r"
+fn main() {
+    let p = Point<|> { x: 1, y: 2.0 };
+}
+
+fn foo() {}
+",
+            r"
+fn main() {
+    let p = Point<|> { x: 1, y: 2.0 };
+}
+
+struct Point {
+    x: i32,
+    y: f64,
+}
+
+fn foo() {}
+",
+        )
+    }
+
+    #[test]
+    fn add_struct_into_other_module() {
+        check_assist(
+            add_struct,
+            r"
+mod geometry {
+    fn foo() {}
+}
+fn main() {
+    let p = geometry::Point<|> {};
+}
+",
+            r"
+mod geometry {
+    fn foo() {}
+
+    pub(crate) struct Point {}
+}
+fn main() {
+    let p = geometry::Point<|> {};
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_struct_into_parent_module() {
+        check_assist(
+            add_struct,
+            r"
+mod geometry {
+    mod point {
+        fn origin() {
+            let p = super::Point<|> { x: 0 };
+        }
+    }
+}
+",
+            r"
+mod geometry {
+    mod point {
+        fn origin() {
+            let p = super::Point<|> { x: 0 };
+        }
+    }
+
+    struct Point {
+        x: i32,
+    }
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_struct_into_empty_module() {
+        check_assist(
+            add_struct,
+            r"
+mod geometry {}
+fn main() {
+    let p = geometry::Point<|> { x: 0 };
+}
+",
+            r"
+mod geometry {
+    pub(crate) struct Point {
+        pub(crate) x: i32,
+    }
+}
+fn main() {
+    let p = geometry::Point<|> { x: 0 };
+}
+",
+        )
+    }
+
+    #[test]
+    fn add_struct_not_applicable() {
+        check_assist_not_applicable(
+            add_struct,
+            r"
+struct Point { x: i32 }
+fn main() {
+    let p = Point<|> { x: 1 };
+}
+",
+        );
+        check_assist_not_applicable(
+            add_struct,
+            r"
+enum Shape {}
+fn main() {
+    let p = Shape::Point<|> { x: 1 };
+}
+",
+        );
+    }
+
+    #[test]
+    fn add_type_alias_into_module_file() {
+        check_assist(
+            add_type_alias,
+            r"
+//- /main.rs
+mod types;
+fn frobnicate(x: types::Frobnicator<|>) {}
+//- /types.rs
+pub struct Foo;
+",
+            r"pub struct Foo;
+
+pub(crate) type Frobnicator = ${0:()};
+",
+        )
+    }
+
+    #[test]
+    fn add_type_alias_not_applicable_to_generic_types() {
+        check_assist_not_applicable(
+            add_type_alias,
+            r"
+fn frobnicate(x: Frobnicator<|><i32>) {}
+",
+        )
+    }
+}
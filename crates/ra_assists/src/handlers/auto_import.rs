@@ -4,7 +4,7 @@ use hir::{
     AsAssocItem, AssocItemContainer, ModPath, Module, ModuleDef, PathResolution, Semantics, Trait,
     Type,
 };
-use ra_ide_db::{imports_locator::ImportsLocator, RootDatabase};
+use ra_ide_db::{imports_locator::ImportsLocator, insert_use::insert_use_statement, RootDatabase};
 use ra_prof::profile;
 use ra_syntax::{
     ast::{self, AstNode},
@@ -14,7 +14,6 @@ use rustc_hash::FxHashSet;
 
 use crate::{
     assist_ctx::{Assist, AssistCtx},
-    utils::insert_use_statement,
     AssistId,
 };
 use either::Either;
@@ -45,6 +44,7 @@ pub(crate) fn auto_import(ctx: AssistCtx) -> Option<Assist> {
         return None;
     }
 
+    let config = ctx.config;
     let mut group = ctx.add_assist_group(auto_import_assets.get_import_group_message());
     for import in proposed_imports {
         group.add_assist(AssistId("auto_import"), format!("Import `{}`", &import), |edit| {
@@ -52,6 +52,7 @@ pub(crate) fn auto_import(ctx: AssistCtx) -> Option<Assist> {
             insert_use_statement(
                 &auto_import_assets.syntax_under_caret,
                 &import,
+                &config.insert_use,
                 edit.text_edit_builder(),
             );
         });
@@ -0,0 +1,455 @@
+use std::collections::BTreeMap;
+
+use hir::{PathResolution, ScopeDef};
+use ra_ide_db::{defs::classify_name, insert_use::render_use_items};
+use ra_syntax::{
+    ast::{self, edit::IndentLevel, AstNode, PathSegmentKind, VisibilityKind},
+    NodeOrToken, SyntaxKind, SyntaxNode, TextRange,
+};
+use ra_text_edit::TextEditBuilder;
+use rustc_hash::FxHashSet;
+use stdx::format_to;
+
+use crate::{
+    utils::{escape_snippet, indent_text, visibility_offset},
+    Assist, AssistCtx, AssistId,
+};
+
+// Assist: extract_module
+//
+// Moves the selected items into a new inline module.
+//
+// ```
+// struct Point { x: i32, y: i32 }
+//
+// <|>fn norm(p: &Point) -> i32 {
+//     p.x.abs() + p.y.abs()
+// }<|>
+//
+// fn main() {
+//     norm(&Point { x: 1, y: 2 });
+// }
+// ```
+// ->
+// ```
+// struct Point { x: i32, y: i32 }
+//
+// mod ${1:modname} {
+//     use super::Point;
+//
+//     pub(crate) fn norm(p: &Point) -> i32 {
+//         p.x.abs() + p.y.abs()
+//     }
+// }
+//
+// use $1::norm;
+//
+// fn main() {
+//     norm(&Point { x: 1, y: 2 });
+// }
+// ```
+pub(crate) fn extract_module(ctx: AssistCtx) -> Option<Assist> {
+    if ctx.frange.range.is_empty() {
+        return None;
+    }
+    let items = selected_items(&ctx)?;
+    let first = items.first()?;
+    let range = TextRange::from_to(first.text_range().start(), items.last()?.text_range().end());
+
+    let mut edits = Vec::new();
+    let imports = collect_imports(&ctx, &items, &mut edits)?;
+    let reexports = collect_reexports(&ctx, &items, range, &mut edits);
+
+    let file_text = first.ancestors().last()?.text().to_string();
+    let mut builder = TextEditBuilder::default();
+    for (edit_range, text) in edits {
+        let edit_range = TextRange::from_to(
+            edit_range.start() - range.start(),
+            edit_range.end() - range.start(),
+        );
+        builder.replace(edit_range, text);
+    }
+    let items_text = builder.finish().apply(&file_text[range]);
+
+    let indent = indent_text(IndentLevel::from_node(first));
+    let inner_indent = format!("{}    ", indent);
+    let mut buf = String::from("mod ${1:modname} {\n");
+    let imports = render_use_items("super", &imports, &ctx.config.insert_use);
+    for use_item in imports.iter() {
+        format_to!(buf, "{}{}\n", inner_indent, use_item);
+    }
+    if !imports.is_empty() {
+        buf.push('\n');
+    }
+    for (i, line) in items_text.lines().enumerate() {
+        if i == 0 {
+            buf.push_str(&inner_indent);
+        } else if !line.trim().is_empty() {
+            buf.push_str("    ");
+        }
+        if !line.trim().is_empty() {
+            buf.push_str(&escape_snippet(line));
+        }
+        buf.push('\n');
+    }
+    format_to!(buf, "{}}}", indent);
+    if !reexports.is_empty() {
+        buf.push('\n');
+    }
+    for (visibility, names) in reexports.iter() {
+        for use_item in render_use_items("$1", names, &ctx.config.insert_use) {
+            format_to!(buf, "\n{}{}{}", indent, visibility, use_item);
+        }
+    }
+
+    ctx.add_assist(AssistId("extract_module"), "Extract into module", |edit| {
+        edit.target(range);
+        edit.replace_snippet(range, buf);
+    })
+}
+
+/// Finds the items of a module which intersect the selection.
+fn selected_items(ctx: &AssistCtx) -> Option<Vec<SyntaxNode>> {
+    let node = match ctx.covering_element() {
+        NodeOrToken::Node(it) => it,
+        NodeOrToken::Token(it) => it.parent(),
+    };
+    let is_item_container = |node: &SyntaxNode| match node.kind() {
+        SyntaxKind::SOURCE_FILE => true,
+        SyntaxKind::ITEM_LIST => node.parent().map_or(false, |it| ast::Module::can_cast(it.kind())),
+        _ => false,
+    };
+    if ast::ModuleItem::can_cast(node.kind())
+        && node.parent().map_or(false, |it| is_item_container(&it))
+    {
+        return Some(vec![node]);
+    }
+    if !is_item_container(&node) {
+        return None;
+    }
+    let selection = ctx.frange.range;
+    let items: Vec<SyntaxNode> = node
+        .children()
+        .filter(|it| ast::ModuleItem::can_cast(it.kind()))
+        .filter(|it| {
+            let range = it.text_range();
+            range.start() < selection.end() && selection.start() < range.end()
+        })
+        .collect();
+    if items.is_empty() {
+        None
+    } else {
+        Some(items)
+    }
+}
+
+/// Finds the items of the parent module the selected items refer to, which
+/// have to be imported into the new module. Paths starting with `self` or
+/// `super` are adjusted to the deeper nesting.
+fn collect_imports(
+    ctx: &AssistCtx,
+    items: &[SyntaxNode],
+    edits: &mut Vec<(TextRange, String)>,
+) -> Option<Vec<String>> {
+    let module = ctx.sema.scope(&items[0]).module()?;
+    let scope = module.scope(ctx.db, None);
+    let defined: FxHashSet<String> = items
+        .iter()
+        .filter_map(|it| it.children().find_map(ast::Name::cast))
+        .map(|it| it.text().to_string())
+        .collect();
+
+    let mut imports = Vec::new();
+    let paths = items.iter().flat_map(|it| it.descendants()).filter_map(ast::Path::cast);
+    for path in paths.filter(|it| it.qualifier().is_none()) {
+        let segment = match path.segment() {
+            Some(it) => it,
+            None => continue,
+        };
+        let is_qualifier = path.syntax().parent().map_or(false, |it| {
+            ast::Path::can_cast(it.kind())
+                || ast::UseTree::cast(it).map_or(false, |it| it.use_tree_list().is_some())
+        });
+        match segment.kind() {
+            Some(PathSegmentKind::SuperKw) => {
+                edits.push((segment.syntax().text_range(), "super::super".to_string()))
+            }
+            Some(PathSegmentKind::SelfKw) if is_qualifier => {
+                edits.push((segment.syntax().text_range(), "super".to_string()))
+            }
+            Some(PathSegmentKind::Name(name_ref)) => {
+                let name = name_ref.text().to_string();
+                if defined.contains(&name) || imports.contains(&name) {
+                    continue;
+                }
+                let def = match ctx.sema.resolve_path(&path) {
+                    Some(PathResolution::Def(it)) => it,
+                    _ => continue,
+                };
+                let in_scope = scope.iter().any(|(scope_name, scope_def)| match scope_def {
+                    ScopeDef::ModuleDef(it) => *it == def && scope_name.to_string() == name,
+                    _ => false,
+                });
+                if in_scope {
+                    imports.push(name);
+                }
+            }
+            _ => (),
+        }
+    }
+    imports.sort();
+    Some(imports)
+}
+
+/// Makes the items and fields which are used outside of the selection
+/// `pub(crate)`. Returns the names of the selected items which are used, and
+/// have to be imported back into the parent module, grouped by the visibility
+/// the re-export needs to keep the items visible where they were before.
+fn collect_reexports(
+    ctx: &AssistCtx,
+    items: &[SyntaxNode],
+    range: TextRange,
+    edits: &mut Vec<(TextRange, String)>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut reexports: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let names = items.iter().flat_map(|it| it.descendants()).filter_map(ast::Name::cast);
+    for name in names {
+        let owner = match name.syntax().parent() {
+            Some(it) => it,
+            None => continue,
+        };
+        match owner.kind() {
+            SyntaxKind::FN_DEF
+            | SyntaxKind::STRUCT_DEF
+            | SyntaxKind::ENUM_DEF
+            | SyntaxKind::UNION_DEF
+            | SyntaxKind::TRAIT_DEF
+            | SyntaxKind::TYPE_ALIAS_DEF
+            | SyntaxKind::CONST_DEF
+            | SyntaxKind::STATIC_DEF
+            | SyntaxKind::RECORD_FIELD_DEF => (),
+            _ => continue,
+        }
+        // The items of traits and trait impls can't have a visibility, and
+        // the items in function bodies aren't visible outside of them.
+        if is_in_trait_or_trait_impl(&owner)
+            || owner.ancestors().skip(1).any(|it| it.kind() == SyntaxKind::BLOCK_EXPR)
+        {
+            continue;
+        }
+        let def = match classify_name(&ctx.sema, &name).and_then(|it| it.into_definition()) {
+            Some(it) => it,
+            None => continue,
+        };
+        let used_outside = def.find_usages(ctx.db, None).iter().any(|it| {
+            it.file_range.file_id != ctx.frange.file_id || !it.file_range.range.is_subrange(&range)
+        });
+        if !used_outside {
+            continue;
+        }
+        // Restricted visibilities are relative to the module of the item,
+        // so they would shrink by one level inside of the new module. The
+        // re-export keeps the original visibility instead.
+        let visibility = owner.children().find_map(ast::Visibility::cast);
+        let reexport_visibility = match &visibility {
+            None => String::new(),
+            Some(it) => format!("{} ", it.syntax()),
+        };
+        match visibility.as_ref().map(|it| (it, it.kind())) {
+            None => {
+                let offset = visibility_offset(&owner);
+                edits.push((TextRange::offset_len(offset, 0.into()), "pub(crate) ".to_string()));
+            }
+            Some((_, VisibilityKind::Pub)) | Some((_, VisibilityKind::PubCrate)) => (),
+            Some((it, _)) => edits.push((it.syntax().text_range(), "pub(crate)".to_string())),
+        }
+        if items.contains(&owner) {
+            reexports.entry(reexport_visibility).or_default().push(name.text().to_string());
+        }
+    }
+    for names in reexports.values_mut() {
+        names.sort();
+    }
+    reexports
+}
+
+fn is_in_trait_or_trait_impl(node: &SyntaxNode) -> bool {
+    let container = match node.parent().filter(|it| it.kind() == SyntaxKind::ITEM_LIST) {
+        Some(item_list) => item_list.parent(),
+        None => return false,
+    };
+    match container {
+        Some(it) if ast::TraitDef::can_cast(it.kind()) => true,
+        Some(it) => ast::ImplDef::cast(it).map_or(false, |it| it.target_trait().is_some()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn extracts_multiple_items() {
+        check_assist(
+            extract_module,
+            r#"
+struct Point { x: i32, y: i32 }
+
+<|>fn norm(p: &Point) -> i32 {
+    abs(p.x) + abs(p.y)
+}
+
+fn abs(x: i32) -> i32 {
+    if x < 0 { -x } else { x }
+}<|>
+"#,
+            r#"
+struct Point { x: i32, y: i32 }
+
+mod ${1:modname} {
+    use super::Point;
+
+    fn norm(p: &Point) -> i32 {
+        abs(p.x) + abs(p.y)
+    }
+
+    fn abs(x: i32) -> i32 {
+        if x < 0 { -x } else { x }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn makes_used_items_and_fields_visible() {
+        check_assist(
+            extract_module,
+            r#"
+<|>struct S {
+    a: i32,
+    b: i32,
+}
+
+impl S {
+    fn new() -> S { S { a: 0, b: 0 } }
+}<|>
+
+fn main() {
+    let s = S::new();
+    s.a;
+}
+"#,
+            r#"
+mod ${1:modname} {
+    pub(crate) struct S {
+        pub(crate) a: i32,
+        b: i32,
+    }
+
+    impl S {
+        pub(crate) fn new() -> S { S { a: 0, b: 0 } }
+    }
+}
+
+use $1::S;
+
+fn main() {
+    let s = S::new();
+    s.a;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn reexports_items_with_their_visibility() {
+        check_assist(
+            extract_module,
+            r#"
+mod m {
+    <|>pub struct A;
+    pub(crate) struct B;
+    pub(super) struct C;
+    struct D;<|>
+
+    fn f(_: A, _: B, _: C, _: D) {}
+}
+
+fn main() {
+    let _ = (m::A, m::B, m::C);
+}
+"#,
+            r#"
+mod m {
+    mod ${1:modname} {
+        pub struct A;
+        pub(crate) struct B;
+        pub(crate) struct C;
+        pub(crate) struct D;
+    }
+
+    use $1::D;
+    pub use $1::A;
+    pub(crate) use $1::B;
+    pub(super) use $1::C;
+
+    fn f(_: A, _: B, _: C, _: D) {}
+}
+
+fn main() {
+    let _ = (m::A, m::B, m::C);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn adjusts_relative_paths() {
+        check_assist(
+            extract_module,
+            r#"
+mod m {
+    pub fn f() {}
+}
+mod n {
+    <|>fn g() {
+        super::m::f();
+        self::h();
+    }<|>
+    fn h() {}
+}
+"#,
+            r#"
+mod m {
+    pub fn f() {}
+}
+mod n {
+    mod ${1:modname} {
+        fn g() {
+            super::super::m::f();
+            super::h();
+        }
+    }
+    fn h() {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_inside_of_item() {
+        check_assist_not_applicable(extract_module, "fn main() { <|>1 + 1<|>; }");
+    }
+
+    #[test]
+    fn not_applicable_to_impl_items() {
+        check_assist_not_applicable(
+            extract_module,
+            "struct S;\nimpl S {\n    <|>fn f() {}<|>\n}\n",
+        );
+    }
+}
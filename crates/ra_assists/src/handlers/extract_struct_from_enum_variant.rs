@@ -0,0 +1,340 @@
+use hir::{ModuleDef, SemanticsScope};
+use ra_db::FileId;
+use ra_ide_db::{defs::Definition, insert_use::insert_use_statement, RootDatabase};
+use ra_syntax::{
+    algo::find_covering_element,
+    ast::{
+        self, edit::IndentLevel, AstNode, AttrsOwner, NameOwner, StructKind, TypeParamsOwner,
+        VisibilityOwner,
+    },
+    SyntaxKind, SyntaxNode, TextRange,
+};
+use ra_text_edit::TextEditBuilder;
+use stdx::format_to;
+
+use crate::{
+    assist_ctx::ActionBuilder,
+    utils::{indent_text, visibility_offset},
+    Assist, AssistCtx, AssistId,
+};
+
+// Assist: extract_struct_from_enum_variant
+//
+// Extracts the fields of an enum variant into a struct of the same name.
+//
+// ```
+// enum A { <|>One { a: u32, b: u32 } }
+//
+// fn f(a: A) -> u32 {
+//     match a {
+//         A::One { a, .. } => a,
+//     }
+// }
+// ```
+// ->
+// ```
+// struct One { a: u32, b: u32 }
+//
+// enum A { One(One) }
+//
+// fn f(a: A) -> u32 {
+//     match a {
+//         A::One(One { a, .. }) => a,
+//     }
+// }
+// ```
+pub(crate) fn extract_struct_from_enum_variant(ctx: AssistCtx) -> Option<Assist> {
+    let variant = ctx.find_node_at_offset::<ast::EnumVariant>()?;
+    let name = variant.name()?;
+    if !name.syntax().text_range().contains_inclusive(ctx.frange.range.start()) {
+        return None;
+    }
+    let field_list = match variant.kind() {
+        StructKind::Record(it) => it,
+        _ => return None,
+    };
+    let enum_def = variant.parent_enum();
+    // The struct would need the generic parameters its fields use.
+    if enum_def.type_param_list().is_some() {
+        return None;
+    }
+    let struct_name = name.text().to_string();
+    let enum_scope = ctx.sema.scope(enum_def.syntax());
+    let module = enum_scope.module()?;
+    // The struct would shadow the other items of the name, like the ones from
+    // the prelude.
+    if is_in_scope(&enum_scope, &struct_name) {
+        return None;
+    }
+
+    // Wraps the fields of record literals and patterns into the struct, which
+    // is imported into the other modules using the variant.
+    let variant_def = ctx.sema.to_def(&variant)?;
+    let mut usages = Vec::new();
+    let mut imports = Vec::new();
+    let references =
+        Definition::ModuleDef(ModuleDef::EnumVariant(variant_def)).find_usages(ctx.db, None);
+    for reference in references {
+        let file_id = reference.file_range.file_id;
+        let source_file = ctx.sema.parse(file_id);
+        let path = find_covering_element(source_file.syntax(), reference.file_range.range)
+            .ancestors()
+            .find_map(ast::Path::cast)?;
+        let record = path.syntax().parent()?;
+        match record.kind() {
+            SyntaxKind::RECORD_LIT | SyntaxKind::RECORD_PAT => (),
+            _ => return None,
+        }
+        let scope = ctx.sema.scope(&record);
+        let usage_module = scope.module()?;
+        let mut struct_path = struct_name.clone();
+        if usage_module != module {
+            let mut mod_path = usage_module.find_use_path(ctx.db, ModuleDef::Module(module))?;
+            mod_path.segments.push(variant_def.name(ctx.db));
+            if is_in_scope(&scope, &struct_name) {
+                struct_path = mod_path.to_string();
+            } else if !imports.iter().any(|(_, it, _, _)| *it == usage_module) {
+                imports.push((file_id, usage_module, record.clone(), mod_path));
+            }
+        }
+        usages.push((
+            file_id,
+            path.syntax().text_range().end(),
+            record.text_range().end(),
+            struct_path,
+        ));
+    }
+
+    let indent = IndentLevel::from_node(enum_def.syntax());
+    let struct_def = struct_text(&enum_def, &variant, &field_list, &struct_name, indent);
+    let current_file = ctx.frange.file_id;
+    let config = ctx.config;
+    ctx.add_assist(
+        AssistId("extract_struct_from_enum_variant"),
+        "Extract struct from enum variant",
+        |edit| {
+            edit.target(variant.syntax().text_range());
+            edit.insert(enum_def.syntax().text_range().start(), struct_def);
+            edit.replace(field_list.syntax().text_range(), format!("({})", struct_name));
+            for (file_id, path_end, record_end, struct_path) in usages {
+                let builder = file_edit_builder(edit, current_file, file_id);
+                builder.insert(path_end, format!("({}", struct_path));
+                builder.insert(record_end, ")".to_string());
+            }
+            for (file_id, _, position, mod_path) in imports {
+                let builder = file_edit_builder(edit, current_file, file_id);
+                insert_use_statement(&position, &mod_path, &config.insert_use, builder);
+            }
+        },
+    )
+}
+
+fn is_in_scope(scope: &SemanticsScope<'_, RootDatabase>, name: &str) -> bool {
+    let mut res = false;
+    scope.process_all_names(&mut |it, _| res |= it.to_string() == name);
+    res
+}
+
+fn file_edit_builder<'a>(
+    edit: &'a mut ActionBuilder,
+    current_file: FileId,
+    file_id: FileId,
+) -> &'a mut TextEditBuilder {
+    if file_id == current_file {
+        edit.text_edit_builder()
+    } else {
+        edit.other_file_edit_builder(file_id)
+    }
+}
+
+/// Renders the struct, followed by the indent of the enum. The struct and its
+/// fields are as visible as the enum, and get its derives.
+fn struct_text(
+    enum_def: &ast::EnumDef,
+    variant: &ast::EnumVariant,
+    field_list: &ast::RecordFieldDefList,
+    struct_name: &str,
+    indent: IndentLevel,
+) -> String {
+    let visibility = enum_def.visibility().map(|it| format!("{} ", it.syntax()));
+
+    let levels = IndentLevel::from_node(variant.syntax()).0.saturating_sub(indent.0);
+    let field_list = IndentLevel(levels).decrease_indent(field_list.clone());
+    let mut fields = field_list.syntax().text().to_string();
+    if let Some(visibility) = &visibility {
+        let start = field_list.syntax().text_range().start();
+        let mut builder = TextEditBuilder::default();
+        for field in field_list.fields().filter(|it| it.visibility().is_none()) {
+            builder.insert(visibility_offset(field.syntax()) - start, visibility.clone());
+        }
+        fields = builder.finish().apply(&fields);
+    }
+
+    let mut buf = String::new();
+    for attr in enum_def.attrs().filter(|it| it.simple_name().as_deref() == Some("derive")) {
+        format_to!(buf, "{}\n{}", attr.syntax(), indent_text(indent));
+    }
+    format_to!(
+        buf,
+        "{}struct {} {}\n\n{}",
+        visibility.unwrap_or_default(),
+        struct_name,
+        fields,
+        indent_text(indent)
+    );
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn extracts_record_variant() {
+        check_assist(
+            extract_struct_from_enum_variant,
+            r#"
+#[derive(Debug, Clone)]
+pub enum A {
+    <|>One {
+        a: u32,
+        /// The b.
+        b: String,
+    },
+    Two,
+}
+
+fn f() -> A {
+    A::One { a: 1, b: String::new() }
+}
+"#,
+            r#"
+#[derive(Debug, Clone)]
+pub struct One {
+    pub a: u32,
+    /// The b.
+    pub b: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum A {
+    <|>One(One),
+    Two,
+}
+
+fn f() -> A {
+    A::One(One { a: 1, b: String::new() })
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn rewrites_patterns() {
+        check_assist(
+            extract_struct_from_enum_variant,
+            r#"
+enum A { <|>One { a: u32 }, Two }
+
+fn f(a: A) -> u32 {
+    if let A::One { a } = a { a } else { 0 }
+}
+"#,
+            r#"
+struct One { a: u32 }
+
+enum A { <|>One(One), Two }
+
+fn f(a: A) -> u32 {
+    if let A::One(One { a }) = a { a } else { 0 }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn rewrites_usages_in_other_files() {
+        check_assist(
+            extract_struct_from_enum_variant,
+            r#"
+//- /main.rs
+mod a;
+
+enum A { <|>One { a: u32 } }
+
+mod b {
+    fn f() -> crate::A {
+        crate::A::One { a: 1 }
+    }
+}
+//- /a.rs
+struct One;
+
+fn g() -> crate::A {
+    crate::A::One { a: 2 }
+}
+"#,
+            r#"mod a;
+
+struct One { a: u32 }
+
+enum A { <|>One(One) }
+
+mod b {
+    use crate::One;
+
+    fn f() -> crate::A {
+        crate::A::One(One { a: 1 })
+    }
+}
+//- /a.rs
+struct One;
+
+fn g() -> crate::A {
+    crate::A::One(crate::One { a: 2 })
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_to_tuple_variants() {
+        check_assist_not_applicable(extract_struct_from_enum_variant, "enum A { <|>One(u32) }");
+    }
+
+    #[test]
+    fn not_applicable_if_name_is_taken() {
+        check_assist_not_applicable(
+            extract_struct_from_enum_variant,
+            "struct One;\nenum A { <|>One { a: u32 } }",
+        );
+    }
+
+    #[test]
+    fn not_applicable_if_name_is_in_prelude() {
+        check_assist_not_applicable(
+            extract_struct_from_enum_variant,
+            r#"
+//- /main.rs crate:main deps:std
+enum Json { <|>String { value: String } }
+
+//- /std.rs crate:std
+#[prelude_import]
+pub use self::prelude::*;
+mod prelude {
+    pub struct String;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_to_generic_enums() {
+        check_assist_not_applicable(
+            extract_struct_from_enum_variant,
+            "enum A<T> { <|>One { a: T } }",
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::iter;
 
-use hir::{Adt, HasSource, ModuleDef, Semantics};
+use hir::{Adt, HasAttrs, HasSource, ModuleDef, PathResolution, Semantics};
 use itertools::Itertools;
 use ra_ide_db::RootDatabase;
 use ra_syntax::ast::{self, make, AstNode, MatchArm, NameOwner, Pat};
@@ -43,24 +43,21 @@ pub(crate) fn fill_match_arms(ctx: AssistCtx) -> Option<Assist> {
             arms.clear();
         }
     }
+    let arm_pats: Vec<Pat> = arms.iter().filter_map(|arm| arm.pat()).collect();
+    // An arm with a guard doesn't cover anything.
+    let covering_pats: Vec<Pat> =
+        arms.iter().filter(|arm| arm.guard().is_none()).filter_map(|arm| arm.pat()).collect();
 
     let module = ctx.sema.scope(expr.syntax()).module()?;
 
-    let missing_arms: Vec<MatchArm> = if let Some(enum_def) = resolve_enum_def(&ctx.sema, &expr) {
-        let variants = enum_def.variants(ctx.db);
-
-        variants
+    let (candidates, enum_defs) = if let Some(enum_def) = resolve_enum_def(&ctx.sema, &expr) {
+        let candidates = enum_def
+            .variants(ctx.db)
             .into_iter()
-            .filter_map(|variant| build_pat(ctx.db, module, variant))
-            .filter(|variant_pat| is_variant_missing(&mut arms, variant_pat))
-            .map(|pat| make::match_arm(iter::once(pat), make::expr_empty_block()))
-            .collect()
+            .flat_map(|variant| expand_variant(&ctx.sema, &arm_pats, variant))
+            .collect::<Vec<_>>();
+        (candidates, vec![enum_def])
     } else if let Some(enum_defs) = resolve_tuple_of_enum_def(&ctx.sema, &expr) {
-        // Partial fill not currently supported for tuple of enums.
-        if !arms.is_empty() {
-            return None;
-        }
-
         // We do not currently support filling match arms for a tuple
         // containing a single enum.
         if enum_defs.len() < 2 {
@@ -72,22 +69,43 @@ pub(crate) fn fill_match_arms(ctx: AssistCtx) -> Option<Assist> {
         // values. The `multi_cartesian_product` method transforms
         // Vec<Vec<EnumVariant>> into Vec<(EnumVariant, .., EnumVariant)>
         // where each tuple represents a proposed match arm.
-        enum_defs
-            .into_iter()
+        let candidates = enum_defs
+            .iter()
             .map(|enum_def| enum_def.variants(ctx.db))
             .multi_cartesian_product()
             .map(|variants| {
-                let patterns =
-                    variants.into_iter().filter_map(|variant| build_pat(ctx.db, module, variant));
-                ast::Pat::from(make::tuple_pat(patterns))
+                Candidate::Tuple(
+                    variants.into_iter().map(|it| Candidate::Variant(it, Vec::new())).collect(),
+                )
             })
-            .filter(|variant_pat| is_variant_missing(&mut arms, variant_pat))
-            .map(|pat| make::match_arm(iter::once(pat), make::expr_empty_block()))
-            .collect()
+            .collect::<Vec<_>>();
+        (candidates, enum_defs)
     } else {
         return None;
     };
 
+    let mut missing_arms: Vec<MatchArm> = candidates
+        .iter()
+        .filter(|candidate| !covering_pats.iter().any(|pat| covers(&ctx.sema, pat, candidate)))
+        .filter_map(|candidate| build_pat(ctx.db, module, candidate))
+        .map(|pat| make::match_arm(iter::once(pat), make::expr_empty_block()))
+        .collect();
+
+    // Enums marked `#[non_exhaustive]` in other crates always need a wildcard
+    // arm.
+    let is_non_exhaustive = enum_defs.iter().any(|enum_def| {
+        Adt::Enum(*enum_def).attrs(ctx.db).is_non_exhaustive()
+            && enum_def.module(ctx.db).krate() != module.krate()
+    });
+    if is_non_exhaustive
+        && !covering_pats.iter().any(|pat| covers(&ctx.sema, pat, &Candidate::Wild))
+    {
+        missing_arms.push(make::match_arm(
+            iter::once(make::placeholder_pat().into()),
+            make::expr_empty_block(),
+        ));
+    }
+
     if missing_arms.is_empty() {
         return None;
     }
@@ -101,23 +119,171 @@ pub(crate) fn fill_match_arms(ctx: AssistCtx) -> Option<Assist> {
     })
 }
 
-fn is_variant_missing(existing_arms: &mut Vec<MatchArm>, var: &Pat) -> bool {
-    existing_arms.iter().filter_map(|arm| arm.pat()).all(|pat| {
-        // Special casee OrPat as separate top-level pats
-        let top_level_pats: Vec<Pat> = match pat {
-            Pat::OrPat(pats) => pats.pats().collect::<Vec<_>>(),
-            _ => vec![pat],
+/// A value (or set of values) a match arm may be missing for.
+enum Candidate {
+    /// A variant, with candidates for its tuple fields if existing arms match
+    /// on them. No candidates means the fields are not matched on.
+    Variant(hir::EnumVariant, Vec<Candidate>),
+    Tuple(Vec<Candidate>),
+    Wild,
+}
+
+/// Splits `variant` into one candidate per variant of the enums its tuple
+/// fields are matched against in `arm_pats`. We only look one level deep.
+fn expand_variant(
+    sema: &Semantics<RootDatabase>,
+    arm_pats: &[Pat],
+    variant: hir::EnumVariant,
+) -> Vec<Candidate> {
+    let field_count = variant.fields(sema.db).len();
+    let mut field_enums: Vec<Option<hir::Enum>> = vec![None; field_count];
+    for pat in arm_pats.iter().flat_map(alternatives) {
+        let pat = match pat {
+            Pat::TupleStructPat(it) => it,
+            _ => continue,
         };
+        if pat_variant(sema, &Pat::from(pat.clone())) != Some(variant) {
+            continue;
+        }
+        let args: Vec<Pat> = pat.args().collect();
+        if args.iter().any(|arg| is_rest_pat(arg)) {
+            continue;
+        }
+        for (field_enum, arg) in field_enums.iter_mut().zip(args.iter()) {
+            if let Some(arg_variant) = pat_variant(sema, arg) {
+                *field_enum = Some(arg_variant.parent_enum(sema.db));
+            }
+        }
+    }
 
-        !top_level_pats.iter().any(|pat| does_pat_match_variant(pat, var))
-    })
+    if field_enums.iter().all(Option::is_none) {
+        return vec![Candidate::Variant(variant, Vec::new())];
+    }
+    field_enums
+        .into_iter()
+        .map(|field_enum| match field_enum {
+            Some(field_enum) => field_enum.variants(sema.db),
+            None => Vec::new(),
+        })
+        .map(|variants| {
+            if variants.is_empty() {
+                vec![None]
+            } else {
+                variants.into_iter().map(Some).collect()
+            }
+        })
+        .multi_cartesian_product()
+        .map(|fields| {
+            let fields = fields
+                .into_iter()
+                .map(|it| match it {
+                    Some(it) => Candidate::Variant(it, Vec::new()),
+                    None => Candidate::Wild,
+                })
+                .collect();
+            Candidate::Variant(variant, fields)
+        })
+        .collect()
+}
+
+/// Returns `true` if every value described by `candidate` matches `pat`.
+///
+/// Patterns we can't reason about, like unresolved paths, are assumed to
+/// match.
+fn covers(sema: &Semantics<RootDatabase>, pat: &Pat, candidate: &Candidate) -> bool {
+    match pat {
+        Pat::OrPat(it) => it.pats().any(|pat| covers(sema, &pat, candidate)),
+        Pat::ParenPat(it) => it.pat().map_or(false, |pat| covers(sema, &pat, candidate)),
+        Pat::RefPat(it) => it.pat().map_or(false, |pat| covers(sema, &pat, candidate)),
+        Pat::PlaceholderPat(_) | Pat::DotDotPat(_) => true,
+        Pat::BindPat(it) => match sema.resolve_bind_pat_to_const(it) {
+            Some(ModuleDef::EnumVariant(variant)) => is_variant(candidate, variant),
+            Some(_) => false,
+            None => it.pat().map_or(true, |pat| covers(sema, &pat, candidate)),
+        },
+        Pat::PathPat(_) | Pat::RecordPat(_) => match pat_variant(sema, pat) {
+            Some(variant) => is_variant(candidate, variant),
+            None => true,
+        },
+        Pat::TupleStructPat(it) => match (pat_variant(sema, pat), candidate) {
+            (Some(variant), Candidate::Variant(candidate_variant, fields)) => {
+                variant == *candidate_variant && fields_cover(sema, it.args(), fields)
+            }
+            (Some(_), _) => false,
+            (None, _) => true,
+        },
+        Pat::TuplePat(it) => match candidate {
+            Candidate::Tuple(fields) => fields_cover(sema, it.args(), fields),
+            Candidate::Wild => fields_cover(sema, it.args(), &[]),
+            Candidate::Variant(..) => false,
+        },
+        _ => false,
+    }
+}
+
+fn fields_cover(
+    sema: &Semantics<RootDatabase>,
+    pats: impl Iterator<Item = Pat>,
+    fields: &[Candidate],
+) -> bool {
+    let pats: Vec<Pat> = pats.collect();
+    if fields.is_empty() {
+        return pats.iter().all(|pat| covers(sema, pat, &Candidate::Wild));
+    }
+    match pats.iter().position(is_rest_pat) {
+        Some(idx) => {
+            let (prefix, suffix) = (&pats[..idx], &pats[idx + 1..]);
+            prefix.iter().zip(fields.iter()).all(|(pat, field)| covers(sema, pat, field))
+                && suffix
+                    .iter()
+                    .rev()
+                    .zip(fields.iter().rev())
+                    .all(|(pat, field)| covers(sema, pat, field))
+        }
+        None => pats.iter().zip(fields.iter()).all(|(pat, field)| covers(sema, pat, field)),
+    }
+}
+
+fn is_variant(candidate: &Candidate, variant: hir::EnumVariant) -> bool {
+    match candidate {
+        Candidate::Variant(candidate_variant, _) => *candidate_variant == variant,
+        _ => false,
+    }
 }
 
-fn does_pat_match_variant(pat: &Pat, var: &Pat) -> bool {
-    let pat_head = pat.syntax().first_child().map(|node| node.text());
-    let var_head = var.syntax().first_child().map(|node| node.text());
+fn is_rest_pat(pat: &Pat) -> bool {
+    match pat {
+        Pat::DotDotPat(_) => true,
+        _ => false,
+    }
+}
+
+/// Flattens or-patterns (and patterns wrapping them) into their alternatives.
+fn alternatives(pat: &Pat) -> Vec<Pat> {
+    match pat {
+        Pat::OrPat(it) => it.pats().flat_map(|pat| alternatives(&pat)).collect(),
+        Pat::ParenPat(it) => it.pat().map(|pat| alternatives(&pat)).unwrap_or_default(),
+        Pat::RefPat(it) => it.pat().map(|pat| alternatives(&pat)).unwrap_or_default(),
+        _ => vec![pat.clone()],
+    }
+}
 
-    pat_head == var_head
+/// Returns the enum variant `pat` matches on, if any.
+fn pat_variant(sema: &Semantics<RootDatabase>, pat: &Pat) -> Option<hir::EnumVariant> {
+    let path = match pat {
+        Pat::PathPat(it) => it.path()?,
+        Pat::RecordPat(it) => it.path()?,
+        Pat::TupleStructPat(it) => it.path()?,
+        Pat::BindPat(it) => match sema.resolve_bind_pat_to_const(it)? {
+            ModuleDef::EnumVariant(it) => return Some(it),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match sema.resolve_path(&path)? {
+        PathResolution::Def(ModuleDef::EnumVariant(it)) => Some(it),
+        _ => None,
+    }
 }
 
 fn resolve_enum_def(sema: &Semantics<RootDatabase>, expr: &ast::Expr) -> Option<hir::Enum> {
@@ -146,11 +312,44 @@ fn resolve_tuple_of_enum_def(
         .collect()
 }
 
-fn build_pat(db: &RootDatabase, module: hir::Module, var: hir::EnumVariant) -> Option<ast::Pat> {
+fn build_pat(db: &RootDatabase, module: hir::Module, candidate: &Candidate) -> Option<ast::Pat> {
+    match candidate {
+        Candidate::Wild => Some(make::placeholder_pat().into()),
+        Candidate::Tuple(fields) => {
+            let pats = fields
+                .iter()
+                .map(|field| build_pat(db, module, field))
+                .collect::<Option<Vec<_>>>()?;
+            Some(make::tuple_pat(pats).into())
+        }
+        Candidate::Variant(var, fields) => build_variant_pat(db, module, *var, fields),
+    }
+}
+
+fn build_variant_pat(
+    db: &RootDatabase,
+    module: hir::Module,
+    var: hir::EnumVariant,
+    fields: &[Candidate],
+) -> Option<ast::Pat> {
     let path = crate::ast_transform::path_to_ast(module.find_use_path(db, ModuleDef::from(var))?);
+    // Variants marked `#[non_exhaustive]` in other crates can only be matched
+    // with `..`.
+    let is_non_exhaustive =
+        var.attrs(db).is_non_exhaustive() && var.module(db).krate() != module.krate();
 
     // FIXME: use HIR for this; it doesn't currently expose struct vs. tuple vs. unit variants though
     let pat: ast::Pat = match var.source(db).value.kind() {
+        ast::StructKind::Tuple(_) if is_non_exhaustive => {
+            make::tuple_struct_pat(path, iter::once(make::dot_dot_pat().into())).into()
+        }
+        ast::StructKind::Tuple(_) if !fields.is_empty() => {
+            let pats = fields
+                .iter()
+                .map(|field| build_pat(db, module, field))
+                .collect::<Option<Vec<_>>>()?;
+            make::tuple_struct_pat(path, pats).into()
+        }
         ast::StructKind::Tuple(field_list) => {
             let pats =
                 iter::repeat(make::placeholder_pat().into()).take(field_list.fields().count());
@@ -158,7 +357,9 @@ fn build_pat(db: &RootDatabase, module: hir::Module, var: hir::EnumVariant) -> O
         }
         ast::StructKind::Record(field_list) => {
             let pats = field_list.fields().map(|f| make::bind_pat(f.name().unwrap()).into());
-            make::record_pat(path, pats).into()
+            let rest: Option<ast::Pat> =
+                if is_non_exhaustive { Some(make::dot_dot_pat().into()) } else { None };
+            make::record_pat(path, pats.chain(rest)).into()
         }
         ast::StructKind::Unit => make::path_pat(path),
     };
@@ -316,7 +517,9 @@ mod tests {
                     A::Ds(_value) => { let x = 1; }
                     A::Es(B::Xs) => (),
                     A::As => {}
+                    A::Bs => {}
                     A::Cs => {}
+                    A::Es(B::Ys) => {}
                 }
             }
             "#,
@@ -454,7 +657,7 @@ mod tests {
 
     #[test]
     fn fill_match_arms_tuple_of_enum_partial() {
-        check_assist_not_applicable(
+        check_assist(
             fill_match_arms,
             r#"
             enum A {
@@ -471,6 +674,29 @@ mod tests {
                 let b = B::One;
                 match (a<|>, b) {
                     (A::Two, B::One) => {}
+                    (A::One, _) if true => {}
+                    (_, B::Two) => {}
+                }
+            }
+            "#,
+            r#"
+            enum A {
+                One,
+                Two,
+            }
+            enum B {
+                One,
+                Two,
+            }
+
+            fn main() {
+                let a = A::One;
+                let b = B::One;
+                match <|>(a, b) {
+                    (A::Two, B::One) => {}
+                    (A::One, _) if true => {}
+                    (_, B::Two) => {}
+                    (A::One, B::One) => {}
                 }
             }
             "#,
@@ -737,4 +963,105 @@ mod tests {
             "#,
         );
     }
+
+    #[test]
+    fn fill_match_arms_nested_tuple_variant_fields() {
+        check_assist(
+            fill_match_arms,
+            r#"
+            enum A { X(B, u32), Y }
+            enum B { One, Two(u32), Three }
+            fn foo(a: A) {
+                match a<|> {
+                    A::X(B::One, 0) => {}
+                    A::X(B::Two(_), n) => {}
+                }
+            }
+            "#,
+            r#"
+            enum A { X(B, u32), Y }
+            enum B { One, Two(u32), Three }
+            fn foo(a: A) {
+                match <|>a {
+                    A::X(B::One, 0) => {}
+                    A::X(B::Two(_), n) => {}
+                    A::X(B::One, _) => {}
+                    A::X(B::Three, _) => {}
+                    A::Y => {}
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn fill_match_arms_bindings_cover_everything() {
+        check_assist_not_applicable(
+            fill_match_arms,
+            r#"
+            enum A { One, Two }
+            fn foo(a: A) {
+                match a<|> {
+                    A::One => {}
+                    other => {}
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn fill_match_arms_non_exhaustive_enum() {
+        check_assist(
+            fill_match_arms,
+            r#"
+            //- /main.rs crate:main deps:e
+            fn foo(t: e::E) {
+                match t<|> {
+                    e::E::A => {}
+                }
+            }
+            //- /e.rs crate:e
+            #[non_exhaustive]
+            pub enum E { A, B }
+            "#,
+            r#"fn foo(t: e::E) {
+    match <|>t {
+        e::E::A => {}
+        e::E::B => {}
+        _ => {}
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn fill_match_arms_non_exhaustive_variants() {
+        check_assist(
+            fill_match_arms,
+            r#"
+            //- /main.rs crate:main deps:e
+            fn foo(t: e::E) {
+                match t<|> {}
+            }
+            //- /e.rs crate:e
+            pub enum E {
+                #[non_exhaustive]
+                A(u32),
+                #[non_exhaustive]
+                B { x: u32 },
+                C,
+            }
+            "#,
+            r#"fn foo(t: e::E) {
+    match <|>t {
+        e::E::A(..) => {}
+        e::E::B { x, .. } => {}
+        e::E::C => {}
+    }
+}
+"#,
+        );
+    }
 }
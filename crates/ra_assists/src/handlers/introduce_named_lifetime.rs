@@ -0,0 +1,253 @@
+use ra_syntax::{
+    ast::{
+        self,
+        lifetimes::{declared_lifetimes, fresh_lifetime, lifetime_of, lifetimes, Lifetime},
+        AstNode, NameOwner, TypeAscriptionOwner, TypeParamsOwner,
+    },
+    SyntaxKind, SyntaxNode, TextRange, TextUnit, T,
+};
+
+use crate::{Assist, AssistCtx, AssistId};
+
+// Assist: introduce_named_lifetime
+//
+// Introduces a named lifetime parameter for an anonymous lifetime of a
+// function or impl signature.
+//
+// ```
+// fn first(s: &<|>str) -> &str { s }
+// ```
+// ->
+// ```
+// fn first<'a>(s: &'a str) -> &'a str { s }
+// ```
+pub(crate) fn introduce_named_lifetime(ctx: AssistCtx) -> Option<Assist> {
+    let (node, selected) = selected_lifetime(&ctx)?;
+    let (owner, ranges) = match find_owner(&node)? {
+        Owner::Fn(fn_def) => {
+            let ranges = fn_lifetime_ranges(&fn_def, selected)?;
+            (Owner::Fn(fn_def), ranges)
+        }
+        owner => (owner, vec![selected]),
+    };
+    let name = fresh_lifetime_for(&owner);
+    let (offset, param) = generic_param_insertion(&owner, &name)?;
+
+    ctx.add_assist(AssistId("introduce_named_lifetime"), "Introduce named lifetime", |edit| {
+        edit.target(selected);
+        edit.insert(offset, param);
+        for range in ranges {
+            if range.is_empty() {
+                edit.insert(range.start(), format!("{} ", name));
+            } else {
+                edit.replace(range, name.clone());
+            }
+        }
+    })
+}
+
+/// Finds the anonymous lifetime under the cursor: the range of a `'_`, or the
+/// empty range after the `&` of a reference without a lifetime.
+fn selected_lifetime(ctx: &AssistCtx) -> Option<(SyntaxNode, TextRange)> {
+    if let Some(lifetime) = ctx.find_token_at_offset(T![lifetime]) {
+        if lifetime.text() != "'_" {
+            return None;
+        }
+        return Some((lifetime.parent(), lifetime.text_range()));
+    }
+    let amp = ctx.find_token_at_offset(T![&])?;
+    let node = amp.parent();
+    let has_lifetime = if let Some(ty) = ast::ReferenceType::cast(node.clone()) {
+        ty.lifetime_token().is_some()
+    } else {
+        ast::SelfParam::cast(node.clone())?.lifetime_token().is_some()
+    };
+    if has_lifetime {
+        return None;
+    }
+    Some((node, TextRange::offset_len(amp.text_range().end(), 0.into())))
+}
+
+enum Owner {
+    Fn(ast::FnDef),
+    Impl(ast::ImplDef),
+}
+
+/// Finds the function or impl with the lifetime in its signature. Lifetimes in
+/// bodies, in other items and in `fn` pointers or `Fn` traits, which have
+/// their own lifetime scope, can't be named by a parameter of those.
+fn find_owner(node: &SyntaxNode) -> Option<Owner> {
+    for it in node.ancestors() {
+        match it.kind() {
+            SyntaxKind::FN_DEF => return ast::FnDef::cast(it).map(Owner::Fn),
+            SyntaxKind::IMPL_DEF => return ast::ImplDef::cast(it).map(Owner::Impl),
+            SyntaxKind::BLOCK_EXPR
+            | SyntaxKind::ITEM_LIST
+            | SyntaxKind::FN_POINTER_TYPE
+            | SyntaxKind::FOR_TYPE => return None,
+            SyntaxKind::PARAM_LIST | SyntaxKind::RET_TYPE
+                if it.parent().map_or(false, |it| it.kind() == SyntaxKind::PATH_SEGMENT) =>
+            {
+                return None
+            }
+            kind if ast::ModuleItem::can_cast(kind) => return None,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Finds the lifetimes to name in a function, so that the elided lifetimes
+/// of the return type keep referring to the same lifetime of the parameters.
+fn fn_lifetime_ranges(fn_def: &ast::FnDef, selected: TextRange) -> Option<Vec<TextRange>> {
+    let param_list = fn_def.param_list()?;
+    let self_param = param_list.self_param();
+    let self_lifetime =
+        self_param.as_ref().and_then(|it| lifetime_of(it.amp_token(), it.lifetime_token()));
+    let mut inputs: Vec<Lifetime> = self_lifetime.clone().into_iter().collect();
+    let types = self_param
+        .and_then(|it| it.ascribed_type())
+        .into_iter()
+        .chain(param_list.params().filter_map(|it| it.ascribed_type()));
+    for ty in types {
+        inputs.extend(lifetimes(ty.syntax()));
+    }
+    let outputs: Vec<TextRange> = fn_def
+        .ret_type()
+        .and_then(|it| it.type_ref())
+        .map(|it| lifetimes(it.syntax()))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|it| match it {
+            Lifetime::Elided(range) => Some(range),
+            Lifetime::Named(_) => None,
+        })
+        .collect();
+    let source = match (self_lifetime, inputs.as_slice()) {
+        (Some(it), _) => Some(it),
+        (None, [it]) => Some(it.clone()),
+        (None, _) => None,
+    };
+
+    let mut res = vec![selected];
+    if outputs.contains(&selected) {
+        // The lifetime of the return type comes from a parameter, which has
+        // to get the same name.
+        match source {
+            Some(Lifetime::Elided(range)) => res.push(range),
+            _ => return None,
+        }
+        res.extend(outputs.into_iter().filter(|it| *it != selected));
+    } else if source == Some(Lifetime::Elided(selected)) {
+        res.extend(outputs);
+    }
+    Some(res)
+}
+
+/// Picks the first of `'a`, `'b`, ... which isn't a lifetime parameter of the
+/// owner, or of the impl or trait of a function.
+fn fresh_lifetime_for(owner: &Owner) -> String {
+    let declared = match owner {
+        Owner::Fn(fn_def) => declared_lifetimes(fn_def),
+        Owner::Impl(impl_def) => impl_def
+            .type_param_list()
+            .into_iter()
+            .flat_map(|it| it.lifetime_params())
+            .filter_map(|it| Some(it.lifetime_token()?.text().to_string()))
+            .collect(),
+    };
+    fresh_lifetime(&declared)
+}
+
+/// Where to declare the lifetime: first in the generic parameters, which are
+/// added after the name of a function or after `impl` if there are none.
+fn generic_param_insertion(owner: &Owner, name: &str) -> Option<(TextUnit, String)> {
+    let (param_list, anchor) = match owner {
+        Owner::Fn(fn_def) => (fn_def.type_param_list(), fn_def.name()?.syntax().text_range()),
+        Owner::Impl(impl_def) => (impl_def.type_param_list(), impl_def.impl_token()?.text_range()),
+    };
+    match param_list {
+        Some(param_list) => {
+            let offset = param_list.l_angle_token()?.text_range().end();
+            if param_list.generic_params().next().is_some() {
+                Some((offset, format!("{}, ", name)))
+            } else {
+                Some((offset, name.to_string()))
+            }
+        }
+        None => Some((anchor.end(), format!("<{}>", name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn names_input_and_output_lifetimes() {
+        check_assist(
+            introduce_named_lifetime,
+            "fn first(s: &<|>str) -> &str { s }",
+            "fn first<'a>(s: &<|>'a str) -> &'a str { s }",
+        );
+        check_assist(
+            introduce_named_lifetime,
+            "fn first(s: &str) -> &<|>str { s }",
+            "fn first<'a>(s: &'a str) -> &<|>'a str { s }",
+        );
+    }
+
+    #[test]
+    fn names_only_selected_input() {
+        check_assist(
+            introduce_named_lifetime,
+            "fn longest<T>(a: &<|>str, b: &str) {}",
+            "fn longest<'a, T>(a: &<|>'a str, b: &str) {}",
+        );
+    }
+
+    #[test]
+    fn names_lifetime_of_self() {
+        check_assist(
+            introduce_named_lifetime,
+            r#"
+struct S;
+impl<'a> S {
+    fn get(&<|>self, key: &str) -> &str {}
+}
+"#,
+            r#"
+struct S;
+impl<'a> S {
+    fn get<'b>(&<|>'b self, key: &str) -> &'b str {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn names_anonymous_lifetime_of_impl() {
+        check_assist(
+            introduce_named_lifetime,
+            "impl Cursor<<|>'_> {}",
+            "impl<'a> Cursor<<|>'a> {}",
+        );
+    }
+
+    #[test]
+    fn not_applicable_to_ambiguous_output() {
+        check_assist_not_applicable(
+            introduce_named_lifetime,
+            "fn longest(a: &str, b: &str) -> &<|>str {}",
+        );
+    }
+
+    #[test]
+    fn not_applicable_to_named_lifetimes_and_bodies() {
+        check_assist_not_applicable(introduce_named_lifetime, "fn f<'a>(s: &<|>'a str) {}");
+        check_assist_not_applicable(introduce_named_lifetime, "fn f() { let s: &<|>str = \"\"; }");
+        check_assist_not_applicable(introduce_named_lifetime, "fn f(g: fn(&<|>str)) {}");
+    }
+}
@@ -0,0 +1,570 @@
+use hir::{Adt, HasSource, PathResolution, Semantics};
+use ra_ide_db::{
+    defs::{classify_name, Definition},
+    moves::may_be_moved,
+    search::{ReferenceAccess, ReferenceKind},
+    RootDatabase,
+};
+use ra_syntax::{
+    algo::{find_node_at_offset, non_trivia_sibling},
+    ast::{
+        self, edit::IndentLevel, AstNode, NameOwner, SelfParamKind, StructKind, TypeAscriptionOwner,
+    },
+    match_ast, Direction, SyntaxKind, SyntaxNode, TextRange, TextUnit, T,
+};
+
+use crate::{
+    utils::{indent_text, original_node, render_type_of_expr},
+    Assist, AssistCtx, AssistId,
+};
+
+// Assist: promote_local_to_field
+//
+// Turns a local variable of a method into a field of `self`, which is
+// initialized in `new`.
+//
+// ```
+// struct Counter { count: u32 }
+// impl Counter {
+//     fn new() -> Counter { Counter { count: 0 } }
+//     fn tick(&mut self) {
+//         let <|>step: u32 = 2;
+//         self.count += step;
+//     }
+// }
+// ```
+// ->
+// ```
+// struct Counter { count: u32, step: u32 }
+// impl Counter {
+//     fn new() -> Counter { Counter { count: 0, step: 2 } }
+//     fn tick(&mut self) {
+//         self.count += self.step;
+//     }
+// }
+// ```
+pub(crate) fn promote_local_to_field(ctx: AssistCtx) -> Option<Assist> {
+    let let_stmt = ctx.find_node_at_offset::<ast::LetStmt>()?;
+    let bind_pat = match let_stmt.pat()? {
+        ast::Pat::BindPat(it) if it.ref_token().is_none() && it.pat().is_none() => it,
+        _ => return None,
+    };
+    if !bind_pat.syntax().text_range().contains_inclusive(ctx.frange.range.start()) {
+        return None;
+    }
+    let name = bind_pat.name()?.text().to_string();
+    // The initializer moves to `new`, where the locals of the method are gone.
+    let initializer = let_stmt.initializer()?;
+    if refers_to_locals(&ctx.sema, initializer.syntax()) {
+        return None;
+    }
+    let ty = match let_stmt.ascribed_type() {
+        Some(it) => it.syntax().to_string(),
+        None => render_type_of_expr(&ctx.sema, &initializer)?,
+    };
+
+    let method = let_stmt.syntax().ancestors().find_map(ast::FnDef::cast)?;
+    let self_param = method.param_list()?.self_param()?;
+    if bind_pat.mut_token().is_some() && self_param.kind() != SelfParamKind::MutRef {
+        return None;
+    }
+    let impl_def = method.syntax().parent()?.parent().and_then(ast::ImplDef::cast)?;
+    let strukt = match ctx.sema.to_def(&impl_def)?.target_ty(ctx.db).as_adt()? {
+        Adt::Struct(it) => it,
+        _ => return None,
+    };
+    let (file_id, struct_def) = original_node(ctx.db, strukt.source(ctx.db))?;
+    if file_id != ctx.frange.file_id {
+        return None;
+    }
+    let field_list = match struct_def.kind() {
+        StructKind::Record(it) => it,
+        _ => return None,
+    };
+    if field_list.fields().any(|it| it.name().map_or(false, |it| it.text() == name.as_str())) {
+        return None;
+    }
+    let literals = constructor_literals(&ctx, strukt)?;
+
+    let local = ctx.sema.to_def(&bind_pat)?;
+    // A field can't be moved out of `self`, like the local can.
+    if !local.ty(ctx.db).is_copy(ctx.db) && may_be_moved(&ctx.sema, local) {
+        return None;
+    }
+    let usages = Definition::Local(local).find_usages(ctx.db, None);
+    if usages.iter().any(|it| it.file_range.file_id != ctx.frange.file_id) {
+        return None;
+    }
+
+    let mut edits = vec![append_to_list(field_list.syntax(), &format!("{}: {}", name, ty))?];
+    for record_field_list in literals {
+        let field = format!("{}: {}", name, initializer.syntax());
+        edits.push(append_to_list(record_field_list.syntax(), &field)?);
+    }
+    let delete_range = stmt_range(let_stmt.syntax());
+    let cursor = shifted_offset(delete_range.start(), &edits);
+    edits.push((delete_range, String::new()));
+    for usage in usages {
+        let replacement = match usage.kind {
+            ReferenceKind::StructFieldShorthandForLocal => format!("{}: self.{}", name, name),
+            _ => format!("self.{}", name),
+        };
+        edits.push((usage.file_range.range, replacement));
+    }
+
+    ctx.add_assist(AssistId("promote_local_to_field"), "Promote local to field", |edit| {
+        edit.target(let_stmt.syntax().text_range());
+        for (range, text) in edits {
+            edit.replace(range, text);
+        }
+        edit.set_cursor(cursor);
+    })
+}
+
+// Assist: demote_field_to_local
+//
+// Turns a field which is only used in one method into a local variable of
+// that method.
+//
+// ```
+// struct Counter { count: u32, <|>step: u32 }
+// impl Counter {
+//     fn new() -> Counter { Counter { count: 0, step: 2 } }
+//     fn tick(&mut self) {
+//         self.count += self.step;
+//     }
+// }
+// ```
+// ->
+// ```
+// struct Counter { count: u32 }
+// impl Counter {
+//     fn new() -> Counter { Counter { count: 0 } }
+//     fn tick(&mut self) {
+//         let step: u32 = 2;
+//         self.count += step;
+//     }
+// }
+// ```
+pub(crate) fn demote_field_to_local(ctx: AssistCtx) -> Option<Assist> {
+    let field = ctx.find_node_at_offset::<ast::RecordFieldDef>()?;
+    let name = field.name()?;
+    if !name.syntax().text_range().contains_inclusive(ctx.frange.range.start()) {
+        return None;
+    }
+    let ty = field.ascribed_type()?;
+    let def = classify_name(&ctx.sema, &name)?.into_definition()?;
+    let source_file = ctx.sema.parse(ctx.frange.file_id);
+
+    let mut method = None;
+    let mut field_exprs = Vec::new();
+    let mut is_written = false;
+    let mut initializer = None;
+    for reference in def.find_usages(ctx.db, None) {
+        if reference.file_range.file_id != ctx.frange.file_id {
+            return None;
+        }
+        let name_ref = find_node_at_offset::<ast::NameRef>(
+            source_file.syntax(),
+            reference.file_range.range.start(),
+        )?;
+        let parent = name_ref.syntax().parent()?;
+        if let Some(field_expr) = ast::FieldExpr::cast(parent.clone()) {
+            match field_expr.expr()? {
+                ast::Expr::PathExpr(it) if it.syntax().text() == "self" => (),
+                _ => return None,
+            }
+            let fn_def = field_expr.syntax().ancestors().find_map(ast::FnDef::cast)?;
+            match &method {
+                Some(it) if *it != fn_def => return None,
+                _ => method = Some(fn_def),
+            }
+            is_written |= reference.access == Some(ReferenceAccess::Write);
+            field_exprs.push(field_expr);
+        } else if let Some(record_field) = ast::RecordField::cast(parent) {
+            // The value of the field in the single struct literal becomes the
+            // initializer of the local.
+            let expr = record_field.expr()?;
+            if initializer.is_some() || refers_to_locals(&ctx.sema, expr.syntax()) {
+                return None;
+            }
+            initializer = Some((record_field, expr));
+        } else {
+            return None;
+        }
+    }
+    let method = method?;
+    // The local would clash with a binding of the same name, or shadow an item
+    // the method refers to.
+    if mentions_name(method.syntax(), name.text()) {
+        return None;
+    }
+    let (record_field, initializer) = initializer?;
+    let block = method.body()?.block()?;
+    let anchor = match block.statements().next() {
+        Some(it) => it.syntax().clone(),
+        None => block.expr()?.syntax().clone(),
+    };
+
+    let mut edits = vec![
+        (list_item_range(field.syntax()), String::new()),
+        (list_item_range(record_field.syntax()), String::new()),
+    ];
+    let offset = anchor.text_range().start();
+    let cursor = shifted_offset(offset, &edits);
+    let mutability = if is_written { "mut " } else { "" };
+    let indent = indent_text(IndentLevel::from_node(&anchor));
+    let let_stmt = format!(
+        "let {}{}: {} = {};\n{}",
+        mutability,
+        name.text(),
+        ty.syntax(),
+        initializer.syntax(),
+        indent
+    );
+    edits.push((TextRange::offset_len(offset, 0.into()), let_stmt));
+    for field_expr in field_exprs {
+        edits.push((field_expr.syntax().text_range(), name.text().to_string()));
+    }
+
+    ctx.add_assist(AssistId("demote_field_to_local"), "Demote field to local", |edit| {
+        edit.target(field.syntax().text_range());
+        for (range, text) in edits {
+            edit.replace(range, text);
+        }
+        edit.set_cursor(cursor);
+    })
+}
+
+/// Whether any path in `node` refers to a local variable, including `self`.
+fn refers_to_locals(sema: &Semantics<RootDatabase>, node: &SyntaxNode) -> bool {
+    node.descendants()
+        .filter_map(ast::Path::cast)
+        .any(|it| matches!(sema.resolve_path(&it), Some(PathResolution::Local(_))))
+}
+
+/// Whether `node` binds `name`, or refers to something by a path `name`.
+fn mentions_name(node: &SyntaxNode, name: &str) -> bool {
+    node.descendants().any(|node| match_ast! {
+        match node {
+            ast::BindPat(it) => it.name().map_or(false, |it| it.text() == name),
+            ast::Path(it) => {
+                it.qualifier().is_none()
+                    && it.segment().and_then(|it| it.name_ref()).map_or(false, |it| it.text() == name)
+            },
+            _ => false,
+        }
+    })
+}
+
+/// Finds the field lists of the struct literals creating `strukt`. Returns
+/// `None` unless all the struct literals without a `..base` are in a `new`
+/// function, so that the new field can be initialized in all of them.
+fn constructor_literals(ctx: &AssistCtx, strukt: hir::Struct) -> Option<Vec<ast::RecordFieldList>> {
+    let source_file = ctx.sema.parse(ctx.frange.file_id);
+    let mut res = Vec::new();
+    for literal in source_file.syntax().descendants().filter_map(ast::RecordLit::cast) {
+        let creates_struct = match ctx.sema.resolve_path(&literal.path()?) {
+            Some(PathResolution::Def(hir::ModuleDef::Adt(Adt::Struct(it)))) => it == strukt,
+            Some(PathResolution::SelfType(it)) => {
+                it.target_ty(ctx.db).as_adt() == Some(Adt::Struct(strukt))
+            }
+            _ => false,
+        };
+        if !creates_struct {
+            continue;
+        }
+        let field_list = literal.record_field_list()?;
+        if field_list.spread().is_some() {
+            continue;
+        }
+        let in_new = literal
+            .syntax()
+            .ancestors()
+            .find_map(ast::FnDef::cast)
+            .and_then(|it| it.name())
+            .map_or(false, |it| it.text() == "new");
+        if !in_new {
+            return None;
+        }
+        res.push(field_list);
+    }
+    // Struct literals in other files can't be updated.
+    let def = Definition::ModuleDef(hir::ModuleDef::Adt(Adt::Struct(strukt)));
+    if def.find_usages(ctx.db, None).iter().any(|it| it.file_range.file_id != ctx.frange.file_id) {
+        return None;
+    }
+    if res.is_empty() {
+        None
+    } else {
+        Some(res)
+    }
+}
+
+/// Appends `item` to a comma separated list in braces, like the fields of a
+/// struct, keeping the list on one line or one item per line.
+fn append_to_list(list: &SyntaxNode, item: &str) -> Option<(TextRange, String)> {
+    let r_curly = list.last_token().filter(|it| it.kind() == T!['}'])?;
+    let last = match non_trivia_sibling(r_curly.into(), Direction::Prev) {
+        Some(it) if it.kind() != T!['{'] => it,
+        _ => return Some((list.text_range(), format!("{{ {} }}", item))),
+    };
+    let has_comma = last.kind() == T![,];
+    let text = if list.text().contains_char('\n') {
+        let indent = indent_text(IndentLevel(IndentLevel::from_node(list).0 + 1));
+        let comma = if has_comma { "" } else { "," };
+        format!("{}\n{}{},", comma, indent, item)
+    } else if has_comma {
+        format!(" {},", item)
+    } else {
+        format!(", {}", item)
+    };
+    Some((TextRange::offset_len(last.text_range().end(), 0.into()), text))
+}
+
+/// Where `offset` ends up after the `edits` in front of it are applied.
+fn shifted_offset(offset: TextUnit, edits: &[(TextRange, String)]) -> TextUnit {
+    edits
+        .iter()
+        .filter(|(range, _)| range.end() <= offset)
+        .fold(offset, |acc, (range, text)| acc + TextUnit::of_str(text) - range.len())
+}
+
+/// The range of a statement, together with the whitespace after it.
+fn stmt_range(stmt: &SyntaxNode) -> TextRange {
+    let range = stmt.text_range();
+    match stmt.next_sibling_or_token().filter(|it| it.kind() == SyntaxKind::WHITESPACE) {
+        Some(ws) => TextRange::from_to(range.start(), ws.text_range().end()),
+        None => range,
+    }
+}
+
+/// The range of a member of a comma separated list in braces, together with
+/// the comma which separates it from the next member, or from the previous
+/// one if it is the last. A trailing comma stays in place.
+fn list_item_range(node: &SyntaxNode) -> TextRange {
+    let range = node.text_range();
+    let comma_after =
+        non_trivia_sibling(node.clone().into(), Direction::Next).filter(|it| it.kind() == T![,]);
+    let next = comma_after
+        .clone()
+        .and_then(|it| non_trivia_sibling(it, Direction::Next))
+        .filter(|it| it.kind() != T!['}']);
+    if let Some(next) = next {
+        return TextRange::from_to(range.start(), next.text_range().start());
+    }
+    let comma_before =
+        non_trivia_sibling(node.clone().into(), Direction::Prev).filter(|it| it.kind() == T![,]);
+    match (comma_before, comma_after) {
+        (Some(before), Some(after)) => {
+            TextRange::from_to(before.text_range().end(), after.text_range().end())
+        }
+        (Some(before), None) => TextRange::from_to(before.text_range().start(), range.end()),
+        (None, after) => {
+            TextRange::from_to(range.start(), after.map_or(range.end(), |it| it.text_range().end()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn promotes_local_and_rewrites_usages() {
+        check_assist(
+            promote_local_to_field,
+            r#"
+struct S {
+    a: u32,
+}
+
+impl S {
+    fn new() -> Self {
+        Self { a: 0 }
+    }
+
+    fn f(&mut self) -> u32 {
+        let mut <|>x: u64 = 92;
+        x += 1;
+        self.a + x as u32
+    }
+}
+"#,
+            r#"
+struct S {
+    a: u32,
+    x: u64,
+}
+
+impl S {
+    fn new() -> Self {
+        Self { a: 0, x: 92 }
+    }
+
+    fn f(&mut self) -> u32 {
+        <|>self.x += 1;
+        self.a + self.x as u32
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn promote_not_applicable_if_initializer_uses_locals() {
+        check_assist_not_applicable(
+            promote_local_to_field,
+            r#"
+struct S { a: u32 }
+impl S {
+    fn new() -> S { S { a: 0 } }
+    fn f(&self) -> u32 {
+        let <|>x = self.a;
+        x
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn promote_not_applicable_without_constructor() {
+        check_assist_not_applicable(
+            promote_local_to_field,
+            r#"
+struct S { a: u32 }
+impl S {
+    fn f(&self) -> u32 {
+        let <|>x = 1;
+        x
+    }
+}
+fn make() -> S { S { a: 0 } }
+"#,
+        );
+    }
+
+    #[test]
+    fn promote_not_applicable_if_local_is_moved() {
+        check_assist_not_applicable(
+            promote_local_to_field,
+            r#"
+#[lang = "copy"]
+trait Copy {}
+struct Buf;
+fn consume(buf: Buf) {}
+struct S { a: u32 }
+impl S {
+    fn new() -> S { S { a: 0 } }
+    fn f(&self) {
+        let <|>v = Buf;
+        consume(v);
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn promote_mutable_local_needs_mutable_self() {
+        check_assist_not_applicable(
+            promote_local_to_field,
+            r#"
+struct S { a: u32 }
+impl S {
+    fn new() -> S { S { a: 0 } }
+    fn f(&self) {
+        let mut <|>x = 1;
+        x += 1;
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn demotes_written_field() {
+        check_assist(
+            demote_field_to_local,
+            r#"
+struct S {
+    a: u32,
+    <|>b: u32,
+}
+
+impl S {
+    fn new() -> S {
+        S { a: 0, b: 1 }
+    }
+
+    fn f(&mut self) -> u32 {
+        self.b = 2;
+        self.a + self.b
+    }
+}
+"#,
+            r#"
+struct S {
+    a: u32,
+}
+
+impl S {
+    fn new() -> S {
+        S { a: 0 }
+    }
+
+    fn f(&mut self) -> u32 {
+        <|>let mut b: u32 = 1;
+        b = 2;
+        self.a + b
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn demote_not_applicable_if_name_is_taken() {
+        check_assist_not_applicable(
+            demote_field_to_local,
+            r#"
+struct S { a: u32, <|>b: u32 }
+impl S {
+    fn new() -> S { S { a: 0, b: 1 } }
+    fn set(&mut self, b: u32) { self.b = b; }
+}
+"#,
+        );
+        check_assist_not_applicable(
+            demote_field_to_local,
+            r#"
+struct S { a: u32, <|>b: u32 }
+impl S {
+    fn new() -> S { S { a: 0, b: 1 } }
+    fn f(&self) -> u32 {
+        let b = 2;
+        self.b + b
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn demote_not_applicable_if_used_in_two_methods() {
+        check_assist_not_applicable(
+            demote_field_to_local,
+            r#"
+struct S { <|>a: u32 }
+impl S {
+    fn new() -> S { S { a: 0 } }
+    fn f(&self) -> u32 { self.a }
+    fn g(&self) -> u32 { self.a }
+}
+"#,
+        );
+    }
+}
@@ -1,9 +1,9 @@
 use hir;
+use ra_ide_db::insert_use::insert_use_statement;
 use ra_syntax::{ast, AstNode, SmolStr, TextRange};
 
 use crate::{
     assist_ctx::{Assist, AssistCtx},
-    utils::insert_use_statement,
     AssistId,
 };
 
@@ -33,12 +33,18 @@ pub(crate) fn replace_qualified_name_with_use(ctx: AssistCtx) -> Option<Assist>
         return None;
     }
 
+    let config = ctx.config;
     ctx.add_assist(
         AssistId("replace_qualified_name_with_use"),
         "Replace qualified path with use",
         |edit| {
             let path_to_import = hir_path.mod_path().clone();
-            insert_use_statement(path.syntax(), &path_to_import, edit.text_edit_builder());
+            insert_use_statement(
+                path.syntax(),
+                &path_to_import,
+                &config.insert_use,
+                edit.text_edit_builder(),
+            );
 
             if let Some(last) = path.segment() {
                 // Here we are assuming the assist will provide a correct use statement
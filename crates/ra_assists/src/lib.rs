@@ -17,14 +17,20 @@ mod doc_tests;
 pub mod utils;
 pub mod ast_transform;
 
-use ra_db::FileRange;
-use ra_ide_db::RootDatabase;
+use ra_db::{FileId, FileRange};
+use ra_ide_db::{insert_use::InsertUseConfig, RootDatabase};
 use ra_syntax::{TextRange, TextUnit};
 use ra_text_edit::TextEdit;
 
 pub(crate) use crate::assist_ctx::{Assist, AssistCtx, AssistHandler};
 use hir::Semantics;
 
+/// Configures how the assists edit the code.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AssistConfig {
+    pub insert_use: InsertUseConfig,
+}
+
 /// Unique identifier of the assist, should not be shown to the user
 /// directly.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -52,8 +58,28 @@ impl AssistLabel {
 pub struct AssistAction {
     pub edit: TextEdit,
     pub cursor_position: Option<TextUnit>,
+    /// The inserted text uses the LSP snippet syntax, where `$0` or
+    /// `${0:placeholder}` marks where the cursor should end up.
+    pub is_snippet: bool,
     // FIXME: This belongs to `AssistLabel`
     pub target: Option<TextRange>,
+    pub file: AssistFile,
+    /// The edits of other files than `file`, like the usages of a changed
+    /// item in other modules.
+    pub other_edits: Vec<(FileId, TextEdit)>,
+}
+
+/// The file edited by an `AssistAction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssistFile {
+    CurrentFile,
+    TargetFile(FileId),
+}
+
+impl Default for AssistFile {
+    fn default() -> Self {
+        AssistFile::CurrentFile
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -67,9 +93,13 @@ pub struct ResolvedAssist {
 ///
 /// Assists are returned in the "unresolved" state, that is only labels are
 /// returned, without actual edits.
-pub fn unresolved_assists(db: &RootDatabase, range: FileRange) -> Vec<AssistLabel> {
+pub fn unresolved_assists(
+    db: &RootDatabase,
+    range: FileRange,
+    config: &AssistConfig,
+) -> Vec<AssistLabel> {
     let sema = Semantics::new(db);
-    let ctx = AssistCtx::new(&sema, range, false);
+    let ctx = AssistCtx::new(&sema, range, config, false);
     handlers::all()
         .iter()
         .filter_map(|f| f(ctx.clone()))
@@ -82,9 +112,13 @@ pub fn unresolved_assists(db: &RootDatabase, range: FileRange) -> Vec<AssistLabe
 ///
 /// Assists are returned in the "resolved" state, that is with edit fully
 /// computed.
-pub fn resolved_assists(db: &RootDatabase, range: FileRange) -> Vec<ResolvedAssist> {
+pub fn resolved_assists(
+    db: &RootDatabase,
+    range: FileRange,
+    config: &AssistConfig,
+) -> Vec<ResolvedAssist> {
     let sema = Semantics::new(db);
-    let ctx = AssistCtx::new(&sema, range, true);
+    let ctx = AssistCtx::new(&sema, range, config, true);
     let mut a = handlers::all()
         .iter()
         .filter_map(|f| f(ctx.clone()))
@@ -105,17 +139,22 @@ mod handlers {
     mod add_impl;
     mod add_missing_impl_members;
     mod add_new;
+    mod add_type;
     mod apply_demorgan;
     mod auto_import;
     mod change_visibility;
     mod early_return;
+    mod extract_module;
+    mod extract_struct_from_enum_variant;
     mod fill_match_arms;
     mod flip_binexpr;
     mod flip_comma;
     mod flip_trait_bound;
     mod inline_local_variable;
+    mod introduce_named_lifetime;
     mod introduce_variable;
     mod invert_if;
+    mod local_to_field;
     mod merge_imports;
     mod merge_match_arms;
     mod move_bounds;
@@ -138,19 +177,28 @@ mod handlers {
             add_derive::add_derive,
             add_explicit_type::add_explicit_type,
             add_function::add_function,
+            add_function::add_method,
             add_impl::add_impl,
             add_new::add_new,
+            add_type::add_enum_variant,
+            add_type::add_struct,
+            add_type::add_type_alias,
             apply_demorgan::apply_demorgan,
             auto_import::auto_import,
             change_visibility::change_visibility,
             early_return::convert_to_guarded_return,
+            extract_module::extract_module,
+            extract_struct_from_enum_variant::extract_struct_from_enum_variant,
             fill_match_arms::fill_match_arms,
             flip_binexpr::flip_binexpr,
             flip_comma::flip_comma,
             flip_trait_bound::flip_trait_bound,
             inline_local_variable::inline_local_variable,
+            introduce_named_lifetime::introduce_named_lifetime,
             introduce_variable::introduce_variable,
             invert_if::invert_if,
+            local_to_field::demote_field_to_local,
+            local_to_field::promote_local_to_field,
             merge_imports::merge_imports,
             merge_match_arms::merge_match_arms,
             move_bounds::move_bounds_to_where_clause,
@@ -182,9 +230,10 @@ mod helpers {
 
     use ra_db::{fixture::WithFixture, FileId, FileRange, SourceDatabaseExt};
     use ra_ide_db::{symbol_index::SymbolsDatabase, RootDatabase};
+    use stdx::format_to;
     use test_utils::{add_cursor, assert_eq_text, extract_range_or_offset, RangeOrOffset};
 
-    use crate::{AssistCtx, AssistHandler};
+    use crate::{AssistConfig, AssistCtx, AssistFile, AssistHandler};
     use hir::Semantics;
 
     pub(crate) fn with_single_file(text: &str) -> (RootDatabase, FileId) {
@@ -240,14 +289,22 @@ mod helpers {
         let frange = FileRange { file_id: file_with_caret_id, range: range_or_offset.into() };
 
         let sema = Semantics::new(&db);
-        let assist_ctx = AssistCtx::new(&sema, frange, true);
+        let config = AssistConfig::default();
+        let assist_ctx = AssistCtx::new(&sema, frange, &config, true);
 
         match (assist(assist_ctx), expected) {
             (Some(assist), ExpectedResult::After(after)) => {
                 let action = assist.0[0].action.clone().unwrap();
 
-                let mut actual = action.edit.apply(&text_without_caret);
+                let assist_file_text = match action.file {
+                    AssistFile::CurrentFile => text_without_caret.clone(),
+                    AssistFile::TargetFile(file_id) => db.file_text(file_id).as_ref().to_owned(),
+                };
+                let mut actual = action.edit.apply(&assist_file_text);
                 match action.cursor_position {
+                    // Snippets mark the cursor themselves, and the cursor
+                    // isn't in other files.
+                    None if action.is_snippet || action.file != AssistFile::CurrentFile => (),
                     None => {
                         if let RangeOrOffset::Offset(before_cursor_pos) = range_or_offset {
                             let off = action
@@ -259,6 +316,11 @@ mod helpers {
                     }
                     Some(off) => actual = add_cursor(&actual, off),
                 };
+                // The other edited files follow, in the fixture format.
+                for (file_id, edit) in action.other_edits.iter() {
+                    let text = edit.apply(&db.file_text(*file_id));
+                    format_to!(actual, "//- /{}\n{}", db.file_relative_path(*file_id), text);
+                }
 
                 assert_eq_text!(after, &actual);
             }
@@ -282,7 +344,7 @@ mod tests {
     use ra_syntax::TextRange;
     use test_utils::{extract_offset, extract_range};
 
-    use crate::{helpers, resolved_assists};
+    use crate::{helpers, resolved_assists, AssistConfig};
 
     #[test]
     fn assist_order_field_struct() {
@@ -291,7 +353,7 @@ mod tests {
         let (db, file_id) = helpers::with_single_file(&before);
         let frange =
             FileRange { file_id, range: TextRange::offset_len(before_cursor_pos, 0.into()) };
-        let assists = resolved_assists(&db, frange);
+        let assists = resolved_assists(&db, frange, &AssistConfig::default());
         let mut assists = assists.iter();
 
         assert_eq!(
@@ -314,7 +376,7 @@ mod tests {
         let (range, before) = extract_range(before);
         let (db, file_id) = helpers::with_single_file(&before);
         let frange = FileRange { file_id, range };
-        let assists = resolved_assists(&db, frange);
+        let assists = resolved_assists(&db, frange, &AssistConfig::default());
         let mut assists = assists.iter();
 
         assert_eq!(assists.next().expect("expected assist").label.label, "Extract into variable");
@@ -1,16 +1,21 @@
 //! Assorted functions shared by several assists.
-pub(crate) mod insert_use;
 
-use hir::Semantics;
+use std::iter::successors;
+
+use hir::{HirDisplay, InFile, Semantics};
+use ra_db::FileId;
 use ra_ide_db::RootDatabase;
 use ra_syntax::{
-    ast::{self, make, NameOwner},
-    AstNode, T,
+    algo::skip_trivia_token,
+    ast::{
+        self,
+        edit::{AstNodeEdit, IndentLevel},
+        make, NameOwner,
+    },
+    AstNode, Direction, InsertPosition, SyntaxElement, SyntaxKind, SyntaxNode, TextUnit, T,
 };
 use rustc_hash::FxHashSet;
 
-pub use insert_use::insert_use_statement;
-
 pub fn get_missing_impl_items(
     sema: &Semantics<RootDatabase>,
     impl_def: &ast::ImplDef,
@@ -79,6 +84,66 @@ pub(crate) fn resolve_target_trait(
     }
 }
 
+/// Renders the inferred type of `expr` for use in generated code.
+pub(crate) fn render_type_of_expr(
+    sema: &Semantics<RootDatabase>,
+    expr: &ast::Expr,
+) -> Option<String> {
+    let ty = sema.type_of_expr(expr)?;
+    if ty.is_unknown() {
+        return None;
+    }
+    Some(ty.display(sema.db).to_string())
+}
+
+/// Returns the node of `src` together with its file, unless it was expanded
+/// from a macro, where assists can't insert anything.
+pub(crate) fn original_node<N>(db: &RootDatabase, src: InFile<N>) -> Option<(FileId, N)> {
+    let file_id = src.file_id.original_file(db);
+    if src.file_id != file_id.into() {
+        return None;
+    }
+    Some((file_id, src.value))
+}
+
+/// Returns the item containing `node` which is directly inside the current
+/// mod or file, so that generated items can be inserted behind it.
+/// Items generated for code in fns, impls or macro calls go behind those,
+/// but stay inside of mods.
+pub(crate) fn next_space_for_item(node: &SyntaxNode) -> Option<SyntaxNode> {
+    let mut ancestors = node.ancestors().peekable();
+    let mut last_ancestor: Option<SyntaxNode> = None;
+    while let Some(next_ancestor) = ancestors.next() {
+        match next_ancestor.kind() {
+            SyntaxKind::SOURCE_FILE => {
+                break;
+            }
+            SyntaxKind::ITEM_LIST => {
+                if ancestors.peek().map(|a| a.kind()) == Some(SyntaxKind::MODULE) {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        last_ancestor = Some(next_ancestor);
+    }
+    last_ancestor
+}
+
+/// The whitespace at the start of a line with the given indentation.
+pub(crate) fn indent_text(indent: IndentLevel) -> String {
+    " ".repeat(indent.0 as usize * 4)
+}
+
+/// The offset to insert a visibility at, after the attributes and comments.
+pub(crate) fn visibility_offset(node: &SyntaxNode) -> TextUnit {
+    node.children_with_tokens()
+        .find(|it| {
+            !matches!(it.kind(), SyntaxKind::ATTR | SyntaxKind::COMMENT | SyntaxKind::WHITESPACE)
+        })
+        .map_or(node.text_range().start(), |it| it.text_range().start())
+}
+
 pub(crate) fn invert_boolean_expression(expr: ast::Expr) -> ast::Expr {
     if let Some(expr) = invert_special_case(&expr) {
         return expr;
@@ -99,3 +164,80 @@ fn invert_special_case(expr: &ast::Expr) -> Option<ast::Expr> {
         _ => None,
     }
 }
+
+/// Merges two use trees with a common prefix, like `std::fmt::Debug` and
+/// `std::io`, into `std::{fmt::Debug, io}`.
+pub fn try_merge_trees(old: &ast::UseTree, new: &ast::UseTree) -> Option<ast::UseTree> {
+    let lhs_path = old.path()?;
+    let rhs_path = new.path()?;
+
+    let (lhs_prefix, rhs_prefix) = common_prefix(&lhs_path, &rhs_path)?;
+
+    let lhs = old.split_prefix(&lhs_prefix);
+    let rhs = new.split_prefix(&rhs_prefix);
+
+    let should_insert_comma = lhs
+        .use_tree_list()?
+        .r_curly_token()
+        .and_then(|it| skip_trivia_token(it.prev_token()?, Direction::Prev))
+        .map(|it| it.kind() != T![,])
+        .unwrap_or(true);
+
+    let mut to_insert: Vec<SyntaxElement> = Vec::new();
+    if should_insert_comma {
+        to_insert.push(make::token(T![,]).into());
+        to_insert.push(make::tokens::single_space().into());
+    }
+    to_insert.extend(
+        rhs.use_tree_list()?
+            .syntax()
+            .children_with_tokens()
+            .filter(|it| it.kind() != T!['{'] && it.kind() != T!['}']),
+    );
+    let use_tree_list = lhs.use_tree_list()?;
+    let pos = InsertPosition::Before(use_tree_list.r_curly_token()?.into());
+    let use_tree_list = use_tree_list.insert_children(pos, to_insert);
+    Some(lhs.with_use_tree_list(use_tree_list))
+}
+
+fn common_prefix(lhs: &ast::Path, rhs: &ast::Path) -> Option<(ast::Path, ast::Path)> {
+    let mut res = None;
+    let mut lhs_curr = first_path(&lhs);
+    let mut rhs_curr = first_path(&rhs);
+    loop {
+        match (lhs_curr.segment(), rhs_curr.segment()) {
+            (Some(lhs), Some(rhs)) if lhs.syntax().text() == rhs.syntax().text() => (),
+            _ => break,
+        }
+        res = Some((lhs_curr.clone(), rhs_curr.clone()));
+
+        match (lhs_curr.parent_path(), rhs_curr.parent_path()) {
+            (Some(lhs), Some(rhs)) => {
+                lhs_curr = lhs;
+                rhs_curr = rhs;
+            }
+            _ => break,
+        }
+    }
+
+    res
+}
+
+fn first_path(path: &ast::Path) -> ast::Path {
+    successors(Some(path.clone()), |it| it.qualifier()).last().unwrap()
+}
+
+/// Escapes the characters which have a special meaning in LSP snippets.
+///
+/// `}` is only special inside of placeholders, so this is not suitable for
+/// the text of a placeholder.
+pub(crate) fn escape_snippet(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\\' || c == '$' {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
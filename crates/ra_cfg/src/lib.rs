@@ -49,6 +49,12 @@ impl CfgOptions {
         self.key_values.insert((key, value));
     }
 
+    /// Enables all the options which are enabled in `other`.
+    pub fn union(&mut self, other: &CfgOptions) {
+        self.atoms.extend(other.atoms.iter().cloned());
+        self.key_values.extend(other.key_values.iter().cloned());
+    }
+
     /// Shortcut to set features
     pub fn insert_features(&mut self, iter: impl IntoIterator<Item = SmolStr>) {
         iter.into_iter().for_each(|feat| self.insert_key_value("feature".into(), feat));
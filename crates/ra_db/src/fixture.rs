@@ -173,10 +173,13 @@ fn with_files(db: &mut dyn SourceDatabaseExt, fixture: &str) -> Option<FilePosit
             Default::default(),
         );
     } else {
-        for (from, to) in crate_deps {
+        for (from, dep) in crate_deps {
+            // A dependency can be renamed with `alias=krate`, like
+            // `alias = { package = "krate" }` in `Cargo.toml`.
+            let (name, to) = split1(&dep, '=').unwrap_or((dep.as_str(), dep.as_str()));
             let from_id = crates[&from];
-            let to_id = crates[&to];
-            crate_graph.add_dep(from_id, CrateName::new(&to).unwrap(), to_id).unwrap();
+            let to_id = crates[to];
+            crate_graph.add_dep(from_id, CrateName::new(name).unwrap(), to_id).unwrap();
         }
     }
 
@@ -200,7 +203,7 @@ struct FileMeta {
     env: Env,
 }
 
-//- /lib.rs crate:foo deps:bar,baz cfg:foo=a,bar=b env:OUTDIR=path/to,OTHER=foo)
+//- /lib.rs crate:foo deps:bar,qux=baz cfg:foo=a,bar=b env:OUTDIR=path/to,OTHER=foo)
 fn parse_meta(meta: &str) -> ParsedMeta {
     let components = meta.split_ascii_whitespace().collect::<Vec<_>>();
 
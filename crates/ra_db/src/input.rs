@@ -146,10 +146,15 @@ pub struct CrateData {
     pub extern_source: ExternSource,
     pub dependencies: Vec<Dependency>,
     pub proc_macro: Vec<ProcMacro>,
+    /// Whether this is the test crate of a target, which is compiled with
+    /// `cfg(test)` and the dev-dependencies. A lib or bin has another crate
+    /// with the same root for its normal configuration.
+    pub is_test: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Edition {
+    Edition2021,
     Edition2018,
     Edition2015,
 }
@@ -199,6 +204,7 @@ impl CrateGraph {
             extern_source,
             proc_macro,
             dependencies: Vec::new(),
+            is_test: false,
         };
         let crate_id = CrateId(self.arena.len() as u32);
         let prev = self.arena.insert(crate_id, data);
@@ -219,6 +225,11 @@ impl CrateGraph {
         Ok(())
     }
 
+    /// Marks the crate as a test crate.
+    pub fn set_test(&mut self, crate_id: CrateId) {
+        self.arena.get_mut(&crate_id).unwrap().is_test = true;
+    }
+
     pub fn is_empty(&self) -> bool {
         self.arena.is_empty()
     }
@@ -227,28 +238,140 @@ impl CrateGraph {
         self.arena.keys().copied()
     }
 
-    // FIXME: this only finds one crate with the given root; we could have multiple
-    pub fn crate_id_for_crate_root(&self, file_id: FileId) -> Option<CrateId> {
-        let (&crate_id, _) =
-            self.arena.iter().find(|(_crate_id, data)| data.root_file_id == file_id)?;
-        Some(crate_id)
+    /// Returns all crates which transitively depend on `of`, including `of`
+    /// itself.
+    pub fn transitive_reverse_dependencies(&self, of: CrateId) -> FxHashSet<CrateId> {
+        let mut rev_deps: FxHashMap<CrateId, Vec<CrateId>> = FxHashMap::default();
+        for (&krate, data) in self.arena.iter() {
+            for dep in data.dependencies.iter() {
+                rev_deps.entry(dep.crate_id).or_default().push(krate);
+            }
+        }
+
+        let mut res = FxHashSet::default();
+        let mut worklist = vec![of];
+        while let Some(krate) = worklist.pop() {
+            if !res.insert(krate) {
+                continue;
+            }
+            worklist.extend(rev_deps.get(&krate).into_iter().flatten().copied());
+        }
+        res
+    }
+
+    /// Returns the crates with the given root, the normal ones before the test
+    /// crates, so that the analysis of a file prefers the crate which the
+    /// dependents of a lib see.
+    pub fn crate_ids_for_crate_root(&self, file_id: FileId) -> Vec<CrateId> {
+        let mut res: Vec<CrateId> = self
+            .arena
+            .iter()
+            .filter(|(_crate_id, data)| data.root_file_id == file_id)
+            .map(|(&crate_id, _)| crate_id)
+            .collect();
+        res.sort_by_key(|&it| (self[it].is_test, it));
+        res
+    }
+
+    /// Returns the test crate with the same root as `of`, if there is one.
+    pub fn test_crate_of(&self, of: CrateId) -> Option<CrateId> {
+        let root = self[of].root_file_id;
+        self.crate_ids_for_crate_root(root).into_iter().find(|&it| self[it].is_test)
     }
 
-    /// Extends this crate graph by adding a complete disjoint second crate
-    /// graph.
+    /// Extends this crate graph by adding the crates of the `other` one.
     ///
-    /// The ids of the crates in the `other` graph are shifted by the return
-    /// amount.
-    pub fn extend(&mut self, other: CrateGraph) -> u32 {
-        let start = self.arena.len() as u32;
-        self.arena.extend(other.arena.into_iter().map(|(id, mut data)| {
-            let new_id = id.shift(start);
+    /// The crates which are already present, like the dependencies shared by
+    /// two workspaces, are not added again. A crate with the same root as an
+    /// existing one is merged into it instead: a path dependency which is a
+    /// member of one workspace is compiled with other cfgs and dependencies
+    /// than in the workspace depending on it, but two copies of it would make
+    /// the analysis of its files depend on which copy is picked. Returns the
+    /// new ids of the crates of the `other` graph.
+    pub fn extend(&mut self, mut other: CrateGraph) -> FxHashMap<CrateId, CrateId> {
+        let mut by_root: FxHashMap<FileId, Vec<CrateId>> = FxHashMap::default();
+        for (&id, data) in self.arena.iter() {
+            by_root.entry(data.root_file_id).or_default().push(id);
+        }
+
+        // The dependencies must be remapped before a crate is compared with
+        // the existing ones.
+        let mut id_map = FxHashMap::default();
+        for id in other.crates_in_topological_order() {
+            let mut data = other.arena.remove(&id).unwrap();
             for dep in &mut data.dependencies {
-                dep.crate_id = dep.crate_id.shift(start);
+                dep.crate_id = id_map[&dep.crate_id];
             }
-            (new_id, data)
-        }));
-        start
+            let candidates = by_root.entry(data.root_file_id).or_default();
+            let new_id = match candidates.iter().copied().find(|it| self.arena[it] == data) {
+                Some(existing) => existing,
+                None => {
+                    match candidates.iter().copied().find(|it| self.arena[it].is_copy_of(&data)) {
+                        Some(existing) => {
+                            self.merge_crate(existing, data);
+                            existing
+                        }
+                        None => {
+                            let new_id = CrateId(self.arena.len() as u32);
+                            self.arena.insert(new_id, data);
+                            candidates.push(new_id);
+                            new_id
+                        }
+                    }
+                }
+            };
+            id_map.insert(id, new_id);
+        }
+        id_map
+    }
+
+    /// Merges the cfgs, env vars and dependencies of `data` into the crate `id`.
+    fn merge_crate(&mut self, id: CrateId, data: CrateData) {
+        for dep in data.dependencies {
+            let has_dep = self[id].dependencies.iter().any(|it| it.name == dep.name);
+            if has_dep
+                || dep.crate_id == id
+                || self.dfs_find(id, dep.crate_id, &mut FxHashSet::default())
+            {
+                continue;
+            }
+            self.arena.get_mut(&id).unwrap().add_dep(dep.name, dep.crate_id);
+        }
+        let krate = self.arena.get_mut(&id).unwrap();
+        krate.cfg_options.union(&data.cfg_options);
+        krate.env.merge(data.env);
+        krate.extern_source.merge(data.extern_source);
+        if krate.proc_macro.is_empty() {
+            krate.proc_macro = data.proc_macro;
+        }
+    }
+
+    /// Returns the crates ordered so that the dependencies come before their
+    /// dependents.
+    pub fn crates_in_topological_order(&self) -> Vec<CrateId> {
+        let mut res = Vec::new();
+        let mut visited = FxHashSet::default();
+        let mut roots: Vec<CrateId> = self.arena.keys().copied().collect();
+        roots.sort();
+        for krate in roots {
+            go(self, &mut visited, &mut res, krate);
+        }
+        return res;
+
+        fn go(
+            graph: &CrateGraph,
+            visited: &mut FxHashSet<CrateId>,
+            res: &mut Vec<CrateId>,
+            source: CrateId,
+        ) {
+            if !visited.insert(source) {
+                return;
+            }
+            for dep in graph[source].dependencies.iter() {
+                go(graph, visited, res, dep.crate_id)
+            }
+            res.push(source)
+        }
     }
 
     fn dfs_find(&self, target: CrateId, from: CrateId, visited: &mut FxHashSet<CrateId>) -> bool {
@@ -287,6 +410,14 @@ impl CrateData {
     fn add_dep(&mut self, name: SmolStr, crate_id: CrateId) {
         self.dependencies.push(Dependency { name, crate_id })
     }
+
+    /// Whether `other` is the same crate, as seen from another workspace.
+    fn is_copy_of(&self, other: &CrateData) -> bool {
+        self.root_file_id == other.root_file_id
+            && self.edition == other.edition
+            && self.display_name == other.display_name
+            && self.is_test == other.is_test
+    }
 }
 
 impl FromStr for Edition {
@@ -296,6 +427,7 @@ impl FromStr for Edition {
         let res = match s {
             "2015" => Edition::Edition2015,
             "2018" => Edition::Edition2018,
+            "2021" => Edition::Edition2021,
             _ => return Err(ParseEditionError { invalid_input: s.to_string() }),
         };
         Ok(res)
@@ -307,6 +439,7 @@ impl fmt::Display for Edition {
         f.write_str(match self {
             Edition::Edition2015 => "2015",
             Edition::Edition2018 => "2018",
+            Edition::Edition2021 => "2021",
         })
     }
 }
@@ -319,6 +452,13 @@ impl Env {
     pub fn get(&self, env: &str) -> Option<String> {
         self.entries.get(env).cloned()
     }
+
+    /// Adds the variables of `other` which aren't set yet.
+    fn merge(&mut self, other: Env) {
+        for (key, value) in other.entries {
+            self.entries.entry(key).or_insert(value);
+        }
+    }
 }
 
 impl ExternSource {
@@ -337,6 +477,12 @@ impl ExternSource {
     pub fn set_extern_path(&mut self, root_path: &Path, root: ExternSourceId) {
         self.extern_paths.insert(root_path.to_path_buf(), root);
     }
+
+    fn merge(&mut self, other: ExternSource) {
+        for (path, id) in other.extern_paths {
+            self.extern_paths.entry(path).or_insert(id);
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -428,6 +574,34 @@ mod tests {
         assert!(graph.add_dep(crate2, CrateName::new("crate3").unwrap(), crate3).is_ok());
     }
 
+    #[test]
+    fn transitive_reverse_dependencies() {
+        let mut graph = CrateGraph::default();
+        let mut add_crate = |file_id| {
+            graph.add_crate_root(
+                FileId(file_id),
+                Edition2018,
+                None,
+                CfgOptions::default(),
+                Env::default(),
+                Default::default(),
+                Default::default(),
+            )
+        };
+        let crate1 = add_crate(1u32);
+        let crate2 = add_crate(2u32);
+        let crate3 = add_crate(3u32);
+        let crate4 = add_crate(4u32);
+        assert!(graph.add_dep(crate2, CrateName::new("crate1").unwrap(), crate1).is_ok());
+        assert!(graph.add_dep(crate3, CrateName::new("crate2").unwrap(), crate2).is_ok());
+        assert!(graph.add_dep(crate4, CrateName::new("crate3").unwrap(), crate3).is_ok());
+
+        let mut rev_deps =
+            graph.transitive_reverse_dependencies(crate2).into_iter().collect::<Vec<_>>();
+        rev_deps.sort();
+        assert_eq!(rev_deps, vec![crate2, crate3, crate4]);
+    }
+
     #[test]
     fn dashes_are_normalized() {
         let mut graph = CrateGraph::default();
@@ -457,4 +631,105 @@ mod tests {
             vec![Dependency { crate_id: crate2, name: "crate_name_with_dashes".into() }]
         );
     }
+
+    #[test]
+    fn extend_deduplicates_shared_crates() {
+        let add_crate = |graph: &mut CrateGraph, file_id| {
+            graph.add_crate_root(
+                FileId(file_id),
+                Edition2018,
+                None,
+                CfgOptions::default(),
+                Env::default(),
+                Default::default(),
+                Default::default(),
+            )
+        };
+        let mut graph = CrateGraph::default();
+        let shared = add_crate(&mut graph, 1u32);
+        let ws1 = add_crate(&mut graph, 2u32);
+        assert!(graph.add_dep(ws1, CrateName::new("shared").unwrap(), shared).is_ok());
+
+        let mut other = CrateGraph::default();
+        let ws2 = add_crate(&mut other, 3u32);
+        let other_shared = add_crate(&mut other, 1u32);
+        assert!(other.add_dep(ws2, CrateName::new("shared").unwrap(), other_shared).is_ok());
+
+        let id_map = graph.extend(other);
+        assert_eq!(graph.iter().count(), 3);
+        assert_eq!(id_map[&other_shared], shared);
+        assert_eq!(
+            graph[id_map[&ws2]].dependencies,
+            vec![Dependency { crate_id: shared, name: "shared".into() }]
+        );
+    }
+
+    #[test]
+    fn extend_merges_copies_of_path_dependencies() {
+        let add_crate = |graph: &mut CrateGraph, file_id, cfg_options| {
+            graph.add_crate_root(
+                FileId(file_id),
+                Edition2018,
+                Some(CrateName::new(&format!("crate{}", file_id)).unwrap()),
+                cfg_options,
+                Env::default(),
+                Default::default(),
+                Default::default(),
+            )
+        };
+        let mut test_cfg = CfgOptions::default();
+        test_cfg.insert_atom("test".into());
+
+        // `crate1` is a path dependency of `crate2`, ...
+        let mut graph = CrateGraph::default();
+        let path_dep = add_crate(&mut graph, 1u32, CfgOptions::default());
+        let ws1 = add_crate(&mut graph, 2u32, CfgOptions::default());
+        assert!(graph.add_dep(ws1, CrateName::new("crate1").unwrap(), path_dep).is_ok());
+
+        // ... and a member of another workspace, with a dev-dependency.
+        let mut other = CrateGraph::default();
+        let dev_dep = add_crate(&mut other, 3u32, CfgOptions::default());
+        let member = add_crate(&mut other, 1u32, test_cfg.clone());
+        assert!(other.add_dep(member, CrateName::new("crate3").unwrap(), dev_dep).is_ok());
+
+        let id_map = graph.extend(other);
+        assert_eq!(graph.iter().count(), 3);
+        assert_eq!(id_map[&member], path_dep);
+        assert_eq!(graph[path_dep].cfg_options, test_cfg);
+        assert_eq!(
+            graph[path_dep].dependencies,
+            vec![Dependency { crate_id: id_map[&dev_dep], name: "crate3".into() }]
+        );
+    }
+
+    #[test]
+    fn test_crates_are_kept_apart_from_normal_crates() {
+        let add_crate = |graph: &mut CrateGraph| {
+            graph.add_crate_root(
+                FileId(1u32),
+                Edition2018,
+                Some(CrateName::new("crate1").unwrap()),
+                CfgOptions::default(),
+                Env::default(),
+                Default::default(),
+                Default::default(),
+            )
+        };
+
+        let mut other = CrateGraph::default();
+        let test_crate = add_crate(&mut other);
+        other.set_test(test_crate);
+        let normal = add_crate(&mut other);
+
+        let mut graph = CrateGraph::default();
+        let path_dep = add_crate(&mut graph);
+        let id_map = graph.extend(other);
+        assert_eq!(id_map[&normal], path_dep);
+        assert_ne!(id_map[&test_crate], path_dep);
+        assert_eq!(
+            graph.crate_ids_for_crate_root(FileId(1u32)),
+            vec![path_dep, id_map[&test_crate]]
+        );
+        assert_eq!(graph.test_crate_of(path_dep), Some(id_map[&test_crate]));
+    }
 }
@@ -144,7 +144,7 @@ fn source_root_crates(
 ) -> Arc<Vec<CrateId>> {
     let root = db.source_root(id);
     let graph = db.crate_graph();
-    let res = root.walk().filter_map(|it| graph.crate_id_for_crate_root(it)).collect::<Vec<_>>();
+    let res = root.walk().flat_map(|it| graph.crate_ids_for_crate_root(it)).collect::<Vec<_>>();
     Arc::new(res)
 }
 
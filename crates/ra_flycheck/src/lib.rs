@@ -24,8 +24,19 @@ pub use crate::conv::url_from_path_with_drive_lowercasing;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum FlycheckConfig {
-    CargoCommand { command: String, all_targets: bool, extra_args: Vec<String> },
-    CustomCommand { command: String, args: Vec<String> },
+    CargoCommand {
+        command: String,
+        all_targets: bool,
+        all_features: bool,
+        no_default_features: bool,
+        features: Vec<String>,
+        target: Option<String>,
+        extra_args: Vec<String>,
+    },
+    CustomCommand {
+        command: String,
+        args: Vec<String>,
+    },
 }
 
 /// Flycheck wraps the shared state and communication machinery used for
@@ -215,7 +226,15 @@ impl FlycheckThread {
         self.check_process = None;
 
         let mut cmd = match &self.config {
-            FlycheckConfig::CargoCommand { command, all_targets, extra_args } => {
+            FlycheckConfig::CargoCommand {
+                command,
+                all_targets,
+                all_features,
+                no_default_features,
+                features,
+                target,
+                extra_args,
+            } => {
                 let mut cmd = Command::new(cargo_binary());
                 cmd.arg(command);
                 cmd.args(&["--workspace", "--message-format=json", "--manifest-path"]);
@@ -223,6 +242,19 @@ impl FlycheckThread {
                 if *all_targets {
                     cmd.arg("--all-targets");
                 }
+                if *all_features {
+                    cmd.arg("--all-features");
+                } else {
+                    if *no_default_features {
+                        cmd.arg("--no-default-features");
+                    }
+                    if !features.is_empty() {
+                        cmd.arg("--features").arg(features.join(" "));
+                    }
+                }
+                if let Some(target) = target {
+                    cmd.args(&["--target", target]);
+                }
                 cmd.args(extra_args);
                 cmd
             }
@@ -6,15 +6,20 @@ use either::Either;
 use hir_def::{
     adt::StructKind,
     adt::VariantData,
-    builtin_type::BuiltinType,
+    attr::ReprOptions,
+    body::captures::{closure_captures, CaptureMode},
+    builtin_type::{BuiltinInt, BuiltinType},
+    diagnostics::DiscriminantOverflow,
     docs::Documentation,
-    expr::{BindingAnnotation, Pat, PatId},
+    expr::{BindingAnnotation, ExprId, Pat, PatId},
+    path::{path, ModPath},
     per_ns::PerNs,
     resolver::{HasResolver, Resolver},
-    type_ref::{Mutability, TypeRef},
-    AdtId, AssocContainerId, ConstId, DefWithBodyId, EnumId, FunctionId, GenericDefId, HasModule,
-    ImplId, LocalEnumVariantId, LocalModuleId, LocalStructFieldId, Lookup, ModuleId, StaticId,
-    StructId, TraitId, TypeAliasId, TypeParamId, UnionId,
+    src::HasChildSource,
+    type_ref::{Abi, Mutability, TypeRef},
+    AdtId, AssocContainerId, ConstId, DefWithBodyId, EnumId, EnumVariantId, FunctionId,
+    GenericDefId, HasModule, ImplId, LocalEnumVariantId, LocalModuleId, LocalStructFieldId, Lookup,
+    ModuleId, StaticId, StructId, TraitId, TypeAliasId, TypeParamId, UnionId, VariantId,
 };
 use hir_expand::{
     diagnostics::DiagnosticSink,
@@ -22,14 +27,21 @@ use hir_expand::{
     MacroDefId,
 };
 use hir_ty::{
-    autoderef, display::HirFormatter, expr::ExprValidator, method_resolution, ApplicationTy,
-    Canonical, InEnvironment, Substs, TraitEnvironment, Ty, TyDefId, TypeCtor,
+    autoderef,
+    consteval::{self, ConstValue},
+    display::HirFormatter,
+    expr::ExprValidator,
+    method_resolution,
+    pretty::print_typed_body,
+    traits::{Solution, SolutionVariables},
+    ApplicationTy, BoundVar, Canonical, DebruijnIndex, InEnvironment, Substs, TraitEnvironment, Ty,
+    TyDefId, TypeCtor,
 };
 use ra_db::{CrateId, Edition, FileId};
 use ra_prof::profile;
 use ra_syntax::{
     ast::{self, AttrsOwner, NameOwner},
-    AstNode,
+    AstNode, AstPtr,
 };
 use rustc_hash::FxHashSet;
 
@@ -66,7 +78,14 @@ impl Crate {
             .collect()
     }
 
-    // FIXME: add `transitive_reverse_dependencies`.
+    pub fn transitive_reverse_dependencies(self, db: &dyn HirDatabase) -> Vec<Crate> {
+        db.crate_graph()
+            .transitive_reverse_dependencies(self.id)
+            .into_iter()
+            .map(|id| Crate { id })
+            .collect()
+    }
+
     pub fn reverse_dependencies(self, db: &dyn HirDatabase) -> Vec<Crate> {
         let crate_graph = db.crate_graph();
         crate_graph
@@ -91,6 +110,10 @@ impl Crate {
         db.crate_graph()[self.id].edition
     }
 
+    pub fn display_name(self, db: &dyn HirDatabase) -> Option<String> {
+        db.crate_graph()[self.id].display_name.as_ref().map(ToString::to_string)
+    }
+
     pub fn all(db: &dyn HirDatabase) -> Vec<Crate> {
         db.crate_graph().iter().map(|id| Crate { id }).collect()
     }
@@ -141,6 +164,21 @@ impl ModuleDef {
             ModuleDef::BuiltinType(_) => None,
         }
     }
+
+    pub fn attrs(self, db: &dyn HirDatabase) -> Option<Attrs> {
+        let def: AttrDef = match self {
+            ModuleDef::Module(it) => it.into(),
+            ModuleDef::Function(it) => it.into(),
+            ModuleDef::Adt(it) => it.into(),
+            ModuleDef::EnumVariant(it) => it.into(),
+            ModuleDef::Const(it) => it.into(),
+            ModuleDef::Static(it) => it.into(),
+            ModuleDef::Trait(it) => it.into(),
+            ModuleDef::TypeAlias(it) => it.into(),
+            ModuleDef::BuiltinType(_) => return None,
+        };
+        Some(def.attrs(db))
+    }
 }
 
 pub use hir_def::{
@@ -245,6 +283,7 @@ impl Module {
         for decl in self.declarations(db) {
             match decl {
                 crate::ModuleDef::Function(f) => f.diagnostics(db, sink),
+                crate::ModuleDef::Adt(Adt::Enum(e)) => e.diagnostics(db, sink),
                 crate::ModuleDef::Module(m) => {
                     // Only add diagnostics from inline modules
                     if crate_def_map[m.id.local_id].origin.is_inline() {
@@ -433,6 +472,27 @@ impl Enum {
     pub fn ty(self, db: &dyn HirDatabase) -> Type {
         Type::from_def(db, self.id.lookup(db.upcast()).container.module(db.upcast()).krate, self.id)
     }
+
+    pub fn repr(self, db: &dyn HirDatabase) -> Option<ReprOptions> {
+        db.attrs(self.id.into()).repr()
+    }
+
+    pub fn diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
+        let repr = self.repr(db).and_then(|it| it.int).unwrap_or(BuiltinInt::ISIZE);
+        let src = self.id.child_source(db.upcast());
+        for (id, data) in db.enum_data(self.id).variants.iter() {
+            let value = match data.discriminant {
+                Some(it) if !repr.fits(it) => it,
+                _ => continue,
+            };
+            sink.push(DiscriminantOverflow {
+                file: src.file_id,
+                variant: AstPtr::new(&src.value[id]),
+                value,
+                repr,
+            });
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -465,6 +525,12 @@ impl EnumVariant {
         self.variant_data(db).kind()
     }
 
+    /// Returns the discriminant of this variant. It's only computed for
+    /// fieldless enums, so it's `None` for all other enums.
+    pub fn discriminant(self, db: &dyn HirDatabase) -> Option<i128> {
+        db.enum_data(self.parent.id).variants[self.id].discriminant
+    }
+
     pub(crate) fn variant_data(self, db: &dyn HirDatabase) -> Arc<VariantData> {
         db.enum_data(self.parent.id).variants[self.id].variant_data.clone()
     }
@@ -607,7 +673,76 @@ impl Function {
     }
 
     pub fn params(self, db: &dyn HirDatabase) -> Vec<TypeRef> {
-        db.function_data(self.id).params.clone()
+        db.function_data(self.id).params.iter().map(|it| TypeRef::clone(it)).collect()
+    }
+
+    pub fn is_unsafe(self, db: &dyn HirDatabase) -> bool {
+        db.function_data(self.id).is_unsafe
+    }
+
+    pub fn abi(self, db: &dyn HirDatabase) -> Abi {
+        db.function_data(self.id).abi
+    }
+
+    pub fn is_varargs(self, db: &dyn HirDatabase) -> bool {
+        db.function_data(self.id).is_varargs
+    }
+
+    /// For a method of a trait impl, the trait method it implements or, if the
+    /// trait method has a default body, overrides.
+    pub fn super_method(self, db: &dyn HirDatabase) -> Option<Function> {
+        let impl_def: ImplDef = match self.id.lookup(db.upcast()).container {
+            AssocContainerId::ImplId(it) => it.into(),
+            _ => return None,
+        };
+        let name = self.name(db);
+        impl_def.target_trait_def(db)?.items(db).into_iter().find_map(|it| match it {
+            AssocItem::Function(it) if it.name(db) == name => Some(it),
+            _ => None,
+        })
+    }
+
+    /// For a trait method, the methods of all the impls of the trait which
+    /// implement or override it.
+    pub fn overriding_methods(self, db: &dyn HirDatabase) -> Vec<Function> {
+        let trait_: Trait = match self.id.lookup(db.upcast()).container {
+            AssocContainerId::TraitId(it) => it.into(),
+            _ => return Vec::new(),
+        };
+        let name = self.name(db);
+        ImplDef::all_for_trait(db, trait_)
+            .into_iter()
+            .flat_map(|it| it.items(db))
+            .filter_map(|it| match it {
+                AssocItem::Function(it) if it.name(db) == name => Some(it),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// For a trait method, the impls of the trait which don't implement it,
+    /// and so use its default body.
+    pub fn impls_without_override(self, db: &dyn HirDatabase) -> Vec<ImplDef> {
+        let trait_: Trait = match self.id.lookup(db.upcast()).container {
+            AssocContainerId::TraitId(it) => it.into(),
+            _ => return Vec::new(),
+        };
+        let name = self.name(db);
+        ImplDef::all_for_trait(db, trait_)
+            .into_iter()
+            .filter(|imp| {
+                !imp.items(db).into_iter().any(|it| match it {
+                    AssocItem::Function(it) => it.name(db) == name,
+                    _ => false,
+                })
+            })
+            .collect()
+    }
+
+    /// Renders the lowered body of the function with the inferred types, for
+    /// debugging.
+    pub fn debug_hir(self, db: &dyn HirDatabase) -> String {
+        print_typed_body(db, self.id.into())
     }
 
     pub fn diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
@@ -617,6 +752,15 @@ impl Function {
         let mut validator = ExprValidator::new(self.id, infer, sink);
         validator.validate_body(db);
     }
+
+    /// Adds the diagnostics of the experimental checks, which approximate
+    /// the borrow checker and may be wrong.
+    pub fn experimental_diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
+        let _p = profile("Function::experimental_diagnostics");
+        let infer = db.infer(self.id.into());
+        let mut validator = ExprValidator::new(self.id, infer, sink);
+        validator.validate_moves(db);
+    }
 }
 
 impl HasVisibility for Function {
@@ -644,6 +788,12 @@ impl Const {
     pub fn name(self, db: &dyn HirDatabase) -> Option<Name> {
         db.const_data(self.id).name.clone()
     }
+
+    /// Evaluates the initializer, if it's simple enough for the interpreter
+    /// in `hir_ty::consteval`.
+    pub fn eval(self, db: &dyn HirDatabase) -> Option<ConstValue> {
+        consteval::eval_const(db, self.id)
+    }
 }
 
 impl HasVisibility for Const {
@@ -671,6 +821,10 @@ impl Static {
     pub fn name(self, db: &dyn HirDatabase) -> Option<Name> {
         db.static_data(self.id).name.clone()
     }
+
+    pub fn is_mut(self, db: &dyn HirDatabase) -> bool {
+        self.source(db).value.mut_token().is_some()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -922,6 +1076,49 @@ impl Local {
             ast.map_left(|it| it.cast().unwrap().to_node(&root)).map_right(|it| it.to_node(&root))
         })
     }
+
+    /// Returns the closures in the parent body which capture this local.
+    pub fn closure_captures(self, db: &dyn HirDatabase) -> Vec<ClosureCapture> {
+        let body = db.body(self.parent);
+        let scopes = db.expr_scopes(self.parent);
+        let infer = db.infer(self.parent);
+        let mutates_receiver = |call| match infer.method_resolution(call) {
+            Some(func) => {
+                let data = db.function_data(func);
+                data.has_self_param
+                    && matches!(&*data.params[0], TypeRef::Reference(_, Mutability::Mut))
+            }
+            None => false,
+        };
+        closure_captures(&body, &scopes, self.pat_id, &mutates_receiver)
+            .into_iter()
+            .map(|it| ClosureCapture { parent: self.parent, closure: it.closure, mode: it.mode })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClosureCapture {
+    pub(crate) parent: DefWithBodyId,
+    pub(crate) closure: ExprId,
+    mode: CaptureMode,
+}
+
+impl ClosureCapture {
+    pub fn mode(self) -> CaptureMode {
+        self.mode
+    }
+
+    pub fn source(self, db: &dyn HirDatabase) -> Option<InFile<ast::LambdaExpr>> {
+        let (_body, source_map) = db.body_with_source_map(self.parent);
+        let src = source_map.expr_syntax(self.closure).ok()?;
+        let root = src.file_syntax(db.upcast());
+        src.map(|ptr| match ptr.to_node(&root) {
+            ast::Expr::LambdaExpr(it) => Some(it),
+            _ => None,
+        })
+        .transpose()
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -956,10 +1153,50 @@ impl ImplDef {
         impls.lookup_impl_defs_for_trait(trait_.id).map(Self::from).collect()
     }
 
+    /// Finds all impls of `trait_` in the crate which defines it and in all
+    /// crates depending on it.
+    pub fn all_for_trait(db: &dyn HirDatabase, trait_: Trait) -> Vec<ImplDef> {
+        let krate = trait_.module(db).id.krate;
+        db.crate_graph()
+            .transitive_reverse_dependencies(krate)
+            .into_iter()
+            .flat_map(|krate| {
+                let impls = db.impls_in_crate(krate);
+                impls.lookup_impl_defs_for_trait(trait_.id).map(Self::from).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Finds all inherent and trait impls of `ty` in the crates which can
+    /// see it.
+    pub fn all_for_type(db: &dyn HirDatabase, Type { krate, ty }: Type) -> Vec<ImplDef> {
+        let def_crates = match ty.value.def_crates(db, krate) {
+            Some(def_crates) => def_crates,
+            None => return Vec::new(),
+        };
+        let crate_graph = db.crate_graph();
+        let krates = def_crates
+            .into_iter()
+            .flat_map(|krate| crate_graph.transitive_reverse_dependencies(krate))
+            .collect::<FxHashSet<_>>();
+        krates
+            .into_iter()
+            .flat_map(|krate| {
+                let impls = db.impls_in_crate(krate);
+                impls.lookup_all_impl_defs_for_ty(&ty.value).map(Self::from).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     pub fn target_trait(&self, db: &dyn HirDatabase) -> Option<TypeRef> {
         db.impl_data(self.id).target_trait.clone()
     }
 
+    /// The trait implemented by this impl, if it's a trait impl.
+    pub fn target_trait_def(&self, db: &dyn HirDatabase) -> Option<Trait> {
+        db.impl_trait(self.id).map(|it| Trait { id: it.value.trait_ })
+    }
+
     pub fn target_type(&self, db: &dyn HirDatabase) -> TypeRef {
         db.impl_data(self.id).target_type.clone()
     }
@@ -1066,6 +1303,10 @@ impl Type {
         )
     }
 
+    pub fn is_raw_ptr(&self) -> bool {
+        matches!(self.ty.value, Ty::Apply(ApplicationTy { ctor: TypeCtor::RawPtr(..), .. }))
+    }
+
     pub fn is_unknown(&self) -> bool {
         matches!(self.ty.value, Ty::Unknown)
     }
@@ -1092,6 +1333,8 @@ impl Type {
         )
     }
 
+    /// Checks whether the type implements `trait_` with the given arguments
+    /// for its type parameters (not including `Self`).
     pub fn impls_trait(&self, db: &dyn HirDatabase, trait_: Trait, args: &[Type]) -> bool {
         let trait_ref = hir_ty::TraitRef {
             trait_: trait_.id,
@@ -1112,6 +1355,64 @@ impl Type {
         db.trait_solve(self.krate, goal).is_some()
     }
 
+    /// Checks whether the type implements `std::convert::From<source>`.
+    pub fn impls_from(&self, db: &dyn HirDatabase, source: &Type) -> bool {
+        match self.resolve_known_trait(db, &path![std::convert::From]) {
+            Some(id) => self.impls_trait(db, Trait { id }, &[source.clone()]),
+            None => false,
+        }
+    }
+
+    /// Returns the `Item` type if the type implements `std::iter::Iterator`.
+    pub fn iterator_item(&self, db: &dyn HirDatabase) -> Option<Type> {
+        let iterator_trait = self.resolve_known_trait(db, &path![std::iter::Iterator])?;
+        let item = db.trait_data(iterator_trait).associated_type_by_name(&name![Item])?;
+        self.normalize_trait_assoc_type(db, &[], item.into())
+    }
+
+    /// Resolves a trait of the standard library from the crate of the type.
+    fn resolve_known_trait(&self, db: &dyn HirDatabase, path: &ModPath) -> Option<TraitId> {
+        let def_map = db.crate_def_map(self.krate);
+        let root = ModuleId { krate: self.krate, local_id: def_map.root };
+        root.resolver(db.upcast()).resolve_known_trait(db.upcast(), path)
+    }
+
+    /// Resolves `<Self as Trait<args>>::Alias`, with `Trait` the trait
+    /// `alias` belongs to.
+    pub fn normalize_trait_assoc_type(
+        &self,
+        db: &dyn HirDatabase,
+        args: &[Type],
+        alias: TypeAlias,
+    ) -> Option<Type> {
+        let trait_ = match alias.id.lookup(db.upcast()).container {
+            AssocContainerId::TraitId(it) => it,
+            _ => return None,
+        };
+        let parameters = Substs::build_for_def(db, trait_)
+            .push(self.ty.value.clone())
+            .fill(args.iter().map(|t| t.ty.value.clone()))
+            .build();
+        let projection = hir_ty::ProjectionPredicate {
+            projection_ty: hir_ty::ProjectionTy { associated_ty: alias.id, parameters },
+            ty: Ty::Bound(BoundVar::new(DebruijnIndex::INNERMOST, 0)),
+        };
+        let goal = Canonical {
+            value: InEnvironment::new(
+                self.ty.environment.clone(),
+                hir_ty::Obligation::Projection(projection),
+            ),
+            num_vars: 1,
+        };
+
+        match db.trait_solve(self.krate, goal)? {
+            Solution::Unique(SolutionVariables(vars)) => {
+                vars.value.into_iter().next().map(|ty| self.derived(ty))
+            }
+            Solution::Ambig(_) => None,
+        }
+    }
+
     // FIXME: this method is broken, as it doesn't take closures into account.
     pub fn as_callable(&self) -> Option<CallableDef> {
         Some(self.ty.value.as_callable()?.0)
@@ -1160,6 +1461,86 @@ impl Type {
         res
     }
 
+    /// Whether a value of the type can still be used after it's moved. Types
+    /// we can't tell, like unknown types, are assumed to be `Copy`, and so are
+    /// mutable references, which are usually reborrowed rather than moved.
+    pub fn is_copy(&self, db: &dyn HirDatabase) -> bool {
+        let copy_trait = match db.lang_item(self.krate, "copy".into()).and_then(|it| it.as_trait())
+        {
+            Some(id) => Trait { id },
+            None => return true,
+        };
+        return go(db, self, copy_trait);
+
+        fn go(db: &dyn HirDatabase, ty: &Type, copy_trait: Trait) -> bool {
+            let a_ty = match &ty.ty.value {
+                Ty::Apply(it) => it,
+                Ty::Placeholder(_) => return ty.impls_trait(db, copy_trait, &[]),
+                _ => return true,
+            };
+            match a_ty.ctor {
+                TypeCtor::Adt(_) => ty.impls_trait(db, copy_trait, &[]),
+                TypeCtor::Tuple { .. } | TypeCtor::Array => {
+                    a_ty.parameters.iter().all(|it| go(db, &ty.derived(it.clone()), copy_trait))
+                }
+                _ => true,
+            }
+        }
+    }
+
+    /// Whether dropping a value of the type runs any code, because the type or
+    /// one of its fields implements `Drop`. This is an approximation: type
+    /// parameters and trait objects are assumed to need dropping, closures
+    /// are assumed not to.
+    pub fn needs_drop(&self, db: &dyn HirDatabase) -> bool {
+        let drop_trait = db
+            .lang_item(self.krate, "drop".into())
+            .and_then(|it| it.as_trait())
+            .map(|id| Trait { id });
+        return go(db, self, drop_trait, 0);
+
+        fn go(db: &dyn HirDatabase, ty: &Type, drop_trait: Option<Trait>, depth: usize) -> bool {
+            // Recursive types end up in a type which implements `Drop`, like
+            // `Box`, but give up on deep nesting all the same.
+            if depth > 8 {
+                return true;
+            }
+            let a_ty = match &ty.ty.value {
+                Ty::Apply(it) => it,
+                Ty::Infer(_) | Ty::Unknown => return false,
+                _ => return true,
+            };
+            let needs_drop = |it: Ty| go(db, &ty.derived(it), drop_trait, depth + 1);
+            match a_ty.ctor {
+                TypeCtor::Adt(adt) => {
+                    if drop_trait.map_or(false, |it| ty.impls_trait(db, it, &[])) {
+                        return true;
+                    }
+                    let variants: Vec<VariantId> = match adt {
+                        AdtId::StructId(it) => vec![it.into()],
+                        // Union fields are never dropped.
+                        AdtId::UnionId(_) => return false,
+                        AdtId::EnumId(it) => db
+                            .enum_data(it)
+                            .variants
+                            .iter()
+                            .map(|(local_id, _)| EnumVariantId { parent: it, local_id }.into())
+                            .collect(),
+                    };
+                    variants.into_iter().any(|variant| {
+                        db.field_types(variant)
+                            .iter()
+                            .any(|(_, it)| needs_drop(it.clone().subst(&a_ty.parameters)))
+                    })
+                }
+                TypeCtor::Array | TypeCtor::Slice | TypeCtor::Tuple { .. } => {
+                    a_ty.parameters.iter().any(|it| needs_drop(it.clone()))
+                }
+                _ => false,
+            }
+        }
+    }
+
     pub fn autoderef<'a>(&'a self, db: &'a dyn HirDatabase) -> impl Iterator<Item = Type> + 'a {
         // There should be no inference vars in types passed here
         // FIXME check that?
@@ -22,8 +22,8 @@ use crate::{
     db::HirDatabase,
     semantics::source_to_def::{ChildContainer, SourceToDefCache, SourceToDefCtx},
     source_analyzer::{resolve_hir_path, SourceAnalyzer},
-    AssocItem, Function, HirFileId, ImplDef, InFile, Local, MacroDef, Module, ModuleDef, Name,
-    Origin, Path, ScopeDef, StructField, Trait, Type, TypeParam,
+    AssocItem, Function, HasSource, HirFileId, ImplDef, InFile, Local, MacroDef, Module, ModuleDef,
+    Name, Origin, Path, ScopeDef, StructField, Trait, Type, TypeParam,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -63,6 +63,16 @@ impl<'db, DB: HirDatabase> Semantics<'db, DB> {
         tree
     }
 
+    /// The source of `def`, which can be queried further with this instance.
+    pub fn source<Def: HasSource>(&self, def: Def) -> InFile<Def::Ast>
+    where
+        Def::Ast: AstNode,
+    {
+        let res = def.source(self.db);
+        self.cache(find_root(res.value.syntax()), res.file_id);
+        res
+    }
+
     pub fn expand(&self, macro_call: &ast::MacroCall) -> Option<SyntaxNode> {
         let macro_call = self.find_file(macro_call.syntax().clone()).with_value(macro_call);
         let sa = self.analyze2(macro_call.map(|it| it.syntax()), None);
@@ -187,6 +197,12 @@ impl<'db, DB: HirDatabase> Semantics<'db, DB> {
         self.analyze(field.syntax()).resolve_record_field(self.db, field)
     }
 
+    /// Resolves a shorthand field pattern, like `bar` in `Foo { bar }`, to the
+    /// field it binds.
+    pub fn resolve_record_field_pat(&self, field: &ast::BindPat) -> Option<StructField> {
+        self.analyze(field.syntax()).resolve_record_field_pat(self.db, field)
+    }
+
     pub fn resolve_macro_call(&self, macro_call: &ast::MacroCall) -> Option<MacroDef> {
         let sa = self.analyze(macro_call.syntax());
         let macro_call = self.find_file(macro_call.syntax().clone()).with_value(macro_call);
@@ -201,6 +217,11 @@ impl<'db, DB: HirDatabase> Semantics<'db, DB> {
         self.analyze(pat.syntax()).resolve_bind_pat_to_const(self.db, pat)
     }
 
+    /// Evaluates the length of an array type, like `[u8; HEADER_LEN * 2]`.
+    pub fn array_len(&self, ty: &ast::ArrayType) -> Option<u64> {
+        self.analyze(ty.syntax()).array_len(self.db, ty)
+    }
+
     // FIXME: use this instead?
     // pub fn resolve_name_ref(&self, name_ref: &ast::NameRef) -> Option<???>;
 
@@ -430,6 +451,26 @@ pub fn original_range(db: &dyn HirDatabase, node: InFile<&SyntaxNode>) -> FileRa
     FileRange { file_id: node.file_id.original_file(db.upcast()), range: node.value.text_range() }
 }
 
+/// Maps a token produced by macro expansion back to the token it was created
+/// from, through any number of nested expansions. The returned origin is
+/// `Origin::Def` if the token was written in a macro definition rather than
+/// passed to a macro call.
+pub fn original_token(
+    db: &dyn HirDatabase,
+    token: InFile<SyntaxToken>,
+) -> Option<(InFile<SyntaxToken>, Origin)> {
+    let mut token = token;
+    let mut origin = Origin::Call;
+    while let Some(expansion) = token.file_id.expansion_info(db.upcast()) {
+        let (mapped, mapped_origin) = expansion.map_token_up(token.as_ref())?;
+        if mapped_origin == Origin::Def {
+            origin = Origin::Def;
+        }
+        token = mapped;
+    }
+    Some((token, origin))
+}
+
 fn original_range_opt(
     db: &dyn HirDatabase,
     node: InFile<&SyntaxNode>,
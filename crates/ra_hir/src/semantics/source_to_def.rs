@@ -9,7 +9,7 @@ use hir_def::{
     StaticId, StructFieldId, StructId, TraitId, TypeAliasId, TypeParamId, UnionId, VariantId,
 };
 use hir_expand::{name::AsName, AstId, MacroDefKind};
-use ra_db::FileId;
+use ra_db::{CrateId, FileId};
 use ra_prof::profile;
 use ra_syntax::{
     ast::{self, NameOwner},
@@ -39,6 +39,14 @@ impl SourceToDefCtx<'_, '_> {
 
     pub(super) fn module_to_def(&mut self, src: InFile<ast::Module>) -> Option<ModuleId> {
         let _p = profile("module_to_def");
+        // A module can be cfg'd out in the first crate of the file, like a
+        // `#[cfg(test)]` module in the normal crate of a lib with a test crate.
+        let file_id = src.file_id.original_file(self.db.upcast());
+        let crates = self.db.relevant_crates(file_id);
+        crates.iter().find_map(|&krate| self.module_to_def_in(src.clone(), krate))
+    }
+
+    fn module_to_def_in(&mut self, src: InFile<ast::Module>, krate: CrateId) -> Option<ModuleId> {
         let parent_declaration = src
             .as_ref()
             .map(|it| it.syntax())
@@ -51,10 +59,11 @@ impl SourceToDefCtx<'_, '_> {
             });
 
         let parent_module = match parent_declaration {
-            Some(parent_declaration) => self.module_to_def(parent_declaration),
+            Some(parent_declaration) => self.module_to_def_in(parent_declaration, krate),
             None => {
                 let file_id = src.file_id.original_file(self.db.upcast());
-                self.file_to_def(file_id)
+                let local_id = self.db.crate_def_map(krate).modules_for_file(file_id).next()?;
+                Some(ModuleId { krate, local_id })
             }
         }?;
 
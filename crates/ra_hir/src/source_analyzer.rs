@@ -14,22 +14,24 @@ use hir_def::{
     },
     expr::{ExprId, Pat, PatId},
     resolver::{resolver_for_scope, Resolver, TypeNs, ValueNs},
+    type_ref::ConstExpr,
     AsMacroCall, DefWithBodyId, LocalStructFieldId, StructFieldId, VariantId,
 };
 use hir_expand::{hygiene::Hygiene, name::AsName, HirFileId, InFile};
 use hir_ty::{
+    consteval,
     expr::{record_literal_missing_fields, record_pattern_missing_fields},
     InferenceResult, Substs, Ty,
 };
 use ra_syntax::{
-    ast::{self, AstNode},
+    ast::{self, AstNode, NameOwner},
     SyntaxNode, SyntaxNodePtr, TextUnit,
 };
 
 use crate::{
     db::HirDatabase, semantics::PathResolution, Adt, Const, EnumVariant, Function, Local, MacroDef,
     ModPath, ModuleDef, Path, PathKind, Static, Struct, StructField, Trait, Type, TypeAlias,
-    TypeParam,
+    TypeParam, VariantDef,
 };
 use ra_db::CrateId;
 
@@ -167,6 +169,18 @@ impl SourceAnalyzer {
         Some((struct_field.into(), local))
     }
 
+    pub(crate) fn resolve_record_field_pat(
+        &self,
+        db: &dyn HirDatabase,
+        field: &ast::BindPat,
+    ) -> Option<StructField> {
+        let record_pat = field.syntax().parent()?.parent().and_then(ast::RecordPat::cast)?;
+        let pat_id = self.pat_id(&record_pat.into())?;
+        let variant: VariantDef = self.infer.as_ref()?.variant_resolution_for_pat(pat_id)?.into();
+        let field_name = field.name()?.as_name();
+        variant.fields(db).into_iter().find(|it| it.name(db) == field_name)
+    }
+
     pub(crate) fn resolve_macro_call(
         &self,
         db: &dyn HirDatabase,
@@ -195,6 +209,11 @@ impl SourceAnalyzer {
         }
     }
 
+    pub(crate) fn array_len(&self, db: &dyn HirDatabase, ty: &ast::ArrayType) -> Option<u64> {
+        let len = ConstExpr::from_ast(ty.expr());
+        consteval::eval_array_len(db, &self.resolver, &len)
+    }
+
     pub(crate) fn resolve_path(
         &self,
         db: &dyn HirDatabase,
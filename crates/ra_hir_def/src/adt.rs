@@ -1,6 +1,6 @@
 //! Defines hir-level representation of structs, enums and unions
 
-use std::sync::Arc;
+use std::{convert::TryFrom, sync::Arc};
 
 use either::Either;
 use hir_expand::{
@@ -9,12 +9,12 @@ use hir_expand::{
 };
 use ra_arena::{map::ArenaMap, Arena};
 use ra_prof::profile;
-use ra_syntax::ast::{self, NameOwner, TypeAscriptionOwner, VisibilityOwner};
+use ra_syntax::ast::{self, AstToken, NameOwner, TypeAscriptionOwner, VisibilityOwner};
 
 use crate::{
-    body::CfgExpander, db::DefDatabase, src::HasChildSource, src::HasSource, trace::Trace,
-    type_ref::TypeRef, visibility::RawVisibility, EnumId, HasModule, LocalEnumVariantId,
-    LocalStructFieldId, Lookup, ModuleId, StructId, UnionId, VariantId,
+    body::CfgExpander, db::DefDatabase, intern::Interned, src::HasChildSource, src::HasSource,
+    trace::Trace, type_ref::TypeRef, visibility::RawVisibility, EnumId, HasModule,
+    LocalEnumVariantId, LocalStructFieldId, Lookup, ModuleId, StructId, UnionId, VariantId,
 };
 
 /// Note that we use `StructData` for unions as well!
@@ -34,6 +34,9 @@ pub struct EnumData {
 pub struct EnumVariantData {
     pub name: Name,
     pub variant_data: Arc<VariantData>,
+    /// The value of the discriminant, if the enum is fieldless and it is an
+    /// integer literal or can be derived from the previous variant's one.
+    pub discriminant: Option<i128>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -47,7 +50,7 @@ pub enum VariantData {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StructFieldData {
     pub name: Name,
-    pub type_ref: TypeRef,
+    pub type_ref: Interned<TypeRef>,
     pub visibility: RawVisibility,
 }
 
@@ -111,17 +114,44 @@ fn lower_enum(
     ast: &InFile<ast::EnumDef>,
     module_id: ModuleId,
 ) {
-    for var in ast.value.variant_list().into_iter().flat_map(|it| it.variants()) {
+    let variants: Vec<_> =
+        ast.value.variant_list().into_iter().flat_map(|it| it.variants()).collect();
+    // Discriminants are only computed for fieldless enums.
+    let is_fieldless = variants.iter().all(|it| it.kind() == ast::StructKind::Unit);
+    let mut next_discriminant = if is_fieldless { Some(0) } else { None };
+    for var in variants {
+        let discriminant = match var.expr() {
+            Some(expr) if is_fieldless => eval_discriminant(&expr),
+            _ => next_discriminant,
+        };
+        next_discriminant = discriminant.and_then(|it| it.checked_add(1));
         trace.alloc(
             || var.clone(),
             || EnumVariantData {
                 name: var.name().map_or_else(Name::missing, |it| it.as_name()),
                 variant_data: Arc::new(VariantData::new(db, ast.with_value(var.kind()), module_id)),
+                discriminant,
             },
         );
     }
 }
 
+/// Evaluates an explicit discriminant like `-0x10`. Anything but (negated)
+/// integer literals is left to const eval, which we don't have yet.
+fn eval_discriminant(expr: &ast::Expr) -> Option<i128> {
+    match expr {
+        ast::Expr::Literal(lit) => {
+            let value = ast::IntNumber::cast(lit.token())?.value()?;
+            i128::try_from(value).ok()
+        }
+        ast::Expr::PrefixExpr(prefix) if prefix.op_kind() == Some(ast::PrefixOp::Neg) => {
+            eval_discriminant(&prefix.expr()?)?.checked_neg()
+        }
+        ast::Expr::ParenExpr(paren) => eval_discriminant(&paren.expr()?),
+        _ => None,
+    }
+}
+
 impl VariantData {
     fn new(db: &dyn DefDatabase, flavor: InFile<ast::StructKind>, module_id: ModuleId) -> Self {
         let mut expander = CfgExpander::new(db, flavor.file_id, module_id.krate);
@@ -210,7 +240,7 @@ fn lower_struct(
                     || Either::Left(fd.clone()),
                     || StructFieldData {
                         name: Name::new_tuple_field(i),
-                        type_ref: TypeRef::from_ast_opt(fd.type_ref()),
+                        type_ref: Interned::new(TypeRef::from_ast_opt(fd.type_ref())),
                         visibility: RawVisibility::from_ast(db, ast.with_value(fd.visibility())),
                     },
                 );
@@ -228,7 +258,7 @@ fn lower_struct(
                     || Either::Right(fd.clone()),
                     || StructFieldData {
                         name: fd.name().map(|n| n.as_name()).unwrap_or_else(Name::missing),
-                        type_ref: TypeRef::from_ast_opt(fd.ascribed_type()),
+                        type_ref: Interned::new(TypeRef::from_ast_opt(fd.ascribed_type())),
                         visibility: RawVisibility::from_ast(db, ast.with_value(fd.visibility())),
                     },
                 );
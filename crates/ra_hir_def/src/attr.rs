@@ -1,6 +1,6 @@
 //! A higher level attributes based on TokenTree, with also some shortcuts.
 
-use std::{ops, sync::Arc};
+use std::{fmt, ops, sync::Arc};
 
 use either::Either;
 use hir_expand::{hygiene::Hygiene, AstId, InFile};
@@ -10,10 +10,15 @@ use ra_syntax::{
     ast::{self, AstNode, AttrsOwner},
     SmolStr,
 };
-use tt::Subtree;
+use tt::{Leaf, Subtree, TokenTree};
 
 use crate::{
-    db::DefDatabase, path::ModPath, src::HasChildSource, src::HasSource, AdtId, AttrDefId, Lookup,
+    builtin_type::{BuiltinInt, BuiltinType},
+    db::DefDatabase,
+    path::ModPath,
+    src::HasChildSource,
+    src::HasSource,
+    AdtId, AttrDefId, Lookup,
 };
 
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
@@ -94,7 +99,133 @@ impl Attrs {
 
     pub(crate) fn is_cfg_enabled(&self, cfg_options: &CfgOptions) -> bool {
         // FIXME: handle cfg_attr :-)
-        self.by_key("cfg").tt_values().all(|tt| cfg_options.is_cfg_enabled(tt) != Some(false))
+        self.cfg().all(|tt| cfg_options.is_cfg_enabled(tt) != Some(false))
+    }
+
+    /// The predicates of all `#[cfg(...)]` attributes.
+    pub fn cfg(&self) -> impl Iterator<Item = &Subtree> + '_ {
+        self.by_key("cfg").tt_values()
+    }
+
+    /// Paths of all macros listed in `#[derive(...)]` attributes.
+    pub fn derives(&self) -> impl Iterator<Item = ModPath> + '_ {
+        // for #[derive(Copy, Clone)], `derive_subtree` is the `(Copy, Clone)` subtree
+        self.by_key("derive").tt_values().flat_map(|derive_subtree| {
+            derive_subtree.token_trees.iter().filter_map(|tt| match tt {
+                TokenTree::Leaf(Leaf::Ident(ident)) => Some(ModPath::from_tt_ident(ident)),
+                // `,` is ok, anything else would be an error (which we currently ignore)
+                _ => None,
+            })
+        })
+    }
+
+    /// The value of `#[path = "..."]` on a module.
+    pub fn path(&self) -> Option<&SmolStr> {
+        self.by_key("path").string_value()
+    }
+
+    pub fn is_macro_export(&self) -> bool {
+        self.by_key("macro_export").exists()
+    }
+
+    pub fn is_macro_use(&self) -> bool {
+        self.by_key("macro_use").exists()
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.by_key("deprecated").exists()
+    }
+
+    /// Whether the item is marked `#[doc(hidden)]`.
+    pub fn is_doc_hidden(&self) -> bool {
+        self.by_key("doc").tt_values().any(|tt| {
+            tt.token_trees.iter().any(|it| match it {
+                TokenTree::Leaf(Leaf::Ident(ident)) => ident.text == "hidden",
+                _ => false,
+            })
+        })
+    }
+
+    pub fn is_must_use(&self) -> bool {
+        self.by_key("must_use").exists()
+    }
+
+    pub fn is_non_exhaustive(&self) -> bool {
+        self.by_key("non_exhaustive").exists()
+    }
+
+    /// Merges all `#[repr(...)]` attributes, or returns `None` if there are
+    /// none.
+    pub fn repr(&self) -> Option<ReprOptions> {
+        let mut tt_values = self.by_key("repr").tt_values().peekable();
+        tt_values.peek()?;
+        let mut res = ReprOptions::default();
+        for tt in tt_values {
+            let mut tokens = tt.token_trees.iter().peekable();
+            while let Some(token) = tokens.next() {
+                let ident = match token {
+                    TokenTree::Leaf(Leaf::Ident(ident)) => &ident.text,
+                    _ => continue,
+                };
+                let arg = match tokens.peek().copied() {
+                    Some(TokenTree::Subtree(subtree)) => {
+                        tokens.next();
+                        match subtree.token_trees.first() {
+                            Some(TokenTree::Leaf(Leaf::Literal(lit))) => lit.text.parse().ok(),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                match ident.as_str() {
+                    "C" => res.c = true,
+                    "transparent" => res.transparent = true,
+                    "packed" => res.packed = Some(arg.unwrap_or(1)),
+                    "align" => res.align = arg,
+                    it => {
+                        if let Some(int) = BuiltinInt::from_suffix(it) {
+                            res.int = Some(int);
+                        }
+                    }
+                }
+            }
+        }
+        Some(res)
+    }
+}
+
+/// The layout options given by `#[repr(...)]`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReprOptions {
+    pub int: Option<BuiltinInt>,
+    pub c: bool,
+    pub transparent: bool,
+    pub packed: Option<u32>,
+    pub align: Option<u32>,
+}
+
+impl fmt::Display for ReprOptions {
+    /// Renders the options as they'd appear inside `#[repr(...)]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.c {
+            parts.push("C".to_string());
+        }
+        if self.transparent {
+            parts.push("transparent".to_string());
+        }
+        if let Some(int) = self.int {
+            parts.push(BuiltinType::Int(int).to_string());
+        }
+        match self.packed {
+            Some(1) => parts.push("packed".to_string()),
+            Some(n) => parts.push(format!("packed({})", n)),
+            None => {}
+        }
+        if let Some(n) = self.align {
+            parts.push(format!("align({})", n));
+        }
+        write!(f, "{}", parts.join(", "))
     }
 }
 
@@ -0,0 +1,128 @@
+//! Finds closures which capture a local binding from their environment, and
+//! how they capture it.
+//!
+//! The capture mode is computed syntactically: we don't know whether a use
+//! moves a non-`Copy` value, so only `move` closures capture by move. Whether
+//! a method call borrows its receiver mutably needs the resolved method, so
+//! it's left to the caller.
+use crate::{
+    body::{
+        scope::{ExprScopes, ScopeId},
+        Body,
+    },
+    expr::{BinaryOp, Expr, ExprId, PatId},
+    type_ref::Mutability,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CaptureMode {
+    ByRef,
+    ByMutRef,
+    ByMove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClosureCapture {
+    /// The `Expr::Lambda` capturing the binding.
+    pub closure: ExprId,
+    pub mode: CaptureMode,
+}
+
+/// Returns all closures in `body` which refer to `binding` while it is
+/// defined outside of them, in source order. `mutates_receiver` tells whether
+/// a method call takes its receiver by `&mut self`.
+pub fn closure_captures(
+    body: &Body,
+    scopes: &ExprScopes,
+    binding: PatId,
+    mutates_receiver: &dyn Fn(ExprId) -> bool,
+) -> Vec<ClosureCapture> {
+    let mut res = Vec::new();
+    for (closure, expr) in body.exprs.iter() {
+        let (closure_body, is_move) = match expr {
+            Expr::Lambda { body, is_move, .. } => (*body, *is_move),
+            _ => continue,
+        };
+        // The closure's parameters live in the scope of its body.
+        let closure_scope = match scopes.scope_for(closure_body) {
+            Some(it) => it,
+            None => continue,
+        };
+
+        let mut uses = Vec::new();
+        collect_uses(body, mutates_receiver, closure_body, false, &mut uses);
+        let mode = uses
+            .into_iter()
+            .filter(|&(expr, _)| refers_to_outer(body, scopes, closure_scope, expr, binding))
+            .map(|(_, mutated)| {
+                if is_move {
+                    CaptureMode::ByMove
+                } else if mutated {
+                    CaptureMode::ByMutRef
+                } else {
+                    CaptureMode::ByRef
+                }
+            })
+            .max();
+        if let Some(mode) = mode {
+            res.push(ClosureCapture { closure, mode });
+        }
+    }
+    res
+}
+
+/// Collects path expressions under `expr`, noting whether the place they name
+/// is assigned to or mutably borrowed.
+fn collect_uses(
+    body: &Body,
+    mutates_receiver: &dyn Fn(ExprId) -> bool,
+    expr: ExprId,
+    mutated: bool,
+    acc: &mut Vec<(ExprId, bool)>,
+) {
+    let mut collect = |expr, mutated| collect_uses(body, mutates_receiver, expr, mutated, acc);
+    match &body[expr] {
+        Expr::Path(_) => acc.push((expr, mutated)),
+        Expr::BinaryOp { lhs, rhs, op: Some(BinaryOp::Assignment { .. }) } => {
+            collect(*lhs, true);
+            collect(*rhs, false);
+        }
+        Expr::Ref { expr, mutability: Mutability::Mut } => collect(*expr, true),
+        Expr::MethodCall { receiver, args, .. } => {
+            collect(*receiver, mutates_receiver(expr));
+            args.iter().for_each(|it| collect(*it, false));
+        }
+        Expr::Field { expr, .. } => collect(*expr, mutated),
+        Expr::Index { base, index } => {
+            collect(*base, mutated);
+            collect(*index, false);
+        }
+        e => e.walk_child_exprs(|it| collect(it, false)),
+    }
+}
+
+fn refers_to_outer(
+    body: &Body,
+    scopes: &ExprScopes,
+    closure_scope: ScopeId,
+    expr: ExprId,
+    binding: PatId,
+) -> bool {
+    let name = match &body[expr] {
+        Expr::Path(path) => match path.as_ident() {
+            Some(it) => it,
+            None => return false,
+        },
+        _ => return false,
+    };
+    let mut crossed_closure = false;
+    for scope in scopes.scope_chain(scopes.scope_for(expr)) {
+        if let Some(entry) = scopes.entries(scope).iter().find(|it| it.name() == name) {
+            return crossed_closure && entry.pat() == binding;
+        }
+        if scope == closure_scope {
+            crossed_closure = true;
+        }
+    }
+    false
+}
@@ -9,8 +9,8 @@ use hir_expand::{
 use ra_arena::Arena;
 use ra_syntax::{
     ast::{
-        self, ArgListOwner, ArrayExprKind, LiteralKind, LoopBodyOwner, ModuleItemOwner, NameOwner,
-        SlicePatComponents, TypeAscriptionOwner,
+        self, ArgListOwner, ArrayExprKind, AstToken, HasStringValue, LiteralKind, LoopBodyOwner,
+        ModuleItemOwner, NameOwner, SlicePatComponents, TypeAscriptionOwner,
     },
     AstNode, AstPtr,
 };
@@ -182,7 +182,14 @@ impl ExprCollector<'_> {
                 let body = self.collect_block_opt(e.body());
                 self.alloc_expr(Expr::TryBlock { body }, syntax_ptr)
             }
-            ast::Expr::BlockExpr(e) => self.collect_block(e),
+            ast::Expr::BlockExpr(e) => {
+                if e.async_token().is_some() {
+                    let body = self.collect_block(e);
+                    self.alloc_expr(Expr::Async { body }, syntax_ptr)
+                } else {
+                    self.collect_block(e)
+                }
+            }
             ast::Expr::LoopExpr(e) => {
                 let body = self.collect_block_opt(e.loop_body());
                 self.alloc_expr(Expr::Loop { body }, syntax_ptr)
@@ -371,7 +378,11 @@ impl ExprCollector<'_> {
                 }
                 let ret_type = e.ret_type().and_then(|r| r.type_ref()).map(TypeRef::from_ast);
                 let body = self.collect_expr_opt(e.body());
-                self.alloc_expr(Expr::Lambda { args, arg_types, ret_type, body }, syntax_ptr)
+                let is_move = e.move_token().is_some();
+                self.alloc_expr(
+                    Expr::Lambda { args, arg_types, ret_type, body, is_move },
+                    syntax_ptr,
+                )
             }
             ast::Expr::BinExpr(e) => {
                 let lhs = self.collect_expr_opt(e.lhs());
@@ -407,7 +418,7 @@ impl ExprCollector<'_> {
                 }
             }
 
-            ast::Expr::Literal(e) => self.alloc_expr(Expr::Literal(e.kind().into()), syntax_ptr),
+            ast::Expr::Literal(e) => self.alloc_expr(Expr::Literal(Literal::from(&e)), syntax_ptr),
             ast::Expr::IndexExpr(e) => {
                 let base = self.collect_expr_opt(e.base());
                 let index = self.collect_expr_opt(e.index());
@@ -658,7 +669,7 @@ impl ExprCollector<'_> {
             }
             ast::Pat::LiteralPat(lit) => {
                 if let Some(ast_lit) = lit.literal() {
-                    let expr = Expr::Literal(ast_lit.kind().into());
+                    let expr = Expr::Literal(Literal::from(&ast_lit));
                     let expr_ptr = AstPtr::new(&ast::Expr::Literal(ast_lit));
                     let expr_id = self.alloc_expr(expr, expr_ptr);
                     Pat::Lit(expr_id)
@@ -742,24 +753,44 @@ impl From<ast::BinOp> for BinaryOp {
     }
 }
 
-impl From<ast::LiteralKind> for Literal {
-    fn from(ast_lit_kind: ast::LiteralKind) -> Self {
-        match ast_lit_kind {
+impl From<&ast::Literal> for Literal {
+    fn from(ast_lit: &ast::Literal) -> Self {
+        let token = ast_lit.token();
+        match ast_lit.kind() {
             LiteralKind::IntNumber { suffix } => {
                 let known_name = suffix.and_then(|it| BuiltinInt::from_suffix(&it));
+                let value = ast::IntNumber::cast(token).and_then(|it| it.value());
 
-                Literal::Int(Default::default(), known_name)
+                Literal::Int(value.unwrap_or_default(), known_name)
             }
             LiteralKind::FloatNumber { suffix } => {
                 let known_name = suffix.and_then(|it| BuiltinFloat::from_suffix(&it));
+                let value = ast::FloatNumber::cast(token).and_then(|it| it.value());
 
-                Literal::Float(Default::default(), known_name)
+                Literal::Float(value.unwrap_or_default().to_bits(), known_name)
+            }
+            LiteralKind::ByteString => {
+                let value = match ast::ByteString::cast(token.clone()) {
+                    Some(it) => it.unescape().map(|it| it.value),
+                    None => ast::RawByteString::cast(token).and_then(|it| it.value()),
+                };
+                Literal::ByteString(value.unwrap_or_default())
+            }
+            LiteralKind::String => {
+                let value = match ast::String::cast(token.clone()) {
+                    Some(it) => it.value(),
+                    None => ast::RawString::cast(token).and_then(|it| it.value()),
+                };
+                Literal::String(value.unwrap_or_default())
+            }
+            LiteralKind::Byte => {
+                let value = ast::Byte::cast(token).and_then(|it| it.value());
+                Literal::Int(value.unwrap_or_default().into(), Some(BuiltinInt::U8))
             }
-            LiteralKind::ByteString => Literal::ByteString(Default::default()),
-            LiteralKind::String => Literal::String(Default::default()),
-            LiteralKind::Byte => Literal::Int(Default::default(), Some(BuiltinInt::U8)),
             LiteralKind::Bool(val) => Literal::Bool(val),
-            LiteralKind::Char => Literal::Char(Default::default()),
+            LiteralKind::Char => {
+                Literal::Char(ast::Char::cast(token).and_then(|it| it.value()).unwrap_or_default())
+            }
         }
     }
 }
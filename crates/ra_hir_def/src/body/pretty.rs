@@ -0,0 +1,807 @@
+//! A pretty-printer for lowered bodies, used by the "view HIR" request and in
+//! tests.
+//!
+//! The output looks like Rust, but shows the body as it was lowered: macros are
+//! expanded, `if let` and `while let` are `match`es, and literals show the
+//! value stored in the HIR. Parentheses are only printed where the tree needs
+//! them. Annotations, like inferred types, are printed as comments.
+
+use std::fmt::{self, Write};
+
+use hir_expand::name::Name;
+use ra_syntax::ast::RangeOp;
+
+use crate::{
+    body::Body,
+    builtin_type::BuiltinType,
+    db::DefDatabase,
+    expr::{
+        ArithOp, Array, BinaryOp, BindingAnnotation, CmpOp, Expr, ExprId, Literal, LogicOp,
+        Ordering, Pat, PatId, Statement, UnaryOp,
+    },
+    path::{GenericArg, GenericArgs, Path, PathKind},
+    type_ref::{Abi, ConstExpr, TypeBound, TypeRef},
+    DefWithBodyId,
+};
+
+/// Extra information to print along with a body, like the inferred types.
+pub trait Annotations {
+    /// Printed behind a binding.
+    fn pat(&self, _pat: PatId) -> Option<String> {
+        None
+    }
+
+    /// Printed behind a path expression or a method call.
+    fn expr(&self, _expr: ExprId) -> Option<String> {
+        None
+    }
+}
+
+impl Annotations for () {}
+
+macro_rules! w {
+    ($dst:expr, $($arg:tt)*) => {
+        { let _ = write!($dst, $($arg)*); }
+    };
+}
+
+/// Prints the body of `owner`, preceded by its name and the patterns of its
+/// parameters.
+pub fn print_body(
+    db: &dyn DefDatabase,
+    owner: DefWithBodyId,
+    annotations: &dyn Annotations,
+) -> String {
+    let body = db.body(owner);
+    let mut p = Printer { body: &body, annotations, buf: String::new(), indent_level: 0 };
+    match owner {
+        DefWithBodyId::FunctionId(it) => {
+            w!(p, "fn {}(", db.function_data(it).name);
+            p.print_sep(&body.params, ", ", |p, &pat| p.print_pat(pat));
+            w!(p, ") ");
+            p.print_expr(body.body_expr);
+        }
+        DefWithBodyId::ConstId(it) => {
+            match &db.const_data(it).name {
+                Some(name) => w!(p, "const {} = ", name),
+                None => w!(p, "const _ = "),
+            }
+            p.print_expr(body.body_expr);
+            w!(p, ";");
+        }
+        DefWithBodyId::StaticId(it) => {
+            let name = db.static_data(it).name.clone().unwrap_or_else(Name::missing);
+            w!(p, "static {} = ", name);
+            p.print_expr(body.body_expr);
+            w!(p, ";");
+        }
+    }
+    p.buf
+}
+
+pub fn print_type_ref(type_ref: &TypeRef) -> String {
+    let mut p = TypePrinter { buf: String::new() };
+    p.print_type_ref(type_ref);
+    p.buf
+}
+
+/// Prints a path as written in a type, with the generic args of its segments.
+pub fn print_path(path: &Path) -> String {
+    let mut p = TypePrinter { buf: String::new() };
+    p.print_path(path, false);
+    p.buf
+}
+
+struct Printer<'a> {
+    body: &'a Body,
+    annotations: &'a dyn Annotations,
+    buf: String,
+    indent_level: usize,
+}
+
+impl Write for Printer<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.buf.push('\n');
+            }
+            if !line.is_empty() && self.buf.ends_with('\n') {
+                for _ in 0..self.indent_level {
+                    self.buf.push_str("    ");
+                }
+            }
+            self.buf.push_str(line);
+        }
+        Ok(())
+    }
+}
+
+impl Printer<'_> {
+    fn indented(&mut self, f: impl FnOnce(&mut Self)) {
+        self.indent_level += 1;
+        f(self);
+        self.indent_level -= 1;
+    }
+
+    fn print_sep<T>(&mut self, items: &[T], sep: &str, mut f: impl FnMut(&mut Self, &T)) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                w!(self, "{}", sep);
+            }
+            f(self, item);
+        }
+    }
+
+    fn print_annotation(&mut self, annotation: Option<String>) {
+        if let Some(annotation) = annotation {
+            w!(self, " /* {} */", annotation);
+        }
+    }
+
+    fn print_type_ref(&mut self, type_ref: &TypeRef) {
+        w!(self, "{}", print_type_ref(type_ref));
+    }
+
+    fn print_path(&mut self, path: &Path) {
+        let mut p = TypePrinter { buf: String::new() };
+        p.print_path(path, true);
+        w!(self, "{}", p.buf);
+    }
+
+    fn print_expr(&mut self, expr: ExprId) {
+        let body = self.body;
+        match &body[expr] {
+            Expr::Missing => w!(self, "{{missing}}"),
+            Expr::Path(path) => {
+                self.print_path(path);
+                self.print_annotation(self.annotations.expr(expr));
+            }
+            Expr::If { condition, then_branch, else_branch } => {
+                w!(self, "if ");
+                self.print_expr(*condition);
+                w!(self, " ");
+                self.print_expr(*then_branch);
+                if let Some(else_branch) = else_branch {
+                    w!(self, " else ");
+                    self.print_expr(*else_branch);
+                }
+            }
+            Expr::Block { statements, tail } => {
+                if statements.is_empty() && tail.is_none() {
+                    w!(self, "{{}}");
+                    return;
+                }
+                w!(self, "{{");
+                self.indented(|p| {
+                    for stmt in statements {
+                        w!(p, "\n");
+                        p.print_stmt(stmt);
+                    }
+                    if let Some(tail) = tail {
+                        w!(p, "\n");
+                        p.print_expr(*tail);
+                    }
+                });
+                w!(self, "\n}}");
+            }
+            Expr::Loop { body } => {
+                w!(self, "loop ");
+                self.print_expr(*body);
+            }
+            Expr::While { condition, body } => {
+                w!(self, "while ");
+                self.print_expr(*condition);
+                w!(self, " ");
+                self.print_expr(*body);
+            }
+            Expr::For { iterable, pat, body } => {
+                w!(self, "for ");
+                self.print_pat(*pat);
+                w!(self, " in ");
+                self.print_expr(*iterable);
+                w!(self, " ");
+                self.print_expr(*body);
+            }
+            Expr::Call { callee, args } => {
+                self.print_postfix_operand(*callee);
+                w!(self, "(");
+                self.print_sep(args, ", ", |p, &arg| p.print_expr(arg));
+                w!(self, ")");
+            }
+            Expr::MethodCall { receiver, method_name, args, generic_args } => {
+                self.print_postfix_operand(*receiver);
+                w!(self, ".{}", method_name);
+                if let Some(generic_args) = generic_args {
+                    let mut p = TypePrinter { buf: String::new() };
+                    p.print_generic_args(generic_args);
+                    w!(self, "::{}", p.buf);
+                }
+                w!(self, "(");
+                self.print_sep(args, ", ", |p, &arg| p.print_expr(arg));
+                w!(self, ")");
+                self.print_annotation(self.annotations.expr(expr));
+            }
+            Expr::Match { expr, arms } => {
+                w!(self, "match ");
+                self.print_expr(*expr);
+                w!(self, " {{");
+                self.indented(|p| {
+                    for arm in arms {
+                        w!(p, "\n");
+                        p.print_pat(arm.pat);
+                        if let Some(guard) = arm.guard {
+                            w!(p, " if ");
+                            p.print_expr(guard);
+                        }
+                        w!(p, " => ");
+                        p.print_expr(arm.expr);
+                        w!(p, ",");
+                    }
+                });
+                w!(self, "\n}}");
+            }
+            Expr::Continue => w!(self, "continue"),
+            Expr::Break { expr } => {
+                w!(self, "break");
+                if let Some(expr) = expr {
+                    w!(self, " ");
+                    self.print_expr(*expr);
+                }
+            }
+            Expr::Return { expr } => {
+                w!(self, "return");
+                if let Some(expr) = expr {
+                    w!(self, " ");
+                    self.print_expr(*expr);
+                }
+            }
+            Expr::RecordLit { path, fields, spread } => {
+                match path {
+                    Some(path) => self.print_path(path),
+                    None => w!(self, "{{missing}}"),
+                }
+                if fields.is_empty() && spread.is_none() {
+                    w!(self, " {{}}");
+                    return;
+                }
+                w!(self, " {{ ");
+                self.print_sep(fields, ", ", |p, field| {
+                    w!(p, "{}: ", field.name);
+                    p.print_expr(field.expr);
+                });
+                if let Some(spread) = spread {
+                    if !fields.is_empty() {
+                        w!(self, ", ");
+                    }
+                    w!(self, "..");
+                    self.print_expr(*spread);
+                }
+                w!(self, " }}");
+            }
+            Expr::Field { expr, name } => {
+                self.print_postfix_operand(*expr);
+                w!(self, ".{}", name);
+            }
+            Expr::Await { expr } => {
+                self.print_postfix_operand(*expr);
+                w!(self, ".await");
+            }
+            Expr::Try { expr } => {
+                self.print_postfix_operand(*expr);
+                w!(self, "?");
+            }
+            Expr::TryBlock { body } => {
+                w!(self, "try ");
+                self.print_expr(*body);
+            }
+            Expr::Async { body } => {
+                w!(self, "async ");
+                self.print_expr(*body);
+            }
+            Expr::Cast { expr, type_ref } => {
+                self.print_operand(*expr);
+                w!(self, " as ");
+                self.print_type_ref(type_ref);
+            }
+            Expr::Ref { expr, mutability } => {
+                w!(self, "&{}", mutability.as_keyword_for_ref());
+                self.print_operand(*expr);
+            }
+            Expr::Box { expr } => {
+                w!(self, "box ");
+                self.print_operand(*expr);
+            }
+            Expr::UnaryOp { expr, op } => {
+                let op = match op {
+                    UnaryOp::Deref => "*",
+                    UnaryOp::Not => "!",
+                    UnaryOp::Neg => "-",
+                };
+                w!(self, "{}", op);
+                self.print_operand(*expr);
+            }
+            Expr::BinaryOp { lhs, rhs, op } => {
+                self.print_operand(*lhs);
+                match op {
+                    Some(op) => w!(self, " {} ", binary_op_str(*op)),
+                    None => w!(self, " {{missing}} "),
+                }
+                self.print_operand(*rhs);
+            }
+            Expr::Range { lhs, rhs, range_type } => {
+                if let Some(lhs) = lhs {
+                    self.print_operand(*lhs);
+                }
+                match range_type {
+                    RangeOp::Exclusive => w!(self, ".."),
+                    RangeOp::Inclusive => w!(self, "..="),
+                }
+                if let Some(rhs) = rhs {
+                    self.print_operand(*rhs);
+                }
+            }
+            Expr::Index { base, index } => {
+                self.print_postfix_operand(*base);
+                w!(self, "[");
+                self.print_expr(*index);
+                w!(self, "]");
+            }
+            Expr::Lambda { args, arg_types, ret_type, body, is_move } => {
+                if *is_move {
+                    w!(self, "move ");
+                }
+                w!(self, "|");
+                for (i, (&arg, arg_type)) in args.iter().zip(arg_types).enumerate() {
+                    if i > 0 {
+                        w!(self, ", ");
+                    }
+                    self.print_pat(arg);
+                    if let Some(arg_type) = arg_type {
+                        w!(self, ": ");
+                        self.print_type_ref(arg_type);
+                    }
+                }
+                w!(self, "|");
+                if let Some(ret_type) = ret_type {
+                    w!(self, " -> ");
+                    self.print_type_ref(ret_type);
+                }
+                w!(self, " ");
+                self.print_expr(*body);
+            }
+            Expr::Tuple { exprs } => {
+                w!(self, "(");
+                self.print_sep(exprs, ", ", |p, &expr| p.print_expr(expr));
+                if exprs.len() == 1 {
+                    w!(self, ",");
+                }
+                w!(self, ")");
+            }
+            Expr::Array(Array::ElementList(exprs)) => {
+                w!(self, "[");
+                self.print_sep(exprs, ", ", |p, &expr| p.print_expr(expr));
+                w!(self, "]");
+            }
+            Expr::Array(Array::Repeat { initializer, repeat }) => {
+                w!(self, "[");
+                self.print_expr(*initializer);
+                w!(self, "; ");
+                self.print_expr(*repeat);
+                w!(self, "]");
+            }
+            Expr::Literal(literal) => self.print_literal(literal),
+        }
+    }
+
+    fn print_stmt(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let { pat, type_ref, initializer } => {
+                w!(self, "let ");
+                self.print_pat(*pat);
+                if let Some(type_ref) = type_ref {
+                    w!(self, ": ");
+                    self.print_type_ref(type_ref);
+                }
+                if let Some(initializer) = initializer {
+                    w!(self, " = ");
+                    self.print_expr(*initializer);
+                }
+                w!(self, ";");
+            }
+            Statement::Expr(expr) => {
+                self.print_expr(*expr);
+                w!(self, ";");
+            }
+        }
+    }
+
+    /// Prints an operand of a binary or prefix operator or of a cast.
+    fn print_operand(&mut self, expr: ExprId) {
+        let needs_parens = match &self.body[expr] {
+            Expr::BinaryOp { .. }
+            | Expr::Range { .. }
+            | Expr::Cast { .. }
+            | Expr::Lambda { .. }
+            | Expr::Break { .. }
+            | Expr::Return { .. } => true,
+            _ => false,
+        };
+        self.print_in_parens_if(needs_parens, expr);
+    }
+
+    /// Prints the expression a call, field access, index or `?` applies to.
+    fn print_postfix_operand(&mut self, expr: ExprId) {
+        let needs_parens = match &self.body[expr] {
+            Expr::BinaryOp { .. }
+            | Expr::Range { .. }
+            | Expr::Cast { .. }
+            | Expr::Lambda { .. }
+            | Expr::Break { .. }
+            | Expr::Return { .. }
+            | Expr::Ref { .. }
+            | Expr::Box { .. }
+            | Expr::UnaryOp { .. } => true,
+            _ => false,
+        };
+        self.print_in_parens_if(needs_parens, expr);
+    }
+
+    fn print_in_parens_if(&mut self, needs_parens: bool, expr: ExprId) {
+        if needs_parens {
+            w!(self, "(");
+        }
+        self.print_expr(expr);
+        if needs_parens {
+            w!(self, ")");
+        }
+    }
+
+    fn print_pat(&mut self, pat: PatId) {
+        let body = self.body;
+        match &body[pat] {
+            Pat::Missing => w!(self, "{{missing}}"),
+            Pat::Wild => w!(self, "_"),
+            Pat::Tuple { args, ellipsis } => {
+                w!(self, "(");
+                self.print_pats_with_ellipsis(args, *ellipsis);
+                if args.len() == 1 && ellipsis.is_none() {
+                    w!(self, ",");
+                }
+                w!(self, ")");
+            }
+            Pat::Or(pats) => self.print_sep(pats, " | ", |p, &pat| p.print_pat(pat)),
+            Pat::Record { path, args, ellipsis } => {
+                match path {
+                    Some(path) => self.print_path(path),
+                    None => w!(self, "{{missing}}"),
+                }
+                if args.is_empty() && !ellipsis {
+                    w!(self, " {{}}");
+                    return;
+                }
+                w!(self, " {{ ");
+                self.print_sep(args, ", ", |p, field| {
+                    w!(p, "{}: ", field.name);
+                    p.print_pat(field.pat);
+                });
+                if *ellipsis {
+                    if !args.is_empty() {
+                        w!(self, ", ");
+                    }
+                    w!(self, "..");
+                }
+                w!(self, " }}");
+            }
+            Pat::Range { start, end } => {
+                self.print_expr(*start);
+                w!(self, "..=");
+                self.print_expr(*end);
+            }
+            Pat::Slice { prefix, slice, suffix } => {
+                w!(self, "[");
+                let pats: Vec<PatId> =
+                    prefix.iter().chain(slice.iter()).chain(suffix.iter()).copied().collect();
+                self.print_sep(&pats, ", ", |p, &pat| p.print_pat(pat));
+                w!(self, "]");
+            }
+            Pat::Path(path) => self.print_path(path),
+            Pat::Lit(expr) => self.print_expr(*expr),
+            Pat::Bind { mode, name, subpat } => {
+                let mode = match mode {
+                    BindingAnnotation::Unannotated => "",
+                    BindingAnnotation::Mutable => "mut ",
+                    BindingAnnotation::Ref => "ref ",
+                    BindingAnnotation::RefMut => "ref mut ",
+                };
+                w!(self, "{}{}", mode, name);
+                self.print_annotation(self.annotations.pat(pat));
+                if let Some(subpat) = subpat {
+                    w!(self, " @ ");
+                    self.print_pat(*subpat);
+                }
+            }
+            Pat::TupleStruct { path, args, ellipsis } => {
+                match path {
+                    Some(path) => self.print_path(path),
+                    None => w!(self, "{{missing}}"),
+                }
+                w!(self, "(");
+                self.print_pats_with_ellipsis(args, *ellipsis);
+                w!(self, ")");
+            }
+            Pat::Ref { pat, mutability } => {
+                w!(self, "&{}", mutability.as_keyword_for_ref());
+                self.print_pat(*pat);
+            }
+        }
+    }
+
+    /// Prints the fields of a tuple pattern, with the `..` at the index it
+    /// was lowered from.
+    fn print_pats_with_ellipsis(&mut self, pats: &[PatId], ellipsis: Option<usize>) {
+        for (i, &pat) in pats.iter().enumerate() {
+            if i > 0 {
+                w!(self, ", ");
+            }
+            if ellipsis == Some(i) {
+                w!(self, ".., ");
+            }
+            self.print_pat(pat);
+        }
+        if let Some(ellipsis) = ellipsis {
+            if ellipsis >= pats.len() {
+                w!(self, "{}..", if pats.is_empty() { "" } else { ", " });
+            }
+        }
+    }
+
+    fn print_literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::String(it) => w!(self, "{:?}", it),
+            Literal::ByteString(it) => {
+                let escaped: String = it
+                    .iter()
+                    .flat_map(|&b| std::ascii::escape_default(b))
+                    .map(char::from)
+                    .collect();
+                w!(self, "b\"{}\"", escaped);
+            }
+            Literal::Char(it) => w!(self, "{:?}", it),
+            Literal::Bool(it) => w!(self, "{}", it),
+            Literal::Int(value, suffix) => {
+                w!(self, "{}", value);
+                if let Some(suffix) = suffix {
+                    w!(self, "{}", BuiltinType::Int(*suffix));
+                }
+            }
+            Literal::Float(bits, suffix) => {
+                w!(self, "{:?}", f64::from_bits(*bits));
+                if let Some(suffix) = suffix {
+                    w!(self, "{}", BuiltinType::Float(*suffix));
+                }
+            }
+        }
+    }
+}
+
+fn binary_op_str(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::LogicOp(LogicOp::And) => "&&",
+        BinaryOp::LogicOp(LogicOp::Or) => "||",
+        BinaryOp::CmpOp(CmpOp::Eq { negated: false }) => "==",
+        BinaryOp::CmpOp(CmpOp::Eq { negated: true }) => "!=",
+        BinaryOp::CmpOp(CmpOp::Ord { ordering: Ordering::Less, strict: true }) => "<",
+        BinaryOp::CmpOp(CmpOp::Ord { ordering: Ordering::Less, strict: false }) => "<=",
+        BinaryOp::CmpOp(CmpOp::Ord { ordering: Ordering::Greater, strict: true }) => ">",
+        BinaryOp::CmpOp(CmpOp::Ord { ordering: Ordering::Greater, strict: false }) => ">=",
+        BinaryOp::ArithOp(op) => arith_op_str(op),
+        BinaryOp::Assignment { op: None } => "=",
+        BinaryOp::Assignment { op: Some(op) } => match op {
+            ArithOp::Add => "+=",
+            ArithOp::Mul => "*=",
+            ArithOp::Sub => "-=",
+            ArithOp::Div => "/=",
+            ArithOp::Rem => "%=",
+            ArithOp::Shl => "<<=",
+            ArithOp::Shr => ">>=",
+            ArithOp::BitXor => "^=",
+            ArithOp::BitOr => "|=",
+            ArithOp::BitAnd => "&=",
+        },
+    }
+}
+
+fn arith_op_str(op: ArithOp) -> &'static str {
+    match op {
+        ArithOp::Add => "+",
+        ArithOp::Mul => "*",
+        ArithOp::Sub => "-",
+        ArithOp::Div => "/",
+        ArithOp::Rem => "%",
+        ArithOp::Shl => "<<",
+        ArithOp::Shr => ">>",
+        ArithOp::BitXor => "^",
+        ArithOp::BitOr => "|",
+        ArithOp::BitAnd => "&",
+    }
+}
+
+/// Prints types and paths, which don't need the body.
+struct TypePrinter {
+    buf: String,
+}
+
+impl TypePrinter {
+    fn print_type_ref(&mut self, type_ref: &TypeRef) {
+        match type_ref {
+            TypeRef::Never => self.buf.push('!'),
+            TypeRef::Placeholder => self.buf.push('_'),
+            TypeRef::Tuple(fields) => {
+                self.buf.push('(');
+                self.print_type_refs(fields);
+                if fields.len() == 1 {
+                    self.buf.push(',');
+                }
+                self.buf.push(')');
+            }
+            TypeRef::Path(path) => self.print_path(path, false),
+            TypeRef::RawPtr(inner, mutability) => {
+                self.buf.push('*');
+                self.buf.push_str(mutability.as_keyword_for_ptr());
+                self.print_type_ref(inner);
+            }
+            TypeRef::Reference(inner, mutability) => {
+                self.buf.push('&');
+                self.buf.push_str(mutability.as_keyword_for_ref());
+                self.print_type_ref(inner);
+            }
+            TypeRef::Array(inner, len) => {
+                self.buf.push('[');
+                self.print_type_ref(inner);
+                self.buf.push_str("; ");
+                self.print_const_expr(len);
+                self.buf.push(']');
+            }
+            TypeRef::Slice(inner) => {
+                self.buf.push('[');
+                self.print_type_ref(inner);
+                self.buf.push(']');
+            }
+            TypeRef::Fn(params_and_ret, abi) => {
+                if *abi != Abi::Rust {
+                    let _ = write!(self.buf, "extern {:?} ", abi.name());
+                }
+                let (ret, params) = params_and_ret.split_last().expect("fn pointer without ret");
+                self.buf.push_str("fn(");
+                self.print_type_refs(params);
+                self.buf.push(')');
+                if *ret != TypeRef::unit() {
+                    self.buf.push_str(" -> ");
+                    self.print_type_ref(ret);
+                }
+            }
+            TypeRef::ImplTrait(bounds) => {
+                self.buf.push_str("impl ");
+                self.print_bounds(bounds);
+            }
+            TypeRef::DynTrait(bounds) => {
+                self.buf.push_str("dyn ");
+                self.print_bounds(bounds);
+            }
+            TypeRef::Error => self.buf.push_str("{error}"),
+        }
+    }
+
+    fn print_const_expr(&mut self, expr: &ConstExpr) {
+        match expr {
+            ConstExpr::Int(it) => self.buf.push_str(&it.to_string()),
+            ConstExpr::Path(path) => self.print_path(path, true),
+            ConstExpr::BinaryOp { lhs, op, rhs } => {
+                self.buf.push('(');
+                self.print_const_expr(lhs);
+                self.buf.push(' ');
+                self.buf.push_str(arith_op_str(*op));
+                self.buf.push(' ');
+                self.print_const_expr(rhs);
+                self.buf.push(')');
+            }
+            ConstExpr::Unknown => self.buf.push('_'),
+        }
+    }
+
+    fn print_type_refs(&mut self, type_refs: &[TypeRef]) {
+        for (i, type_ref) in type_refs.iter().enumerate() {
+            if i > 0 {
+                self.buf.push_str(", ");
+            }
+            self.print_type_ref(type_ref);
+        }
+    }
+
+    fn print_bounds(&mut self, bounds: &[TypeBound]) {
+        for (i, bound) in bounds.iter().enumerate() {
+            if i > 0 {
+                self.buf.push_str(" + ");
+            }
+            match bound {
+                TypeBound::Path(path) => self.print_path(path, false),
+                TypeBound::Lifetime(name) => self.buf.push_str(name),
+                TypeBound::Error => self.buf.push_str("{error}"),
+            }
+        }
+    }
+
+    /// Prints `path`, using the turbofish for generic args in expressions.
+    /// Qualified paths like `<T as Trait>::Item` are lowered to the trait
+    /// path with `T` as its first generic arg, and are printed like written.
+    fn print_path(&mut self, path: &Path, in_expr: bool) {
+        let segments = path.segments();
+        let self_segment = segments
+            .iter()
+            .position(|it| it.args_and_bindings.map_or(false, |it| it.has_self_type));
+        if let Some(type_anchor) = path.type_anchor() {
+            self.buf.push('<');
+            self.print_type_ref(type_anchor);
+            self.buf.push_str(">::");
+        } else if let Some(idx) = self_segment {
+            let args = segments.get(idx).and_then(|it| it.args_and_bindings);
+            if let Some(GenericArg::Type(self_ty)) = args.and_then(|it| it.args.first()) {
+                self.buf.push('<');
+                self.print_type_ref(self_ty);
+                self.buf.push_str(" as ");
+            }
+        }
+        match path.kind() {
+            PathKind::Plain => {}
+            PathKind::Super(0) => self.buf.push_str("self::"),
+            PathKind::Super(n) => {
+                for _ in 0..*n {
+                    self.buf.push_str("super::");
+                }
+            }
+            PathKind::Crate => self.buf.push_str("crate::"),
+            PathKind::Abs => self.buf.push_str("::"),
+            PathKind::DollarCrate(_) => self.buf.push_str("$crate::"),
+        }
+        for (i, segment) in segments.iter().enumerate() {
+            if i > 0 {
+                self.buf.push_str("::");
+            }
+            let _ = write!(self.buf, "{}", segment.name);
+            let args = segment
+                .args_and_bindings
+                .filter(|it| it.args.len() > it.has_self_type as usize || !it.bindings.is_empty());
+            if let Some(args) = args {
+                if in_expr && self_segment != Some(i) {
+                    self.buf.push_str("::");
+                }
+                self.print_generic_args(args);
+            }
+            if self_segment == Some(i) {
+                self.buf.push('>');
+            }
+        }
+    }
+
+    fn print_generic_args(&mut self, generic_args: &GenericArgs) {
+        self.buf.push('<');
+        let args = generic_args.args.iter().skip(generic_args.has_self_type as usize);
+        let mut first = true;
+        for GenericArg::Type(type_ref) in args {
+            if !first {
+                self.buf.push_str(", ");
+            }
+            first = false;
+            self.print_type_ref(type_ref);
+        }
+        for (name, type_ref) in &generic_args.bindings {
+            if !first {
+                self.buf.push_str(", ");
+            }
+            first = false;
+            let _ = write!(self.buf, "{} = ", name);
+            self.print_type_ref(type_ref);
+        }
+        self.buf.push('>');
+    }
+}
@@ -142,6 +142,22 @@ impl BuiltinInt {
         };
         Some(res)
     }
+
+    /// Returns `true` if `value` can be represented by this type. `isize` and
+    /// `usize` are assumed to be 64 bits wide.
+    pub fn fits(self, value: i128) -> bool {
+        let bits = match self.bitness {
+            IntBitness::X8 => 8,
+            IntBitness::X16 => 16,
+            IntBitness::X32 => 32,
+            IntBitness::Xsize | IntBitness::X64 => 64,
+            IntBitness::X128 => return self.signedness == Signedness::Signed || value >= 0,
+        };
+        match self.signedness {
+            Signedness::Signed => -(1 << (bits - 1)) <= value && value < 1 << (bits - 1),
+            Signedness::Unsigned => 0 <= value && value < 1 << bits,
+        }
+    }
 }
 
 #[rustfmt::skip]
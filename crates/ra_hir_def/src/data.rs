@@ -15,9 +15,10 @@ use ra_syntax::ast::{
 use crate::{
     attr::Attrs,
     db::DefDatabase,
+    intern::Interned,
     path::{path, GenericArgs, Path},
     src::HasSource,
-    type_ref::{Mutability, TypeBound, TypeRef},
+    type_ref::{Abi, Mutability, TypeBound, TypeRef},
     visibility::RawVisibility,
     AssocContainerId, AssocItemId, ConstId, ConstLoc, Expander, FunctionId, FunctionLoc, HasModule,
     ImplId, Intern, Lookup, StaticId, TraitId, TypeAliasId, TypeAliasLoc,
@@ -26,12 +27,18 @@ use crate::{
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FunctionData {
     pub name: Name,
-    pub params: Vec<TypeRef>,
-    pub ret_type: TypeRef,
+    pub params: Vec<Interned<TypeRef>>,
+    pub ret_type: Interned<TypeRef>,
     pub attrs: Attrs,
     /// True if the first param is `self`. This is relevant to decide whether this
     /// can be called as a method.
     pub has_self_param: bool,
+    pub is_unsafe: bool,
+    pub is_const: bool,
+    pub abi: Abi,
+    /// True for C functions taking variable arguments, like
+    /// `fn printf(format: *const c_char, ...)`.
+    pub is_varargs: bool,
     pub visibility: RawVisibility,
 }
 
@@ -42,6 +49,7 @@ impl FunctionData {
         let name = src.value.name().map(|n| n.as_name()).unwrap_or_else(Name::missing);
         let mut params = Vec::new();
         let mut has_self_param = false;
+        let mut is_varargs = false;
         if let Some(param_list) = src.value.param_list() {
             if let Some(self_param) = param_list.self_param() {
                 let self_type = if let Some(type_ref) = self_param.ascribed_type() {
@@ -58,12 +66,16 @@ impl FunctionData {
                         }
                     }
                 };
-                params.push(self_type);
+                params.push(Interned::new(self_type));
                 has_self_param = true;
             }
             for param in param_list.params() {
+                if param.dotdotdot_token().is_some() {
+                    is_varargs = true;
+                    continue;
+                }
                 let type_ref = TypeRef::from_ast_opt(param.ascribed_type());
-                params.push(type_ref);
+                params.push(Interned::new(type_ref));
             }
         }
         let attrs = Attrs::new(&src.value, &Hygiene::new(db.upcast(), src.file_id));
@@ -81,12 +93,34 @@ impl FunctionData {
         } else {
             ret_type
         };
+        let ret_type = Interned::new(ret_type);
 
         let vis_default = RawVisibility::default_for_container(loc.container);
         let visibility =
             RawVisibility::from_ast_with_default(db, vis_default, src.map(|s| s.visibility()));
 
-        let sig = FunctionData { name, params, ret_type, has_self_param, visibility, attrs };
+        // Functions in `extern` blocks are unsafe to call, and take the ABI
+        // of the block.
+        let extern_block =
+            src.value.syntax().parent().and_then(|it| it.parent()).and_then(ast::ExternBlock::cast);
+        let is_unsafe = src.value.unsafe_token().is_some() || extern_block.is_some();
+        let is_const = src.value.const_token().is_some();
+        let abi = match extern_block {
+            Some(block) => Abi::from_ast(block.abi()),
+            None => Abi::from_ast(src.value.abi()),
+        };
+        let sig = FunctionData {
+            name,
+            params,
+            ret_type,
+            has_self_param,
+            is_unsafe,
+            is_const,
+            abi,
+            is_varargs,
+            visibility,
+            attrs,
+        };
         Arc::new(sig)
     }
 }
@@ -8,6 +8,8 @@ use ra_syntax::{ast, AstPtr, SyntaxNodePtr};
 
 use hir_expand::{HirFileId, InFile};
 
+use crate::builtin_type::{BuiltinInt, BuiltinType};
+
 #[derive(Debug)]
 pub struct UnresolvedModule {
     pub file: HirFileId,
@@ -16,6 +18,9 @@ pub struct UnresolvedModule {
 }
 
 impl Diagnostic for UnresolvedModule {
+    fn code(&self) -> &'static str {
+        "unresolved-module"
+    }
     fn message(&self) -> String {
         "unresolved module".to_string()
     }
@@ -26,3 +31,30 @@ impl Diagnostic for UnresolvedModule {
         self
     }
 }
+
+#[derive(Debug)]
+pub struct DiscriminantOverflow {
+    pub file: HirFileId,
+    pub variant: AstPtr<ast::EnumVariant>,
+    pub value: i128,
+    pub repr: BuiltinInt,
+}
+
+impl Diagnostic for DiscriminantOverflow {
+    fn code(&self) -> &'static str {
+        "discriminant-overflow"
+    }
+    fn message(&self) -> String {
+        format!(
+            "discriminant value `{}` does not fit in `{}`",
+            self.value,
+            BuiltinType::Int(self.repr)
+        )
+    }
+    fn source(&self) -> InFile<SyntaxNodePtr> {
+        InFile { file_id: self.file, value: self.variant.clone().into() }
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
@@ -370,6 +370,17 @@ mod tests {
         check_found_path(code, "std_renamed::S");
     }
 
+    #[test]
+    fn different_crate_renamed_in_dependencies() {
+        let code = r#"
+            //- /main.rs crate:main deps:renamed=dep
+            <|>
+            //- /dep.rs crate:dep
+            pub struct S;
+        "#;
+        check_found_path(code, "renamed::S");
+    }
+
     #[test]
     fn same_crate_reexport() {
         let code = r#"
@@ -0,0 +1,118 @@
+//! Global interning of the values which are duplicated a lot, like the types
+//! in the signatures of the functions and the fields.
+//!
+//! Structurally equal values share the same allocation, which is freed when
+//! the last `Interned` pointing to it is dropped. Equality and hashing of an
+//! `Interned` only look at the pointer.
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use rustc_hash::FxHashSet;
+
+use crate::type_ref::TypeRef;
+
+pub struct Interned<T: Internable> {
+    arc: Arc<T>,
+}
+
+impl<T: Internable> Interned<T> {
+    pub fn new(obj: T) -> Interned<T> {
+        let mut storage = T::storage().lock().unwrap();
+        if let Some(arc) = storage.get(&obj) {
+            return Interned { arc: arc.clone() };
+        }
+        let arc = Arc::new(obj);
+        storage.insert(arc.clone());
+        Interned { arc }
+    }
+}
+
+impl<T: Internable> Drop for Interned<T> {
+    fn drop(&mut self) {
+        // The storage holds the other reference. The count is checked again
+        // with the lock held, as another thread may have interned an equal
+        // value meanwhile.
+        if Arc::strong_count(&self.arc) == 2 {
+            let mut storage = T::storage().lock().unwrap();
+            if Arc::strong_count(&self.arc) == 2 {
+                storage.remove(&*self.arc);
+            }
+        }
+    }
+}
+
+impl<T: Internable> Clone for Interned<T> {
+    fn clone(&self) -> Interned<T> {
+        Interned { arc: self.arc.clone() }
+    }
+}
+
+impl<T: Internable> PartialEq for Interned<T> {
+    fn eq(&self, other: &Interned<T>) -> bool {
+        Arc::ptr_eq(&self.arc, &other.arc)
+    }
+}
+
+impl<T: Internable> Eq for Interned<T> {}
+
+impl<T: Internable> Hash for Interned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (&*self.arc as *const T).hash(state)
+    }
+}
+
+impl<T: Internable> Deref for Interned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.arc
+    }
+}
+
+impl<T: Internable + fmt::Debug> fmt::Debug for Interned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (*self.arc).fmt(f)
+    }
+}
+
+pub trait Internable: Hash + Eq + Sized + 'static {
+    fn storage() -> &'static Mutex<FxHashSet<Arc<Self>>>;
+}
+
+macro_rules! impl_internable {
+    ($($t:ty),+ $(,)?) => { $(
+        impl Internable for $t {
+            fn storage() -> &'static Mutex<FxHashSet<Arc<Self>>> {
+                static STORAGE: once_cell::sync::Lazy<Mutex<FxHashSet<Arc<$t>>>> =
+                    once_cell::sync::Lazy::new(Default::default);
+                &STORAGE
+            }
+        }
+    )+ };
+}
+
+impl_internable!(TypeRef);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::type_ref::Mutability;
+
+    #[test]
+    fn test_equal_values_share_storage() {
+        let unit = || TypeRef::Reference(Box::new(TypeRef::unit()), Mutability::Shared);
+        let a = Interned::new(unit());
+        let b = Interned::new(unit());
+        assert_eq!(a, b);
+        assert!(std::ptr::eq(&*a, &*b));
+        assert_eq!(*a, unit());
+
+        let c = Interned::new(TypeRef::Never);
+        assert_ne!(a, c);
+    }
+}
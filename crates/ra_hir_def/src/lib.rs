@@ -17,6 +17,7 @@ pub mod db;
 pub mod attr;
 pub mod path;
 pub mod type_ref;
+pub mod intern;
 pub mod builtin_type;
 pub mod diagnostics;
 pub mod per_ns;
@@ -150,7 +151,7 @@ pub struct StaticId(salsa::InternId);
 pub type StaticLoc = ItemLoc<ast::StaticDef>;
 impl_intern!(StaticId, StaticLoc, intern_static, lookup_intern_static);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TraitId(salsa::InternId);
 pub type TraitLoc = ItemLoc<ast::TraitDef>;
 impl_intern!(TraitId, TraitLoc, intern_trait, lookup_intern_trait);
@@ -10,8 +10,8 @@ use hir_expand::{
     proc_macro::ProcMacroExpander,
     HirFileId, MacroCallId, MacroDefId, MacroDefKind,
 };
-use ra_cfg::CfgOptions;
-use ra_db::{CrateId, FileId, ProcMacroId};
+use ra_cfg::{parse_cfg, CfgOptions};
+use ra_db::{CrateId, Edition, FileId, ProcMacroId};
 use ra_syntax::ast;
 use rustc_hash::FxHashMap;
 use test_utils::tested_by;
@@ -33,15 +33,23 @@ use crate::{
 
 pub(super) fn collect_defs(db: &dyn DefDatabase, mut def_map: CrateDefMap) -> CrateDefMap {
     let crate_graph = db.crate_graph();
+    let krate = &crate_graph[def_map.krate];
+    let no_std = is_no_std(db, krate.root_file_id, &krate.cfg_options);
 
     // populate external prelude
-    for dep in &crate_graph[def_map.krate].dependencies {
+    let mut extern_crates = FxHashMap::default();
+    for dep in &krate.dependencies {
         let dep_def_map = db.crate_def_map(dep.crate_id);
         log::debug!("crate dep {:?} -> {:?}", dep.name, dep.crate_id);
-        def_map.extern_prelude.insert(
-            dep.as_name(),
-            ModuleId { krate: dep.crate_id, local_id: dep_def_map.root }.into(),
-        );
+        let dep_root = ModuleId { krate: dep.crate_id, local_id: dep_def_map.root }.into();
+
+        // `#![no_std]` crates only get `core` and its prelude, `alloc` and
+        // `std` have to be declared with `extern crate`.
+        if no_std && (dep.name.as_str() == "std" || dep.name.as_str() == "alloc") {
+            extern_crates.insert(dep.as_name(), dep_root);
+            continue;
+        }
+        def_map.extern_prelude.insert(dep.as_name(), dep_root);
 
         // look for the prelude
         // If the dependency defines a prelude, we overwrite an already defined
@@ -52,8 +60,12 @@ pub(super) fn collect_defs(db: &dyn DefDatabase, mut def_map: CrateDefMap) -> Cr
         }
     }
 
-    let cfg_options = &crate_graph[def_map.krate].cfg_options;
-    let proc_macros = &crate_graph[def_map.krate].proc_macro;
+    if let Some(prelude) = def_map.prelude {
+        def_map.prelude = Some(edition_prelude(db, prelude, krate.edition));
+    }
+
+    let cfg_options = &krate.cfg_options;
+    let proc_macros = &krate.proc_macro;
     let proc_macros = proc_macros
         .iter()
         .enumerate()
@@ -76,11 +88,53 @@ pub(super) fn collect_defs(db: &dyn DefDatabase, mut def_map: CrateDefMap) -> Cr
         mod_dirs: FxHashMap::default(),
         cfg_options,
         proc_macros,
+        extern_crates,
     };
     collector.collect();
     collector.finish()
 }
 
+/// Newer standard libraries have a prelude per edition, like
+/// `std::prelude::rust_2021`, next to the `v1` prelude they import themselves.
+/// We only look for it if the imported prelude is such a `v1` module, and keep
+/// `v1` if the edition has no prelude of its own.
+fn edition_prelude(db: &dyn DefDatabase, prelude: ModuleId, edition: Edition) -> ModuleId {
+    let def_map = db.crate_def_map(prelude.krate);
+    let parent = match def_map[prelude.local_id].parent {
+        Some(it) => &def_map[it],
+        None => return prelude,
+    };
+    if parent.children.get(&name![v1]) != Some(&prelude.local_id) {
+        return prelude;
+    }
+    let name = match edition {
+        Edition::Edition2015 => name![rust_2015],
+        Edition::Edition2018 => name![rust_2018],
+        Edition::Edition2021 => name![rust_2021],
+    };
+    parent
+        .children
+        .get(&name)
+        .map_or(prelude, |&local_id| ModuleId { krate: prelude.krate, local_id })
+}
+
+/// Whether the crate root has a `#![no_std]` attribute, possibly behind a
+/// `cfg_attr`, like `#![cfg_attr(not(feature = "std"), no_std)]`.
+fn is_no_std(db: &dyn DefDatabase, root: FileId, cfg_options: &CfgOptions) -> bool {
+    let raw_items = db.raw_items(root.into());
+    let attrs = raw_items.attrs();
+    if attrs.by_key("no_std").exists() {
+        return true;
+    }
+    attrs.by_key("cfg_attr").tt_values().any(|tt| {
+        let is_no_std = match tt.token_trees.last() {
+            Some(tt::TokenTree::Leaf(tt::Leaf::Ident(ident))) => ident.text == "no_std",
+            _ => false,
+        };
+        is_no_std && cfg_options.check(&parse_cfg(tt)) == Some(true)
+    })
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum PartialResolvedImport {
     /// None of any namespaces is resolved
@@ -135,6 +189,9 @@ struct DefCollector<'a> {
     mod_dirs: FxHashMap<LocalModuleId, ModDir>,
     cfg_options: &'a CfgOptions,
     proc_macros: Vec<(Name, ProcMacroExpander)>,
+    /// The dependencies which are not in the extern prelude, but can be
+    /// declared with `extern crate`.
+    extern_crates: FxHashMap<Name, ModuleDefId>,
 }
 
 impl DefCollector<'_> {
@@ -293,7 +350,7 @@ impl DefCollector<'_> {
             self.def_map.edition,
         );
 
-        let res = self.def_map.resolve_name_in_extern_prelude(
+        let res = self.resolve_extern_crate(
             &import
                 .path
                 .as_ident()
@@ -351,6 +408,14 @@ impl DefCollector<'_> {
         }
     }
 
+    fn resolve_extern_crate(&self, name: &Name) -> PerNs {
+        let res = self.def_map.resolve_name_in_extern_prelude(name);
+        match self.extern_crates.get(name) {
+            Some(&it) if res.is_none() => PerNs::types(it, Visibility::Public),
+            _ => res,
+        }
+    }
+
     fn resolve_import(
         &self,
         module_id: LocalModuleId,
@@ -358,7 +423,7 @@ impl DefCollector<'_> {
     ) -> PartialResolvedImport {
         log::debug!("resolving import: {:?} ({:?})", import, self.def_map.edition);
         if import.is_extern_crate {
-            let res = self.def_map.resolve_name_in_extern_prelude(
+            let res = self.resolve_extern_crate(
                 &import
                     .path
                     .as_ident()
@@ -739,8 +804,8 @@ impl ModCollector<'_, '_> {
     }
 
     fn collect_module(&mut self, module: &raw::ModuleData, attrs: &Attrs) {
-        let path_attr = attrs.by_key("path").string_value();
-        let is_macro_use = attrs.by_key("macro_use").exists();
+        let path_attr = attrs.path();
+        let is_macro_use = attrs.is_macro_use();
         match module {
             // inline module, just recurse
             raw::ModuleData::Definition { name, visibility, items, ast_id } => {
@@ -897,21 +962,11 @@ impl ModCollector<'_, '_> {
     }
 
     fn collect_derives(&mut self, attrs: &Attrs, def: &raw::DefData) {
-        for derive_subtree in attrs.by_key("derive").tt_values() {
-            // for #[derive(Copy, Clone)], `derive_subtree` is the `(Copy, Clone)` subtree
-            for tt in &derive_subtree.token_trees {
-                let ident = match &tt {
-                    tt::TokenTree::Leaf(tt::Leaf::Ident(ident)) => ident,
-                    tt::TokenTree::Leaf(tt::Leaf::Punct(_)) => continue, // , is ok
-                    _ => continue, // anything else would be an error (which we currently ignore)
-                };
-                let path = ModPath::from_tt_ident(ident);
-
-                let ast_id = AstIdWithPath::new(self.file_id, def.kind.ast_id(), path);
-                self.def_collector
-                    .unexpanded_attribute_macros
-                    .push(DeriveDirective { module_id: self.module_id, ast_id });
-            }
+        for path in attrs.derives() {
+            let ast_id = AstIdWithPath::new(self.file_id, def.kind.ast_id(), path);
+            self.def_collector
+                .unexpanded_attribute_macros
+                .push(DeriveDirective { module_id: self.module_id, ast_id });
         }
     }
 
@@ -1013,6 +1068,7 @@ mod tests {
             mod_dirs: FxHashMap::default(),
             cfg_options: &CfgOptions::default(),
             proc_macros: Default::default(),
+            extern_crates: FxHashMap::default(),
         };
         collector.collect();
         collector.def_map
@@ -41,6 +41,8 @@ pub struct RawItems {
     impls: Arena<ImplData>,
     /// items for top-level module
     items: Vec<RawItem>,
+    /// The inner attributes of the file, like `#![no_std]`.
+    attrs: Attrs,
 }
 
 impl RawItems {
@@ -54,6 +56,7 @@ impl RawItems {
         };
         if let Some(node) = db.parse_or_expand(file_id) {
             if let Some(source_file) = ast::SourceFile::cast(node.clone()) {
+                collector.raw_items.attrs = Attrs::new(&source_file, &collector.hygiene);
                 collector.process_module(None, source_file);
             } else if let Some(item_list) = ast::MacroItems::cast(node) {
                 collector.process_module(None, item_list);
@@ -66,6 +69,10 @@ impl RawItems {
     pub(super) fn items(&self) -> &[RawItem] {
         &self.items
     }
+
+    pub(super) fn attrs(&self) -> &Attrs {
+        &self.attrs
+    }
 }
 
 impl Index<Idx<ModuleData>> for RawItems {
@@ -266,8 +273,10 @@ impl RawItemsCollector {
                 self.add_macro(current_module, it);
                 return;
             }
-            ast::ModuleItem::ExternBlock(_) => {
-                // FIXME: add extern block
+            ast::ModuleItem::ExternBlock(it) => {
+                if let Some(item_list) = it.extern_item_list() {
+                    self.process_module(current_module, item_list);
+                }
                 return;
             }
         };
@@ -350,7 +359,7 @@ impl RawItemsCollector {
             });
             let attrs = self.parse_attrs(&extern_crate);
             // FIXME: cfg_attr
-            let is_macro_use = extern_crate.has_atom_attr("macro_use");
+            let is_macro_use = attrs.is_macro_use();
             let import_data = ImportData {
                 path,
                 alias,
@@ -374,7 +383,7 @@ impl RawItemsCollector {
         let name = m.name().map(|it| it.as_name());
         let ast_id = self.source_ast_id_map.ast_id(&m);
         // FIXME: cfg_attr
-        let export = m.attrs().filter_map(|x| x.simple_name()).any(|name| name == "macro_export");
+        let export = attrs.is_macro_export();
 
         // FIXME: cfg_attr
         let builtin =
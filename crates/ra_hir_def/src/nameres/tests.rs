@@ -65,6 +65,26 @@ fn crate_def_map_smoke_test() {
     "###)
 }
 
+#[test]
+fn extern_block_items() {
+    let map = def_map(
+        r#"
+        //- /lib.rs
+        extern "C" {
+            fn malloc(size: usize) -> *mut u8;
+            static mut errno: i32;
+        }
+        use self::malloc as alloc;
+        "#,
+    );
+    assert_snapshot!(map, @r###"
+        ⋮crate
+        ⋮alloc: v
+        ⋮errno: v
+        ⋮malloc: v
+    "###)
+}
+
 #[test]
 fn crate_def_map_super_super() {
     let map = def_map(
@@ -464,6 +484,27 @@ fn reexport_across_crates() {
     "###);
 }
 
+#[test]
+fn renamed_dependency_is_in_extern_prelude_under_its_alias() {
+    let map = def_map(
+        "
+        //- /main.rs crate:main deps:renamed=dep
+        use renamed::Bar;
+        use dep::Baz;
+
+        //- /dep.rs crate:dep
+        pub struct Bar;
+        pub struct Baz;
+        ",
+    );
+
+    assert_snapshot!(map, @r###"
+        ⋮crate
+        ⋮Bar: t v
+        ⋮Baz: _
+    "###);
+}
+
 #[test]
 fn values_dont_shadow_extern_crates() {
     let map = def_map(
@@ -515,6 +556,143 @@ fn std_prelude_takes_precedence_above_core_prelude() {
     "###);
 }
 
+#[test]
+fn no_std_crates_use_core_prelude() {
+    let map = def_map(
+        r#"
+        //- /main.rs crate:main deps:core,alloc,std
+        #![no_std]
+        extern crate alloc;
+        use {Foo, Bar};
+        use alloc::Vec;
+        use std::Baz;
+
+        //- /core.rs crate:core
+        #[prelude_import]
+        pub use self::prelude::*;
+        mod prelude {
+            pub struct Bar;
+        }
+
+        //- /alloc.rs crate:alloc deps:core
+        pub struct Vec;
+
+        //- /std.rs crate:std deps:core
+        #[prelude_import]
+        pub use self::prelude::*;
+        mod prelude {
+            pub struct Foo;
+        }
+        pub struct Baz;
+        "#,
+    );
+
+    assert_snapshot!(map, @r###"
+        ⋮crate
+        ⋮Bar: t v
+        ⋮Baz: _
+        ⋮Foo: _
+        ⋮Vec: t v
+        ⋮alloc: t
+    "###);
+}
+
+#[test]
+fn no_std_behind_cfg_attr() {
+    let map = def_map(
+        r#"
+        //- /main.rs crate:main deps:core,std
+        #![cfg_attr(not(feature = "std"), no_std)]
+        use {Foo, Bar};
+
+        //- /core.rs crate:core
+        #[prelude_import]
+        pub use self::prelude::*;
+        mod prelude {
+            pub struct Bar;
+        }
+
+        //- /std.rs crate:std deps:core
+        #[prelude_import]
+        pub use self::prelude::*;
+        mod prelude {
+            pub struct Foo;
+        }
+        "#,
+    );
+
+    assert_snapshot!(map, @r###"
+        ⋮crate
+        ⋮Bar: t v
+        ⋮Foo: _
+    "###);
+}
+
+#[test]
+fn prelude_depends_on_edition() {
+    let std = r#"
+        //- /std.rs crate:std
+        #[prelude_import]
+        pub use self::prelude::v1::*;
+        pub mod prelude {
+            pub mod v1 {
+                pub struct Option;
+            }
+            pub mod rust_2018 {
+                pub use super::v1::*;
+            }
+            pub mod rust_2021 {
+                pub use super::v1::*;
+                pub struct TryFrom;
+            }
+        }
+    "#;
+    let map = def_map(&format!(
+        "//- /main.rs crate:main deps:std edition:2021\nuse {{Option, TryFrom}};\n{}",
+        std
+    ));
+    assert_snapshot!(map, @r###"
+        ⋮crate
+        ⋮Option: t v
+        ⋮TryFrom: t v
+    "###);
+
+    let map = def_map(&format!(
+        "//- /main.rs crate:main deps:std edition:2018\nuse {{Option, TryFrom}};\n{}",
+        std
+    ));
+    assert_snapshot!(map, @r###"
+        ⋮crate
+        ⋮Option: t v
+        ⋮TryFrom: _
+    "###);
+}
+
+#[test]
+fn edition_prelude_is_looked_up_next_to_v1() {
+    let map = def_map(
+        r#"
+        //- /main.rs crate:main deps:std edition:2021
+        use {Option, TryFrom};
+
+        //- /std.rs crate:std
+        #[prelude_import]
+        pub use self::prelude::*;
+        pub mod prelude {
+            pub struct Option;
+        }
+        pub mod rust_2021 {
+            pub struct TryFrom;
+        }
+        "#,
+    );
+    assert_snapshot!(map, @r###"
+        ⋮crate
+        ⋮Option: t v
+        ⋮TryFrom: _
+    "###);
+}
+
 #[test]
 fn cfg_not_test() {
     let map = def_map(
@@ -293,7 +293,10 @@ pub use hir_expand::name as __name;
 #[macro_export]
 macro_rules! __known_path {
     (std::iter::IntoIterator) => {};
+    (std::iter::Iterator) => {};
+    (std::convert::From) => {};
     (std::result::Result) => {};
+    (std::option::Option) => {};
     (std::ops::Range) => {};
     (std::ops::RangeFrom) => {};
     (std::ops::RangeFull) => {};
@@ -1,9 +1,15 @@
 //! HIR for references to types. Paths in these are not yet resolved. They can
 //! be directly created from an ast::TypeRef, without further queries.
 
-use ra_syntax::ast::{self, TypeAscriptionOwner, TypeBoundsOwner};
+use ra_syntax::{
+    ast::{self, HasStringValue, TypeAscriptionOwner, TypeBoundsOwner},
+    AstNode, AstToken, SmolStr,
+};
 
-use crate::path::Path;
+use crate::{
+    expr::{ArithOp, BinaryOp},
+    path::Path,
+};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Mutability {
@@ -35,6 +41,87 @@ impl Mutability {
     }
 }
 
+/// The calling convention of a function or a fn pointer, like the `"C"` in
+/// `extern "C" fn`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Abi {
+    Rust,
+    C,
+    Cdecl,
+    Stdcall,
+    Fastcall,
+    Vectorcall,
+    Thiscall,
+    Aapcs,
+    Win64,
+    SysV64,
+    PtxKernel,
+    Msp430Interrupt,
+    X86Interrupt,
+    AmdGpuKernel,
+    EfiApi,
+    System,
+    RustIntrinsic,
+    RustCall,
+    PlatformIntrinsic,
+    Unadjusted,
+    /// An ABI string rustc doesn't know.
+    Unknown,
+}
+
+const ABI_NAMES: &[(Abi, &str)] = &[
+    (Abi::Rust, "Rust"),
+    (Abi::C, "C"),
+    (Abi::Cdecl, "cdecl"),
+    (Abi::Stdcall, "stdcall"),
+    (Abi::Fastcall, "fastcall"),
+    (Abi::Vectorcall, "vectorcall"),
+    (Abi::Thiscall, "thiscall"),
+    (Abi::Aapcs, "aapcs"),
+    (Abi::Win64, "win64"),
+    (Abi::SysV64, "sysv64"),
+    (Abi::PtxKernel, "ptx-kernel"),
+    (Abi::Msp430Interrupt, "msp430-interrupt"),
+    (Abi::X86Interrupt, "x86-interrupt"),
+    (Abi::AmdGpuKernel, "amdgpu-kernel"),
+    (Abi::EfiApi, "efiapi"),
+    (Abi::System, "system"),
+    (Abi::RustIntrinsic, "rust-intrinsic"),
+    (Abi::RustCall, "rust-call"),
+    (Abi::PlatformIntrinsic, "platform-intrinsic"),
+    (Abi::Unadjusted, "unadjusted"),
+];
+
+impl Abi {
+    pub fn from_name(name: &str) -> Abi {
+        ABI_NAMES.iter().find(|(_, it)| *it == name).map_or(Abi::Unknown, |(abi, _)| *abi)
+    }
+
+    /// The ABI string, as written between the quotes.
+    pub fn name(self) -> &'static str {
+        ABI_NAMES.iter().find(|(it, _)| *it == self).map_or("unknown", |(_, name)| *name)
+    }
+
+    /// The ABI of a function with the given `extern` qualifier. A bare
+    /// `extern` means `extern "C"`.
+    pub(crate) fn from_ast(abi: Option<ast::Abi>) -> Abi {
+        let abi = match abi {
+            Some(it) => it,
+            None => return Abi::Rust,
+        };
+        let name = abi
+            .syntax()
+            .children_with_tokens()
+            .filter_map(|it| it.into_token())
+            .find_map(ast::String::cast)
+            .and_then(|it| it.value());
+        match name {
+            Some(name) => Abi::from_name(&name),
+            None => Abi::C,
+        }
+    }
+}
+
 /// Compare ty::Ty
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum TypeRef {
@@ -44,21 +131,59 @@ pub enum TypeRef {
     Path(Path),
     RawPtr(Box<TypeRef>, Mutability),
     Reference(Box<TypeRef>, Mutability),
-    Array(Box<TypeRef> /*, Expr*/),
+    Array(Box<TypeRef>, ConstExpr),
     Slice(Box<TypeRef>),
     /// A fn pointer. Last element of the vector is the return type.
-    Fn(Vec<TypeRef>),
+    Fn(Vec<TypeRef>, Abi),
     // For
     ImplTrait(Vec<TypeBound>),
     DynTrait(Vec<TypeBound>),
     Error,
 }
 
+/// A constant expression in a type, like the length of an array. Only integer
+/// arithmetic on literals and paths to constants is lowered, since that's all
+/// we can evaluate without a body.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ConstExpr {
+    Int(u128),
+    Path(Path),
+    BinaryOp { lhs: Box<ConstExpr>, op: ArithOp, rhs: Box<ConstExpr> },
+    Unknown,
+}
+
+impl ConstExpr {
+    pub fn from_ast(node: Option<ast::Expr>) -> Self {
+        let node = match node {
+            Some(it) => it,
+            None => return ConstExpr::Unknown,
+        };
+        match node {
+            ast::Expr::Literal(it) => ast::IntNumber::cast(it.token())
+                .and_then(|it| it.value())
+                .map_or(ConstExpr::Unknown, ConstExpr::Int),
+            ast::Expr::PathExpr(it) => {
+                it.path().and_then(Path::from_ast).map_or(ConstExpr::Unknown, ConstExpr::Path)
+            }
+            ast::Expr::ParenExpr(it) => ConstExpr::from_ast(it.expr()),
+            ast::Expr::BinExpr(it) => match it.op_kind().map(BinaryOp::from) {
+                Some(BinaryOp::ArithOp(op)) => ConstExpr::BinaryOp {
+                    lhs: Box::new(ConstExpr::from_ast(it.lhs())),
+                    op,
+                    rhs: Box::new(ConstExpr::from_ast(it.rhs())),
+                },
+                _ => ConstExpr::Unknown,
+            },
+            _ => ConstExpr::Unknown,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum TypeBound {
+    /// A trait bound, with any `for<>` binder dropped.
     Path(Path),
-    // also for<> bounds
-    // also Lifetimes
+    Lifetime(SmolStr),
     Error,
 }
 
@@ -81,7 +206,8 @@ impl TypeRef {
                 TypeRef::RawPtr(Box::new(inner_ty), mutability)
             }
             ast::TypeRef::ArrayType(inner) => {
-                TypeRef::Array(Box::new(TypeRef::from_ast_opt(inner.type_ref())))
+                let len = ConstExpr::from_ast(inner.expr());
+                TypeRef::Array(Box::new(TypeRef::from_ast_opt(inner.type_ref())), len)
             }
             ast::TypeRef::SliceType(inner) => {
                 TypeRef::Slice(Box::new(TypeRef::from_ast_opt(inner.type_ref())))
@@ -104,7 +230,7 @@ impl TypeRef {
                     Vec::new()
                 };
                 params.push(ret_ty);
-                TypeRef::Fn(params)
+                TypeRef::Fn(params, Abi::from_ast(inner.abi()))
             }
             // for types are close enough for our purposes to the inner type for now...
             ast::TypeRef::ForType(inner) => TypeRef::from_ast_opt(inner.type_ref()),
@@ -135,16 +261,18 @@ impl TypeRef {
         fn go(type_ref: &TypeRef, f: &mut impl FnMut(&TypeRef)) {
             f(type_ref);
             match type_ref {
-                TypeRef::Fn(types) | TypeRef::Tuple(types) => types.iter().for_each(|t| go(t, f)),
+                TypeRef::Fn(types, _) | TypeRef::Tuple(types) => {
+                    types.iter().for_each(|t| go(t, f))
+                }
                 TypeRef::RawPtr(type_ref, _)
                 | TypeRef::Reference(type_ref, _)
-                | TypeRef::Array(type_ref)
+                | TypeRef::Array(type_ref, _)
                 | TypeRef::Slice(type_ref) => go(&type_ref, f),
                 TypeRef::ImplTrait(bounds) | TypeRef::DynTrait(bounds) => {
                     for bound in bounds {
                         match bound {
                             TypeBound::Path(path) => go_path(path, f),
-                            TypeBound::Error => (),
+                            TypeBound::Lifetime(_) | TypeBound::Error => (),
                         }
                     }
                 }
@@ -183,22 +311,28 @@ pub(crate) fn type_bounds_from_ast(type_bounds_opt: Option<ast::TypeBoundList>)
 impl TypeBound {
     pub(crate) fn from_ast(node: ast::TypeBound) -> Self {
         match node.kind() {
-            ast::TypeBoundKind::PathType(path_type) => {
-                let path = match path_type.path() {
-                    Some(p) => p,
-                    None => return TypeBound::Error,
-                };
-                // FIXME: Use `Path::from_src`
-                let path = match Path::from_ast(path) {
-                    Some(p) => p,
-                    None => return TypeBound::Error,
-                };
-                TypeBound::Path(path)
-            }
-            ast::TypeBoundKind::ForType(_) | ast::TypeBoundKind::Lifetime(_) => TypeBound::Error,
+            ast::TypeBoundKind::PathType(path_type) => TypeBound::from_path_type(path_type),
+            ast::TypeBoundKind::ForType(for_type) => match for_type.type_ref() {
+                Some(ast::TypeRef::PathType(path_type)) => TypeBound::from_path_type(path_type),
+                _ => TypeBound::Error,
+            },
+            ast::TypeBoundKind::Lifetime(lifetime) => TypeBound::Lifetime(lifetime.text().clone()),
         }
     }
 
+    fn from_path_type(path_type: ast::PathType) -> Self {
+        let path = match path_type.path() {
+            Some(p) => p,
+            None => return TypeBound::Error,
+        };
+        // FIXME: Use `Path::from_src`
+        let path = match Path::from_ast(path) {
+            Some(p) => p,
+            None => return TypeBound::Error,
+        };
+        TypeBound::Path(path)
+    }
+
     pub fn as_path(&self) -> Option<&Path> {
         match self {
             TypeBound::Path(p) => Some(p),
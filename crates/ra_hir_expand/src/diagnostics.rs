@@ -21,11 +21,19 @@ use ra_syntax::{SyntaxNode, SyntaxNodePtr, TextRange};
 use crate::{db::AstDatabase, InFile};
 
 pub trait Diagnostic: Any + Send + Sync + fmt::Debug + 'static {
+    /// The kind of the diagnostic, like `unresolved-module`. Users refer to
+    /// diagnostics by their code in the configuration, so it must not change.
+    fn code(&self) -> &'static str;
     fn message(&self) -> String;
     fn source(&self) -> InFile<SyntaxNodePtr>;
     fn highlight_range(&self) -> TextRange {
         self.source().value.range()
     }
+    /// Secondary locations which explain the diagnostic, each with a message
+    /// like "binding declared here".
+    fn labels(&self) -> Vec<(InFile<SyntaxNodePtr>, String)> {
+        Vec::new()
+    }
     fn as_any(&self) -> &(dyn Any + Send + 'static);
 }
 
@@ -88,6 +88,14 @@ impl HirFileId {
         }
     }
 
+    /// Returns `true` if this is a macro-expansion file.
+    pub fn is_macro(self) -> bool {
+        match self.0 {
+            HirFileIdRepr::FileId(_) => false,
+            HirFileIdRepr::MacroFile(_) => true,
+        }
+    }
+
     /// If this is a macro call, returns the syntax node of the call.
     pub fn call_node(self, db: &dyn db::AstDatabase) -> Option<InFile<SyntaxNode>> {
         match self.0 {
@@ -153,17 +153,26 @@ pub mod known {
         core,
         alloc,
         iter,
+        convert,
         ops,
         future,
         result,
+        option,
         boxed,
+        v1,
+        rust_2015,
+        rust_2018,
+        rust_2021,
         // Components of known path (type name)
         IntoIterator,
+        Iterator,
+        From,
         Item,
         Try,
         Ok,
         Future,
         Result,
+        Option,
         Output,
         Target,
         Box,
@@ -0,0 +1,585 @@
+//! A tiny interpreter for constant expressions.
+//!
+//! It handles integer and boolean arithmetic, `if`, `match`, `let` and calls
+//! to other `const fn`s, which covers the constants people usually want to see
+//! the value of, like `HEADER_LEN * 2`. Anything else, like references, structs
+//! or loops, makes the evaluation fail instead of producing a wrong value.
+//!
+//! Array lengths are evaluated from their `ConstExpr` in the type, since they
+//! aren't part of a body.
+
+use std::{convert::TryFrom, fmt, sync::Arc};
+
+use hir_def::{
+    body::Body,
+    expr::{ArithOp, BinaryOp, CmpOp, Expr, ExprId, Literal, LogicOp, Ordering, Pat, PatId},
+    expr::{Statement, UnaryOp},
+    path::Path,
+    resolver::{resolver_for_expr, Resolver, ValueNs},
+    type_ref::ConstExpr,
+    ConstId, DefWithBodyId,
+};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    db::HirDatabase,
+    primitive::{IntBitness, IntTy, Signedness, Uncertain},
+    ApplicationTy, InferenceResult, Ty, TypeCtor,
+};
+
+/// The number of expressions evaluated before giving up, which bounds the time
+/// spent on runaway recursion.
+const STEP_LIMIT: usize = 10_000;
+/// How deep calls to `const fn`s and uses of other constants can be nested.
+const DEPTH_LIMIT: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstValue {
+    Bool(bool),
+    Int(i128),
+}
+
+impl fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstValue::Bool(it) => write!(f, "{}", it),
+            ConstValue::Int(it) => write!(f, "{}", it),
+        }
+    }
+}
+
+/// Evaluates the initializer of `konst`, returning `None` if it uses anything
+/// the interpreter doesn't support or if the evaluation fails, for example
+/// because of an overflow.
+pub fn eval_const(db: &dyn HirDatabase, konst: ConstId) -> Option<ConstValue> {
+    Evaluator { db, steps: 0, depth: 0 }.eval_body(konst.into(), Vec::new())
+}
+
+/// Evaluates the length of an array type, with the paths in it resolved by
+/// `resolver`.
+pub fn eval_array_len(db: &dyn HirDatabase, resolver: &Resolver, len: &ConstExpr) -> Option<u64> {
+    let value = Evaluator { db, steps: 0, depth: 0 }.eval_const_expr(resolver, len)?;
+    match value {
+        ConstValue::Int(it) => u64::try_from(it).ok(),
+        ConstValue::Bool(_) => None,
+    }
+}
+
+struct Evaluator<'a> {
+    db: &'a dyn HirDatabase,
+    steps: usize,
+    depth: usize,
+}
+
+/// The evaluation of a single body, with the values of its local bindings.
+struct Frame {
+    owner: DefWithBodyId,
+    body: Arc<Body>,
+    infer: Arc<InferenceResult>,
+    locals: FxHashMap<PatId, ConstValue>,
+}
+
+impl Evaluator<'_> {
+    fn eval_body(&mut self, owner: DefWithBodyId, args: Vec<ConstValue>) -> Option<ConstValue> {
+        if self.depth == DEPTH_LIMIT {
+            return None;
+        }
+        let body = self.db.body(owner);
+        if body.params.len() != args.len() {
+            return None;
+        }
+        let mut frame = Frame {
+            owner,
+            body: body.clone(),
+            infer: self.db.infer(owner),
+            locals: FxHashMap::default(),
+        };
+        for (&param, arg) in body.params.iter().zip(args) {
+            if !self.match_pat(&mut frame, param, body.body_expr, arg)? {
+                return None;
+            }
+        }
+        self.depth += 1;
+        let res = self.eval_expr(&mut frame, body.body_expr);
+        self.depth -= 1;
+        res
+    }
+
+    fn eval_const_expr(&mut self, resolver: &Resolver, expr: &ConstExpr) -> Option<ConstValue> {
+        let value = match expr {
+            ConstExpr::Int(it) => ConstValue::Int(i128::try_from(*it).ok()?),
+            ConstExpr::Path(path) => {
+                match resolver.resolve_path_in_value_ns_fully(self.db.upcast(), path.mod_path())? {
+                    ValueNs::ConstId(konst) => self.eval_body(konst.into(), Vec::new())?,
+                    _ => return None,
+                }
+            }
+            ConstExpr::BinaryOp { lhs, op, rhs } => {
+                let lhs = self.eval_const_expr(resolver, lhs)?;
+                let rhs = self.eval_const_expr(resolver, rhs)?;
+                eval_arith(*op, lhs, rhs, Some(IntTy::usize()))?
+            }
+            ConstExpr::Unknown => return None,
+        };
+        match value {
+            ConstValue::Int(it) if wrap(it, IntTy::usize()) != it => None,
+            _ => Some(value),
+        }
+    }
+
+    fn eval_expr(&mut self, frame: &mut Frame, expr: ExprId) -> Option<ConstValue> {
+        self.steps += 1;
+        if self.steps > STEP_LIMIT {
+            return None;
+        }
+        let body = frame.body.clone();
+        let value = match &body[expr] {
+            Expr::Literal(Literal::Bool(it)) => ConstValue::Bool(*it),
+            Expr::Literal(Literal::Int(it, _)) => ConstValue::Int(i128::try_from(*it).ok()?),
+            Expr::Path(path) => self.eval_path(frame, expr, path)?,
+            Expr::Call { callee, args } => {
+                let func = match &body[*callee] {
+                    Expr::Path(path) => match self.resolve(frame, *callee, path)? {
+                        ValueNs::FunctionId(it) if self.db.function_data(it).is_const => it,
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                let mut arg_values = Vec::with_capacity(args.len());
+                for &arg in args {
+                    arg_values.push(self.eval_expr(frame, arg)?);
+                }
+                self.eval_body(func.into(), arg_values)?
+            }
+            Expr::If { condition, then_branch, else_branch } => {
+                if self.eval_bool(frame, *condition)? {
+                    self.eval_expr(frame, *then_branch)?
+                } else {
+                    self.eval_expr(frame, (*else_branch)?)?
+                }
+            }
+            Expr::Block { statements, tail } => {
+                for stmt in statements {
+                    match stmt {
+                        Statement::Let { pat, initializer, .. } => {
+                            let value = self.eval_expr(frame, (*initializer)?)?;
+                            if !self.match_pat(frame, *pat, expr, value)? {
+                                return None;
+                            }
+                        }
+                        Statement::Expr(it) => {
+                            self.eval_expr(frame, *it)?;
+                        }
+                    }
+                }
+                self.eval_expr(frame, (*tail)?)?
+            }
+            Expr::Match { expr: scrutinee, arms } => {
+                let value = self.eval_expr(frame, *scrutinee)?;
+                let mut res = None;
+                for arm in arms {
+                    if !self.match_pat(frame, arm.pat, arm.expr, value)? {
+                        continue;
+                    }
+                    if let Some(guard) = arm.guard {
+                        if !self.eval_bool(frame, guard)? {
+                            continue;
+                        }
+                    }
+                    res = Some(self.eval_expr(frame, arm.expr)?);
+                    break;
+                }
+                res?
+            }
+            // A negated literal is only checked after the negation, so that
+            // `-128i8` fits.
+            Expr::UnaryOp { expr: operand, op: UnaryOp::Neg } => {
+                let value = match &body[*operand] {
+                    Expr::Literal(Literal::Int(it, _)) => i128::try_from(*it).ok()?,
+                    _ => match self.eval_expr(frame, *operand)? {
+                        ConstValue::Int(it) => it,
+                        ConstValue::Bool(_) => return None,
+                    },
+                };
+                ConstValue::Int(value.checked_neg()?)
+            }
+            Expr::UnaryOp { expr: operand, op } => match (op, self.eval_expr(frame, *operand)?) {
+                (UnaryOp::Not, ConstValue::Bool(it)) => ConstValue::Bool(!it),
+                (UnaryOp::Not, ConstValue::Int(it)) => {
+                    ConstValue::Int(wrap(!it, frame.int_ty(expr)?))
+                }
+                _ => return None,
+            },
+            Expr::BinaryOp { lhs, rhs, op } => match (*op)? {
+                BinaryOp::LogicOp(op) => {
+                    let lhs = self.eval_bool(frame, *lhs)?;
+                    let short_circuit = match op {
+                        LogicOp::And => !lhs,
+                        LogicOp::Or => lhs,
+                    };
+                    if short_circuit {
+                        ConstValue::Bool(lhs)
+                    } else {
+                        ConstValue::Bool(self.eval_bool(frame, *rhs)?)
+                    }
+                }
+                BinaryOp::ArithOp(op) => {
+                    let lhs = self.eval_expr(frame, *lhs)?;
+                    let rhs = self.eval_expr(frame, *rhs)?;
+                    eval_arith(op, lhs, rhs, frame.int_ty(expr))?
+                }
+                BinaryOp::CmpOp(op) => {
+                    let lhs = self.eval_expr(frame, *lhs)?;
+                    let rhs = self.eval_expr(frame, *rhs)?;
+                    ConstValue::Bool(eval_cmp(op, lhs, rhs)?)
+                }
+                BinaryOp::Assignment { .. } => return None,
+            },
+            Expr::Cast { expr: operand, .. } => {
+                let value = match self.eval_expr(frame, *operand)? {
+                    ConstValue::Bool(it) => it as i128,
+                    ConstValue::Int(it) => it,
+                };
+                ConstValue::Int(wrap(value, frame.int_ty(expr)?))
+            }
+            _ => return None,
+        };
+        frame.check_ty(expr, value)
+    }
+
+    fn eval_bool(&mut self, frame: &mut Frame, expr: ExprId) -> Option<bool> {
+        match self.eval_expr(frame, expr)? {
+            ConstValue::Bool(it) => Some(it),
+            ConstValue::Int(_) => None,
+        }
+    }
+
+    fn eval_path(&mut self, frame: &mut Frame, expr: ExprId, path: &Path) -> Option<ConstValue> {
+        match self.resolve(frame, expr, path)? {
+            ValueNs::LocalBinding(pat) => frame.locals.get(&pat).copied(),
+            ValueNs::ConstId(konst) => self.eval_body(konst.into(), Vec::new()),
+            _ => None,
+        }
+    }
+
+    fn resolve(&self, frame: &Frame, expr: ExprId, path: &Path) -> Option<ValueNs> {
+        let resolver = resolver_for_expr(self.db.upcast(), frame.owner, expr);
+        resolver.resolve_path_in_value_ns_fully(self.db.upcast(), path.mod_path())
+    }
+
+    /// Matches `value` against `pat`, binding the names in it. `scope` is an
+    /// expression where the paths in the pattern are resolved.
+    fn match_pat(
+        &mut self,
+        frame: &mut Frame,
+        pat: PatId,
+        scope: ExprId,
+        value: ConstValue,
+    ) -> Option<bool> {
+        let body = frame.body.clone();
+        let res = match &body[pat] {
+            Pat::Wild => true,
+            Pat::Bind { subpat, .. } => {
+                frame.locals.insert(pat, value);
+                match subpat {
+                    Some(subpat) => self.match_pat(frame, *subpat, scope, value)?,
+                    None => true,
+                }
+            }
+            Pat::Lit(expr) => self.eval_expr(frame, *expr)? == value,
+            Pat::Range { start, end } => {
+                let start = self.eval_expr(frame, *start)?;
+                let end = self.eval_expr(frame, *end)?;
+                eval_cmp(CmpOp::Ord { ordering: Ordering::Greater, strict: false }, value, start)?
+                    && eval_cmp(CmpOp::Ord { ordering: Ordering::Less, strict: false }, value, end)?
+            }
+            Pat::Path(path) => self.eval_path(frame, scope, path)? == value,
+            Pat::Or(pats) => {
+                let mut res = false;
+                for &pat in pats {
+                    if self.match_pat(frame, pat, scope, value)? {
+                        res = true;
+                        break;
+                    }
+                }
+                res
+            }
+            _ => return None,
+        };
+        Some(res)
+    }
+}
+
+impl Frame {
+    fn int_ty(&self, expr: ExprId) -> Option<IntTy> {
+        match &self.infer[expr] {
+            Ty::Apply(ApplicationTy { ctor: TypeCtor::Int(Uncertain::Known(it)), .. }) => Some(*it),
+            _ => None,
+        }
+    }
+
+    /// Makes sure the value fits into the type of `expr`, which is how
+    /// overflows are detected.
+    fn check_ty(&self, expr: ExprId, value: ConstValue) -> Option<ConstValue> {
+        match (value, self.int_ty(expr)) {
+            (ConstValue::Int(it), Some(ty)) if wrap(it, ty) != it => None,
+            _ => Some(value),
+        }
+    }
+}
+
+/// Evaluates an arithmetic operation whose result has the type `ty`, which is
+/// needed for shifts: they only fail if the shift amount is too large, and
+/// otherwise drop the bits shifted out.
+fn eval_arith(
+    op: ArithOp,
+    lhs: ConstValue,
+    rhs: ConstValue,
+    ty: Option<IntTy>,
+) -> Option<ConstValue> {
+    let (lhs, rhs) = match (lhs, rhs) {
+        (ConstValue::Int(lhs), ConstValue::Int(rhs)) => (lhs, rhs),
+        (ConstValue::Bool(lhs), ConstValue::Bool(rhs)) => {
+            let res = match op {
+                ArithOp::BitAnd => lhs & rhs,
+                ArithOp::BitOr => lhs | rhs,
+                ArithOp::BitXor => lhs ^ rhs,
+                _ => return None,
+            };
+            return Some(ConstValue::Bool(res));
+        }
+        _ => return None,
+    };
+    let res = match op {
+        ArithOp::Add => lhs.checked_add(rhs)?,
+        ArithOp::Sub => lhs.checked_sub(rhs)?,
+        ArithOp::Mul => lhs.checked_mul(rhs)?,
+        ArithOp::Div => lhs.checked_div(rhs)?,
+        ArithOp::Rem => lhs.checked_rem(rhs)?,
+        ArithOp::Shl | ArithOp::Shr => {
+            let amount = shift_amount(rhs)?;
+            if amount >= ty.map_or(128, bits) {
+                return None;
+            }
+            match op {
+                ArithOp::Shl => ty.map_or(lhs << amount, |ty| wrap(lhs << amount, ty)),
+                _ => lhs >> amount,
+            }
+        }
+        ArithOp::BitAnd => lhs & rhs,
+        ArithOp::BitOr => lhs | rhs,
+        ArithOp::BitXor => lhs ^ rhs,
+    };
+    Some(ConstValue::Int(res))
+}
+
+fn shift_amount(value: i128) -> Option<u32> {
+    if value < 0 || value > u32::max_value() as i128 {
+        return None;
+    }
+    Some(value as u32)
+}
+
+fn eval_cmp(op: CmpOp, lhs: ConstValue, rhs: ConstValue) -> Option<bool> {
+    let (lhs, rhs) = match (lhs, rhs) {
+        (ConstValue::Int(lhs), ConstValue::Int(rhs)) => (lhs, rhs),
+        (ConstValue::Bool(lhs), ConstValue::Bool(rhs)) => (lhs as i128, rhs as i128),
+        _ => return None,
+    };
+    let res = match op {
+        CmpOp::Eq { negated } => (lhs == rhs) != negated,
+        CmpOp::Ord { ordering: Ordering::Less, strict: true } => lhs < rhs,
+        CmpOp::Ord { ordering: Ordering::Less, strict: false } => lhs <= rhs,
+        CmpOp::Ord { ordering: Ordering::Greater, strict: true } => lhs > rhs,
+        CmpOp::Ord { ordering: Ordering::Greater, strict: false } => lhs >= rhs,
+    };
+    Some(res)
+}
+
+/// The width of `ty`. `usize` and `isize` are assumed to be 64 bits wide.
+fn bits(ty: IntTy) -> u32 {
+    match ty.bitness {
+        IntBitness::X8 => 8,
+        IntBitness::X16 => 16,
+        IntBitness::X32 => 32,
+        IntBitness::Xsize | IntBitness::X64 => 64,
+        IntBitness::X128 => 128,
+    }
+}
+
+/// Truncates `value` to the width of `ty`, like an `as` cast. 128 bit integers
+/// are approximated with `i128`.
+fn wrap(value: i128, ty: IntTy) -> i128 {
+    let bits = bits(ty);
+    if bits == 128 {
+        return value;
+    }
+    let modulus = 1i128 << bits;
+    let value = value.rem_euclid(modulus);
+    match ty.signedness {
+        Signedness::Signed if value >= modulus / 2 => value - modulus,
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hir_def::{db::DefDatabase, resolver::HasResolver, type_ref::TypeRef, ModuleDefId};
+    use ra_db::{fixture::WithFixture, FileId};
+
+    use super::*;
+    use crate::test_db::TestDB;
+
+    fn goal(db: &TestDB, file_id: FileId) -> ConstId {
+        let module = db.module_for_file(file_id);
+        let def_map = db.crate_def_map(module.krate);
+        def_map[module.local_id]
+            .scope
+            .declarations()
+            .find_map(|it| match it {
+                ModuleDefId::ConstId(it)
+                    if db.const_data(it).name.as_ref().map(|it| it.to_string())
+                        == Some("GOAL".to_string()) =>
+                {
+                    Some(it)
+                }
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn eval(ra_fixture: &str) -> Option<ConstValue> {
+        let (db, file_id) = TestDB::with_single_file(ra_fixture);
+        eval_const(&db, goal(&db, file_id))
+    }
+
+    /// Evaluates the length of the array type of `GOAL`.
+    fn array_len(ra_fixture: &str) -> Option<u64> {
+        let (db, file_id) = TestDB::with_single_file(ra_fixture);
+        let konst = goal(&db, file_id);
+        match &db.const_data(konst).type_ref {
+            TypeRef::Array(_, len) => eval_array_len(&db, &konst.resolver(&db), len),
+            _ => panic!("GOAL is not an array"),
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(eval("const GOAL: usize = 2 + 3 * 4;"), Some(ConstValue::Int(14)));
+        assert_eq!(eval("const GOAL: i32 = -7 / 2 % 3;"), Some(ConstValue::Int(0)));
+        assert_eq!(eval("const GOAL: u32 = 0xff & !0x0f;"), Some(ConstValue::Int(0xf0)));
+        assert_eq!(eval("const GOAL: u8 = 1 << 3 | b'\\x01';"), Some(ConstValue::Int(9)));
+        assert_eq!(eval("const GOAL: u8 = 300u16 as u8;"), Some(ConstValue::Int(44)));
+        assert_eq!(eval("const GOAL: bool = 1 < 2 && !(3 >= 4);"), Some(ConstValue::Bool(true)));
+    }
+
+    #[test]
+    fn overflow_fails() {
+        assert_eq!(eval("const GOAL: u8 = 255 + 1;"), None);
+        assert_eq!(eval("const GOAL: u32 = 1 - 2;"), None);
+        assert_eq!(eval("const GOAL: i32 = 1 / 0;"), None);
+        assert_eq!(eval("const GOAL: u8 = 1 << 8;"), None);
+    }
+
+    #[test]
+    fn edges_of_the_range() {
+        assert_eq!(eval("const GOAL: i8 = -128i8;"), Some(ConstValue::Int(-128)));
+        assert_eq!(eval("const GOAL: i32 = 1i32 << 31;"), Some(ConstValue::Int(-2147483648)));
+        assert_eq!(eval("const GOAL: u8 = 0xffu8 << 4 >> 4;"), Some(ConstValue::Int(0xf)));
+    }
+
+    #[test]
+    fn array_lengths() {
+        assert_eq!(
+            array_len(
+                r#"
+const HEADER_LEN: usize = 4;
+mod consts { pub const PAD: usize = 1; }
+const GOAL: [u8; (HEADER_LEN * 2) + consts::PAD] = [0; 9];
+"#
+            ),
+            Some(9)
+        );
+        assert_eq!(array_len("const GOAL: [u8; 0x10] = [0; 16];"), Some(16));
+        assert_eq!(array_len("const GOAL: [u8; 1 - 2] = [];"), None);
+        assert_eq!(array_len("const GOAL: [u8; N] = [];"), None);
+    }
+
+    #[test]
+    fn uses_other_consts() {
+        assert_eq!(
+            eval(
+                r#"
+const HEADER_LEN: usize = 4;
+mod consts { pub const PAD: usize = 1; }
+const GOAL: usize = HEADER_LEN * 2 + consts::PAD;
+"#
+            ),
+            Some(ConstValue::Int(9))
+        );
+    }
+
+    #[test]
+    fn branches() {
+        assert_eq!(
+            eval(
+                r#"
+const DEBUG: bool = false;
+const GOAL: usize = if DEBUG { 64 } else { let x = 8; x * 2 };
+"#
+            ),
+            Some(ConstValue::Int(16))
+        );
+        assert_eq!(
+            eval(
+                r#"
+const LEVEL: u8 = 3;
+const MAX: u8 = 3;
+const GOAL: u8 = match LEVEL {
+    0 | 1 => 10,
+    MAX => 30,
+    n if n > 5 => n,
+    _ => 20,
+};
+"#
+            ),
+            Some(ConstValue::Int(30))
+        );
+    }
+
+    #[test]
+    fn const_fn_calls() {
+        assert_eq!(
+            eval(
+                r#"
+const fn fact(n: u64) -> u64 { if n == 0 { 1 } else { n * fact(n - 1) } }
+const GOAL: u64 = fact(5);
+"#
+            ),
+            Some(ConstValue::Int(120))
+        );
+        assert_eq!(
+            eval(
+                r#"
+fn not_const() -> u64 { 1 }
+const GOAL: u64 = not_const();
+"#
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn runaway_recursion_fails() {
+        assert_eq!(
+            eval(
+                r#"
+const fn forever(n: u64) -> u64 { forever(n + 1) }
+const GOAL: u64 = forever(0);
+"#
+            ),
+            None
+        );
+        assert_eq!(eval("const GOAL: u64 = GOAL + 1;"), None);
+    }
+}
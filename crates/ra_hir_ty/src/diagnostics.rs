@@ -3,7 +3,7 @@
 use std::any::Any;
 
 use hir_expand::{db::AstDatabase, name::Name, HirFileId, InFile};
-use ra_syntax::{ast, AstNode, AstPtr, SyntaxNodePtr};
+use ra_syntax::{ast, AstNode, AstPtr, SyntaxNodePtr, TextRange};
 use stdx::format_to;
 
 pub use hir_def::{diagnostics::UnresolvedModule, expr::MatchArm};
@@ -16,6 +16,10 @@ pub struct NoSuchField {
 }
 
 impl Diagnostic for NoSuchField {
+    fn code(&self) -> &'static str {
+        "no-such-field"
+    }
+
     fn message(&self) -> String {
         "no such field".to_string()
     }
@@ -37,6 +41,9 @@ pub struct MissingFields {
 }
 
 impl Diagnostic for MissingFields {
+    fn code(&self) -> &'static str {
+        "missing-structure-fields"
+    }
     fn message(&self) -> String {
         let mut buf = String::from("Missing structure fields:\n");
         for field in &self.missed_fields {
@@ -70,6 +77,9 @@ pub struct MissingPatFields {
 }
 
 impl Diagnostic for MissingPatFields {
+    fn code(&self) -> &'static str {
+        "missing-pat-fields"
+    }
     fn message(&self) -> String {
         let mut buf = String::from("Missing structure fields:\n");
         for field in &self.missed_fields {
@@ -93,6 +103,9 @@ pub struct MissingMatchArms {
 }
 
 impl Diagnostic for MissingMatchArms {
+    fn code(&self) -> &'static str {
+        "missing-match-arm"
+    }
     fn message(&self) -> String {
         String::from("Missing match arm")
     }
@@ -108,15 +121,27 @@ impl Diagnostic for MissingMatchArms {
 pub struct MissingOkInTailExpr {
     pub file: HirFileId,
     pub expr: AstPtr<ast::Expr>,
+    /// The return type of the function, which makes it expect a `Result`.
+    pub ret_type: Option<InFile<AstPtr<ast::RetType>>>,
 }
 
 impl Diagnostic for MissingOkInTailExpr {
+    fn code(&self) -> &'static str {
+        "missing-ok-in-tail-expr"
+    }
     fn message(&self) -> String {
         "wrap return expression in Ok".to_string()
     }
     fn source(&self) -> InFile<SyntaxNodePtr> {
         InFile { file_id: self.file, value: self.expr.clone().into() }
     }
+    fn labels(&self) -> Vec<(InFile<SyntaxNodePtr>, String)> {
+        let message = "expected `Result` because of this return type";
+        self.ret_type
+            .iter()
+            .map(|it| (it.clone().map(|it| it.into()), message.to_string()))
+            .collect()
+    }
     fn as_any(&self) -> &(dyn Any + Send + 'static) {
         self
     }
@@ -131,3 +156,257 @@ impl AstDiagnostic for MissingOkInTailExpr {
         ast::Expr::cast(node).unwrap()
     }
 }
+
+#[derive(Debug)]
+pub struct NeedsMut {
+    pub file: HirFileId,
+    pub binding: AstPtr<ast::BindPat>,
+    pub mutation: AstPtr<ast::Expr>,
+    pub name: Name,
+}
+
+impl Diagnostic for NeedsMut {
+    fn code(&self) -> &'static str {
+        "needs-mut"
+    }
+    fn message(&self) -> String {
+        format!("cannot mutate immutable binding `{}`", self.name)
+    }
+    fn source(&self) -> InFile<SyntaxNodePtr> {
+        InFile { file_id: self.file, value: self.mutation.clone().into() }
+    }
+    fn labels(&self) -> Vec<(InFile<SyntaxNodePtr>, String)> {
+        let binding = InFile { file_id: self.file, value: self.binding.clone().into() };
+        vec![(binding, format!("`{}` is declared immutable here", self.name))]
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+impl AstDiagnostic for NeedsMut {
+    type AST = ast::BindPat;
+
+    fn ast(&self, db: &impl AstDatabase) -> Self::AST {
+        let root = db.parse_or_expand(self.file).unwrap();
+        self.binding.to_node(&root)
+    }
+}
+
+#[derive(Debug)]
+pub struct UnusedMut {
+    pub file: HirFileId,
+    pub binding: AstPtr<ast::BindPat>,
+}
+
+impl Diagnostic for UnusedMut {
+    fn code(&self) -> &'static str {
+        "unused-mut"
+    }
+    fn message(&self) -> String {
+        "variable does not need to be mutable".to_string()
+    }
+    fn source(&self) -> InFile<SyntaxNodePtr> {
+        InFile { file_id: self.file, value: self.binding.clone().into() }
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+impl AstDiagnostic for UnusedMut {
+    type AST = ast::BindPat;
+
+    fn ast(&self, db: &impl AstDatabase) -> Self::AST {
+        let root = db.parse_or_expand(self.file).unwrap();
+        self.binding.to_node(&root)
+    }
+}
+
+#[derive(Debug)]
+pub struct UnreachableCode {
+    pub file: HirFileId,
+    /// The first unreachable statement.
+    pub node: SyntaxNodePtr,
+    /// Covers all unreachable statements of the block.
+    pub range: TextRange,
+}
+
+impl Diagnostic for UnreachableCode {
+    fn code(&self) -> &'static str {
+        "unreachable-code"
+    }
+    fn message(&self) -> String {
+        "unreachable code".to_string()
+    }
+    fn source(&self) -> InFile<SyntaxNodePtr> {
+        InFile { file_id: self.file, value: self.node.clone() }
+    }
+    fn highlight_range(&self) -> TextRange {
+        self.range
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnusedMustUse {
+    pub file: HirFileId,
+    pub expr: AstPtr<ast::Expr>,
+    /// The called function, if it is `#[must_use]`.
+    pub function: Option<Name>,
+    /// The type of the value, if it is `#[must_use]`.
+    pub ty: Option<Name>,
+    /// The value is a `std::result::Result`.
+    pub is_result: bool,
+    /// The value is a `Result` and the enclosing function returns one too.
+    pub can_propagate: bool,
+}
+
+impl Diagnostic for UnusedMustUse {
+    fn code(&self) -> &'static str {
+        "unused-must-use"
+    }
+    fn message(&self) -> String {
+        match (&self.function, &self.ty) {
+            (Some(function), _) => {
+                format!("unused return value of `{}` that must be used", function)
+            }
+            (None, Some(ty)) => format!("unused `{}` that must be used", ty),
+            (None, None) => "unused value that must be used".to_string(),
+        }
+    }
+    fn source(&self) -> InFile<SyntaxNodePtr> {
+        InFile { file_id: self.file, value: self.expr.clone().into() }
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+impl AstDiagnostic for UnusedMustUse {
+    type AST = ast::Expr;
+
+    fn ast(&self, db: &impl AstDatabase) -> Self::AST {
+        let root = db.parse_or_expand(self.file).unwrap();
+        self.expr.to_node(&root)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidVariadicArg {
+    pub file: HirFileId,
+    pub arg: AstPtr<ast::Expr>,
+    /// The type of the argument.
+    pub ty: String,
+    /// The type to cast the argument to, if C would promote it, like `f32`
+    /// to `f64`. `None` if the type isn't FFI-safe at all.
+    pub cast_to: Option<String>,
+}
+
+impl Diagnostic for InvalidVariadicArg {
+    fn code(&self) -> &'static str {
+        "invalid-variadic-arg"
+    }
+    fn message(&self) -> String {
+        match &self.cast_to {
+            Some(cast_to) => {
+                format!("can't pass `{}` to variadic function, cast it to `{}`", self.ty, cast_to)
+            }
+            None => format!("can't pass `{}` to variadic function, it is not FFI-safe", self.ty),
+        }
+    }
+    fn source(&self) -> InFile<SyntaxNodePtr> {
+        InFile { file_id: self.file, value: self.arg.clone().into() }
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+impl AstDiagnostic for InvalidVariadicArg {
+    type AST = ast::Expr;
+
+    fn ast(&self, db: &impl AstDatabase) -> Self::AST {
+        let root = db.parse_or_expand(self.file).unwrap();
+        self.arg.to_node(&root)
+    }
+}
+
+#[derive(Debug)]
+pub struct UseAfterMove {
+    pub file: HirFileId,
+    pub used: AstPtr<ast::Expr>,
+    pub moved: AstPtr<ast::Expr>,
+    pub name: Name,
+}
+
+impl Diagnostic for UseAfterMove {
+    fn code(&self) -> &'static str {
+        "use-after-move"
+    }
+    fn message(&self) -> String {
+        format!("use of moved value `{}`", self.name)
+    }
+    fn source(&self) -> InFile<SyntaxNodePtr> {
+        InFile { file_id: self.file, value: self.used.clone().into() }
+    }
+    fn labels(&self) -> Vec<(InFile<SyntaxNodePtr>, String)> {
+        let moved = InFile { file_id: self.file, value: self.moved.clone().into() };
+        vec![(moved, format!("`{}` is moved here", self.name))]
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+/// How a binding is accessed while it is borrowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictingAccess {
+    Use,
+    Mutation,
+    Move,
+}
+
+#[derive(Debug)]
+pub struct BorrowConflict {
+    pub file: HirFileId,
+    pub access: AstPtr<ast::Expr>,
+    pub kind: ConflictingAccess,
+    /// The `&` or `&mut` expression which borrows the binding.
+    pub borrow: AstPtr<ast::Expr>,
+    pub mutable_borrow: bool,
+    /// A use of the borrow after the access.
+    pub later_use: AstPtr<ast::Expr>,
+    pub name: Name,
+}
+
+impl Diagnostic for BorrowConflict {
+    fn code(&self) -> &'static str {
+        "borrow-conflict"
+    }
+    fn message(&self) -> String {
+        let access = match self.kind {
+            ConflictingAccess::Use => "use",
+            ConflictingAccess::Mutation => "mutate",
+            ConflictingAccess::Move => "move",
+        };
+        let borrow = if self.mutable_borrow { "mutably" } else { "immutably" };
+        format!("cannot {} `{}` while it is {} borrowed", access, self.name, borrow)
+    }
+    fn source(&self) -> InFile<SyntaxNodePtr> {
+        InFile { file_id: self.file, value: self.access.clone().into() }
+    }
+    fn labels(&self) -> Vec<(InFile<SyntaxNodePtr>, String)> {
+        let borrow = InFile { file_id: self.file, value: self.borrow.clone().into() };
+        let later_use = InFile { file_id: self.file, value: self.later_use.clone().into() };
+        vec![
+            (borrow, format!("`{}` is borrowed here", self.name)),
+            (later_use, "the borrow is used later here".to_string()),
+        ]
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
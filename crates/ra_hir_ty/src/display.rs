@@ -6,7 +6,7 @@ use crate::{
     db::HirDatabase, utils::generics, ApplicationTy, CallableDef, FnSig, GenericPredicate,
     Obligation, ProjectionTy, Substs, TraitRef, Ty, TypeCtor,
 };
-use hir_def::{generics::TypeParamProvenance, AdtId, AssocContainerId, Lookup};
+use hir_def::{generics::TypeParamProvenance, type_ref::Abi, AdtId, AssocContainerId, Lookup};
 use hir_expand::name::Name;
 
 pub struct HirFormatter<'a, 'b> {
@@ -151,8 +151,11 @@ impl HirDisplay for ApplicationTy {
                     write!(f, ")")?;
                 }
             }
-            TypeCtor::FnPtr { .. } => {
+            TypeCtor::FnPtr { abi, .. } => {
                 let sig = FnSig::from_fn_ptr_substs(&self.parameters);
+                if abi != Abi::Rust {
+                    write!(f, "extern \"{}\" ", abi.name())?;
+                }
                 write!(f, "fn(")?;
                 f.write_joined(sig.params(), ", ")?;
                 write!(f, ")")?;
@@ -303,10 +306,10 @@ impl HirDisplay for Ty {
                         write!(f, "{}", param_data.name.clone().unwrap_or_else(Name::missing))?
                     }
                     TypeParamProvenance::ArgumentImplTrait => {
-                        write!(f, "impl ")?;
                         let bounds = f.db.generic_predicates_for_param(*id);
                         let substs = Substs::type_params_for_generics(&generics);
-                        write_bounds_like_dyn_trait(
+                        write_bounds_like_dyn_trait_with_prefix(
+                            "impl",
                             &bounds.iter().map(|b| b.clone().subst(&substs)).collect::<Vec<_>>(),
                             f,
                         )?;
@@ -314,13 +317,9 @@ impl HirDisplay for Ty {
                 }
             }
             Ty::Bound(idx) => write!(f, "?{}.{}", idx.debruijn.depth(), idx.index)?,
-            Ty::Dyn(predicates) | Ty::Opaque(predicates) => {
-                match self {
-                    Ty::Dyn(_) => write!(f, "dyn ")?,
-                    Ty::Opaque(_) => write!(f, "impl ")?,
-                    _ => unreachable!(),
-                };
-                write_bounds_like_dyn_trait(predicates, f)?;
+            Ty::Dyn(predicates) => write_bounds_like_dyn_trait_with_prefix("dyn", predicates, f)?,
+            Ty::Opaque(predicates) => {
+                write_bounds_like_dyn_trait_with_prefix("impl", predicates, f)?
             }
             Ty::Unknown => write!(f, "{{unknown}}")?,
             Ty::Infer(..) => write!(f, "_")?,
@@ -329,6 +328,20 @@ impl HirDisplay for Ty {
     }
 }
 
+fn write_bounds_like_dyn_trait_with_prefix(
+    prefix: &str,
+    predicates: &[GenericPredicate],
+    f: &mut HirFormatter,
+) -> fmt::Result {
+    write!(f, "{}", prefix)?;
+    // bounds like `'a` aren't kept, so there may be nothing to write
+    if !predicates.is_empty() {
+        write!(f, " ")?;
+        write_bounds_like_dyn_trait(predicates, f)?;
+    }
+    Ok(())
+}
+
 fn write_bounds_like_dyn_trait(
     predicates: &[GenericPredicate],
     f: &mut HirFormatter,
@@ -2,17 +2,27 @@
 
 use std::sync::Arc;
 
-use hir_def::{path::path, resolver::HasResolver, AdtId, FunctionId};
-use hir_expand::diagnostics::DiagnosticSink;
-use ra_syntax::{ast, AstPtr};
+use hir_def::{
+    path::path, resolver::HasResolver, src::HasSource, type_ref::Abi, AdtId, FunctionId, Lookup,
+};
+use hir_expand::{diagnostics::DiagnosticSink, InFile};
+use ra_syntax::{ast, AstNode, AstPtr, SyntaxNode, SyntaxNodePtr, TextRange};
 use rustc_hash::FxHashSet;
 
 use crate::{
+    _match::{is_useful, MatchCheckCtx, Matrix, PatStack, Usefulness},
     db::HirDatabase,
-    diagnostics::{MissingFields, MissingMatchArms, MissingOkInTailExpr, MissingPatFields},
+    diagnostics::{
+        BorrowConflict, InvalidVariadicArg, MissingFields, MissingMatchArms, MissingOkInTailExpr,
+        MissingPatFields, NeedsMut, UnreachableCode, UnusedMustUse, UnusedMut, UseAfterMove,
+    },
+    display::HirDisplay,
+    moves::{move_errors, MoveError},
+    mutation::binding_mutations,
+    primitive::{FloatBitness, IntBitness, Signedness, Uncertain},
+    reachability::{unreachable_code, DeadCode},
     utils::variant_data,
-    ApplicationTy, InferenceResult, Ty, TypeCtor,
-    _match::{is_useful, MatchCheckCtx, Matrix, PatStack, Usefulness},
+    ApplicationTy, CallableDef, InferenceResult, Substs, Ty, TypeCtor,
 };
 
 pub use hir_def::{
@@ -76,6 +86,10 @@ impl<'a, 'b> ExprValidator<'a, 'b> {
         if let Expr::Block { tail: Some(t), .. } = body_expr {
             self.validate_results_in_tail_expr(body.body_expr, *t, db);
         }
+        self.validate_mutability(db);
+        self.validate_reachability(db);
+        self.validate_unused_results(db);
+        self.validate_variadic_calls(db);
     }
 
     fn create_record_literal_missing_fields_diagnostic(
@@ -219,6 +233,288 @@ impl<'a, 'b> ExprValidator<'a, 'b> {
         }
     }
 
+    fn validate_mutability(&mut self, db: &dyn HirDatabase) {
+        let (body, source_map) = db.body_with_source_map(self.func.into());
+        let scopes = db.expr_scopes(self.func.into());
+        let mutations = binding_mutations(db, &body, &scopes, &self.infer);
+
+        // Bindings declared without an initializer may be assigned once
+        // without being `mut`, which needs a real dataflow analysis to check.
+        let mut late_initialized = FxHashSet::default();
+        for (_, expr) in body.exprs.iter() {
+            if let Expr::Block { statements, .. } = expr {
+                for stmt in statements {
+                    if let Statement::Let { pat, initializer: None, .. } = stmt {
+                        collect_pats(&body, *pat, &mut late_initialized);
+                    }
+                }
+            }
+        }
+
+        // A macro we failed to expand might mutate any binding.
+        let has_unexpanded_macros = body.exprs.iter().any(|(id, expr)| match expr {
+            Expr::Missing => match source_map.expr_syntax(id) {
+                Ok(source_ptr) => {
+                    let root = source_ptr.file_syntax(db.upcast());
+                    match source_ptr.value.to_node(&root) {
+                        ast::Expr::MacroCall(_) => true,
+                        _ => false,
+                    }
+                }
+                Err(_) => false,
+            },
+            _ => false,
+        });
+
+        let body_file = match source_map.expr_syntax(body.body_expr) {
+            Ok(it) => it.file_id,
+            Err(_) => return,
+        };
+
+        for (pat_id, pat) in body.pats.iter() {
+            let (name, mode) = match pat {
+                Pat::Bind { name, mode, subpat: None } => (name, *mode),
+                _ => continue,
+            };
+            if late_initialized.contains(&pat_id) {
+                continue;
+            }
+            let binding = match source_map.pat_syntax(pat_id) {
+                Ok(source_ptr) if source_ptr.file_id == body_file => {
+                    match source_ptr.value.left().and_then(|it| it.cast::<ast::BindPat>()) {
+                        Some(it) => it,
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+            match (mode, mutations.get(&pat_id)) {
+                (BindingAnnotation::Unannotated, Some(Some(site))) => {
+                    if let Ok(source_ptr) = source_map.expr_syntax(*site) {
+                        if source_ptr.file_id == body_file {
+                            self.sink.push(NeedsMut {
+                                file: body_file,
+                                binding,
+                                mutation: source_ptr.value,
+                                name: name.clone(),
+                            })
+                        }
+                    }
+                }
+                (BindingAnnotation::Mutable, None) if !has_unexpanded_macros => {
+                    self.sink.push(UnusedMut { file: body_file, binding })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks for uses of moved bindings and conflicting borrows. This is an
+    /// experimental approximation, so it isn't part of `validate_body`.
+    pub fn validate_moves(&mut self, db: &dyn HirDatabase) {
+        let (body, source_map) = db.body_with_source_map(self.func.into());
+        let scopes = db.expr_scopes(self.func.into());
+        let body_file = match source_map.expr_syntax(body.body_expr) {
+            Ok(it) => it.file_id,
+            Err(_) => return,
+        };
+        // Expressions from macro expansions can't be pointed at.
+        let expr_ptr = |expr: ExprId| match source_map.expr_syntax(expr) {
+            Ok(source_ptr) if source_ptr.file_id == body_file => Some(source_ptr.value),
+            _ => None,
+        };
+        let binding_name = |pat: PatId| match &body[pat] {
+            Pat::Bind { name, .. } => Some(name.clone()),
+            _ => None,
+        };
+
+        for error in move_errors(db, self.func, &body, &scopes, &self.infer) {
+            match error {
+                MoveError::UseAfterMove { binding, moved, used } => {
+                    if let (Some(used), Some(moved), Some(name)) =
+                        (expr_ptr(used), expr_ptr(moved), binding_name(binding))
+                    {
+                        self.sink.push(UseAfterMove { file: body_file, used, moved, name })
+                    }
+                }
+                MoveError::BorrowConflict {
+                    binding,
+                    access,
+                    kind,
+                    borrow,
+                    mutable_borrow,
+                    later_use,
+                } => {
+                    if let (Some(access), Some(borrow), Some(later_use), Some(name)) = (
+                        expr_ptr(access),
+                        expr_ptr(borrow),
+                        expr_ptr(later_use),
+                        binding_name(binding),
+                    ) {
+                        self.sink.push(BorrowConflict {
+                            file: body_file,
+                            access,
+                            kind,
+                            borrow,
+                            mutable_borrow,
+                            later_use,
+                            name,
+                        })
+                    }
+                }
+            }
+        }
+    }
+
+    fn validate_reachability(&mut self, db: &dyn HirDatabase) {
+        let (body, source_map) = db.body_with_source_map(self.func.into());
+        let dead_code_node = |item| -> Option<InFile<SyntaxNode>> {
+            match item {
+                DeadCode::Expr(expr) => {
+                    let source_ptr = source_map.expr_syntax(expr).ok()?;
+                    let root = source_ptr.file_syntax(db.upcast());
+                    let node = source_ptr.value.to_node(&root).syntax().clone();
+                    let node = match node.parent().and_then(ast::ExprStmt::cast) {
+                        Some(stmt) => stmt.syntax().clone(),
+                        None => node,
+                    };
+                    Some(InFile::new(source_ptr.file_id, node))
+                }
+                DeadCode::Let(pat) => {
+                    let source_ptr = source_map.pat_syntax(pat).ok()?;
+                    let root = source_ptr.file_syntax(db.upcast());
+                    let node = source_ptr
+                        .value
+                        .either(|it| it.syntax_node_ptr(), |it| it.syntax_node_ptr())
+                        .to_node(&root);
+                    let stmt = node.ancestors().find_map(ast::LetStmt::cast)?;
+                    Some(InFile::new(source_ptr.file_id, stmt.syntax().clone()))
+                }
+            }
+        };
+
+        for (first, last) in unreachable_code(&body, &self.infer) {
+            let (first, last) = match (dead_code_node(first), dead_code_node(last)) {
+                (Some(first), Some(last)) if first.file_id == last.file_id => (first, last),
+                _ => continue,
+            };
+            self.sink.push(UnreachableCode {
+                file: first.file_id,
+                node: SyntaxNodePtr::new(&first.value),
+                range: TextRange::from_to(
+                    first.value.text_range().start(),
+                    last.value.text_range().end(),
+                ),
+            })
+        }
+    }
+
+    fn validate_unused_results(&mut self, db: &dyn HirDatabase) {
+        let (body, source_map) = db.body_with_source_map(self.func.into());
+        let resolver = self.func.resolver(db.upcast());
+        let std_result = resolver
+            .resolve_known_enum(db.upcast(), &path![std::result::Result])
+            .map(AdtId::EnumId);
+        let adt_of = |ty: &Ty| match ty {
+            Ty::Apply(ApplicationTy { ctor: TypeCtor::Adt(it), .. }) => Some(*it),
+            _ => None,
+        };
+        let sig = db.callable_item_signature(self.func.into());
+        let returns_result = std_result.is_some() && adt_of(sig.value.ret()) == std_result;
+        // `?` in closures and async blocks doesn't return from the function.
+        let nested = nested_bodies(&body);
+
+        for (block, expr) in body.exprs.iter() {
+            let statements = match expr {
+                Expr::Block { statements, .. } => statements,
+                _ => continue,
+            };
+            for stmt in statements {
+                let call = match stmt {
+                    Statement::Expr(it) => *it,
+                    _ => continue,
+                };
+                let function = match &body[call] {
+                    Expr::Call { callee, .. } => match &self.infer[*callee] {
+                        Ty::Apply(ApplicationTy {
+                            ctor: TypeCtor::FnDef(CallableDef::FunctionId(it)),
+                            ..
+                        }) => Some(*it),
+                        _ => None,
+                    },
+                    Expr::MethodCall { .. } => self.infer.method_resolution(call),
+                    _ => continue,
+                };
+                let function = function.filter(|&it| db.attrs(it.into()).is_must_use());
+                let adt = adt_of(&self.infer[call]);
+                let must_use_adt = adt.filter(|&it| db.attrs(it.into()).is_must_use());
+                if function.is_none() && must_use_adt.is_none() {
+                    continue;
+                }
+
+                let source_ptr = match source_map.expr_syntax(call) {
+                    Ok(it) if !it.file_id.is_macro() => it,
+                    _ => continue,
+                };
+                let is_result = adt.is_some() && adt == std_result;
+                self.sink.push(UnusedMustUse {
+                    file: source_ptr.file_id,
+                    expr: source_ptr.value,
+                    function: function.map(|it| db.function_data(it).name.clone()),
+                    ty: must_use_adt.map(|it| match it {
+                        AdtId::StructId(it) => db.struct_data(it).name.clone(),
+                        AdtId::UnionId(it) => db.union_data(it).name.clone(),
+                        AdtId::EnumId(it) => db.enum_data(it).name.clone(),
+                    }),
+                    is_result,
+                    can_propagate: is_result && returns_result && !nested.contains(&block),
+                })
+            }
+        }
+    }
+
+    fn validate_variadic_calls(&mut self, db: &dyn HirDatabase) {
+        let (body, source_map) = db.body_with_source_map(self.func.into());
+        let std_option = self
+            .func
+            .resolver(db.upcast())
+            .resolve_known_enum(db.upcast(), &path![std::option::Option])
+            .map(AdtId::EnumId);
+        for (_, expr) in body.exprs.iter() {
+            let (callee, args) = match expr {
+                Expr::Call { callee, args } => (*callee, args),
+                _ => continue,
+            };
+            let function = match &self.infer[callee] {
+                Ty::Apply(ApplicationTy {
+                    ctor: TypeCtor::FnDef(CallableDef::FunctionId(it)),
+                    ..
+                }) => *it,
+                _ => continue,
+            };
+            let data = db.function_data(function);
+            if !data.is_varargs {
+                continue;
+            }
+            for &arg in args.iter().skip(data.params.len()) {
+                let ty = &self.infer[arg];
+                let cast_to = match check_variadic_arg(db, ty, std_option) {
+                    VariadicArg::Valid => continue,
+                    VariadicArg::CastTo(it) => Some(it),
+                    VariadicArg::NotFfiSafe => None,
+                };
+                if let Ok(source_ptr) = source_map.expr_syntax(arg) {
+                    self.sink.push(InvalidVariadicArg {
+                        file: source_ptr.file_id,
+                        arg: source_ptr.value,
+                        ty: ty.display(db).to_string(),
+                        cast_to,
+                    })
+                }
+            }
+        }
+    }
+
     fn validate_results_in_tail_expr(&mut self, body_id: ExprId, id: ExprId, db: &dyn HirDatabase) {
         // the mismatch will be on the whole block currently
         let mismatch = match self.infer.type_mismatch_for_expr(body_id) {
@@ -244,11 +540,116 @@ impl<'a, 'b> ExprValidator<'a, 'b> {
             let (_, source_map) = db.body_with_source_map(self.func.into());
 
             if let Ok(source_ptr) = source_map.expr_syntax(id) {
-                self.sink
-                    .push(MissingOkInTailExpr { file: source_ptr.file_id, expr: source_ptr.value });
+                let source = self.func.lookup(db.upcast()).source(db.upcast());
+                let ret_type =
+                    source.value.ret_type().map(|it| InFile::new(source.file_id, AstPtr::new(&it)));
+                self.sink.push(MissingOkInTailExpr {
+                    file: source_ptr.file_id,
+                    expr: source_ptr.value,
+                    ret_type,
+                });
+            }
+        }
+    }
+}
+
+enum VariadicArg {
+    Valid,
+    CastTo(String),
+    NotFfiSafe,
+}
+
+/// Checks a value passed as one of the variadic arguments of a C function.
+/// Values of the types C promotes have to be cast first, and values without a
+/// C layout can't be passed at all.
+fn check_variadic_arg(db: &dyn HirDatabase, ty: &Ty, std_option: Option<AdtId>) -> VariadicArg {
+    let a_ty = match ty {
+        Ty::Apply(it) => it,
+        Ty::Dyn(_) => return VariadicArg::NotFfiSafe,
+        _ => return VariadicArg::Valid,
+    };
+    match a_ty.ctor {
+        TypeCtor::Bool => VariadicArg::CastTo("i32".to_string()),
+        TypeCtor::Int(Uncertain::Known(int)) => match (int.signedness, int.bitness) {
+            (Signedness::Signed, IntBitness::X8) | (Signedness::Signed, IntBitness::X16) => {
+                VariadicArg::CastTo("i32".to_string())
+            }
+            (Signedness::Unsigned, IntBitness::X8) | (Signedness::Unsigned, IntBitness::X16) => {
+                VariadicArg::CastTo("u32".to_string())
+            }
+            (_, IntBitness::X128) => VariadicArg::NotFfiSafe,
+            _ => VariadicArg::Valid,
+        },
+        TypeCtor::Float(Uncertain::Known(float)) if float.bitness == FloatBitness::X32 => {
+            VariadicArg::CastTo("f64".to_string())
+        }
+        TypeCtor::FnDef(def) => {
+            let abi = def.abi(db);
+            match ty.callable_sig(db) {
+                Some(sig) => {
+                    let num_args = sig.params_and_return.len() as u16 - 1;
+                    let fn_ptr =
+                        Ty::apply(TypeCtor::FnPtr { num_args, abi }, Substs(sig.params_and_return));
+                    VariadicArg::CastTo(fn_ptr.display(db).to_string())
+                }
+                None => VariadicArg::NotFfiSafe,
+            }
+        }
+        TypeCtor::FnPtr { abi, .. } if abi == Abi::Rust => VariadicArg::NotFfiSafe,
+        // Pointers to unsized types are fat.
+        TypeCtor::RawPtr(_) | TypeCtor::Ref(_) => match a_ty.parameters.as_single() {
+            Ty::Apply(ApplicationTy { ctor: TypeCtor::Str, .. })
+            | Ty::Apply(ApplicationTy { ctor: TypeCtor::Slice, .. })
+            | Ty::Dyn(_) => VariadicArg::NotFfiSafe,
+            _ => VariadicArg::Valid,
+        },
+        // `None` of a non-null pointer is passed as a null pointer.
+        TypeCtor::Adt(adt) if Some(adt) == std_option => match a_ty.parameters.as_single() {
+            inner @ Ty::Apply(ApplicationTy { ctor: TypeCtor::Ref(_), .. })
+            | inner @ Ty::Apply(ApplicationTy { ctor: TypeCtor::FnPtr { .. }, .. }) => {
+                check_variadic_arg(db, inner, std_option)
+            }
+            _ => VariadicArg::NotFfiSafe,
+        },
+        TypeCtor::Adt(adt) => match db.attrs(adt.into()).repr() {
+            Some(repr) if repr.c || repr.transparent || repr.int.is_some() => VariadicArg::Valid,
+            _ => VariadicArg::NotFfiSafe,
+        },
+        TypeCtor::Char
+        | TypeCtor::Str
+        | TypeCtor::Slice
+        | TypeCtor::Array
+        | TypeCtor::Tuple { .. }
+        | TypeCtor::Never
+        | TypeCtor::Closure { .. } => VariadicArg::NotFfiSafe,
+        TypeCtor::Int(Uncertain::Unknown)
+        | TypeCtor::Float(_)
+        | TypeCtor::FnPtr { .. }
+        | TypeCtor::AssociatedType(_) => VariadicArg::Valid,
+    }
+}
+
+fn collect_pats(body: &Body, pat: PatId, acc: &mut FxHashSet<PatId>) {
+    acc.insert(pat);
+    body[pat].walk_child_pats(|it| collect_pats(body, it, acc));
+}
+
+/// Returns the expressions inside of closures and async blocks.
+fn nested_bodies(body: &Body) -> FxHashSet<ExprId> {
+    fn collect_exprs(body: &Body, expr: ExprId, acc: &mut FxHashSet<ExprId>) {
+        acc.insert(expr);
+        body[expr].walk_child_exprs(|it| collect_exprs(body, it, acc));
+    }
+    let mut acc = FxHashSet::default();
+    for (_, expr) in body.exprs.iter() {
+        match expr {
+            Expr::Lambda { body: inner, .. } | Expr::Async { body: inner } => {
+                collect_exprs(body, *inner, &mut acc)
             }
+            _ => {}
         }
     }
+    acc
 }
 
 pub fn record_literal_missing_fields(
@@ -79,6 +79,31 @@ pub(crate) fn infer_query(db: &dyn HirDatabase, def: DefWithBodyId) -> Arc<Infer
     Arc::new(ctx.resolve_all())
 }
 
+/// Infers the hidden type of an `fn foo() -> impl Trait` function, i.e. the
+/// type its body actually returns.
+///
+/// Calls to other such functions aren't leaked while doing this, which
+/// would lead to cycles for recursive functions.
+pub(crate) fn hidden_return_type_query(db: &dyn HirDatabase, func: FunctionId) -> Option<Ty> {
+    let _p = profile("hidden_return_type_query");
+    let data = db.function_data(func);
+    match &*data.ret_type {
+        TypeRef::ImplTrait(_) => {}
+        _ => return None,
+    }
+    let resolver = func.resolver(db.upcast());
+    let mut ctx = InferenceContext::new(db, func.into(), resolver);
+    ctx.leak_opaque_types = false;
+    ctx.collect_fn(&data);
+    ctx.infer_body();
+
+    let return_ty = ctx.return_ty.clone();
+    match ctx.table.resolve_ty_completely(return_ty) {
+        Ty::Unknown => None,
+        ty => Some(ty),
+    }
+}
+
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 enum ExprOrPatId {
     ExprId(ExprId),
@@ -206,6 +231,9 @@ struct InferenceContext<'a> {
     /// closures, but currently this is the only field that will change there,
     /// so it doesn't make sense.
     return_ty: Ty,
+    /// Whether calls to `fn foo() -> impl Trait` functions of the same crate
+    /// get the hidden type instead of the opaque one.
+    leak_opaque_types: bool,
 }
 
 impl<'a> InferenceContext<'a> {
@@ -215,6 +243,7 @@ impl<'a> InferenceContext<'a> {
             table: unify::InferenceTable::new(),
             obligations: Vec::default(),
             return_ty: Ty::Unknown, // set in collect_fn_signature
+            leak_opaque_types: true,
             trait_env: TraitEnvironment::lower(db, &resolver),
             db,
             owner,
@@ -80,13 +80,16 @@ impl<'a> InferenceContext<'a> {
             ) => return false,
 
             // `{function_type}` -> `fn()`
-            (ty_app!(TypeCtor::FnDef(_)), ty_app!(TypeCtor::FnPtr { .. })) => {
+            (ty_app!(TypeCtor::FnDef(def)), ty_app!(TypeCtor::FnPtr { .. })) => {
+                let abi = def.abi(self.db);
                 match from_ty.callable_sig(self.db) {
                     None => return false,
                     Some(sig) => {
                         let num_args = sig.params_and_return.len() as u16 - 1;
-                        from_ty =
-                            Ty::apply(TypeCtor::FnPtr { num_args }, Substs(sig.params_and_return));
+                        from_ty = Ty::apply(
+                            TypeCtor::FnPtr { num_args, abi },
+                            Substs(sig.params_and_return),
+                        );
                     }
                 }
             }
@@ -8,7 +8,8 @@ use hir_def::{
     expr::{Array, BinaryOp, Expr, ExprId, Literal, Statement, UnaryOp},
     path::{GenericArg, GenericArgs},
     resolver::resolver_for_expr,
-    AdtId, AssocContainerId, Lookup, StructFieldId,
+    type_ref::Abi,
+    AdtId, AssocContainerId, DefWithBodyId, HasModule, Lookup, StructFieldId,
 };
 use hir_expand::name::Name;
 use ra_syntax::ast::RangeOp;
@@ -17,8 +18,8 @@ use crate::{
     autoderef, method_resolution, op,
     traits::InEnvironment,
     utils::{generics, variant_data, Generics},
-    ApplicationTy, Binders, CallableDef, InferTy, IntTy, Mutability, Obligation, Substs, TraitRef,
-    Ty, TypeCtor, Uncertain,
+    ApplicationTy, Binders, CallableDef, GenericPredicate, InferTy, IntTy, Mutability, Obligation,
+    Substs, TraitRef, Ty, TypeCtor, TypeWalk, Uncertain,
 };
 
 use super::{BindingMode, Expectation, InferenceContext, InferenceDiagnostic, TypeMismatch};
@@ -78,6 +79,11 @@ impl<'a> InferenceContext<'a> {
                 // FIXME should be std::result::Result<{inner}, _>
                 Ty::Unknown
             }
+            Expr::Async { body } => {
+                let _inner = self.infer_expr(*body, &Expectation::none());
+                // FIXME should be impl Future<Output = {inner}>
+                Ty::Unknown
+            }
             Expr::Loop { body } => {
                 self.infer_expr(*body, &Expectation::has_type(Ty::unit()));
                 // FIXME handle break with value
@@ -99,7 +105,7 @@ impl<'a> InferenceContext<'a> {
                 self.infer_expr(*body, &Expectation::has_type(Ty::unit()));
                 Ty::unit()
             }
-            Expr::Lambda { body, args, ret_type, arg_types } => {
+            Expr::Lambda { body, args, ret_type, arg_types, .. } => {
                 assert_eq!(args.len(), arg_types.len());
 
                 let mut sig_tys = Vec::new();
@@ -121,7 +127,7 @@ impl<'a> InferenceContext<'a> {
                 };
                 sig_tys.push(ret_ty.clone());
                 let sig_ty = Ty::apply(
-                    TypeCtor::FnPtr { num_args: sig_tys.len() as u16 - 1 },
+                    TypeCtor::FnPtr { num_args: sig_tys.len() as u16 - 1, abi: Abi::Rust },
                     Substs(sig_tys.into()),
                 );
                 let closure_ty =
@@ -152,6 +158,7 @@ impl<'a> InferenceContext<'a> {
                 };
                 self.register_obligations_for_call(&callee_ty);
                 self.check_call_arguments(args, &param_tys);
+                let ret_ty = self.leak_opaque_ret_ty(&callee_ty, ret_ty);
                 self.normalize_associated_types_in(ret_ty)
             }
             Expr::MethodCall { receiver, args, method_name, generic_args } => self
@@ -620,9 +627,62 @@ impl<'a> InferenceContext<'a> {
         self.unify(&expected_receiver_ty, &actual_receiver_ty);
 
         self.check_call_arguments(args, &param_tys);
+        let ret_ty = self.leak_opaque_ret_ty(&method_ty, ret_ty);
         self.normalize_associated_types_in(ret_ty)
     }
 
+    /// Replaces the `impl Trait` return type of a function from the current
+    /// crate by the hidden type, so that the methods of that type are known
+    /// to the callers. Signatures still show the `impl Trait`.
+    fn leak_opaque_ret_ty(&mut self, callee_ty: &Ty, ret_ty: Ty) -> Ty {
+        if !self.leak_opaque_types {
+            return ret_ty;
+        }
+        let (bounds, func, substs) = match (&ret_ty, callee_ty) {
+            (
+                Ty::Opaque(bounds),
+                Ty::Apply(ApplicationTy {
+                    ctor: TypeCtor::FnDef(CallableDef::FunctionId(func)),
+                    parameters,
+                }),
+            ) => (bounds.clone(), *func, parameters.clone()),
+            _ => return ret_ty,
+        };
+        let krate = DefWithBodyId::from(func).module(self.db.upcast()).krate;
+        if krate != self.owner.module(self.db.upcast()).krate {
+            return ret_ty;
+        }
+        let hidden_ty = match self.db.hidden_return_type(func) {
+            Some(it) => it,
+            None => return ret_ty,
+        };
+        // the hidden type is in terms of the function's own type parameters
+        let generics = generics(self.db.upcast(), func.into());
+        let hidden_ty = hidden_ty.fold(&mut |ty| match ty {
+            Ty::Placeholder(id) => generics
+                .param_idx(id)
+                .and_then(|idx| substs.get(idx))
+                .cloned()
+                .unwrap_or(Ty::Unknown),
+            _ => ty,
+        });
+        // Stub bodies like `{}` don't produce a type implementing the bounds,
+        // and the opaque type is more useful then.
+        for bound in bounds.iter() {
+            let trait_ref = match bound {
+                GenericPredicate::Implemented(it) => it.clone(),
+                _ => continue,
+            };
+            let trait_ref = trait_ref.subst_bound_vars(&Substs::single(hidden_ty.clone()));
+            let goal = InEnvironment::new(self.trait_env.clone(), Obligation::Trait(trait_ref));
+            let canonicalized = self.canonicalizer().canonicalize_obligation(goal);
+            if self.db.trait_solve(krate, canonicalized.value).is_none() {
+                return ret_ty;
+            }
+        }
+        hidden_ty
+    }
+
     fn check_call_arguments(&mut self, args: &[ExprId], param_tys: &[Ty]) {
         // Quoting https://github.com/rust-lang/rust/blob/6ef275e6c3cb1384ec78128eceeb4963ff788dca/src/librustc_typeck/check/mod.rs#L3325 --
         // We do this in a pretty awful way: first we type-check any arguments
@@ -37,6 +37,8 @@ pub(crate) mod utils;
 pub mod db;
 pub mod diagnostics;
 pub mod expr;
+pub mod consteval;
+pub mod pretty;
 
 #[cfg(test)]
 mod tests;
@@ -44,14 +46,19 @@ mod tests;
 mod test_db;
 mod marks;
 mod _match;
+mod mutation;
+mod moves;
+mod reachability;
 
 use std::ops::Deref;
 use std::sync::Arc;
 use std::{iter, mem};
 
 use hir_def::{
-    expr::ExprId, type_ref::Mutability, AdtId, AssocContainerId, DefWithBodyId, GenericDefId,
-    HasModule, Lookup, TraitId, TypeAliasId, TypeParamId,
+    expr::ExprId,
+    type_ref::{Abi, Mutability},
+    AdtId, AssocContainerId, DefWithBodyId, GenericDefId, HasModule, Lookup, TraitId, TypeAliasId,
+    TypeParamId,
 };
 use ra_db::{impl_intern_key, salsa, CrateId};
 
@@ -131,7 +138,7 @@ pub enum TypeCtor {
     /// fn foo() -> i32 { 1 }
     /// let bar: fn() -> i32 = foo;
     /// ```
-    FnPtr { num_args: u16 },
+    FnPtr { num_args: u16, abi: Abi },
 
     /// The never type `!`.
     Never,
@@ -186,7 +193,7 @@ impl TypeCtor {
                 let generic_params = generics(db.upcast(), type_alias.into());
                 generic_params.len()
             }
-            TypeCtor::FnPtr { num_args } => num_args as usize + 1,
+            TypeCtor::FnPtr { num_args, .. } => num_args as usize + 1,
             TypeCtor::Tuple { cardinality } => cardinality as usize,
         }
     }
@@ -707,7 +714,9 @@ impl Ty {
         }
     }
 
-    /// If this is a `dyn Trait` type, this returns the `Trait` part.
+    /// If this is a `dyn Trait` type, this returns the `Trait` part. Auto
+    /// traits come after it, so this is the `Trait` in `dyn Send + Trait`
+    /// too.
     pub fn dyn_trait_ref(&self) -> Option<&TraitRef> {
         match self {
             Ty::Dyn(bounds) => bounds.get(0).and_then(|b| match b {
@@ -770,17 +779,17 @@ impl Ty {
         }
     }
 
-    /// If this is an `impl Trait` or `dyn Trait`, returns that trait.
-    pub fn inherent_trait(&self) -> Option<TraitId> {
-        match self {
-            Ty::Dyn(predicates) | Ty::Opaque(predicates) => {
-                predicates.iter().find_map(|pred| match pred {
-                    GenericPredicate::Implemented(tr) => Some(tr.trait_),
-                    _ => None,
-                })
-            }
+    /// If this is an `impl Trait` or `dyn Trait`, returns the traits in its
+    /// bounds, including auto traits like `Send`.
+    pub fn inherent_traits(&self) -> impl Iterator<Item = TraitId> + '_ {
+        let predicates: &[GenericPredicate] = match self {
+            Ty::Dyn(predicates) | Ty::Opaque(predicates) => predicates,
+            _ => &[],
+        };
+        predicates.iter().filter_map(|pred| match pred {
+            GenericPredicate::Implemented(tr) => Some(tr.trait_),
             _ => None,
-        }
+        })
     }
 }
 
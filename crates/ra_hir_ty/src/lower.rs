@@ -14,7 +14,7 @@ use hir_def::{
     generics::{TypeParamProvenance, WherePredicate, WherePredicateTarget},
     path::{GenericArg, Path, PathSegment, PathSegments},
     resolver::{HasResolver, Resolver, TypeNs},
-    type_ref::{TypeBound, TypeRef},
+    type_ref::{Abi, TypeBound, TypeRef},
     AdtId, AssocContainerId, ConstId, EnumId, EnumVariantId, FunctionId, GenericDefId, HasModule,
     ImplId, LocalStructFieldId, Lookup, StaticId, StructId, TraitId, TypeAliasId, TypeParamId,
     UnionId, VariantId,
@@ -113,7 +113,7 @@ impl Ty {
                 let inner_ty = Ty::from_hir(ctx, inner);
                 Ty::apply_one(TypeCtor::RawPtr(*mutability), inner_ty)
             }
-            TypeRef::Array(inner) => {
+            TypeRef::Array(inner, _) => {
                 let inner_ty = Ty::from_hir(ctx, inner);
                 Ty::apply_one(TypeCtor::Array, inner_ty)
             }
@@ -126,17 +126,29 @@ impl Ty {
                 Ty::apply_one(TypeCtor::Ref(*mutability), inner_ty)
             }
             TypeRef::Placeholder => Ty::Unknown,
-            TypeRef::Fn(params) => {
+            TypeRef::Fn(params, abi) => {
                 let sig = Substs(params.iter().map(|tr| Ty::from_hir(ctx, tr)).collect());
-                Ty::apply(TypeCtor::FnPtr { num_args: sig.len() as u16 - 1 }, sig)
+                Ty::apply(TypeCtor::FnPtr { num_args: sig.len() as u16 - 1, abi: *abi }, sig)
             }
             TypeRef::DynTrait(bounds) => {
                 let self_ty = Ty::Bound(BoundVar::new(DebruijnIndex::INNERMOST, 0));
-                let predicates = bounds
+                let mut predicates: Vec<_> = bounds
                     .iter()
                     .flat_map(|b| GenericPredicate::from_type_bound(ctx, b, self_ty.clone()))
                     .collect();
-                Ty::Dyn(predicates)
+                // Put the auto traits after the principal trait, in a fixed
+                // order and without duplicates, so that `dyn Send + Trait + Sync`
+                // is the same type as `dyn Trait + Sync + Send`. This is a
+                // stable sort, and auto traits have no associated types, so
+                // projections still follow their trait.
+                predicates.sort_by_key(|p| match p {
+                    GenericPredicate::Implemented(tr) if ctx.db.trait_data(tr.trait_).auto => {
+                        Some(tr.trait_)
+                    }
+                    _ => None,
+                });
+                predicates.dedup();
+                Ty::Dyn(predicates.into())
             }
             TypeRef::ImplTrait(bounds) => {
                 match ctx.impl_trait_mode {
@@ -540,7 +552,7 @@ impl TraitRef {
     ) -> Option<TraitRef> {
         match bound {
             TypeBound::Path(path) => TraitRef::from_path(ctx, path, Some(self_ty)),
-            TypeBound::Error => None,
+            TypeBound::Lifetime(_) | TypeBound::Error => None,
         }
     }
 }
@@ -574,12 +586,17 @@ impl GenericPredicate {
         self_ty: Ty,
     ) -> impl Iterator<Item = GenericPredicate> + 'a {
         let trait_ref = TraitRef::from_type_bound(ctx, bound, self_ty);
-        iter::once(trait_ref.clone().map_or(GenericPredicate::Error, GenericPredicate::Implemented))
-            .chain(
-                trait_ref
-                    .into_iter()
-                    .flat_map(move |tr| assoc_type_bindings_from_type_bound(ctx, bound, tr)),
-            )
+        let predicate = match (&trait_ref, bound) {
+            (Some(tr), _) => Some(GenericPredicate::Implemented(tr.clone())),
+            // lifetimes aren't tracked in types, so the bound is just dropped
+            (None, TypeBound::Lifetime(_)) => None,
+            (None, _) => Some(GenericPredicate::Error),
+        };
+        predicate.into_iter().chain(
+            trait_ref
+                .into_iter()
+                .flat_map(move |tr| assoc_type_bindings_from_type_bound(ctx, bound, tr)),
+        )
     }
 }
 
@@ -590,7 +607,7 @@ fn assoc_type_bindings_from_type_bound<'a>(
 ) -> impl Iterator<Item = GenericPredicate> + 'a {
     let last_segment = match bound {
         TypeBound::Path(path) => path.segments().last(),
-        TypeBound::Error => None,
+        TypeBound::Lifetime(_) | TypeBound::Error => None,
     };
     last_segment
         .into_iter()
@@ -889,6 +906,13 @@ impl CallableDef {
         }
         .krate
     }
+
+    pub fn abi(self, db: &dyn HirDatabase) -> Abi {
+        match self {
+            CallableDef::FunctionId(f) => db.function_data(f).abi,
+            CallableDef::StructId(_) | CallableDef::EnumVariantId(_) => Abi::Rust,
+        }
+    }
 }
 
 impl From<CallableDef> for GenericDefId {
@@ -40,19 +40,36 @@ impl TyFingerprint {
             _ => None,
         }
     }
+
+    /// Like `for_impl`, but looks through references, so that `impl Trait for
+    /// &S` is considered an impl of `S`. Used for IDE-level impl searches.
+    fn for_impl_search(ty: &Ty) -> Option<TyFingerprint> {
+        match ty {
+            Ty::Apply(ApplicationTy { ctor: TypeCtor::Ref(..), parameters }) => {
+                TyFingerprint::for_impl(parameters.as_single())
+            }
+            _ => TyFingerprint::for_impl(ty),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct CrateImplDefs {
     impls: FxHashMap<TyFingerprint, Vec<ImplId>>,
     impls_by_trait: FxHashMap<TraitId, FxHashMap<Option<TyFingerprint>, Vec<ImplId>>>,
+    /// Reverse index from self types to trait impls, so that we can find all
+    /// impls of a type without walking every trait.
+    trait_impls_by_self_ty: FxHashMap<TyFingerprint, Vec<ImplId>>,
 }
 
 impl CrateImplDefs {
     pub(crate) fn impls_in_crate_query(db: &dyn HirDatabase, krate: CrateId) -> Arc<CrateImplDefs> {
         let _p = profile("impls_in_crate_query");
-        let mut res =
-            CrateImplDefs { impls: FxHashMap::default(), impls_by_trait: FxHashMap::default() };
+        let mut res = CrateImplDefs {
+            impls: FxHashMap::default(),
+            impls_by_trait: FxHashMap::default(),
+            trait_impls_by_self_ty: FxHashMap::default(),
+        };
 
         let crate_def_map = db.crate_def_map(krate);
         for (_module_id, module_data) in crate_def_map.modules.iter() {
@@ -67,6 +84,9 @@ impl CrateImplDefs {
                             .entry(self_ty_fp)
                             .or_default()
                             .push(impl_id);
+                        if let Some(fp) = TyFingerprint::for_impl_search(&self_ty.value) {
+                            res.trait_impls_by_self_ty.entry(fp).or_default().push(impl_id);
+                        }
                     }
                     None => {
                         let self_ty = db.impl_self_ty(impl_id);
@@ -85,6 +105,15 @@ impl CrateImplDefs {
         fingerprint.and_then(|f| self.impls.get(&f)).into_iter().flatten().copied()
     }
 
+    /// Returns both inherent and trait impls for the given type, including
+    /// trait impls for references to it.
+    pub fn lookup_all_impl_defs_for_ty(&self, ty: &Ty) -> impl Iterator<Item = ImplId> + '_ {
+        let fingerprint = TyFingerprint::for_impl_search(ty);
+        let inherent = fingerprint.and_then(|f| self.impls.get(&f)).into_iter().flatten();
+        let traits = fingerprint.and_then(|f| self.trait_impls_by_self_ty.get(&f));
+        inherent.chain(traits.into_iter().flatten()).copied()
+    }
+
     pub fn lookup_impl_defs_for_trait(&self, tr: TraitId) -> impl Iterator<Item = ImplId> + '_ {
         self.impls_by_trait
             .get(&tr)
@@ -408,8 +437,8 @@ fn iterate_trait_method_candidates<T>(
     receiver_ty: Option<&Canonical<Ty>>,
     mut callback: impl FnMut(&Ty, AssocItemId) -> Option<T>,
 ) -> Option<T> {
-    // if ty is `impl Trait` or `dyn Trait`, the trait doesn't need to be in scope
-    let inherent_trait = self_ty.value.inherent_trait().into_iter();
+    // if ty is `impl Trait` or `dyn Trait`, the traits don't need to be in scope
+    let inherent_traits = self_ty.value.inherent_traits();
     let env_traits = if let Ty::Placeholder(_) = self_ty.value {
         // if we have `T: Trait` in the param env, the trait doesn't need to be in scope
         env.trait_predicates_for_self_ty(&self_ty.value)
@@ -420,7 +449,7 @@ fn iterate_trait_method_candidates<T>(
         Vec::new()
     };
     let traits =
-        inherent_trait.chain(env_traits.into_iter()).chain(traits_in_scope.iter().copied());
+        inherent_traits.chain(env_traits.into_iter()).chain(traits_in_scope.iter().copied());
     'traits: for t in traits {
         let data = db.trait_data(t);
 
@@ -601,7 +630,7 @@ pub fn implements_trait(
     krate: CrateId,
     trait_: TraitId,
 ) -> bool {
-    if ty.value.inherent_trait() == Some(trait_) {
+    if ty.value.inherent_traits().any(|it| it == trait_) {
         // FIXME this is a bit of a hack, since Chalk should say the same thing
         // anyway, but currently Chalk doesn't implement `dyn/impl Trait` yet
         return true;
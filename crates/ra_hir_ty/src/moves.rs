@@ -0,0 +1,446 @@
+//! Finds uses of moved bindings, and accesses of borrowed bindings while the
+//! borrow is still used later, in straight-line code.
+//!
+//! This is a rough approximation of the borrow checker, which rather misses
+//! errors than reports wrong ones:
+//! - only whole bindings are tracked, so moving out of a field or
+//!   destructuring doesn't count as a move;
+//! - only borrows of a whole binding which are stored in another binding by a
+//!   `let`, like `let r = &mut x;`, are tracked;
+//! - moves and borrows in branches, loops and closures are forgotten after
+//!   them, so code is only checked against what runs unconditionally before
+//!   it;
+//! - a moved binding which is assigned in any of them is assumed to be
+//!   initialized again after them.
+
+use std::sync::Arc;
+
+use hir_def::{
+    body::{scope::ExprScopes, Body},
+    expr::{BinaryOp, BindingAnnotation, Expr, ExprId, Pat, PatId, Statement, UnaryOp},
+    lang_item::LangItemTarget,
+    resolver::HasResolver,
+    type_ref::{Mutability, TypeRef},
+    ContainerId, FunctionId, Lookup, TraitId,
+};
+use hir_expand::name::name;
+use ra_db::CrateId;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    db::HirDatabase, diagnostics::ConflictingAccess, method_resolution::implements_trait,
+    mutation::binds_by_ref_mut, Canonical, InferenceResult, TraitEnvironment, Ty, TypeCtor,
+    TypeWalk,
+};
+
+pub(crate) enum MoveError {
+    UseAfterMove {
+        binding: PatId,
+        moved: ExprId,
+        used: ExprId,
+    },
+    BorrowConflict {
+        binding: PatId,
+        access: ExprId,
+        kind: ConflictingAccess,
+        borrow: ExprId,
+        mutable_borrow: bool,
+        later_use: ExprId,
+    },
+}
+
+pub(crate) fn move_errors(
+    db: &dyn HirDatabase,
+    func: FunctionId,
+    body: &Body,
+    scopes: &ExprScopes,
+    infer: &InferenceResult,
+) -> Vec<MoveError> {
+    let resolver = func.resolver(db.upcast());
+    let krate = match resolver.krate() {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    let copy_trait = match db.lang_item(krate, "copy".into()) {
+        Some(LangItemTarget::TraitId(it)) => Some(it),
+        _ => None,
+    };
+    let mut checker = MoveChecker {
+        db,
+        body,
+        scopes,
+        infer,
+        krate,
+        env: TraitEnvironment::lower(db, &resolver),
+        copy_trait,
+        reported: FxHashSet::default(),
+        res: Vec::new(),
+    };
+    checker.walk(body.body_expr, Access::Move, &mut State::default());
+    checker.res
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// Reading or borrowing immutably.
+    Read,
+    /// Assigning to a place in it or borrowing mutably.
+    Write,
+    Move,
+}
+
+#[derive(Clone)]
+struct Borrow {
+    borrowed: PatId,
+    expr: ExprId,
+    mutable: bool,
+}
+
+#[derive(Clone, Default)]
+struct State {
+    /// The moved bindings, with the expression which moves them.
+    moved: FxHashMap<PatId, ExprId>,
+    /// The tracked borrows, by the binding which holds them.
+    borrows: FxHashMap<PatId, Borrow>,
+    /// Accesses which conflict with a borrow, with the binding which holds it.
+    /// They are errors if that binding is used later.
+    conflicts: Vec<(PatId, ExprId, ConflictingAccess)>,
+}
+
+struct MoveChecker<'a> {
+    db: &'a dyn HirDatabase,
+    body: &'a Body,
+    scopes: &'a ExprScopes,
+    infer: &'a InferenceResult,
+    krate: CrateId,
+    env: Arc<TraitEnvironment>,
+    copy_trait: Option<TraitId>,
+    /// Bindings with a reported use after move, to report only the first.
+    reported: FxHashSet<PatId>,
+    res: Vec<MoveError>,
+}
+
+impl MoveChecker<'_> {
+    /// Walks `expr` in evaluation order. `access` is how the context uses the
+    /// value of `expr`.
+    fn walk(&mut self, expr: ExprId, access: Access, state: &mut State) {
+        let body = self.body;
+        match &body[expr] {
+            Expr::Path(_) => {
+                if let Some(binding) = self.resolve_binding(expr) {
+                    self.access(binding, expr, access, state);
+                }
+            }
+            Expr::Block { statements, tail } => {
+                for stmt in statements {
+                    match stmt {
+                        Statement::Let { pat, initializer: Some(init), .. } => {
+                            self.walk(*init, pat_access(body, *pat), state);
+                            self.bind(*pat, *init, state);
+                        }
+                        Statement::Let { initializer: None, .. } => {}
+                        Statement::Expr(it) => self.walk(*it, Access::Read, state),
+                    }
+                }
+                if let Some(tail) = tail {
+                    self.walk(*tail, Access::Move, state);
+                }
+            }
+            Expr::If { condition, then_branch, else_branch } => {
+                self.walk(*condition, Access::Read, state);
+                self.walk_branch(*then_branch, state);
+                if let Some(else_branch) = else_branch {
+                    self.walk_branch(*else_branch, state);
+                }
+            }
+            Expr::Match { expr: scrutinee, arms } => {
+                let access = if arms.iter().any(|arm| binds_by_ref_mut(body, arm.pat)) {
+                    Access::Write
+                } else {
+                    Access::Read
+                };
+                self.walk(*scrutinee, access, state);
+                for arm in arms {
+                    let mut branch = state.clone();
+                    if let Some(guard) = arm.guard {
+                        self.walk(guard, Access::Read, &mut branch);
+                    }
+                    self.walk(arm.expr, Access::Move, &mut branch);
+                    merge_branch(state, &branch);
+                }
+            }
+            Expr::While { condition, body: loop_body } => {
+                let mut branch = state.clone();
+                self.walk(*condition, Access::Read, &mut branch);
+                self.walk(*loop_body, Access::Move, &mut branch);
+                merge_branch(state, &branch);
+            }
+            Expr::For { iterable, body: loop_body, .. } => {
+                self.walk(*iterable, Access::Move, state);
+                self.walk_branch(*loop_body, state);
+            }
+            Expr::Loop { body: inner }
+            | Expr::Lambda { body: inner, .. }
+            | Expr::Async { body: inner } => self.walk_branch(*inner, state),
+            Expr::Call { callee, args } => {
+                self.walk(*callee, Access::Read, state);
+                for arg in args {
+                    self.walk(*arg, Access::Move, state);
+                }
+            }
+            Expr::MethodCall { receiver, args, .. } => {
+                let receiver_access = self.receiver_access(expr, *receiver);
+                self.walk(*receiver, receiver_access, state);
+                for arg in args {
+                    self.walk(*arg, Access::Move, state);
+                }
+            }
+            Expr::Ref { expr: inner, mutability } => {
+                let access = reference_access(*mutability);
+                self.walk(*inner, access, state);
+            }
+            Expr::Field { expr: base, .. } | Expr::UnaryOp { expr: base, op: UnaryOp::Deref } => {
+                self.walk(*base, projection_access(access), state)
+            }
+            Expr::Index { base, index } => {
+                self.walk(*base, projection_access(access), state);
+                self.walk(*index, Access::Move, state);
+            }
+            Expr::RecordLit { fields, spread, .. } => {
+                for field in fields {
+                    self.walk(field.expr, Access::Move, state);
+                }
+                // Only the fields which aren't given are moved out of it.
+                if let Some(spread) = spread {
+                    self.walk(*spread, Access::Read, state);
+                }
+            }
+            Expr::BinaryOp { lhs, rhs, op } => match op {
+                Some(BinaryOp::Assignment { op: None }) => {
+                    self.walk(*rhs, Access::Move, state);
+                    match self.resolve_binding(*lhs) {
+                        Some(binding) => self.assign(binding, *lhs, state),
+                        None => self.walk(*lhs, Access::Write, state),
+                    }
+                }
+                Some(BinaryOp::Assignment { op: Some(_) }) => {
+                    self.walk(*rhs, Access::Move, state);
+                    self.walk(*lhs, Access::Write, state);
+                }
+                Some(BinaryOp::CmpOp(_)) => {
+                    self.walk(*lhs, Access::Read, state);
+                    self.walk(*rhs, Access::Read, state);
+                }
+                Some(BinaryOp::LogicOp(_)) => {
+                    self.walk(*lhs, Access::Read, state);
+                    self.walk_branch(*rhs, state);
+                }
+                Some(BinaryOp::ArithOp(_)) | None => {
+                    self.walk(*lhs, Access::Move, state);
+                    self.walk(*rhs, Access::Move, state);
+                }
+            },
+            it => it.walk_child_exprs(|child| self.walk(child, Access::Move, state)),
+        }
+    }
+
+    /// Walks code which might not run, or might run more than once.
+    fn walk_branch(&mut self, expr: ExprId, state: &mut State) {
+        let mut branch = state.clone();
+        self.walk(expr, Access::Move, &mut branch);
+        merge_branch(state, &branch);
+    }
+
+    fn access(&mut self, binding: PatId, expr: ExprId, access: Access, state: &mut State) {
+        let access = if access == Access::Move && self.is_copy(&self.infer[binding]) {
+            Access::Read
+        } else {
+            access
+        };
+        if let Some(&moved) = state.moved.get(&binding) {
+            if self.reported.insert(binding) {
+                self.res.push(MoveError::UseAfterMove { binding, moved, used: expr });
+            }
+            return;
+        }
+        self.use_borrow(binding, expr, state);
+        let kind = match access {
+            Access::Read => ConflictingAccess::Use,
+            Access::Write => ConflictingAccess::Mutation,
+            Access::Move => ConflictingAccess::Move,
+        };
+        for (&holder, borrow) in state.borrows.iter() {
+            if borrow.borrowed == binding && (borrow.mutable || access != Access::Read) {
+                state.conflicts.push((holder, expr, kind));
+            }
+        }
+        if access == Access::Move {
+            state.moved.insert(binding, expr);
+        }
+    }
+
+    /// Handles `binding = value;`, which doesn't read the binding, so it can
+    /// be moved before.
+    fn assign(&mut self, binding: PatId, expr: ExprId, state: &mut State) {
+        state.moved.remove(&binding);
+        for (&holder, borrow) in state.borrows.iter() {
+            if borrow.borrowed == binding {
+                state.conflicts.push((holder, expr, ConflictingAccess::Mutation));
+            }
+        }
+        if state.borrows.remove(&binding).is_some() {
+            state.conflicts.retain(|(holder, ..)| *holder != binding);
+        }
+    }
+
+    /// Reports the accesses which conflict with the borrow held by `holder`,
+    /// since `expr` uses it.
+    fn use_borrow(&mut self, holder: PatId, expr: ExprId, state: &mut State) {
+        let borrow = match state.borrows.get(&holder) {
+            Some(it) => it.clone(),
+            None => return,
+        };
+        let (conflicts, rest): (Vec<_>, Vec<_>) =
+            state.conflicts.drain(..).partition(|(it, ..)| *it == holder);
+        state.conflicts = rest;
+        for (_, access, kind) in conflicts {
+            self.res.push(MoveError::BorrowConflict {
+                binding: borrow.borrowed,
+                access,
+                kind,
+                borrow: borrow.expr,
+                mutable_borrow: borrow.mutable,
+                later_use: expr,
+            });
+        }
+    }
+
+    /// Tracks the borrow of `let r = &x;`.
+    fn bind(&mut self, pat: PatId, init: ExprId, state: &mut State) {
+        match &self.body[pat] {
+            Pat::Bind { mode: BindingAnnotation::Unannotated, subpat: None, .. }
+            | Pat::Bind { mode: BindingAnnotation::Mutable, subpat: None, .. } => {}
+            _ => return,
+        }
+        if let Expr::Ref { expr: inner, mutability } = &self.body[init] {
+            if let Some(borrowed) = self.resolve_binding(*inner) {
+                let mutable = *mutability == Mutability::Mut;
+                state.borrows.insert(pat, Borrow { borrowed, expr: init, mutable });
+            }
+        }
+    }
+
+    fn resolve_binding(&self, expr: ExprId) -> Option<PatId> {
+        let name = match &self.body[expr] {
+            Expr::Path(path) => path.as_ident()?,
+            _ => return None,
+        };
+        let scope = self.scopes.scope_for(expr)?;
+        Some(self.scopes.resolve_name_in_scope(scope, name)?.pat())
+    }
+
+    fn receiver_access(&self, call: ExprId, receiver: ExprId) -> Access {
+        let func = match self.infer.method_resolution(call) {
+            Some(it) => it,
+            None => return Access::Read,
+        };
+        let data = self.db.function_data(func);
+        if !data.has_self_param {
+            return Access::Read;
+        }
+        match &*data.params[0] {
+            TypeRef::Reference(_, mutability) => reference_access(*mutability),
+            // Taking `self` by value in an impl for a reference type, like
+            // `impl Trait for &S`, autorefs the receiver rather than moving it.
+            TypeRef::Path(path) if path.as_ident() == Some(&name![Self]) => {
+                match func.lookup(self.db.upcast()).container {
+                    ContainerId::ImplId(it) => match &self.db.impl_data(it).target_type {
+                        TypeRef::Reference(_, mutability) => reference_access(*mutability),
+                        _ => Access::Move,
+                    },
+                    // Calls of trait methods resolve to the method of the
+                    // trait, so the receiver is autoref'd if its type doesn't
+                    // implement the trait itself. The autoref is assumed to
+                    // be shared.
+                    ContainerId::TraitId(it) => {
+                        let canonical =
+                            Canonical { value: self.infer[receiver].clone(), num_vars: 0 };
+                        if implements_trait(&canonical, self.db, self.env.clone(), self.krate, it) {
+                            Access::Move
+                        } else {
+                            Access::Read
+                        }
+                    }
+                    _ => Access::Move,
+                }
+            }
+            _ => Access::Move,
+        }
+    }
+
+    /// Whether a moved value of the type can still be used, which is assumed
+    /// if we can't tell.
+    fn is_copy(&self, ty: &Ty) -> bool {
+        // Chalk doesn't know about the types we failed to infer.
+        let mut has_unknown = false;
+        ty.walk(&mut |it| has_unknown |= *it == Ty::Unknown);
+        if has_unknown {
+            return true;
+        }
+        let a_ty = match ty {
+            Ty::Apply(it) => it,
+            Ty::Placeholder(_) => return self.implements_copy(ty),
+            _ => return true,
+        };
+        match a_ty.ctor {
+            TypeCtor::Adt(_) => self.implements_copy(ty),
+            TypeCtor::Tuple { .. } | TypeCtor::Array => {
+                a_ty.parameters.iter().all(|it| self.is_copy(it))
+            }
+            // Mutable references are reborrowed rather than moved, most of the
+            // time.
+            _ => true,
+        }
+    }
+
+    fn implements_copy(&self, ty: &Ty) -> bool {
+        let copy_trait = match self.copy_trait {
+            Some(it) => it,
+            None => return true,
+        };
+        let canonical = Canonical { value: ty.clone(), num_vars: 0 };
+        implements_trait(&canonical, self.db, self.env.clone(), self.krate, copy_trait)
+    }
+}
+
+/// Forgets the moves of the bindings which were assigned in a branch, as
+/// they might be initialized after it.
+fn merge_branch(state: &mut State, branch: &State) {
+    state.moved.retain(|binding, _| branch.moved.contains_key(binding));
+}
+
+/// How borrowing a value with the given mutability accesses it.
+fn reference_access(mutability: Mutability) -> Access {
+    match mutability {
+        Mutability::Mut => Access::Write,
+        Mutability::Shared => Access::Read,
+    }
+}
+
+/// How a `let` or a `match` with the pattern uses the matched value.
+fn pat_access(body: &Body, pat: PatId) -> Access {
+    match &body[pat] {
+        Pat::Bind { mode: BindingAnnotation::Unannotated, subpat: None, .. }
+        | Pat::Bind { mode: BindingAnnotation::Mutable, subpat: None, .. } => Access::Move,
+        _ if binds_by_ref_mut(body, pat) => Access::Write,
+        _ => Access::Read,
+    }
+}
+
+/// How a field, element or dereference of a place uses the place.
+fn projection_access(access: Access) -> Access {
+    match access {
+        Access::Write => Access::Write,
+        Access::Read | Access::Move => Access::Read,
+    }
+}
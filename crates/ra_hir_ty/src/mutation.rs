@@ -0,0 +1,157 @@
+//! Classifies uses of local bindings as reads or writes.
+//!
+//! A binding is mutated if a place rooted at it is assigned to, mutably
+//! borrowed (explicitly, by a `&mut self` method call, or by a `ref mut`
+//! pattern) or, for closures, called. Places which go through a reference or a
+//! raw pointer don't mutate the binding itself. When types are unknown we can't
+//! tell, so such uses are recorded as possible mutations.
+
+use hir_def::{
+    body::{scope::ExprScopes, Body},
+    expr::{BinaryOp, BindingAnnotation, Expr, ExprId, Pat, PatId, Statement, UnaryOp},
+    type_ref::{Mutability, TypeRef},
+};
+use rustc_hash::FxHashMap;
+
+use crate::{db::HirDatabase, ApplicationTy, InferenceResult, Ty, TypeCtor};
+
+/// Maps each mutated binding to the first expression which definitely mutates
+/// it, or to `None` if it is only possibly mutated.
+pub(crate) fn binding_mutations(
+    db: &dyn HirDatabase,
+    body: &Body,
+    scopes: &ExprScopes,
+    infer: &InferenceResult,
+) -> FxHashMap<PatId, Option<ExprId>> {
+    let mut collector = MutationCollector { db, body, scopes, infer, res: FxHashMap::default() };
+    collector.collect();
+    collector.res
+}
+
+/// Returns `true` if `pat` binds anything by `ref mut`, so that matching on a
+/// place mutably borrows it.
+pub(crate) fn binds_by_ref_mut(body: &Body, pat: PatId) -> bool {
+    match &body[pat] {
+        Pat::Bind { mode: BindingAnnotation::RefMut, .. } => true,
+        p => {
+            let mut res = false;
+            p.walk_child_pats(|it| res = res || binds_by_ref_mut(body, it));
+            res
+        }
+    }
+}
+
+struct MutationCollector<'a> {
+    db: &'a dyn HirDatabase,
+    body: &'a Body,
+    scopes: &'a ExprScopes,
+    infer: &'a InferenceResult,
+    res: FxHashMap<PatId, Option<ExprId>>,
+}
+
+impl MutationCollector<'_> {
+    fn collect(&mut self) {
+        let (body, infer) = (self.body, self.infer);
+        for (id, expr) in body.exprs.iter() {
+            match expr {
+                Expr::BinaryOp { lhs, op: Some(BinaryOp::Assignment { .. }), .. } => {
+                    self.mutate_place(id, *lhs, true)
+                }
+                Expr::Ref { expr, mutability: Mutability::Mut } => {
+                    self.mutate_place(id, *expr, true)
+                }
+                Expr::MethodCall { receiver, .. } => self.method_call(id, *receiver),
+                Expr::Call { callee, .. } => {
+                    // Calling an `FnMut` closure borrows it mutably.
+                    match &infer[*callee] {
+                        Ty::Apply(ApplicationTy { ctor: TypeCtor::FnDef(_), .. })
+                        | Ty::Apply(ApplicationTy { ctor: TypeCtor::FnPtr { .. }, .. }) => {}
+                        _ => self.mutate_place(id, *callee, false),
+                    }
+                }
+                Expr::Match { expr, arms } => {
+                    if arms.iter().any(|arm| binds_by_ref_mut(body, arm.pat)) {
+                        self.mutate_place(id, *expr, true)
+                    }
+                }
+                Expr::Block { statements, .. } => {
+                    for stmt in statements {
+                        if let Statement::Let { pat, initializer: Some(init), .. } = stmt {
+                            if binds_by_ref_mut(body, *pat) {
+                                self.mutate_place(*init, *init, true)
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn method_call(&mut self, call: ExprId, receiver: ExprId) {
+        let certain = match self.infer.method_resolution(call) {
+            Some(func) => {
+                let data = self.db.function_data(func);
+                let takes_mut_self = data.has_self_param
+                    && match &*data.params[0] {
+                        TypeRef::Reference(_, Mutability::Mut) => true,
+                        _ => false,
+                    };
+                if !takes_mut_self {
+                    return;
+                }
+                true
+            }
+            None => false,
+        };
+        // A receiver which is already a reference is reborrowed.
+        match through_pointer(&self.infer[receiver]) {
+            Some(true) => {}
+            Some(false) => self.mutate_place(call, receiver, certain),
+            None => self.mutate_place(call, receiver, false),
+        }
+    }
+
+    fn mutate_place(&mut self, site: ExprId, place: ExprId, certain: bool) {
+        let body = self.body;
+        match &body[place] {
+            Expr::Path(path) => {
+                let name = match path.as_ident() {
+                    Some(it) => it,
+                    None => return,
+                };
+                let scope = match self.scopes.scope_for(place) {
+                    Some(it) => it,
+                    None => return,
+                };
+                if let Some(entry) = self.scopes.resolve_name_in_scope(scope, name) {
+                    let mutation = self.res.entry(entry.pat()).or_insert(None);
+                    if certain && mutation.is_none() {
+                        *mutation = Some(site);
+                    }
+                }
+            }
+            Expr::Field { expr: base, .. }
+            | Expr::Index { base, .. }
+            | Expr::UnaryOp { expr: base, op: UnaryOp::Deref } => {
+                match through_pointer(&self.infer[*base]) {
+                    Some(true) => {}
+                    Some(false) => self.mutate_place(site, *base, certain),
+                    None => self.mutate_place(site, *base, false),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns whether a place of type `ty` is accessed through a pointer when
+/// projecting from it, or `None` if we don't know.
+fn through_pointer(ty: &Ty) -> Option<bool> {
+    match ty {
+        Ty::Apply(ApplicationTy { ctor: TypeCtor::Ref(_), .. })
+        | Ty::Apply(ApplicationTy { ctor: TypeCtor::RawPtr(_), .. }) => Some(true),
+        Ty::Apply(_) => Some(false),
+        _ => None,
+    }
+}
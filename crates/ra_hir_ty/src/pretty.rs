@@ -0,0 +1,104 @@
+//! Prints lowered bodies along with their inferred types and the items their
+//! paths and method calls resolve to.
+
+use hir_def::{
+    body::pretty::{print_body, print_type_ref, Annotations},
+    expr::{Expr, ExprId, PatId},
+    resolver::{resolver_for_expr, ValueNs},
+    AssocContainerId, AssocItemId, DefWithBodyId, EnumVariantId, FunctionId, Lookup, VariantId,
+};
+use hir_expand::name::Name;
+
+use crate::{db::HirDatabase, display::HirDisplay, InferenceResult};
+
+/// Prints the body of `owner`, with the type of every binding and the
+/// resolution of every path expression and method call.
+pub fn print_typed_body(db: &dyn HirDatabase, owner: DefWithBodyId) -> String {
+    let infer = db.infer(owner);
+    print_body(db.upcast(), owner, &TypeAnnotations { db, owner, infer: &infer })
+}
+
+struct TypeAnnotations<'a> {
+    db: &'a dyn HirDatabase,
+    owner: DefWithBodyId,
+    infer: &'a InferenceResult,
+}
+
+impl Annotations for TypeAnnotations<'_> {
+    fn pat(&self, pat: PatId) -> Option<String> {
+        Some(self.infer[pat].display(self.db).to_string())
+    }
+
+    fn expr(&self, expr: ExprId) -> Option<String> {
+        if let Some(func) = self.infer.method_resolution(expr) {
+            return Some(describe_function(self.db, func));
+        }
+        if let Some(variant) = self.infer.variant_resolution_for_expr(expr) {
+            return Some(describe_variant(self.db, variant));
+        }
+        if let Some(item) = self.infer.assoc_resolutions_for_expr(expr) {
+            return match item {
+                AssocItemId::FunctionId(it) => Some(describe_function(self.db, it)),
+                AssocItemId::ConstId(it) => {
+                    let data = self.db.const_data(it);
+                    let name = data.name.clone().unwrap_or_else(Name::missing);
+                    let container = it.lookup(self.db.upcast()).container;
+                    Some(format!("const {}", qualified_name(self.db, container, &name)))
+                }
+                AssocItemId::TypeAliasId(_) => None,
+            };
+        }
+        let body = self.db.body(self.owner);
+        let path = match &body[expr] {
+            Expr::Path(path) => path,
+            _ => return None,
+        };
+        let resolver = resolver_for_expr(self.db.upcast(), self.owner, expr);
+        let value = resolver.resolve_path_in_value_ns_fully(self.db.upcast(), path.mod_path())?;
+        let res = match value {
+            // The type of the binding is printed where it's bound.
+            ValueNs::LocalBinding(_) => return None,
+            ValueNs::FunctionId(it) => describe_function(self.db, it),
+            ValueNs::ConstId(it) => {
+                let name = self.db.const_data(it).name.clone().unwrap_or_else(Name::missing);
+                format!("const {}", name)
+            }
+            ValueNs::StaticId(it) => {
+                let name = self.db.static_data(it).name.clone().unwrap_or_else(Name::missing);
+                format!("static {}", name)
+            }
+            ValueNs::StructId(it) => describe_variant(self.db, it.into()),
+            ValueNs::EnumVariantId(it) => describe_variant(self.db, it.into()),
+        };
+        Some(res)
+    }
+}
+
+fn describe_function(db: &dyn HirDatabase, func: FunctionId) -> String {
+    let name = &db.function_data(func).name;
+    let container = func.lookup(db.upcast()).container;
+    format!("fn {}", qualified_name(db, container, name))
+}
+
+fn describe_variant(db: &dyn HirDatabase, variant: VariantId) -> String {
+    match variant {
+        VariantId::StructId(it) => format!("struct {}", db.struct_data(it).name),
+        VariantId::UnionId(it) => format!("union {}", db.union_data(it).name),
+        VariantId::EnumVariantId(EnumVariantId { parent, local_id }) => {
+            let data = db.enum_data(parent);
+            format!("variant {}::{}", data.name, data.variants[local_id].name)
+        }
+    }
+}
+
+/// Qualifies the name of an associated item by its trait, or by the self type
+/// of its impl, like `<Vec<T>>::new`.
+fn qualified_name(db: &dyn HirDatabase, container: AssocContainerId, name: &Name) -> String {
+    match container {
+        AssocContainerId::ContainerId(_) => name.to_string(),
+        AssocContainerId::TraitId(it) => format!("{}::{}", db.trait_data(it).name, name),
+        AssocContainerId::ImplId(it) => {
+            format!("<{}>::{}", print_type_ref(&db.impl_data(it).target_type), name)
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Finds statements which can never be executed because control flow diverges
+//! before reaching them: after `return`, `break`, `continue`, an infinite
+//! `loop` or a call to a function returning `!`.
+
+use hir_def::{
+    body::Body,
+    expr::{BinaryOp, Expr, ExprId, PatId, Statement},
+};
+
+use crate::{ApplicationTy, InferenceResult, Ty, TypeCtor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DeadCode {
+    /// An expression statement or a block's tail expression.
+    Expr(ExprId),
+    /// A `let` statement, identified by its pattern.
+    Let(PatId),
+}
+
+/// Returns the first and last dead statement of each block containing
+/// unreachable code. Code nested in an already dead statement is not reported
+/// again.
+pub(crate) fn unreachable_code(body: &Body, infer: &InferenceResult) -> Vec<(DeadCode, DeadCode)> {
+    let mut res = Vec::new();
+    collect_unreachable(body, infer, body.body_expr, &mut res);
+    res
+}
+
+fn collect_unreachable(
+    body: &Body,
+    infer: &InferenceResult,
+    expr: ExprId,
+    acc: &mut Vec<(DeadCode, DeadCode)>,
+) {
+    let (statements, tail) = match &body[expr] {
+        Expr::Block { statements, tail } => (statements, tail),
+        e => {
+            e.walk_child_exprs(|it| collect_unreachable(body, infer, it, acc));
+            return;
+        }
+    };
+
+    let mut diverged = false;
+    let mut dead: Option<(DeadCode, DeadCode)> = None;
+    let stmts = statements
+        .iter()
+        .map(|stmt| match stmt {
+            Statement::Let { pat, initializer, .. } => (DeadCode::Let(*pat), *initializer),
+            Statement::Expr(expr) => (DeadCode::Expr(*expr), Some(*expr)),
+        })
+        .chain(tail.map(|tail| (DeadCode::Expr(tail), Some(tail))));
+    for (item, expr) in stmts {
+        if diverged {
+            dead = Some(match dead {
+                Some((first, _)) => (first, item),
+                None => (item, item),
+            });
+            continue;
+        }
+        if let Some(expr) = expr {
+            collect_unreachable(body, infer, expr, acc);
+            diverged = diverges(body, infer, expr);
+        }
+    }
+    acc.extend(dead);
+}
+
+/// Returns `true` if evaluating `expr` never finishes normally.
+fn diverges(body: &Body, infer: &InferenceResult, expr: ExprId) -> bool {
+    let check = |expr| diverges(body, infer, expr);
+    match &body[expr] {
+        Expr::Return { .. } | Expr::Break { .. } | Expr::Continue => true,
+        // Loops are typed as `!` even if they contain a `break`.
+        Expr::Loop { body: loop_body } => !contains_break(body, *loop_body),
+        Expr::While { condition, .. } => check(*condition),
+        Expr::For { iterable, .. } => check(*iterable),
+        Expr::If { condition, then_branch, else_branch } => {
+            check(*condition)
+                || else_branch
+                    .map_or(false, |else_branch| check(*then_branch) && check(else_branch))
+        }
+        Expr::Match { expr, arms } => check(*expr) || arms.iter().all(|arm| check(arm.expr)),
+        Expr::BinaryOp { lhs, op: Some(BinaryOp::LogicOp(_)), .. } => check(*lhs),
+        // `return` in a closure or async block only leaves that.
+        Expr::Lambda { .. } | Expr::Async { .. } => false,
+        Expr::Call { .. } | Expr::MethodCall { .. } if is_never(infer, expr) => true,
+        e => {
+            let mut res = false;
+            e.walk_child_exprs(|it| res = res || check(it));
+            res
+        }
+    }
+}
+
+fn contains_break(body: &Body, expr: ExprId) -> bool {
+    match &body[expr] {
+        Expr::Break { .. } => true,
+        Expr::Lambda { .. } | Expr::Async { .. } => false,
+        e => {
+            let mut res = false;
+            e.walk_child_exprs(|it| res = res || contains_break(body, it));
+            res
+        }
+    }
+}
+
+fn is_never(infer: &InferenceResult, expr: ExprId) -> bool {
+    match infer.type_of_expr.get(expr) {
+        Some(Ty::Apply(ApplicationTy { ctor: TypeCtor::Never, .. })) => true,
+        _ => false,
+    }
+}
@@ -20,14 +20,17 @@ use hir_def::{
 };
 use hir_expand::InFile;
 use insta::assert_snapshot;
-use ra_db::{fixture::WithFixture, salsa::Database, FilePosition, SourceDatabase};
+use ra_db::{fixture::WithFixture, salsa::Database, FileId, FilePosition, SourceDatabase};
 use ra_syntax::{
     algo,
     ast::{self, AstNode},
 };
 use stdx::format_to;
 
-use crate::{db::HirDatabase, display::HirDisplay, test_db::TestDB, InferenceResult};
+use crate::{
+    db::HirDatabase, display::HirDisplay, pretty::print_typed_body, test_db::TestDB,
+    InferenceResult,
+};
 
 // These tests compare the inference results for all expressions in a file
 // against snapshots of the expected results using insta. Use cargo-insta to
@@ -132,30 +135,44 @@ fn infer_with_mismatches(content: &str, include_mismatches: bool) -> String {
         }
     };
 
+    for def in defs_in_file(&db, file_id) {
+        let (_body, source_map) = db.body_with_source_map(def);
+        let infer = db.infer(def);
+        infer_def(infer, source_map);
+    }
+
+    buf.truncate(buf.trim_end().len());
+    buf
+}
+
+/// Prints the bodies of all the functions and constants in the file, with
+/// the types of the bindings and the resolutions of paths and method calls.
+fn infer_bodies(ra_fixture: &str) -> String {
+    let (db, file_id) = TestDB::with_single_file(ra_fixture);
+    let bodies: Vec<String> =
+        defs_in_file(&db, file_id).into_iter().map(|def| print_typed_body(&db, def)).collect();
+    bodies.join("\n\n")
+}
+
+/// The items with bodies in the file, in the order they are defined.
+fn defs_in_file(db: &TestDB, file_id: FileId) -> Vec<DefWithBodyId> {
     let module = db.module_for_file(file_id);
     let crate_def_map = db.crate_def_map(module.krate);
 
     let mut defs: Vec<DefWithBodyId> = Vec::new();
-    visit_module(&db, &crate_def_map, module.local_id, &mut |it| defs.push(it));
+    visit_module(db, &crate_def_map, module.local_id, &mut |it| defs.push(it));
     defs.sort_by_key(|def| match def {
         DefWithBodyId::FunctionId(it) => {
-            it.lookup(&db).ast_id.to_node(&db).syntax().text_range().start()
+            it.lookup(db).ast_id.to_node(db).syntax().text_range().start()
         }
         DefWithBodyId::ConstId(it) => {
-            it.lookup(&db).ast_id.to_node(&db).syntax().text_range().start()
+            it.lookup(db).ast_id.to_node(db).syntax().text_range().start()
         }
         DefWithBodyId::StaticId(it) => {
-            it.lookup(&db).ast_id.to_node(&db).syntax().text_range().start()
+            it.lookup(db).ast_id.to_node(db).syntax().text_range().start()
         }
     });
-    for def in defs {
-        let (_body, source_map) = db.body_with_source_map(def);
-        let infer = db.infer(def);
-        infer_def(infer, source_map);
-    }
-
-    buf.truncate(buf.trim_end().len());
-    buf
+    defs
 }
 
 fn visit_module(
@@ -291,6 +308,52 @@ fn typing_whitespace_inside_a_function_should_not_invalidate_types() {
     }
 }
 
+#[test]
+fn typing_inside_a_function_should_only_reinfer_that_function() {
+    let (mut db, pos) = TestDB::with_position(
+        "
+        //- /lib.rs
+        fn foo() -> i32 {
+            <|>1 + 1
+        }
+
+        fn bar() -> i32 {
+            foo() + 2
+        }
+    ",
+    );
+    let infer_all = |db: &TestDB| {
+        db.log_executed(|| {
+            let module = db.module_for_file(pos.file_id);
+            let crate_def_map = db.crate_def_map(module.krate);
+            visit_module(db, &crate_def_map, module.local_id, &mut |def| {
+                db.infer(def);
+            });
+        })
+    };
+    let events = infer_all(&db);
+    assert_eq!(events.iter().filter(|it| it.contains("infer")).count(), 2, "{:#?}", events);
+
+    // The items after the edit are shifted, so their bodies are lowered again,
+    // but the lowered bodies are the same and their types are not inferred.
+    let new_text = "
+        fn foo() -> i32 {
+            let x = 92;
+            x + 1
+        }
+
+        fn bar() -> i32 {
+            foo() + 2
+        }
+    "
+    .to_string();
+    db.query_mut(ra_db::FileTextQuery).set(pos.file_id, Arc::new(new_text));
+
+    let events = infer_all(&db);
+    assert_eq!(events.iter().filter(|it| it.contains("infer")).count(), 1, "{:#?}", events);
+    assert!(!format!("{:?}", events).contains("crate_def_map"), "{:#?}", events);
+}
+
 #[test]
 fn no_such_field_diagnostics() {
     let diagnostics = TestDB::with_files(
@@ -447,3 +510,33 @@ fn missing_record_pat_field_no_diagnostic_if_not_exhaustive() {
 
     assert_snapshot!(diagnostics, @"");
 }
+
+#[test]
+fn unreachable_code_diagnostic() {
+    let diagnostics = TestDB::with_files(
+        r"
+        //- /lib.rs
+        fn never() -> ! { loop {} }
+        fn f(x: bool) -> u32 {
+            if x {
+                return 1;
+                let y = 2;
+                y
+            }
+            loop {
+                if x { break; }
+            }
+            never();
+            92
+        }
+        ",
+    )
+    .diagnostics()
+    .0;
+
+    assert_snapshot!(diagnostics, @r###"
+    "let y = 2;": unreachable code
+    "92": unreachable code
+    "###
+    );
+}
@@ -545,6 +545,28 @@ fn test() {
     );
 }
 
+#[test]
+fn coerce_extern_fn_item_to_fn_ptr() {
+    assert_snapshot!(
+        infer_with_mismatches(r#"
+extern "C" { fn foo(x: u32) -> isize; }
+fn test() {
+    let f: extern "C" fn(u32) -> isize = foo;
+    let g: fn(u32) -> isize = foo;
+}
+"#, true),
+        @r###"
+    [21; 22) 'x': u32
+    [51; 135) '{     ...foo; }': ()
+    [61; 62) 'f': extern "C" fn(u32) -> isize
+    [94; 97) 'foo': fn foo(u32) -> isize
+    [107; 108) 'g': fn(u32) -> isize
+    [129; 132) 'foo': fn foo(u32) -> isize
+    [129; 132): expected fn(u32) -> isize, got fn foo(u32) -> isize
+    "###
+    );
+}
+
 #[test]
 fn coerce_closure_to_fn_ptr() {
     assert_snapshot!(
@@ -697,6 +719,69 @@ fn test() {
     );
 }
 
+#[test]
+fn coerce_unsize_trait_object_with_auto_traits() {
+    assert_snapshot!(
+        infer_with_mismatches(r#"
+#[lang = "unsize"]
+pub trait Unsize<T> {}
+#[lang = "coerce_unsized"]
+pub trait CoerceUnsized<T> {}
+
+impl<T: Unsize<U>, U> CoerceUnsized<&U> for &T {}
+
+auto trait Send {}
+auto trait Sync {}
+
+trait Error {}
+trait Foo: Error {}
+
+struct S;
+impl Error for S {}
+impl Foo for S {}
+unsafe impl Send for S {}
+unsafe impl Sync for S {}
+
+fn test() {
+    let obj: &(dyn Send + Foo + Sync) = &S;
+    let obj: &(dyn Error + Sync) = obj;
+    let _: &dyn Error = obj;
+}
+"#, true),
+        @r###"
+    [338; 454) '{     ...obj; }': ()
+    [348; 351) 'obj': &dyn Foo + Send + Sync
+    [380; 382) '&S': &S
+    [381; 382) 'S': S
+    [392; 395) 'obj': &dyn Error + Sync
+    [419; 422) 'obj': &dyn Foo + Send + Sync
+    [432; 433) '_': &dyn Error
+    [448; 451) 'obj': &dyn Error + Sync
+    "###
+    );
+}
+
+#[test]
+fn dyn_trait_auto_traits_are_unordered() {
+    assert_snapshot!(
+        infer_with_mismatches(r#"
+auto trait Send {}
+auto trait Sync {}
+trait Foo {}
+
+fn test(a: &mut &(dyn Foo + Send + Sync)) {
+    let b: &mut &(dyn Sync + Foo + Send + Sync) = a;
+}
+"#, true),
+        @r###"
+    [61; 62) 'a': &mut &dyn Foo + Send + Sync
+    [95; 151) '{     ...= a; }': ()
+    [105; 106) 'b': &mut &dyn Foo + Send + Sync
+    [147; 148) 'a': &mut &dyn Foo + Send + Sync
+    "###
+    );
+}
+
 #[ignore]
 #[test]
 fn coerce_unsize_generic() {
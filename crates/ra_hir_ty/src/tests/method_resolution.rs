@@ -1067,6 +1067,24 @@ fn main() {
     assert_eq!(t, "A<i32>");
 }
 
+#[test]
+fn method_resolution_dyn_trait_with_auto_traits() {
+    let t = type_at(
+        r#"
+//- /main.rs
+auto trait Send {}
+mod m {
+    pub trait Trait { fn foo(&self) -> u32; }
+}
+
+fn test(x: &(dyn Send + m::Trait)) {
+    x.foo()<|>;
+}
+"#,
+    );
+    assert_eq!(t, "u32");
+}
+
 #[test]
 fn method_resolution_slow() {
     // this can get quite slow if we set the solver size limit too high
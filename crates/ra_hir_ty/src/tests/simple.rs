@@ -1,4 +1,4 @@
-use super::{infer, type_at, type_at_pos};
+use super::{infer, infer_bodies, type_at, type_at_pos};
 use crate::test_db::TestDB;
 use insta::assert_snapshot;
 use ra_db::fixture::WithFixture;
@@ -1755,3 +1755,52 @@ fn main() {
     "###
     );
 }
+
+#[test]
+fn infer_typed_bodies() {
+    assert_snapshot!(
+        infer_bodies(r#"
+struct S { x: u32 }
+
+impl S {
+    fn get(&self) -> u32 { self.x }
+}
+
+fn id<T>(t: T) -> T { t }
+
+fn test(s: S) -> u32 {
+    let a = id(1u8);
+    let b = s.get();
+    let (c, d) = (a, &b);
+    let e = if let (1, x) = (a, b) { x } else { 0 };
+    let f = ("s", 'c', 2.5);
+    b + *d
+}
+"#),
+        @r###"
+    fn get(self /* &S */) {
+        self.x
+    }
+
+    fn id(t /* T */) {
+        t
+    }
+
+    fn test(s /* S */) {
+        let a /* u8 */ = id /* fn id */(1u8);
+        let b /* u32 */ = s.get() /* fn <S>::get */;
+        let (c /* u8 */, d /* &u32 */) = (a, &b);
+        let e /* u32 */ = match (a, b) {
+            (1, x /* u32 */) => {
+                x
+            },
+            {missing} => {
+                0
+            },
+        };
+        let f /* (&str, char, f64) */ = ("s", 'c', 2.5);
+        b + *d
+    }
+    "###
+    );
+}
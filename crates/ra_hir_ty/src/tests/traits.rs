@@ -1165,6 +1165,72 @@ fn test(x: impl Trait<u64>, y: &impl Trait<u64>) {
     );
 }
 
+#[test]
+fn impl_trait_return_leaks_hidden_type() {
+    assert_snapshot!(
+        infer(r#"
+trait Trait {}
+struct S;
+struct W<T>(T);
+impl Trait for S {}
+impl<T> Trait for W<T> {}
+impl S {
+    fn inherent(&self) -> u64 { 0 }
+}
+
+fn make() -> impl Trait { S }
+fn wrap<T>(t: T) -> impl Trait { W(t) }
+
+fn test() {
+    let s = make();
+    let w = wrap(1u8);
+    s.inherent();
+}
+"#),
+        @r###"
+    [114; 118) 'self': &S
+    [127; 132) '{ 0 }': u64
+    [129; 130) '0': u64
+    [160; 165) '{ S }': S
+    [162; 163) 'S': S
+    [177; 178) 't': T
+    [197; 205) '{ W(t) }': W<T>
+    [199; 200) 'W': W<T>(T) -> W<T>
+    [199; 203) 'W(t)': W<T>
+    [201; 202) 't': T
+    [217; 281) '{     ...t(); }': ()
+    [227; 228) 's': S
+    [231; 235) 'make': fn make() -> impl Trait
+    [231; 237) 'make()': S
+    [247; 248) 'w': W<u8>
+    [251; 255) 'wrap': fn wrap<u8>(u8) -> impl Trait
+    [251; 260) 'wrap(1u8)': W<u8>
+    [256; 259) '1u8': u8
+    [266; 267) 's': S
+    [266; 278) 's.inherent()': u64
+    "###
+    );
+}
+
+#[test]
+fn impl_trait_return_from_other_crate_is_opaque() {
+    let t = type_at(
+        r#"
+//- /main.rs crate:main deps:other
+fn test() {
+    other::make()<|>;
+}
+
+//- /other.rs crate:other
+pub trait Trait {}
+pub struct S;
+impl Trait for S {}
+pub fn make() -> impl Trait { S }
+"#,
+    );
+    assert_eq!(t, "impl Trait");
+}
+
 #[test]
 fn dyn_trait() {
     assert_snapshot!(
@@ -1263,10 +1329,10 @@ fn test(a: impl Trait + 'lifetime, b: impl 'lifetime, c: impl (Trait), d: impl (
 }
 "#),
         @r###"
-    [24; 25) 'a': impl Trait + {error}
-    [51; 52) 'b': impl {error}
+    [24; 25) 'a': impl Trait
+    [51; 52) 'b': impl
     [70; 71) 'c': impl Trait
-    [87; 88) 'd': impl {error}
+    [87; 88) 'd': impl
     [108; 109) 'e': impl {error}
     [124; 125) 'f': impl Trait + {error}
     [148; 151) '{ }': ()
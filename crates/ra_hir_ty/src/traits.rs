@@ -303,15 +303,26 @@ pub struct ClosureFnTraitImplData {
     fn_trait: FnTrait,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnsizeToTraitObjectData {
+    trait_: TraitId,
+    /// The auto traits of the target, like `Send` in `dyn Trait + Send`.
+    auto_traits: Vec<TraitId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct UnsizeToSuperTraitObjectData {
     trait_: TraitId,
     super_trait: TraitId,
+    auto_traits: Vec<TraitId>,
+    /// A subset of `auto_traits`, since auto traits can be dropped but not
+    /// added.
+    super_auto_traits: Vec<TraitId>,
 }
 
 /// An impl. Usually this comes from an impl block, but some built-in types get
 /// synthetic impls.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Impl {
     /// A normal impl from an impl block.
     ImplDef(ImplId),
@@ -319,9 +330,9 @@ pub enum Impl {
     ClosureFnTraitImpl(ClosureFnTraitImplData),
     /// [T; n]: Unsize<[T]>
     UnsizeArray,
-    /// T: Unsize<dyn Trait> where T: Trait
-    UnsizeToTraitObject(TraitId),
-    /// dyn Trait: Unsize<dyn SuperTrait> if Trait: SuperTrait
+    /// T: Unsize<dyn Trait + Auto> where T: Trait + Auto
+    UnsizeToTraitObject(UnsizeToTraitObjectData),
+    /// dyn Trait + Auto: Unsize<dyn SuperTrait> if Trait: SuperTrait
     UnsizeToSuperTraitObject(UnsizeToSuperTraitObjectData),
 }
 /// This exists just for Chalk, because our ImplIds are only unique per module.
@@ -1,10 +1,10 @@
 //! This module provides the built-in trait implementations, e.g. to make
 //! closures implement `Fn`.
-use hir_def::{expr::Expr, lang_item::LangItemTarget, TraitId, TypeAliasId};
+use hir_def::{expr::Expr, lang_item::LangItemTarget, type_ref::Abi, TraitId, TypeAliasId};
 use hir_expand::name::name;
 use ra_db::CrateId;
 
-use super::{AssocTyValue, Impl, UnsizeToSuperTraitObjectData};
+use super::{AssocTyValue, Impl, UnsizeToSuperTraitObjectData, UnsizeToTraitObjectData};
 use crate::{
     db::HirDatabase,
     utils::{all_super_traits, generics},
@@ -76,31 +76,48 @@ fn get_builtin_unsize_impls(
         return; // array is unsized, the rest of the impls shouldn't apply
     }
 
-    if let Some(target_trait) = arg.as_ref().and_then(|t| t.dyn_trait_ref()) {
-        // FIXME what about more complicated dyn tys with marker traits?
-        if let Some(trait_ref) = ty.dyn_trait_ref() {
-            if trait_ref.trait_ != target_trait.trait_ {
-                let super_traits = all_super_traits(db.upcast(), trait_ref.trait_);
-                if super_traits.contains(&target_trait.trait_) {
-                    callback(Impl::UnsizeToSuperTraitObject(UnsizeToSuperTraitObjectData {
-                        trait_: trait_ref.trait_,
-                        super_trait: target_trait.trait_,
-                    }));
-                }
+    if let Some((target_trait, target_auto_traits)) = arg.as_ref().and_then(dyn_traits) {
+        if let Some((trait_, auto_traits)) = dyn_traits(ty) {
+            // auto traits can be dropped, but not added
+            if !target_auto_traits.iter().all(|t| auto_traits.contains(t)) {
+                return;
+            }
+            if trait_ == target_trait && auto_traits.len() == target_auto_traits.len() {
+                return;
+            }
+            let super_traits = all_super_traits(db.upcast(), trait_);
+            if super_traits.contains(&target_trait) {
+                callback(Impl::UnsizeToSuperTraitObject(UnsizeToSuperTraitObjectData {
+                    trait_,
+                    super_trait: target_trait,
+                    auto_traits,
+                    super_auto_traits: target_auto_traits,
+                }));
             }
         } else {
             // FIXME only for sized types
-            callback(Impl::UnsizeToTraitObject(target_trait.trait_));
+            callback(Impl::UnsizeToTraitObject(UnsizeToTraitObjectData {
+                trait_: target_trait,
+                auto_traits: target_auto_traits,
+            }));
         }
     }
 }
 
+/// Splits a `dyn Trait + Auto` type into the principal trait and the auto
+/// traits.
+fn dyn_traits(ty: &Ty) -> Option<(TraitId, Vec<TraitId>)> {
+    let trait_ = ty.dyn_trait_ref()?.trait_;
+    let auto_traits = ty.inherent_traits().filter(|&it| it != trait_).collect();
+    Some((trait_, auto_traits))
+}
+
 pub(super) fn impl_datum(db: &dyn HirDatabase, krate: CrateId, impl_: Impl) -> BuiltinImplData {
     match impl_ {
         Impl::ImplDef(_) => unreachable!(),
         Impl::ClosureFnTraitImpl(data) => closure_fn_trait_impl_datum(db, krate, data),
         Impl::UnsizeArray => array_unsize_impl_datum(db, krate),
-        Impl::UnsizeToTraitObject(trait_) => trait_object_unsize_impl_datum(db, krate, trait_),
+        Impl::UnsizeToTraitObject(data) => trait_object_unsize_impl_datum(db, krate, data),
         Impl::UnsizeToSuperTraitObject(data) => {
             super_trait_object_unsize_impl_datum(db, krate, data)
         }
@@ -170,7 +187,7 @@ fn closure_fn_trait_impl_datum(
             .build(),
     );
     let sig_ty = Ty::apply(
-        TypeCtor::FnPtr { num_args },
+        TypeCtor::FnPtr { num_args, abi: Abi::Rust },
         Substs::builder(num_args as usize + 1)
             .fill_with_bound_vars(DebruijnIndex::INNERMOST, 0)
             .build(),
@@ -267,14 +284,15 @@ fn array_unsize_impl_datum(db: &dyn HirDatabase, krate: CrateId) -> BuiltinImplD
 fn trait_object_unsize_impl_datum(
     db: &dyn HirDatabase,
     krate: CrateId,
-    trait_: TraitId,
+    data: UnsizeToTraitObjectData,
 ) -> BuiltinImplData {
-    // impl<T, T1, ...> Unsize<dyn Trait<T1, ...>> for T where T: Trait<T1, ...>
+    // impl<T, T1, ...> Unsize<dyn Trait<T1, ...> + Auto> for T where T: Trait<T1, ...> + Auto
 
     let unsize_trait = get_unsize_trait(db, krate) // get unsize trait
         // the existence of the Unsize trait has been checked before
         .expect("Unsize trait missing");
 
+    let trait_ = data.trait_;
     let self_ty = Ty::Bound(BoundVar::new(DebruijnIndex::INNERMOST, 0));
 
     let target_substs = Substs::build_for_def(db, trait_)
@@ -283,12 +301,14 @@ fn trait_object_unsize_impl_datum(
         .build();
     let num_vars = target_substs.len();
     let target_trait_ref = TraitRef { trait_, substs: target_substs };
-    let target_bounds = vec![GenericPredicate::Implemented(target_trait_ref)];
+    let mut target_bounds = vec![GenericPredicate::Implemented(target_trait_ref)];
+    target_bounds.extend(auto_trait_bounds(&data.auto_traits, self_ty.clone()));
 
     let self_substs =
         Substs::build_for_def(db, trait_).fill_with_bound_vars(DebruijnIndex::INNERMOST, 0).build();
     let self_trait_ref = TraitRef { trait_, substs: self_substs };
-    let where_clauses = vec![GenericPredicate::Implemented(self_trait_ref)];
+    let mut where_clauses = vec![GenericPredicate::Implemented(self_trait_ref)];
+    where_clauses.extend(auto_trait_bounds(&data.auto_traits, self_ty.clone()));
 
     let impl_substs =
         Substs::builder(2).push(self_ty).push(Ty::Dyn(target_bounds.clone().into())).build();
@@ -303,7 +323,7 @@ fn super_trait_object_unsize_impl_datum(
     krate: CrateId,
     data: UnsizeToSuperTraitObjectData,
 ) -> BuiltinImplData {
-    // impl<T1, ...> Unsize<dyn SuperTrait> for dyn Trait<T1, ...>
+    // impl<T1, ...> Unsize<dyn SuperTrait + Auto> for dyn Trait<T1, ...> + Auto + Auto2
 
     let unsize_trait = get_unsize_trait(db, krate) // get unsize trait
         // the existence of the Unsize trait has been checked before
@@ -346,10 +366,13 @@ fn super_trait_object_unsize_impl_datum(
         .fill_with_bound_vars(DebruijnIndex::ONE, 0)
         .build();
 
-    let self_bounds =
+    let dyn_self_ty = Ty::Bound(BoundVar::new(DebruijnIndex::INNERMOST, 0));
+    let mut self_bounds =
         vec![GenericPredicate::Implemented(self_trait_ref.subst_bound_vars(&new_substs))];
-    let super_bounds =
+    self_bounds.extend(auto_trait_bounds(&data.auto_traits, dyn_self_ty.clone()));
+    let mut super_bounds =
         vec![GenericPredicate::Implemented(current_trait_ref.subst_bound_vars(&new_substs))];
+    super_bounds.extend(auto_trait_bounds(&data.super_auto_traits, dyn_self_ty));
 
     let substs = Substs::builder(2)
         .push(Ty::Dyn(self_bounds.into()))
@@ -361,6 +384,18 @@ fn super_trait_object_unsize_impl_datum(
     BuiltinImplData { num_vars, trait_ref, where_clauses: Vec::new(), assoc_ty_values: Vec::new() }
 }
 
+fn auto_trait_bounds(auto_traits: &[TraitId], self_ty: Ty) -> Vec<GenericPredicate> {
+    auto_traits
+        .iter()
+        .map(|&trait_| {
+            GenericPredicate::Implemented(TraitRef {
+                trait_,
+                substs: Substs::single(self_ty.clone()),
+            })
+        })
+        .collect()
+}
+
 fn get_fn_trait(db: &dyn HirDatabase, krate: CrateId, fn_trait: super::FnTrait) -> Option<TraitId> {
     let target = db.lang_item(krate, fn_trait.lang_item_name().into())?;
     match target {
@@ -1,12 +1,12 @@
 //! FIXME: write short doc here
 
-use ra_assists::{resolved_assists, AssistAction, AssistLabel};
+use ra_assists::{resolved_assists, AssistAction, AssistConfig, AssistFile};
 use ra_db::{FilePosition, FileRange};
 use ra_ide_db::RootDatabase;
 
 use crate::{FileId, SourceChange, SourceFileEdit};
 
-pub use ra_assists::AssistId;
+pub use ra_assists::{AssistConfig, AssistId, AssistLabel};
 
 #[derive(Debug)]
 pub struct Assist {
@@ -16,8 +16,8 @@ pub struct Assist {
     pub source_change: SourceChange,
 }
 
-pub(crate) fn assists(db: &RootDatabase, frange: FileRange) -> Vec<Assist> {
-    resolved_assists(db, frange)
+pub(crate) fn assists(db: &RootDatabase, frange: FileRange, config: &AssistConfig) -> Vec<Assist> {
+    resolved_assists(db, frange, config)
         .into_iter()
         .map(|assist| {
             let file_id = frange.file_id;
@@ -32,12 +32,31 @@ pub(crate) fn assists(db: &RootDatabase, frange: FileRange) -> Vec<Assist> {
         .collect()
 }
 
+/// Computes the labels of the assists without computing their edits, which
+/// can later be resolved with `assists` by the `id`.
+pub(crate) fn unresolved_assists(
+    db: &RootDatabase,
+    frange: FileRange,
+    config: &AssistConfig,
+) -> Vec<AssistLabel> {
+    ra_assists::unresolved_assists(db, frange, config)
+}
+
 fn action_to_edit(
     action: AssistAction,
     file_id: FileId,
     assist_label: &AssistLabel,
 ) -> SourceChange {
-    let file_edit = SourceFileEdit { file_id, edit: action.edit };
-    SourceChange::source_file_edit(assist_label.label.clone(), file_edit)
-        .with_cursor_opt(action.cursor_position.map(|offset| FilePosition { offset, file_id }))
+    let file_id = match action.file {
+        AssistFile::TargetFile(it) => it,
+        AssistFile::CurrentFile => file_id,
+    };
+    let mut file_edits = vec![SourceFileEdit { file_id, edit: action.edit }];
+    file_edits.extend(
+        action.other_edits.into_iter().map(|(file_id, edit)| SourceFileEdit { file_id, edit }),
+    );
+    let mut source_change = SourceChange::source_file_edits(assist_label.label.clone(), file_edits)
+        .with_cursor_opt(action.cursor_position.map(|offset| FilePosition { offset, file_id }));
+    source_change.is_snippet = action.is_snippet;
+    source_change
 }
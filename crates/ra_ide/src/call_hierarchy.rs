@@ -2,13 +2,13 @@
 
 use indexmap::IndexMap;
 
-use hir::Semantics;
-use ra_ide_db::RootDatabase;
+use hir::{PathResolution, Semantics};
+use ra_ide_db::{search::SearchMode, RootDatabase};
 use ra_syntax::{ast, match_ast, AstNode, TextRange};
 
 use crate::{
     call_info::FnCallNode, display::ToNav, goto_definition, references, FilePosition,
-    NavigationTarget, RangeInfo,
+    NavigationTarget, RangeInfo, ReferenceKind,
 };
 
 #[derive(Debug, Clone)]
@@ -37,20 +37,30 @@ pub(crate) fn call_hierarchy(
     goto_definition::goto_definition(db, position)
 }
 
-pub(crate) fn incoming_calls(db: &RootDatabase, position: FilePosition) -> Option<Vec<CallItem>> {
+pub(crate) fn incoming_calls(
+    db: &RootDatabase,
+    position: FilePosition,
+    mode: SearchMode,
+) -> Option<Vec<CallItem>> {
     let sema = Semantics::new(db);
-    // 1. Find all refs
+    // 1. Find all refs which are calls
     // 2. Loop through refs and determine unique fndef. This will become our `from: CallHierarchyItem,` in the reply.
     // 3. Add ranges relative to the start of the fndef.
-    let refs = references::find_all_refs(db, position, None)?;
+    let refs = references::find_all_refs(db, position, None, mode)?;
 
     let mut calls = CallLocations::default();
 
     for reference in refs.info.references() {
+        if reference.kind != ReferenceKind::FnCall {
+            continue;
+        }
         let file_id = reference.file_range.file_id;
         let file = sema.parse(file_id);
         let file = file.syntax();
-        let token = file.token_at_offset(reference.file_range.range.start()).next()?;
+        let token = match file.token_at_offset(reference.file_range.range.start()).next() {
+            Some(it) => it,
+            None => continue,
+        };
         let token = sema.descend_into_macros(token);
         let syntax = token.parent();
 
@@ -81,28 +91,23 @@ pub(crate) fn outgoing_calls(db: &RootDatabase, position: FilePosition) -> Optio
     let file = file.syntax();
     let token = file.token_at_offset(position.offset).next()?;
     let token = sema.descend_into_macros(token);
-    let syntax = token.parent();
+    let fn_def = token.parent().ancestors().find_map(ast::FnDef::cast)?;
+    let body = fn_def.body()?;
 
     let mut calls = CallLocations::default();
 
-    syntax
+    body.syntax()
         .descendants()
+        // Calls from nested functions belong to those functions
+        .filter(|node| node.ancestors().find_map(ast::FnDef::cast).as_ref() == Some(&fn_def))
         .filter_map(|node| FnCallNode::with_node_exact(&node))
         .filter_map(|call_node| {
             let name_ref = call_node.name_ref()?;
 
             if let Some(func_target) = match &call_node {
                 FnCallNode::CallExpr(expr) => {
-                    //FIXME: Type::as_callable is broken
-                    let callable_def = sema.type_of_expr(&expr.expr()?)?.as_callable()?;
-                    match callable_def {
-                        hir::CallableDef::FunctionId(it) => {
-                            let fn_def: hir::Function = it.into();
-                            let nav = fn_def.to_nav(db);
-                            Some(nav)
-                        }
-                        _ => None,
-                    }
+                    let function = resolve_callee(&sema, &expr.expr()?)?;
+                    Some(function.to_nav(db))
                 }
                 FnCallNode::MethodCallExpr(expr) => {
                     let function = sema.resolve_method_call(&expr)?;
@@ -123,6 +128,21 @@ pub(crate) fn outgoing_calls(db: &RootDatabase, position: FilePosition) -> Optio
     Some(calls.into_items())
 }
 
+fn resolve_callee(sema: &Semantics<RootDatabase>, callee: &ast::Expr) -> Option<hir::Function> {
+    if let ast::Expr::PathExpr(path_expr) = callee {
+        match sema.resolve_path(&path_expr.path()?)? {
+            PathResolution::Def(hir::ModuleDef::Function(it)) => return Some(it),
+            PathResolution::AssocItem(hir::AssocItem::Function(it)) => return Some(it),
+            _ => (),
+        }
+    }
+    //FIXME: Type::as_callable is broken
+    match sema.type_of_expr(callee)?.as_callable()? {
+        hir::CallableDef::FunctionId(it) => Some(it.into()),
+        _ => None,
+    }
+}
+
 #[derive(Default)]
 struct CallLocations {
     funcs: IndexMap<NavigationTarget, Vec<TextRange>>,
@@ -141,6 +161,7 @@ impl CallLocations {
 #[cfg(test)]
 mod tests {
     use ra_db::FilePosition;
+    use ra_ide_db::search::SearchMode;
 
     use crate::mock_analysis::analysis_and_position;
 
@@ -157,8 +178,12 @@ mod tests {
         let nav = navs.pop().unwrap();
         nav.assert_match(expected);
 
-        let item_pos = FilePosition { file_id: nav.file_id(), offset: nav.range().start() };
-        let incoming_calls = analysis.incoming_calls(item_pos).unwrap().unwrap();
+        let item_pos = FilePosition {
+            file_id: nav.file_id(),
+            offset: nav.focus_range().unwrap_or_else(|| nav.range()).start(),
+        };
+        let incoming_calls =
+            analysis.incoming_calls(item_pos, SearchMode::WithDispatch).unwrap().unwrap();
         assert_eq!(incoming_calls.len(), expected_incoming.len());
 
         for call in 0..incoming_calls.len() {
@@ -304,6 +329,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_call_hierarchy_incoming_only_calls() {
+        check_hierarchy(
+            r#"
+            //- /lib.rs
+            fn call<|>ee() {}
+            fn caller() {
+                let _f = callee;
+                callee();
+            }
+            "#,
+            "callee FN_DEF FileId(1) [0; 14) [3; 9)",
+            &["caller FN_DEF FileId(1) [15; 65) [18; 24) : [[54; 60)]"],
+            &[],
+        );
+    }
+
+    #[test]
+    fn test_call_hierarchy_outgoing_skips_nested_fn() {
+        check_hierarchy(
+            r#"
+            //- /lib.rs
+            fn callee() {}
+            fn call<|>er() {
+                fn nested() { callee(); }
+                callee();
+            }
+            "#,
+            "caller FN_DEF FileId(1) [15; 74) [18; 24)",
+            &[],
+            &["callee FN_DEF FileId(1) [0; 14) [3; 9) : [[63; 69)]"],
+        );
+    }
+
     #[test]
     fn test_call_hierarchy_incoming_outgoing() {
         check_hierarchy(
@@ -3,25 +3,30 @@ use hir::Semantics;
 use ra_ide_db::RootDatabase;
 use ra_syntax::{
     ast::{self, ArgListOwner},
-    match_ast, AstNode, SyntaxNode, SyntaxToken,
+    match_ast, AstNode, SyntaxNode, SyntaxToken, T,
 };
 use test_utils::tested_by;
 
 use crate::{CallInfo, FilePosition, FunctionSignature};
 
 /// Computes parameter information for the given call expression.
-pub(crate) fn call_info(db: &RootDatabase, position: FilePosition) -> Option<CallInfo> {
+pub(crate) fn call_info(
+    db: &RootDatabase,
+    position: FilePosition,
+    show_elided_lifetimes: bool,
+) -> Option<CallInfo> {
     let sema = Semantics::new(db);
     let file = sema.parse(position.file_id);
     let file = file.syntax();
     let token = file.token_at_offset(position.offset).next()?;
     let token = sema.descend_into_macros(token);
-    call_info_for_token(&sema, token)
+    call_info_for_token(&sema, token, show_elided_lifetimes)
 }
 
 pub(crate) fn call_info_for_token(
     sema: &Semantics<RootDatabase>,
     token: SyntaxToken,
+    show_elided_lifetimes: bool,
 ) -> Option<CallInfo> {
     // Find the calling expression and it's NameRef
     let calling_node = FnCallNode::with_node(&token.parent())?;
@@ -33,7 +38,10 @@ pub(crate) fn call_info_for_token(
             match callable_def {
                 hir::CallableDef::FunctionId(it) => {
                     let fn_def = it.into();
-                    (CallInfo::with_fn(sema.db, fn_def), fn_def.has_self_param(sema.db))
+                    (
+                        CallInfo::with_fn(sema, fn_def, show_elided_lifetimes),
+                        fn_def.has_self_param(sema.db),
+                    )
                 }
                 hir::CallableDef::StructId(it) => {
                     (CallInfo::with_struct(sema.db, it.into())?, false)
@@ -45,7 +53,10 @@ pub(crate) fn call_info_for_token(
         }
         FnCallNode::MethodCallExpr(method_call) => {
             let function = sema.resolve_method_call(&method_call)?;
-            (CallInfo::with_fn(sema.db, function), function.has_self_param(sema.db))
+            (
+                CallInfo::with_fn(sema, function, show_elided_lifetimes),
+                function.has_self_param(sema.db),
+            )
         }
         FnCallNode::MacroCallExpr(macro_call) => {
             let macro_def = sema.resolve_macro_call(&macro_call)?;
@@ -65,30 +76,34 @@ pub(crate) fn call_info_for_token(
         }
         _ => {
             if let Some(arg_list) = calling_node.arg_list() {
-                // Number of arguments specified at the call site
-                let num_args_at_callsite = arg_list.args().count();
-
                 let arg_list_range = arg_list.syntax().text_range();
                 if !arg_list_range.contains_inclusive(token.text_range().start()) {
                     tested_by!(call_info_bad_offset);
                     return None;
                 }
 
-                let mut param = std::cmp::min(
-                    num_args_at_callsite,
-                    arg_list
-                        .args()
-                        .take_while(|arg| {
-                            arg.syntax().text_range().end() < token.text_range().start()
-                        })
-                        .count(),
-                );
+                // Only the commas which are direct children of the argument
+                // list separate its arguments, the ones inside of nested calls,
+                // generic arguments or closures belong to the nested nodes.
+                let mut param = arg_list
+                    .syntax()
+                    .children_with_tokens()
+                    .filter(|it| it.kind() == T![,])
+                    .take_while(|it| it.text_range().start() < token.text_range().start())
+                    .count();
 
                 // If we are in a method account for `self`
                 if has_self {
                     param += 1;
                 }
 
+                // The trailing arguments of a C function all go to its `...`.
+                let is_varargs =
+                    call_info.parameters().last().map_or(false, |it| it.ends_with("..."));
+                if is_varargs {
+                    param = param.min(num_params - 1);
+                }
+
                 call_info.active_parameter = Some(param);
             }
         }
@@ -109,7 +124,15 @@ impl FnCallNode {
         syntax.ancestors().find_map(|node| {
             match_ast! {
                 match node {
-                    ast::CallExpr(it) => Some(FnCallNode::CallExpr(it)),
+                    ast::CallExpr(it) => {
+                        // In `foo(ba<|>r(1))`, the interesting call is `foo`
+                        if let Some(callee) = it.expr() {
+                            if syntax.text_range().is_subrange(&callee.syntax().text_range()) {
+                                return None;
+                            }
+                        }
+                        Some(FnCallNode::CallExpr(it))
+                    },
                     ast::MethodCallExpr(it) => {
                         let arg_list = it.arg_list()?;
                         if !syntax.text_range().is_subrange(&arg_list.syntax().text_range()) {
@@ -160,8 +183,15 @@ impl FnCallNode {
 }
 
 impl CallInfo {
-    fn with_fn(db: &RootDatabase, function: hir::Function) -> Self {
-        let signature = FunctionSignature::from_hir(db, function);
+    fn with_fn(
+        sema: &Semantics<RootDatabase>,
+        function: hir::Function,
+        show_elided_lifetimes: bool,
+    ) -> Self {
+        let mut signature = FunctionSignature::from_hir(sema.db, function);
+        if show_elided_lifetimes {
+            signature = signature.with_elided_lifetimes(sema, &sema.source(function).value);
+        }
 
         CallInfo { signature, active_parameter: None }
     }
@@ -210,7 +240,7 @@ mod tests {
 
     fn call_info_helper(text: &str) -> Option<CallInfo> {
         let (analysis, position) = single_file_with_position(text);
-        analysis.call_info(position).unwrap()
+        analysis.call_info(position, false).unwrap()
     }
 
     fn call_info(text: &str) -> CallInfo {
@@ -235,6 +265,18 @@ fn bar() { foo(<|>3, ); }"#,
         assert_eq!(info.active_parameter, Some(0));
     }
 
+    #[test]
+    fn test_fn_signature_with_elided_lifetimes() {
+        let (analysis, position) = single_file_with_position(
+            r#"fn first(s: &str, n: usize) -> &str { s }
+fn bar() { first(<|>"", 1); }"#,
+        );
+        let info = analysis.call_info(position, true).unwrap().unwrap();
+
+        assert_eq!(info.label(), "fn first<'a>(s: &'a str, n: usize) -> &'a str");
+        assert_eq!(info.parameters(), ["s: &'a str", "n: usize"]);
+    }
+
     #[test]
     fn test_fn_signature_two_args_second() {
         let info = call_info(
@@ -277,6 +319,18 @@ where T: Copy + Display,
         assert_eq!(info.active_parameter, Some(0));
     }
 
+    #[test]
+    fn test_fn_signature_extern_varargs() {
+        let info = call_info(
+            r#"extern "C" { fn printf(format: *const u8, ...) -> i32; }
+fn bar() { unsafe { printf("%d %d".as_ptr(), 1, <|>2); } }"#,
+        );
+
+        assert_eq!(info.parameters(), ["format: *const u8", "..."]);
+        assert_eq!(info.label(), r#"extern "C" fn printf(format: *const u8, ...) -> i32"#);
+        assert_eq!(info.active_parameter, Some(1));
+    }
+
     #[test]
     fn test_fn_signature_no_params() {
         let info = call_info(
@@ -512,7 +566,7 @@ By default this method stops actor's `Context`."#
             r#"fn foo(x: u32, y: u32) -> u32 {x + y}
                fn bar() { foo <|> (3, ); }"#,
         );
-        let call_info = analysis.call_info(position).unwrap();
+        let call_info = analysis.call_info(position, false).unwrap();
         assert!(call_info.is_none());
     }
 
@@ -662,4 +716,52 @@ fn f() {
 
         assert_eq!(info.label(), "fn foo()");
     }
+
+    #[test]
+    fn fn_signature_on_callee_of_nested_call() {
+        let info = call_info(
+            r#"fn foo(x: u32, y: u32) -> u32 {x + y}
+fn bar(z: u32) -> u32 {z}
+fn main() { foo(1, ba<|>r(2)); }"#,
+        );
+
+        assert_eq!(info.label(), "fn foo(x: u32, y: u32) -> u32");
+        assert_eq!(info.active_parameter, Some(1));
+    }
+
+    #[test]
+    fn fn_signature_ignores_commas_in_nested_nodes() {
+        let info = call_info(
+            r#"fn foo(f: impl Fn(u32, u32) -> u32, x: u32, y: u32) {}
+fn bar(x: u32, y: u32) -> u32 {x + y}
+fn main() { foo(|a, b| bar(a, b), bar(1, 2), <|>); }"#,
+        );
+
+        assert_eq!(info.active_parameter, Some(2));
+    }
+
+    #[test]
+    fn fn_signature_with_parameter_docs() {
+        let info = call_info(
+            r#"
+/// Adds two numbers.
+///
+/// # Arguments
+///
+/// * `x` - the first number
+/// * `y` - the second number,
+///   which is added to the first one
+fn add(x: u32, y: u32) -> u32 {x + y}
+fn main() { add(1, <|>); }"#,
+        );
+
+        assert_eq!(
+            info.signature.parameter_docs,
+            [
+                Some("the first number".to_string()),
+                Some("the second number, which is added to the first one".to_string())
+            ]
+        );
+        assert_eq!(info.active_parameter, Some(1));
+    }
 }
@@ -829,4 +829,46 @@ mod tests {
         "###
         );
     }
+
+    #[test]
+    fn doc_hidden_fields_and_methods_are_only_completed_in_their_crate() {
+        let labels = |code| {
+            do_ref_completion(code).iter().map(|it| it.label().to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            labels(
+                r"
+                //- /main.rs
+                fn foo(a: foo::A) {
+                    a.<|>
+                }
+
+                //- /foo/lib.rs
+                pub struct A { pub shown: u32, #[doc(hidden)] pub hidden: u32 }
+                impl A {
+                    pub fn method(&self) {}
+                    #[doc(hidden)]
+                    pub fn hidden_method(&self) {}
+                }
+                ",
+            ),
+            vec!["method()", "shown"]
+        );
+        assert_eq!(
+            labels(
+                r"
+                //- /main.rs
+                struct A { shown: u32, #[doc(hidden)] hidden: u32 }
+                impl A {
+                    #[doc(hidden)]
+                    fn hidden_method(&self) {}
+                }
+                fn foo(a: A) {
+                    a.<|>
+                }
+                ",
+            ),
+            vec!["hidden", "hidden_method()", "shown"]
+        );
+    }
 }
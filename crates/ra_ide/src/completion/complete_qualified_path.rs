@@ -706,6 +706,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn doesnt_complete_doc_hidden_items_from_other_crates() {
+        assert_debug_snapshot!(
+            do_reference_completion(
+                "
+                //- /main.rs
+                use foo::<|>;
+
+                //- /foo/lib.rs
+                pub mod bar {}
+                #[doc(hidden)]
+                pub mod hidden {}
+                "
+            ),
+            @r###"
+        [
+            CompletionItem {
+                label: "bar",
+                source_range: [9; 9),
+                delete: [9; 9),
+                insert: "bar",
+                kind: Module,
+            },
+        ]
+        "###
+        );
+    }
+
     #[test]
     fn completes_trait_associated_method_1() {
         assert_debug_snapshot!(
@@ -21,6 +21,9 @@ impl Completions {
         field: hir::StructField,
         ty: &Type,
     ) {
+        if is_hidden(ctx, field, Some(field.parent_def(ctx.db).module(ctx.db))) {
+            return;
+        }
         let is_deprecated = is_deprecated(field, ctx.db);
         CompletionItem::new(
             CompletionKind::Reference,
@@ -49,6 +52,13 @@ impl Completions {
     ) {
         use hir::ModuleDef::*;
 
+        if let ScopeDef::ModuleDef(def) = resolution {
+            let is_doc_hidden = def.attrs(ctx.db).map_or(false, |it| it.is_doc_hidden());
+            if is_doc_hidden && !is_in_local_crate(ctx, def.module(ctx.db)) {
+                return;
+            }
+        }
+
         let completion_kind = match resolution {
             ScopeDef::ModuleDef(BuiltinType(..)) => CompletionKind::BuiltinType,
             _ => CompletionKind::Reference,
@@ -160,6 +170,9 @@ impl Completions {
             Some(it) => it,
             None => return,
         };
+        if is_hidden(ctx, macro_, macro_.module(ctx.db)) {
+            return;
+        }
 
         let ast_node = macro_.source(ctx.db).value;
         let detail = macro_label(&ast_node);
@@ -192,6 +205,9 @@ impl Completions {
         func: hir::Function,
         local_name: Option<String>,
     ) {
+        if is_hidden(ctx, func, Some(func.module(ctx.db))) {
+            return;
+        }
         let has_self_param = func.has_self_param(ctx.db);
 
         let name = local_name.unwrap_or_else(|| func.name(ctx.db).to_string());
@@ -222,6 +238,9 @@ impl Completions {
     }
 
     pub(crate) fn add_const(&mut self, ctx: &CompletionContext, constant: hir::Const) {
+        if is_hidden(ctx, constant, Some(constant.module(ctx.db))) {
+            return;
+        }
         let ast_node = constant.source(ctx.db).value;
         let name = match ast_node.name() {
             Some(name) => name,
@@ -238,6 +257,9 @@ impl Completions {
     }
 
     pub(crate) fn add_type_alias(&mut self, ctx: &CompletionContext, type_alias: hir::TypeAlias) {
+        if is_hidden(ctx, type_alias, Some(type_alias.module(ctx.db))) {
+            return;
+        }
         let type_def = type_alias.source(ctx.db).value;
         let name = match type_def.name() {
             Some(name) => name,
@@ -259,6 +281,9 @@ impl Completions {
         variant: hir::EnumVariant,
         local_name: Option<String>,
     ) {
+        if is_hidden(ctx, variant, Some(variant.module(ctx.db))) {
+            return;
+        }
         let is_deprecated = is_deprecated(variant, ctx.db);
         let name = local_name.unwrap_or_else(|| variant.name(ctx.db).to_string());
         let detail_types = variant
@@ -266,13 +291,17 @@ impl Completions {
             .into_iter()
             .map(|field| (field.name(ctx.db), field.signature_ty(ctx.db)));
         let variant_kind = variant.kind(ctx.db);
-        let detail = match variant_kind {
-            StructKind::Tuple | StructKind::Unit => detail_types
+        // Implicit discriminants would only clutter the list.
+        let discriminant =
+            variant.discriminant(ctx.db).filter(|_| variant.source(ctx.db).value.expr().is_some());
+        let detail = match (variant_kind, discriminant) {
+            (StructKind::Unit, Some(discriminant)) => format!("= {}", discriminant),
+            (StructKind::Tuple, _) | (StructKind::Unit, _) => detail_types
                 .map(|(_, t)| t.display(ctx.db).to_string())
                 .sep_by(", ")
                 .surround_with("(", ")")
                 .to_string(),
-            StructKind::Record => detail_types
+            (StructKind::Record, _) => detail_types
                 .map(|(n, t)| format!("{}: {}", n, t.display(ctx.db).to_string()))
                 .sep_by(", ")
                 .surround_with("{ ", " }")
@@ -346,7 +375,16 @@ impl Builder {
 }
 
 fn is_deprecated(node: impl HasAttrs, db: &RootDatabase) -> bool {
-    node.attrs(db).by_key("deprecated").exists()
+    node.attrs(db).is_deprecated()
+}
+
+/// Items marked `#[doc(hidden)]` are only completed in the crate defining them.
+fn is_hidden(ctx: &CompletionContext, node: impl HasAttrs, module: Option<hir::Module>) -> bool {
+    node.attrs(ctx.db).is_doc_hidden() && !is_in_local_crate(ctx, module)
+}
+
+fn is_in_local_crate(ctx: &CompletionContext, module: Option<hir::Module>) -> bool {
+    module.map(|it| it.krate()) == ctx.krate
 }
 
 #[cfg(test)]
@@ -450,6 +488,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn enum_detail_shows_explicit_discriminant_for_unit() {
+        assert_debug_snapshot!(
+        do_reference_completion(
+            r#"
+                enum Foo {
+                    Foo = 3
+                }
+
+                fn main() { Foo::Fo<|> }
+                "#,
+        ),
+        @r###"
+        [
+            CompletionItem {
+                label: "Foo",
+                source_range: [108; 110),
+                delete: [108; 110),
+                insert: "Foo",
+                kind: EnumVariant,
+                detail: "= 3",
+            },
+        ]"###
+        );
+    }
+
     #[test]
     fn sets_deprecated_flag_in_completion_items() {
         assert_debug_snapshot!(
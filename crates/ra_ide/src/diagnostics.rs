@@ -3,21 +3,26 @@
 use std::cell::RefCell;
 
 use hir::{
+    db::AstDatabase,
     diagnostics::{AstDiagnostic, Diagnostic as _, DiagnosticSink},
-    Semantics,
+    PathResolution, Semantics,
 };
 use itertools::Itertools;
-use ra_db::{RelativePath, SourceDatabase, SourceDatabaseExt};
+use ra_db::{Edition, RelativePath, SourceDatabase, SourceDatabaseExt};
 use ra_ide_db::RootDatabase;
 use ra_prof::profile;
 use ra_syntax::{
     algo,
-    ast::{self, make, AstNode},
-    SyntaxNode, TextRange, T,
+    ast::{self, make, AstNode, NameOwner},
+    SyntaxError, SyntaxErrorCode, SyntaxKind, SyntaxNode, TextRange, TextUnit, T,
 };
 use ra_text_edit::{TextEdit, TextEditBuilder};
 
-use crate::{Diagnostic, FileId, FileSystemEdit, SourceChange, SourceFileEdit};
+use crate::{
+    format_string::{is_format_string, lex_format_string},
+    include_path::IncludePath,
+    Diagnostic, DiagnosticLabel, FileId, FileSystemEdit, SourceChange, SourceFileEdit,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub enum Severity {
@@ -25,7 +30,11 @@ pub enum Severity {
     WeakWarning,
 }
 
-pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic> {
+pub(crate) fn diagnostics(
+    db: &RootDatabase,
+    file_id: FileId,
+    enable_experimental: bool,
+) -> Vec<Diagnostic> {
     let _p = profile("diagnostics");
     let sema = Semantics::new(db);
     let parse = db.parse(file_id);
@@ -35,12 +44,18 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
         range: err.range(),
         message: format!("Syntax Error: {}", err),
         severity: Severity::Error,
-        fix: None,
+        fixes: syntax_error_fix(file_id, parse.tree().syntax(), err).into_iter().collect(),
+        code: "syntax-error",
+        labels: Vec::new(),
     }));
 
-    for node in parse.tree().syntax().descendants() {
+    let edition = sema.to_module_def(file_id).map(|it| it.krate().edition(db));
+    for node in sema.parse(file_id).syntax().descendants() {
         check_unnecessary_braces_in_use_statement(&mut res, file_id, &node);
         check_struct_shorthand_initialization(&mut res, file_id, &node);
+        check_include_path(db, &mut res, file_id, &node);
+        check_format_strings(&mut res, &node);
+        check_bare_trait_object(&sema, edition, &mut res, file_id, &node);
     }
     let res = RefCell::new(res);
     let mut sink = DiagnosticSink::new(|d| {
@@ -48,7 +63,9 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
             message: d.message(),
             range: d.highlight_range(),
             severity: Severity::Error,
-            fix: None,
+            fixes: Vec::new(),
+            code: d.code(),
+            labels: labels(db, d),
         })
     })
     .on::<hir::diagnostics::UnresolvedModule, _>(|d| {
@@ -65,7 +82,9 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
             range: d.highlight_range(),
             message: d.message(),
             severity: Severity::Error,
-            fix: Some(fix),
+            fixes: vec![fix],
+            code: d.code(),
+            labels: labels(db, d),
         })
     })
     .on::<hir::diagnostics::MissingFields, _>(|d| {
@@ -98,7 +117,9 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
             range: d.highlight_range(),
             message: d.message(),
             severity: Severity::Error,
-            fix,
+            fixes: fix.into_iter().collect(),
+            code: d.code(),
+            labels: labels(db, d),
         })
     })
     .on::<hir::diagnostics::MissingMatchArms, _>(|d| {
@@ -106,7 +127,9 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
             range: d.highlight_range(),
             message: d.message(),
             severity: Severity::Error,
-            fix: None,
+            fixes: Vec::new(),
+            code: d.code(),
+            labels: labels(db, d),
         })
     })
     .on::<hir::diagnostics::MissingOkInTailExpr, _>(|d| {
@@ -118,16 +141,198 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
             range: d.highlight_range(),
             message: d.message(),
             severity: Severity::Error,
-            fix: Some(fix),
+            fixes: vec![fix],
+            code: d.code(),
+            labels: labels(db, d),
+        })
+    })
+    .on::<hir::diagnostics::NeedsMut, _>(|d| {
+        let fix = d.ast(db).name().map(|name| {
+            let edit = TextEdit::insert(name.syntax().text_range().start(), "mut ".to_string());
+            SourceChange::source_file_edit_from("make binding mutable", file_id, edit)
+        });
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: d.message(),
+            severity: Severity::Error,
+            fixes: fix.into_iter().collect(),
+            code: d.code(),
+            labels: labels(db, d),
+        })
+    })
+    .on::<hir::diagnostics::UnusedMut, _>(|d| {
+        let fix = d.ast(db).mut_token().map(|mut_token| {
+            let mut range = mut_token.text_range();
+            if let Some(ws) =
+                mut_token.next_token().filter(|it| it.kind() == SyntaxKind::WHITESPACE)
+            {
+                range = TextRange::from_to(range.start(), ws.text_range().end());
+            }
+            SourceChange::source_file_edit_from("remove `mut`", file_id, TextEdit::delete(range))
+        });
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: d.message(),
+            severity: Severity::WeakWarning,
+            fixes: fix.into_iter().collect(),
+            code: d.code(),
+            labels: labels(db, d),
+        })
+    })
+    .on::<hir::diagnostics::UnreachableCode, _>(|d| {
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: d.message(),
+            severity: Severity::WeakWarning,
+            fixes: Vec::new(),
+            code: d.code(),
+            labels: labels(db, d),
+        })
+    })
+    .on::<hir::diagnostics::UnusedMustUse, _>(|d| {
+        let range = d.ast(db).syntax().text_range();
+        let mut fixes = vec![SourceChange::source_file_edit_from(
+            "assign to `let _`",
+            file_id,
+            TextEdit::insert(range.start(), "let _ = ".to_string()),
+        )];
+        if d.can_propagate {
+            let edit = TextEdit::insert(range.end(), "?".to_string());
+            fixes.push(SourceChange::source_file_edit_from("add `?`", file_id, edit));
+        }
+        if d.is_result {
+            let edit = TextEdit::insert(range.end(), ".unwrap()".to_string());
+            fixes.push(SourceChange::source_file_edit_from("add `.unwrap()`", file_id, edit));
+        }
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: d.message(),
+            severity: Severity::WeakWarning,
+            fixes,
+            code: d.code(),
+            labels: labels(db, d),
+        })
+    })
+    .on::<hir::diagnostics::InvalidVariadicArg, _>(|d| {
+        let (severity, fixes) = match &d.cast_to {
+            Some(cast_to) => {
+                let arg = d.ast(db);
+                let replacement = match arg {
+                    ast::Expr::PathExpr(_)
+                    | ast::Expr::Literal(_)
+                    | ast::Expr::ParenExpr(_)
+                    | ast::Expr::CallExpr(_)
+                    | ast::Expr::MethodCallExpr(_)
+                    | ast::Expr::FieldExpr(_) => format!("{} as {}", arg.syntax(), cast_to),
+                    _ => format!("({}) as {}", arg.syntax(), cast_to),
+                };
+                let edit = TextEdit::replace(arg.syntax().text_range(), replacement);
+                let label = format!("cast to `{}`", cast_to);
+                (Severity::Error, vec![SourceChange::source_file_edit_from(label, file_id, edit)])
+            }
+            None => (Severity::WeakWarning, Vec::new()),
+        };
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: d.message(),
+            severity,
+            fixes,
+            code: d.code(),
+            labels: labels(db, d),
+        })
+    })
+    .on::<hir::diagnostics::UseAfterMove, _>(|d| {
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: format!("{} (experimental)", d.message()),
+            severity: Severity::WeakWarning,
+            fixes: Vec::new(),
+            code: d.code(),
+            labels: labels(db, d),
+        })
+    })
+    .on::<hir::diagnostics::BorrowConflict, _>(|d| {
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: format!("{} (experimental)", d.message()),
+            severity: Severity::WeakWarning,
+            fixes: Vec::new(),
+            code: d.code(),
+            labels: labels(db, d),
+        })
+    })
+    .on::<hir::diagnostics::DiscriminantOverflow, _>(|d| {
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: d.message(),
+            severity: Severity::WeakWarning,
+            fixes: Vec::new(),
+            code: d.code(),
+            labels: labels(db, d),
         })
     });
     if let Some(m) = sema.to_module_def(file_id) {
         m.diagnostics(db, &mut sink);
     };
+    if enable_experimental {
+        for fn_def in parse.tree().syntax().descendants().filter_map(ast::FnDef::cast) {
+            if let Some(f) = sema.to_def(&fn_def) {
+                f.experimental_diagnostics(db, &mut sink);
+            }
+        }
+    }
     drop(sink);
     res.into_inner()
 }
 
+/// Maps the labels of `d` to the files they are in.
+fn labels(db: &RootDatabase, d: &dyn hir::diagnostics::Diagnostic) -> Vec<DiagnosticLabel> {
+    d.labels()
+        .into_iter()
+        .filter_map(|(ptr, message)| {
+            let root = db.parse_or_expand(ptr.file_id)?;
+            let node = ptr.value.to_node(&root);
+            let range = hir::original_range(db, ptr.with_value(&node));
+            Some(DiagnosticLabel { range, message })
+        })
+        .collect()
+}
+
+fn syntax_error_fix(file_id: FileId, root: &SyntaxNode, err: &SyntaxError) -> Option<SourceChange> {
+    let (label, edit) = match err.code()? {
+        SyntaxErrorCode::UnnecessaryVisibility => {
+            let vis = algo::find_node_at_offset::<ast::Visibility>(root, err.range().start())?;
+            let mut range = vis.syntax().text_range();
+            if let Some(ws) = vis
+                .syntax()
+                .next_sibling_or_token()
+                .filter(|it| it.kind() == SyntaxKind::WHITESPACE)
+            {
+                range = TextRange::from_to(range.start(), ws.text_range().end());
+            }
+            ("remove visibility qualifier", TextEdit::delete(range))
+        }
+        SyntaxErrorCode::InvalidLiteralSuffix => {
+            ("remove invalid suffix", TextEdit::delete(err.range()))
+        }
+        SyntaxErrorCode::DocCommentDocumentsNothing => {
+            let comment = algo::find_covering_element(root, err.range());
+            if comment.kind() != SyntaxKind::COMMENT {
+                return None;
+            }
+            // Dropping the third character turns both `///` and `/**` into
+            // regular comments.
+            let range = TextRange::offset_len(
+                comment.text_range().start() + TextUnit::of_str("//"),
+                1.into(),
+            );
+            ("turn into a regular comment", TextEdit::delete(range))
+        }
+        _ => return None,
+    };
+    Some(SourceChange::source_file_edit_from(label, file_id, edit))
+}
+
 fn check_unnecessary_braces_in_use_statement(
     acc: &mut Vec<Diagnostic>,
     file_id: FileId,
@@ -150,10 +355,12 @@ fn check_unnecessary_braces_in_use_statement(
             range,
             message: "Unnecessary braces in use statement".to_string(),
             severity: Severity::WeakWarning,
-            fix: Some(SourceChange::source_file_edit(
+            fixes: vec![SourceChange::source_file_edit(
                 "Remove unnecessary braces",
                 SourceFileEdit { file_id, edit },
-            )),
+            )],
+            code: "unnecessary-braces",
+            labels: Vec::new(),
         });
     }
 
@@ -194,10 +401,12 @@ fn check_struct_shorthand_initialization(
                     range: record_field.syntax().text_range(),
                     message: "Shorthand struct initialization".to_string(),
                     severity: Severity::WeakWarning,
-                    fix: Some(SourceChange::source_file_edit(
+                    fixes: vec![SourceChange::source_file_edit(
                         "use struct shorthand initialization",
                         SourceFileEdit { file_id, edit },
-                    )),
+                    )],
+                    code: "struct-shorthand-initialization",
+                    labels: Vec::new(),
                 });
             }
         }
@@ -205,6 +414,95 @@ fn check_struct_shorthand_initialization(
     Some(())
 }
 
+fn check_include_path(
+    db: &RootDatabase,
+    acc: &mut Vec<Diagnostic>,
+    file_id: FileId,
+    node: &SyntaxNode,
+) -> Option<()> {
+    let macro_call = ast::MacroCall::cast(node.clone())?;
+    let include = IncludePath::from_macro_call(&macro_call)?;
+    if !include.is_checkable(db, file_id) || include.resolve(db, file_id).is_some() {
+        return None;
+    }
+    acc.push(Diagnostic {
+        range: include.range,
+        message: format!("included file `{}` does not exist", include.path),
+        // Excluded directories, like `target`, aren't loaded either.
+        severity: Severity::WeakWarning,
+        fixes: Vec::new(),
+        code: "unresolved-include",
+        labels: Vec::new(),
+    });
+    Some(())
+}
+
+fn check_format_strings(acc: &mut Vec<Diagnostic>, node: &SyntaxNode) -> Option<()> {
+    let tt = ast::TokenTree::cast(node.clone())?;
+    let string = tt
+        .syntax()
+        .children_with_tokens()
+        .filter_map(|it| it.into_token())
+        .find(|it| is_format_string(it))?;
+    for (range, message) in lex_format_string(&string)?.errors {
+        acc.push(Diagnostic {
+            range,
+            message,
+            severity: Severity::Error,
+            fixes: Vec::new(),
+            code: "malformed-format-string",
+            labels: Vec::new(),
+        });
+    }
+    Some(())
+}
+
+/// Trait objects without `dyn` are linted against since the 2018 edition, and
+/// are an error since 2021.
+fn check_bare_trait_object(
+    sema: &Semantics<RootDatabase>,
+    edition: Option<Edition>,
+    acc: &mut Vec<Diagnostic>,
+    file_id: FileId,
+    node: &SyntaxNode,
+) -> Option<()> {
+    let (message, severity) = match edition? {
+        Edition::Edition2015 => return None,
+        Edition::Edition2018 => {
+            ("trait objects without an explicit `dyn` are deprecated", Severity::WeakWarning)
+        }
+        Edition::Edition2021 => ("trait objects must include the `dyn` keyword", Severity::Error),
+    };
+    let path_type = ast::PathType::cast(node.clone())?;
+    let parent = path_type.syntax().parent()?;
+    // Bounds, implemented traits and the trait of a qualified path name the
+    // trait itself rather than a trait object.
+    if matches!(parent.kind(), SyntaxKind::TYPE_BOUND | SyntaxKind::PATH_SEGMENT) {
+        return None;
+    }
+    if let Some(impl_def) = ast::ImplDef::cast(parent) {
+        if impl_def.target_trait().map_or(false, |it| it.syntax() == path_type.syntax()) {
+            return None;
+        }
+    }
+    match sema.resolve_path(&path_type.path()?)? {
+        PathResolution::Def(hir::ModuleDef::Trait(_)) => {}
+        _ => return None,
+    }
+
+    let range = path_type.syntax().text_range();
+    let edit = TextEdit::insert(range.start(), "dyn ".to_string());
+    acc.push(Diagnostic {
+        range,
+        message: message.to_string(),
+        severity,
+        fixes: vec![SourceChange::source_file_edit_from("add `dyn` keyword", file_id, edit)],
+        code: "bare-trait-object",
+        labels: Vec::new(),
+    });
+    Some(())
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_debug_snapshot;
@@ -212,7 +510,10 @@ mod tests {
     use stdx::SepBy;
     use test_utils::assert_eq_text;
 
-    use crate::mock_analysis::{analysis_and_position, single_file};
+    use crate::{
+        mock_analysis::{analysis_and_position, single_file, MockAnalysis},
+        FileRange,
+    };
 
     use super::*;
 
@@ -235,7 +536,7 @@ mod tests {
         }
         let diagnostic =
             diagnostics.pop().unwrap_or_else(|| panic!("no diagnostics for:\n{}\n", before));
-        let mut fix = diagnostic.fix.unwrap();
+        let mut fix = diagnostic.fixes.into_iter().next().unwrap();
         let edit = fix.source_file_edits.pop().unwrap().edit;
         let actual = edit.apply(&before);
         assert_eq_text!(after, &actual);
@@ -248,8 +549,8 @@ mod tests {
     ///  * that the contents of the file containing the cursor match `after` after the diagnostic fix is applied
     fn check_apply_diagnostic_fix_from_position(fixture: &str, after: &str) {
         let (analysis, file_position) = analysis_and_position(fixture);
-        let diagnostic = analysis.diagnostics(file_position.file_id).unwrap().pop().unwrap();
-        let mut fix = diagnostic.fix.unwrap();
+        let diagnostic = analysis.diagnostics(file_position.file_id, false).unwrap().pop().unwrap();
+        let mut fix = diagnostic.fixes.into_iter().next().unwrap();
         let edit = fix.source_file_edits.pop().unwrap().edit;
         let target_file_contents = analysis.file_text(file_position.file_id).unwrap();
         let actual = edit.apply(&target_file_contents);
@@ -281,8 +582,8 @@ mod tests {
 
     fn check_apply_diagnostic_fix(before: &str, after: &str) {
         let (analysis, file_id) = single_file(before);
-        let diagnostic = analysis.diagnostics(file_id).unwrap().pop().unwrap();
-        let mut fix = diagnostic.fix.unwrap();
+        let diagnostic = analysis.diagnostics(file_id, false).unwrap().pop().unwrap();
+        let mut fix = diagnostic.fixes.into_iter().next().unwrap();
         let edit = fix.source_file_edits.pop().unwrap().edit;
         let actual = edit.apply(&before);
         assert_eq_text!(after, &actual);
@@ -292,13 +593,13 @@ mod tests {
     /// apply to the file containing the cursor.
     fn check_no_diagnostic_for_target_file(fixture: &str) {
         let (analysis, file_position) = analysis_and_position(fixture);
-        let diagnostics = analysis.diagnostics(file_position.file_id).unwrap();
+        let diagnostics = analysis.diagnostics(file_position.file_id, false).unwrap();
         assert_eq!(diagnostics.len(), 0);
     }
 
     fn check_no_diagnostic(content: &str) {
         let (analysis, file_id) = single_file(content);
-        let diagnostics = analysis.diagnostics(file_id).unwrap();
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
         assert_eq!(diagnostics.len(), 0, "expected no diagnostic, found one");
     }
 
@@ -594,13 +895,13 @@ mod tests {
     #[test]
     fn test_unresolved_module_diagnostic() {
         let (analysis, file_id) = single_file("mod foo;");
-        let diagnostics = analysis.diagnostics(file_id).unwrap();
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
         assert_debug_snapshot!(diagnostics, @r###"
         [
             Diagnostic {
                 message: "unresolved module",
                 range: [0; 8),
-                fix: Some(
+                fixes: [
                     SourceChange {
                         label: "create module",
                         source_file_edits: [],
@@ -613,9 +914,12 @@ mod tests {
                             },
                         ],
                         cursor_position: None,
+                        is_snippet: false,
                     },
-                ),
+                ],
                 severity: Error,
+                code: "unresolved-module",
+                labels: [],
             },
         ]
         "###);
@@ -640,6 +944,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_check_format_strings() {
+        check_not_applicable(
+            r#"fn main() { println!("{} {:>8.3} {{}}", a, b); }"#,
+            |acc, _, node| check_format_strings(acc, node),
+        );
+        check_not_applicable(
+            r#"
+            fn main() {
+                panic!("{:z}");
+                unreachable!("{:z}",);
+                unimplemented!("{:z}");
+                asm!("mov {:z}, 1");
+            }
+        "#,
+            |acc, _, node| check_format_strings(acc, node),
+        );
+
+        let (analysis, file_id) = single_file(r#"fn main() { println!("{:z}", a); }"#);
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
+        assert_debug_snapshot!(diagnostics, @r###"
+        [
+            Diagnostic {
+                message: "unknown format trait `z`",
+                range: [24; 25),
+                fixes: [],
+                severity: Error,
+                code: "malformed-format-string",
+                labels: [],
+            },
+        ]
+        "###);
+    }
+
     #[test]
     fn test_check_struct_shorthand_initialization() {
         check_not_applicable(
@@ -719,4 +1057,484 @@ fn main() {
             check_struct_shorthand_initialization,
         );
     }
+
+    #[test]
+    fn test_remove_unnecessary_visibility() {
+        check_apply_diagnostic_fix(
+            "trait T { fn f(); }\nimpl T for () { pub fn f() {} }\n",
+            "trait T { fn f(); }\nimpl T for () { fn f() {} }\n",
+        );
+    }
+
+    #[test]
+    fn test_remove_invalid_literal_suffix() {
+        check_apply_diagnostic_fix("fn main() { let _ = 92u3; }", "fn main() { let _ = 92; }");
+    }
+
+    #[test]
+    fn test_turn_dangling_doc_comment_into_comment() {
+        check_apply_diagnostic_fix(
+            "struct S {\n    a: u32,\n    /// Nothing\n}\n",
+            "struct S {\n    a: u32,\n    // Nothing\n}\n",
+        );
+        check_apply_diagnostic_fix(
+            "fn main() {\n    /** Nothing */\n}\n",
+            "fn main() {\n    /* Nothing */\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_add_mut_to_mutated_binding() {
+        check_apply_diagnostic_fix(
+            "struct S { f: i32 }\nfn main() {\n    let s = S { f: 0 };\n    s.f = 1;\n}\n",
+            "struct S { f: i32 }\nfn main() {\n    let mut s = S { f: 0 };\n    s.f = 1;\n}\n",
+        );
+        check_apply_diagnostic_fix(
+            "struct S;\nimpl S { fn touch(&mut self) {} }\nfn main() {\n    let s = S;\n    s.touch();\n}\n",
+            "struct S;\nimpl S { fn touch(&mut self) {} }\nfn main() {\n    let mut s = S;\n    s.touch();\n}\n",
+        );
+        check_apply_diagnostic_fix(
+            "fn main(x: i32) {\n    let r = &mut x;\n}\n",
+            "fn main(mut x: i32) {\n    let r = &mut x;\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_remove_unused_mut() {
+        check_apply_diagnostic_fix(
+            "fn main() {\n    let mut x = 92;\n    let _ = x;\n}\n",
+            "fn main() {\n    let x = 92;\n    let _ = x;\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_no_mutability_diagnostics_for_mutation_through_references() {
+        check_no_diagnostic(
+            r"
+            struct S { f: i32 }
+            impl S { fn touch(&mut self) {} }
+            fn f(r: &mut S) {
+                let r2 = r;
+                r2.f = 1;
+                r2.touch();
+                let mut s = S { f: 0 };
+                let t = &mut s;
+                t.f = 2;
+                let mut late;
+                late = 1;
+                let _ = late;
+                let mut n = 0;
+                let mut inc = || n += 1;
+                inc();
+            }
+        ",
+        );
+    }
+
+    #[test]
+    fn test_unreachable_code_covers_all_dead_statements() {
+        let (analysis, file_id) = single_file("fn f() {\n    return;\n    let x = 1;\n    x;\n}\n");
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "unreachable code");
+        assert_eq!(diagnostics[0].range, TextRange::from_to(25.into(), 42.into()));
+    }
+
+    #[test]
+    fn test_no_unreachable_code_after_return_in_closures_and_async_blocks() {
+        check_no_diagnostic(
+            r"
+            fn f() {
+                let g = || return;
+                let h = async { return; };
+                let x = 1;
+                let _ = x;
+            }
+        ",
+        );
+    }
+
+    #[test]
+    fn test_needs_mut_points_at_binding() {
+        let (analysis, file_id) = single_file("fn main() {\n    let x = 1;\n    x = 2;\n}\n");
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        let labels = &diagnostics[0].labels;
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].message, "`x` is declared immutable here");
+        assert_eq!(
+            labels[0].range,
+            FileRange { file_id, range: TextRange::from_to(20.into(), 21.into()) }
+        );
+    }
+
+    #[test]
+    fn test_discriminant_overflow() {
+        let (analysis, file_id) =
+            single_file("#[repr(u8)]\nenum E { A = 254, B, C }\nenum F { X = 256, Y = -1 }\n");
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "discriminant value `256` does not fit in `u8`");
+        assert_eq!(diagnostics[0].range, TextRange::from_to(33.into(), 34.into()));
+    }
+
+    #[test]
+    fn test_unused_must_use_fixes() {
+        let before = r#"
+            //- /main.rs
+            use std::result::Result;
+
+            #[must_use]
+            fn answer() -> i32 { 42 }
+            fn fallible() -> Result<i32, ()> { Result::Ok(1) }
+
+            fn f() -> Result<(), ()> {
+                answer();
+                fallible();
+                let _ = fallible();
+                Result::Ok(())
+            }
+
+            //- /std/lib.rs
+            pub mod result {
+                #[must_use]
+                pub enum Result<T, E> { Ok(T), Err(E) }
+            }
+        "#;
+        let mock = MockAnalysis::with_files(before);
+        let file_id = mock.id_of("/main.rs");
+        let analysis = mock.analysis();
+        let text = analysis.file_text(file_id).unwrap();
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
+        assert_eq!(diagnostics.len(), 2, "{:#?}", diagnostics);
+
+        assert_eq!(diagnostics[0].message, "unused return value of `answer` that must be used");
+        let labels = diagnostics[0].fixes.iter().map(|it| it.label.as_str()).collect::<Vec<_>>();
+        assert_eq!(labels, ["assign to `let _`"]);
+        let edit = &diagnostics[0].fixes[0].source_file_edits[0].edit;
+        assert!(edit.apply(&text).contains("    let _ = answer();\n"));
+
+        assert_eq!(diagnostics[1].message, "unused `Result` that must be used");
+        let labels = diagnostics[1].fixes.iter().map(|it| it.label.as_str()).collect::<Vec<_>>();
+        assert_eq!(labels, ["assign to `let _`", "add `?`", "add `.unwrap()`"]);
+        let edit = &diagnostics[1].fixes[1].source_file_edits[0].edit;
+        assert!(edit.apply(&text).contains("    fallible()?;\n"));
+    }
+
+    #[test]
+    fn test_unused_must_use_in_closures_and_macros() {
+        let mock = MockAnalysis::with_files(
+            r#"
+            //- /main.rs
+            use std::result::Result;
+
+            macro_rules! call { () => { fallible() }; }
+            fn fallible() -> Result<i32, ()> { Result::Ok(1) }
+
+            fn f() -> Result<(), ()> {
+                call!();
+                let g = || { fallible(); };
+                let h = async { fallible(); };
+                Result::Ok(())
+            }
+
+            //- /std/lib.rs
+            pub mod result {
+                #[must_use]
+                pub enum Result<T, E> { Ok(T), Err(E) }
+            }
+        "#,
+        );
+        let file_id = mock.id_of("/main.rs");
+        let diagnostics = mock.analysis().diagnostics(file_id, false).unwrap();
+        assert_eq!(diagnostics.len(), 2, "{:#?}", diagnostics);
+        for diagnostic in &diagnostics {
+            let labels = diagnostic.fixes.iter().map(|it| it.label.as_str()).collect::<Vec<_>>();
+            assert_eq!(labels, ["assign to `let _`", "add `.unwrap()`"]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_variadic_args() {
+        let (analysis, file_id) = single_file(
+            r#"
+extern "C" { fn printf(format: *const u8, ...) -> i32; }
+struct Point { x: i32 }
+fn f(x: f32, p: Point) {
+    unsafe { printf(0 as *const u8, 1, 2.5, x, p, "s"); }
+}
+"#,
+        );
+        let text = analysis.file_text(file_id).unwrap();
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
+        let messages = diagnostics.iter().map(|it| it.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "can't pass `f32` to variadic function, cast it to `f64`",
+                "can't pass `Point` to variadic function, it is not FFI-safe",
+                "can't pass `&str` to variadic function, it is not FFI-safe",
+            ]
+        );
+        assert_eq!(diagnostics[0].fixes[0].label, "cast to `f64`");
+        let edit = &diagnostics[0].fixes[0].source_file_edits[0].edit;
+        assert!(edit.apply(&text).contains("1, 2.5, x as f64, p,"));
+    }
+
+    #[test]
+    fn test_nullable_pointers_are_valid_variadic_args() {
+        let mock = MockAnalysis::with_files(
+            r#"
+            //- /main.rs
+            use std::option::Option;
+            extern "C" { fn printf(format: *const u8, ...) -> i32; }
+            fn f(r: Option<&i32>, cb: Option<extern "C" fn()>, s: Option<&str>, n: Option<i32>) {
+                unsafe { printf(0 as *const u8, r, cb, s, n); }
+            }
+
+            //- /std/lib.rs
+            pub mod option {
+                pub enum Option<T> { None, Some(T) }
+            }
+        "#,
+        );
+        let file_id = mock.id_of("/main.rs");
+        let analysis = mock.analysis();
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
+        let messages = diagnostics.iter().map(|it| it.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "can't pass `Option<&str>` to variadic function, it is not FFI-safe",
+                "can't pass `Option<i32>` to variadic function, it is not FFI-safe",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_included_file() {
+        let mock = MockAnalysis::with_files(
+            r#"
+            //- /main.rs
+            include!("gen/foo.rs");
+            include!("gen/bar.rs");
+            const DATA: &[u8] = include_bytes!("data.bin");
+            include!(concat!(env!("OUT_DIR"), "/out.rs"));
+            include!("../target/debug/out.rs");
+            include!("/tmp/out.rs");
+
+            //- /gen/foo.rs
+            fn foo() {}
+        "#,
+        );
+        let file_id = mock.id_of("/main.rs");
+        let analysis = mock.analysis();
+        let text = analysis.file_text(file_id).unwrap();
+        let diagnostics = analysis.diagnostics(file_id, false).unwrap();
+        assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+        assert_eq!(diagnostics[0].message, "included file `gen/bar.rs` does not exist");
+        assert_eq!(&text[diagnostics[0].range], "\"gen/bar.rs\"");
+    }
+
+    const MOVE_FIXTURE: &str = r#"
+#[lang = "copy"]
+trait Copy {}
+struct S;
+impl S { fn len(&self) -> usize { 0 } }
+fn consume(s: S) {}
+"#;
+
+    #[test]
+    fn test_use_after_move() {
+        let text = format!(
+            "{}{}",
+            MOVE_FIXTURE,
+            "fn f() {\n    let s = S;\n    consume(s);\n    s.len();\n    let n = 1;\n    let m = n;\n    n;\n}\n"
+        );
+        let (analysis, file_id) = single_file(&text);
+        assert!(analysis.diagnostics(file_id, false).unwrap().is_empty());
+        let diagnostics = analysis.diagnostics(file_id, true).unwrap();
+        assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+        assert_eq!(diagnostics[0].message, "use of moved value `s` (experimental)");
+        assert!(matches!(diagnostics[0].severity, Severity::WeakWarning));
+        assert_eq!(&text[diagnostics[0].range], "s");
+        assert_eq!(
+            diagnostics[0].range.start(),
+            TextUnit::of_str(&text[..text.rfind("s.len").unwrap()])
+        );
+        let labels = &diagnostics[0].labels;
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].message, "`s` is moved here");
+        assert_eq!(&text[labels[0].range.range], "s");
+    }
+
+    #[test]
+    fn test_borrow_conflict() {
+        let text = format!(
+            "{}{}",
+            MOVE_FIXTURE,
+            "fn f() {\n    let mut s = S;\n    let r = &mut s;\n    s.len();\n    r.len();\n}\n"
+        );
+        let (analysis, file_id) = single_file(&text);
+        let diagnostics = analysis.diagnostics(file_id, true).unwrap();
+        assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot use `s` while it is mutably borrowed (experimental)"
+        );
+        let labels: Vec<_> =
+            diagnostics[0].labels.iter().map(|it| (&text[it.range.range], &*it.message)).collect();
+        assert_eq!(
+            labels,
+            vec![("&mut s", "`s` is borrowed here"), ("r", "the borrow is used later here")]
+        );
+    }
+
+    #[test]
+    fn test_no_move_diagnostics_after_reassignment_or_last_use_of_borrow() {
+        let text = format!(
+            "{}{}",
+            MOVE_FIXTURE,
+            r#"
+fn f(c: bool) {
+    let mut s = S;
+    consume(s);
+    s = S;
+    let r = &mut s;
+    r.len();
+    s.len();
+    let t = S;
+    let u = &t;
+    t.len();
+    u.len();
+    if c {
+        consume(t);
+    } else {
+        t.len();
+    }
+}
+"#
+        );
+        let (analysis, file_id) = single_file(&text);
+        let diagnostics = analysis.diagnostics(file_id, true).unwrap();
+        assert!(diagnostics.is_empty(), "{:#?}", diagnostics);
+    }
+
+    #[test]
+    fn test_no_use_after_move_after_reassignment_in_branches() {
+        let text = format!(
+            "{}{}",
+            MOVE_FIXTURE,
+            r#"
+fn f(c: bool, n: u32) {
+    let mut s = S;
+    consume(s);
+    if c { s = S } else { s = S }
+    s.len();
+    consume(s);
+    match n {
+        0 => s = S,
+        _ => s = S,
+    }
+    s.len();
+    consume(s);
+    loop {
+        s = S;
+        break;
+    }
+    s.len();
+}
+"#
+        );
+        let (analysis, file_id) = single_file(&text);
+        let diagnostics = analysis.diagnostics(file_id, true).unwrap();
+        assert!(diagnostics.is_empty(), "{:#?}", diagnostics);
+    }
+
+    #[test]
+    fn test_no_use_after_move_for_unknown_types() {
+        let text = format!(
+            "{}{}",
+            MOVE_FIXTURE,
+            r#"
+enum Opt<T> { No, Yes(T) }
+fn take<T>(t: T) {}
+fn f() {
+    let o = Opt::No;
+    take(o);
+    take(o);
+}
+"#
+        );
+        let (analysis, file_id) = single_file(&text);
+        let diagnostics = analysis.diagnostics(file_id, true).unwrap();
+        assert!(diagnostics.is_empty(), "{:#?}", diagnostics);
+    }
+
+    #[test]
+    fn test_no_use_after_move_for_autoref_receivers() {
+        let text = format!(
+            "{}{}",
+            MOVE_FIXTURE,
+            r#"
+trait Peek { fn peek(self) -> usize; }
+impl Peek for &S { fn peek(self) -> usize { 0 } }
+fn f() {
+    let s = S;
+    s.peek();
+    s.len();
+}
+"#
+        );
+        let (analysis, file_id) = single_file(&text);
+        let diagnostics = analysis.diagnostics(file_id, true).unwrap();
+        assert!(diagnostics.is_empty(), "{:#?}", diagnostics);
+    }
+
+    #[test]
+    fn test_add_dyn_to_bare_trait_object() {
+        check_apply_diagnostic_fix(
+            r#"
+trait Foo {}
+fn foo(_: &Foo) {}
+"#,
+            r#"
+trait Foo {}
+fn foo(_: &dyn Foo) {}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_bare_trait_objects_depend_on_edition() {
+        let text = r#"
+trait Foo {}
+trait Bar {}
+trait Baz { type Assoc; }
+struct S;
+impl Baz for S { type Assoc = (); }
+impl Bar {}
+fn foo<T: Baz>(_: &Foo, _: &dyn Baz, _: impl Baz, _: <S as Baz>::Assoc) where T: Baz {}
+"#;
+        let diagnostics = |edition| {
+            let mut mock = MockAnalysis::new();
+            mock.set_edition(edition);
+            let file_id = mock.add_file("/main.rs", text);
+            mock.analysis()
+                .diagnostics(file_id, false)
+                .unwrap()
+                .into_iter()
+                .map(|it| (&text[it.range], it.message))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(diagnostics(Edition::Edition2015).is_empty());
+        let deprecated = "trait objects without an explicit `dyn` are deprecated".to_string();
+        assert_eq!(
+            diagnostics(Edition::Edition2018),
+            [("Bar", deprecated.clone()), ("Foo", deprecated)]
+        );
+        let error = "trait objects must include the `dyn` keyword".to_string();
+        assert_eq!(diagnostics(Edition::Edition2021), [("Bar", error.clone()), ("Foo", error)]);
+    }
 }
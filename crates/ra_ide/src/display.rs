@@ -2,6 +2,7 @@
 //! into types that may be used to render in a UI.
 
 mod function_signature;
+mod lifetime_elision;
 mod navigation_target;
 mod structure;
 mod short_label;
@@ -5,12 +5,12 @@ use std::{
     fmt::{self, Display},
 };
 
-use hir::{Docs, Documentation, HasSource, HirDisplay};
+use hir::{Docs, Documentation, HasSource, HirDisplay, Semantics};
 use ra_ide_db::RootDatabase;
-use ra_syntax::ast::{self, AstNode, NameOwner, VisibilityOwner};
+use ra_syntax::ast::{self, AstNode, NameOwner, TypeParamsOwner, VisibilityOwner};
 use stdx::SepBy;
 
-use crate::display::{generic_parameters, where_predicates};
+use crate::display::{generic_parameters, lifetime_elision::explicit_lifetimes, where_predicates};
 
 #[derive(Debug)]
 pub enum CallableKind {
@@ -26,6 +26,8 @@ pub struct FunctionSignature {
     pub kind: CallableKind,
     /// Optional visibility
     pub visibility: Option<String>,
+    /// Optional `extern` qualifier, with the ABI
+    pub abi: Option<String>,
     /// Name of the function
     pub name: Option<String>,
     /// Documentation for the function
@@ -36,6 +38,8 @@ pub struct FunctionSignature {
     pub parameters: Vec<String>,
     /// Parameter names of the function
     pub parameter_names: Vec<String>,
+    /// Documentation for each of the parameters
+    pub parameter_docs: Vec<Option<String>>,
     /// Optional return type
     pub ret_type: Option<String>,
     /// Where predicates
@@ -53,7 +57,32 @@ impl FunctionSignature {
     pub(crate) fn from_hir(db: &RootDatabase, function: hir::Function) -> Self {
         let doc = function.docs(db);
         let ast_node = function.source(db).value;
-        FunctionSignature::from(&ast_node).with_doc_opt(doc)
+        let mut signature = FunctionSignature::from(&ast_node);
+        if let Some(doc) = &doc {
+            signature.parameter_docs = signature
+                .parameter_names
+                .iter()
+                .map(|name| argument_doc(doc.as_str(), name))
+                .collect();
+        }
+        signature.with_doc_opt(doc)
+    }
+
+    /// Spells out the lifetimes which are elided in the signature of `node`,
+    /// like `fn get<'a>(&'a self) -> &'a str` for `fn get(&self) -> &str`.
+    pub(crate) fn with_elided_lifetimes(
+        mut self,
+        sema: &Semantics<RootDatabase>,
+        node: &ast::FnDef,
+    ) -> Self {
+        if let Some(explicit) = explicit_lifetimes(sema, node) {
+            // Lifetime parameters come before the type parameters.
+            let idx = node.type_param_list().map_or(0, |it| it.lifetime_params().count());
+            self.generic_parameters.splice(idx..idx, explicit.lifetime_params);
+            self.parameters = explicit.parameters;
+            self.ret_type = explicit.ret_type;
+        }
+        self
     }
 
     pub(crate) fn from_struct(db: &RootDatabase, st: hir::Struct) -> Option<Self> {
@@ -75,10 +104,12 @@ impl FunctionSignature {
             FunctionSignature {
                 kind: CallableKind::StructConstructor,
                 visibility: node.visibility().map(|n| n.syntax().text().to_string()),
+                abi: None,
                 name: node.name().map(|n| n.text().to_string()),
                 ret_type: node.name().map(|n| n.text().to_string()),
                 parameters: params,
                 parameter_names: vec![],
+                parameter_docs: vec![],
                 generic_parameters: generic_parameters(&node),
                 where_predicates: where_predicates(&node),
                 doc: None,
@@ -113,10 +144,12 @@ impl FunctionSignature {
             FunctionSignature {
                 kind: CallableKind::VariantConstructor,
                 visibility: None,
+                abi: None,
                 name: Some(name),
                 ret_type: None,
                 parameters: params,
                 parameter_names: vec![],
+                parameter_docs: vec![],
                 generic_parameters: vec![],
                 where_predicates: vec![],
                 doc: None,
@@ -135,10 +168,12 @@ impl FunctionSignature {
             FunctionSignature {
                 kind: CallableKind::Macro,
                 visibility: None,
+                abi: None,
                 name: node.name().map(|n| n.text().to_string()),
                 ret_type: None,
                 parameters: params,
                 parameter_names: vec![],
+                parameter_docs: vec![],
                 generic_parameters: vec![],
                 where_predicates: vec![],
                 doc: None,
@@ -149,6 +184,52 @@ impl FunctionSignature {
     }
 }
 
+/// Finds the description of the argument in the `# Arguments` section of the
+/// function docs, which conventionally looks like this:
+///
+/// ```text
+/// # Arguments
+///
+/// * `name` - description of the argument
+/// ```
+fn argument_doc(docs: &str, name: &str) -> Option<String> {
+    let mut lines = docs
+        .lines()
+        .skip_while(|line| {
+            let line = line.trim();
+            !(line.starts_with('#') && line.trim_start_matches('#').trim() == "Arguments")
+        })
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with('#'));
+
+    let quoted_name = format!("`{}`", name);
+    let mut res = loop {
+        let line = lines.next()?.trim();
+        let item = match line.get(..2) {
+            Some("* ") | Some("- ") => line[2..].trim_start(),
+            _ => continue,
+        };
+        if item.starts_with(&quoted_name) {
+            let description = item[quoted_name.len()..]
+                .trim_start_matches(|c: char| c == ':' || c == '-' || c.is_whitespace());
+            break description.to_string();
+        }
+    };
+    // Indented lines continue the list item
+    for line in lines {
+        if !line.starts_with(char::is_whitespace) || line.trim().is_empty() {
+            break;
+        }
+        res.push(' ');
+        res.push_str(line.trim());
+    }
+    if res.is_empty() {
+        None
+    } else {
+        Some(res)
+    }
+}
+
 impl From<&'_ ast::FnDef> for FunctionSignature {
     fn from(node: &ast::FnDef) -> FunctionSignature {
         fn param_list(node: &ast::FnDef) -> (bool, Vec<String>) {
@@ -193,10 +274,16 @@ impl From<&'_ ast::FnDef> for FunctionSignature {
         }
 
         let (has_self_param, parameters) = param_list(node);
+        let parameter_docs = vec![None; parameters.len()];
+        // Functions in `extern` blocks take the ABI of the block.
+        let abi = node
+            .abi()
+            .or_else(|| node.syntax().parent()?.parent().and_then(ast::ExternBlock::cast)?.abi());
 
         FunctionSignature {
             kind: CallableKind::Function,
             visibility: node.visibility().map(|n| n.syntax().text().to_string()),
+            abi: abi.map(|n| n.syntax().text().to_string()),
             name: node.name().map(|n| n.text().to_string()),
             ret_type: node
                 .ret_type()
@@ -204,6 +291,7 @@ impl From<&'_ ast::FnDef> for FunctionSignature {
                 .map(|n| n.syntax().text().to_string()),
             parameters,
             parameter_names: param_name_list(node),
+            parameter_docs,
             generic_parameters: generic_parameters(node),
             where_predicates: where_predicates(node),
             // docs are processed separately
@@ -219,6 +307,10 @@ impl Display for FunctionSignature {
             write!(f, "{} ", t)?;
         }
 
+        if let Some(abi) = &self.abi {
+            write!(f, "{} ", abi)?;
+        }
+
         if let Some(name) = &self.name {
             match self.kind {
                 CallableKind::Function => write!(f, "fn {}", name)?,
@@ -0,0 +1,340 @@
+//! Resolves the lifetimes which are elided in the signature of a function, so
+//! that the signature can be shown with all of its lifetimes spelled out.
+//!
+//! This follows the elision rules of the reference: each elided lifetime of
+//! the parameters becomes a fresh lifetime parameter, and the elided lifetimes
+//! of the return type are the lifetime of `&self`, or else the only lifetime
+//! of the parameters. The lifetimes in `fn` pointers and `Fn` traits are
+//! elided within those types, and are left alone.
+//!
+//! Paths to types with lifetime parameters, like `Formatter`, elide all of
+//! their lifetimes, so the paths are resolved to count them.
+
+use hir::{Adt, HasSource, ModuleDef, PathResolution, Semantics};
+use ra_ide_db::RootDatabase;
+use ra_syntax::{
+    ast::{
+        self,
+        lifetimes::{
+            declared_lifetimes, fresh_lifetime, is_in_nested_scope, lifetime_of, Lifetime,
+        },
+        AstNode, TypeAscriptionOwner, TypeParamsOwner,
+    },
+    SyntaxNode, TextRange, TextUnit,
+};
+use ra_text_edit::TextEditBuilder;
+
+/// The parts of a function signature which change when its elided lifetimes
+/// are spelled out.
+#[derive(Debug)]
+pub(crate) struct ExplicitLifetimes {
+    /// The lifetime parameters which stand for the elided lifetimes.
+    pub(crate) lifetime_params: Vec<String>,
+    /// The self parameter, if any, and the parameters.
+    pub(crate) parameters: Vec<String>,
+    pub(crate) ret_type: Option<String>,
+}
+
+/// Returns `None` if no lifetime is elided in the signature.
+pub(crate) fn explicit_lifetimes(
+    sema: &Semantics<RootDatabase>,
+    node: &ast::FnDef,
+) -> Option<ExplicitLifetimes> {
+    let param_list = node.param_list()?;
+    let mut fresh = FreshLifetimes::new(node);
+    let mut input_lifetimes = Vec::new();
+    let mut self_lifetime = None;
+    let mut parameters = Vec::new();
+
+    if let Some(self_param) = param_list.self_param() {
+        let mut edits = Vec::new();
+        if let Some(lifetime) = lifetime_of(self_param.amp_token(), self_param.lifetime_token()) {
+            let name = fresh.resolve(lifetime, &mut edits);
+            self_lifetime = Some(name.clone());
+            input_lifetimes.push(name);
+        }
+        if let Some(ty) = self_param.ascribed_type() {
+            let names = resolve_inputs(sema, ty.syntax(), &mut fresh, &mut edits);
+            // The outermost reference comes first.
+            if let ast::TypeRef::ReferenceType(_) = ty {
+                self_lifetime = names.first().cloned();
+            }
+            input_lifetimes.extend(names);
+        }
+        parameters.push(apply(self_param.syntax(), edits));
+    }
+    for param in param_list.params() {
+        let mut edits = Vec::new();
+        if let Some(ty) = param.ascribed_type() {
+            input_lifetimes.extend(resolve_inputs(sema, ty.syntax(), &mut fresh, &mut edits));
+        }
+        parameters.push(apply(param.syntax(), edits));
+    }
+
+    let output_lifetime = match (self_lifetime, input_lifetimes.as_slice()) {
+        (Some(it), _) => Some(it),
+        (None, [it]) => Some(it.clone()),
+        // Anything else is an error, unless nothing is elided in the output.
+        (None, _) => None,
+    };
+    let mut ret_type_changed = false;
+    let ret_type = node.ret_type().and_then(|it| it.type_ref()).map(|ty| {
+        let mut edits = Vec::new();
+        if let Some(name) = &output_lifetime {
+            for lifetime in type_lifetimes(sema, ty.syntax()) {
+                match lifetime {
+                    TypeLifetime::Written(Lifetime::Elided(range)) => {
+                        edits.push((range, lifetime_text(range, name)))
+                    }
+                    TypeLifetime::Written(Lifetime::Named(_)) => (),
+                    TypeLifetime::Hidden { offset, count, has_args } => {
+                        let names = vec![name.clone(); count];
+                        edits.push(hidden_lifetimes_edit(offset, &names, has_args));
+                    }
+                }
+            }
+        }
+        ret_type_changed = !edits.is_empty();
+        apply(ty.syntax(), edits)
+    });
+
+    if fresh.introduced.is_empty() && !ret_type_changed {
+        return None;
+    }
+    Some(ExplicitLifetimes { lifetime_params: fresh.introduced, parameters, ret_type })
+}
+
+/// A lifetime of a type, in the order in which they appear.
+enum TypeLifetime {
+    Written(Lifetime),
+    /// The lifetime parameters of a type which a path leaves out, like the
+    /// one of `Formatter`. They go at `offset`, after the `<` if the path has
+    /// other generic arguments.
+    Hidden {
+        offset: TextUnit,
+        count: usize,
+        has_args: bool,
+    },
+}
+
+fn type_lifetimes(sema: &Semantics<RootDatabase>, ty: &SyntaxNode) -> Vec<TypeLifetime> {
+    ty.descendants()
+        .filter(|it| !is_in_nested_scope(it, ty))
+        .filter_map(|it| {
+            if let Some(reference) = ast::ReferenceType::cast(it.clone()) {
+                lifetime_of(reference.amp_token(), reference.lifetime_token())
+                    .map(TypeLifetime::Written)
+            } else if let Some(path_type) = ast::PathType::cast(it.clone()) {
+                hidden_lifetimes(sema, &path_type)
+            } else {
+                lifetime_of(None, ast::LifetimeArg::cast(it)?.lifetime_token())
+                    .map(TypeLifetime::Written)
+            }
+        })
+        .collect()
+}
+
+fn hidden_lifetimes(
+    sema: &Semantics<RootDatabase>,
+    path_type: &ast::PathType,
+) -> Option<TypeLifetime> {
+    let path = path_type.path()?;
+    let segment = path.segment()?;
+    let args = segment.type_arg_list();
+    if args.as_ref().map_or(false, |it| it.lifetime_args().next().is_some()) {
+        return None;
+    }
+    let params = match sema.resolve_path(&path)? {
+        PathResolution::Def(ModuleDef::Adt(Adt::Struct(it))) => {
+            it.source(sema.db).value.type_param_list()
+        }
+        PathResolution::Def(ModuleDef::Adt(Adt::Enum(it))) => {
+            it.source(sema.db).value.type_param_list()
+        }
+        PathResolution::Def(ModuleDef::Adt(Adt::Union(it))) => {
+            it.source(sema.db).value.type_param_list()
+        }
+        PathResolution::Def(ModuleDef::TypeAlias(it)) => it.source(sema.db).value.type_param_list(),
+        _ => None,
+    }?;
+    let count = params.lifetime_params().count();
+    if count == 0 {
+        return None;
+    }
+    let offset = match &args {
+        Some(it) => it.l_angle_token()?.text_range().end(),
+        None => segment.syntax().text_range().end(),
+    };
+    Some(TypeLifetime::Hidden { offset, count, has_args: args.is_some() })
+}
+
+/// Gives the elided lifetimes of a parameter type fresh names, and returns
+/// the names of all of its lifetimes.
+fn resolve_inputs(
+    sema: &Semantics<RootDatabase>,
+    ty: &SyntaxNode,
+    fresh: &mut FreshLifetimes,
+    edits: &mut Vec<(TextRange, String)>,
+) -> Vec<String> {
+    let mut names = Vec::new();
+    for lifetime in type_lifetimes(sema, ty) {
+        match lifetime {
+            TypeLifetime::Written(it) => names.push(fresh.resolve(it, edits)),
+            TypeLifetime::Hidden { offset, count, has_args } => {
+                let hidden: Vec<String> = (0..count).map(|_| fresh.next()).collect();
+                edits.push(hidden_lifetimes_edit(offset, &hidden, has_args));
+                names.extend(hidden);
+            }
+        }
+    }
+    names
+}
+
+fn hidden_lifetimes_edit(
+    offset: TextUnit,
+    names: &[String],
+    has_args: bool,
+) -> (TextRange, String) {
+    let text = if has_args {
+        format!("{}, ", names.join(", "))
+    } else {
+        format!("<{}>", names.join(", "))
+    };
+    (TextRange::offset_len(offset, 0.into()), text)
+}
+
+fn lifetime_text(range: TextRange, name: &str) -> String {
+    if range.is_empty() {
+        format!("{} ", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn apply(node: &SyntaxNode, edits: Vec<(TextRange, String)>) -> String {
+    let start = node.text_range().start();
+    let mut builder = TextEditBuilder::default();
+    for (range, text) in edits {
+        builder.replace(TextRange::from_to(range.start() - start, range.end() - start), text);
+    }
+    builder.finish().apply(&node.text().to_string())
+}
+
+/// Names lifetimes `'a`, `'b`, ..., skipping the ones which are declared by
+/// the function or by its impl or trait.
+struct FreshLifetimes {
+    declared: Vec<String>,
+    introduced: Vec<String>,
+}
+
+impl FreshLifetimes {
+    fn new(node: &ast::FnDef) -> FreshLifetimes {
+        FreshLifetimes { declared: declared_lifetimes(node), introduced: Vec::new() }
+    }
+
+    fn resolve(&mut self, lifetime: Lifetime, edits: &mut Vec<(TextRange, String)>) -> String {
+        match lifetime {
+            Lifetime::Named(name) => name,
+            Lifetime::Elided(range) => {
+                let name = self.next();
+                edits.push((range, lifetime_text(range, &name)));
+                name
+            }
+        }
+    }
+
+    fn next(&mut self) -> String {
+        let taken: Vec<String> = self.declared.iter().chain(&self.introduced).cloned().collect();
+        let name = fresh_lifetime(&taken);
+        self.introduced.push(name.clone());
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hir::Semantics;
+    use ra_db::fixture::WithFixture;
+    use ra_ide_db::RootDatabase;
+    use ra_syntax::{ast, AstNode};
+
+    use super::explicit_lifetimes;
+    use crate::FunctionSignature;
+
+    fn check(text: &str, expected: &str) {
+        let (db, file_id) = RootDatabase::with_single_file(text);
+        let sema = Semantics::new(&db);
+        let file = sema.parse(file_id);
+        let fn_def = file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        let signature = FunctionSignature::from(&fn_def).with_elided_lifetimes(&sema, &fn_def);
+        assert_eq!(signature.to_string(), expected);
+    }
+
+    #[test]
+    fn method_returns_lifetime_of_self() {
+        check(
+            "impl S { fn get(&self, key: &str) -> &str {} }",
+            "fn get<'a, 'b>(&'a self, key: &'b str) -> &'a str",
+        );
+    }
+
+    #[test]
+    fn single_input_lifetime_is_used_for_output() {
+        check("fn first(s: &mut String) -> &str {}", "fn first<'a>(s: &'a mut String) -> &'a str");
+        check("fn first<'x>(s: &'x str) -> Iter<'_> {}", "fn first<'x>(s: &'x str) -> Iter<'x>");
+    }
+
+    #[test]
+    fn ambiguous_output_is_left_alone() {
+        check(
+            "fn longest(a: &str, b: &str) -> &str {}",
+            "fn longest<'a, 'b>(a: &'a str, b: &'b str) -> &str",
+        );
+    }
+
+    #[test]
+    fn skips_declared_lifetimes() {
+        check(
+            "impl<'a> S<'a> { fn f<T>(&self, t: T) -> &T {} }",
+            "fn f<'b, T>(&'b self, t: T) -> &'b T",
+        );
+    }
+
+    #[test]
+    fn ignores_fn_pointers_and_fn_traits() {
+        check(
+            "fn call(f: fn(&str) -> &str, g: &dyn Fn(&u8) -> &u8) {}",
+            "fn call<'a>(f: fn(&str) -> &str, g: &'a dyn Fn(&u8) -> &u8)",
+        );
+    }
+
+    #[test]
+    fn spells_out_lifetimes_of_paths() {
+        check(
+            "fn new(input: &str) -> Parser {}\nstruct Parser<'a>(&'a str);",
+            "fn new<'a>(input: &'a str) -> Parser<'a>",
+        );
+        check(
+            "impl S { fn fmt(&self, f: &mut Formatter) -> Result {} }\nstruct Formatter<'a>(&'a str);",
+            "fn fmt<'a, 'b, 'c>(&'a self, f: &'b mut Formatter<'c>) -> Result",
+        );
+        check(
+            "fn get(w: Wrapper) -> &str {}\nstruct Wrapper<'a>(&'a str);",
+            "fn get<'a>(w: Wrapper<'a>) -> &'a str",
+        );
+        check(
+            "fn f(r: Ref<u8>) -> Ref<u8> {}\ntype Ref<'a, T> = &'a T;",
+            "fn f<'a>(r: Ref<'a, u8>) -> Ref<'a, u8>",
+        );
+    }
+
+    #[test]
+    fn nothing_elided() {
+        let (db, file_id) =
+            RootDatabase::with_single_file("fn f<'a>(x: &'a str, n: u32) -> &'a str {}");
+        let sema = Semantics::new(&db);
+        let file = sema.parse(file_id);
+        let fn_def = file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        assert!(explicit_lifetimes(&sema, &fn_def).is_none());
+    }
+}
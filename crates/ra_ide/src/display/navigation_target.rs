@@ -1,7 +1,9 @@
 //! FIXME: write short doc here
 
 use either::Either;
-use hir::{original_range, AssocItem, FieldSource, HasSource, InFile, ModuleSource};
+use hir::{
+    original_range, original_token, AssocItem, FieldSource, HasSource, InFile, ModuleSource, Origin,
+};
 use ra_db::{FileId, SourceDatabase};
 use ra_ide_db::{defs::Definition, RootDatabase};
 use ra_syntax::{
@@ -133,6 +135,31 @@ impl NavigationTarget {
     ) -> NavigationTarget {
         //FIXME: use `_` instead of empty string
         let name = node.value.name().map(|it| it.text().clone()).unwrap_or_default();
+
+        // Items generated by a macro whose name is spelled out in the macro
+        // definition, not in the call, navigate to the definition.
+        let name_token = node.value.name().and_then(|it| it.syntax().first_token());
+        if let Some((token, Origin::Def)) =
+            name_token.and_then(|it| original_token(db, node.with_value(it)))
+        {
+            let focus_range = token.value.text_range();
+            let full_range = token
+                .value
+                .parent()
+                .ancestors()
+                .find_map(ast::MacroCall::cast)
+                .map_or(focus_range, |it| it.syntax().text_range());
+            return NavigationTarget::from_syntax(
+                token.file_id.original_file(db),
+                name,
+                Some(focus_range),
+                full_range,
+                node.value.syntax().kind(),
+                docs,
+                description,
+            );
+        }
+
         let focus_range =
             node.value.name().map(|it| original_range(db, node.with_value(it.syntax())).range);
         let frange = original_range(db, node.map(|it| it.syntax()));
@@ -148,7 +175,7 @@ impl NavigationTarget {
         )
     }
 
-    fn from_syntax(
+    pub(crate) fn from_syntax(
         file_id: FileId,
         name: SmolStr,
         focus_range: Option<TextRange>,
@@ -1,4 +1,5 @@
-//! FIXME: write short doc here
+//! Computes the outline of a file: a tree of the items it defines, used for
+//! document symbols.
 
 use crate::TextRange;
 
@@ -103,7 +104,14 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
     match_ast! {
         match node {
             ast::FnDef(it) => {
-                let mut detail = String::from("fn");
+                let mut detail = String::new();
+                for qualifier in [it.const_token(), it.async_token(), it.unsafe_token()].iter() {
+                    if let Some(qualifier) = qualifier {
+                        detail.push_str(qualifier.text());
+                        detail.push(' ');
+                    }
+                }
+                detail.push_str("fn");
                 if let Some(type_param_list) = it.type_param_list() {
                     collapse_ws(type_param_list.syntax(), &mut detail);
                 }
@@ -119,7 +127,17 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
             },
             ast::StructDef(it) => decl(it),
             ast::EnumDef(it) => decl(it),
-            ast::EnumVariant(it) => decl(it),
+            ast::EnumVariant(it) => {
+                let detail = match it.field_def_list() {
+                    Some(ast::FieldDefList::TupleFieldDefList(fields)) => {
+                        let mut detail = String::new();
+                        collapse_ws(fields.syntax(), &mut detail);
+                        Some(detail)
+                    }
+                    _ => None,
+                };
+                decl_with_detail(it, detail)
+            },
             ast::TraitDef(it) => decl(it),
             ast::Module(it) => decl(it),
             ast::TypeAliasDef(it) => {
@@ -319,7 +337,9 @@ fn very_obsolete() {}
                 navigation_range: [172; 173),
                 node_range: [172; 178),
                 kind: ENUM_VARIANT,
-                detail: None,
+                detail: Some(
+                    "(i32)",
+                ),
                 deprecated: false,
             },
             StructureNode {
@@ -426,4 +446,46 @@ fn very_obsolete() {}
         "###
                 );
     }
+
+    #[test]
+    fn test_file_structure_impl_methods() {
+        let file = SourceFile::parse(
+            r#"
+impl S {
+    unsafe fn f(&self) {}
+}
+"#,
+        )
+        .ok()
+        .unwrap();
+        let structure = file_structure(&file);
+        assert_debug_snapshot!(structure,
+        @r###"
+        [
+            StructureNode {
+                parent: None,
+                label: "impl S",
+                navigation_range: [6; 7),
+                node_range: [1; 37),
+                kind: IMPL_DEF,
+                detail: None,
+                deprecated: false,
+            },
+            StructureNode {
+                parent: Some(
+                    0,
+                ),
+                label: "f",
+                navigation_range: [24; 25),
+                node_range: [14; 35),
+                kind: FN_DEF,
+                detail: Some(
+                    "unsafe fn(&self)",
+                ),
+                deprecated: false,
+            },
+        ]
+        "###
+        );
+    }
 }
@@ -6,7 +6,7 @@ use hir::Semantics;
 use ra_ide_db::RootDatabase;
 use ra_syntax::{
     algo::{self, find_covering_element, skip_trivia_token},
-    ast::{self, AstNode, AstToken},
+    ast::{self, AstNode, AstToken, HasQuotes},
     Direction, NodeOrToken,
     SyntaxKind::{self, *},
     SyntaxNode, SyntaxToken, TextRange, TextUnit, TokenAtOffset, T,
@@ -71,6 +71,12 @@ fn try_extend_selection(
     let node = match find_covering_element(root, range) {
         NodeOrToken::Token(token) => {
             if token.text_range() != range {
+                // Select the contents of a string before the whole literal
+                if let Some(contents) = string_contents(&token) {
+                    if range.is_subrange(&contents) && range != contents {
+                        return Some(contents);
+                    }
+                }
                 return Some(token.text_range());
             }
             if let Some(comment) = ast::Comment::cast(token.clone()) {
@@ -107,6 +113,30 @@ fn try_extend_selection(
     node.parent().map(|it| it.text_range())
 }
 
+/// Returns the range which the selection shrinks to: the largest range which
+/// `extend_selection` reaches from the start of the selection before reaching
+/// the selection itself.
+pub(crate) fn shrink_selection(db: &RootDatabase, frange: FileRange) -> TextRange {
+    let sema = Semantics::new(db);
+    let src = sema.parse(frange.file_id);
+    let mut curr = TextRange::offset_len(frange.range.start(), 0.into());
+    loop {
+        let next = try_extend_selection(&sema, src.syntax(), FileRange { range: curr, ..frange })
+            .unwrap_or(curr);
+        if next == curr || next == frange.range || !next.is_subrange(&frange.range) {
+            return curr;
+        }
+        curr = next;
+    }
+}
+
+fn string_contents(token: &SyntaxToken) -> Option<TextRange> {
+    if let Some(string) = ast::String::cast(token.clone()) {
+        return string.text_range_between_quotes();
+    }
+    ast::RawString::cast(token.clone())?.text_range_between_quotes()
+}
+
 fn extend_tokens_from_range(
     sema: &Semantics<RootDatabase>,
     macro_call: ast::MacroCall,
@@ -313,7 +343,7 @@ fn adj_comments(comment: &ast::Comment, dir: Direction) -> ast::Comment {
 
 #[cfg(test)]
 mod tests {
-    use test_utils::extract_offset;
+    use test_utils::{extract_offset, extract_range};
 
     use crate::mock_analysis::single_file;
 
@@ -488,10 +518,37 @@ fn bar(){}
 
 " fn f<|>oo() {"
 "#,
-            &["foo", "\" fn foo() {\""],
+            &["foo", " fn foo() {", "\" fn foo() {\""],
         );
     }
 
+    #[test]
+    fn test_extend_selection_string_contents() {
+        do_check(
+            r#"fn main() { let s = "hello <|>world"; }"#,
+            &["world", "hello world", "\"hello world\""],
+        );
+        do_check(
+            r##"fn main() { let s = r#"hello <|>world"#; }"##,
+            &["world", "hello world", "r#\"hello world\"#"],
+        );
+    }
+
+    fn do_check_shrink(before: &str, after: &str) {
+        let (range, before) = extract_range(before);
+        let (analysis, file_id) = single_file(&before);
+        let range = analysis.shrink_selection(FileRange { file_id, range }).unwrap();
+        assert_eq!(after, &before[range]);
+    }
+
+    #[test]
+    fn test_shrink_selection() {
+        do_check_shrink(r#"fn foo() { <|>1 + 1<|> }"#, "1");
+        do_check_shrink(r#"fn foo(<|>x: i32, <|>y: i32) {}"#, "x: i32");
+        do_check_shrink(r#"fn foo() { <|>bar(1, 2)<|> }"#, "bar");
+        do_check_shrink(r#"fn foo() { <|>1<|> + 1 }"#, "");
+    }
+
     #[test]
     fn test_extend_trait_bounds_list_in_where_clause() {
         do_check(
@@ -1,4 +1,4 @@
-//! FIXME: write short doc here
+//! Computes the ranges which can be folded in the editor.
 
 use rustc_hash::FxHashSet;
 
@@ -6,7 +6,7 @@ use ra_syntax::{
     ast::{self, AstNode, AstToken, VisibilityOwner},
     Direction, NodeOrToken, SourceFile,
     SyntaxKind::{self, *},
-    SyntaxNode, TextRange,
+    SyntaxNode, TextRange, TextUnit,
 };
 
 #[derive(Debug, PartialEq, Eq)]
@@ -15,6 +15,8 @@ pub enum FoldKind {
     Imports,
     Mods,
     Block,
+    ArgList,
+    Region,
 }
 
 #[derive(Debug)]
@@ -28,6 +30,8 @@ pub(crate) fn folding_ranges(file: &SourceFile) -> Vec<Fold> {
     let mut visited_comments = FxHashSet::default();
     let mut visited_imports = FxHashSet::default();
     let mut visited_mods = FxHashSet::default();
+    // regions can be nested, here is a LIFO buffer
+    let mut regions_starts: Vec<TextUnit> = vec![];
 
     for element in file.syntax().descendants_with_tokens() {
         // Fold items that span multiple lines
@@ -46,7 +50,19 @@ pub(crate) fn folding_ranges(file: &SourceFile) -> Vec<Fold> {
             NodeOrToken::Token(token) => {
                 // Fold groups of comments
                 if let Some(comment) = ast::Comment::cast(token) {
-                    if !visited_comments.contains(&comment) {
+                    if is_region_start(&comment) {
+                        regions_starts.push(comment.syntax().text_range().start());
+                    } else if is_region_end(&comment) {
+                        if let Some(start) = regions_starts.pop() {
+                            res.push(Fold {
+                                range: TextRange::from_to(
+                                    start,
+                                    comment.syntax().text_range().end(),
+                                ),
+                                kind: FoldKind::Region,
+                            })
+                        }
+                    } else if !visited_comments.contains(&comment) {
                         if let Some(range) =
                             contiguous_range_for_comment(comment, &mut visited_comments)
                         {
@@ -76,6 +92,8 @@ pub(crate) fn folding_ranges(file: &SourceFile) -> Vec<Fold> {
         }
     }
 
+    // Regions are only known once their end is reached
+    res.sort_by_key(|fold| (fold.range.start(), fold.range.end()));
     res
 }
 
@@ -92,10 +110,20 @@ fn fold_kind(kind: SyntaxKind) -> Option<FoldKind> {
         | MATCH_ARM_LIST
         | ENUM_VARIANT_LIST
         | TOKEN_TREE => Some(FoldKind::Block),
+        ARG_LIST | PARAM_LIST => Some(FoldKind::ArgList),
         _ => None,
     }
 }
 
+/// Regions are delimited by `// region: name` and `// endregion: name` comments.
+fn is_region_start(comment: &ast::Comment) -> bool {
+    comment.text().starts_with("// region:")
+}
+
+fn is_region_end(comment: &ast::Comment) -> bool {
+    comment.text().starts_with("// endregion")
+}
+
 fn has_visibility(node: &SyntaxNode) -> bool {
     ast::Module::cast(node.clone()).and_then(|m| m.visibility()).is_some()
 }
@@ -171,7 +199,7 @@ fn contiguous_range_for_comment(
                     }
                 }
                 if let Some(c) = ast::Comment::cast(token) {
-                    if c.kind() == group_kind {
+                    if c.kind() == group_kind && !is_region_start(&c) && !is_region_end(&c) {
                         visited.insert(c.clone());
                         last = c;
                         continue;
@@ -362,6 +390,42 @@ macro_rules! foo <fold>{
         do_check(text, folds);
     }
 
+    #[test]
+    fn test_fold_arg_lists() {
+        let text = r#"
+fn foo<fold>(
+    a: i32,
+    b: i32,
+)</fold> <fold>{
+    bar<fold>(
+        a,
+        b,
+    )</fold>;
+    bar(a, b);
+}</fold>"#;
+
+        let folds = &[FoldKind::ArgList, FoldKind::Block, FoldKind::ArgList];
+        do_check(text, folds);
+    }
+
+    #[test]
+    fn test_fold_regions() {
+        let text = r#"
+<fold>// region: outer
+fn main() <fold>{
+    <fold>// region: inner
+    foo();
+    // endregion: inner</fold>
+}</fold>
+// endregion: outer</fold>
+
+// region: unclosed
+fn foo() {}"#;
+
+        let folds = &[FoldKind::Region, FoldKind::Block, FoldKind::Region];
+        do_check(text, folds);
+    }
+
     #[test]
     fn test_fold_match_arms() {
         let text = r#"
@@ -0,0 +1,617 @@
+//! Analysis of format strings in `format!`-like macro calls.
+//!
+//! The macros themselves are builtin, so their expansion doesn't tell us
+//! which argument a `{}` in the string refers to. Instead, we parse the
+//! format string and the macro arguments syntactically and match them up.
+
+use ra_syntax::{
+    ast, AstNode, SmolStr, SyntaxElement,
+    SyntaxKind::{IDENT, RAW_STRING, STRING},
+    SyntaxToken, TextRange, TextUnit, T,
+};
+
+const FORMAT_MACROS: &[&str] = &[
+    "format",
+    "format_args",
+    "print",
+    "println",
+    "eprint",
+    "eprintln",
+    "write",
+    "writeln",
+    "panic",
+    "unreachable",
+    "todo",
+    "unimplemented",
+];
+
+/// Macros which take a single argument as it is, rather than as a format
+/// string, like `panic!("{}")`.
+const MESSAGE_MACROS: &[&str] = &["panic", "unreachable", "todo", "unimplemented"];
+
+/// A `format!`-like macro call, split into placeholders and arguments.
+pub(crate) struct FormatCall {
+    pub(crate) placeholders: Vec<Placeholder>,
+    pub(crate) args: Vec<FormatArg>,
+}
+
+/// A reference to an argument inside of the format string.
+#[derive(Debug)]
+pub(crate) struct Placeholder {
+    /// The argument name or index, or the whole `{}` for implicit references.
+    pub(crate) range: TextRange,
+    pub(crate) arg: ArgRef,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ArgRef {
+    Index(usize),
+    Name(SmolStr),
+}
+
+/// An argument after the format string, like `x` or `name = x`.
+pub(crate) struct FormatArg {
+    pub(crate) name: Option<SyntaxToken>,
+    /// The tokens of the value, without the `name =` part.
+    pub(crate) value: Vec<SyntaxElement>,
+}
+
+impl FormatArg {
+    pub(crate) fn value_range(&self) -> TextRange {
+        let first = self.value.first().unwrap().text_range();
+        let last = self.value.last().unwrap().text_range();
+        first.extend_to(&last)
+    }
+
+    pub(crate) fn value_text(&self) -> String {
+        let tt = self.value[0].parent().unwrap();
+        let range = self.value_range() - tt.text_range().start();
+        tt.text().slice(range).to_string()
+    }
+
+    pub(crate) fn full_range(&self) -> TextRange {
+        match &self.name {
+            Some(name) => name.text_range().extend_to(&self.value_range()),
+            None => self.value_range(),
+        }
+    }
+}
+
+impl FormatCall {
+    /// Parses the macro call `token` belongs to, if `token` is either the
+    /// format string or one of the argument names.
+    pub(crate) fn from_token(token: &SyntaxToken) -> Option<FormatCall> {
+        let tt = ast::TokenTree::cast(token.parent())?;
+        let mut elements = format_macro_elements(&tt)?;
+        let string = format_string(&mut elements)?;
+        let placeholders = parse_placeholders(&string)?;
+
+        let mut rest: Vec<SyntaxElement> = elements.collect();
+        // Drop the closing delimiter of the token tree.
+        rest.pop();
+        let args = rest
+            .split(|it| it.kind() == T![,])
+            .filter(|it| !it.is_empty())
+            .map(|arg| {
+                // `a == b` is a positional argument, not a named one.
+                let is_named = arg.len() > 2
+                    && arg[0].kind() == IDENT
+                    && arg[1].kind() == T![=]
+                    && arg[2].kind() != T![=];
+                if is_named {
+                    FormatArg { name: arg[0].clone().into_token(), value: arg[2..].to_vec() }
+                } else {
+                    FormatArg { name: None, value: arg.to_vec() }
+                }
+            })
+            .collect();
+        let res = FormatCall { placeholders, args };
+
+        let is_format_string = token == &string;
+        let is_arg_name = res.args.iter().any(|it| it.name.as_ref() == Some(token));
+        if is_format_string || is_arg_name {
+            Some(res)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn placeholder_at(&self, offset: TextUnit) -> Option<&Placeholder> {
+        self.placeholders.iter().find(|it| it.range.start() <= offset && offset <= it.range.end())
+    }
+
+    /// Finds the placeholder at `offset` together with the argument it refers to.
+    pub(crate) fn resolve_at(&self, offset: TextUnit) -> Option<(&Placeholder, &FormatArg)> {
+        let placeholder = self.placeholder_at(offset)?;
+        let arg = self.resolve(&placeholder.arg)?;
+        Some((placeholder, arg))
+    }
+
+    pub(crate) fn resolve(&self, arg: &ArgRef) -> Option<&FormatArg> {
+        match arg {
+            ArgRef::Index(idx) => self.args.get(*idx),
+            ArgRef::Name(name) => {
+                self.args.iter().find(|it| it.name.as_ref().map_or(false, |it| it.text() == name))
+            }
+        }
+    }
+}
+
+/// Whether `token` is the format string of a `format!`-like macro call.
+pub(crate) fn is_format_string(token: &SyntaxToken) -> bool {
+    let tt = match ast::TokenTree::cast(token.parent()) {
+        Some(it) => it,
+        None => return false,
+    };
+    format_macro_elements(&tt).and_then(|mut it| format_string(&mut it)).as_ref() == Some(token)
+}
+
+/// The non-trivia elements of `tt`, if it's the input of a format macro.
+fn format_macro_elements(tt: &ast::TokenTree) -> Option<impl Iterator<Item = SyntaxElement>> {
+    let macro_call = ast::MacroCall::cast(tt.syntax().parent()?)?;
+    let macro_name = macro_call.path()?.segment()?.name_ref()?;
+    let macro_name = macro_name.text().as_str();
+    if !FORMAT_MACROS.contains(&macro_name) {
+        return None;
+    }
+    let elements = || tt.syntax().children_with_tokens().filter(|it| !it.kind().is_trivia());
+    if MESSAGE_MACROS.contains(&macro_name) {
+        // The delimiters, the message and an optional trailing comma
+        let kinds: Vec<_> = elements().map(|it| it.kind()).collect();
+        match kinds.as_slice() {
+            [_, _, _] | [_, _, T![,], _] => return None,
+            _ => (),
+        }
+    }
+    Some(elements())
+}
+
+fn format_string(elements: &mut impl Iterator<Item = SyntaxElement>) -> Option<SyntaxToken> {
+    // `write!` and `writeln!` take the destination first, so the format
+    // string is the first string literal rather than the first token.
+    elements
+        .filter_map(|it| it.into_token())
+        .find(|it| it.kind() == STRING || it.kind() == RAW_STRING)
+}
+
+/// The content of a string literal, with the offset it starts at.
+fn string_content(string: &SyntaxToken) -> Option<(TextUnit, &str)> {
+    let text = string.text();
+    let content_start = text.find('"')? + 1;
+    let content_end = text.rfind('"')?;
+    if content_end < content_start {
+        return None;
+    }
+    let base = string.text_range().start() + TextUnit::from_usize(content_start);
+    Some((base, &text[content_start..content_end]))
+}
+
+/// Finds all `{name}`, `{0}` and `{}` placeholders in a format string.
+///
+/// FIXME: widths and precisions like `{:width$}` and `{:.*}` refer to
+/// arguments as well, but are not handled yet.
+fn parse_placeholders(string: &SyntaxToken) -> Option<Vec<Placeholder>> {
+    let is_raw = string.kind() == RAW_STRING;
+    let (base, content) = string_content(string)?;
+    let range = |start: usize, end: usize| {
+        TextRange::from_to(base + TextUnit::from_usize(start), base + TextUnit::from_usize(end))
+    };
+
+    let mut res = Vec::new();
+    let mut next_implicit = 0;
+    let mut chars = content.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' if !is_raw => {
+                // `\u{..}` escapes contain braces, which are not placeholders.
+                if let Some((_, 'u')) = chars.next() {
+                    while let Some((_, c)) = chars.next() {
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                }
+            }
+            '{' | '}' if chars.peek().map(|&(_, it)| it) == Some(c) => {
+                chars.next();
+            }
+            '{' => {
+                let start = idx + 1;
+                let mut end = start;
+                while let Some(&(idx, c)) = chars.peek() {
+                    if c == ':' || c == '}' {
+                        break;
+                    }
+                    end = idx + c.len_utf8();
+                    chars.next();
+                }
+                let mut close = end;
+                while let Some((idx, c)) = chars.next() {
+                    if c == '}' {
+                        close = idx + 1;
+                        break;
+                    }
+                }
+                let arg = content[start..end].trim();
+                let placeholder = if arg.is_empty() {
+                    next_implicit += 1;
+                    Placeholder { range: range(idx, close), arg: ArgRef::Index(next_implicit - 1) }
+                } else if let Ok(index) = arg.parse::<usize>() {
+                    Placeholder { range: range(start, end), arg: ArgRef::Index(index) }
+                } else {
+                    Placeholder { range: range(start, end), arg: ArgRef::Name(arg.into()) }
+                };
+                res.push(placeholder);
+            }
+            _ => (),
+        }
+    }
+    Some(res)
+}
+
+/// A part of a format string which is not plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FormatPart {
+    /// The braces of a placeholder and the `:`, fill, alignment, flags, `$`,
+    /// `.`, `*` and trait of its spec, like `:>+#?` in `{:>+#?}`.
+    Specifier,
+    /// An argument index, width or precision, like `0` and `8` in `{0:8}`.
+    Integer,
+    /// An argument name, like `name` and `width` in `{name:width$}`.
+    Identifier,
+    /// An escape sequence, like `\n`, `\u{1F600}` or `{{`.
+    Escape,
+}
+
+/// The highlightable parts of a format string, and the errors in it, both in
+/// source order.
+#[derive(Debug, Default)]
+pub(crate) struct FormatString {
+    pub(crate) parts: Vec<(TextRange, FormatPart)>,
+    pub(crate) errors: Vec<(TextRange, String)>,
+}
+
+/// Splits a format string into parts, following the grammar of `std::fmt`:
+///
+/// ```text
+/// format_spec := [[fill]align][sign]['#']['0'][width]['.' precision][type]
+/// ```
+pub(crate) fn lex_format_string(string: &SyntaxToken) -> Option<FormatString> {
+    let (base, content) = string_content(string)?;
+    let mut lexer = FormatLexer {
+        content,
+        chars: content.char_indices().collect(),
+        pos: 0,
+        base,
+        is_raw: string.kind() == RAW_STRING,
+        res: FormatString::default(),
+    };
+    lexer.lex();
+    Some(lexer.res)
+}
+
+const FORMAT_TRAITS: &[&str] = &["", "?", "x?", "X?", "o", "x", "X", "p", "b", "e", "E"];
+
+struct FormatLexer<'a> {
+    content: &'a str,
+    chars: Vec<(usize, char)>,
+    /// Index into `chars`.
+    pos: usize,
+    base: TextUnit,
+    is_raw: bool,
+    res: FormatString,
+}
+
+impl FormatLexer<'_> {
+    fn lex(&mut self) {
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            match c {
+                '\\' if !self.is_raw => {
+                    self.bump();
+                    match self.bump() {
+                        Some('u') => {
+                            self.eat_while(|c| c != '}');
+                            self.bump();
+                        }
+                        Some('x') => {
+                            self.bump();
+                            self.bump();
+                        }
+                        _ => (),
+                    }
+                    self.push(start, FormatPart::Escape);
+                }
+                '{' | '}' if self.nth(1) == Some(c) => {
+                    self.pos += 2;
+                    self.push(start, FormatPart::Escape);
+                }
+                '{' => self.placeholder(),
+                '}' => {
+                    self.bump();
+                    self.error(start, "unmatched `}` in format string".to_string());
+                }
+                _ => {
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    fn placeholder(&mut self) {
+        let open = self.pos;
+        self.bump();
+        self.push(open, FormatPart::Specifier);
+
+        self.argument();
+        if self.peek() == Some(':') {
+            self.specifier(|_| true);
+            self.spec();
+        }
+
+        match self.peek() {
+            Some('}') => self.specifier(|_| true),
+            Some(c) => {
+                let start = self.pos;
+                self.bump();
+                self.error(start, format!("expected `}}` in format string, found `{}`", c));
+                // Skip to the end of the placeholder.
+                self.eat_while(|c| c != '}' && c != '{');
+                if self.peek() == Some('}') {
+                    self.specifier(|_| true);
+                }
+            }
+            None => self.error(open, "unterminated placeholder in format string".to_string()),
+        }
+    }
+
+    /// The `0` or `name` at the start of a placeholder.
+    fn argument(&mut self) {
+        let start = self.pos;
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() => {
+                self.eat_while(|c| c.is_ascii_digit());
+                self.push(start, FormatPart::Integer);
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                self.eat_while(|c| c.is_alphanumeric() || c == '_');
+                self.push(start, FormatPart::Identifier);
+            }
+            _ => (),
+        }
+    }
+
+    /// Everything after the `:` of a placeholder.
+    fn spec(&mut self) {
+        let is_align = |c: Option<char>| c == Some('<') || c == Some('^') || c == Some('>');
+        if is_align(self.nth(1)) && self.peek() != Some('}') {
+            self.specifier(|_| true);
+            self.specifier(|_| true);
+        } else if is_align(self.peek()) {
+            self.specifier(|_| true);
+        }
+        self.specifier(|c| c == '+' || c == '-');
+        self.specifier(|c| c == '#');
+        if self.peek() == Some('0') && self.nth(1) != Some('$') {
+            self.specifier(|c| c == '0');
+        }
+        self.count();
+        if self.peek() == Some('.') {
+            let dot = self.pos;
+            self.specifier(|_| true);
+            if self.peek() == Some('*') {
+                self.specifier(|_| true);
+            } else if !self.count() {
+                self.error(dot, "expected a precision after `.` in format string".to_string());
+            }
+        }
+
+        let start = self.pos;
+        self.eat_while(|c| c.is_alphanumeric() || c == '_' || c == '?');
+        let trait_ = self.text(start);
+        if !trait_.is_empty() {
+            if FORMAT_TRAITS.contains(&trait_) {
+                self.push(start, FormatPart::Specifier);
+            } else {
+                let message = format!("unknown format trait `{}`", trait_);
+                self.error(start, message);
+            }
+        }
+    }
+
+    /// A width or precision, like `8`, `1$` or `width$`. Returns whether there
+    /// was one.
+    fn count(&mut self) -> bool {
+        let start = self.pos;
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() => {
+                self.eat_while(|c| c.is_ascii_digit());
+                self.push(start, FormatPart::Integer);
+                self.specifier(|c| c == '$');
+                true
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                self.eat_while(|c| c.is_alphanumeric() || c == '_');
+                if self.peek() == Some('$') {
+                    self.push(start, FormatPart::Identifier);
+                    self.specifier(|_| true);
+                    true
+                } else {
+                    // Not a count, but the format trait.
+                    self.pos = start;
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Eats the next char as a `FormatPart::Specifier` if it matches `pred`.
+    fn specifier(&mut self, pred: impl Fn(char) -> bool) {
+        let start = self.pos;
+        if self.peek().map_or(false, pred) {
+            self.bump();
+            self.push(start, FormatPart::Specifier);
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.nth(0)
+    }
+
+    fn nth(&self, n: usize) -> Option<char> {
+        self.chars.get(self.pos + n).map(|&(_, c)| c)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat_while(&mut self, pred: impl Fn(char) -> bool) {
+        while self.peek().map_or(false, &pred) {
+            self.pos += 1;
+        }
+    }
+
+    /// The byte offset in the content of the char at `pos`.
+    fn offset(&self, pos: usize) -> usize {
+        self.chars.get(pos).map_or(self.content.len(), |&(idx, _)| idx)
+    }
+
+    fn text(&self, start: usize) -> &str {
+        &self.content[self.offset(start)..self.offset(self.pos)]
+    }
+
+    fn range(&self, start: usize, end: usize) -> TextRange {
+        TextRange::from_to(
+            self.base + TextUnit::from_usize(self.offset(start)),
+            self.base + TextUnit::from_usize(self.offset(end)),
+        )
+    }
+
+    fn push(&mut self, start: usize, part: FormatPart) {
+        let range = self.range(start, self.pos);
+        self.res.parts.push((range, part));
+    }
+
+    fn error(&mut self, start: usize, message: String) {
+        let range = self.range(start, self.pos);
+        self.res.errors.push((range, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::{AstNode, SourceFile, SyntaxKind::STRING, SyntaxToken};
+
+    use super::{lex_format_string, ArgRef, FormatCall, FormatPart, FormatString};
+
+    fn format_string(text: &str) -> SyntaxToken {
+        let file = SourceFile::parse(text).ok().unwrap();
+        file.syntax()
+            .descendants_with_tokens()
+            .filter_map(|it| it.into_token())
+            .find(|it| it.kind() == STRING)
+            .unwrap()
+    }
+
+    fn format_call(text: &str) -> Option<FormatCall> {
+        FormatCall::from_token(&format_string(text))
+    }
+
+    fn lex(text: &str) -> FormatString {
+        lex_format_string(&format_string(text)).unwrap()
+    }
+
+    fn check(text: &str, expected: &[(&str, ArgRef)]) {
+        let call = format_call(text).unwrap();
+        let actual: Vec<(&str, &ArgRef)> =
+            call.placeholders.iter().map(|it| (&text[it.range], &it.arg)).collect();
+        let expected: Vec<(&str, &ArgRef)> = expected.iter().map(|(s, a)| (*s, a)).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parses_placeholders() {
+        check(
+            r#"fn f() { println!("{} {0:?} {{}} {name:>8} \u{1F600} {}", a, b, name = c); }"#,
+            &[
+                ("{}", ArgRef::Index(0)),
+                ("0", ArgRef::Index(0)),
+                ("name", ArgRef::Name("name".into())),
+                ("{}", ArgRef::Index(1)),
+            ],
+        );
+    }
+
+    #[test]
+    fn splits_arguments() {
+        let call = format_call(r#"fn f() { write!(f, "{}", a == b, x = (1, 2)); }"#).unwrap();
+        let args: Vec<(Option<String>, String)> = call
+            .args
+            .iter()
+            .map(|it| (it.name.as_ref().map(|it| it.text().to_string()), it.value_text()))
+            .collect();
+        assert_eq!(
+            args,
+            vec![(None, "a == b".to_string()), (Some("x".to_string()), "(1, 2)".to_string())]
+        );
+    }
+
+    #[test]
+    fn ignores_non_format_macros() {
+        assert!(format_call(r#"fn f() { foo!("{}", a); }"#).is_none());
+    }
+
+    #[test]
+    fn lexes_format_specs() {
+        use FormatPart::*;
+
+        let text =
+            r#"fn f() { println!("{0:>8.3} {name:width$?} \u{1F600}\n {{}}", a, name = b); }"#;
+        let parts: Vec<(&str, FormatPart)> =
+            lex(text).parts.iter().map(|(range, part)| (&text[*range], *part)).collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("{", Specifier),
+                ("0", Integer),
+                (":", Specifier),
+                (">", Specifier),
+                ("8", Integer),
+                (".", Specifier),
+                ("3", Integer),
+                ("}", Specifier),
+                ("{", Specifier),
+                ("name", Identifier),
+                (":", Specifier),
+                ("width", Identifier),
+                ("$", Specifier),
+                ("?", Specifier),
+                ("}", Specifier),
+                ("\\u{1F600}", Escape),
+                ("\\n", Escape),
+                ("{{", Escape),
+                ("}}", Escape),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_malformed_format_specs() {
+        let text = r#"fn f() { println!("{:z} {:.} } {0", a); }"#;
+        let errors: Vec<(&str, &str)> =
+            lex(text).errors.iter().map(|(range, msg)| (&text[*range], msg.as_str())).collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("z", "unknown format trait `z`"),
+                (".", "expected a precision after `.` in format string"),
+                ("}", "unmatched `}` in format string"),
+                ("{0", "unterminated placeholder in format string"),
+            ]
+        );
+    }
+}
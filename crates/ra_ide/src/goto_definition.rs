@@ -1,6 +1,7 @@
 //! FIXME: write short doc here
 
 use hir::Semantics;
+use ra_db::SourceDatabase;
 use ra_ide_db::{
     defs::{classify_name, classify_name_ref},
     symbol_index, RootDatabase,
@@ -14,7 +15,9 @@ use ra_syntax::{
 
 use crate::{
     display::{ToNav, TryToNav},
-    FilePosition, NavigationTarget, RangeInfo,
+    format_string::FormatCall,
+    include_path::IncludePath,
+    FileId, FilePosition, NavigationTarget, RangeInfo,
 };
 
 pub(crate) fn goto_definition(
@@ -24,6 +27,13 @@ pub(crate) fn goto_definition(
     let sema = Semantics::new(db);
     let file = sema.parse(position.file_id).syntax().clone();
     let original_token = pick_best(file.token_at_offset(position.offset))?;
+    if let Some(include) = IncludePath::from_token(&original_token) {
+        let nav = included_file_nav(db, position.file_id, &include)?;
+        return Some(RangeInfo::new(include.range, vec![nav]));
+    }
+    if let Some(res) = format_arg_definition(position, &original_token) {
+        return Some(res);
+    }
     let token = sema.descend_into_macros(original_token.clone());
 
     let nav_targets = match_ast! {
@@ -43,6 +53,49 @@ pub(crate) fn goto_definition(
     Some(RangeInfo::new(original_token.text_range(), nav_targets))
 }
 
+fn included_file_nav(
+    db: &RootDatabase,
+    file_id: FileId,
+    include: &IncludePath,
+) -> Option<NavigationTarget> {
+    let included_file = include.resolve(db, file_id)?;
+    let name = include.path.rsplit('/').next().unwrap_or_default();
+    let range = db.parse(included_file).tree().syntax().text_range();
+    Some(NavigationTarget::from_syntax(
+        included_file,
+        name.into(),
+        None,
+        range,
+        SOURCE_FILE,
+        None,
+        None,
+    ))
+}
+
+/// Resolves a placeholder like `{name}` or `{0}` inside of a format string to
+/// the macro argument it refers to.
+fn format_arg_definition(
+    position: FilePosition,
+    token: &SyntaxToken,
+) -> Option<RangeInfo<Vec<NavigationTarget>>> {
+    let call = FormatCall::from_token(token)?;
+    let (placeholder, arg) = call.resolve_at(position.offset)?;
+    let (name, focus_range, kind) = match &arg.name {
+        Some(name) => (name.text().clone(), name.text_range(), name.kind()),
+        None => (arg.value_text().into(), arg.value_range(), arg.value[0].kind()),
+    };
+    let nav = NavigationTarget::from_syntax(
+        position.file_id,
+        name,
+        Some(focus_range),
+        arg.full_range(),
+        kind,
+        None,
+        None,
+    );
+    Some(RangeInfo::new(placeholder.range, vec![nav]))
+}
+
 fn pick_best(tokens: TokenAtOffset<SyntaxToken>) -> Option<SyntaxToken> {
     return tokens.max_by_key(priority);
     fn priority(n: &SyntaxToken) -> usize {
@@ -297,8 +350,91 @@ mod tests {
                <|>foo();
             }
             ",
-            "foo FN_DEF FileId(1) [51; 64) [51; 64)",
-            "define_fn!();|define_fn!();",
+            "foo FN_DEF FileId(1) [0; 50) [39; 42)",
+            "macro_rules! define_fn {\n    () => (fn foo() {})\n}|foo",
+        );
+    }
+
+    #[test]
+    fn goto_def_for_macro_defined_method() {
+        check_goto(
+            "
+            //- /lib.rs
+            macro_rules! gen_method {
+                () => { fn method(&self) {} }
+            }
+            struct S;
+            impl S { gen_method!(); }
+            fn f(s: S) { s.meth<|>od(); }
+            ",
+            "method FN_DEF FileId(1) [0; 61) [41; 47)",
+            "macro_rules! gen_method {\n    () => { fn method(&self) {} }\n}|method",
+        );
+    }
+
+    #[test]
+    fn goto_def_for_included_file() {
+        check_goto(
+            r#"
+            //- /lib.rs
+            include!("gen/<|>foo.rs");
+
+            //- /gen/foo.rs
+            fn foo() {}
+            "#,
+            "foo.rs SOURCE_FILE FileId(2) [0; 13)",
+            "fn foo() {}\n\n",
+        );
+        check_goto(
+            r#"
+            //- /src/lib.rs
+            const S: &str = include_str!(<|>"../data.rs");
+
+            //- /data.rs
+            // data
+            "#,
+            "data.rs SOURCE_FILE FileId(2) [0; 9)",
+            "// data\n\n",
+        );
+    }
+
+    #[test]
+    fn goto_def_for_named_format_arg() {
+        check_goto(
+            r#"
+            //- /lib.rs
+            fn f(x: u32) { println!("{na<|>me}", name = x); }
+            "#,
+            "name IDENT FileId(1) [34; 42) [34; 38)",
+            "name = x|name",
+        );
+    }
+
+    #[test]
+    fn goto_def_for_positional_format_args() {
+        check_goto(
+            r#"
+            //- /lib.rs
+            fn f(x: u32, y: u32) { format!("{} {1} {<|>}", x + 1, y); }
+            "#,
+            "y IDENT FileId(1) [51; 52) [51; 52)",
+            "y|y",
+        );
+        check_goto(
+            r#"
+            //- /lib.rs
+            fn f(x: u32, y: u32) { format!("{} {<|>1}", x + 1, y); }
+            "#,
+            "y IDENT FileId(1) [48; 49) [48; 49)",
+            "y|y",
+        );
+        check_goto(
+            r#"
+            //- /lib.rs
+            fn f(w: W, x: u32) { write!(w, "{<|>:?}", x + 1); }
+            "#,
+            "x + 1 IDENT FileId(1) [39; 44) [39; 44)",
+            "x + 1|x + 1",
         );
     }
 
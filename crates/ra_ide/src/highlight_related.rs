@@ -0,0 +1,312 @@
+//! Highlights the places related to the keyword under the cursor: the exit
+//! points of a function, the `break`s and `continue`s of a loop and the
+//! `.await`s of an async function or block.
+
+use ra_syntax::{
+    ast::{self, AstNode, LoopBodyOwner},
+    match_ast, SourceFile, SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextUnit, T,
+};
+
+pub fn highlight_related(file: &SourceFile, offset: TextUnit) -> Option<Vec<TextRange>> {
+    let token = file.syntax().token_at_offset(offset).find(|it| {
+        matches!(
+            it.kind(),
+            T![fn]
+                | T![return]
+                | T![?]
+                | T![loop]
+                | T![while]
+                | T![for]
+                | T![break]
+                | T![continue]
+                | T![async]
+                | T![await]
+        )
+    })?;
+    let mut res = match token.kind() {
+        T![fn] | T![return] | T![?] => exit_points(&token)?,
+        T![loop] | T![while] | T![for] | T![break] | T![continue] => break_points(&token)?,
+        _ => await_points(&token)?,
+    };
+    res.sort_by_key(|range| (range.start(), range.end()));
+    res.dedup();
+    Some(res)
+}
+
+fn exit_points(token: &SyntaxToken) -> Option<Vec<TextRange>> {
+    let parent = token.parent();
+    match token.kind() {
+        T![fn] if parent.kind() != SyntaxKind::FN_DEF => return None,
+        T![?] if parent.kind() != SyntaxKind::TRY_EXPR => return None,
+        _ => (),
+    }
+    let context = parent.ancestors().find(is_new_context)?;
+
+    let mut res = Vec::new();
+    let body = match_ast! {
+        match context {
+            ast::FnDef(it) => {
+                res.extend(it.fn_token().map(|it| it.text_range()));
+                ast::Expr::from(it.body()?)
+            },
+            ast::LambdaExpr(it) => it.body()?,
+            ast::BlockExpr(it) => {
+                res.extend(async_token(it.syntax()).map(|it| it.text_range()));
+                ast::Expr::from(it)
+            },
+            _ => return None,
+        }
+    };
+
+    walk_body(body.syntax(), &mut |node| match node.kind() {
+        SyntaxKind::RETURN_EXPR => res.extend(node.first_token().map(|it| it.text_range())),
+        SyntaxKind::TRY_EXPR => res.extend(
+            node.children_with_tokens().filter(|it| it.kind() == T![?]).map(|it| it.text_range()),
+        ),
+        _ => (),
+    });
+    tail_exprs(&body, &mut res);
+    Some(res)
+}
+
+/// Collects the expressions whose value is returned from the `expr`, looking
+/// through blocks, `if`s and `match`es.
+fn tail_exprs(expr: &ast::Expr, acc: &mut Vec<TextRange>) {
+    match expr {
+        ast::Expr::BlockExpr(it) => {
+            if let Some(expr) = it.block().and_then(|it| it.expr()) {
+                tail_exprs(&expr, acc);
+            }
+        }
+        ast::Expr::IfExpr(it) => {
+            if let Some(then_branch) = it.then_branch() {
+                tail_exprs(&then_branch.into(), acc);
+            }
+            match it.else_branch() {
+                Some(ast::ElseBranch::Block(it)) => tail_exprs(&it.into(), acc),
+                Some(ast::ElseBranch::IfExpr(it)) => tail_exprs(&it.into(), acc),
+                None => (),
+            }
+        }
+        ast::Expr::MatchExpr(it) => {
+            let arms = it.match_arm_list().into_iter().flat_map(|it| it.arms());
+            for expr in arms.filter_map(|it| it.expr()) {
+                tail_exprs(&expr, acc);
+            }
+        }
+        // `return` is already handled and a loop only exits via `break`
+        ast::Expr::ReturnExpr(_) | ast::Expr::LoopExpr(_) => (),
+        _ => acc.push(expr.syntax().text_range()),
+    }
+}
+
+fn break_points(token: &SyntaxToken) -> Option<Vec<TextRange>> {
+    let parent = token.parent();
+    let target = match token.kind() {
+        T![break] | T![continue] => target_loop(&parent)?,
+        _ if is_loop(&parent) => parent,
+        _ => return None,
+    };
+
+    let mut res = Vec::new();
+    res.extend(loop_token(&target).map(|it| it.text_range()));
+    walk_body(&target, &mut |node| {
+        if !matches!(node.kind(), SyntaxKind::BREAK_EXPR | SyntaxKind::CONTINUE_EXPR) {
+            return;
+        }
+        if target_loop(&node).as_ref() == Some(&target) {
+            res.extend(node.first_token().map(|it| it.text_range()));
+        }
+    });
+    Some(res)
+}
+
+/// Finds the loop which is the target of the `break` or `continue` expression.
+fn target_loop(expr: &SyntaxNode) -> Option<SyntaxNode> {
+    let label = match_ast! {
+        match expr {
+            ast::BreakExpr(it) => it.lifetime_token(),
+            ast::ContinueExpr(it) => it.lifetime_token(),
+            _ => return None,
+        }
+    };
+    expr.ancestors().take_while(|it| !is_new_context(it)).filter(is_loop).find(|it| match &label {
+        Some(label) => loop_label(it).map_or(false, |it| it.text() == label.text()),
+        None => true,
+    })
+}
+
+fn await_points(token: &SyntaxToken) -> Option<Vec<TextRange>> {
+    let context = token.parent().ancestors().find(is_new_context)?;
+    let async_token = match_ast! {
+        match context {
+            ast::FnDef(it) => it.async_token(),
+            ast::LambdaExpr(it) => it.async_token(),
+            ast::BlockExpr(it) => async_token(it.syntax()),
+            _ => None,
+        }
+    }?;
+
+    let mut res = vec![async_token.text_range()];
+    walk_body(&context, &mut |node| {
+        if let Some(await_token) = ast::AwaitExpr::cast(node).and_then(|it| it.await_token()) {
+            res.push(await_token.text_range());
+        }
+    });
+    Some(res)
+}
+
+/// Visits the nodes of the `body`, skipping nested functions, closures and
+/// async blocks, which have their own exit, loop and await points.
+fn walk_body(body: &SyntaxNode, cb: &mut impl FnMut(SyntaxNode)) {
+    for child in body.children() {
+        if is_new_context(&child) {
+            continue;
+        }
+        cb(child.clone());
+        walk_body(&child, cb);
+    }
+}
+
+fn is_new_context(node: &SyntaxNode) -> bool {
+    match node.kind() {
+        SyntaxKind::FN_DEF | SyntaxKind::LAMBDA_EXPR => true,
+        SyntaxKind::BLOCK_EXPR => async_token(node).is_some(),
+        _ => false,
+    }
+}
+
+fn async_token(block: &SyntaxNode) -> Option<SyntaxToken> {
+    block.children_with_tokens().filter_map(|it| it.into_token()).find(|it| it.kind() == T![async])
+}
+
+fn is_loop(node: &SyntaxNode) -> bool {
+    matches!(node.kind(), SyntaxKind::LOOP_EXPR | SyntaxKind::WHILE_EXPR | SyntaxKind::FOR_EXPR)
+}
+
+fn loop_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    match_ast! {
+        match node {
+            ast::LoopExpr(it) => it.loop_token(),
+            ast::WhileExpr(it) => it.while_token(),
+            ast::ForExpr(it) => it.for_token(),
+            _ => None,
+        }
+    }
+}
+
+fn loop_label(node: &SyntaxNode) -> Option<SyntaxToken> {
+    let label = match_ast! {
+        match node {
+            ast::LoopExpr(it) => it.label(),
+            ast::WhileExpr(it) => it.label(),
+            ast::ForExpr(it) => it.label(),
+            _ => None,
+        }
+    };
+    label?.lifetime_token()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::extract_offset;
+
+    use super::*;
+
+    fn check(before: &str, expected: &[&str]) {
+        let (offset, before) = extract_offset(before);
+        let file = SourceFile::parse(&before).tree();
+        let ranges = highlight_related(&file, offset).unwrap_or_default();
+        let actual: Vec<&str> = ranges.iter().map(|&range| &before[range]).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_highlight_exit_points() {
+        check(
+            r#"
+f<|>n foo(x: Option<u32>) -> u32 {
+    if x.is_none() {
+        return 0;
+    }
+    let y = x?;
+    let f = || { return 1; };
+    if y > 1 { y } else { 1 }
+}
+"#,
+            &["fn", "return", "?", "y", "1"],
+        );
+        check(
+            r#"
+fn foo() -> u32 {
+    let f = |x: u32| {
+        if x > 0 { ret<|>urn 1; }
+        x
+    };
+    return 2;
+}
+"#,
+            &["return", "x"],
+        );
+    }
+
+    #[test]
+    fn test_highlight_break_points() {
+        check(
+            r#"
+fn foo() {
+    'outer: lo<|>op {
+        while true {
+            break;
+            break 'outer;
+        }
+        continue;
+        let f = || loop { break; };
+    }
+}
+"#,
+            &["loop", "break", "continue"],
+        );
+        check(
+            r#"
+fn foo() {
+    for x in 0..10 {
+        if x > 5 { br<|>eak; }
+        loop { break; }
+        continue;
+    }
+}
+"#,
+            &["for", "break", "continue"],
+        );
+    }
+
+    #[test]
+    fn test_highlight_await_points() {
+        check(
+            r#"
+async fn foo() {
+    bar().aw<|>ait;
+    let x = async { baz().await };
+    qux().await;
+}
+"#,
+            &["async", "await", "await"],
+        );
+        check(
+            r#"
+fn foo() {
+    as<|>ync {
+        bar().await;
+    };
+}
+"#,
+            &["async", "await"],
+        );
+    }
+
+    #[test]
+    fn test_no_highlight_for_fn_pointers() {
+        check("fn foo(f: f<|>n()) {}", &[]);
+    }
+}
@@ -2,8 +2,8 @@
 //! source code items (e.g. function call, struct field, variable symbol...)
 
 use hir::{
-    Adt, AsAssocItem, AssocItemContainer, FieldSource, HasSource, HirDisplay, ModuleDef,
-    ModuleSource, Semantics,
+    Adt, AsAssocItem, AssocItemContainer, CaptureMode, FieldSource, HasSource, HirDisplay, Local,
+    ModuleDef, ModuleSource, Semantics,
 };
 use ra_db::SourceDatabase;
 use ra_ide_db::{
@@ -11,15 +11,16 @@ use ra_ide_db::{
     RootDatabase,
 };
 use ra_syntax::{
-    ast::{self, DocCommentsOwner},
-    match_ast, AstNode,
-    SyntaxKind::*,
-    SyntaxToken, TokenAtOffset,
+    ast::{self, ArgListOwner, DocCommentsOwner, HasStringValue, LiteralKind},
+    match_ast, AstNode, AstToken,
+    SyntaxKind::{self, *},
+    SyntaxToken, TokenAtOffset, T,
 };
 
 use crate::{
     display::{macro_label, rust_code_markup, rust_code_markup_with_doc, ShortLabel},
-    FilePosition, RangeInfo,
+    format_string::FormatCall,
+    FilePosition, FunctionSignature, RangeInfo,
 };
 use itertools::Itertools;
 use std::iter::once;
@@ -109,7 +110,12 @@ fn determine_mod_path(db: &RootDatabase, def: &Definition) -> Option<String> {
     mod_path
 }
 
-fn hover_text_from_name_kind(db: &RootDatabase, def: Definition) -> Option<String> {
+fn hover_text_from_name_kind(
+    sema: &Semantics<RootDatabase>,
+    def: Definition,
+    show_elided_lifetimes: bool,
+) -> Option<String> {
+    let db = sema.db;
     let mod_path = determine_mod_path(db, &def);
     return match def {
         Definition::Macro(it) => {
@@ -132,18 +138,50 @@ fn hover_text_from_name_kind(db: &RootDatabase, def: Definition) -> Option<Strin
                 }
                 _ => None,
             },
-            ModuleDef::Function(it) => from_def_source(db, it, mod_path),
+            ModuleDef::Function(it) => {
+                let src = sema.source(it);
+                let mut signature = FunctionSignature::from(&src.value);
+                if show_elided_lifetimes {
+                    signature = signature.with_elided_lifetimes(sema, &src.value);
+                }
+                hover_text(src.value.doc_comment_text(), Some(signature.to_string()), mod_path)
+            }
             ModuleDef::Adt(Adt::Struct(it)) => from_def_source(db, it, mod_path),
             ModuleDef::Adt(Adt::Union(it)) => from_def_source(db, it, mod_path),
-            ModuleDef::Adt(Adt::Enum(it)) => from_def_source(db, it, mod_path),
-            ModuleDef::EnumVariant(it) => from_def_source(db, it, mod_path),
-            ModuleDef::Const(it) => from_def_source(db, it, mod_path),
+            ModuleDef::Adt(Adt::Enum(it)) => {
+                let src = it.source(db);
+                let label = src.value.short_label().map(|label| match it.repr(db) {
+                    Some(repr) => format!("#[repr({})]\n{}", repr, label),
+                    None => label,
+                });
+                hover_text(src.value.doc_comment_text(), label, mod_path)
+            }
+            ModuleDef::EnumVariant(it) => {
+                let src = it.source(db);
+                let label = src.value.short_label().map(|label| match it.discriminant(db) {
+                    Some(discriminant) => format!("{} = {}", label, discriminant),
+                    None => label,
+                });
+                hover_text(src.value.doc_comment_text(), label, mod_path)
+            }
+            ModuleDef::Const(it) => {
+                let src = it.source(db);
+                let label = src.value.short_label().map(|label| match it.eval(db) {
+                    Some(value) => format!("{} = {}", label, value),
+                    None => label,
+                });
+                hover_text(src.value.doc_comment_text(), label, mod_path)
+            }
             ModuleDef::Static(it) => from_def_source(db, it, mod_path),
             ModuleDef::Trait(it) => from_def_source(db, it, mod_path),
             ModuleDef::TypeAlias(it) => from_def_source(db, it, mod_path),
             ModuleDef::BuiltinType(it) => Some(it.to_string()),
         },
-        Definition::Local(it) => Some(rust_code_markup(&it.ty(db).display_truncated(db, None))),
+        Definition::Local(it) => Some(rust_code_markup_with_doc(
+            &it.ty(db).display_truncated(db, None),
+            closure_captures_text(db, it).as_deref(),
+            None,
+        )),
         Definition::TypeParam(_) | Definition::SelfType(_) => {
             // FIXME: Hover for generic param
             None
@@ -160,14 +198,54 @@ fn hover_text_from_name_kind(db: &RootDatabase, def: Definition) -> Option<Strin
     }
 }
 
-pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeInfo<HoverResult>> {
+fn closure_captures_text(db: &RootDatabase, local: Local) -> Option<String> {
+    let captures = local.closure_captures(db);
+    let mut modes = captures.iter().map(|it| it.mode()).collect::<Vec<_>>();
+    modes.sort();
+    modes.dedup();
+    let modes = modes
+        .into_iter()
+        .map(|mode| match mode {
+            CaptureMode::ByRef => "by reference",
+            CaptureMode::ByMutRef => "by mutable reference",
+            CaptureMode::ByMove => "by move",
+        })
+        .join(", ");
+    match captures.len() {
+        0 => None,
+        1 => Some(format!("Captured by a closure {}", modes)),
+        n => Some(format!("Captured by {} closures: {}", n, modes)),
+    }
+}
+
+pub(crate) fn hover(
+    db: &RootDatabase,
+    position: FilePosition,
+    show_elided_lifetimes: bool,
+) -> Option<RangeInfo<HoverResult>> {
     let sema = Semantics::new(db);
     let file = sema.parse(position.file_id).syntax().clone();
     let token = pick_best(file.token_at_offset(position.offset))?;
-    let token = sema.descend_into_macros(token);
+
+    // Placeholders in format strings show the hover of the argument they refer to.
+    if let Some(call) = FormatCall::from_token(&token) {
+        if let Some((placeholder, arg)) = call.resolve_at(position.offset) {
+            let offset = arg.value_range().start();
+            let RangeInfo { info, .. } =
+                hover(db, FilePosition { offset, ..position }, show_elided_lifetimes)?;
+            return Some(RangeInfo::new(placeholder.range, info));
+        }
+    }
 
     let mut res = HoverResult::new();
 
+    if let Some(doc) = keyword_doc(token.kind()) {
+        res.extend(Some(doc.to_string()));
+        return Some(RangeInfo::new(token.text_range(), res));
+    }
+
+    let token = sema.descend_into_macros(token);
+
     if let Some((node, name_kind)) = match_ast! {
         match (token.parent()) {
             ast::NameRef(name_ref) => {
@@ -180,13 +258,32 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
         }
     } {
         let range = sema.original_range(&node).range;
-        res.extend(hover_text_from_name_kind(db, name_kind));
+        res.extend(hover_text_from_name_kind(&sema, name_kind, show_elided_lifetimes));
+        if let Some(name_ref) = ast::NameRef::cast(node.clone()) {
+            res.extend(iterator_chain_steps(&sema, &name_ref));
+        }
 
         if !res.is_empty() {
             return Some(RangeInfo::new(range, res));
         }
     }
 
+    if let Some(array) = token.parent().and_then(ast::ArrayType::cast) {
+        let len = sema.array_len(&array)?;
+        let elem = array.type_ref()?;
+        res.extend(Some(rust_code_markup(&format!("[{}; {}]", elem.syntax(), len))));
+        let range = sema.original_range(array.syntax()).range;
+        return Some(RangeInfo::new(range, res));
+    }
+
+    if let Some(literal) = token.parent().and_then(ast::Literal::cast) {
+        let ty = sema.type_of_expr(&ast::Expr::Literal(literal.clone()))?;
+        res.extend(Some(rust_code_markup(&ty.display_truncated(db, None))));
+        res.extend(literal_value(&literal));
+        let range = sema.original_range(literal.syntax()).range;
+        return Some(RangeInfo::new(range, res));
+    }
+
     let node = token
         .ancestors()
         .find(|n| ast::Expr::cast(n.clone()).is_some() || ast::Pat::cast(n.clone()).is_some())?;
@@ -213,12 +310,115 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
     Some(RangeInfo::new(range, res))
 }
 
+/// For a method of an iterator chain like `xs.iter().map(f).filter(g)`,
+/// shows the full type after each step of the chain, which the inlay hints
+/// abbreviate to `impl Iterator<Item = T>`.
+fn iterator_chain_steps(sema: &Semantics<RootDatabase>, name_ref: &ast::NameRef) -> Option<String> {
+    let mut call = name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast)?;
+    while let Some(parent) = call.syntax().parent().and_then(ast::MethodCallExpr::cast) {
+        call = parent;
+    }
+    let mut steps = Vec::new();
+    loop {
+        let receiver = call.expr();
+        steps.push(call);
+        call = match receiver {
+            Some(ast::Expr::MethodCallExpr(it)) => it,
+            _ => break,
+        };
+    }
+    if steps.len() < 2 {
+        return None;
+    }
+
+    let mut is_iterator_chain = false;
+    let mut lines = Vec::new();
+    for step in steps.into_iter().rev() {
+        let ty = sema.type_of_expr(&step.clone().into())?;
+        is_iterator_chain |= ty.iterator_item(sema.db).is_some();
+        let has_args = step.arg_list().map_or(false, |it| it.args().next().is_some());
+        lines.push(format!(
+            ".{}({}): {}",
+            step.name_ref()?,
+            if has_args { ".." } else { "" },
+            ty.display_truncated(sema.db, None)
+        ));
+    }
+    if !is_iterator_chain {
+        return None;
+    }
+    Some(rust_code_markup(&lines.join("\n")))
+}
+
+/// Short explanations for keywords and operators which have no definition to
+/// navigate to.
+fn keyword_doc(kind: SyntaxKind) -> Option<&'static str> {
+    let doc = match kind {
+        T![as] => "`as` performs a primitive cast or renames an import.",
+        T![async] => {
+            "`async` turns a function, closure or block into a `Future`, which does nothing \
+             until it is `.await`ed."
+        }
+        T![await] => {
+            "`.await` suspends the enclosing `async` context until the future completes, \
+             and evaluates to its output."
+        }
+        T![dyn] => {
+            "`dyn Trait` is a trait object: the concrete type is erased and method calls are \
+             dispatched dynamically through a vtable."
+        }
+        T![move] => "`move` makes a closure or an async block take ownership of its captures.",
+        T![ref] => "`ref` binds by reference instead of by value in a pattern.",
+        T![unsafe] => {
+            "`unsafe` marks code whose memory safety is checked by the programmer rather than \
+             by the compiler."
+        }
+        T![?] => {
+            "`?` unwraps an `Ok`/`Some` value, or returns early from the enclosing function \
+             with the `Err` (converted via `From`) or `None`."
+        }
+        _ => return None,
+    };
+    Some(doc)
+}
+
+/// Describes the value of a literal, when it is not obvious from the source
+/// text: numbers in all bases, code points of chars and unescaped strings.
+fn literal_value(literal: &ast::Literal) -> Option<String> {
+    let token = literal.token();
+    let res = match literal.kind() {
+        LiteralKind::IntNumber { .. } => {
+            let value = ast::IntNumber::cast(token)?.value()?;
+            format!("`{}` = `{:#x}` = `{:#o}` = `{:#b}`", value, value, value, value)
+        }
+        LiteralKind::Byte => {
+            let value = ast::Byte::cast(token)?.value()?;
+            format!("`{}` = `{:#x}`", value, value)
+        }
+        LiteralKind::Char => {
+            let c = ast::Char::cast(token)?.value()?;
+            format!("`{:?}` (U+{:04X})", c, c as u32)
+        }
+        LiteralKind::String => {
+            let string = ast::String::cast(token)?;
+            if !string.text().contains('\\') {
+                return None;
+            }
+            format!("```text\n{}\n```", string.value()?)
+        }
+        LiteralKind::ByteString | LiteralKind::FloatNumber { .. } | LiteralKind::Bool(_) => {
+            return None;
+        }
+    };
+    Some(res)
+}
+
 fn pick_best(tokens: TokenAtOffset<SyntaxToken>) -> Option<SyntaxToken> {
     return tokens.max_by_key(priority);
     fn priority(n: &SyntaxToken) -> usize {
         match n.kind() {
             IDENT | INT_NUMBER => 3,
-            L_PAREN | R_PAREN => 2,
+            L_PAREN | R_PAREN | T![?] => 2,
             kind if kind.is_trivia() => 0,
             _ => 1,
         }
@@ -242,7 +442,7 @@ mod tests {
 
     fn check_hover_result(fixture: &str, expected: &[&str]) -> String {
         let (analysis, position) = analysis_and_position(fixture);
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         let mut results = Vec::from(hover.info.results());
         results.sort();
 
@@ -260,7 +460,7 @@ mod tests {
 
     fn check_hover_no_result(fixture: &str) {
         let (analysis, position) = analysis_and_position(fixture);
-        assert!(analysis.hover(position).unwrap().is_none());
+        assert!(analysis.hover(position, false).unwrap().is_none());
     }
 
     #[test]
@@ -274,7 +474,7 @@ mod tests {
             }
             ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(hover.range, TextRange::from_to(95.into(), 100.into()));
         assert_eq!(trim_markup_opt(hover.info.first()), Some("u32"));
     }
@@ -334,6 +534,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hover_shows_elided_lifetimes() {
+        let (analysis, position) = single_file_with_position(
+            "
+            struct S;
+            impl S {
+                fn get(&self, key: &str) -> &str { key }
+            }
+            fn main() {
+                S.ge<|>t(\"\");
+            }
+            ",
+        );
+        let hover = analysis.hover(position, true).unwrap().unwrap();
+        assert_eq!(
+            trim_markup_opt(hover.info.first()),
+            Some("S\nfn get<'a, 'b>(&'a self, key: &'b str) -> &'a str")
+        );
+    }
+
     #[test]
     fn hover_shows_fn_signature_on_fn_name() {
         check_hover_result(
@@ -392,7 +612,7 @@ mod tests {
             //- /main.rs
             const foo<|>: u32 = 0;
         "#,
-            &["const foo: u32"],
+            &["const foo: u32 = 0"],
         );
 
         check_hover_result(
@@ -404,6 +624,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hover_const_value() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            const HEADER_LEN: usize = 4;
+            const fn padded(len: usize) -> usize { if len % 8 == 0 { len } else { len + 8 - len % 8 } }
+            const BUF<|>_LEN: usize = padded(HEADER_LEN * 2 + 1);
+        "#,
+            &["const BUF_LEN: usize = 16"],
+        );
+
+        check_hover_result(
+            r#"
+            //- /main.rs
+            const NAME<|>: &str = "foo";
+        "#,
+            &["const NAME: &str"],
+        );
+    }
+
+    #[test]
+    fn hover_array_len() {
+        let hover_on = check_hover_result(
+            r#"
+            //- /main.rs
+            const HEADER_LEN: usize = 4;
+            struct Packet { header: [u8;<|> HEADER_LEN * 2] }
+        "#,
+            &["[u8; 8]"],
+        );
+        assert_eq!(hover_on, "[u8; HEADER_LEN * 2]");
+
+        check_hover_no_result(
+            r#"
+            //- /main.rs
+            struct Packet { header: [u8;<|> LEN] }
+        "#,
+        );
+    }
+
+    #[test]
+    fn hover_extern_fn() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            extern "C" { pub fn abs(x: i32) -> i32; }
+            fn main() { unsafe { ab<|>s(-1); } }
+        "#,
+            &[r#"pub extern "C" fn abs(x: i32) -> i32"#],
+        );
+
+        check_hover_result(
+            r#"
+            //- /main.rs
+            extern "C" { pub fn abs(x: i32) -> i32; }
+            fn main() {
+                let cb: extern "C" fn(i32) -> i32 = abs;
+                c<|>b;
+            }
+        "#,
+            &[r#"extern "C" fn(i32) -> i32"#],
+        );
+    }
+
     #[test]
     fn hover_omits_default_generic_types() {
         check_hover_result(
@@ -433,7 +718,7 @@ fn main() {
             }
             ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("Option\nSome"));
 
         let (analysis, position) = single_file_with_position(
@@ -446,7 +731,7 @@ fn main() {
             }
             ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("Option<i32>"));
     }
 
@@ -462,7 +747,7 @@ fn main() {
         "#,
             &["
 Option
-None
+None = 0
 ```
 
 The None variant
@@ -492,17 +777,76 @@ The Some variant
         );
     }
 
+    #[test]
+    fn hover_enum_repr_and_discriminants() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            #[repr(C, u8)]
+            enum Fo<|>o { A = 2, B(u8) }
+        "#,
+            &["#[repr(C, u8)]\nenum Foo"],
+        );
+        check_hover_result(
+            r#"
+            //- /main.rs
+            #[repr(u8)]
+            enum Foo { A = 2, B<|> }
+        "#,
+            &["Foo\nB = 3"],
+        );
+        check_hover_result(
+            r#"
+            //- /main.rs
+            #[repr(u8)]
+            enum Foo { A = 2, B<|>(u8) }
+        "#,
+            &["Foo\nB"],
+        );
+        check_hover_result(
+            r#"
+            //- /main.rs
+            enum Foo { A, B, C(u8) }
+            fn main() { Foo::B<|>; }
+        "#,
+            &["Foo\nB"],
+        );
+    }
+
+    #[test]
+    fn hover_format_string_placeholders() {
+        let hover_on = check_hover_result(
+            r#"
+            //- /main.rs
+            macro_rules! format { ($($arg:tt)*) => { ($($arg)*) } }
+            fn main() { let foo = 1u32; format!("{fo<|>o:?}", foo = foo); }
+        "#,
+            &["u32"],
+        );
+        assert_eq!(hover_on, "foo");
+        let hover_on = check_hover_result(
+            r#"
+            //- /main.rs
+            macro_rules! format { ($($arg:tt)*) => { ($($arg)*) } }
+            fn bar() -> u8 { 0 }
+            fn main() { format!("{} {<|>}", 1, bar()); }
+        "#,
+            &["fn bar() -> u8"],
+        );
+        assert_eq!(hover_on, "{}");
+    }
+
     #[test]
     fn hover_for_local_variable() {
         let (analysis, position) = single_file_with_position("fn func(foo: i32) { fo<|>o; }");
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("i32"));
     }
 
     #[test]
     fn hover_for_local_variable_pat() {
         let (analysis, position) = single_file_with_position("fn func(fo<|>o: i32) {}");
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("i32"));
     }
 
@@ -513,17 +857,77 @@ The Some variant
 fn func(foo: i32) { if true { <|>foo; }; }
 ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("i32"));
     }
 
     #[test]
     fn hover_for_param_edge() {
         let (analysis, position) = single_file_with_position("fn func(<|>foo: i32) {}");
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("i32"));
     }
 
+    #[test]
+    fn hover_local_captured_by_closures() {
+        check_hover_result(
+            "
+            //- /lib.rs
+            fn func() {
+                let mut fo<|>o = 92;
+                let a = || foo + 1;
+                let b = || foo += 1;
+                let c = move || foo;
+                let d = |foo: i32| foo;
+            }
+            ",
+            &["i32\n```\n\nCaptured by 3 closures: by reference, by mutable reference, by move"],
+        );
+        check_hover_result(
+            "
+            //- /lib.rs
+            struct S { field: i32 }
+            fn func(s: S) {
+                let f = || {
+                    let t = &mut s<|>.field;
+                };
+            }
+            ",
+            &["S\n```\n\nCaptured by a closure by mutable reference"],
+        );
+        check_hover_result(
+            "
+            //- /lib.rs
+            struct S;
+            impl S {
+                fn get(&self) {}
+                fn set(&mut self) {}
+            }
+            fn func(mut s: S) {
+                let a = || s.get();
+                let b = || s<|>.set();
+            }
+            ",
+            &["S\n```\n\nCaptured by 2 closures: by reference, by mutable reference"],
+        );
+    }
+
+    #[test]
+    fn hover_local_not_captured_by_closure() {
+        check_hover_result(
+            "
+            //- /lib.rs
+            fn func() {
+                let f = || {
+                    let foo = 92;
+                    fo<|>o
+                };
+            }
+            ",
+            &["i32"],
+        );
+    }
+
     #[test]
     fn test_hover_infer_associated_method_result() {
         let (analysis, position) = single_file_with_position(
@@ -541,7 +945,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
             }
             ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("Thing"));
     }
 
@@ -564,7 +968,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
             }
             ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("wrapper::Thing\nfn new() -> Thing"));
     }
 
@@ -586,8 +990,8 @@ fn func(foo: i32) { if true { <|>foo; }; }
             }
             ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
-        assert_eq!(trim_markup_opt(hover.info.first()), Some("const C: u32"));
+        let hover = analysis.hover(position, false).unwrap().unwrap();
+        assert_eq!(trim_markup_opt(hover.info.first()), Some("const C: u32 = 1"));
     }
 
     #[test]
@@ -602,7 +1006,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
             }
         ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("Thing"));
 
         /* FIXME: revive these tests
@@ -617,7 +1021,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
                     ",
                 );
 
-                let hover = analysis.hover(position).unwrap().unwrap();
+                let hover = analysis.hover(position, false).unwrap().unwrap();
                 assert_eq!(trim_markup_opt(hover.info.first()), Some("Thing"));
 
                 let (analysis, position) = single_file_with_position(
@@ -630,7 +1034,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
                     }
                     ",
                 );
-                let hover = analysis.hover(position).unwrap().unwrap();
+                let hover = analysis.hover(position, false).unwrap().unwrap();
                 assert_eq!(trim_markup_opt(hover.info.first()), Some("enum Thing"));
 
                 let (analysis, position) = single_file_with_position(
@@ -642,7 +1046,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
                     }
                     ",
                 );
-                let hover = analysis.hover(position).unwrap().unwrap();
+                let hover = analysis.hover(position, false).unwrap().unwrap();
                 assert_eq!(trim_markup_opt(hover.info.first()), Some("enum Thing"));
         */
     }
@@ -659,7 +1063,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
             }
             ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("i32"));
     }
 
@@ -676,7 +1080,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
             }
             ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("macro_rules! foo"));
     }
 
@@ -687,7 +1091,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
             struct TS(String, i32<|>);
             ",
         );
-        let hover = analysis.hover(position).unwrap().unwrap();
+        let hover = analysis.hover(position, false).unwrap().unwrap();
         assert_eq!(trim_markup_opt(hover.info.first()), Some("i32"));
     }
 
@@ -844,4 +1248,119 @@ fn func(foo: i32) { if true { <|>foo; }; }
             &["fn foo()\n```\n\n<- `\u{3000}` here"],
         );
     }
+
+    #[test]
+    fn hover_int_literal() {
+        let hover_on = check_hover_result(
+            r#"
+            //- /main.rs
+            fn main() {
+                let x = 0xF<|>F_u8;
+            }
+            "#,
+            &["`255` = `0xff` = `0o377` = `0b11111111`", "u8"],
+        );
+        assert_eq!(hover_on, "0xFF_u8");
+    }
+
+    #[test]
+    fn hover_char_literal() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            fn main() {
+                let c = '\u{4<|>1}';
+            }
+            "#,
+            &["`'A'` (U+0041)", "char"],
+        );
+    }
+
+    #[test]
+    fn hover_string_literal_with_escapes() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            fn main() {
+                let s = "a\<|>tb";
+            }
+            "#,
+            &["&str", "```text\na\tb"],
+        );
+    }
+
+    #[test]
+    fn hover_keyword() {
+        let hover_on = check_hover_result(
+            r#"
+            //- /main.rs
+            as<|>ync fn foo() {}
+            "#,
+            &["`async` turns a function, closure or block into a `Future`, which does nothing until it is `.await`ed."],
+        );
+        assert_eq!(hover_on, "async");
+    }
+
+    #[test]
+    fn hover_question_mark_operator() {
+        let hover_on = check_hover_result(
+            r#"
+            //- /main.rs
+            enum Option<T> { Some(T), None }
+            fn bar() -> Option<u32> { Option::None }
+            fn foo() -> Option<u32> {
+                let x = bar()?<|>;
+                Option::Some(x)
+            }
+            "#,
+            &["`?` unwraps an `Ok`/`Some` value, or returns early from the enclosing function with the `Err` (converted via `From`) or `None`."],
+        );
+        assert_eq!(hover_on, "?");
+    }
+
+    #[test]
+    fn hover_iterator_chain_shows_each_step() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            use std::iter::Iterator;
+            struct Counter;
+            impl Iterator for Counter { type Item = u32; }
+            fn main() {
+                let c = Counter
+                    .skip(1)
+                    .sk<|>ip(2)
+                    .skip(3);
+            }
+            //- /std/lib.rs
+            pub mod iter {
+                pub trait Iterator {
+                    type Item;
+                    fn skip(self, n: usize) -> Skip<Self> { loop {} }
+                }
+                pub struct Skip<I> { iter: I }
+                impl<I: Iterator> Iterator for Skip<I> { type Item = I::Item; }
+            }
+            "#,
+            &[
+                ".skip(..): Skip<Counter>\n.skip(..): Skip<Skip<Counter>>\n.skip(..): Skip<Skip<Skip<Counter>>>",
+                "std::iter::Iterator\nfn skip(self, n: usize) -> Skip<Self>",
+            ],
+        );
+    }
+
+    #[test]
+    fn hover_method_chain_without_iterators() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            struct A;
+            impl A { fn a(self) -> A { self } }
+            fn main() {
+                A.a().a<|>();
+            }
+            "#,
+            &["A\nfn a(self) -> A"],
+        );
+    }
 }
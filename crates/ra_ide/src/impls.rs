@@ -1,6 +1,9 @@
-//! FIXME: write short doc here
+//! Finds impls of a type or a trait across all crates which can see it, and
+//! navigates between trait methods and the impl methods implementing them. For
+//! a trait method with a default body, the impls which use the default are
+//! found too.
 
-use hir::{Crate, ImplDef, Semantics};
+use hir::{AsAssocItem, AssocItemContainer, ImplDef, Semantics};
 use ra_ide_db::RootDatabase;
 use ra_syntax::{algo::find_node_at_offset, ast, AstNode};
 
@@ -14,17 +17,23 @@ pub(crate) fn goto_implementation(
     let source_file = sema.parse(position.file_id);
     let syntax = source_file.syntax().clone();
 
-    let krate = sema.to_module_def(position.file_id)?.krate();
+    if let Some(name) = find_node_at_offset::<ast::Name>(&syntax, position.offset) {
+        if let Some(fn_def) = name.syntax().parent().and_then(ast::FnDef::cast) {
+            if let Some(navs) = impls_for_trait_method(&sema, &fn_def) {
+                return Some(RangeInfo::new(name.syntax().text_range(), navs));
+            }
+        }
+    }
 
     if let Some(nominal_def) = find_node_at_offset::<ast::NominalDef>(&syntax, position.offset) {
         return Some(RangeInfo::new(
             nominal_def.syntax().text_range(),
-            impls_for_def(&sema, &nominal_def, krate)?,
+            impls_for_def(&sema, &nominal_def)?,
         ));
     } else if let Some(trait_def) = find_node_at_offset::<ast::TraitDef>(&syntax, position.offset) {
         return Some(RangeInfo::new(
             trait_def.syntax().text_range(),
-            impls_for_trait(&sema, &trait_def, krate)?,
+            impls_for_trait(&sema, &trait_def)?,
         ));
     }
 
@@ -34,7 +43,6 @@ pub(crate) fn goto_implementation(
 fn impls_for_def(
     sema: &Semantics<RootDatabase>,
     node: &ast::NominalDef,
-    krate: Crate,
 ) -> Option<Vec<NavigationTarget>> {
     let ty = match node {
         ast::NominalDef::StructDef(def) => sema.to_def(def)?.ty(sema.db),
@@ -42,29 +50,57 @@ fn impls_for_def(
         ast::NominalDef::UnionDef(def) => sema.to_def(def)?.ty(sema.db),
     };
 
-    let impls = ImplDef::all_in_crate(sema.db, krate);
+    let impls = ImplDef::all_for_type(sema.db, ty);
 
-    Some(
-        impls
-            .into_iter()
-            .filter(|impl_def| ty.is_equal_for_find_impls(&impl_def.target_ty(sema.db)))
-            .map(|imp| imp.to_nav(sema.db))
-            .collect(),
-    )
+    Some(impls.into_iter().map(|imp| imp.to_nav(sema.db)).collect())
 }
 
 fn impls_for_trait(
     sema: &Semantics<RootDatabase>,
     node: &ast::TraitDef,
-    krate: Crate,
 ) -> Option<Vec<NavigationTarget>> {
     let tr = sema.to_def(node)?;
 
-    let impls = ImplDef::for_trait(sema.db, krate, tr);
+    let impls = ImplDef::all_for_trait(sema.db, tr);
 
     Some(impls.into_iter().map(|imp| imp.to_nav(sema.db)).collect())
 }
 
+fn impls_for_trait_method(
+    sema: &Semantics<RootDatabase>,
+    node: &ast::FnDef,
+) -> Option<Vec<NavigationTarget>> {
+    let func = sema.to_def(node)?;
+    match func.as_assoc_item(sema.db)?.container(sema.db) {
+        AssocItemContainer::Trait(_) => (),
+        AssocItemContainer::ImplDef(_) => return None,
+    }
+
+    let mut navs: Vec<_> =
+        func.overriding_methods(sema.db).into_iter().map(|it| it.to_nav(sema.db)).collect();
+    // The impls which use the default body have no method to jump to, so
+    // they are targets themselves.
+    if node.body().is_some() {
+        let impls = func.impls_without_override(sema.db);
+        navs.extend(impls.into_iter().map(|it| it.to_nav(sema.db)));
+    }
+    Some(navs)
+}
+
+/// Navigates from a method of a trait impl to the trait method it implements.
+pub(crate) fn goto_super_method(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<NavigationTarget>>> {
+    let sema = Semantics::new(db);
+    let source_file = sema.parse(position.file_id);
+
+    let fn_def = find_node_at_offset::<ast::FnDef>(source_file.syntax(), position.offset)?;
+    let super_method = sema.to_def(&fn_def)?.super_method(db)?;
+
+    Some(RangeInfo::new(fn_def.syntax().text_range(), vec![super_method.to_nav(db)]))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::mock_analysis::analysis_and_position;
@@ -187,6 +223,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn goto_implementation_in_dependent_crates() {
+        check_goto(
+            "
+            //- /main.rs
+            trait Local {}
+            impl Local for foo::Foo {}
+            //- /foo/lib.rs
+            pub struct Foo<|>;
+            impl Foo {}
+            ",
+            &["impl IMPL_DEF FileId(1) [15; 41)", "impl IMPL_DEF FileId(2) [16; 27)"],
+        );
+    }
+
+    #[test]
+    fn goto_implementation_for_trait_in_dependent_crates() {
+        check_goto(
+            "
+            //- /main.rs
+            struct Bar;
+            impl foo::T for Bar {}
+            //- /foo/lib.rs
+            pub trait T<|> {}
+            pub struct Foo;
+            impl T for Foo {}
+            ",
+            &["impl IMPL_DEF FileId(1) [12; 34)", "impl IMPL_DEF FileId(2) [31; 48)"],
+        );
+    }
+
     #[test]
     fn goto_implementation_to_builtin_derive() {
         check_goto(
@@ -198,4 +265,75 @@ mod tests {
             &["impl IMPL_DEF FileId(1) [0; 15)"],
         );
     }
+
+    #[test]
+    fn goto_implementation_for_trait_method() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait T { fn f<|>(&self); fn g(&self) {} }
+            struct A;
+            struct B;
+            impl T for A { fn f(&self) {} }
+            impl T for B { fn f(&self) {} fn g(&self) {} }
+            ",
+            &["f FN_DEF FileId(1) [75; 89) [78; 79)", "f FN_DEF FileId(1) [107; 121) [110; 111)"],
+        );
+    }
+
+    #[test]
+    fn goto_implementation_for_default_trait_method() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait T { fn f(&self); fn g<|>(&self) {} }
+            struct A;
+            struct B;
+            impl T for A { fn f(&self) {} }
+            impl T for B { fn f(&self) {} fn g(&self) {} }
+            ",
+            &["impl IMPL_DEF FileId(1) [60; 91)", "g FN_DEF FileId(1) [122; 136) [125; 126)"],
+        );
+    }
+
+    #[test]
+    fn goto_super_method() {
+        let check = |fixture: &str, expected: &str| {
+            let (analysis, pos) = analysis_and_position(fixture);
+            let navs = analysis.goto_super_method(pos).unwrap().unwrap().info;
+            assert_eq!(navs.len(), 1);
+            navs[0].assert_match(expected);
+        };
+        check(
+            "
+            //- /lib.rs
+            trait T { fn f(&self); }
+            struct A;
+            impl T for A { fn f(&self) { <|> } }
+            ",
+            "f FN_DEF FileId(1) [10; 22) [13; 14)",
+        );
+        // overridden default method
+        check(
+            "
+            //- /lib.rs
+            trait T { fn g(&self) {} }
+            struct A;
+            impl T for A { fn g<|>(&self) {} }
+            ",
+            "g FN_DEF FileId(1) [10; 24) [13; 14)",
+        );
+    }
+
+    #[test]
+    fn goto_super_method_not_applicable() {
+        let (analysis, pos) = analysis_and_position(
+            "
+            //- /lib.rs
+            struct A;
+            impl A { fn f<|>(&self) {} }
+            ",
+        );
+        assert!(analysis.goto_super_method(pos).unwrap().is_none());
+    }
 }
@@ -0,0 +1,71 @@
+//! Paths of `include!`, `include_str!` and `include_bytes!` calls.
+//!
+//! Such paths are relative to the directory of the file containing the call.
+
+use std::path::Path;
+
+use ra_db::{FileId, FileLoader, RelativePath, SourceDatabaseExt};
+use ra_ide_db::RootDatabase;
+use ra_syntax::{
+    ast::{self, AstToken, HasStringValue},
+    AstNode,
+    SyntaxKind::{RAW_STRING, STRING},
+    SyntaxToken, TextRange,
+};
+
+const INCLUDE_MACROS: &[&str] = &["include", "include_str", "include_bytes"];
+
+/// The string literal path argument of an `include!`-like macro call.
+pub(crate) struct IncludePath {
+    pub(crate) range: TextRange,
+    pub(crate) path: String,
+}
+
+impl IncludePath {
+    pub(crate) fn from_macro_call(macro_call: &ast::MacroCall) -> Option<IncludePath> {
+        let macro_name = macro_call.path()?.segment()?.name_ref()?;
+        if !INCLUDE_MACROS.contains(&macro_name.text().as_str()) {
+            return None;
+        }
+        let token = macro_call
+            .token_tree()?
+            .syntax()
+            .children_with_tokens()
+            .filter(|it| !it.kind().is_trivia())
+            // skip the opening delimiter
+            .nth(1)?
+            .into_token()?;
+        let path = match token.kind() {
+            STRING => ast::String::cast(token.clone())?.value()?,
+            RAW_STRING => ast::RawString::cast(token.clone())?.value()?,
+            // `include!(concat!(env!("OUT_DIR"), "/foo.rs"))` and the like
+            _ => return None,
+        };
+        Some(IncludePath { range: token.text_range(), path })
+    }
+
+    /// Finds the path if `token` is the path argument of an include macro.
+    pub(crate) fn from_token(token: &SyntaxToken) -> Option<IncludePath> {
+        let tt = ast::TokenTree::cast(token.parent())?;
+        let macro_call = ast::MacroCall::cast(tt.syntax().parent()?)?;
+        IncludePath::from_macro_call(&macro_call).filter(|it| it.range == token.text_range())
+    }
+
+    pub(crate) fn resolve(&self, db: &RootDatabase, file_id: FileId) -> Option<FileId> {
+        db.resolve_relative_path(file_id, RelativePath::new(&self.path))
+    }
+
+    /// Only Rust files inside of the source root of the including file are
+    /// loaded into the VFS, so we can't tell whether other included files
+    /// exist.
+    pub(crate) fn is_checkable(&self, db: &RootDatabase, file_id: FileId) -> bool {
+        if !self.path.ends_with(".rs") || Path::new(&self.path).is_absolute() {
+            return false;
+        }
+        let mut path = db.file_relative_path(file_id);
+        path.pop();
+        path.push(&self.path);
+        // Only paths which leave the source root start with `..` once normalized.
+        !path.normalize().starts_with("..")
+    }
+}
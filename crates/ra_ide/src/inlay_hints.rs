@@ -1,5 +1,7 @@
 //! This module defines multiple types of inlay hints and their visibility
 
+mod drop_hints;
+
 use hir::{Adt, HirDisplay, Semantics, Type};
 use ra_ide_db::RootDatabase;
 use ra_prof::profile;
@@ -15,12 +17,19 @@ pub struct InlayHintsConfig {
     pub type_hints: bool,
     pub parameter_hints: bool,
     pub chaining_hints: bool,
+    pub drop_hints: bool,
     pub max_length: Option<usize>,
 }
 
 impl Default for InlayHintsConfig {
     fn default() -> Self {
-        Self { type_hints: true, parameter_hints: true, chaining_hints: true, max_length: None }
+        Self {
+            type_hints: true,
+            parameter_hints: true,
+            chaining_hints: true,
+            drop_hints: false,
+            max_length: None,
+        }
     }
 }
 
@@ -29,6 +38,7 @@ pub enum InlayKind {
     TypeHint,
     ParameterHint,
     ChainingHint,
+    DropHint,
 }
 
 #[derive(Debug)]
@@ -58,6 +68,9 @@ pub(crate) fn inlay_hints(
                 ast::CallExpr(it) => { get_param_name_hints(&mut res, &sema, config, ast::Expr::from(it)); },
                 ast::MethodCallExpr(it) => { get_param_name_hints(&mut res, &sema, config, ast::Expr::from(it)); },
                 ast::BindPat(it) => { get_bind_pat_hints(&mut res, &sema, config, it); },
+                ast::BlockExpr(it) => { drop_hints::get_scope_drop_hints(&mut res, &sema, config, it); },
+                ast::ExprStmt(it) => { drop_hints::get_temporary_drop_hints(&mut res, &sema, config, it.into()); },
+                ast::LetStmt(it) => { drop_hints::get_temporary_drop_hints(&mut res, &sema, config, it.into()); },
                 _ => (),
             }
         }
@@ -105,7 +118,8 @@ fn get_chaining_hints(
                 }
             }
         }
-        let label = ty.display_truncated(sema.db, config.max_length).to_string();
+        let label = iterator_hint(sema, config, &ty)
+            .unwrap_or_else(|| ty.display_truncated(sema.db, config.max_length).to_string());
         acc.push(InlayHint {
             range: expr.syntax().text_range(),
             kind: InlayKind::ChainingHint,
@@ -115,6 +129,28 @@ fn get_chaining_hints(
     Some(())
 }
 
+/// Shows the iterator adapters of `core::iter` and `std::iter`, like
+/// `Map<Filter<Iter<u32>, F>, G>`, as `impl Iterator<Item = T>`, since only the
+/// item type is interesting in a chain.
+fn iterator_hint(
+    sema: &Semantics<RootDatabase>,
+    config: &InlayHintsConfig,
+    ty: &Type,
+) -> Option<String> {
+    let module = ty.as_adt()?.module(sema.db);
+    let krate = module.krate().display_name(sema.db);
+    if !matches!(krate.as_deref(), Some("core") | Some("std")) {
+        return None;
+    }
+    // `path_to_root` ends with the crate root, so this is the top-level module.
+    let top_level = module.path_to_root(sema.db).into_iter().rev().nth(1)?;
+    if top_level.name(sema.db)?.to_string() != "iter" {
+        return None;
+    }
+    let item = ty.iterator_item(sema.db)?;
+    Some(format!("impl Iterator<Item = {}>", item.display_truncated(sema.db, config.max_length)))
+}
+
 fn get_param_name_hints(
     acc: &mut Vec<InlayHint>,
     sema: &Semantics<RootDatabase>,
@@ -144,7 +180,7 @@ fn get_param_name_hints(
         .iter()
         .skip(n_params_to_skip)
         .zip(args)
-        .filter(|(param, arg)| should_show_param_hint(&fn_signature, param, &arg))
+        .filter(|(param, arg)| should_show_param_hint(sema, &fn_signature, param, &arg))
         .map(|(param_name, arg)| InlayHint {
             range: arg.syntax().text_range(),
             kind: InlayKind::ParameterHint,
@@ -231,12 +267,14 @@ fn should_not_display_type_hint(db: &RootDatabase, bind_pat: &ast::BindPat, pat_
 }
 
 fn should_show_param_hint(
+    sema: &Semantics<RootDatabase>,
     fn_signature: &FunctionSignature,
     param_name: &str,
     argument: &ast::Expr,
 ) -> bool {
+    let param_name = param_name.trim_start_matches('_');
     if param_name.is_empty()
-        || is_argument_similar_to_param(argument, param_name)
+        || is_argument_similar_to_param(sema, argument, param_name)
         || Some(param_name) == fn_signature.name.as_ref().map(String::as_str)
     {
         return false;
@@ -253,18 +291,59 @@ fn should_show_param_hint(
     parameters_len != 1 || !is_obvious_param(param_name)
 }
 
-fn is_argument_similar_to_param(argument: &ast::Expr, param_name: &str) -> bool {
-    let argument_string = remove_ref(argument.clone()).syntax().to_string();
+fn is_argument_similar_to_param(
+    sema: &Semantics<RootDatabase>,
+    argument: &ast::Expr,
+    param_name: &str,
+) -> bool {
+    if is_enum_name_similar_to_param(sema, argument, param_name) {
+        return true;
+    }
+    let argument_string = match argument_name(argument) {
+        Some(it) => it,
+        None => return false,
+    };
+    let argument_string = argument_string.trim_start_matches('_');
     argument_string.starts_with(&param_name) || argument_string.ends_with(&param_name)
 }
 
-fn remove_ref(expr: ast::Expr) -> ast::Expr {
-    if let ast::Expr::RefExpr(ref_expr) = &expr {
-        if let Some(inner) = ref_expr.expr() {
-            return inner;
+/// `foo(CompletionKind::Reference)` doesn't need a hint for a parameter named
+/// `completion_kind`.
+fn is_enum_name_similar_to_param(
+    sema: &Semantics<RootDatabase>,
+    argument: &ast::Expr,
+    param_name: &str,
+) -> bool {
+    match sema.type_of_expr(argument).and_then(|ty| ty.as_adt()) {
+        Some(Adt::Enum(e)) => to_lower_snake_case(&e.name(sema.db).to_string()) == param_name,
+        _ => false,
+    }
+}
+
+/// Returns the text the argument is compared with: the method name for method
+/// calls, so that `foo(bar.len())` doesn't get a hint for `len`, and the whole
+/// expression otherwise.
+fn argument_name(argument: &ast::Expr) -> Option<String> {
+    match argument {
+        ast::Expr::MethodCallExpr(call) => Some(call.name_ref()?.text().to_string()),
+        ast::Expr::RefExpr(ref_expr) => argument_name(&ref_expr.expr()?),
+        _ => Some(argument.syntax().to_string()),
+    }
+}
+
+fn to_lower_snake_case(name: &str) -> String {
+    let mut res = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                res.push('_');
+            }
+            res.extend(c.to_lowercase());
+        } else {
+            res.push(c);
         }
     }
-    expr
+    res
 }
 
 fn is_obvious_param(param_name: &str) -> bool {
@@ -305,7 +384,7 @@ mod tests {
     use crate::inlay_hints::InlayHintsConfig;
     use insta::assert_debug_snapshot;
 
-    use crate::mock_analysis::single_file;
+    use crate::mock_analysis::{single_file, MockAnalysis};
 
     #[test]
     fn param_hints_only() {
@@ -316,7 +395,7 @@ mod tests {
                 let _x = foo(4, 4);
             }"#,
         );
-        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: true, type_hints: false, chaining_hints: false, max_length: None}).unwrap(), @r###"
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: true, type_hints: false, chaining_hints: false, drop_hints: false, max_length: None}).unwrap(), @r###"
         [
             InlayHint {
                 range: [106; 107),
@@ -340,7 +419,7 @@ mod tests {
                 let _x = foo(4, 4);
             }"#,
         );
-        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ type_hints: false, parameter_hints: false, chaining_hints: false, max_length: None}).unwrap(), @r###"[]"###);
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ type_hints: false, parameter_hints: false, chaining_hints: false, drop_hints: false, max_length: None}).unwrap(), @r###"[]"###);
     }
 
     #[test]
@@ -352,7 +431,7 @@ mod tests {
                 let _x = foo(4, 4);
             }"#,
         );
-        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ type_hints: true, parameter_hints: false, chaining_hints: false, max_length: None}).unwrap(), @r###"
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ type_hints: true, parameter_hints: false, chaining_hints: false, drop_hints: false, max_length: None}).unwrap(), @r###"
         [
             InlayHint {
                 range: [97; 99),
@@ -1130,6 +1209,35 @@ fn main() {
         );
     }
 
+    #[test]
+    fn self_describing_arguments_have_no_param_hints() {
+        let (analysis, file_id) = single_file(
+            r#"
+enum CompletionKind {
+    Keyword,
+}
+
+struct Item;
+
+impl Item {
+    fn name(&self) -> &str { "" }
+    fn count(&self) -> i32 { 0 }
+}
+
+fn complete(completion_kind: CompletionKind, _count: i32, name: &str) {}
+
+fn main() {
+    let item = Item;
+    complete(CompletionKind::Keyword, item.count(), &item.name());
+}"#,
+        );
+
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig { type_hints: false, ..Default::default() }).unwrap(), @r###"
+        []
+        "###
+        );
+    }
+
     #[test]
     fn unit_structs_have_no_type_hints() {
         let (analysis, file_id) = single_file(
@@ -1172,7 +1280,7 @@ fn main() {
                     .into_c();
             }"#,
         );
-        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, max_length: None}).unwrap(), @r###"
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, drop_hints: false, max_length: None}).unwrap(), @r###"
         [
             InlayHint {
                 range: [232; 269),
@@ -1201,7 +1309,7 @@ fn main() {
                 let c = A(B(C)).into_b().into_c();
             }"#,
         );
-        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, max_length: None}).unwrap(), @r###"[]"###);
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, drop_hints: false, max_length: None}).unwrap(), @r###"[]"###);
     }
 
     #[test]
@@ -1226,7 +1334,7 @@ fn main() {
                     .foo();
             }"#,
         );
-        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, max_length: None}).unwrap(), @r###"
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, drop_hints: false, max_length: None}).unwrap(), @r###"
         [
             InlayHint {
                 range: [252; 323),
@@ -1264,7 +1372,7 @@ fn main() {
                     .into_c();
             }"#,
         );
-        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, max_length: None}).unwrap(), @r###"
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, drop_hints: false, max_length: None}).unwrap(), @r###"
         [
             InlayHint {
                 range: [403; 452),
@@ -1278,4 +1386,83 @@ fn main() {
             },
         ]"###);
     }
+
+    #[test]
+    fn iterator_chaining_hints() {
+        let mock = MockAnalysis::with_files(
+            r#"
+            //- /main.rs
+            use std::iter::Iterator;
+            struct Counter;
+            impl Iterator for Counter { type Item = u32; }
+            fn main() {
+                let c = Counter
+                    .skip(1)
+                    .skip(2)
+                    .skip(3);
+            }
+            //- /std/lib.rs
+            pub mod iter {
+                pub trait Iterator {
+                    type Item;
+                    fn skip(self, n: usize) -> Skip<Self> { loop {} }
+                }
+                pub struct Skip<I> { iter: I }
+                impl<I: Iterator> Iterator for Skip<I> { type Item = I::Item; }
+            }
+            "#,
+        );
+        let file_id = mock.id_of("/main.rs");
+        let analysis = mock.analysis();
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, drop_hints: false, max_length: None}).unwrap(), @r###"
+        [
+            InlayHint {
+                range: [112; 153),
+                kind: ChainingHint,
+                label: "impl Iterator<Item = u32>",
+            },
+            InlayHint {
+                range: [112; 136),
+                kind: ChainingHint,
+                label: "impl Iterator<Item = u32>",
+            },
+        ]"###);
+    }
+
+    #[test]
+    fn iterator_chaining_hints_only_for_std_iter() {
+        let (analysis, file_id) = single_file(
+            r#"
+            mod iter {
+                pub trait Iterator {
+                    type Item;
+                    fn skip(self, n: usize) -> Skip<Self> { loop {} }
+                }
+                pub struct Skip<I> { iter: I }
+                impl<I: Iterator> Iterator for Skip<I> { type Item = I::Item; }
+            }
+            use iter::Iterator;
+            struct Counter;
+            impl Iterator for Counter { type Item = u32; }
+            fn main() {
+                let c = Counter
+                    .skip(1)
+                    .skip(2)
+                    .skip(3);
+            }"#,
+        );
+        assert_debug_snapshot!(analysis.inlay_hints(file_id, &InlayHintsConfig{ parameter_hints: false, type_hints: false, chaining_hints: true, drop_hints: false, max_length: None}).unwrap(), @r###"
+        [
+            InlayHint {
+                range: [488; 553),
+                kind: ChainingHint,
+                label: "Skip<Skip<Counter>>",
+            },
+            InlayHint {
+                range: [488; 524),
+                kind: ChainingHint,
+                label: "Skip<Counter>",
+            },
+        ]"###);
+    }
 }
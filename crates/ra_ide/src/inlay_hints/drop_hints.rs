@@ -0,0 +1,266 @@
+//! Shows where values are dropped: the locals of a block at its end, and the
+//! temporaries of a statement at its semicolon.
+//!
+//! This is an approximation. Locals which might be moved somewhere, like by a
+//! call of a method which takes `self`, are assumed to be moved, and get no
+//! hint. Temporaries are only the results of calls which are borrowed, or
+//! which aren't used at all.
+
+use hir::Semantics;
+use ra_ide_db::{
+    moves::{is_borrowed, may_be_moved},
+    RootDatabase,
+};
+use ra_syntax::{
+    ast::{self, AstNode},
+    SyntaxNode,
+};
+
+use crate::inlay_hints::{InlayHint, InlayHintsConfig, InlayKind};
+
+/// Hints the locals of the block, including the parameters for the body of
+/// a function, in the order in which they are dropped.
+pub(super) fn get_scope_drop_hints(
+    acc: &mut Vec<InlayHint>,
+    sema: &Semantics<RootDatabase>,
+    config: &InlayHintsConfig,
+    block_expr: ast::BlockExpr,
+) -> Option<()> {
+    if !config.drop_hints {
+        return None;
+    }
+    let block = block_expr.block()?;
+    let r_curly = block.r_curly_token()?;
+
+    let mut pats = Vec::new();
+    // The parameters are dropped after the locals of the body.
+    if let Some(fn_def) = block_expr.syntax().parent().and_then(ast::FnDef::cast) {
+        let params = fn_def.param_list()?.params().filter_map(|it| it.pat());
+        pats.extend(params.flat_map(|it| bind_pats(it.syntax())));
+    }
+    for stmt in block.statements() {
+        if let ast::Stmt::LetStmt(it) = stmt {
+            pats.extend(it.pat().into_iter().flat_map(|it| bind_pats(it.syntax())));
+        }
+    }
+    let names: Vec<String> = pats
+        .iter()
+        .rev()
+        .filter_map(|pat| {
+            let local = sema.to_def(pat)?;
+            if !local.ty(sema.db).needs_drop(sema.db) || may_be_moved(sema, local) {
+                return None;
+            }
+            Some(local.name(sema.db)?.to_string())
+        })
+        .collect();
+    if names.is_empty() {
+        return None;
+    }
+    acc.push(InlayHint {
+        range: r_curly.text_range(),
+        kind: InlayKind::DropHint,
+        label: format!("drop({})", names.join(", ")).into(),
+    });
+    Some(())
+}
+
+/// Hints the temporaries of an expression statement or of the initializer
+/// of a `let`, which are dropped at the end of the statement.
+pub(super) fn get_temporary_drop_hints(
+    acc: &mut Vec<InlayHint>,
+    sema: &Semantics<RootDatabase>,
+    config: &InlayHintsConfig,
+    stmt: ast::Stmt,
+) -> Option<()> {
+    if !config.drop_hints {
+        return None;
+    }
+    let (expr, semicolon) = match &stmt {
+        ast::Stmt::ExprStmt(it) => (it.expr()?, it.semicolon_token()?),
+        ast::Stmt::LetStmt(it) => (it.initializer()?, it.semicolon_token()?),
+    };
+
+    let mut temporaries: Vec<ast::Expr> = expr
+        .syntax()
+        .descendants()
+        .filter(|it| !has_own_scope(it, expr.syntax()))
+        .filter_map(ast::Expr::cast)
+        .filter(|it| matches!(it, ast::Expr::CallExpr(_) | ast::Expr::MethodCallExpr(_)))
+        .filter(|it| is_temporary(sema, &stmt, it))
+        .collect();
+    // Temporaries are dropped in the reverse order of their evaluation, which
+    // ends with the outermost expression.
+    temporaries.sort_by_key(|it| std::cmp::Reverse(it.syntax().text_range().end()));
+    let types: Vec<String> = temporaries
+        .iter()
+        .filter_map(|it| sema.type_of_expr(it))
+        .filter(|it| it.needs_drop(sema.db))
+        .map(|it| it.display_truncated(sema.db, config.max_length).to_string())
+        .collect();
+    if types.is_empty() {
+        return None;
+    }
+    acc.push(InlayHint {
+        range: semicolon.text_range(),
+        kind: InlayKind::DropHint,
+        label: format!("drop({})", types.join(", ")).into(),
+    });
+    Some(())
+}
+
+fn bind_pats(pat: &SyntaxNode) -> impl Iterator<Item = ast::BindPat> {
+    pat.descendants().filter_map(ast::BindPat::cast)
+}
+
+/// Whether `node` is in a closure or block within `root`, whose temporaries
+/// are dropped before the end of the statement.
+fn has_own_scope(node: &SyntaxNode, root: &SyntaxNode) -> bool {
+    node.ancestors()
+        .skip(1)
+        .take_while(|it| it != root)
+        .any(|it| ast::LambdaExpr::can_cast(it.kind()) || ast::BlockExpr::can_cast(it.kind()))
+}
+
+/// Whether the value of a call is only borrowed or not used at all, so that
+/// it lives until the end of the statement.
+fn is_temporary(sema: &Semantics<RootDatabase>, stmt: &ast::Stmt, expr: &ast::Expr) -> bool {
+    let parent = match expr.syntax().parent() {
+        Some(it) => it,
+        None => return false,
+    };
+    if ast::ExprStmt::can_cast(parent.kind()) {
+        return true;
+    }
+    if let Some(ref_expr) = ast::RefExpr::cast(parent.clone()) {
+        // `let x = &make();` extends the lifetime of the temporary to the
+        // enclosing block.
+        return match stmt {
+            ast::Stmt::LetStmt(it) => {
+                it.initializer().map_or(true, |it| it.syntax() != ref_expr.syntax())
+            }
+            ast::Stmt::ExprStmt(_) => true,
+        };
+    }
+    is_borrowed(sema, expr, &parent)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inlay_hints::{InlayHintsConfig, InlayKind},
+        mock_analysis::single_file,
+    };
+
+    fn check(text: &str, expected: &[(&str, &str)]) {
+        let text = format!(
+            "{}\n{}",
+            r#"
+#[lang = "drop"]
+trait Drop { fn drop(&mut self); }
+struct Guard;
+impl Drop for Guard { fn drop(&mut self) {} }
+impl Guard {
+    fn new() -> Guard { Guard }
+    fn peek(&self) -> u32 { 0 }
+    fn into_inner(self) -> u32 { 0 }
+}
+"#,
+            text
+        );
+        let (analysis, file_id) = single_file(&text);
+        let config = InlayHintsConfig { drop_hints: true, ..InlayHintsConfig::default() };
+        let hints: Vec<(String, String)> = analysis
+            .inlay_hints(file_id, &config)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.kind == InlayKind::DropHint)
+            .map(|it| (text[it.range].to_string(), it.label.to_string()))
+            .collect();
+        let expected: Vec<(String, String)> =
+            expected.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+        assert_eq!(hints, expected);
+    }
+
+    #[test]
+    fn locals_are_dropped_in_reverse_order() {
+        check(
+            r#"
+fn f(g: Guard) {
+    let a = Guard::new();
+    let n = 1;
+    let (b, c) = (Guard::new(), Guard::new());
+    a.peek();
+}
+"#,
+            &[("}", "drop(c, b, a, g)")],
+        );
+    }
+
+    #[test]
+    fn moved_locals_are_not_dropped() {
+        check(
+            r#"
+fn take(g: Guard) {}
+fn f() {
+    let a = Guard::new();
+    let b = Guard::new();
+    let c = Guard::new();
+    take(a);
+    b.into_inner();
+    &c;
+}
+"#,
+            &[("}", "drop(g)"), ("}", "drop(c)")],
+        );
+    }
+
+    #[test]
+    fn locals_in_struct_shorthand_are_moved() {
+        check(
+            r#"
+struct S { g: Guard }
+fn f() {
+    let g = Guard::new();
+    let s = S { g };
+}
+"#,
+            &[("}", "drop(s)")],
+        );
+    }
+
+    #[test]
+    fn uses_in_macro_calls_are_checked_in_the_expansion() {
+        check(
+            r#"
+macro_rules! peek { ($e:expr) => { $e.peek() }; }
+macro_rules! consume { ($e:expr) => { $e.into_inner() }; }
+macro_rules! ignore { ($($t:tt)*) => {}; }
+fn f() {
+    let a = Guard::new();
+    let b = Guard::new();
+    let c = Guard::new();
+    peek!(a);
+    consume!(b);
+    ignore!(c);
+}
+"#,
+            &[("}", "drop(c, a)")],
+        );
+    }
+
+    #[test]
+    fn temporaries_are_dropped_at_end_of_statement() {
+        check(
+            r#"
+fn f() {
+    Guard::new().peek();
+    Guard::new();
+    let n = Guard::new().into_inner();
+    let r = &Guard::new();
+}
+"#,
+            &[(";", "drop(Guard)"), (";", "drop(Guard)")],
+        );
+    }
+}
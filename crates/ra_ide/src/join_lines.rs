@@ -1,12 +1,13 @@
 //! FIXME: write short doc here
 
 use itertools::Itertools;
+use ra_assists::utils::try_merge_trees;
 use ra_fmt::{compute_ws, extract_trivial_expression};
 use ra_syntax::{
     algo::{find_covering_element, non_trivia_sibling},
-    ast::{self, AstNode, AstToken},
-    Direction, NodeOrToken, SourceFile,
-    SyntaxKind::{self, WHITESPACE},
+    ast::{self, AstNode, AstToken, AttrsOwner, VisibilityOwner},
+    Direction, NodeOrToken, SourceFile, SyntaxElement,
+    SyntaxKind::{self, BLOCK, BYTE_STRING, EXPR_STMT, STRING, WHITESPACE},
     SyntaxNode, SyntaxToken, TextRange, TextUnit, T,
 };
 use ra_text_edit::{TextEdit, TextEditBuilder};
@@ -48,6 +49,19 @@ pub fn join_lines(file: &SourceFile, range: TextRange) -> TextEdit {
 }
 
 fn remove_newline(edit: &mut TextEditBuilder, token: &SyntaxToken, offset: TextUnit) {
+    if token.kind() == STRING || token.kind() == BYTE_STRING {
+        // Turns a string continuation like
+        //
+        // ```
+        // "foo \<|>
+        //     bar"
+        // ```
+        //
+        // into `"foo bar"`, which doesn't change the value of the string.
+        if join_string_continuation(edit, token, offset).is_some() {
+            return;
+        }
+    }
     if token.kind() != WHITESPACE || token.text().bytes().filter(|&b| b == b'\n').count() != 1 {
         // The node is either the first or the last in the file
         let suff = &token.text()[TextRange::from_to(
@@ -116,6 +130,27 @@ fn remove_newline(edit: &mut TextEditBuilder, token: &SyntaxToken, offset: TextU
     if join_single_use_tree(edit, token).is_some() {
         return;
     }
+    // ditto for
+    //
+    // ```
+    // {<|>
+    //     foo();
+    // }
+    // ```
+    if join_single_stmt_block(edit, token).is_some() {
+        return;
+    }
+    // Merges two imports with a common prefix
+    //
+    // ```
+    // use foo::bar;<|>
+    // use foo::baz;
+    // ```
+    //
+    // into `use foo::{bar, baz};`
+    if join_use_items(edit, &prev, &next).is_some() {
+        return;
+    }
 
     // Remove newline but add a computed amount of whitespace characters
     edit.replace(token.text_range(), compute_ws(prev.kind(), next.kind()).to_string());
@@ -155,6 +190,79 @@ fn join_single_use_tree(edit: &mut TextEditBuilder, token: &SyntaxToken) -> Opti
     Some(())
 }
 
+fn join_single_stmt_block(edit: &mut TextEditBuilder, token: &SyntaxToken) -> Option<()> {
+    let block = ast::Block::cast(token.parent())?;
+    let block_expr = ast::BlockExpr::cast(block.syntax().parent()?)?;
+    // Labeled, `unsafe` and `async` blocks can't be unwrapped
+    if block_expr.syntax().first_child_or_token()?.kind() != BLOCK || block.expr().is_some() {
+        return None;
+    }
+    // Neither can blocks which are a part of another expression, like `if`
+    let parent = block_expr.syntax().parent()?;
+    if parent.kind() != EXPR_STMT && parent.kind() != BLOCK {
+        return None;
+    }
+    let (stmt,) = block.statements().collect_tuple()?;
+    // Unwrapping a `let` would change the scope of the binding
+    if !matches!(stmt, ast::Stmt::ExprStmt(_)) {
+        return None;
+    }
+    let has_comments =
+        block.syntax().children_with_tokens().any(|it| ast::Comment::can_cast(it.kind()));
+    if has_comments {
+        return None;
+    }
+
+    let range = match ast::ExprStmt::cast(parent) {
+        Some(expr_stmt) if expr_stmt.semicolon_token().is_some() => expr_stmt.syntax().text_range(),
+        _ => block_expr.syntax().text_range(),
+    };
+    edit.replace(range, stmt.syntax().text().to_string());
+    Some(())
+}
+
+fn join_use_items(
+    edit: &mut TextEditBuilder,
+    prev: &SyntaxElement,
+    next: &SyntaxElement,
+) -> Option<()> {
+    let prev = ast::UseItem::cast(prev.as_node()?.clone())?;
+    let next = ast::UseItem::cast(next.as_node()?.clone())?;
+    if prev.attrs().next().is_some()
+        || next.attrs().next().is_some()
+        || prev.visibility().map(|it| it.syntax().to_string())
+            != next.visibility().map(|it| it.syntax().to_string())
+    {
+        return None;
+    }
+    let prev_tree = prev.use_tree()?;
+    let merged = try_merge_trees(&prev_tree, &next.use_tree()?)?;
+    let range = TextRange::from_to(
+        prev_tree.syntax().text_range().start(),
+        next.syntax().text_range().end(),
+    );
+    edit.replace(range, format!("{};", merged.syntax()));
+    Some(())
+}
+
+fn join_string_continuation(
+    edit: &mut TextEditBuilder,
+    token: &SyntaxToken,
+    offset: TextUnit,
+) -> Option<()> {
+    let text = token.text().as_str();
+    let newline = (offset - token.text_range().start()).to_usize();
+    let backslashes = text[..newline].bytes().rev().take_while(|&b| b == b'\\').count();
+    // `"foo\\` ends with an escaped backslash, not with a continuation
+    if backslashes % 2 == 0 {
+        return None;
+    }
+    let indent = text[newline..].bytes().take_while(|b| b.is_ascii_whitespace()).count();
+    let start = offset - TextUnit::of_char('\\');
+    edit.delete(TextRange::from_to(start, offset + TextUnit::from_usize(indent)));
+    Some(())
+}
+
 fn is_trailing_comma(left: SyntaxKind, right: SyntaxKind) -> bool {
     match (left, right) {
         (T![,], T![')']) | (T![,], T![']']) => true,
@@ -474,6 +582,119 @@ use ra_syntax::{
         );
     }
 
+    #[test]
+    fn test_join_lines_single_stmt_block() {
+        check_join_lines(
+            r"
+fn foo() {
+    <|>{
+        foo();
+    }
+    bar();
+}",
+            r"
+fn foo() {
+    <|>foo();
+    bar();
+}",
+        );
+    }
+
+    #[test]
+    fn test_join_lines_single_stmt_block_in_if() {
+        check_join_lines(
+            r"
+fn foo() {
+    if true <|>{
+        foo();
+    }
+}",
+            r"
+fn foo() {
+    if true <|>{ foo();
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn test_join_lines_use_items() {
+        check_join_lines(
+            r"
+<|>use std::fmt::Debug;
+use std::fmt::Display;
+",
+            r"
+<|>use std::fmt::{Debug, Display};
+",
+        );
+    }
+
+    #[test]
+    fn test_join_lines_unrelated_use_items() {
+        check_join_lines(
+            r"
+<|>use std::fmt::Debug;
+use foo::Display;
+",
+            r"
+<|>use std::fmt::Debug; use foo::Display;
+",
+        );
+    }
+
+    #[test]
+    fn test_join_lines_use_items_with_attrs() {
+        check_join_lines(
+            r"
+<|>#[cfg(test)] use a::b;
+use a::c;
+",
+            r"
+<|>#[cfg(test)] use a::b; use a::c;
+",
+        );
+        check_join_lines(
+            r"
+<|>use a::b;
+#[cfg(test)] use a::c;
+",
+            r"
+<|>use a::b; #[cfg(test)] use a::c;
+",
+        );
+    }
+
+    #[test]
+    fn test_join_lines_string_continuation() {
+        check_join_lines(
+            r#"
+fn foo() {
+    let s = "foo <|>\
+        bar";
+}"#,
+            r#"
+fn foo() {
+    let s = "foo <|>bar";
+}"#,
+        );
+    }
+
+    #[test]
+    fn test_join_lines_string_escaped_backslash() {
+        check_join_lines(
+            r#"
+fn foo() {
+    let s = "foo\\<|>
+        bar";
+}"#,
+            r#"
+fn foo() {
+    let s = "foo\\<|> bar";
+}"#,
+        );
+    }
+
     #[test]
     fn test_join_lines_normal_comments() {
         check_join_lines(
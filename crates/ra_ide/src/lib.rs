@@ -35,14 +35,19 @@ mod impls;
 mod assists;
 mod diagnostics;
 mod syntax_tree;
+mod view_hir;
 mod folding_ranges;
 mod join_lines;
 mod typing;
 mod matching_brace;
+mod highlight_related;
 mod display;
 mod inlay_hints;
 mod expand_macro;
+mod format_string;
+mod include_path;
 mod ssr;
+mod test_tree;
 
 #[cfg(test)]
 mod marks;
@@ -61,11 +66,12 @@ use ra_ide_db::{
     LineIndexDatabase,
 };
 use ra_syntax::{SourceFile, TextRange, TextUnit};
+use rustc_hash::FxHashMap;
 
 use crate::display::ToNav;
 
 pub use crate::{
-    assists::{Assist, AssistId},
+    assists::{Assist, AssistConfig, AssistId, AssistLabel},
     call_hierarchy::CallItem,
     completion::{CompletionConfig, CompletionItem, CompletionItemKind, InsertTextFormat},
     diagnostics::Severity,
@@ -74,13 +80,15 @@ pub use crate::{
     folding_ranges::{Fold, FoldKind},
     hover::HoverResult,
     inlay_hints::{InlayHint, InlayHintsConfig, InlayKind},
+    prime_caches::PrimeCachesProgress,
     references::{Declaration, Reference, ReferenceAccess, ReferenceKind, ReferenceSearchResult},
     runnables::{Runnable, RunnableKind, TestId},
     source_change::{FileSystemEdit, SourceChange, SourceFileEdit},
-    ssr::SsrError,
     syntax_highlighting::{
         Highlight, HighlightModifier, HighlightModifiers, HighlightTag, HighlightedRange,
+        InjectedLanguage,
     },
+    test_tree::{TestItem, TestItemKind},
 };
 
 pub use hir::Documentation;
@@ -88,13 +96,15 @@ pub use ra_db::{
     Canceled, CrateGraph, CrateId, Edition, FileId, FilePosition, FileRange, SourceRootId,
 };
 pub use ra_ide_db::{
-    change::{AnalysisChange, LibraryData},
-    line_index::{LineCol, LineIndex},
+    change::AnalysisChange,
+    insert_use::{ImportGranularity, InsertUseConfig},
+    line_index::{LineCol, LineColUtf8, LineIndex},
     line_index_utils::translate_offset_with_edit,
-    search::SearchScope,
-    symbol_index::Query,
+    search::{SearchMode, SearchScope},
+    symbol_index::{Query, SymbolCache},
     RootDatabase,
 };
+pub use ra_ssr::SsrError;
 
 pub type Cancelable<T> = Result<T, Canceled>;
 
@@ -102,8 +112,20 @@ pub type Cancelable<T> = Result<T, Canceled>;
 pub struct Diagnostic {
     pub message: String,
     pub range: TextRange,
-    pub fix: Option<SourceChange>,
+    pub fixes: Vec<SourceChange>,
     pub severity: Severity,
+    /// The kind of the diagnostic, like `unresolved-module`.
+    pub code: &'static str,
+    /// Secondary locations related to the diagnostic.
+    pub labels: Vec<DiagnosticLabel>,
+}
+
+/// A location which explains a diagnostic, like the declaration of the
+/// immutable binding for "cannot mutate immutable binding".
+#[derive(Debug)]
+pub struct DiagnosticLabel {
+    pub range: FileRange,
+    pub message: String,
 }
 
 /// Info associated with a text range.
@@ -236,8 +258,13 @@ impl Analysis {
         self.with_db(|db| status::status(&*db))
     }
 
-    pub fn prime_caches(&self, files: Vec<FileId>) -> Cancelable<()> {
-        self.with_db(|db| prime_caches::prime_caches(db, files))
+    /// Computes the def maps of all the crates in parallel, reporting the
+    /// progress to `cb`.
+    pub fn prime_caches<F>(&self, cb: F) -> Cancelable<()>
+    where
+        F: Fn(PrimeCachesProgress) + Sync + std::panic::UnwindSafe,
+    {
+        self.with_db(move |db| prime_caches::prime_caches(db, &cb))
     }
 
     /// Gets the text of the source file.
@@ -261,6 +288,12 @@ impl Analysis {
         self.with_db(|db| extend_selection::extend_selection(db, frange))
     }
 
+    /// Undoes one step of `extend_selection`, selecting the largest syntactic
+    /// construct inside of the range.
+    pub fn shrink_selection(&self, frange: FileRange) -> Cancelable<TextRange> {
+        self.with_db(|db| extend_selection::shrink_selection(db, frange))
+    }
+
     /// Returns position of the matching brace (all types of braces are
     /// supported).
     pub fn matching_brace(&self, position: FilePosition) -> Cancelable<Option<TextUnit>> {
@@ -271,6 +304,16 @@ impl Analysis {
         })
     }
 
+    /// Returns the exit points, loop `break`s or `.await`s related to the
+    /// keyword at the given position.
+    pub fn highlight_related(&self, position: FilePosition) -> Cancelable<Option<Vec<TextRange>>> {
+        self.with_db(|db| {
+            let parse = db.parse(position.file_id);
+            let file = parse.tree();
+            highlight_related::highlight_related(&file, position.offset)
+        })
+    }
+
     /// Returns a syntax tree represented as `String`, for debug purposes.
     // FIXME: use a better name here.
     pub fn syntax_tree(
@@ -285,6 +328,12 @@ impl Analysis {
         self.with_db(|db| expand_macro::expand_macro(db, position))
     }
 
+    /// Returns the lowered HIR of the function at the given position, for
+    /// debug purposes.
+    pub fn view_hir(&self, position: FilePosition) -> Cancelable<String> {
+        self.with_db(|db| view_hir::view_hir(&db, position))
+    }
+
     /// Returns an edit to remove all newlines in the range, cleaning up minor
     /// stuff like trailing commas.
     pub fn join_lines(&self, frange: FileRange) -> Cancelable<SourceChange> {
@@ -358,6 +407,14 @@ impl Analysis {
         self.with_db(|db| goto_definition::goto_definition(db, position))
     }
 
+    /// Returns the trait method implemented by the impl method at `position`.
+    pub fn goto_super_method(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Option<RangeInfo<Vec<NavigationTarget>>>> {
+        self.with_db(|db| impls::goto_super_method(db, position))
+    }
+
     /// Returns the impls from the symbol at `position`.
     pub fn goto_implementation(
         &self,
@@ -379,18 +436,30 @@ impl Analysis {
         &self,
         position: FilePosition,
         search_scope: Option<SearchScope>,
+        mode: SearchMode,
     ) -> Cancelable<Option<ReferenceSearchResult>> {
-        self.with_db(|db| references::find_all_refs(db, position, search_scope).map(|it| it.info))
+        self.with_db(|db| {
+            references::find_all_refs(db, position, search_scope, mode).map(|it| it.info)
+        })
     }
 
-    /// Returns a short text describing element at position.
-    pub fn hover(&self, position: FilePosition) -> Cancelable<Option<RangeInfo<HoverResult>>> {
-        self.with_db(|db| hover::hover(db, position))
+    /// Returns a short text describing element at position. Function
+    /// signatures can show their elided lifetimes.
+    pub fn hover(
+        &self,
+        position: FilePosition,
+        show_elided_lifetimes: bool,
+    ) -> Cancelable<Option<RangeInfo<HoverResult>>> {
+        self.with_db(|db| hover::hover(db, position, show_elided_lifetimes))
     }
 
     /// Computes parameter information for the given call expression.
-    pub fn call_info(&self, position: FilePosition) -> Cancelable<Option<CallInfo>> {
-        self.with_db(|db| call_info::call_info(db, position))
+    pub fn call_info(
+        &self,
+        position: FilePosition,
+        show_elided_lifetimes: bool,
+    ) -> Cancelable<Option<CallInfo>> {
+        self.with_db(|db| call_info::call_info(db, position, show_elided_lifetimes))
     }
 
     /// Computes call hierarchy candidates for the given file position.
@@ -402,8 +471,12 @@ impl Analysis {
     }
 
     /// Computes incoming calls for the given file position.
-    pub fn incoming_calls(&self, position: FilePosition) -> Cancelable<Option<Vec<CallItem>>> {
-        self.with_db(|db| call_hierarchy::incoming_calls(db, position))
+    pub fn incoming_calls(
+        &self,
+        position: FilePosition,
+        mode: SearchMode,
+    ) -> Cancelable<Option<Vec<CallItem>>> {
+        self.with_db(|db| call_hierarchy::incoming_calls(db, position, mode))
     }
 
     /// Computes incoming calls for the given file position.
@@ -436,6 +509,12 @@ impl Analysis {
         self.with_db(|db| runnables::runnables(db, file_id))
     }
 
+    /// Returns the tests of a crate grouped by module, or `None` if the crate
+    /// has no tests.
+    pub fn test_tree(&self, crate_id: CrateId) -> Cancelable<Option<TestItem>> {
+        self.with_db(|db| test_tree::test_tree(db, crate_id))
+    }
+
     /// Computes syntax highlighting for the given file
     pub fn highlight(&self, file_id: FileId) -> Cancelable<Vec<HighlightedRange>> {
         self.with_db(|db| syntax_highlighting::highlight(db, file_id, None))
@@ -446,6 +525,16 @@ impl Analysis {
         self.with_db(|db| syntax_highlighting::highlight(db, frange.file_id, Some(frange.range)))
     }
 
+    /// Finds the ranges of the file which are written in another language,
+    /// which are the inputs of the given macros, keyed by name.
+    pub fn injected_languages(
+        &self,
+        file_id: FileId,
+        macros: &FxHashMap<String, String>,
+    ) -> Cancelable<Vec<InjectedLanguage>> {
+        self.with_db(|db| syntax_highlighting::injected_languages(db, file_id, macros))
+    }
+
     /// Computes syntax highlighting for the given file.
     pub fn highlight_as_html(&self, file_id: FileId, rainbow: bool) -> Cancelable<String> {
         self.with_db(|db| syntax_highlighting::highlight_as_html(db, file_id, rainbow))
@@ -462,13 +551,28 @@ impl Analysis {
 
     /// Computes assists (aka code actions aka intentions) for the given
     /// position.
-    pub fn assists(&self, frange: FileRange) -> Cancelable<Vec<Assist>> {
-        self.with_db(|db| assists::assists(db, frange))
+    pub fn assists(&self, frange: FileRange, config: &AssistConfig) -> Cancelable<Vec<Assist>> {
+        self.with_db(|db| assists::assists(db, frange, config))
+    }
+
+    /// Computes assists for the given position without computing their edits.
+    pub fn unresolved_assists(
+        &self,
+        frange: FileRange,
+        config: &AssistConfig,
+    ) -> Cancelable<Vec<AssistLabel>> {
+        self.with_db(|db| assists::unresolved_assists(db, frange, config))
     }
 
-    /// Computes the set of diagnostics for the given file.
-    pub fn diagnostics(&self, file_id: FileId) -> Cancelable<Vec<Diagnostic>> {
-        self.with_db(|db| diagnostics::diagnostics(db, file_id))
+    /// Computes the set of diagnostics for the given file. The experimental
+    /// ones, which check for uses of moved values and conflicting borrows,
+    /// are only computed with `enable_experimental`.
+    pub fn diagnostics(
+        &self,
+        file_id: FileId,
+        enable_experimental: bool,
+    ) -> Cancelable<Vec<Diagnostic>> {
+        self.with_db(|db| diagnostics::diagnostics(db, file_id, enable_experimental))
     }
 
     /// Returns the edit required to rename reference at the position to the new
@@ -481,13 +585,25 @@ impl Analysis {
         self.with_db(|db| references::rename(db, position, new_name))
     }
 
+    /// Deletes the item or the function parameter at `position`. If it is
+    /// still used, returns the references which keep it from being deleted.
+    pub fn safe_delete(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Option<RangeInfo<Result<SourceChange, Vec<Reference>>>>> {
+        self.with_db(|db| references::safe_delete(db, position))
+    }
+
+    /// Applies a structural search replace rule to the workspace. The paths of
+    /// the rule are resolved at `position`.
     pub fn structural_search_replace(
         &self,
         query: &str,
         parse_only: bool,
+        position: FilePosition,
     ) -> Cancelable<Result<SourceChange, SsrError>> {
         self.with_db(|db| {
-            let edits = ssr::parse_search_replace(query, parse_only, db)?;
+            let edits = ssr::parse_search_replace(query, parse_only, position, db)?;
             Ok(SourceChange::source_file_edits("ssr", edits))
         })
     }
@@ -575,6 +691,85 @@ struct Foo;
         assert_eq!(struct_match, Some(STRUCT_DEF));
     }
 
+    #[test]
+    fn test_world_symbols_are_ranked_by_match_quality() {
+        let code = r#"
+fn f_o_o() {}
+fn barfoo() {}
+fn foo_bar() {}
+struct Foo;
+fn foo() {}
+        "#;
+
+        let symbols = get_symbols_matching(code, "foo");
+        let names: Vec<_> = symbols.iter().map(|s| s.name().to_string()).collect();
+
+        assert_eq!(names, ["foo", "Foo", "foo_bar", "barfoo", "f_o_o"]);
+    }
+
+    #[test]
+    fn test_world_symbols_limit_keeps_the_best_matches() {
+        let code = r#"
+fn f_o_o() {}
+fn barfoo() {}
+fn foo_bar() {}
+struct Foo;
+fn foo() {}
+        "#;
+
+        let (analysis, _) = single_file(code);
+        let mut query = Query::new("foo".into());
+        query.limit(2);
+        let symbols = analysis.symbol_search(query).unwrap();
+        let names: Vec<_> = symbols.iter().map(|s| s.name().to_string()).collect();
+
+        assert_eq!(names, ["foo", "Foo"]);
+    }
+
+    #[test]
+    fn test_world_symbols_query_operators() {
+        let code = r#"
+fn foo() {}
+struct Foo;
+struct FooBar;
+        "#;
+
+        let names = |query: &str| -> Vec<String> {
+            let (analysis, _) = single_file(code);
+            let symbols = analysis.symbol_search(Query::parse(query)).unwrap();
+            symbols.iter().map(|s| s.name().to_string()).collect()
+        };
+
+        assert_eq!(names("foo#"), ["Foo", "FooBar"]);
+        assert_eq!(names("\"Foo\""), ["Foo"]);
+        assert_eq!(names("\"Foo\" #"), ["Foo"]);
+    }
+
+    #[test]
+    fn test_world_symbols_signature_query() {
+        let code = r#"
+struct Foo;
+impl Foo {
+    fn len(&self) -> usize { 0 }
+    fn parse(s: &str) -> Option<Foo> { None }
+}
+fn count(s: &str) -> usize { 0 }
+fn count_bytes(b: &[u8]) -> usize { 0 }
+fn parse<T: AsRef<str>>(s: T) -> u32 { 0 }
+        "#;
+
+        let names = |query: &str| -> Vec<String> {
+            let (analysis, _) = single_file(code);
+            let symbols = analysis.symbol_search(Query::parse(query)).unwrap();
+            symbols.iter().map(|s| s.name().to_string()).collect()
+        };
+
+        assert_eq!(names("fn(&str) -> usize"), ["count"]);
+        assert_eq!(names("fn(&str)"), ["count", "parse", "parse"]);
+        assert_eq!(names("fn(&Foo) -> usize"), ["len"]);
+        assert_eq!(names("fn(&[u8]) -> usize *"), ["count_bytes"]);
+    }
+
     fn get_symbols_matching(text: &str, query: &str) -> Vec<NavigationTarget> {
         let (analysis, _) = single_file(text);
         analysis.symbol_search(Query::new(query.into())).unwrap()
@@ -1,10 +1,17 @@
-//! FIXME: write short doc here
+//! Finds the brace matching the one at the cursor.
 
-use ra_syntax::{ast::AstNode, SourceFile, SyntaxKind, TextUnit, T};
+use ra_syntax::{
+    ast::{self, AstNode},
+    SourceFile, SyntaxKind, SyntaxToken, TextUnit, T,
+};
 
 pub fn matching_brace(file: &SourceFile, offset: TextUnit) -> Option<TextUnit> {
     const BRACES: &[SyntaxKind] =
         &[T!['{'], T!['}'], T!['['], T![']'], T!['('], T![')'], T![<], T![>]];
+    let pipe = file.syntax().token_at_offset(offset).find(|it| it.kind() == T![|]);
+    if let Some(matching) = pipe.and_then(matching_closure_pipe) {
+        return Some(matching);
+    }
     let (brace_node, brace_idx) = file
         .syntax()
         .token_at_offset(offset)
@@ -19,6 +26,15 @@ pub fn matching_brace(file: &SourceFile, offset: TextUnit) -> Option<TextUnit> {
     Some(matching_node.text_range().start())
 }
 
+/// Closure parameters are delimited by a pair of `|`s, like in `|x| x + 1`.
+fn matching_closure_pipe(pipe: SyntaxToken) -> Option<TextUnit> {
+    let param_list = ast::ParamList::cast(pipe.parent())?;
+    let mut pipes = param_list.syntax().children_with_tokens().filter(|it| it.kind() == T![|]);
+    let (first, second) = (pipes.next()?, pipes.next()?);
+    let matching = if first.as_token() == Some(&pipe) { second } else { first };
+    Some(matching.text_range().start())
+}
+
 #[cfg(test)]
 mod tests {
     use test_utils::{add_cursor, assert_eq_text, extract_offset};
@@ -39,5 +55,14 @@ mod tests {
         }
 
         do_check("struct Foo { a: i32, }<|>", "struct Foo <|>{ a: i32, }");
+        do_check(
+            "fn main() { let f = <|>|x, y| x + y; }",
+            "fn main() { let f = |x, y<|>| x + y; }",
+        );
+        do_check(
+            "fn main() { let f = |x, y<|>| x + y; }",
+            "fn main() { let f = <|>|x, y| x + y; }",
+        );
+        do_check("fn main() { a <|>| b; }", "fn main() { a <|>| b; }");
     }
 }
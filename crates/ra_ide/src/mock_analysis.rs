@@ -7,8 +7,8 @@ use ra_db::{CrateName, Env, RelativePathBuf};
 use test_utils::{extract_offset, extract_range, parse_fixture, CURSOR_MARKER};
 
 use crate::{
-    Analysis, AnalysisChange, AnalysisHost, CrateGraph, Edition::Edition2018, FileId, FilePosition,
-    FileRange, SourceRootId,
+    Analysis, AnalysisChange, AnalysisHost, CrateGraph, Edition, FileId, FilePosition, FileRange,
+    SourceRootId,
 };
 
 /// Mock analysis is used in test to bootstrap an AnalysisHost/Analysis
@@ -16,6 +16,7 @@ use crate::{
 #[derive(Debug, Default)]
 pub struct MockAnalysis {
     files: Vec<(String, String)>,
+    edition: Option<Edition>,
 }
 
 impl MockAnalysis {
@@ -74,6 +75,10 @@ impl MockAnalysis {
         self.files.push((path.to_string(), text));
         FileRange { file_id, range }
     }
+    /// Sets the edition of the crates, which is 2018 by default.
+    pub fn set_edition(&mut self, edition: Edition) {
+        self.edition = Some(edition);
+    }
     pub fn id_of(&self, path: &str) -> FileId {
         let (idx, _) = self
             .files
@@ -89,6 +94,7 @@ impl MockAnalysis {
         let mut change = AnalysisChange::new();
         change.add_root(source_root, true);
         let mut crate_graph = CrateGraph::default();
+        let edition = self.edition.unwrap_or(Edition::Edition2018);
         let mut root_crate = None;
         for (i, (path, contents)) in self.files.into_iter().enumerate() {
             assert!(path.starts_with('/'));
@@ -98,7 +104,7 @@ impl MockAnalysis {
             if path == "/lib.rs" || path == "/main.rs" {
                 root_crate = Some(crate_graph.add_crate_root(
                     file_id,
-                    Edition2018,
+                    edition,
                     None,
                     cfg_options,
                     Env::default(),
@@ -109,7 +115,7 @@ impl MockAnalysis {
                 let crate_name = path.parent().unwrap().file_name().unwrap();
                 let other_crate = crate_graph.add_crate_root(
                     file_id,
-                    Edition2018,
+                    edition,
                     Some(CrateName::new(crate_name).unwrap()),
                     cfg_options,
                     Env::default(),
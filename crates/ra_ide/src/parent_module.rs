@@ -38,6 +38,10 @@ pub(crate) fn parent_module(db: &RootDatabase, position: FilePosition) -> Vec<Na
         None => return Vec::new(),
         Some(it) => it,
     };
+    // The crate root is not declared anywhere in the code
+    if module.parent(db).is_none() {
+        return Vec::new();
+    }
     let nav = NavigationTarget::from_module_to_decl(db, module);
     vec![nav]
 }
@@ -114,6 +118,20 @@ mod tests {
         nav.assert_match("baz MODULE FileId(1) [32; 44)");
     }
 
+    #[test]
+    fn test_no_parent_module_for_crate_root() {
+        let (analysis, pos) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod foo;
+            <|>
+            //- /foo.rs
+            // empty
+            ",
+        );
+        assert!(analysis.parent_module(pos).unwrap().is_empty());
+    }
+
     #[test]
     fn test_resolve_crate_root() {
         let mock = MockAnalysis::with_files(
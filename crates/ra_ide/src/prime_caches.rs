@@ -2,14 +2,69 @@
 //! sometimes is counter productive when, for example, the first goto definition
 //! request takes longer to compute. This modules implemented prepopulating of
 //! various caches, it's not really advanced at the moment.
+//!
+//! Priming collects the def maps of all the crates, which runs name resolution
+//! and expands the macros. The crates are independent enough to be processed
+//! in parallel, salsa blocks a thread which needs a def map which is being
+//! computed by another one.
 
-use hir::Semantics;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::{FileId, RootDatabase};
+use hir::db::DefDatabase;
+use ra_db::{
+    salsa::{self, ParallelDatabase},
+    CrateId, SourceDatabase,
+};
+use ra_prof::profile;
+#[cfg(not(feature = "wasm"))]
+use rayon::prelude::*;
 
-pub(crate) fn prime_caches(db: &RootDatabase, files: Vec<FileId>) {
-    let sema = Semantics::new(db);
-    for file in files {
-        let _ = sema.to_module_def(file);
+use crate::RootDatabase;
+
+#[derive(Debug)]
+pub enum PrimeCachesProgress {
+    Started,
+    /// The def map of a crate was computed.
+    CrateDone {
+        name: String,
+        n_done: usize,
+        n_total: usize,
+    },
+    Finished,
+}
+
+pub(crate) fn prime_caches(db: &RootDatabase, cb: &(dyn Fn(PrimeCachesProgress) + Sync)) {
+    let _p = profile("prime_caches");
+    /// Need to wrap Snapshot to provide `Clone` impl for `for_each_with`
+    struct Snap(salsa::Snapshot<RootDatabase>);
+    impl Clone for Snap {
+        fn clone(&self) -> Snap {
+            Snap(self.0.snapshot())
+        }
     }
+
+    cb(PrimeCachesProgress::Started);
+
+    // Dependencies go first, so that the threads rarely have to wait for each
+    // other.
+    let crates = db.crate_graph().crates_in_topological_order();
+    let n_total = crates.len();
+    let n_done = AtomicUsize::new(0);
+    let prime = |db: &RootDatabase, krate: CrateId| {
+        db.crate_def_map(krate);
+        let name = match &db.crate_graph()[krate].display_name {
+            Some(name) => name.to_string(),
+            None => format!("{:?}", krate),
+        };
+        let n_done = n_done.fetch_add(1, Ordering::SeqCst) + 1;
+        cb(PrimeCachesProgress::CrateDone { name, n_done, n_total });
+    };
+
+    let snap = Snap(db.snapshot());
+    #[cfg(not(feature = "wasm"))]
+    crates.par_iter().for_each_with(snap, |snap, &krate| prime(&snap.0, krate));
+    #[cfg(feature = "wasm")]
+    crates.iter().for_each(|&krate| prime(&snap.0, krate));
+
+    cb(PrimeCachesProgress::Finished);
 }
@@ -10,11 +10,12 @@
 //! resolved to the search element definition, we get a reference.
 
 mod rename;
+mod safe_delete;
 
 use hir::Semantics;
 use ra_ide_db::{
     defs::{classify_name, classify_name_ref, Definition},
-    search::SearchScope,
+    search::{is_shorthand_field_pat, SearchMode, SearchScope},
     RootDatabase,
 };
 use ra_prof::profile;
@@ -26,7 +27,7 @@ use ra_syntax::{
 
 use crate::{display::TryToNav, FilePosition, FileRange, NavigationTarget, RangeInfo};
 
-pub(crate) use self::rename::rename;
+pub(crate) use self::{rename::rename, safe_delete::safe_delete};
 
 pub use ra_ide_db::search::{Reference, ReferenceAccess, ReferenceKind};
 
@@ -34,6 +35,7 @@ pub use ra_ide_db::search::{Reference, ReferenceAccess, ReferenceKind};
 pub struct ReferenceSearchResult {
     declaration: Declaration,
     references: Vec<Reference>,
+    truncated: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +58,12 @@ impl ReferenceSearchResult {
         &self.references
     }
 
+    /// Whether the search was canceled by a change before all the files were
+    /// searched, in which case only some of the references are returned.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
     /// Total number of references
     /// At least 1 since all valid references should
     /// Have a declaration
@@ -89,6 +97,7 @@ pub(crate) fn find_all_refs(
     db: &RootDatabase,
     position: FilePosition,
     search_scope: Option<SearchScope>,
+    mode: SearchMode,
 ) -> Option<RangeInfo<ReferenceSearchResult>> {
     let _p = profile("find_all_refs");
     let sema = Semantics::new(db);
@@ -107,8 +116,10 @@ pub(crate) fn find_all_refs(
 
     let RangeInfo { range, info: def } = find_name(&sema, &syntax, position, opt_name)?;
 
-    let references = def
-        .find_usages(db, search_scope)
+    let usages = def.find_usages_until_canceled(db, search_scope, mode);
+    let truncated = usages.truncated;
+    let references = usages
+        .references
         .into_iter()
         .filter(|r| search_kind == ReferenceKind::Other || search_kind == r.kind)
         .collect();
@@ -117,11 +128,11 @@ pub(crate) fn find_all_refs(
 
     let declaration = Declaration {
         nav: def.try_to_nav(db)?,
-        kind: ReferenceKind::Other,
+        kind: decl_kind(&def, &syntax, decl_range),
         access: decl_access(&def, &syntax, decl_range),
     };
 
-    Some(RangeInfo::new(range, ReferenceSearchResult { declaration, references }))
+    Some(RangeInfo::new(range, ReferenceSearchResult { declaration, references, truncated }))
 }
 
 fn find_name(
@@ -142,6 +153,18 @@ fn find_name(
     Some(RangeInfo::new(range, def))
 }
 
+fn decl_kind(def: &Definition, syntax: &SyntaxNode, range: TextRange) -> ReferenceKind {
+    if let Definition::Local(_) = def {
+        let bind_pat = find_node_at_offset::<ast::BindPat>(syntax, range.start());
+        if let Some(bind_pat) = bind_pat {
+            if is_shorthand_field_pat(&bind_pat) {
+                return ReferenceKind::StructFieldShorthandForLocal;
+            }
+        }
+    }
+    ReferenceKind::Other
+}
+
 fn decl_access(def: &Definition, syntax: &SyntaxNode, range: TextRange) -> Option<ReferenceAccess> {
     match def {
         Definition::Local(_) | Definition::StructField(_) => {}
@@ -194,7 +217,7 @@ mod tests {
 
     use crate::{
         mock_analysis::{analysis_and_position, single_file_with_position, MockAnalysis},
-        Declaration, Reference, ReferenceSearchResult, SearchScope,
+        Declaration, Reference, ReferenceSearchResult, SearchMode, SearchScope,
     };
 
     #[test]
@@ -435,7 +458,7 @@ mod tests {
         "#;
 
         let (analysis, pos) = analysis_and_position(code);
-        let refs = analysis.find_all_refs(pos, None).unwrap().unwrap();
+        let refs = analysis.find_all_refs(pos, None, SearchMode::WithDispatch).unwrap().unwrap();
         check_result(
             refs,
             "Foo STRUCT_DEF FileId(2) [16; 50) [27; 30) Other",
@@ -465,11 +488,11 @@ mod tests {
         "#;
 
         let (analysis, pos) = analysis_and_position(code);
-        let refs = analysis.find_all_refs(pos, None).unwrap().unwrap();
+        let refs = analysis.find_all_refs(pos, None, SearchMode::WithDispatch).unwrap().unwrap();
         check_result(
             refs,
             "foo SOURCE_FILE FileId(2) [0; 35) Other",
-            &["FileId(1) [13; 16) Other"],
+            &["FileId(1) [13; 16) Import"],
         );
     }
 
@@ -494,11 +517,11 @@ mod tests {
         "#;
 
         let (analysis, pos) = analysis_and_position(code);
-        let refs = analysis.find_all_refs(pos, None).unwrap().unwrap();
+        let refs = analysis.find_all_refs(pos, None, SearchMode::WithDispatch).unwrap().unwrap();
         check_result(
             refs,
             "Foo STRUCT_DEF FileId(3) [0; 41) [18; 21) Other",
-            &["FileId(2) [20; 23) Other", "FileId(2) [46; 49) StructLiteral"],
+            &["FileId(2) [20; 23) Import", "FileId(2) [46; 49) StructLiteral"],
         );
     }
 
@@ -522,19 +545,102 @@ mod tests {
         let bar = mock.id_of("/bar.rs");
         let analysis = mock.analysis();
 
-        let refs = analysis.find_all_refs(pos, None).unwrap().unwrap();
+        let refs = analysis.find_all_refs(pos, None, SearchMode::WithDispatch).unwrap().unwrap();
         check_result(
             refs,
             "quux FN_DEF FileId(1) [18; 34) [25; 29) Other",
-            &["FileId(2) [16; 20) StructLiteral", "FileId(3) [16; 20) StructLiteral"],
+            &["FileId(2) [16; 20) FnCall", "FileId(3) [16; 20) FnCall"],
         );
 
-        let refs =
-            analysis.find_all_refs(pos, Some(SearchScope::single_file(bar))).unwrap().unwrap();
+        let refs = analysis
+            .find_all_refs(pos, Some(SearchScope::single_file(bar)), SearchMode::WithDispatch)
+            .unwrap()
+            .unwrap();
         check_result(
             refs,
             "quux FN_DEF FileId(1) [18; 34) [25; 29) Other",
-            &["FileId(3) [16; 20) StructLiteral"],
+            &["FileId(3) [16; 20) FnCall"],
+        );
+    }
+
+    #[test]
+    fn test_find_all_refs_with_crate_scope() {
+        let code = r#"
+            //- /main.rs
+            fn f() { foo::quux(); }
+
+            //- /foo/lib.rs
+            pub fn quux<|>() {}
+            fn g() { quux(); }
+        "#;
+
+        let (analysis, pos) = analysis_and_position(code);
+        let krate = analysis.crate_for(pos.file_id).unwrap()[0];
+        let scope = SearchScope::krate(&*analysis.db, krate.into());
+
+        let refs =
+            analysis.find_all_refs(pos, Some(scope), SearchMode::WithDispatch).unwrap().unwrap();
+        check_result(
+            refs,
+            "quux FN_DEF FileId(2) [0; 16) [7; 11) Other",
+            &["FileId(2) [26; 30) FnCall"],
+        );
+    }
+
+    #[test]
+    fn search_skips_longer_identifiers() {
+        covers!(ra_ide_db::search_skips_longer_identifiers);
+        let code = r#"
+            fn spam<|>() {}
+            fn spam_eggs() {}
+            fn main() {
+                spam();
+                spam_eggs();
+            }
+        "#;
+        let refs = get_all_refs(code);
+        check_result(
+            refs,
+            "spam FN_DEF FileId(1) [13; 25) [16; 20) Other",
+            &["FileId(1) [96; 100) FnCall"],
+        );
+    }
+
+    #[test]
+    fn test_find_all_refs_fn_kinds() {
+        let code = r#"mod foo {
+    pub fn bar<|>() {}
+}
+use foo::bar;
+fn main() {
+    let f = foo::bar;
+    bar();
+}"#;
+
+        let refs = get_all_refs(code);
+        check_result(
+            refs,
+            "bar FN_DEF FileId(1) [14; 29) [21; 24) Other",
+            &["FileId(1) [41; 44) Import", "FileId(1) [75; 78) Other", "FileId(1) [84; 87) FnCall"],
+        );
+    }
+
+    #[test]
+    fn test_find_all_refs_method_call() {
+        let code = r#"struct S;
+impl S {
+    fn m<|>(&self) {}
+}
+fn main() {
+    let mut s = S;
+    s.m();
+}"#;
+
+        let refs = get_all_refs(code);
+        check_result(
+            refs,
+            "m FN_DEF FileId(1) [23; 37) [26; 27) Other",
+            &["FileId(1) [77; 78) FnCall"],
         );
     }
 
@@ -593,6 +699,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_basic_highlight_mut_borrow_write() {
+        let code = r#"fn main() {
+    let mut i<|> = 0;
+    let r = &mut i;
+    let x = &i;
+}"#;
+
+        let refs = get_all_refs(code);
+        check_result(
+            refs,
+            "i BIND_PAT FileId(1) [24; 25) Other Write",
+            &["FileId(1) [48; 49) Other Write", "FileId(1) [64; 65) Other Read"],
+        );
+    }
+
     #[test]
     fn test_basic_highlight_decl_no_write() {
         let code = r#"
@@ -609,9 +731,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_find_all_refs_trait_method_dispatch() {
+        let code = r#"
+        trait Tr { fn f<|>(&self); }
+        struct A;
+        struct B;
+        impl Tr for A { fn f(&self) {} }
+        impl Tr for B { fn f(&self) {} }
+        fn g(a: A, b: &B, d: &dyn Tr) { a.f(); b.f(); d.f(); }
+    "#;
+        let (analysis, pos) = single_file_with_position(code);
+
+        let refs = analysis.find_all_refs(pos, None, SearchMode::WithDispatch).unwrap().unwrap();
+        check_result(
+            refs,
+            "f FN_DEF FileId(1) [20; 32) [23; 24) Other",
+            &[
+                "FileId(1) [195; 196) FnCall",
+                "FileId(1) [202; 203) FnCall",
+                "FileId(1) [209; 210) FnCall",
+            ],
+        );
+
+        let refs = analysis.find_all_refs(pos, None, SearchMode::Exact).unwrap().unwrap();
+        check_result(
+            refs,
+            "f FN_DEF FileId(1) [20; 32) [23; 24) Other",
+            &["FileId(1) [209; 210) FnCall"],
+        );
+    }
+
+    #[test]
+    fn test_find_all_refs_impl_method_dispatch() {
+        let code = r#"
+        trait Tr { fn f(&self); }
+        struct A;
+        struct B;
+        impl Tr for A { fn f<|>(&self) {} }
+        impl Tr for B { fn f(&self) {} }
+        fn g(a: A, b: &B, d: &dyn Tr) { a.f(); b.f(); d.f(); }
+    "#;
+        let (analysis, pos) = single_file_with_position(code);
+
+        let refs = analysis.find_all_refs(pos, None, SearchMode::WithDispatch).unwrap().unwrap();
+        check_result(
+            refs,
+            "f FN_DEF FileId(1) [95; 109) [98; 99) Other",
+            &["FileId(1) [195; 196) FnCall", "FileId(1) [209; 210) FnCall"],
+        );
+
+        let refs = analysis.find_all_refs(pos, None, SearchMode::Exact).unwrap().unwrap();
+        check_result(
+            refs,
+            "f FN_DEF FileId(1) [95; 109) [98; 99) Other",
+            &["FileId(1) [195; 196) FnCall"],
+        );
+    }
+
     fn get_all_refs(text: &str) -> ReferenceSearchResult {
         let (analysis, position) = single_file_with_position(text);
-        analysis.find_all_refs(position, None).unwrap().unwrap()
+        analysis.find_all_refs(position, None, SearchMode::WithDispatch).unwrap().unwrap()
     }
 
     fn check_result(res: ReferenceSearchResult, expected_decl: &str, expected_refs: &[&str]) {
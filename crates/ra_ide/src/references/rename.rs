@@ -1,14 +1,24 @@
-//! FIXME: write short doc here
+//! Renaming of a symbol under the cursor, including all of its references.
+//!
+//! Besides plain identifiers, this handles modules (which also renames the
+//! module file), field shorthands and the `self` parameter.
 
-use hir::{ModuleSource, Semantics};
-use ra_db::{RelativePath, RelativePathBuf, SourceDatabaseExt};
-use ra_ide_db::RootDatabase;
+use hir::{Module, ModuleDef, ModuleSource, Semantics};
+use ra_db::{Canceled, RelativePath, RelativePathBuf, SourceDatabase, SourceDatabaseExt};
+use ra_ide_db::{
+    defs::{classify_name_ref, Definition, NameRefClass},
+    search::{is_shorthand_field_pat, SearchMode},
+    RootDatabase,
+};
 use ra_syntax::{
-    algo::find_node_at_offset, ast, lex_single_valid_syntax_kind, AstNode, SyntaxKind, SyntaxNode,
+    algo::find_node_at_offset,
+    ast::{self, NameOwner, SelfParamKind, TypeAscriptionOwner},
+    lex_single_valid_syntax_kind, AstNode, SyntaxKind, SyntaxNode, SyntaxToken, T,
 };
-use ra_text_edit::TextEdit;
+use ra_text_edit::{TextEdit, TextEditBuilder};
 
 use crate::{
+    format_string::{ArgRef, FormatCall, Placeholder},
     FilePosition, FileSystemEdit, RangeInfo, Reference, ReferenceKind, SourceChange,
     SourceFileEdit, TextRange,
 };
@@ -27,27 +37,94 @@ pub(crate) fn rename(
 
     let sema = Semantics::new(db);
     let source_file = sema.parse(position.file_id);
-    if let Some((ast_name, ast_module)) =
-        find_name_and_module_at_offset(source_file.syntax(), position)
+    if let Some(self_token) = find_self_token_at_offset(source_file.syntax(), position) {
+        rename_self_to_param(db, position, &self_token, new_name)
+    } else if let Some(change) = rename_format_arg(source_file.syntax(), position, new_name) {
+        Some(change)
+    } else if let Some((range, module)) =
+        find_module_at_offset(&sema, source_file.syntax(), position)
     {
-        let range = ast_name.syntax().text_range();
-        rename_mod(&sema, &ast_name, &ast_module, position, new_name)
-            .map(|info| RangeInfo::new(range, info))
+        rename_mod(&sema, module, position, new_name).map(|info| RangeInfo::new(range, info))
     } else {
         rename_reference(sema.db, position, new_name)
     }
 }
 
-fn find_name_and_module_at_offset(
+/// Finds a module either by its declaration (`mod foo;`) or by a path
+/// segment referring to it.
+fn find_module_at_offset(
+    sema: &Semantics<RootDatabase>,
+    syntax: &SyntaxNode,
+    position: FilePosition,
+) -> Option<(TextRange, Module)> {
+    if let Some(ast_name) = find_node_at_offset::<ast::Name>(syntax, position.offset) {
+        let ast_module = ast::Module::cast(ast_name.syntax().parent()?)?;
+        return Some((ast_name.syntax().text_range(), sema.to_def(&ast_module)?));
+    }
+    let name_ref = find_node_at_offset::<ast::NameRef>(syntax, position.offset)?;
+    match classify_name_ref(sema, &name_ref)? {
+        // crate roots (`extern crate foo`) don't have a declaration to rename
+        NameRefClass::Definition(Definition::ModuleDef(ModuleDef::Module(module)))
+            if module.parent(sema.db).is_some() =>
+        {
+            Some((name_ref.syntax().text_range(), module))
+        }
+        _ => None,
+    }
+}
+
+/// Finds a `self` token which refers to the `self` parameter (rather than to
+/// the current module, like in `self::foo`).
+fn find_self_token_at_offset(syntax: &SyntaxNode, position: FilePosition) -> Option<SyntaxToken> {
+    let token = syntax.token_at_offset(position.offset).find(|it| it.kind() == T![self])?;
+    let parent = token.parent();
+    if ast::SelfParam::can_cast(parent.kind()) {
+        return Some(token);
+    }
+    let path = parent.ancestors().find_map(ast::Path::cast)?;
+    if path.qualifier().is_none() && ast::PathExpr::can_cast(path.syntax().parent()?.kind()) {
+        return Some(token);
+    }
+    None
+}
+
+/// Renames a named argument of a `format!`-like macro together with all the
+/// `{name}` placeholders referring to it. Works from either side.
+fn rename_format_arg(
     syntax: &SyntaxNode,
     position: FilePosition,
-) -> Option<(ast::Name, ast::Module)> {
-    let ast_name = find_node_at_offset::<ast::Name>(syntax, position.offset)?;
-    let ast_module = ast::Module::cast(ast_name.syntax().parent()?)?;
-    Some((ast_name, ast_module))
+    new_name: &str,
+) -> Option<RangeInfo<SourceChange>> {
+    let (token, call) = syntax
+        .token_at_offset(position.offset)
+        .find_map(|token| FormatCall::from_token(&token).map(|call| (token, call)))?;
+    let (range, name) = if token.kind() == SyntaxKind::IDENT {
+        (token.text_range(), token.text().clone())
+    } else {
+        match call.placeholder_at(position.offset)? {
+            Placeholder { range, arg: ArgRef::Name(name) } => (*range, name.clone()),
+            _ => return None,
+        }
+    };
+    let arg_name = call.resolve(&ArgRef::Name(name.clone()))?.name.as_ref()?;
+
+    let mut edit_builder = TextEditBuilder::default();
+    edit_builder.replace(arg_name.text_range(), new_name.to_string());
+    call.placeholders
+        .iter()
+        .filter(|it| it.arg == ArgRef::Name(name.clone()))
+        .for_each(|it| edit_builder.replace(it.range, new_name.to_string()));
+
+    let edit = edit_builder.finish();
+    let source_file_edit = SourceFileEdit { file_id: position.file_id, edit };
+    Some(RangeInfo::new(range, SourceChange::source_file_edit("rename", source_file_edit)))
 }
 
-fn source_edit_from_reference(reference: Reference, new_name: &str) -> SourceFileEdit {
+fn source_edit_from_reference(
+    db: &RootDatabase,
+    reference: Reference,
+    new_name: &str,
+) -> SourceFileEdit {
     let mut replacement_text = String::new();
     let file_id = reference.file_range.file_id;
     let range = match reference.kind {
@@ -60,6 +137,9 @@ fn source_edit_from_reference(reference: Reference, new_name: &str) -> SourceFil
             )
         }
         ReferenceKind::StructFieldShorthandForLocal => {
+            if let Some(edit) = shorthand_pat_with_binding_mode(db, &reference, new_name) {
+                return SourceFileEdit { file_id, edit };
+            }
             replacement_text.push_str(": ");
             replacement_text.push_str(new_name);
             TextRange::from_to(reference.file_range.range.end(), reference.file_range.range.end())
@@ -72,71 +152,201 @@ fn source_edit_from_reference(reference: Reference, new_name: &str) -> SourceFil
     SourceFileEdit { file_id, edit: TextEdit::replace(range, replacement_text) }
 }
 
+/// Renames the local bound by `Foo { mut i }` or `Foo { ref i }` to
+/// `Foo { i: mut j }`: the field name goes before the binding mode.
+fn shorthand_pat_with_binding_mode(
+    db: &RootDatabase,
+    reference: &Reference,
+    new_name: &str,
+) -> Option<TextEdit> {
+    let range = reference.file_range.range;
+    let parse = db.parse(reference.file_range.file_id);
+    let bind_pat = find_node_at_offset::<ast::BindPat>(parse.tree().syntax(), range.start())?;
+    let name = bind_pat.name()?;
+    if !is_shorthand_field_pat(&bind_pat)
+        || name.syntax().text_range() != range
+        || (bind_pat.mut_token().is_none() && bind_pat.ref_token().is_none())
+    {
+        return None;
+    }
+    let mut builder = TextEditBuilder::default();
+    builder.insert(bind_pat.syntax().text_range().start(), format!("{}: ", name.text()));
+    builder.replace(range, new_name.to_string());
+    Some(builder.finish())
+}
+
 fn rename_mod(
     sema: &Semantics<RootDatabase>,
-    ast_name: &ast::Name,
-    ast_module: &ast::Module,
+    module: Module,
     position: FilePosition,
     new_name: &str,
 ) -> Option<SourceChange> {
     let mut source_file_edits = Vec::new();
     let mut file_system_edits = Vec::new();
-    if let Some(module) = sema.to_def(ast_module) {
-        let src = module.definition_source(sema.db);
-        let file_id = src.file_id.original_file(sema.db);
-        match src.value {
-            ModuleSource::SourceFile(..) => {
-                let mod_path: RelativePathBuf = sema.db.file_relative_path(file_id);
-                // mod is defined in path/to/dir/mod.rs
-                let dst_path = if mod_path.file_stem() == Some("mod") {
-                    mod_path
-                        .parent()
-                        .and_then(|p| p.parent())
-                        .or_else(|| Some(RelativePath::new("")))
-                        .map(|p| p.join(new_name).join("mod.rs"))
-                } else {
-                    Some(mod_path.with_file_name(new_name).with_extension("rs"))
-                };
-                if let Some(path) = dst_path {
-                    let move_file = FileSystemEdit::MoveFile {
-                        src: file_id,
-                        dst_source_root: sema.db.file_source_root(position.file_id),
-                        dst_path: path,
-                    };
-                    file_system_edits.push(move_file);
-                }
+
+    let src = module.definition_source(sema.db);
+    let file_id = src.file_id.original_file(sema.db);
+    if let ModuleSource::SourceFile(..) = src.value {
+        let mod_path: RelativePathBuf = sema.db.file_relative_path(file_id);
+        let dst_source_root = sema.db.file_source_root(file_id);
+        // mod is defined in path/to/dir/mod.rs
+        if mod_path.file_stem() == Some("mod") {
+            let dir = mod_path.parent().unwrap_or_else(|| RelativePath::new(""));
+            let dst_dir = dir.parent().unwrap_or_else(|| RelativePath::new("")).join(new_name);
+            move_module_dir(sema.db, module, dir, &dst_dir, &mut file_system_edits);
+        } else {
+            let dst_path = mod_path.with_file_name(new_name).with_extension("rs");
+            file_system_edits.push(FileSystemEdit::MoveFile {
+                src: file_id,
+                dst_source_root,
+                dst_path,
+            });
+            // child modules of `foo.rs` live in the `foo/` directory
+            if let Some(stem) = mod_path.file_stem() {
+                let dir = mod_path.with_file_name(stem);
+                let dst_dir = mod_path.with_file_name(new_name);
+                move_module_dir(sema.db, module, &dir, &dst_dir, &mut file_system_edits);
             }
-            ModuleSource::Module(..) => {}
         }
     }
 
-    let edit = SourceFileEdit {
-        file_id: position.file_id,
-        edit: TextEdit::replace(ast_name.syntax().text_range(), new_name.into()),
-    };
-    source_file_edits.push(edit);
+    if let Some(decl) = module.declaration_source(sema.db) {
+        if decl.file_id.call_node(sema.db).is_none() {
+            if let Some(name) = decl.value.name() {
+                source_file_edits.push(SourceFileEdit {
+                    file_id: decl.file_id.original_file(sema.db),
+                    edit: TextEdit::replace(name.syntax().text_range(), new_name.into()),
+                });
+            }
+        }
+    }
 
-    if let Some(RangeInfo { range: _, info: refs }) = find_all_refs(sema.db, position, None) {
+    if let Some(RangeInfo { range: _, info: refs }) =
+        find_all_refs(sema.db, position, None, SearchMode::Exact)
+    {
+        if refs.is_truncated() {
+            Canceled::throw()
+        }
+        let old_name = module.name(sema.db).map(|it| it.to_string()).unwrap_or_default();
         let ref_edits = refs
             .references
             .into_iter()
-            .map(|reference| source_edit_from_reference(reference, new_name));
+            .filter(|reference| is_exact_reference(sema.db, reference, &old_name))
+            .map(|reference| source_edit_from_reference(sema.db, reference, new_name));
         source_file_edits.extend(ref_edits);
     }
 
     Some(SourceChange::from_edits("rename", source_file_edits, file_system_edits))
 }
 
+/// Moves the files of all descendants of `module` which live under `dir` to
+/// `dst_dir`, preserving the layout of the directory.
+fn move_module_dir(
+    db: &RootDatabase,
+    module: Module,
+    dir: &RelativePath,
+    dst_dir: &RelativePath,
+    acc: &mut Vec<FileSystemEdit>,
+) {
+    let mut stack = vec![module];
+    while let Some(module) = stack.pop() {
+        stack.extend(module.children(db));
+        let src = module.definition_source(db);
+        if let ModuleSource::Module(..) = src.value {
+            continue;
+        }
+        let file_id = src.file_id.original_file(db);
+        let path = db.file_relative_path(file_id);
+        if let Ok(rest) = path.strip_prefix(dir) {
+            acc.push(FileSystemEdit::MoveFile {
+                src: file_id,
+                dst_source_root: db.file_source_root(file_id),
+                dst_path: dst_dir.join(rest),
+            });
+        }
+    }
+}
+
+fn rename_self_to_param(
+    db: &RootDatabase,
+    position: FilePosition,
+    self_token: &SyntaxToken,
+    new_name: &str,
+) -> Option<RangeInfo<SourceChange>> {
+    if lex_single_valid_syntax_kind(new_name)? != SyntaxKind::IDENT {
+        return None;
+    }
+
+    let fn_def = self_token.parent().ancestors().find_map(ast::FnDef::cast)?;
+    let self_param = fn_def.param_list()?.self_param()?;
+
+    let mut edit_builder = TextEditBuilder::default();
+    edit_builder
+        .replace(self_param.syntax().text_range(), self_param_replacement(&self_param, new_name));
+    if let Some(body) = fn_def.body() {
+        body.syntax()
+            .descendants_with_tokens()
+            .filter_map(|it| it.into_token())
+            .filter(|it| it.kind() == T![self] && is_self_param_usage(&fn_def, it))
+            .for_each(|it| edit_builder.replace(it.text_range(), new_name.to_string()));
+    }
+
+    let edit = edit_builder.finish();
+    let source_file_edit = SourceFileEdit { file_id: position.file_id, edit };
+    let range = self_token.text_range();
+    Some(RangeInfo::new(range, SourceChange::source_file_edit("rename", source_file_edit)))
+}
+
+/// Checks that `token` is a `self` expression of `fn_def` itself, and not of
+/// a nested item or a `self::` path. `self` tokens inside of macro calls are
+/// assumed to be expressions.
+fn is_self_param_usage(fn_def: &ast::FnDef, token: &SyntaxToken) -> bool {
+    let parent = token.parent();
+    if parent.ancestors().find_map(ast::FnDef::cast).as_ref() != Some(fn_def) {
+        return false;
+    }
+    if parent.kind() == SyntaxKind::TOKEN_TREE {
+        return true;
+    }
+    match parent.ancestors().find_map(ast::Path::cast) {
+        Some(path) => {
+            path.qualifier().is_none()
+                && path.syntax().parent().map_or(false, |it| ast::PathExpr::can_cast(it.kind()))
+        }
+        None => false,
+    }
+}
+
+fn self_param_replacement(self_param: &ast::SelfParam, new_name: &str) -> String {
+    let mut_prefix = if self_param.mut_token().is_some() { "mut " } else { "" };
+    if let Some(ty) = self_param.ascribed_type() {
+        return format!("{}{}: {}", mut_prefix, new_name, ty.syntax());
+    }
+    let lifetime = self_param.lifetime_token().map(|it| format!("{} ", it.text()));
+    let lifetime = lifetime.as_deref().unwrap_or("");
+    match self_param.kind() {
+        SelfParamKind::Owned => format!("{}{}: Self", mut_prefix, new_name),
+        SelfParamKind::Ref => format!("{}: &{}Self", new_name, lifetime),
+        SelfParamKind::MutRef => format!("{}: &{}mut Self", new_name, lifetime),
+    }
+}
+
 fn rename_reference(
     db: &RootDatabase,
     position: FilePosition,
     new_name: &str,
 ) -> Option<RangeInfo<SourceChange>> {
-    let RangeInfo { range, info: refs } = find_all_refs(db, position, None)?;
+    let RangeInfo { range, info: refs } = find_all_refs(db, position, None, SearchMode::Exact)?;
+    // All the references must be renamed.
+    if refs.is_truncated() {
+        Canceled::throw()
+    }
+    let old_name = refs.declaration().nav.name().clone();
 
     let edit = refs
         .into_iter()
-        .map(|reference| source_edit_from_reference(reference, new_name))
+        .filter(|reference| is_exact_reference(db, reference, &old_name))
+        .map(|reference| source_edit_from_reference(db, reference, new_name))
         .collect::<Vec<_>>();
 
     if edit.is_empty() {
@@ -146,6 +356,20 @@ fn rename_reference(
     Some(RangeInfo::new(range, SourceChange::source_file_edits("rename", edit)))
 }
 
+/// References produced by a macro expansion are mapped to the whole macro
+/// call if the name does not appear in the call itself. Such references can't
+/// be renamed without breaking the macro call, so they are skipped.
+fn is_exact_reference(db: &RootDatabase, reference: &Reference, name: &str) -> bool {
+    match reference.kind {
+        ReferenceKind::StructFieldShorthandForField
+        | ReferenceKind::StructFieldShorthandForLocal => return true,
+        _ => (),
+    }
+    let text = db.file_text(reference.file_range.file_id);
+    let text = &text[reference.file_range.range];
+    text.trim_start_matches("r#") == name.trim_start_matches("r#")
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_debug_snapshot;
@@ -351,6 +575,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rename_impl_method_keeps_dynamic_calls() {
+        test_rename(
+            r#"
+    trait Tr { fn f(&self) {} }
+    struct S;
+    impl Tr for S { fn f<|>(&self) {} }
+    struct T;
+    impl Tr for T {}
+    fn main(d: &dyn Tr) {
+        S.f();
+        T.f();
+        d.f();
+    }"#,
+            "g",
+            r#"
+    trait Tr { fn f(&self) {} }
+    struct S;
+    impl Tr for S { fn g(&self) {} }
+    struct T;
+    impl Tr for T {}
+    fn main(d: &dyn Tr) {
+        S.g();
+        T.f();
+        d.f();
+    }"#,
+        );
+    }
+
     #[test]
     fn test_rename_struct_field() {
         test_rename(
@@ -438,6 +691,200 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rename_struct_field_for_shorthand_pat() {
+        test_rename(
+            r#"
+    struct Foo {
+        i<|>: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { i } = foo;
+        i
+    }
+    "#,
+            "j",
+            r#"
+    struct Foo {
+        j: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { j: i } = foo;
+        i
+    }
+    "#,
+        );
+    }
+
+    #[test]
+    fn test_rename_local_for_shorthand_pat() {
+        test_rename(
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { i<|> } = foo;
+        i
+    }
+    "#,
+            "j",
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { i: j } = foo;
+        j
+    }
+    "#,
+        );
+    }
+
+    #[test]
+    fn test_rename_local_for_shorthand_pat_with_binding_mode() {
+        test_rename(
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { mut i<|> } = foo;
+        i += 1;
+        i
+    }
+    "#,
+            "j",
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { i: mut j } = foo;
+        j += 1;
+        j
+    }
+    "#,
+        );
+        test_rename(
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(foo: &Foo) -> i32 {
+        let Foo { ref i<|> } = *foo;
+        *i
+    }
+    "#,
+            "j",
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(foo: &Foo) -> i32 {
+        let Foo { i: ref j } = *foo;
+        *j
+    }
+    "#,
+        );
+    }
+
+    #[test]
+    fn test_rename_format_arg() {
+        test_rename(
+            r#"
+    fn main() {
+        let x = 1;
+        println!("{na<|>me} {0} {name:?}", x, name = x);
+    }"#,
+            "value",
+            r#"
+    fn main() {
+        let x = 1;
+        println!("{value} {0} {value:?}", x, value = x);
+    }"#,
+        );
+        test_rename(
+            r#"
+    fn main() {
+        let x = 1;
+        println!("{name} {name:?}", na<|>me = x);
+    }"#,
+            "value",
+            r#"
+    fn main() {
+        let x = 1;
+        println!("{value} {value:?}", value = x);
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_self_to_param() {
+        test_rename(
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    impl Foo {
+        fn f(&self<|>) -> i32 {
+            self.i
+        }
+    }
+    "#,
+            "foo",
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    impl Foo {
+        fn f(foo: &Self) -> i32 {
+            foo.i
+        }
+    }
+    "#,
+        );
+    }
+
+    #[test]
+    fn test_rename_self_to_param_from_usage() {
+        test_rename(
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    impl Foo {
+        fn f(mut self) -> i32 {
+            self<|>.i
+        }
+    }
+    "#,
+            "foo",
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    impl Foo {
+        fn f(mut foo: Self) -> i32 {
+            foo.i
+        }
+    }
+    "#,
+        );
+    }
+
     #[test]
     fn test_field_shorthand_correct_struct() {
         test_rename(
@@ -558,6 +1005,7 @@ mod tests {
                         },
                     ],
                     cursor_position: None,
+                    is_snippet: false,
                 },
             },
         )
@@ -610,6 +1058,7 @@ mod tests {
                         },
                     ],
                     cursor_position: None,
+                    is_snippet: false,
                 },
             },
         )
@@ -706,6 +1155,143 @@ mod tests {
                         },
                     ],
                     cursor_position: None,
+                    is_snippet: false,
+                },
+            },
+        )
+        "###);
+    }
+
+    #[test]
+    fn test_rename_mod_from_path() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod foo;
+            fn f() {
+                foo<|>::bar();
+            }
+
+            //- /foo.rs
+            pub fn bar() {}
+            ",
+        );
+        let new_name = "foo2";
+        let source_change = analysis.rename(position, new_name).unwrap();
+        assert_debug_snapshot!(&source_change,
+@r###"
+        Some(
+            RangeInfo {
+                range: [22; 25),
+                info: SourceChange {
+                    label: "rename",
+                    source_file_edits: [
+                        SourceFileEdit {
+                            file_id: FileId(
+                                1,
+                            ),
+                            edit: TextEdit {
+                                atoms: [
+                                    AtomTextEdit {
+                                        delete: [4; 7),
+                                        insert: "foo2",
+                                    },
+                                ],
+                            },
+                        },
+                        SourceFileEdit {
+                            file_id: FileId(
+                                1,
+                            ),
+                            edit: TextEdit {
+                                atoms: [
+                                    AtomTextEdit {
+                                        delete: [22; 25),
+                                        insert: "foo2",
+                                    },
+                                ],
+                            },
+                        },
+                    ],
+                    file_system_edits: [
+                        MoveFile {
+                            src: FileId(
+                                2,
+                            ),
+                            dst_source_root: SourceRootId(
+                                0,
+                            ),
+                            dst_path: "foo2.rs",
+                        },
+                    ],
+                    cursor_position: None,
+                    is_snippet: false,
+                },
+            },
+        )
+        "###);
+    }
+
+    #[test]
+    fn test_rename_mod_with_child_dir() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod fo<|>o;
+
+            //- /foo.rs
+            mod bar;
+
+            //- /foo/bar.rs
+            // empty
+            ",
+        );
+        let new_name = "foo2";
+        let source_change = analysis.rename(position, new_name).unwrap();
+        assert_debug_snapshot!(&source_change,
+@r###"
+        Some(
+            RangeInfo {
+                range: [4; 7),
+                info: SourceChange {
+                    label: "rename",
+                    source_file_edits: [
+                        SourceFileEdit {
+                            file_id: FileId(
+                                1,
+                            ),
+                            edit: TextEdit {
+                                atoms: [
+                                    AtomTextEdit {
+                                        delete: [4; 7),
+                                        insert: "foo2",
+                                    },
+                                ],
+                            },
+                        },
+                    ],
+                    file_system_edits: [
+                        MoveFile {
+                            src: FileId(
+                                2,
+                            ),
+                            dst_source_root: SourceRootId(
+                                0,
+                            ),
+                            dst_path: "foo2.rs",
+                        },
+                        MoveFile {
+                            src: FileId(
+                                3,
+                            ),
+                            dst_source_root: SourceRootId(
+                                0,
+                            ),
+                            dst_path: "foo2/bar.rs",
+                        },
+                    ],
+                    cursor_position: None,
+                    is_snippet: false,
                 },
             },
         )
@@ -0,0 +1,272 @@
+//! Deletes an item, but only if nothing refers to it anymore.
+//!
+//! Deleting an unused parameter of a function also drops the corresponding
+//! argument from all the calls of the function.
+
+use hir::{ModuleDef, Semantics};
+use ra_ide_db::{
+    defs::{classify_name, Definition},
+    RootDatabase,
+};
+use ra_syntax::{
+    algo::{find_node_at_offset, non_trivia_sibling},
+    ast::{self, ArgListOwner},
+    AstNode, Direction, SyntaxKind, SyntaxNode, TextRange, T,
+};
+use ra_text_edit::TextEdit;
+
+use crate::{FileId, FilePosition, RangeInfo, Reference, SourceChange, SourceFileEdit};
+
+/// Deletes the item or parameter whose name is at `position`. If it is still
+/// referred to, returns the references which keep it from being deleted.
+pub(crate) fn safe_delete(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Result<SourceChange, Vec<Reference>>>> {
+    let sema = Semantics::new(db);
+    let source_file = sema.parse(position.file_id);
+    let name = find_node_at_offset::<ast::Name>(source_file.syntax(), position.offset)?;
+    let res = match name.syntax().parent().and_then(ast::BindPat::cast) {
+        Some(bind_pat) => delete_param(&sema, position.file_id, bind_pat)?,
+        None => delete_item(&sema, position.file_id, &name)?,
+    };
+    Some(RangeInfo::new(name.syntax().text_range(), res))
+}
+
+fn delete_item(
+    sema: &Semantics<RootDatabase>,
+    file_id: FileId,
+    name: &ast::Name,
+) -> Option<Result<SourceChange, Vec<Reference>>> {
+    let node = name.syntax().parent()?;
+    let delete_range = match node.kind() {
+        SyntaxKind::FN_DEF
+        | SyntaxKind::STRUCT_DEF
+        | SyntaxKind::ENUM_DEF
+        | SyntaxKind::UNION_DEF
+        | SyntaxKind::TRAIT_DEF
+        | SyntaxKind::TYPE_ALIAS_DEF
+        | SyntaxKind::CONST_DEF
+        | SyntaxKind::STATIC_DEF => {
+            // Removing an item of a trait or of a trait impl breaks the impls.
+            if is_in_trait_or_trait_impl(&node) {
+                return None;
+            }
+            item_range(&node)
+        }
+        SyntaxKind::ENUM_VARIANT | SyntaxKind::RECORD_FIELD_DEF => list_item_range(&node),
+        _ => return None,
+    };
+    let def = classify_name(sema, name)?.into_definition()?;
+
+    // References from inside the item, like recursive calls, go away with it.
+    let item_range = node.text_range();
+    let blockers: Vec<Reference> = def
+        .find_usages(sema.db, None)
+        .into_iter()
+        .filter(|it| {
+            it.file_range.file_id != file_id || !it.file_range.range.is_subrange(&item_range)
+        })
+        .collect();
+    if !blockers.is_empty() {
+        return Some(Err(blockers));
+    }
+    let edit = TextEdit::delete(delete_range);
+    Some(Ok(SourceChange::source_file_edit_from("safe delete", file_id, edit)))
+}
+
+fn delete_param(
+    sema: &Semantics<RootDatabase>,
+    file_id: FileId,
+    bind_pat: ast::BindPat,
+) -> Option<Result<SourceChange, Vec<Reference>>> {
+    let param = bind_pat.syntax().parent().and_then(ast::Param::cast)?;
+    let param_list = param.syntax().parent().and_then(ast::ParamList::cast)?;
+    let fn_def = param_list.syntax().parent().and_then(ast::FnDef::cast)?;
+    // The other impls of the method would still have the parameter.
+    if is_in_trait_or_trait_impl(fn_def.syntax()) {
+        return None;
+    }
+    let idx = param_list.params().position(|it| it == param)?;
+    // Calls like `S::method(s, arg)` pass the receiver as the first argument.
+    let ufcs_idx = if param_list.self_param().is_some() { idx + 1 } else { idx };
+
+    let local = sema.to_def(&bind_pat)?;
+    let mut blockers = Definition::Local(local).find_usages(sema.db, None);
+    let mut edits =
+        vec![SourceFileEdit { file_id, edit: TextEdit::delete(list_item_range(param.syntax())) }];
+    let func = sema.to_def(&fn_def)?;
+    for reference in Definition::ModuleDef(ModuleDef::Function(func)).find_usages(sema.db, None) {
+        // References which aren't calls, like `map(f)`, can't be fixed up.
+        match argument_at(sema, &reference, idx, ufcs_idx) {
+            Some(arg) => edits.push(SourceFileEdit {
+                file_id: reference.file_range.file_id,
+                edit: TextEdit::delete(list_item_range(arg.syntax())),
+            }),
+            None => blockers.push(reference),
+        }
+    }
+    if !blockers.is_empty() {
+        return Some(Err(blockers));
+    }
+    Some(Ok(SourceChange::source_file_edits("safe delete", edits)))
+}
+
+/// Finds the argument passed for the parameter if `reference` is the callee
+/// of a call.
+fn argument_at(
+    sema: &Semantics<RootDatabase>,
+    reference: &Reference,
+    idx: usize,
+    ufcs_idx: usize,
+) -> Option<ast::Expr> {
+    let source_file = sema.parse(reference.file_range.file_id);
+    let name_ref = find_node_at_offset::<ast::NameRef>(
+        source_file.syntax(),
+        reference.file_range.range.start(),
+    )?;
+    if let Some(call) = name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast) {
+        return call.arg_list()?.args().nth(idx);
+    }
+    let path_expr = name_ref.syntax().ancestors().find_map(ast::PathExpr::cast)?;
+    let call = path_expr.syntax().parent().and_then(ast::CallExpr::cast)?;
+    if call.expr()?.syntax() != path_expr.syntax() {
+        return None;
+    }
+    call.arg_list()?.args().nth(ufcs_idx)
+}
+
+fn is_in_trait_or_trait_impl(node: &SyntaxNode) -> bool {
+    let container = match node.parent().filter(|it| it.kind() == SyntaxKind::ITEM_LIST) {
+        Some(item_list) => item_list.parent(),
+        None => return false,
+    };
+    match container {
+        Some(it) if ast::TraitDef::can_cast(it.kind()) => true,
+        Some(it) => ast::ImplDef::cast(it).map_or(false, |it| it.target_trait().is_some()),
+        None => false,
+    }
+}
+
+/// The range of an item, together with the whitespace after it.
+fn item_range(node: &SyntaxNode) -> TextRange {
+    let range = node.text_range();
+    match node.next_sibling_or_token().filter(|it| it.kind() == SyntaxKind::WHITESPACE) {
+        Some(ws) => TextRange::from_to(range.start(), ws.text_range().end()),
+        None => range,
+    }
+}
+
+/// The range of a member of a comma separated list, together with the comma
+/// which separates it from the next member, or from the previous one if it
+/// is the last.
+fn list_item_range(node: &SyntaxNode) -> TextRange {
+    let range = node.text_range();
+    let comma_after =
+        non_trivia_sibling(node.clone().into(), Direction::Next).filter(|it| it.kind() == T![,]);
+    let next = comma_after
+        .clone()
+        .and_then(|it| non_trivia_sibling(it, Direction::Next))
+        .filter(|it| !matches!(it.kind(), T![')'] | T!['}']));
+    if let Some(next) = next {
+        return TextRange::from_to(range.start(), next.text_range().start());
+    }
+    let end = comma_after.map_or(range.end(), |it| it.text_range().end());
+    match non_trivia_sibling(node.clone().into(), Direction::Prev).filter(|it| it.kind() == T![,]) {
+        Some(comma_before) => TextRange::from_to(comma_before.text_range().start(), end),
+        None => TextRange::from_to(range.start(), end),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_text_edit::TextEditBuilder;
+    use test_utils::assert_eq_text;
+
+    use crate::mock_analysis::single_file_with_position;
+
+    fn check_safe_delete(before: &str, after: &str) {
+        let (analysis, position) = single_file_with_position(before);
+        let change = analysis.safe_delete(position).unwrap().unwrap().info.unwrap();
+        let mut builder = TextEditBuilder::default();
+        for edit in change.source_file_edits {
+            for atom in edit.edit.as_atoms() {
+                builder.replace(atom.delete, atom.insert.clone());
+            }
+        }
+        let actual = builder.finish().apply(&analysis.file_text(position.file_id).unwrap());
+        assert_eq_text!(after, &*actual);
+    }
+
+    fn check_blockers(text: &str, expected: usize) {
+        let (analysis, position) = single_file_with_position(text);
+        let blockers = analysis.safe_delete(position).unwrap().unwrap().info.unwrap_err();
+        assert_eq!(blockers.len(), expected);
+    }
+
+    #[test]
+    fn deletes_unused_function() {
+        check_safe_delete("fn foo<|>() { foo() }\n\nfn main() {}\n", "fn main() {}\n");
+    }
+
+    #[test]
+    fn lists_references_which_block_deletion() {
+        check_blockers("fn foo<|>() {}\n\nfn main() { foo(); foo(); }\n", 2);
+    }
+
+    #[test]
+    fn deletes_unused_enum_variant() {
+        check_safe_delete("enum E { A, B<|>, C }", "enum E { A, C }");
+        check_safe_delete("enum E { A, B<|> }", "enum E { A }");
+    }
+
+    #[test]
+    fn deletes_unused_field() {
+        check_safe_delete("struct S { a: u32, b<|>: u32 }", "struct S { a: u32 }");
+        check_blockers("struct S { a<|>: u32 }\nfn f(s: S) -> u32 { s.a }", 1);
+    }
+
+    #[test]
+    fn not_applicable_to_trait_impl_items() {
+        let (analysis, position) = single_file_with_position(
+            "trait T { fn f(); }\nstruct S;\nimpl T for S { fn f<|>() {} }\n",
+        );
+        assert!(analysis.safe_delete(position).unwrap().is_none());
+    }
+
+    #[test]
+    fn deletes_unused_param_and_arguments() {
+        check_safe_delete(
+            r#"
+struct S;
+impl S {
+    fn foo(&self, a: u32, b<|>: u32) -> u32 { a }
+}
+fn bar(s: S) {
+    s.foo(1, 2);
+    S::foo(&s, 3, 4);
+}
+"#,
+            r#"
+struct S;
+impl S {
+    fn foo(&self, a: u32) -> u32 { a }
+}
+fn bar(s: S) {
+    s.foo(1);
+    S::foo(&s, 3);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn used_param_is_not_deleted() {
+        check_blockers("fn foo(a<|>: u32) -> u32 { a }\nfn main() { foo(1); }", 1);
+    }
+
+    #[test]
+    fn param_of_function_used_as_value_is_not_deleted() {
+        check_blockers("fn foo(_a<|>: u32) {}\nfn main() { Some(1).map(foo); }", 1);
+    }
+}
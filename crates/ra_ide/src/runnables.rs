@@ -4,7 +4,7 @@ use hir::Semantics;
 use itertools::Itertools;
 use ra_ide_db::RootDatabase;
 use ra_syntax::{
-    ast::{self, AstNode, AttrsOwner, ModuleItemOwner, NameOwner},
+    ast::{self, AstNode, AttrsOwner, DocCommentsOwner, ModuleItemOwner, NameOwner},
     match_ast, SyntaxNode, TextRange,
 };
 
@@ -37,6 +37,7 @@ pub enum RunnableKind {
     Test { test_id: TestId },
     TestMod { path: String },
     Bench { test_id: TestId },
+    DocTest { test_id: TestId },
     Bin,
 }
 
@@ -51,6 +52,13 @@ fn runnable(sema: &Semantics<RootDatabase>, item: SyntaxNode) -> Option<Runnable
         match item {
             ast::FnDef(it) => runnable_fn(sema, it),
             ast::Module(it) => runnable_mod(sema, it),
+            ast::StructDef(it) => runnable_doc_test(sema, &it),
+            ast::EnumDef(it) => runnable_doc_test(sema, &it),
+            ast::UnionDef(it) => runnable_doc_test(sema, &it),
+            ast::TraitDef(it) => runnable_doc_test(sema, &it),
+            ast::TypeAliasDef(it) => runnable_doc_test(sema, &it),
+            ast::ConstDef(it) => runnable_doc_test(sema, &it),
+            ast::StaticDef(it) => runnable_doc_test(sema, &it),
             _ => None,
         }
     }
@@ -80,6 +88,8 @@ fn runnable_fn(sema: &Semantics<RootDatabase>, fn_def: ast::FnDef) -> Option<Run
             RunnableKind::Test { test_id }
         } else if fn_def.has_atom_attr("bench") {
             RunnableKind::Bench { test_id }
+        } else if has_doc_test(&fn_def) {
+            RunnableKind::DocTest { test_id: TestId::Path(doc_test_path(sema, &fn_def)?) }
         } else {
             return None;
         }
@@ -93,7 +103,7 @@ fn runnable_fn(sema: &Semantics<RootDatabase>, fn_def: ast::FnDef) -> Option<Run
 ///
 /// It may produce false positives, for example, `#[wasm_bindgen_test]` requires a different command to run the test,
 /// but it's better than not to have the runnables for the tests at all.
-fn has_test_related_attribute(fn_def: &ast::FnDef) -> bool {
+pub(crate) fn has_test_related_attribute(fn_def: &ast::FnDef) -> bool {
     fn_def
         .attrs()
         .filter_map(|attr| attr.path())
@@ -101,6 +111,110 @@ fn has_test_related_attribute(fn_def: &ast::FnDef) -> bool {
         .any(|attribute_text| attribute_text.contains("test"))
 }
 
+fn runnable_doc_test<N: DocCommentsOwner + NameOwner>(
+    sema: &Semantics<RootDatabase>,
+    item: &N,
+) -> Option<Runnable> {
+    if !has_doc_test(item) {
+        return None;
+    }
+    let test_id = TestId::Path(doc_test_path(sema, item)?);
+    Some(Runnable { range: item.syntax().text_range(), kind: RunnableKind::DocTest { test_id } })
+}
+
+/// Rustdoc names doc tests after the path of the documented item, without
+/// the crate name. Associated items are qualified by the type or the trait,
+/// like `module::Type::method`, so the path is what `cargo test --doc`
+/// filters by.
+fn doc_test_path<N: NameOwner>(sema: &Semantics<RootDatabase>, item: &N) -> Option<String> {
+    let module = sema.scope(item.syntax()).module()?;
+    let mut path = module_path(sema, module);
+
+    let container = item.syntax().parent().and_then(|it| it.parent());
+    if let Some(container) = container {
+        match_ast! {
+            match container {
+                ast::ImplDef(it) => {
+                    let self_ty = match it.target_type()? {
+                        ast::TypeRef::PathType(it) => it.path()?,
+                        _ => return None,
+                    };
+                    // The self type is named by its own module rather than a
+                    // relative path, but keeps its generic arguments, like
+                    // `m::Wrapper<T>`.
+                    let adt = sema.to_def(&it)?.target_ty(sema.db).as_adt()?;
+                    let generic_args = self_ty
+                        .segment()?
+                        .type_arg_list()
+                        .map_or(String::new(), |it| it.syntax().to_string());
+                    path = module_path(sema, adt.module(sema.db));
+                    path.push(format!("{}{}", adt.name(sema.db), generic_args));
+                },
+                ast::TraitDef(it) => path.push(it.name()?.text().to_string()),
+                _ => (),
+            }
+        }
+    }
+
+    path.push(item.name()?.text().to_string());
+    Some(path.join("::"))
+}
+
+/// The names of the modules from the crate root to `module`.
+fn module_path(sema: &Semantics<RootDatabase>, module: hir::Module) -> Vec<String> {
+    module
+        .path_to_root(sema.db)
+        .into_iter()
+        .rev()
+        .filter_map(|it| it.name(sema.db))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Checks whether the doc comment of the item contains a code block which
+/// rustdoc would run as a test.
+fn has_doc_test(item: &impl DocCommentsOwner) -> bool {
+    let docs = match item.doc_comment_text() {
+        Some(it) => it,
+        None => return false,
+    };
+    let mut in_code_block = false;
+    for line in docs.lines() {
+        let line = line.trim_start();
+        if !line.starts_with("```") {
+            continue;
+        }
+        if in_code_block {
+            in_code_block = false;
+            continue;
+        }
+        in_code_block = true;
+        if is_doc_test_fence(&line["```".len()..]) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Code blocks are tested by rustdoc unless they are marked `ignore` or are
+/// written in some other language, like `text`.
+fn is_doc_test_fence(info: &str) -> bool {
+    const RUSTDOC_FENCE_TOKENS: &[&str] = &[
+        "",
+        "rust",
+        "should_panic",
+        "no_run",
+        "compile_fail",
+        "allow_fail",
+        "test_harness",
+        "edition2015",
+        "edition2018",
+        "edition2021",
+    ];
+    info.split(|c| c == ',' || c == ' ' || c == '\t')
+        .all(|token| RUSTDOC_FENCE_TOKENS.contains(&token.trim()))
+}
+
 fn runnable_mod(sema: &Semantics<RootDatabase>, module: ast::Module) -> Option<Runnable> {
     let has_test_function = module
         .item_list()?
@@ -125,7 +239,7 @@ fn runnable_mod(sema: &Semantics<RootDatabase>, module: ast::Module) -> Option<R
 mod tests {
     use insta::assert_debug_snapshot;
 
-    use crate::mock_analysis::analysis_and_position;
+    use crate::{mock_analysis::analysis_and_position, RunnableKind};
 
     #[test]
     fn test_runnables() {
@@ -172,6 +286,46 @@ mod tests {
                 );
     }
 
+    #[test]
+    fn test_runnables_doc_test() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+        //- /lib.rs
+        <|> //empty
+        fn main() {}
+
+        /// ```
+        /// let x = 5;
+        /// ```
+        fn foo() {}
+
+        /// ```text
+        /// not a test
+        /// ```
+        fn bar() {}
+        "#,
+        );
+        let runnables = analysis.runnables(pos.file_id).unwrap();
+        assert_debug_snapshot!(&runnables,
+        @r###"
+        [
+            Runnable {
+                range: [1; 21),
+                kind: Bin,
+            },
+            Runnable {
+                range: [23; 65),
+                kind: DocTest {
+                    test_id: Path(
+                        "foo",
+                    ),
+                },
+            },
+        ]
+        "###
+                );
+    }
+
     #[test]
     fn test_runnables_module() {
         let (analysis, pos) = analysis_and_position(
@@ -297,4 +451,88 @@ mod tests {
         let runnables = analysis.runnables(pos.file_id).unwrap();
         assert!(runnables.is_empty())
     }
+
+    #[test]
+    fn test_runnables_doc_test_paths() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+        //- /lib.rs
+        <|> //empty
+        /// ```
+        /// let x = 5;
+        /// ```
+        struct Data;
+        impl Data {
+            /// ```
+            /// let x = 5;
+            /// ```
+            fn foo() {}
+        }
+        trait Tr {
+            /// ```
+            /// let x = 5;
+            /// ```
+            fn bar();
+        }
+        mod m {
+            /// ```
+            /// let x = 5;
+            /// ```
+            const C: u32 = 0;
+            pub struct Wrapper<T>(T);
+        }
+        impl<T> m::Wrapper<T> {
+            /// ```
+            /// let x = 5;
+            /// ```
+            fn baz() {}
+        }
+        "#,
+        );
+        let runnables = analysis.runnables(pos.file_id).unwrap();
+        let paths: Vec<String> = runnables
+            .iter()
+            .map(|it| match &it.kind {
+                RunnableKind::DocTest { test_id } => test_id.to_string(),
+                kind => panic!("unexpected runnable: {:?}", kind),
+            })
+            .collect();
+        assert_eq!(paths, ["Data", "Data::foo", "Tr::bar", "m::C", "m::Wrapper<T>::baz"]);
+    }
+
+    #[test]
+    fn test_runnables_doc_test_paths_resolve_self_type() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+        //- /lib.rs
+        <|> //empty
+        mod m {
+            pub struct Data;
+            mod n {
+                impl super::Data {
+                    /// ```
+                    /// let x = 5;
+                    /// ```
+                    fn foo() {}
+                }
+            }
+        }
+        impl crate::m::Data {
+            /// ```
+            /// let x = 5;
+            /// ```
+            fn bar() {}
+        }
+        "#,
+        );
+        let runnables = analysis.runnables(pos.file_id).unwrap();
+        let paths: Vec<String> = runnables
+            .iter()
+            .map(|it| match &it.kind {
+                RunnableKind::DocTest { test_id } => test_id.to_string(),
+                kind => panic!("unexpected runnable: {:?}", kind),
+            })
+            .collect();
+        assert_eq!(paths, ["m::Data::foo", "m::Data::bar"]);
+    }
 }
@@ -4,7 +4,7 @@
 //! It can be viewed as a dual for `AnalysisChange`.
 
 use ra_db::RelativePathBuf;
-use ra_text_edit::TextEdit;
+use ra_text_edit::{TextEdit, TextEditBuilder};
 
 use crate::{FileId, FilePosition, SourceRootId, TextUnit};
 
@@ -14,6 +14,8 @@ pub struct SourceChange {
     pub source_file_edits: Vec<SourceFileEdit>,
     pub file_system_edits: Vec<FileSystemEdit>,
     pub cursor_position: Option<FilePosition>,
+    /// The inserted text uses the LSP snippet syntax, see `without_snippets`.
+    pub is_snippet: bool,
 }
 
 impl SourceChange {
@@ -29,6 +31,7 @@ impl SourceChange {
             source_file_edits,
             file_system_edits,
             cursor_position: None,
+            is_snippet: false,
         }
     }
 
@@ -40,6 +43,7 @@ impl SourceChange {
             source_file_edits: edits,
             file_system_edits: vec![],
             cursor_position: None,
+            is_snippet: false,
         }
     }
 
@@ -51,6 +55,7 @@ impl SourceChange {
             source_file_edits: vec![],
             file_system_edits: edits,
             cursor_position: None,
+            is_snippet: false,
         }
     }
 
@@ -87,6 +92,79 @@ impl SourceChange {
         self.cursor_position = cursor_position;
         self
     }
+
+    /// Turns snippet edits into plain ones for clients which don't support
+    /// snippets, moving the cursor to the first `$0` tab stop instead.
+    pub fn without_snippets(mut self) -> SourceChange {
+        if !self.is_snippet {
+            return self;
+        }
+        self.is_snippet = false;
+        for file_edit in self.source_file_edits.iter_mut() {
+            let mut builder = TextEditBuilder::default();
+            // The length difference of the edited text caused by the atoms
+            // before the current one.
+            let mut delta = 0i64;
+            for atom in file_edit.edit.as_atoms() {
+                let (text, cursor) = strip_snippet(&atom.insert);
+                if let (Some(cursor), None) = (cursor, self.cursor_position) {
+                    let start = atom.delete.start().to_usize() as i64 + delta;
+                    let offset = TextUnit::from_usize(start as usize) + cursor;
+                    self.cursor_position =
+                        Some(FilePosition { file_id: file_edit.file_id, offset });
+                }
+                delta += text.len() as i64 - atom.delete.len().to_usize() as i64;
+                builder.replace(atom.delete, text);
+            }
+            file_edit.edit = builder.finish();
+        }
+        self
+    }
+}
+
+/// Removes the escapes and tab stops from `snippet`, returning the plain text
+/// and the offset of the `$0` tab stop in it.
+fn strip_snippet(snippet: &str) -> (String, Option<TextUnit>) {
+    let mut text = String::with_capacity(snippet.len());
+    let mut cursor = None;
+    // The number of `${N:placeholder}` tab stops we are inside of.
+    let mut depth = 0;
+    let mut chars = snippet.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    text.push(c);
+                }
+            }
+            '$' => {
+                let has_braces = chars.peek() == Some(&'{');
+                if has_braces {
+                    chars.next();
+                }
+                let mut tab_stop = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_digit() {
+                        break;
+                    }
+                    tab_stop.push(c);
+                    chars.next();
+                }
+                if tab_stop == "0" && cursor.is_none() {
+                    cursor = Some(TextUnit::of_str(&text));
+                }
+                if has_braces {
+                    if chars.peek() == Some(&':') {
+                        chars.next();
+                    }
+                    depth += 1;
+                }
+            }
+            '}' if depth > 0 => depth -= 1,
+            _ => text.push(c),
+        }
+    }
+    (text, cursor)
 }
 
 #[derive(Debug)]
@@ -114,6 +192,50 @@ impl SingleFileChange {
             source_file_edits: vec![SourceFileEdit { file_id, edit: self.edit }],
             file_system_edits: Vec::new(),
             cursor_position: self.cursor_position.map(|offset| FilePosition { file_id, offset }),
+            is_snippet: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::TextRange;
+    use ra_text_edit::{TextEdit, TextEditBuilder};
+
+    use super::{strip_snippet, SourceChange};
+    use crate::FileId;
+
+    #[test]
+    fn strips_snippets() {
+        assert_eq!(
+            strip_snippet("fn f() {\n    $0\n}"),
+            ("fn f() {\n    \n}".to_string(), Some(13.into()))
+        );
+        assert_eq!(
+            strip_snippet("fn f() { ${0:unimplemented!()} }"),
+            ("fn f() { unimplemented!() }".to_string(), Some(9.into()))
+        );
+        assert_eq!(
+            strip_snippet(r#"let s = "\$x\{\}\\";"#),
+            (r#"let s = "$x{}\";"#.to_string(), None)
+        );
+    }
+
+    #[test]
+    fn without_snippets_moves_cursor_to_tab_stop() {
+        let file_id = FileId(1);
+        let mut edit = TextEditBuilder::default();
+        edit.replace(TextRange::from_to(0.into(), 3.into()), "x\\$y".to_string());
+        edit.insert(10.into(), "fn f() { ${0:unimplemented!()} }".to_string());
+        let mut change = SourceChange::source_file_edit_from("add", file_id, edit.finish());
+        change.is_snippet = true;
+
+        let change = change.without_snippets();
+        assert!(!change.is_snippet);
+        let edit: &TextEdit = &change.source_file_edits[0].edit;
+        let inserts: Vec<&str> = edit.as_atoms().iter().map(|it| it.insert.as_str()).collect();
+        assert_eq!(inserts, ["x$y", "fn f() { unimplemented!() }"]);
+        let cursor = change.cursor_position.unwrap();
+        assert_eq!((cursor.file_id, cursor.offset), (file_id, 19.into()));
+    }
+}
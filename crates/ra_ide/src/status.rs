@@ -31,12 +31,16 @@ pub(crate) fn status(db: &RootDatabase) -> String {
     let syntax_tree_stats = syntax_tree_stats(db);
     let macro_syntax_tree_stats = macro_syntax_tree_stats(db);
     let symbols_stats = db.query(LibrarySymbolsQuery).entries::<LibrarySymbolsStats>();
+    let def_map_stats = db.query(hir::db::CrateDefMapQueryQuery).entries::<EntryStats>();
+    let infer_stats = db.query(hir::db::InferQueryQuery).entries::<EntryStats>();
     format!(
-        "{}\n{}\n{}\n{} (macros)\n\n\nmemory:\n{}\ngc {:?} seconds ago",
+        "{}\n{}\n{}\n{} (macros)\n{} def maps\n{} inference results\n\n\nmemory:\n{}\ngc {:?} seconds ago",
         files_stats,
         symbols_stats,
         syntax_tree_stats,
         macro_syntax_tree_stats,
+        def_map_stats,
+        infer_stats,
         memory_usage(),
         db.last_gc.elapsed().as_secs(),
     )
@@ -108,6 +112,33 @@ impl<M> FromIterator<TableEntry<MacroFile, Option<(Parse<SyntaxNode>, M)>>> for
     }
 }
 
+/// Counts the entries of any query, and the ones which still hold a value.
+#[derive(Default)]
+struct EntryStats {
+    total: usize,
+    retained: usize,
+}
+
+impl fmt::Display for EntryStats {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{} ({} retained)", self.total, self.retained)
+    }
+}
+
+impl<K, V> FromIterator<TableEntry<K, V>> for EntryStats {
+    fn from_iter<T>(iter: T) -> EntryStats
+    where
+        T: IntoIterator<Item = TableEntry<K, V>>,
+    {
+        let mut res = EntryStats::default();
+        for entry in iter {
+            res.total += 1;
+            res.retained += entry.value.is_some() as usize;
+        }
+        res
+    }
+}
+
 #[derive(Default)]
 struct LibrarySymbolsStats {
     total: usize,
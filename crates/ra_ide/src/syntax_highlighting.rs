@@ -2,26 +2,33 @@
 
 mod tags;
 mod html;
+mod injection;
 #[cfg(test)]
 mod tests;
 
 use hir::{Name, Semantics};
+use ra_db::Edition;
 use ra_ide_db::{
     defs::{classify_name, classify_name_ref, Definition, NameClass, NameRefClass},
     RootDatabase,
 };
 use ra_prof::profile;
 use ra_syntax::{
-    ast::{self, HasQuotes, HasStringValue},
-    AstNode, AstToken, Direction, NodeOrToken, SyntaxElement,
-    SyntaxKind::*,
-    SyntaxToken, TextRange, WalkEvent, T,
+    ast, AstNode, AstToken, Direction, NodeOrToken, SyntaxElement, SyntaxKind::*, SyntaxToken,
+    TextRange, WalkEvent, T,
 };
 use rustc_hash::FxHashMap;
 
-use crate::{call_info::call_info_for_token, Analysis, FileId};
+use crate::{
+    format_string::{is_format_string, lex_format_string, FormatPart},
+    FileId,
+};
+
+use injection::{doc_comment_injections, highlight_injection};
 
 pub(crate) use html::highlight_as_html;
+pub(crate) use injection::injected_languages;
+pub use injection::InjectedLanguage;
 pub use tags::{Highlight, HighlightModifier, HighlightModifiers, HighlightTag};
 
 #[derive(Debug, Clone)]
@@ -54,12 +61,16 @@ pub(crate) fn highlight(
         }
     };
 
+    let edition = sema.to_module_def(file_id).map(|it| it.krate().edition(db));
     let mut bindings_shadow_count: FxHashMap<Name, u32> = FxHashMap::default();
     // We use a stack for the DFS traversal below.
     // When we leave a node, the we use it to flatten the highlighted ranges.
     let mut res: Vec<Vec<HighlightedRange>> = vec![Vec::new()];
 
     let mut current_macro_call: Option<ast::MacroCall> = None;
+    // The highlights of the doc comments with code blocks, keyed by the range
+    // of the comment.
+    let mut doc_injections: FxHashMap<TextRange, Vec<HighlightedRange>> = FxHashMap::default();
 
     // Walk all nodes, keeping track of whether we are inside a macro or not.
     // If in macro, expand it first and highlight the expanded code.
@@ -142,18 +153,30 @@ pub(crate) fn highlight(
 
         let range = element.text_range();
 
+        if current_macro_call.is_none() {
+            if let Some(injections) = element.as_node().and_then(doc_comment_injections) {
+                doc_injections.extend(injections);
+            }
+            if let Some(highlights) = doc_injections.remove(&range) {
+                current.extend(highlights);
+                continue;
+            }
+        }
+
+        let mut from_expansion = false;
         let element_to_highlight = if current_macro_call.is_some() {
             // Inside a macro -- expand it first
             let token = match element.clone().into_token() {
                 Some(it) if it.parent().kind() == TOKEN_TREE => it,
                 _ => continue,
             };
-            let token = sema.descend_into_macros(token.clone());
-            let parent = token.parent();
+            let expanded = sema.descend_into_macros(token.clone());
+            from_expansion = expanded != token;
+            let parent = expanded.parent();
             // We only care Name and Name_ref
-            match (token.kind(), parent.kind()) {
+            match (expanded.kind(), parent.kind()) {
                 (IDENT, NAME) | (IDENT, NAME_REF) => parent.into(),
-                _ => token.into(),
+                _ => expanded.into(),
             }
         } else {
             element.clone()
@@ -166,9 +189,16 @@ pub(crate) fn highlight(
             }
         }
 
-        if let Some((highlight, binding_hash)) =
-            highlight_element(&sema, &mut bindings_shadow_count, element_to_highlight)
+        if let Some((mut highlight, binding_hash)) =
+            highlight_element(&sema, edition, &mut bindings_shadow_count, element_to_highlight)
         {
+            if from_expansion {
+                highlight |= HighlightModifier::MacroExpansion;
+            }
+            if let Some(string) = element.as_token().filter(|it| is_format_string(it)) {
+                highlight_format_string(current, string, highlight);
+                continue;
+            }
             current.push(HighlightedRange { range, highlight, binding_hash });
         }
     }
@@ -199,8 +229,42 @@ fn macro_call_range(macro_call: &ast::MacroCall) -> Option<TextRange> {
     Some(TextRange::from_to(range_start, range_end))
 }
 
+/// Highlights the placeholders and escape sequences of a format string, and
+/// the rest of it with `highlight`.
+fn highlight_format_string(
+    acc: &mut Vec<HighlightedRange>,
+    string: &SyntaxToken,
+    highlight: Highlight,
+) {
+    let parts = lex_format_string(string).map(|it| it.parts).unwrap_or_default();
+    let mut start = string.text_range().start();
+    for (range, part) in parts {
+        if start < range.start() {
+            let range = TextRange::from_to(start, range.start());
+            acc.push(HighlightedRange { range, highlight, binding_hash: None });
+        }
+        let tag = match part {
+            FormatPart::Specifier => HighlightTag::FormatSpecifier,
+            FormatPart::Integer => HighlightTag::NumericLiteral,
+            FormatPart::Identifier => HighlightTag::Local,
+            FormatPart::Escape => HighlightTag::EscapeSequence,
+        };
+        acc.push(HighlightedRange { range, highlight: tag.into(), binding_hash: None });
+        start = range.end();
+    }
+    let end = string.text_range().end();
+    if start < end {
+        acc.push(HighlightedRange {
+            range: TextRange::from_to(start, end),
+            highlight,
+            binding_hash: None,
+        });
+    }
+}
+
 fn highlight_element(
     sema: &Semantics<RootDatabase>,
+    edition: Option<Edition>,
     bindings_shadow_count: &mut FxHashMap<Name, u32>,
     element: SyntaxElement,
 ) -> Option<(Highlight, Option<u64>)> {
@@ -270,6 +334,19 @@ fn highlight_element(
             }
         }
 
+        // Dereferencing a raw pointer is an unsafe operation
+        T![*] => {
+            let prefix_expr = element.parent().and_then(ast::PrefixExpr::cast)?;
+            let ty = sema.type_of_expr(&prefix_expr.expr()?)?;
+            if !ty.is_raw_ptr() {
+                return None;
+            }
+            HighlightTag::Operator | HighlightModifier::Unsafe
+        }
+
+        // These are only reserved since the 2018 edition, in 2015 crates they
+        // are plain identifiers.
+        T![async] | T![await] | T![try] if edition == Some(Edition::Edition2015) => return None,
         k if k.is_keyword() => {
             let h = Highlight::new(HighlightTag::Keyword);
             match k {
@@ -311,13 +388,26 @@ fn highlight_name(db: &RootDatabase, def: Definition) -> Highlight {
         Definition::StructField(_) => HighlightTag::Field,
         Definition::ModuleDef(def) => match def {
             hir::ModuleDef::Module(_) => HighlightTag::Module,
-            hir::ModuleDef::Function(_) => HighlightTag::Function,
+            hir::ModuleDef::Function(func) => {
+                let mut h = Highlight::new(HighlightTag::Function);
+                if func.is_unsafe(db) {
+                    h |= HighlightModifier::Unsafe;
+                }
+                return h;
+            }
             hir::ModuleDef::Adt(hir::Adt::Struct(_)) => HighlightTag::Struct,
             hir::ModuleDef::Adt(hir::Adt::Enum(_)) => HighlightTag::Enum,
             hir::ModuleDef::Adt(hir::Adt::Union(_)) => HighlightTag::Union,
             hir::ModuleDef::EnumVariant(_) => HighlightTag::EnumVariant,
             hir::ModuleDef::Const(_) => HighlightTag::Constant,
-            hir::ModuleDef::Static(_) => HighlightTag::Static,
+            hir::ModuleDef::Static(s) => {
+                let mut h = Highlight::new(HighlightTag::Static);
+                if s.is_mut(db) {
+                    h |= HighlightModifier::Mutable;
+                    h |= HighlightModifier::Unsafe;
+                }
+                return h;
+            }
             hir::ModuleDef::Trait(_) => HighlightTag::Trait,
             hir::ModuleDef::TypeAlias(_) => HighlightTag::TypeAlias,
             hir::ModuleDef::BuiltinType(_) => HighlightTag::BuiltinType,
@@ -355,44 +445,3 @@ fn highlight_name_by_syntax(name: ast::Name) -> Highlight {
         _ => default,
     }
 }
-
-fn highlight_injection(
-    acc: &mut Vec<HighlightedRange>,
-    sema: &Semantics<RootDatabase>,
-    literal: ast::RawString,
-    expanded: SyntaxToken,
-) -> Option<()> {
-    let call_info = call_info_for_token(&sema, expanded)?;
-    let idx = call_info.active_parameter?;
-    let name = call_info.signature.parameter_names.get(idx)?;
-    if !name.starts_with("ra_fixture") {
-        return None;
-    }
-    let value = literal.value()?;
-    let (analysis, tmp_file_id) = Analysis::from_single_file(value);
-
-    if let Some(range) = literal.open_quote_text_range() {
-        acc.push(HighlightedRange {
-            range,
-            highlight: HighlightTag::StringLiteral.into(),
-            binding_hash: None,
-        })
-    }
-
-    for mut h in analysis.highlight(tmp_file_id).unwrap() {
-        if let Some(r) = literal.map_range_up(h.range) {
-            h.range = r;
-            acc.push(h)
-        }
-    }
-
-    if let Some(range) = literal.close_quote_text_range() {
-        acc.push(HighlightedRange {
-            range,
-            highlight: HighlightTag::StringLiteral.into(),
-            binding_hash: None,
-        })
-    }
-
-    Some(())
-}
@@ -0,0 +1,203 @@
+//! Highlighting of code embedded in other code: Rust in `ra_fixture` string
+//! literals and in doc comment code blocks, and other languages in the token
+//! trees of macros like `html!`.
+
+use hir::Semantics;
+use ra_ide_db::RootDatabase;
+use ra_syntax::{
+    ast::{self, AstToken, HasStringValue},
+    AstNode, SyntaxNode, SyntaxToken, TextRange, TextUnit,
+};
+use rustc_hash::FxHashMap;
+
+use crate::{call_info::call_info_for_token, Analysis, FileId};
+
+use super::{HighlightTag, HighlightedRange};
+
+/// A range of a file which is written in another language, like the body of
+/// `html! { <p>"hello"</p> }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectedLanguage {
+    pub range: TextRange,
+    /// The language id, as configured for the macro.
+    pub language: String,
+}
+
+/// Finds the calls of the given macros, which map a macro name to the
+/// language its input is written in.
+pub(crate) fn injected_languages(
+    db: &RootDatabase,
+    file_id: FileId,
+    macros: &FxHashMap<String, String>,
+) -> Vec<InjectedLanguage> {
+    if macros.is_empty() {
+        return Vec::new();
+    }
+    let sema = Semantics::new(db);
+    let source_file = sema.parse(file_id);
+    source_file
+        .syntax()
+        .descendants()
+        .filter_map(ast::MacroCall::cast)
+        .filter_map(|macro_call| {
+            let name_ref = macro_call.path()?.segment()?.name_ref()?;
+            let language = macros.get(name_ref.text().as_str())?;
+            let token_tree = macro_call.token_tree()?;
+            let start = token_tree.left_delimiter_token()?.text_range().end();
+            let end = match token_tree.right_delimiter_token() {
+                Some(it) => it.text_range().start(),
+                None => token_tree.syntax().text_range().end(),
+            };
+            Some(InjectedLanguage {
+                range: TextRange::from_to(start, end),
+                language: language.clone(),
+            })
+        })
+        .collect()
+}
+
+pub(super) fn highlight_injection(
+    acc: &mut Vec<HighlightedRange>,
+    sema: &Semantics<RootDatabase>,
+    literal: ast::RawString,
+    expanded: SyntaxToken,
+) -> Option<()> {
+    let call_info = call_info_for_token(&sema, expanded, false)?;
+    let idx = call_info.active_parameter?;
+    let name = call_info.signature.parameter_names.get(idx)?;
+    if !name.starts_with("ra_fixture") {
+        return None;
+    }
+    let value = literal.value()?;
+    let (analysis, tmp_file_id) = Analysis::from_single_file(value);
+
+    if let Some(range) = literal.open_quote_text_range() {
+        acc.push(HighlightedRange {
+            range,
+            highlight: HighlightTag::StringLiteral.into(),
+            binding_hash: None,
+        })
+    }
+
+    for mut h in analysis.highlight(tmp_file_id).unwrap() {
+        if let Some(r) = literal.map_range_up(h.range) {
+            h.range = r;
+            acc.push(h)
+        }
+    }
+
+    if let Some(range) = literal.close_quote_text_range() {
+        acc.push(HighlightedRange {
+            range,
+            highlight: HighlightTag::StringLiteral.into(),
+            binding_hash: None,
+        })
+    }
+
+    Some(())
+}
+
+/// Highlights the Rust code blocks in the doc comments of `node` as Rust.
+/// The highlights are keyed by the range of the `///` comment they belong
+/// to, and replace the highlighting of the whole comment.
+pub(super) fn doc_comment_injections(
+    node: &SyntaxNode,
+) -> Option<FxHashMap<TextRange, Vec<HighlightedRange>>> {
+    let docs = ast::Docs::from_node(node)?;
+    // Block comments would need their ` * ` prefixes highlighted line by line,
+    // so only line comments are supported.
+    let comments: Vec<TextRange> = node
+        .children_with_tokens()
+        .filter_map(|it| it.into_token().and_then(ast::Comment::cast))
+        .filter(|it| it.kind().doc.is_some() && it.kind().shape.is_line())
+        .map(|it| it.syntax().text_range())
+        .collect();
+    if comments.is_empty() {
+        return None;
+    }
+
+    // The code lines, as their range in the docs and their offset in `code`.
+    let mut lines: Vec<(TextRange, TextUnit)> = Vec::new();
+    let mut code = String::new();
+    let mut in_rust_block = None;
+    let mut offset = TextUnit::from_usize(0);
+    for line in docs.text().split('\n') {
+        let line_start = offset;
+        offset += TextUnit::of_str(line) + TextUnit::of_char('\n');
+
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_rust_block = match in_rust_block {
+                Some(_) => None,
+                None => Some(is_rust_fence(&trimmed["```".len()..])),
+            };
+            continue;
+        }
+        if in_rust_block != Some(true) {
+            continue;
+        }
+        // rustdoc hides the lines starting with `# `, but they are still code.
+        let mut skip = 0;
+        if trimmed == "#" || trimmed.starts_with("# ") {
+            skip = line.len() - trimmed.len() + trimmed.len().min(2);
+        }
+        let range = TextRange::from_to(
+            line_start + TextUnit::from_usize(skip),
+            line_start + TextUnit::of_str(line),
+        );
+        lines.push((range, TextUnit::of_str(&code)));
+        code.push_str(&line[skip..]);
+        code.push('\n');
+    }
+    if lines.is_empty() {
+        return None;
+    }
+
+    // Like rustdoc, wrap the code in a `main` function if there is none, so
+    // that statements are highlighted as such.
+    let prefix = if code.contains("fn main") { "" } else { "fn main() {\n" };
+    let suffix = if prefix.is_empty() { "" } else { "}" };
+    let (analysis, tmp_file_id) =
+        Analysis::from_single_file(format!("{}{}{}", prefix, code, suffix));
+    let highlights = analysis.highlight(tmp_file_id).unwrap();
+
+    let mut res: FxHashMap<TextRange, Vec<HighlightedRange>> = FxHashMap::default();
+    for (docs_range, code_offset) in lines {
+        let source_range = match docs.map_range_up(docs_range) {
+            Some(it) if it.len() == docs_range.len() => it,
+            _ => continue,
+        };
+        let comment = match comments.iter().find(|it| source_range.is_subrange(it)) {
+            Some(it) => *it,
+            None => continue,
+        };
+        let acc = res.entry(comment).or_default();
+        acc.push(HighlightedRange {
+            range: TextRange::from_to(comment.start(), source_range.start()),
+            highlight: HighlightTag::Comment.into(),
+            binding_hash: None,
+        });
+
+        let code_range =
+            TextRange::offset_len(TextUnit::of_str(prefix) + code_offset, docs_range.len());
+        for h in highlights.iter().filter(|it| it.range.is_subrange(&code_range)) {
+            let start = source_range.start() + (h.range.start() - code_range.start());
+            acc.push(HighlightedRange {
+                range: TextRange::offset_len(start, h.range.len()),
+                ..h.clone()
+            });
+        }
+    }
+    Some(res)
+}
+
+/// Whether rustdoc treats a code block with the given info string, like
+/// `should_panic` in ```` ```should_panic ````, as Rust code.
+fn is_rust_fence(info: &str) -> bool {
+    const RUSTDOC_ATTRIBUTES: &[&str] =
+        &["rust", "ignore", "should_panic", "no_run", "compile_fail", "allow_fail", "test_harness"];
+
+    info.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|it| !it.is_empty())
+        .all(|it| RUSTDOC_ATTRIBUTES.contains(&it) || it.starts_with("edition"))
+}
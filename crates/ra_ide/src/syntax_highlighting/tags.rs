@@ -22,13 +22,16 @@ pub enum HighlightTag {
     Constant,
     Enum,
     EnumVariant,
+    EscapeSequence,
     Field,
+    FormatSpecifier,
     Function,
     Keyword,
     Lifetime,
     Macro,
     Module,
     NumericLiteral,
+    Operator,
     SelfType,
     Static,
     StringLiteral,
@@ -49,7 +52,11 @@ pub enum HighlightModifier {
     /// not.
     Definition,
     Mutable,
+    /// Used for operations which require an `unsafe` block, like calls to
+    /// `unsafe fn`s, dereferencing raw pointers and accessing `static mut`s.
     Unsafe,
+    /// The token comes from the expansion of a macro call.
+    MacroExpansion,
 }
 
 impl HighlightTag {
@@ -63,13 +70,16 @@ impl HighlightTag {
             HighlightTag::Constant => "constant",
             HighlightTag::Enum => "enum",
             HighlightTag::EnumVariant => "enum_variant",
+            HighlightTag::EscapeSequence => "escape_sequence",
             HighlightTag::Field => "field",
+            HighlightTag::FormatSpecifier => "format_specifier",
             HighlightTag::Function => "function",
             HighlightTag::Keyword => "keyword",
             HighlightTag::Lifetime => "lifetime",
             HighlightTag::Macro => "macro",
             HighlightTag::Module => "module",
             HighlightTag::NumericLiteral => "numeric_literal",
+            HighlightTag::Operator => "operator",
             HighlightTag::SelfType => "self_type",
             HighlightTag::Static => "static",
             HighlightTag::StringLiteral => "string_literal",
@@ -95,6 +105,7 @@ impl HighlightModifier {
         HighlightModifier::Definition,
         HighlightModifier::Mutable,
         HighlightModifier::Unsafe,
+        HighlightModifier::MacroExpansion,
     ];
 
     fn as_str(self) -> &'static str {
@@ -103,6 +114,7 @@ impl HighlightModifier {
             HighlightModifier::Definition => "declaration",
             HighlightModifier::Mutable => "mutable",
             HighlightModifier::Unsafe => "unsafe",
+            HighlightModifier::MacroExpansion => "macro_expansion",
         }
     }
 
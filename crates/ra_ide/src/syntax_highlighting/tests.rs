@@ -1,10 +1,11 @@
 use std::fs;
 
+use rustc_hash::FxHashMap;
 use test_utils::{assert_eq_text, project_dir, read_text};
 
 use crate::{
     mock_analysis::{single_file, MockAnalysis},
-    FileRange, TextRange,
+    Edition, FileRange, TextRange,
 };
 
 #[test]
@@ -99,6 +100,32 @@ fn bar() {
     assert_eq_text!(expected_html, actual_html);
 }
 
+#[test]
+fn test_unsafe_highlighting() {
+    let (analysis, file_id) = single_file(
+        r#"
+unsafe fn unsafe_fn() {}
+
+static mut GLOBAL: u32 = 0;
+
+fn main() {
+    let x = &5 as *const usize;
+    unsafe {
+        unsafe_fn();
+        GLOBAL = 1;
+        let y = *x;
+    }
+}
+"#
+        .trim(),
+    );
+    let dst_file = project_dir().join("crates/ra_ide/src/snapshots/highlight_unsafe.html");
+    let actual_html = &analysis.highlight_as_html(file_id, false).unwrap();
+    let expected_html = &read_text(&dst_file);
+    fs::write(dst_file, &actual_html).unwrap();
+    assert_eq_text!(expected_html, actual_html);
+}
+
 #[test]
 fn accidentally_quadratic() {
     let file = project_dir().join("crates/ra_syntax/test_data/accidentally_quadratic");
@@ -113,6 +140,27 @@ fn accidentally_quadratic() {
     // eprintln!("elapsed: {:?}", t.elapsed());
 }
 
+#[test]
+fn test_keywords_depend_on_edition() {
+    let keywords = |edition| {
+        let mut mock = MockAnalysis::new();
+        mock.set_edition(edition);
+        let file_id = mock.add_file("/main.rs", "async fn foo() {}");
+        let analysis = mock.analysis();
+        let text = analysis.file_text(file_id).unwrap();
+        analysis
+            .highlight(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.highlight.to_string() == "keyword")
+            .map(|it| text[it.range].to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(keywords(Edition::Edition2015), ["fn"]);
+    assert_eq!(keywords(Edition::Edition2018), ["async", "fn"]);
+}
+
 #[test]
 fn test_ranges() {
     let (analysis, file_id) = single_file(
@@ -156,3 +204,68 @@ fn main() {
     fs::write(dst_file, &actual_html).unwrap();
     assert_eq_text!(expected_html, actual_html);
 }
+
+#[test]
+fn test_doc_comment_injection() {
+    let (analysis, file_id) = single_file(
+        r#"
+/// Creates a `Foo`:
+///
+/// ```
+/// let foo = Foo::new();
+/// # drop(foo);
+/// ```
+///
+/// ```text
+/// let not_rust = 1;
+/// ```
+struct Foo;
+"#
+        .trim(),
+    );
+    let text = analysis.file_text(file_id).unwrap();
+    let highlights: Vec<(&str, String)> = analysis
+        .highlight(file_id)
+        .unwrap()
+        .into_iter()
+        .map(|it| (&text[it.range], it.highlight.to_string()))
+        .collect();
+
+    assert!(highlights.contains(&("/// ", "comment".to_string())));
+    assert!(highlights.contains(&("foo", "variable.declaration".to_string())));
+    assert!(highlights.contains(&("/// # ", "comment".to_string())));
+    assert!(highlights.contains(&("foo", "variable".to_string())));
+    assert!(highlights.contains(&("/// ```text", "comment".to_string())));
+    assert!(highlights.contains(&("/// let not_rust = 1;", "comment".to_string())));
+    let keywords = highlights.iter().filter(|(_, h)| h == "keyword").collect::<Vec<_>>();
+    assert_eq!(keywords, [&("let", "keyword".to_string()), &("struct", "keyword".to_string())]);
+}
+
+#[test]
+fn test_injected_languages() {
+    let (analysis, file_id) = single_file(
+        r#"
+fn main() {
+    let page = html! { <p>"hello"</p> };
+    let query = sql!("SELECT 1");
+    let v = vec![1, 2];
+}
+"#
+        .trim(),
+    );
+    let mut macros = FxHashMap::default();
+    macros.insert("html".to_string(), "html".to_string());
+    macros.insert("sql".to_string(), "sql".to_string());
+
+    let text = analysis.file_text(file_id).unwrap();
+    let injections: Vec<(&str, String)> = analysis
+        .injected_languages(file_id, &macros)
+        .unwrap()
+        .into_iter()
+        .map(|it| (&text[it.range], it.language))
+        .collect();
+    assert_eq!(
+        injections,
+        [(r#" <p>"hello"</p> "#, "html".to_string()), (r#""SELECT 1""#, "sql".to_string())]
+    );
+}
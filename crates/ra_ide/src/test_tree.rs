@@ -0,0 +1,158 @@
+//! The tests of a crate, grouped by module, for test explorers.
+
+use hir::{Crate, HasSource, Module, ModuleDef};
+use ra_db::{CrateId, SourceDatabase};
+use ra_ide_db::RootDatabase;
+
+use crate::{display::ToNav, runnables::has_test_related_attribute, NavigationTarget};
+
+#[derive(Debug)]
+pub struct TestItem {
+    pub kind: TestItemKind,
+    pub name: String,
+    /// The path relative to the crate root, which is also the name libtest
+    /// reports a test under. Empty for the crate itself.
+    pub path: String,
+    pub nav: NavigationTarget,
+    pub children: Vec<TestItem>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestItemKind {
+    Crate,
+    Module,
+    Test,
+}
+
+pub(crate) fn test_tree(db: &RootDatabase, crate_id: CrateId) -> Option<TestItem> {
+    // The tests are usually in `#[cfg(test)]` modules, which only the test
+    // crate of a lib or bin has.
+    let crate_id = db.crate_graph().test_crate_of(crate_id).unwrap_or(crate_id);
+    let krate = Crate::from(crate_id);
+    let root = krate.root_module(db)?;
+    let children = module_tests(db, root, "");
+    if children.is_empty() {
+        return None;
+    }
+    Some(TestItem {
+        kind: TestItemKind::Crate,
+        name: krate.display_name(db).unwrap_or_default(),
+        path: String::new(),
+        nav: root.to_nav(db),
+        children,
+    })
+}
+
+/// Collects the tests of `module`, and of the submodules which contain any.
+fn module_tests(db: &RootDatabase, module: Module, module_path: &str) -> Vec<TestItem> {
+    let path_of = |name: &str| {
+        if module_path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}::{}", module_path, name)
+        }
+    };
+
+    let mut res = Vec::new();
+    for def in module.declarations(db) {
+        let function = match def {
+            ModuleDef::Function(it) => it,
+            _ => continue,
+        };
+        if !has_test_related_attribute(&function.source(db).value) {
+            continue;
+        }
+        let name = function.name(db).to_string();
+        res.push(TestItem {
+            kind: TestItemKind::Test,
+            path: path_of(&name),
+            name,
+            nav: function.to_nav(db),
+            children: Vec::new(),
+        });
+    }
+    for child in module.children(db) {
+        let name = match child.name(db) {
+            Some(it) => it.to_string(),
+            None => continue,
+        };
+        let path = path_of(&name);
+        let children = module_tests(db, child, &path);
+        if children.is_empty() {
+            continue;
+        }
+        res.push(TestItem {
+            kind: TestItemKind::Module,
+            name,
+            path,
+            nav: child.to_nav(db),
+            children,
+        });
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mock_analysis::analysis_and_position, TestItem};
+
+    fn render(item: &TestItem, indent: usize, buf: &mut String) {
+        buf.push_str(&format!("{}{:?} {}\n", " ".repeat(indent), item.kind, item.path));
+        for child in item.children.iter() {
+            render(child, indent + 2, buf);
+        }
+    }
+
+    #[test]
+    fn test_tree_groups_tests_by_module() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+            //- /lib.rs
+            <|>mod foo;
+
+            #[test]
+            fn root_test() {}
+
+            fn not_a_test() {}
+
+            mod tests {
+                #[test]
+                fn bar() {}
+
+                mod nested {
+                    #[tokio::test]
+                    async fn baz() {}
+                }
+            }
+
+            mod no_tests {
+                fn helper() {}
+            }
+
+            //- /foo.rs
+            #[test]
+            fn in_file() {}
+            "#,
+        );
+        let krate = analysis.crate_for(pos.file_id).unwrap()[0];
+        let tree = analysis.test_tree(krate).unwrap().unwrap();
+        let mut actual = String::new();
+        render(&tree, 0, &mut actual);
+        assert_eq!(
+            actual,
+            "Crate \n  Test root_test\n  Module foo\n    Test foo::in_file\n  Module tests\n    Test tests::bar\n    Module tests::nested\n      Test tests::nested::baz\n"
+        );
+    }
+
+    #[test]
+    fn test_tree_is_empty_without_tests() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+            //- /lib.rs
+            <|>fn main() {}
+            "#,
+        );
+        let krate = analysis.crate_for(pos.file_id).unwrap()[0];
+        assert!(analysis.test_tree(krate).unwrap().is_none());
+    }
+}
@@ -58,15 +58,23 @@ fn on_char_typed_inner(
 }
 
 /// Returns an edit which should be applied after `=` was typed. Primarily,
-/// this works when adding `let =`.
+/// this works when adding `let =`, and for `const` and `static` items.
 // FIXME: use a snippet completion instead of this hack here.
 fn on_eq_typed(file: &SourceFile, offset: TextUnit) -> Option<SingleFileChange> {
     assert_eq!(file.syntax().text().char_at(offset), Some('='));
-    let let_stmt: ast::LetStmt = find_node_at_offset(file.syntax(), offset)?;
-    if let_stmt.semicolon_token().is_some() {
+    let (node, semicolon, initializer) =
+        if let Some(let_stmt) = find_node_at_offset::<ast::LetStmt>(file.syntax(), offset) {
+            (let_stmt.syntax().clone(), let_stmt.semicolon_token(), let_stmt.initializer())
+        } else if let Some(konst) = find_node_at_offset::<ast::ConstDef>(file.syntax(), offset) {
+            (konst.syntax().clone(), konst.semicolon_token(), konst.body())
+        } else {
+            let statik: ast::StaticDef = find_node_at_offset(file.syntax(), offset)?;
+            (statik.syntax().clone(), statik.semicolon_token(), statik.body())
+        };
+    if semicolon.is_some() {
         return None;
     }
-    if let Some(expr) = let_stmt.initializer() {
+    if let Some(expr) = initializer {
         let expr_range = expr.syntax().text_range();
         if expr_range.contains(offset) && offset != expr_range.start() {
             return None;
@@ -77,7 +85,7 @@ fn on_eq_typed(file: &SourceFile, offset: TextUnit) -> Option<SingleFileChange>
     } else {
         return None;
     }
-    let offset = let_stmt.syntax().text_range().end();
+    let offset = node.text_range().end();
     Some(SingleFileChange {
         label: "add semicolon".to_string(),
         edit: TextEdit::insert(offset, ";".to_string()),
@@ -208,6 +216,34 @@ fn foo() {
         // ");
     }
 
+    #[test]
+    fn adds_semicolon_to_const_and_static() {
+        type_char(
+            '=',
+            r"
+const FOO: i32 <|> 1 + 1
+",
+            r"
+const FOO: i32 = 1 + 1;
+",
+        );
+        type_char(
+            '=',
+            r"
+static FOO: i32 <|> 92
+",
+            r"
+static FOO: i32 = 92;
+",
+        );
+        type_char_noop(
+            '=',
+            r"
+const FOO: i32 <|> 92;
+",
+        );
+    }
+
     #[test]
     fn indents_new_chain_call() {
         type_char(
@@ -0,0 +1,58 @@
+//! Shows the lowered HIR of the function at the cursor, for debugging.
+
+use hir::Semantics;
+use ra_ide_db::RootDatabase;
+use ra_syntax::{algo::find_node_at_offset, ast, AstNode};
+
+use crate::FilePosition;
+
+pub(crate) fn view_hir(db: &RootDatabase, position: FilePosition) -> String {
+    body_hir(db, position).unwrap_or_else(|| "Not inside a function body".to_string())
+}
+
+fn body_hir(db: &RootDatabase, position: FilePosition) -> Option<String> {
+    let sema = Semantics::new(db);
+    let source_file = sema.parse(position.file_id);
+
+    let function = find_node_at_offset::<ast::FnDef>(source_file.syntax(), position.offset)?;
+    let function: hir::Function = sema.to_def(&function)?;
+    Some(function.debug_hir(db))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::single_file_with_position;
+
+    #[test]
+    fn view_hir_of_function() {
+        let (analysis, position) =
+            single_file_with_position("fn foo(a: u8) { let x = (a as u32 + 9<|>2) * 2; }");
+        let hir = analysis.view_hir(position).unwrap();
+        assert_eq!(hir, "fn foo(a /* u8 */) {\n    let x /* u32 */ = ((a as u32) + 92) * 2;\n}");
+    }
+
+    #[test]
+    fn view_hir_of_literals() {
+        let (analysis, position) = single_file_with_position(
+            r#"fn foo() { let s = "a\n"; let r = r"\d"; let c = 'x'; let f = 1.5f32; let b = b"a\x01";<|> }"#,
+        );
+        let hir = analysis.view_hir(position).unwrap();
+        assert_eq!(
+            hir,
+            r#"fn foo() {
+    let s /* &str */ = "a\n";
+    let r /* &str */ = "\\d";
+    let c /* char */ = 'x';
+    let f /* f32 */ = 1.5f32;
+    let b /* &[u8] */ = b"a\x01";
+}"#
+        );
+    }
+
+    #[test]
+    fn view_hir_outside_of_function() {
+        let (analysis, position) = single_file_with_position("struct Foo<|>;");
+        let hir = analysis.view_hir(position).unwrap();
+        assert_eq!(hir, "Not inside a function body");
+    }
+}
@@ -9,13 +9,10 @@ use ra_db::{
     SourceRootId,
 };
 use ra_prof::{memory_usage, profile, Bytes};
-use ra_syntax::SourceFile;
-#[cfg(not(feature = "wasm"))]
-use rayon::prelude::*;
 use rustc_hash::FxHashMap;
 
 use crate::{
-    symbol_index::{SymbolIndex, SymbolsDatabase},
+    symbol_index::{SymbolCache, SymbolsDatabase},
     DebugData, RootDatabase,
 };
 
@@ -24,8 +21,8 @@ pub struct AnalysisChange {
     new_roots: Vec<(SourceRootId, bool)>,
     roots_changed: FxHashMap<SourceRootId, RootChange>,
     files_changed: Vec<(FileId, Arc<String>)>,
-    libraries_added: Vec<LibraryData>,
     crate_graph: Option<CrateGraph>,
+    symbol_cache: Option<Option<SymbolCache>>,
     debug_data: DebugData,
 }
 
@@ -41,9 +38,6 @@ impl fmt::Debug for AnalysisChange {
         if !self.files_changed.is_empty() {
             d.field("files_changed", &self.files_changed.len());
         }
-        if !self.libraries_added.is_empty() {
-            d.field("libraries_added", &self.libraries_added.len());
-        }
         if self.crate_graph.is_some() {
             d.field("crate_graph", &self.crate_graph);
         }
@@ -56,6 +50,8 @@ impl AnalysisChange {
         AnalysisChange::default()
     }
 
+    /// Adds a source root. Non-local roots are libraries, which are assumed to
+    /// never change: their symbols are indexed only once they are searched.
     pub fn add_root(&mut self, root_id: SourceRootId, is_local: bool) {
         self.new_roots.push((root_id, is_local));
     }
@@ -80,14 +76,14 @@ impl AnalysisChange {
         self.roots_changed.entry(root_id).or_default().removed.push(file);
     }
 
-    pub fn add_library(&mut self, data: LibraryData) {
-        self.libraries_added.push(data)
-    }
-
     pub fn set_crate_graph(&mut self, graph: CrateGraph) {
         self.crate_graph = Some(graph);
     }
 
+    pub fn set_symbol_cache(&mut self, cache: Option<SymbolCache>) {
+        self.symbol_cache = Some(cache);
+    }
+
     pub fn set_debug_root_path(&mut self, source_root_id: SourceRootId, path: String) {
         self.debug_data.root_paths.insert(source_root_id, path);
     }
@@ -121,47 +117,6 @@ impl fmt::Debug for RootChange {
     }
 }
 
-pub struct LibraryData {
-    root_id: SourceRootId,
-    root_change: RootChange,
-    symbol_index: SymbolIndex,
-}
-
-impl fmt::Debug for LibraryData {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("LibraryData")
-            .field("root_id", &self.root_id)
-            .field("root_change", &self.root_change)
-            .field("n_symbols", &self.symbol_index.len())
-            .finish()
-    }
-}
-
-impl LibraryData {
-    pub fn prepare(
-        root_id: SourceRootId,
-        files: Vec<(FileId, RelativePathBuf, Arc<String>)>,
-    ) -> LibraryData {
-        let _p = profile("LibraryData::prepare");
-
-        #[cfg(not(feature = "wasm"))]
-        let iter = files.par_iter();
-        #[cfg(feature = "wasm")]
-        let iter = files.iter();
-
-        let symbol_index = SymbolIndex::for_files(iter.map(|(file_id, _, text)| {
-            let parse = SourceFile::parse(text);
-            (*file_id, parse)
-        }));
-        let mut root_change = RootChange::default();
-        root_change.added = files
-            .into_iter()
-            .map(|(file_id, path, text)| AddFile { file_id, path, text })
-            .collect();
-        LibraryData { root_id, root_change, symbol_index }
-    }
-}
-
 const GC_COOLDOWN: time::Duration = time::Duration::from_millis(100);
 
 impl RootDatabase {
@@ -176,6 +131,7 @@ impl RootDatabase {
         log::info!("apply_change {:?}", change);
         if !change.new_roots.is_empty() {
             let mut local_roots = Vec::clone(&self.local_roots());
+            let mut library_roots = Vec::clone(&self.library_roots());
             for (root_id, is_local) in change.new_roots {
                 let root =
                     if is_local { SourceRoot::new_local() } else { SourceRoot::new_library() };
@@ -183,9 +139,12 @@ impl RootDatabase {
                 self.set_source_root_with_durability(root_id, Arc::new(root), durability);
                 if is_local {
                     local_roots.push(root_id);
+                } else {
+                    library_roots.push(root_id);
                 }
             }
             self.set_local_roots_with_durability(Arc::new(local_roots), Durability::HIGH);
+            self.set_library_roots_with_durability(Arc::new(library_roots), Durability::HIGH);
         }
 
         for (root_id, root_change) in change.roots_changed {
@@ -197,27 +156,12 @@ impl RootDatabase {
             let durability = durability(&source_root);
             self.set_file_text_with_durability(file_id, text, durability)
         }
-        if !change.libraries_added.is_empty() {
-            let mut libraries = Vec::clone(&self.library_roots());
-            for library in change.libraries_added {
-                libraries.push(library.root_id);
-                self.set_source_root_with_durability(
-                    library.root_id,
-                    Arc::new(SourceRoot::new_library()),
-                    Durability::HIGH,
-                );
-                self.set_library_symbols_with_durability(
-                    library.root_id,
-                    Arc::new(library.symbol_index),
-                    Durability::HIGH,
-                );
-                self.apply_root_change(library.root_id, library.root_change);
-            }
-            self.set_library_roots_with_durability(Arc::new(libraries), Durability::HIGH);
-        }
         if let Some(crate_graph) = change.crate_graph {
             self.set_crate_graph_with_durability(Arc::new(crate_graph), Durability::HIGH)
         }
+        if let Some(symbol_cache) = change.symbol_cache {
+            self.set_symbol_cache_with_durability(symbol_cache.map(Arc::new), Durability::HIGH)
+        }
 
         Arc::make_mut(&mut self.debug_data).merge(change.debug_data)
     }
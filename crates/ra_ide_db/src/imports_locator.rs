@@ -25,23 +25,13 @@ impl<'a> ImportsLocator<'a> {
         let _p = profile("search_for_imports");
         let db = self.sema.db;
 
-        let project_results = {
-            let mut query = Query::new(name_to_import.to_string());
-            query.exact();
-            query.limit(40);
-            symbol_index::world_symbols(db, query)
-        };
-        let lib_results = {
-            let mut query = Query::new(name_to_import.to_string());
-            query.libs();
-            query.exact();
-            query.limit(40);
-            symbol_index::world_symbols(db, query)
-        };
+        let mut query = Query::new(name_to_import.to_string());
+        query.libs();
+        query.exact();
+        query.limit(80);
 
-        project_results
+        symbol_index::world_symbols(db, query)
             .into_iter()
-            .chain(lib_results.into_iter())
             .filter_map(|import_candidate| self.get_name_definition(&import_candidate))
             .filter_map(|name_definition_to_import| match name_definition_to_import {
                 Definition::ModuleDef(module_def) => Some(Either::Left(module_def)),
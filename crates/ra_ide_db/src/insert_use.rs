@@ -11,6 +11,29 @@ use ra_syntax::{
 };
 use ra_text_edit::TextEditBuilder;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InsertUseConfig {
+    pub granularity: ImportGranularity,
+}
+
+impl Default for InsertUseConfig {
+    fn default() -> Self {
+        InsertUseConfig { granularity: ImportGranularity::Crate }
+    }
+}
+
+/// How new imports are merged into the existing ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportGranularity {
+    /// Merge into any `use` with a common prefix, so that there is a single
+    /// `use` per crate: `use std::{fmt::Debug, io};`.
+    Crate,
+    /// Only merge imports from the same module: `use std::fmt::{Debug, Display};`.
+    Module,
+    /// Never merge, every item gets its own `use`.
+    Item,
+}
+
 /// Creates and inserts a use statement for the given path to import.
 /// The use statement is inserted in the scope most appropriate to the
 /// the cursor position given, additionally merged with the existing use imports.
@@ -18,6 +41,7 @@ pub fn insert_use_statement(
     // Ideally the position of the cursor, used to
     position: &SyntaxNode,
     path_to_import: &ModPath,
+    config: &InsertUseConfig,
     edit: &mut TextEditBuilder,
 ) {
     let target = path_to_import.to_string().split("::").map(SmolStr::new).collect::<Vec<_>>();
@@ -29,11 +53,26 @@ pub fn insert_use_statement(
     });
 
     if let Some(container) = container {
-        let action = best_action_for_target(container, position.clone(), &target);
+        let action = best_action_for_target(container, position.clone(), &target, config);
         make_assist(&action, &target, edit);
     }
 }
 
+/// Renders the `use` items for the `names` of the module at `prefix`, for the
+/// code which creates new imports instead of inserting into the existing ones.
+pub fn render_use_items(prefix: &str, names: &[String], config: &InsertUseConfig) -> Vec<String> {
+    match (names, config.granularity) {
+        ([], _) => Vec::new(),
+        ([name], _) => vec![format!("use {}::{};", prefix, name)],
+        (names, ImportGranularity::Item) => {
+            names.iter().map(|name| format!("use {}::{};", prefix, name)).collect()
+        }
+        (names, ImportGranularity::Crate) | (names, ImportGranularity::Module) => {
+            vec![format!("use {}::{{{}}};", prefix, names.join(", "))]
+        }
+    }
+}
+
 fn collect_path_segments_raw(
     segments: &mut Vec<ast::PathSegment>,
     mut path: ast::Path,
@@ -360,11 +399,12 @@ fn best_action_for_target(
     container: SyntaxNode,
     anchor: SyntaxNode,
     target: &[SmolStr],
+    config: &InsertUseConfig,
 ) -> ImportAction {
     let mut storage = Vec::with_capacity(16); // this should be the only allocation
-    let best_action = container
-        .children()
-        .filter_map(ast::UseItem::cast)
+    let use_items = container.children().filter_map(ast::UseItem::cast).collect::<Vec<_>>();
+    let best_action = use_items
+        .iter()
         .filter_map(|it| it.use_tree())
         .map(|u| walk_use_tree_for_best_action(&mut storage, None, u, target))
         .fold(None, |best, a| match best {
@@ -373,7 +413,21 @@ fn best_action_for_target(
         });
 
     match best_action {
-        Some(action) => action,
+        // The path is already imported, whatever the granularity.
+        Some(ImportAction::Nothing) => ImportAction::Nothing,
+        Some(action) => {
+            let action = match config.granularity {
+                ImportGranularity::Crate => Some(action),
+                ImportGranularity::Module => best_action_for_module(&use_items, target),
+                ImportGranularity::Item => None,
+            };
+            match action {
+                Some(ImportAction::AddNewUse { .. }) | None => {
+                    add_new_use_in_group(&use_items, target)
+                }
+                Some(action) => action,
+            }
+        }
         None => {
             // We have no action and no UseItem was found in container so we find
             // another item and we use it as anchor.
@@ -391,6 +445,79 @@ fn best_action_for_target(
     }
 }
 
+/// Finds a `use` of items from the module of `target`, like `use std::fmt::Debug;`
+/// or `use std::fmt::{Debug, Display};` for `std::fmt::Write`.
+fn best_action_for_module(use_items: &[ast::UseItem], target: &[SmolStr]) -> Option<ImportAction> {
+    let module_len = target.len().checked_sub(1).filter(|&it| it > 0)?;
+    use_items.iter().filter_map(|it| it.use_tree()).find_map(|use_tree| {
+        let path = use_tree.path()?;
+        let mut segments = Vec::new();
+        collect_path_segments_raw(&mut segments, path.clone())?;
+        if compare_path_segments(&target[..module_len], &segments) != module_len {
+            return None;
+        }
+        match use_tree.use_tree_list() {
+            Some(tree_list) if segments.len() == module_len => {
+                Some(ImportAction::add_in_tree_list(module_len, tree_list, false))
+            }
+            None if segments.len() == target.len() && use_tree.star_token().is_none() => {
+                let first_segment_to_split = segments[module_len].clone();
+                Some(ImportAction::add_nested_import(
+                    module_len,
+                    path,
+                    Some(first_segment_to_split),
+                    false,
+                ))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Adds a new `use` behind the last one of the same group, or behind the last
+/// one if there are none.
+fn add_new_use_in_group(use_items: &[ast::UseItem], target: &[SmolStr]) -> ImportAction {
+    let group = ImportGroup::new(&target[0]);
+    let anchor = use_items
+        .iter()
+        .rev()
+        .find(|it| ImportGroup::of_use_item(it) == Some(group))
+        .or_else(|| use_items.last())
+        .map(|it| it.syntax().clone());
+    ImportAction::add_new_use(anchor, true)
+}
+
+/// The groups imports are usually sorted into, separated by blank lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ImportGroup {
+    Std,
+    ExternCrate,
+    ThisCrate,
+}
+
+impl ImportGroup {
+    fn new(first_segment: &str) -> ImportGroup {
+        match first_segment {
+            "std" | "core" | "alloc" => ImportGroup::Std,
+            "crate" | "self" | "super" => ImportGroup::ThisCrate,
+            _ => ImportGroup::ExternCrate,
+        }
+    }
+
+    fn of_use_item(use_item: &ast::UseItem) -> Option<ImportGroup> {
+        let mut segments = Vec::new();
+        collect_path_segments_raw(&mut segments, use_item.use_tree()?.path()?)?;
+        let group = match segments.first()?.kind()? {
+            ast::PathSegmentKind::Name(name_ref) => ImportGroup::new(name_ref.text()),
+            ast::PathSegmentKind::SelfKw
+            | ast::PathSegmentKind::SuperKw
+            | ast::PathSegmentKind::CrateKw => ImportGroup::ThisCrate,
+            ast::PathSegmentKind::Type { .. } => return None,
+        };
+        Some(group)
+    }
+}
+
 fn make_assist(action: &ImportAction, target: &[SmolStr], edit: &mut TextEditBuilder) {
     match action {
         ImportAction::AddNewUse { anchor, add_after_anchor } => {
@@ -510,3 +637,171 @@ fn make_assist_add_nested_import(
         edit.insert(end, "}".to_string());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::{ast::make, SourceFile};
+    use ra_text_edit::TextEditBuilder;
+    use test_utils::{assert_eq_text, extract_offset};
+
+    use super::*;
+
+    fn check(granularity: ImportGranularity, path: &str, before: &str, after: &str) {
+        let (offset, before) = extract_offset(before);
+        let file = SourceFile::parse(&before).tree();
+        let position = file.syntax().token_at_offset(offset).left_biased().unwrap().parent();
+        let path = hir::Path::from_ast(make::path_from_text(path)).unwrap().mod_path().clone();
+
+        let mut edit = TextEditBuilder::default();
+        insert_use_statement(&position, &path, &InsertUseConfig { granularity }, &mut edit);
+        let actual = edit.finish().apply(&before);
+        assert_eq_text!(after, &actual);
+    }
+
+    #[test]
+    fn crate_granularity_merges_imports_from_the_same_crate() {
+        check(
+            ImportGranularity::Crate,
+            "std::fmt::Debug",
+            "
+use std::io;
+
+fn main() {<|>}
+",
+            "
+use std::{fmt::Debug, io};
+
+fn main() {}
+",
+        );
+    }
+
+    #[test]
+    fn module_granularity_merges_imports_from_the_same_module() {
+        check(
+            ImportGranularity::Module,
+            "std::fmt::Display",
+            "
+use std::fmt::Debug;
+use std::io;
+
+fn main() {<|>}
+",
+            "
+use std::fmt::{Display, Debug};
+use std::io;
+
+fn main() {}
+",
+        );
+        check(
+            ImportGranularity::Module,
+            "std::fmt::Write",
+            "
+use std::fmt::{Debug, Display};
+
+fn main() {<|>}
+",
+            "
+use std::fmt::{Debug, Display, Write};
+
+fn main() {}
+",
+        );
+    }
+
+    #[test]
+    fn module_granularity_does_not_merge_imports_from_other_modules() {
+        check(
+            ImportGranularity::Module,
+            "std::fmt::Debug",
+            "
+use std::io;
+use std::fmt::nested::Display;
+
+fn main() {<|>}
+",
+            "
+use std::io;
+use std::fmt::nested::Display;
+use std::fmt::Debug;
+
+fn main() {}
+",
+        );
+    }
+
+    #[test]
+    fn item_granularity_adds_a_use_per_item() {
+        check(
+            ImportGranularity::Item,
+            "std::fmt::Display",
+            "
+use std::fmt::Debug;
+
+fn main() {<|>}
+",
+            "
+use std::fmt::Debug;
+use std::fmt::Display;
+
+fn main() {}
+",
+        );
+    }
+
+    #[test]
+    fn does_nothing_if_already_imported() {
+        check(
+            ImportGranularity::Item,
+            "std::fmt::Display",
+            "
+use std::fmt::{Debug, Display};
+
+fn main() {<|>}
+",
+            "
+use std::fmt::{Debug, Display};
+
+fn main() {}
+",
+        );
+    }
+
+    #[test]
+    fn new_imports_go_into_their_group() {
+        check(
+            ImportGranularity::Item,
+            "std::io::Read",
+            "
+use std::fmt;
+
+use crate::foo;
+
+fn main() {<|>}
+",
+            "
+use std::fmt;
+use std::io::Read;
+
+use crate::foo;
+
+fn main() {}
+",
+        );
+    }
+
+    #[test]
+    fn render_use_items_by_granularity() {
+        let names = vec!["Bar".to_string(), "Foo".to_string()];
+        let render =
+            |granularity| render_use_items("super", &names, &InsertUseConfig { granularity });
+        assert_eq!(render(ImportGranularity::Crate), ["use super::{Bar, Foo};"]);
+        assert_eq!(render(ImportGranularity::Module), ["use super::{Bar, Foo};"]);
+        assert_eq!(render(ImportGranularity::Item), ["use super::Bar;", "use super::Foo;"]);
+        assert_eq!(
+            render_use_items("super", &names[..1], &InsertUseConfig::default()),
+            ["use super::Bar;"]
+        );
+    }
+}
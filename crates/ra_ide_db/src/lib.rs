@@ -10,6 +10,8 @@ pub mod change;
 pub mod defs;
 pub mod search;
 pub mod imports_locator;
+pub mod insert_use;
+pub mod moves;
 mod wasm_shims;
 
 use std::sync::Arc;
@@ -115,6 +117,7 @@ impl RootDatabase {
         db.set_crate_graph_with_durability(Default::default(), Durability::HIGH);
         db.set_local_roots_with_durability(Default::default(), Durability::HIGH);
         db.set_library_roots_with_durability(Default::default(), Durability::HIGH);
+        db.set_symbol_cache_with_durability(None, Durability::HIGH);
         db.update_lru_capacity(lru_capacity);
         db
     }
@@ -158,3 +161,130 @@ impl DebugData {
         self.root_paths.extend(other.root_paths.into_iter());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hir::{HirDisplay, Semantics, Type};
+    use ra_db::{fixture::WithFixture, FileId};
+    use ra_syntax::{ast, AstNode};
+
+    use crate::RootDatabase;
+
+    const STD: &str = r#"
+//- /std/lib.rs crate:std
+pub mod convert {
+    pub trait From<T> {
+        fn from(t: T) -> Self;
+    }
+}
+pub mod iter {
+    pub trait Iterator {
+        type Item;
+    }
+}
+"#;
+
+    /// Calls `f` with the types of the initializers of the `let`s in the
+    /// first file, and the type aliases declared in it.
+    fn check(
+        ra_fixture: &str,
+        f: impl FnOnce(&Semantics<RootDatabase>, &[Type], &[hir::TypeAlias]),
+    ) {
+        let db = RootDatabase::with_files(&format!("{}{}", ra_fixture, STD));
+        let sema = Semantics::new(&db);
+        let file = sema.parse(FileId(0));
+        let types: Vec<Type> = file
+            .syntax()
+            .descendants()
+            .filter_map(ast::LetStmt::cast)
+            .map(|it| sema.type_of_expr(&it.initializer().unwrap()).unwrap())
+            .collect();
+        let aliases: Vec<hir::TypeAlias> = file
+            .syntax()
+            .descendants()
+            .filter_map(ast::TypeAliasDef::cast)
+            .filter_map(|it| sema.to_def(&it))
+            .collect();
+        f(&sema, &types, &aliases)
+    }
+
+    #[test]
+    fn iterator_item() {
+        check(
+            r#"
+//- /main.rs crate:main deps:std
+use std::iter::Iterator;
+struct Counter;
+impl Iterator for Counter { type Item = u32; }
+struct Wrapper<I>(I);
+impl<I: Iterator> Iterator for Wrapper<I> { type Item = (I::Item, usize); }
+fn main() {
+    let a = Counter;
+    let b = Wrapper(Counter);
+    let c = 92u8;
+}
+"#,
+            |sema, types, _| {
+                let items: Vec<Option<String>> = types
+                    .iter()
+                    .map(|it| Some(it.iterator_item(sema.db)?.display(sema.db).to_string()))
+                    .collect();
+                assert_eq!(
+                    items,
+                    vec![Some("u32".to_string()), Some("(u32, usize)".to_string()), None]
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn normalize_trait_assoc_type() {
+        check(
+            r#"
+//- /main.rs crate:main deps:std
+trait Convert<T> { type Output; }
+struct S;
+impl Convert<u8> for S { type Output = u16; }
+impl Convert<u32> for S { type Output = u64; }
+fn main() {
+    let s = S;
+    let a = 0u8;
+    let b = 0u32;
+    let c = 0i32;
+}
+"#,
+            |sema, types, aliases| {
+                let output = aliases[0];
+                let normalize = |arg: &Type| {
+                    let ty =
+                        types[0].normalize_trait_assoc_type(sema.db, &[arg.clone()], output)?;
+                    Some(ty.display(sema.db).to_string())
+                };
+                assert_eq!(normalize(&types[1]), Some("u16".to_string()));
+                assert_eq!(normalize(&types[2]), Some("u64".to_string()));
+                assert_eq!(normalize(&types[3]), None);
+            },
+        );
+    }
+
+    #[test]
+    fn impls_from() {
+        check(
+            r#"
+//- /main.rs crate:main deps:std
+use std::convert::From;
+struct S;
+impl From<u8> for S { fn from(_: u8) -> S { S } }
+fn main() {
+    let s = S;
+    let a = 0u8;
+    let b = 0u32;
+}
+"#,
+            |sema, types, _| {
+                assert!(types[0].impls_from(sema.db, &types[1]));
+                assert!(!types[0].impls_from(sema.db, &types[2]));
+            },
+        );
+    }
+}
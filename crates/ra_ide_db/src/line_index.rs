@@ -20,6 +20,16 @@ pub struct LineCol {
     pub col_utf16: u32,
 }
 
+/// Like `LineCol`, but the column is counted in UTF-8 code units, for the
+/// clients which negotiated the UTF-8 offset encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LineColUtf8 {
+    /// Zero-based
+    pub line: u32,
+    /// Zero-based
+    pub col: u32,
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub(crate) struct Utf16Char {
     pub(crate) start: TextUnit,
@@ -88,6 +98,16 @@ impl LineIndex {
         self.newlines[line_col.line as usize] + col
     }
 
+    pub fn line_col_utf8(&self, offset: TextUnit) -> LineColUtf8 {
+        let line = self.newlines.upper_bound(&offset) - 1;
+        let col = offset - self.newlines[line];
+        LineColUtf8 { line: line as u32, col: col.to_usize() as u32 }
+    }
+
+    pub fn offset_utf8(&self, line_col: LineColUtf8) -> TextUnit {
+        self.newlines[line_col.line as usize] + TextUnit::from(line_col.col)
+    }
+
     pub fn lines(&self, range: TextRange) -> impl Iterator<Item = TextRange> + '_ {
         let lo = self.newlines.lower_bound(&range.start());
         let hi = self.newlines.upper_bound(&range.end());
@@ -165,6 +185,19 @@ mod test_line_index {
         assert_eq!(index.line_col(7.into()), LineCol { line: 2, col_utf16: 0 });
     }
 
+    #[test]
+    fn test_line_index_utf8() {
+        let text = "hello\nconst C: char = 'メ';";
+        let index = LineIndex::new(text);
+        assert_eq!(index.line_col_utf8(5.into()), LineColUtf8 { line: 0, col: 5 });
+        assert_eq!(index.line_col_utf8(6.into()), LineColUtf8 { line: 1, col: 0 });
+        assert_eq!(index.line_col_utf8(26.into()), LineColUtf8 { line: 1, col: 20 });
+        assert_eq!(index.line_col(26.into()), LineCol { line: 1, col_utf16: 18 });
+
+        assert_eq!(index.offset_utf8(LineColUtf8 { line: 1, col: 20 }), TextUnit::from(26));
+        assert_eq!(index.offset(LineCol { line: 1, col_utf16: 18 }), TextUnit::from(26));
+    }
+
     #[test]
     fn test_char_len() {
         assert_eq!('メ'.len_utf8(), 3);
@@ -7,4 +7,5 @@ test_utils::marks![
     goto_def_for_record_fields
     goto_def_for_field_init_shorthand
     search_filters_by_range
+    search_skips_longer_identifiers
 ];
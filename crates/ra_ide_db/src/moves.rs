@@ -0,0 +1,99 @@
+//! Tells whether the uses of a local move it, for features which care about
+//! where a value ends up, like drop hints or turning a local into a field.
+//!
+//! This is an approximation which only looks at the syntax around a use and
+//! at the `self` parameter of methods.
+
+use hir::{HasSource, Local, Semantics};
+use ra_syntax::{
+    ast::{self, AstNode, BinOp, SelfParamKind, TypeAscriptionOwner},
+    SyntaxNode,
+};
+
+use crate::{defs::Definition, search::ReferenceKind, RootDatabase};
+
+/// Whether the context of `expr` borrows it, rather than moving it.
+pub fn is_borrowed(sema: &Semantics<RootDatabase>, expr: &ast::Expr, parent: &SyntaxNode) -> bool {
+    if ast::RefExpr::can_cast(parent.kind())
+        || ast::FieldExpr::can_cast(parent.kind())
+        || ast::IndexExpr::can_cast(parent.kind())
+    {
+        return true;
+    }
+    if let Some(call) = ast::MethodCallExpr::cast(parent.clone()) {
+        return call.expr().as_ref() == Some(expr) && !takes_self_by_value(sema, &call);
+    }
+    if let Some(bin_expr) = ast::BinExpr::cast(parent.clone()) {
+        return match bin_expr.op_kind() {
+            Some(BinOp::EqualityTest)
+            | Some(BinOp::NegatedEqualityTest)
+            | Some(BinOp::LesserEqualTest)
+            | Some(BinOp::GreaterEqualTest)
+            | Some(BinOp::LesserTest)
+            | Some(BinOp::GreaterTest) => true,
+            // Assigning to a local drops the old value, and the new one is
+            // dropped at the end of the scope, like the old one would be.
+            Some(op) if op.is_assignment() => bin_expr.lhs().as_ref() == Some(expr),
+            _ => false,
+        };
+    }
+    false
+}
+
+/// Whether the method takes `self` by value. Unresolved methods are assumed
+/// to.
+fn takes_self_by_value(sema: &Semantics<RootDatabase>, call: &ast::MethodCallExpr) -> bool {
+    let self_param = sema
+        .resolve_method_call(call)
+        .and_then(|it| it.source(sema.db).value.param_list())
+        .and_then(|it| it.self_param());
+    match self_param {
+        Some(it) => match it.ascribed_type() {
+            Some(ty) => !matches!(ty, ast::TypeRef::ReferenceType(_)),
+            None => it.kind() == SelfParamKind::Owned,
+        },
+        None => true,
+    }
+}
+
+/// Whether any use of the local might move it.
+pub fn may_be_moved(sema: &Semantics<RootDatabase>, local: Local) -> bool {
+    Definition::Local(local).find_usages(sema.db, None).iter().any(|reference| {
+        // `S { local }` moves the local into the struct.
+        if reference.kind == ReferenceKind::StructFieldShorthandForLocal {
+            return true;
+        }
+        let file = sema.parse(reference.file_range.file_id);
+        // Uses in macro calls are checked in the expansion. If it can't be
+        // found, the use is assumed to be a borrow, like in `println!`.
+        let name_ref = match sema.find_node_at_offset_with_descend::<ast::NameRef>(
+            file.syntax(),
+            reference.file_range.range.start(),
+        ) {
+            Some(it) => it,
+            None => return false,
+        };
+        let path_expr = match name_ref.syntax().ancestors().find_map(ast::PathExpr::cast) {
+            Some(it) => it,
+            None => return false,
+        };
+        let in_move_closure = path_expr
+            .syntax()
+            .ancestors()
+            .filter_map(ast::LambdaExpr::cast)
+            .any(|it| it.move_token().is_some());
+        if in_move_closure {
+            return true;
+        }
+        let mut expr = ast::Expr::from(path_expr);
+        let mut parent = expr.syntax().parent();
+        while let Some(paren) = parent.clone().and_then(ast::ParenExpr::cast) {
+            parent = paren.syntax().parent();
+            expr = paren.into();
+        }
+        match parent {
+            Some(parent) => !is_borrowed(sema, &expr, &parent),
+            None => true,
+        }
+    })
+}
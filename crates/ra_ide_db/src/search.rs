@@ -6,9 +6,12 @@
 
 use std::mem;
 
-use hir::{DefWithBody, HasSource, Module, ModuleSource, Semantics, Visibility};
+use hir::{
+    AsAssocItem, AssocItem, AssocItemContainer, Crate, DefWithBody, Function, HasSource, ImplDef,
+    Module, ModuleDef, ModuleSource, Semantics, Type, Visibility,
+};
 use once_cell::unsync::Lazy;
-use ra_db::{FileId, FileRange, SourceDatabaseExt};
+use ra_db::{salsa::Database, Canceled, FileId, FileRange, SourceDatabaseExt};
 use ra_prof::profile;
 use ra_syntax::{ast, match_ast, AstNode, TextRange, TextUnit};
 use rustc_hash::FxHashMap;
@@ -26,11 +29,23 @@ pub struct Reference {
     pub access: Option<ReferenceAccess>,
 }
 
+/// The references found by `Definition::find_usages_until_canceled`.
+#[derive(Debug, Clone)]
+pub struct Usages {
+    pub references: Vec<Reference>,
+    /// Whether the search was canceled before all the files were searched.
+    pub truncated: bool,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ReferenceKind {
     StructFieldShorthandForField,
     StructFieldShorthandForLocal,
     StructLiteral,
+    /// A call of a function or a method, `foo()` or `x.foo()`.
+    FnCall,
+    /// A path inside of a `use` item.
+    Import,
     Other,
 }
 
@@ -40,6 +55,20 @@ pub enum ReferenceAccess {
     Write,
 }
 
+/// How method calls through a trait are matched when searching for the usages
+/// of a trait method or of a method in a trait impl.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Calls through the trait are usages of the trait method and of all the
+    /// impl methods they might dispatch to, based on the receiver type.
+    WithDispatch,
+    /// Calls on a receiver with a known impl are only usages of the method
+    /// which is actually called: the impl method if the impl overrides it,
+    /// and the trait method otherwise. Dynamically dispatched calls are only
+    /// usages of the trait method.
+    Exact,
+}
+
 /// Generally, `search_scope` returns files that might contain references for the element.
 /// For `pub(crate)` things it's a crate, for `pub` things it's a crate and dependant crates.
 /// In some cases, the location of the references is known to within a `TextRange`,
@@ -61,6 +90,18 @@ impl SearchScope {
         SearchScope::new(std::iter::once((file, None)).collect())
     }
 
+    /// All files which belong to the module tree of the crate.
+    pub fn krate(db: &RootDatabase, krate: Crate) -> SearchScope {
+        let mut entries = FxHashMap::default();
+        let mut to_visit: Vec<Module> = krate.root_module(db).into_iter().collect();
+        while let Some(module) = to_visit.pop() {
+            let file_id = module.definition_source(db).file_id.original_file(db);
+            entries.insert(file_id, None);
+            to_visit.extend(module.children(db));
+        }
+        SearchScope::new(entries)
+    }
+
     pub fn intersection(&self, other: &SearchScope) -> SearchScope {
         let (mut small, mut large) = (&self.entries, &other.entries);
         if small.len() > large.len() {
@@ -175,15 +216,44 @@ impl Definition {
         SearchScope::new(res)
     }
 
+    /// Finds the usages which are resolved to exactly this definition, for
+    /// the callers which edit them.
     pub fn find_usages(
         &self,
         db: &RootDatabase,
         search_scope: Option<SearchScope>,
     ) -> Vec<Reference> {
+        let usages = self.find_usages_until_canceled(db, search_scope, SearchMode::Exact);
+        // The callers edit all the usages, so partial results would be wrong.
+        if usages.truncated {
+            Canceled::throw()
+        }
+        usages.references
+    }
+
+    /// Searches the files one by one, and stops before the next file if the
+    /// search is canceled by a change, returning the references found so far.
+    pub fn find_usages_until_canceled(
+        &self,
+        db: &RootDatabase,
+        search_scope: Option<SearchScope>,
+        mode: SearchMode,
+    ) -> Usages {
         let _p = profile("Definition::find_usages");
 
+        let dispatch = match self {
+            Definition::ModuleDef(ModuleDef::Function(func)) => MethodDispatch::new(db, *func),
+            _ => None,
+        };
         let search_scope = {
-            let base = self.search_scope(db);
+            // Calls of an impl method through the trait can be anywhere the
+            // trait method is visible.
+            let base = match &dispatch {
+                Some(dispatch) => {
+                    Definition::ModuleDef(ModuleDef::Function(dispatch.trait_fn)).search_scope(db)
+                }
+                None => self.search_scope(db),
+            };
             match search_scope {
                 None => base,
                 Some(scope) => base.intersection(&scope),
@@ -191,14 +261,19 @@ impl Definition {
         };
 
         let name = match self.name(db) {
-            None => return Vec::new(),
+            None => return Usages { references: Vec::new(), truncated: false },
             Some(it) => it.to_string(),
         };
 
         let pat = name.as_str();
         let mut refs = vec![];
+        let mut truncated = false;
 
         for (file_id, search_range) in search_scope {
+            if db.salsa_runtime().is_current_revision_canceled() {
+                truncated = true;
+                break;
+            }
             let text = db.file_text(file_id);
             let search_range =
                 search_range.unwrap_or(TextRange::offset_len(0.into(), TextUnit::of_str(&text)));
@@ -212,20 +287,56 @@ impl Definition {
                     tested_by!(search_filters_by_range; force);
                     continue;
                 }
+                // The file is only parsed if the name occurs as a whole word,
+                // not only as a part of a longer identifier like `foo_bar`.
+                if !is_whole_word(&text, idx, pat.len()) {
+                    tested_by!(search_skips_longer_identifiers; force);
+                    continue;
+                }
 
-                let name_ref: ast::NameRef =
-                    if let Some(name_ref) = sema.find_node_at_offset_with_descend(&tree, offset) {
-                        name_ref
-                    } else {
-                        continue;
-                    };
+                let name_ref: ast::NameRef = if let Some(name_ref) =
+                    sema.find_node_at_offset_with_descend(&tree, offset)
+                {
+                    name_ref
+                } else {
+                    // Shorthand field patterns, like `Foo { bar }`, refer to the field
+                    // through an `ast::Name` of the local they bind.
+                    if let Definition::StructField(field) = self {
+                        let bind_pat: Option<ast::BindPat> =
+                            sema.find_node_at_offset_with_descend(&tree, offset);
+                        if let Some(bind_pat) = bind_pat.filter(is_shorthand_field_pat) {
+                            if sema.resolve_record_field_pat(&bind_pat).as_ref() == Some(field) {
+                                refs.push(Reference {
+                                    file_range: sema.original_range(bind_pat.syntax()),
+                                    kind: ReferenceKind::StructFieldShorthandForField,
+                                    access: Some(ReferenceAccess::Read),
+                                });
+                            }
+                        }
+                    }
+                    continue;
+                };
 
                 // FIXME: reuse sb
                 // See https://github.com/rust-lang/rust/pull/68198#issuecomment-574269098
 
+                let is_usage = |def: &Definition| match &dispatch {
+                    Some(dispatch) => dispatch.is_usage(&sema, self, def, &name_ref, mode),
+                    None => def == self,
+                };
                 match classify_name_ref(&sema, &name_ref) {
-                    Some(NameRefClass::Definition(def)) if &def == self => {
-                        let kind = if is_record_lit_name_ref(&name_ref)
+                    Some(NameRefClass::Definition(def)) if is_usage(&def) => {
+                        let kind = if is_use_tree_name_ref(&name_ref) {
+                            ReferenceKind::Import
+                        } else if let Definition::ModuleDef(ModuleDef::Function(_)) = def {
+                            if is_call_expr_name_ref(&name_ref)
+                                || is_method_call_expr_name_ref(&name_ref)
+                            {
+                                ReferenceKind::FnCall
+                            } else {
+                                ReferenceKind::Other
+                            }
+                        } else if is_record_lit_name_ref(&name_ref)
                             || is_call_expr_name_ref(&name_ref)
                         {
                             ReferenceKind::StructLiteral
@@ -260,10 +371,89 @@ impl Definition {
                 }
             }
         }
-        refs
+        Usages { references: refs, truncated }
+    }
+}
+
+/// A trait method and all the impls of its trait, used to match method calls
+/// through the trait with the impl methods they dispatch to.
+struct MethodDispatch {
+    trait_fn: Function,
+    /// The self types of the impls, with the method if the impl overrides it.
+    impls: Vec<(Type, Option<Function>)>,
+}
+
+impl MethodDispatch {
+    fn new(db: &RootDatabase, func: Function) -> Option<MethodDispatch> {
+        let name = func.name(db);
+        let same_name = |item: AssocItem| match item {
+            AssocItem::Function(it) if it.name(db) == name => Some(it),
+            _ => None,
+        };
+        let (trait_, trait_fn) = match func.as_assoc_item(db)?.container(db) {
+            AssocItemContainer::Trait(it) => (it, func),
+            AssocItemContainer::ImplDef(it) => (it.target_trait_def(db)?, func.super_method(db)?),
+        };
+        let impls = ImplDef::all_for_trait(db, trait_)
+            .into_iter()
+            .map(|it| (it.target_ty(db), it.items(db).into_iter().find_map(same_name)))
+            .collect();
+        Some(MethodDispatch { trait_fn, impls })
+    }
+
+    /// Finds the impl a call on `receiver` is statically dispatched to, and
+    /// returns the method it overrides the trait method with, if any.
+    fn static_target(&self, db: &RootDatabase, receiver: &Type) -> Option<Option<Function>> {
+        receiver.autoderef(db).find_map(|ty| {
+            self.impls
+                .iter()
+                .find(|(impl_ty, _)| ty.is_equal_for_find_impls(impl_ty))
+                .map(|it| it.1)
+        })
+    }
+
+    /// Checks if `def`, the resolution of a name reference, is a usage of
+    /// `searched`, taking the receiver type of method calls into account.
+    fn is_usage(
+        &self,
+        sema: &Semantics<RootDatabase>,
+        searched: &Definition,
+        def: &Definition,
+        name_ref: &ast::NameRef,
+        mode: SearchMode,
+    ) -> bool {
+        let trait_fn = Definition::ModuleDef(ModuleDef::Function(self.trait_fn));
+        if def != &trait_fn {
+            return def == searched;
+        }
+        let receiver = name_ref
+            .syntax()
+            .parent()
+            .and_then(ast::MethodCallExpr::cast)
+            .and_then(|call| call.expr())
+            .and_then(|receiver| sema.type_of_expr(&receiver));
+        let target = receiver.and_then(|receiver| self.static_target(sema.db, &receiver));
+        match (target, mode) {
+            // Dynamically dispatched calls might call any of the impl methods.
+            (None, SearchMode::WithDispatch) => true,
+            (None, SearchMode::Exact) => searched == &trait_fn,
+            (Some(_), SearchMode::WithDispatch) if searched == &trait_fn => true,
+            (Some(Some(impl_fn)), _) => {
+                searched == &Definition::ModuleDef(ModuleDef::Function(impl_fn))
+            }
+            // The impl doesn't override the method, so the trait method is called.
+            (Some(None), _) => searched == &trait_fn,
+        }
     }
 }
 
+fn is_whole_word(text: &str, start: usize, len: usize) -> bool {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let before = text[..start].chars().next_back();
+    let after = text[start + len..].chars().next();
+    !before.map_or(false, is_ident_char) && !after.map_or(false, is_ident_char)
+}
+
 fn reference_access(def: &Definition, name_ref: &ast::NameRef) -> Option<ReferenceAccess> {
     // Only Locals and Fields have accesses for now.
     match def {
@@ -286,6 +476,17 @@ fn reference_access(def: &Definition, name_ref: &ast::NameRef) -> Option<Referen
                     }
                     Some(ReferenceAccess::Read)
                 },
+                ast::RefExpr(expr) => {
+                    // `&mut x` is treated as a write, as `x` may be mutated through the borrow.
+                    if expr.mut_token().is_some() {
+                        if let Some(inner) = expr.expr() {
+                            if inner.syntax().text_range().end() == name_ref.syntax().text_range().end() {
+                                return Some(ReferenceAccess::Write);
+                            }
+                        }
+                    }
+                    Some(ReferenceAccess::Read)
+                },
                 _ => None
             }
         }
@@ -309,6 +510,25 @@ fn is_call_expr_name_ref(name_ref: &ast::NameRef) -> bool {
         .unwrap_or(false)
 }
 
+/// Checks if the pattern is a field shorthand, like `bar` in `Foo { bar }`.
+pub fn is_shorthand_field_pat(bind_pat: &ast::BindPat) -> bool {
+    bind_pat.syntax().parent().map_or(false, |it| ast::RecordFieldPatList::can_cast(it.kind()))
+}
+
+fn is_method_call_expr_name_ref(name_ref: &ast::NameRef) -> bool {
+    name_ref
+        .syntax()
+        .parent()
+        .and_then(ast::MethodCallExpr::cast)
+        .and_then(|call| call.name_ref())
+        .map(|it| &it == name_ref)
+        .unwrap_or(false)
+}
+
+fn is_use_tree_name_ref(name_ref: &ast::NameRef) -> bool {
+    name_ref.syntax().ancestors().any(|it| ast::UseItem::can_cast(it.kind()))
+}
+
 fn is_record_lit_name_ref(name_ref: &ast::NameRef) -> bool {
     name_ref
         .syntax()
@@ -22,6 +22,7 @@
 
 use std::{
     cmp::Ordering,
+    collections::BinaryHeap,
     fmt,
     hash::{Hash, Hasher},
     mem,
@@ -31,8 +32,9 @@ use std::{
 use fst::{self, Streamer};
 use ra_db::{
     salsa::{self, ParallelDatabase},
-    FileId, SourceDatabaseExt, SourceRootId,
+    FileId, SourceDatabase, SourceDatabaseExt, SourceRootId,
 };
+use ra_prof::profile;
 use ra_syntax::{
     ast::{self, NameOwner},
     match_ast, AstNode, Parse, SmolStr, SourceFile,
@@ -41,9 +43,17 @@ use ra_syntax::{
 };
 #[cfg(not(feature = "wasm"))]
 use rayon::prelude::*;
+use rustc_hash::FxHashMap;
 
 use crate::RootDatabase;
 
+pub use self::cache::SymbolCache;
+
+use self::signature::SignatureQuery;
+
+mod cache;
+mod signature;
+
 #[derive(Debug)]
 pub struct Query {
     query: String,
@@ -51,6 +61,8 @@ pub struct Query {
     only_types: bool,
     libs: bool,
     exact: bool,
+    /// Searches functions by signature instead of symbols by name.
+    signature: Option<SignatureQuery>,
     limit: usize,
 }
 
@@ -63,14 +75,52 @@ impl Query {
             only_types: false,
             libs: false,
             exact: false,
+            signature: None,
             limit: usize::max_value(),
         }
     }
 
+    /// Parses a query typed by the user. Besides the name to search for, the
+    /// query can contain the following operators:
+    ///
+    /// * `#` restricts the search to types,
+    /// * `*` searches the dependencies as well as the workspace,
+    /// * `"Foo"` looks for the exact name only.
+    ///
+    /// A query like `fn(&str) -> u32` looks for the functions with a similar
+    /// signature instead.
+    pub fn parse(input: &str) -> Query {
+        // Signatures can contain pointer types, so only a leading or trailing
+        // `*` is an operator there.
+        let signature = input.trim().trim_matches('*').trim();
+        if let Some(signature) = SignatureQuery::parse(signature) {
+            let mut query = Query::new(String::new());
+            query.signature = Some(signature);
+            query.libs = input.trim().starts_with('*') || input.trim().ends_with('*');
+            return query;
+        }
+        let only_types = input.contains('#');
+        let libs = input.contains('*');
+        let input: String = input.chars().filter(|&c| c != '#' && c != '*').collect();
+        let input = input.trim();
+        let quoted = input.len() >= 2 && input.starts_with('"') && input.ends_with('"');
+        let mut query = if quoted {
+            let mut query = Query::new(input[1..input.len() - 1].to_string());
+            query.exact();
+            query
+        } else {
+            Query::new(input.to_string())
+        };
+        query.only_types = only_types;
+        query.libs = libs;
+        query
+    }
+
     pub fn only_types(&mut self) {
         self.only_types = true;
     }
 
+    /// Searches the library symbols in addition to the workspace ones.
     pub fn libs(&mut self) {
         self.libs = true;
     }
@@ -87,7 +137,7 @@ impl Query {
 #[salsa::query_group(SymbolsDatabaseStorage)]
 pub trait SymbolsDatabase: hir::db::HirDatabase {
     fn file_symbols(&self, file_id: FileId) -> Arc<SymbolIndex>;
-    #[salsa::input]
+    /// The symbols of a library, which are indexed on the first search.
     fn library_symbols(&self, id: SourceRootId) -> Arc<SymbolIndex>;
     /// The set of "local" (that is, from the current workspace) roots.
     /// Files in local roots are assumed to change frequently.
@@ -97,6 +147,9 @@ pub trait SymbolsDatabase: hir::db::HirDatabase {
     /// Files in libraries are assumed to never change.
     #[salsa::input]
     fn library_roots(&self) -> Arc<Vec<SourceRootId>>;
+    /// Where the library symbols are saved between the runs, if anywhere.
+    #[salsa::input]
+    fn symbol_cache(&self) -> Option<Arc<SymbolCache>>;
 }
 
 fn file_symbols(db: &impl SymbolsDatabase, file_id: FileId) -> Arc<SymbolIndex> {
@@ -110,6 +163,29 @@ fn file_symbols(db: &impl SymbolsDatabase, file_id: FileId) -> Arc<SymbolIndex>
     Arc::new(SymbolIndex::new(symbols))
 }
 
+fn library_symbols(db: &impl SymbolsDatabase, id: SourceRootId) -> Arc<SymbolIndex> {
+    let _p = profile("library_symbols");
+    let files: Vec<_> = db
+        .source_root(id)
+        .walk()
+        .map(|file_id| (file_id, db.file_relative_path(file_id), db.file_text(file_id)))
+        .collect();
+
+    let build = || {
+        #[cfg(not(feature = "wasm"))]
+        let iter = files.par_iter();
+        #[cfg(feature = "wasm")]
+        let iter = files.iter();
+
+        SymbolIndex::for_files(iter.map(|(file_id, _, text)| (*file_id, SourceFile::parse(text))))
+    };
+    let symbol_index = match db.symbol_cache() {
+        Some(cache) => cache.load_or_build(&files, build),
+        None => build(),
+    };
+    Arc::new(symbol_index)
+}
+
 pub fn world_symbols(db: &RootDatabase, query: Query) -> Vec<FileSymbol> {
     /// Need to wrap Snapshot to provide `Clone` impl for `map_with`
     struct Snap(salsa::Snapshot<RootDatabase>);
@@ -119,20 +195,9 @@ pub fn world_symbols(db: &RootDatabase, query: Query) -> Vec<FileSymbol> {
         }
     }
 
-    let buf: Vec<Arc<SymbolIndex>> = if query.libs {
-        let snap = Snap(db.snapshot());
-        #[cfg(not(feature = "wasm"))]
-        let buf = db
-            .library_roots()
-            .par_iter()
-            .map_with(snap, |db, &lib_id| db.0.library_symbols(lib_id))
-            .collect();
-
-        #[cfg(feature = "wasm")]
-        let buf = db.library_roots().iter().map(|&lib_id| snap.0.library_symbols(lib_id)).collect();
-
-        buf
-    } else {
+    // Workspace symbols go first, so that they are ranked higher than the
+    // library ones.
+    let mut buf: Vec<Arc<SymbolIndex>> = {
         let mut files = Vec::new();
         for &root in db.local_roots().iter() {
             let sr = db.source_root(root);
@@ -149,6 +214,24 @@ pub fn world_symbols(db: &RootDatabase, query: Query) -> Vec<FileSymbol> {
 
         buf
     };
+    if query.libs {
+        let snap = Snap(db.snapshot());
+        #[cfg(not(feature = "wasm"))]
+        let libs: Vec<_> = db
+            .library_roots()
+            .par_iter()
+            .map_with(snap, |db, &lib_id| db.0.library_symbols(lib_id))
+            .collect();
+
+        #[cfg(feature = "wasm")]
+        let libs: Vec<_> =
+            db.library_roots().iter().map(|&lib_id| snap.0.library_symbols(lib_id)).collect();
+
+        buf.extend(libs);
+    }
+    if query.signature.is_some() {
+        return query.search_signature(db, &buf);
+    }
     query.search(&buf)
 }
 
@@ -268,6 +351,8 @@ impl SymbolIndex {
 }
 
 impl Query {
+    /// Returns the symbols matching the query, best matches first. Among the
+    /// matches of the same quality, symbols from the earlier indices go first.
     pub(crate) fn search(self, indices: &[Arc<SymbolIndex>]) -> Vec<FileSymbol> {
         let mut op = fst::map::OpBuilder::new();
         for file_symbols in indices.iter() {
@@ -275,28 +360,97 @@ impl Query {
             op = op.add(file_symbols.map.search(automaton))
         }
         let mut stream = op.union();
-        let mut res = Vec::new();
+        // The best `limit` matches so far, keyed by the quality, the index,
+        // the order in the stream and the position in the index. The worst
+        // of them is on top.
+        let mut best = BinaryHeap::new();
+        let mut seq = 0;
         while let Some((_, indexed_values)) = stream.next() {
-            if res.len() >= self.limit {
-                break;
-            }
             for indexed_value in indexed_values {
                 let symbol_index = &indices[indexed_value.index];
                 let (start, end) = SymbolIndex::map_value_to_range(indexed_value.value);
 
-                for symbol in &symbol_index.symbols[start..end] {
+                for (pos, symbol) in symbol_index.symbols[start..end].iter().enumerate() {
                     if self.only_types && !is_type(symbol.ptr.kind()) {
                         continue;
                     }
                     if self.exact && symbol.name != self.query {
                         continue;
                     }
-                    res.push(symbol.clone());
+                    let quality = self.match_quality(&symbol.name);
+                    let key = (quality, indexed_value.index, seq, start + pos);
+                    seq += 1;
+                    if best.len() < self.limit {
+                        best.push(key);
+                    } else if best.peek().map_or(false, |worst| key < *worst) {
+                        best.pop();
+                        best.push(key);
+                    }
                 }
             }
         }
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|(_, index, _, pos)| indices[index].symbols[pos].clone())
+            .collect()
+    }
+
+    /// Returns the functions matching the signature of the query, in the
+    /// order of the indices.
+    fn search_signature(self, db: &RootDatabase, indices: &[Arc<SymbolIndex>]) -> Vec<FileSymbol> {
+        let signature = match &self.signature {
+            Some(it) => it,
+            None => return Vec::new(),
+        };
+        // The symbols of libraries aren't grouped by file, so parse each
+        // file only once instead of relying on the LRU cache.
+        let mut files: FxHashMap<FileId, SourceFile> = FxHashMap::default();
+        let mut res = Vec::new();
+        for symbol in indices.iter().flat_map(|it| it.symbols.iter()) {
+            if res.len() == self.limit {
+                break;
+            }
+            if symbol.ptr.kind() != FN_DEF {
+                continue;
+            }
+            let file =
+                files.entry(symbol.file_id).or_insert_with(|| db.parse(symbol.file_id).tree());
+            let fn_def = match ast::FnDef::cast(symbol.ptr.to_node(file.syntax())) {
+                Some(it) => it,
+                None => continue,
+            };
+            if signature.matches(&fn_def) {
+                res.push(symbol.clone());
+            }
+        }
         res
     }
+
+    fn match_quality(&self, name: &str) -> MatchQuality {
+        if name == self.query {
+            return MatchQuality::Exact;
+        }
+        let name = name.to_lowercase();
+        if name == self.lowercased {
+            MatchQuality::ExactIgnoreCase
+        } else if name.starts_with(&self.lowercased) {
+            MatchQuality::Prefix
+        } else if name.contains(&self.lowercased) {
+            MatchQuality::Substring
+        } else {
+            MatchQuality::Fuzzy
+        }
+    }
+}
+
+/// How well a symbol name matches the query, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchQuality {
+    Exact,
+    ExactIgnoreCase,
+    Prefix,
+    Substring,
+    Fuzzy,
 }
 
 fn is_type(kind: SyntaxKind) -> bool {
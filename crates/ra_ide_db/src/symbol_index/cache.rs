@@ -0,0 +1,270 @@
+//! Saves the symbol indices of the libraries to disk, so that they don't have
+//! to be rebuilt on every start.
+//!
+//! An index is stored in a file named after a hash of the version of
+//! rust-analyzer and of the paths and contents of the library files, so a
+//! stale index is never loaded. Only the symbols are stored, the `fst` is
+//! rebuilt when an index is loaded. The oldest indices are removed when the
+//! cache grows over `MAX_CACHE_SIZE`.
+
+use std::{
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ra_db::{FileId, RelativePathBuf};
+use ra_syntax::{SmolStr, SyntaxKind, SyntaxNodePtr, TextRange, TextUnit};
+use rustc_hash::{FxHashMap, FxHasher};
+
+use super::{FileSymbol, SymbolIndex};
+
+const MAX_CACHE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// A directory with the symbol indices of the libraries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolCache {
+    dir: PathBuf,
+    version: String,
+}
+
+impl SymbolCache {
+    /// Creates a cache in `dir`. The indices saved by a different `version`
+    /// of rust-analyzer are ignored.
+    pub fn new(dir: PathBuf, version: String) -> SymbolCache {
+        SymbolCache { dir, version }
+    }
+
+    /// Loads the index of the library `files`, or builds it and saves it for
+    /// the next time.
+    pub(crate) fn load_or_build(
+        &self,
+        files: &[(FileId, RelativePathBuf, Arc<String>)],
+        build: impl FnOnce() -> SymbolIndex,
+    ) -> SymbolIndex {
+        // The files come in the order of the directory walk, which isn't
+        // guaranteed to be the same on the next start.
+        let mut files: Vec<_> =
+            files.iter().map(|(file_id, path, text)| (path, text, *file_id)).collect();
+        files.sort_by(|a, b| a.0.cmp(b.0));
+        let file_ids: Vec<FileId> = files.iter().map(|it| it.2).collect();
+
+        let path = self.dir.join(format!("{:016x}.symbols", self.key(&files)));
+        match read_index(&path, &file_ids) {
+            Ok(index) => return index,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => log::warn!("failed to load symbols from {}: {}", path.display(), err),
+        }
+
+        let index = build();
+        if let Err(err) = write_index(&path, &index, &file_ids) {
+            log::warn!("failed to save symbols to {}: {}", path.display(), err);
+        } else if let Err(err) = self.evict(MAX_CACHE_SIZE) {
+            log::warn!("failed to clean up {}: {}", self.dir.display(), err);
+        }
+        index
+    }
+
+    /// Removes the least recently saved indices until the cache takes at most
+    /// `max_size` bytes.
+    fn evict(&self, max_size: u64) -> io::Result<()> {
+        let mut indices = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().map_or(true, |it| it != "symbols") {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            indices.push((metadata.modified()?, metadata.len(), path));
+        }
+        indices.sort();
+
+        let mut size: u64 = indices.iter().map(|it| it.1).sum();
+        for (_, len, path) in indices {
+            if size <= max_size {
+                break;
+            }
+            fs::remove_file(&path)?;
+            size -= len;
+        }
+        Ok(())
+    }
+
+    fn key(&self, files: &[(&RelativePathBuf, &Arc<String>, FileId)]) -> u64 {
+        let mut hasher = FxHasher::default();
+        self.version.hash(&mut hasher);
+        for (path, text, _) in files {
+            path.as_str().hash(&mut hasher);
+            text.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+fn read_index(path: &Path, file_ids: &[FileId]) -> io::Result<SymbolIndex> {
+    let mut r = BufReader::new(fs::File::open(path)?);
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let n_symbols = read_u32(&mut r)?;
+    let mut symbols = Vec::with_capacity(n_symbols as usize);
+    for _ in 0..n_symbols {
+        let file_id = *file_ids
+            .get(read_u32(&mut r)? as usize)
+            .ok_or_else(|| invalid("file index out of bounds"))?;
+        let name = read_str(&mut r)?;
+        let kind = read_u16(&mut r)?;
+        if kind >= SyntaxKind::__LAST as u16 {
+            return Err(invalid("unknown syntax kind"));
+        }
+        let range = read_range(&mut r)?;
+        let name_range = if read_bool(&mut r)? { Some(read_range(&mut r)?) } else { None };
+        let container_name = if read_bool(&mut r)? { Some(read_str(&mut r)?) } else { None };
+        symbols.push(FileSymbol {
+            file_id,
+            name,
+            ptr: SyntaxNodePtr::from_range_and_kind(range, SyntaxKind::from(kind)),
+            name_range,
+            container_name,
+        });
+    }
+    Ok(SymbolIndex::new(symbols))
+}
+
+fn write_index(path: &Path, index: &SymbolIndex, file_ids: &[FileId]) -> io::Result<()> {
+    fs::create_dir_all(&path.parent().unwrap())?;
+    // Write to a temporary file first, so that a concurrent instance never
+    // reads a half-written index.
+    let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    let mut w = BufWriter::new(fs::File::create(&tmp_path)?);
+
+    let file_indices: FxHashMap<FileId, u32> =
+        file_ids.iter().enumerate().map(|(idx, &file_id)| (file_id, idx as u32)).collect();
+    write_u32(&mut w, index.symbols.len() as u32)?;
+    for symbol in index.symbols.iter() {
+        write_u32(&mut w, file_indices[&symbol.file_id])?;
+        write_str(&mut w, &symbol.name)?;
+        write_u16(&mut w, symbol.ptr.kind().into())?;
+        write_range(&mut w, symbol.ptr.range())?;
+        write_bool(&mut w, symbol.name_range.is_some())?;
+        if let Some(name_range) = symbol.name_range {
+            write_range(&mut w, name_range)?;
+        }
+        write_bool(&mut w, symbol.container_name.is_some())?;
+        if let Some(container_name) = &symbol.container_name {
+            write_str(&mut w, container_name)?;
+        }
+    }
+    w.flush()?;
+    drop(w);
+    fs::rename(&tmp_path, path)
+}
+
+fn read_u16(r: &mut impl Read) -> io::Result<u16> {
+    let mut buf = [0; 2];
+    r.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_bool(r: &mut impl Read) -> io::Result<bool> {
+    let mut buf = [0; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0] != 0)
+}
+
+fn read_str(r: &mut impl Read) -> io::Result<SmolStr> {
+    let mut buf = vec![0; read_u32(r)? as usize];
+    r.read_exact(&mut buf)?;
+    let text =
+        String::from_utf8(buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(SmolStr::new(text))
+}
+
+fn read_range(r: &mut impl Read) -> io::Result<TextRange> {
+    let start = read_u32(r)?;
+    let end = read_u32(r)?;
+    if end < start {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid range"));
+    }
+    Ok(TextRange::from_to(TextUnit::from(start), TextUnit::from(end)))
+}
+
+fn write_u16(w: &mut impl Write, value: u16) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_u32(w: &mut impl Write, value: u32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_bool(w: &mut impl Write, value: bool) -> io::Result<()> {
+    w.write_all(&[value as u8])
+}
+
+fn write_str(w: &mut impl Write, value: &str) -> io::Result<()> {
+    write_u32(w, value.len() as u32)?;
+    w.write_all(value.as_bytes())
+}
+
+fn write_range(w: &mut impl Write, range: TextRange) -> io::Result<()> {
+    write_u32(w, range.start().into())?;
+    write_u32(w, range.end().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::SourceFile;
+
+    use super::*;
+    use crate::symbol_index::source_file_to_file_symbols;
+
+    #[test]
+    fn test_symbol_cache_roundtrip() {
+        let text = "
+mod foo {
+    struct Foo;
+    fn bar() {}
+}
+macro_rules! baz { () => () }
+";
+        let file = SourceFile::parse(text).tree();
+        let index = SymbolIndex::new(source_file_to_file_symbols(&file, FileId(7)));
+        assert_eq!(index.len(), 4);
+
+        let path = std::env::temp_dir()
+            .join(format!("ra-symbol-cache-test-{}", std::process::id()))
+            .join("index.symbols");
+        write_index(&path, &index, &[FileId(3), FileId(7)]).unwrap();
+        let loaded = read_index(&path, &[FileId(3), FileId(7)]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded.unwrap().symbols, index.symbols);
+    }
+
+    #[test]
+    fn test_symbol_cache_eviction() {
+        let dir =
+            std::env::temp_dir().join(format!("ra-symbol-cache-evict-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in &["a.symbols", "b.symbols", "c.symbols"] {
+            fs::write(dir.join(name), [0u8; 10]).unwrap();
+        }
+        fs::write(dir.join("other"), [0u8; 10]).unwrap();
+
+        let cache = SymbolCache::new(dir.clone(), String::new());
+        cache.evict(25).unwrap();
+        let mut remaining: Vec<_> =
+            fs::read_dir(&dir).unwrap().map(|it| it.unwrap().file_name()).collect();
+        fs::remove_dir_all(&dir).unwrap();
+        remaining.sort();
+        assert_eq!(remaining.len(), 3);
+        assert_eq!(remaining.last().unwrap(), "other");
+    }
+}
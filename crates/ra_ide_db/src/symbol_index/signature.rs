@@ -0,0 +1,282 @@
+//! Matching of functions against an approximate signature, like
+//! `fn(&str) -> u32`.
+//!
+//! The matching is purely syntactic, so that it's cheap enough to run over
+//! all the functions of the workspace and the dependencies. Paths are compared
+//! by their last segment only, and `_`, generic parameters and `impl Trait`
+//! match any type.
+
+use ra_syntax::{
+    ast::{self, AstNode, NameOwner, SelfParamKind, TypeAscriptionOwner, TypeParamsOwner},
+    SmolStr, SourceFile,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SignatureQuery {
+    params: Vec<TypeShape>,
+    /// `None` if the query doesn't specify the return type.
+    ret: Option<TypeShape>,
+}
+
+impl SignatureQuery {
+    pub(crate) fn parse(input: &str) -> Option<SignatureQuery> {
+        if !input.starts_with("fn") {
+            return None;
+        }
+        let text = format!("type T = {};", input);
+        let file = SourceFile::parse(&text).ok().ok()?;
+        let fn_ptr = file.syntax().descendants().find_map(ast::FnPointerType::cast)?;
+        // Trailing garbage makes the query a name query instead.
+        if fn_ptr.syntax().text().to_string() != input {
+            return None;
+        }
+        let scope = Scope::default();
+        let params =
+            fn_ptr.param_list()?.params().map(|it| scope.shape_of(it.ascribed_type())).collect();
+        let ret = fn_ptr.ret_type().map(|it| scope.shape_of(it.type_ref()));
+        Some(SignatureQuery { params, ret })
+    }
+
+    pub(crate) fn matches(&self, fn_def: &ast::FnDef) -> bool {
+        let scope = Scope::of_fn(fn_def);
+        let param_list = match fn_def.param_list() {
+            Some(it) => it,
+            None => return false,
+        };
+        let self_param = param_list.self_param().map(|it| scope.shape_of_self_param(&it));
+        let params: Vec<TypeShape> = self_param
+            .into_iter()
+            .chain(param_list.params().map(|it| scope.shape_of(it.ascribed_type())))
+            .collect();
+        if !TypeShape::all_match(&self.params, &params) {
+            return false;
+        }
+        match &self.ret {
+            None => true,
+            Some(ret) => {
+                let fn_ret = match fn_def.ret_type() {
+                    Some(it) => scope.shape_of(it.type_ref()),
+                    None => TypeShape::Tuple(Vec::new()),
+                };
+                ret.matches(&fn_ret)
+            }
+        }
+    }
+}
+
+/// The parts of a type which are relevant for matching.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TypeShape {
+    Any,
+    /// The last segment of a path. A path without generic arguments matches
+    /// the same path with any arguments.
+    Path(SmolStr, Vec<TypeShape>),
+    Ref {
+        mutable: bool,
+        inner: Box<TypeShape>,
+    },
+    Ptr {
+        mutable: bool,
+        inner: Box<TypeShape>,
+    },
+    Tuple(Vec<TypeShape>),
+    Slice(Box<TypeShape>),
+    Array(Box<TypeShape>),
+    /// Types which aren't looked into, like `dyn Trait` or `fn()`, compared
+    /// by their text without whitespace.
+    Other(String),
+}
+
+impl TypeShape {
+    fn matches(&self, other: &TypeShape) -> bool {
+        match (self, other) {
+            (TypeShape::Any, _) | (_, TypeShape::Any) => true,
+            (TypeShape::Path(name, args), TypeShape::Path(other_name, other_args)) => {
+                name == other_name
+                    && (args.is_empty()
+                        || other_args.is_empty()
+                        || TypeShape::all_match(args, other_args))
+            }
+            (
+                TypeShape::Ref { mutable, inner },
+                TypeShape::Ref { mutable: other_mutable, inner: other_inner },
+            )
+            | (
+                TypeShape::Ptr { mutable, inner },
+                TypeShape::Ptr { mutable: other_mutable, inner: other_inner },
+            ) => mutable == other_mutable && inner.matches(other_inner),
+            (TypeShape::Tuple(fields), TypeShape::Tuple(other_fields)) => {
+                TypeShape::all_match(fields, other_fields)
+            }
+            (TypeShape::Slice(inner), TypeShape::Slice(other_inner))
+            | (TypeShape::Array(inner), TypeShape::Array(other_inner)) => {
+                inner.matches(other_inner)
+            }
+            (TypeShape::Other(text), TypeShape::Other(other_text)) => text == other_text,
+            _ => false,
+        }
+    }
+
+    fn all_match(shapes: &[TypeShape], others: &[TypeShape]) -> bool {
+        shapes.len() == others.len()
+            && shapes.iter().zip(others).all(|(it, other)| it.matches(other))
+    }
+}
+
+/// The names which have a special meaning in a signature.
+#[derive(Default)]
+struct Scope {
+    generic_params: Vec<SmolStr>,
+    self_ty: Option<TypeShape>,
+}
+
+impl Scope {
+    fn of_fn(fn_def: &ast::FnDef) -> Scope {
+        let mut scope = Scope::default();
+        scope.add_generic_params(fn_def);
+        let container = fn_def.syntax().parent().and_then(|it| it.parent());
+        if let Some(impl_def) = container.clone().and_then(ast::ImplDef::cast) {
+            scope.add_generic_params(&impl_def);
+            scope.self_ty = Some(scope.shape_of(impl_def.target_type()));
+        } else if let Some(trait_def) = container.and_then(ast::TraitDef::cast) {
+            scope.add_generic_params(&trait_def);
+        }
+        scope
+    }
+
+    fn add_generic_params(&mut self, owner: &impl TypeParamsOwner) {
+        let names = owner
+            .type_param_list()
+            .into_iter()
+            .flat_map(|it| it.type_params())
+            .filter_map(|it| it.name())
+            .map(|it| it.text().clone());
+        self.generic_params.extend(names);
+    }
+
+    fn shape_of_self_param(&self, self_param: &ast::SelfParam) -> TypeShape {
+        if let Some(ty) = self_param.ascribed_type() {
+            return self.shape_of(Some(ty));
+        }
+        let self_ty = Box::new(self.self_ty.clone().unwrap_or(TypeShape::Any));
+        match self_param.kind() {
+            SelfParamKind::Owned => *self_ty,
+            SelfParamKind::Ref => TypeShape::Ref { mutable: false, inner: self_ty },
+            SelfParamKind::MutRef => TypeShape::Ref { mutable: true, inner: self_ty },
+        }
+    }
+
+    fn shape_of(&self, ty: Option<ast::TypeRef>) -> TypeShape {
+        let ty = match ty {
+            Some(it) => it,
+            None => return TypeShape::Any,
+        };
+        match &ty {
+            ast::TypeRef::ParenType(it) => self.shape_of(it.type_ref()),
+            ast::TypeRef::TupleType(it) => {
+                TypeShape::Tuple(it.fields().map(|it| self.shape_of(Some(it))).collect())
+            }
+            ast::TypeRef::PathType(it) => {
+                let segment = match it.path().and_then(|it| it.segment()) {
+                    Some(it) => it,
+                    None => return TypeShape::Any,
+                };
+                let name = match segment.name_ref() {
+                    Some(it) => it.text().clone(),
+                    None => return TypeShape::Other(without_whitespace(segment.syntax())),
+                };
+                let args: Vec<TypeShape> = segment
+                    .type_arg_list()
+                    .into_iter()
+                    .flat_map(|it| it.type_args())
+                    .map(|it| self.shape_of(it.type_ref()))
+                    .collect();
+                if args.is_empty() {
+                    if self.generic_params.contains(&name) {
+                        return TypeShape::Any;
+                    }
+                    if name == "Self" {
+                        return self.self_ty.clone().unwrap_or(TypeShape::Any);
+                    }
+                }
+                TypeShape::Path(name, args)
+            }
+            ast::TypeRef::PointerType(it) => TypeShape::Ptr {
+                mutable: it.mut_token().is_some(),
+                inner: Box::new(self.shape_of(it.type_ref())),
+            },
+            ast::TypeRef::ArrayType(it) => TypeShape::Array(Box::new(self.shape_of(it.type_ref()))),
+            ast::TypeRef::SliceType(it) => TypeShape::Slice(Box::new(self.shape_of(it.type_ref()))),
+            ast::TypeRef::ReferenceType(it) => TypeShape::Ref {
+                mutable: it.mut_token().is_some(),
+                inner: Box::new(self.shape_of(it.type_ref())),
+            },
+            ast::TypeRef::PlaceholderType(_) | ast::TypeRef::ImplTraitType(_) => TypeShape::Any,
+            ast::TypeRef::NeverType(_)
+            | ast::TypeRef::FnPointerType(_)
+            | ast::TypeRef::ForType(_)
+            | ast::TypeRef::DynTraitType(_) => TypeShape::Other(without_whitespace(ty.syntax())),
+        }
+    }
+}
+
+fn without_whitespace(node: &ra_syntax::SyntaxNode) -> String {
+    node.text().to_string().split_whitespace().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(query: &str, code: &str, expected: bool) {
+        let query = SignatureQuery::parse(query).unwrap();
+        let file = SourceFile::parse(code).ok().unwrap();
+        let fn_def = file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        assert_eq!(query.matches(&fn_def), expected, "{}", code);
+    }
+
+    #[test]
+    fn parses_signature_queries() {
+        assert!(SignatureQuery::parse("fn(&str) -> u32").is_some());
+        assert!(SignatureQuery::parse("fn()").is_some());
+        assert!(SignatureQuery::parse("fn(&str) foo").is_none());
+        assert!(SignatureQuery::parse("fnord").is_none());
+    }
+
+    #[test]
+    fn matches_params_and_return_type() {
+        check("fn(&str) -> u32", "fn parse(s: &str) -> u32 {}", true);
+        check("fn(&str) -> u32", "fn parse(s: &str) -> u64 {}", false);
+        check("fn(&str) -> u32", "fn parse(s: &mut str) -> u32 {}", false);
+        check("fn(&str) -> u32", "fn parse(s: &str, radix: u32) -> u32 {}", false);
+        check("fn(&str)", "fn parse(s: &str) -> Option<u32> {}", true);
+        check("fn() -> ()", "fn main() {}", true);
+    }
+
+    #[test]
+    fn compares_paths_by_last_segment() {
+        check(
+            "fn(String) -> Vec<u8>",
+            "fn f(s: std::string::String) -> alloc::vec::Vec<u8> {}",
+            true,
+        );
+        check("fn(String) -> Vec", "fn f(s: String) -> Vec<u8> {}", true);
+        check("fn(String) -> Vec<u16>", "fn f(s: String) -> Vec<u8> {}", false);
+    }
+
+    #[test]
+    fn generic_params_match_anything() {
+        check("fn(&str) -> String", "fn f<T: AsRef<str>>(s: T) -> String {}", true);
+        check("fn(&str) -> String", "fn f(s: impl AsRef<str>) -> String {}", true);
+        check("fn(_, u8) -> _", "fn f(a: (u32, u32), b: u8) -> bool {}", true);
+    }
+
+    #[test]
+    fn methods_take_self_type_as_first_param() {
+        check("fn(&Foo) -> usize", "impl Foo { fn len(&self) -> usize {} }", true);
+        check("fn(&mut Foo)", "impl Foo { fn clear(&mut self) {} }", true);
+        check("fn(&Foo) -> usize", "impl Foo { fn len(&mut self) -> usize {} }", false);
+        check("fn(u8) -> Foo", "impl Foo { fn new(x: u8) -> Self {} }", true);
+        check("fn(&Bar) -> usize", "trait Len { fn len(&self) -> usize; }", true);
+    }
+}
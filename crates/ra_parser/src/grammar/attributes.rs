@@ -9,8 +9,10 @@ pub(super) fn inner_attributes(p: &mut Parser) {
 }
 
 pub(super) fn outer_attributes(p: &mut Parser) {
+    // Inner attributes in the wrong position are parsed as such and
+    // reported during validation.
     while p.at(T![#]) {
-        attribute(p, false)
+        attribute(p, p.nth(1) == T![!])
     }
 }
 
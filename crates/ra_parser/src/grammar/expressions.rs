@@ -568,6 +568,16 @@ fn arg_list(p: &mut Parser) {
     let m = p.start();
     p.bump(T!['(']);
     while !p.at(T![')']) && !p.at(EOF) {
+        // test_err arg_list_recovery
+        // fn foo() {
+        //     foo(bar, ;
+        //     baz.qux(;
+        // }
+        if p.at(T![;]) {
+            p.error("expected R_PAREN");
+            break;
+        }
+
         // test arg_with_attr
         // fn main() {
         //     foo(#[attr] 92)
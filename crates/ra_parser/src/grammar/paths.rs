@@ -5,6 +5,11 @@ use super::*;
 pub(super) const PATH_FIRST: TokenSet =
     token_set![IDENT, SELF_KW, SUPER_KW, CRATE_KW, COLON, L_ANGLE];
 
+// Tokens which may follow an unfinished path like `foo::`, these are left for
+// the enclosing expression, pattern or type instead of being eaten as errors.
+const PATH_RECOVERY_SET: TokenSet =
+    items::ITEM_RECOVERY_SET.union(token_set![T![;], T![,], T![')'], T![']'], T![=]]);
+
 pub(super) fn is_path_start(p: &Parser) -> bool {
     is_use_path_start(p) || p.at(T![<])
 }
@@ -84,8 +89,16 @@ fn path_segment(p: &mut Parser, mode: Mode, first: bool) {
             // test crate_path
             // use crate::foo;
             T![self] | T![super] | T![crate] => p.bump_any(),
+            // test_err path_segment_recovery
+            // fn foo() {
+            //     foo::;
+            //     bar(baz::);
+            //     match x {
+            //         Foo::=> (),
+            //     }
+            // }
             _ => {
-                p.err_recover("expected identifier", items::ITEM_RECOVERY_SET);
+                p.err_recover("expected identifier", PATH_RECOVERY_SET);
             }
         };
     }
@@ -2,6 +2,9 @@
 
 use super::*;
 
+// Tokens which end an unfinished argument list like `Vec<` while typing.
+const TYPE_ARG_LIST_RECOVERY_SET: TokenSet = token_set![T![;], T![=], LET_KW];
+
 pub(super) fn opt_type_arg_list(p: &mut Parser, colon_colon_required: bool) {
     let m;
     if p.at(T![::]) && p.nth(2) == T![<] {
@@ -16,6 +19,14 @@ pub(super) fn opt_type_arg_list(p: &mut Parser, colon_colon_required: bool) {
     }
 
     while !p.at(EOF) && !p.at(T![>]) {
+        // test_err type_arg_list_recovery
+        // fn foo() {
+        //     let v: Vec<
+        //     let m: HashMap<String, = HashMap::new();
+        // }
+        if p.at_ts(TYPE_ARG_LIST_RECOVERY_SET) {
+            break;
+        }
         type_arg(p);
         if !p.at(T![>]) && !p.expect(T![,]) {
             break;
@@ -7,11 +7,11 @@ mod google_cpu_profiler;
 use std::{
     cell::RefCell,
     collections::BTreeMap,
-    collections::HashSet,
+    collections::{HashSet, VecDeque},
     io::{stderr, Write},
     sync::{
-        atomic::{AtomicBool, Ordering},
-        RwLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex, RwLock,
     },
     time::{Duration, Instant},
 };
@@ -56,6 +56,19 @@ pub fn set_filter(f: Filter) {
     *old = filter_data;
 }
 
+/// Keeps the last `limit` profiles in memory, instead of printing them to
+/// stderr, so that they can be retrieved with `take_captured`. A `limit` of
+/// zero goes back to printing them.
+pub fn capture(limit: usize) {
+    CAPTURE_LIMIT.store(limit, Ordering::SeqCst);
+    CAPTURED.lock().unwrap().clear();
+}
+
+/// Returns the captured profiles, oldest first, and forgets them.
+pub fn take_captured() -> Vec<String> {
+    CAPTURED.lock().unwrap().drain(..).collect()
+}
+
 pub type Label = &'static str;
 
 /// This function starts a profiling scope in the current execution stack with a given description.
@@ -199,6 +212,10 @@ static PROFILING_ENABLED: AtomicBool = AtomicBool::new(false);
 
 static FILTER: Lazy<RwLock<FilterData>> = Lazy::new(Default::default);
 
+static CAPTURE_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+static CAPTURED: Lazy<Mutex<VecDeque<String>>> = Lazy::new(Default::default);
+
 thread_local!(static PROFILE_STACK: RefCell<ProfileStack> = RefCell::new(ProfileStack::new()));
 
 impl Drop for Profiler {
@@ -212,13 +229,24 @@ impl Drop for Profiler {
                     let level = stack.starts.len();
                     stack.messages.push(Message { level, duration, label, detail: detail.take() });
                     if level == 0 {
-                        let stdout = stderr();
                         let longer_than = stack.filter_data.longer_than;
                         // Convert to millis for comparison to avoid problems with rounding
                         // (otherwise we could print `0ms` despite user's `>0` filter when
                         // `duration` is just a few nanos).
                         if duration.as_millis() > longer_than.as_millis() {
-                            print(&stack.messages, longer_than, &mut stdout.lock());
+                            let capture_limit = CAPTURE_LIMIT.load(Ordering::Relaxed);
+                            if capture_limit > 0 {
+                                let mut buf = Vec::new();
+                                print(&stack.messages, longer_than, &mut buf);
+                                let mut captured = CAPTURED.lock().unwrap();
+                                captured.push_back(String::from_utf8_lossy(&buf).into_owned());
+                                while captured.len() > capture_limit {
+                                    captured.pop_front();
+                                }
+                            } else {
+                                let stdout = stderr();
+                                print(&stack.messages, longer_than, &mut stdout.lock());
+                            }
                         }
                         stack.messages.clear();
                     }
@@ -416,6 +444,20 @@ mod tests {
         profiling_function1();
     }
 
+    #[test]
+    fn test_capture() {
+        let s = vec!["profile1".to_string(), "profile2".to_string()];
+        set_filter(Filter::new(2, s, Duration::new(0, 0)));
+        capture(1);
+        profiling_function1();
+        profiling_function1();
+        let captured = take_captured();
+        capture(0);
+        // Other tests may profile concurrently, but only the last profile is kept.
+        assert_eq!(captured.len(), 1);
+        assert!(captured[0].contains("profile1"), "{}", captured[0]);
+    }
+
     fn profiling_function1() {
         let _p = profile("profile1");
         profiling_function2();
@@ -9,10 +9,11 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use cargo_metadata::{BuildScript, CargoOpt, Message, MetadataCommand, PackageId};
+use cargo_metadata::{Artifact, CargoOpt, DependencyKind, Metadata, MetadataCommand, PackageId};
 use ra_arena::{Arena, Idx};
 use ra_db::Edition;
 use rustc_hash::FxHashMap;
+use serde::Deserialize;
 
 /// `CargoWorkspace` represents the logical structure of, well, a Cargo
 /// workspace. It pretty closely mirrors `cargo metadata` output.
@@ -54,8 +55,17 @@ pub struct CargoConfig {
     /// This will be ignored if `cargo_all_features` is true.
     pub features: Vec<String>,
 
-    /// Runs cargo check on launch to figure out the correct values of OUT_DIR
+    /// Runs cargo check on launch to figure out the correct values of OUT_DIR,
+    /// and the cfgs and env vars set by the build scripts.
     pub load_out_dirs_from_check: bool,
+
+    /// The sources of the standard library, overriding `RUST_SRC_PATH` and
+    /// the discovery with `rustc --print sysroot`.
+    pub sysroot_src: Option<PathBuf>,
+
+    /// The target triple to analyze the code for, like `wasm32-unknown-unknown`,
+    /// instead of the host.
+    pub target: Option<String>,
 }
 
 impl Default for CargoConfig {
@@ -65,6 +75,8 @@ impl Default for CargoConfig {
             all_features: true,
             features: Vec::new(),
             load_out_dirs_from_check: false,
+            sysroot_src: None,
+            target: None,
         }
     }
 }
@@ -77,6 +89,9 @@ pub type Target = Idx<TargetData>;
 pub struct PackageData {
     pub version: String,
     pub name: String,
+    pub authors: Vec<String>,
+    pub description: Option<String>,
+    pub repository: Option<String>,
     pub manifest: PathBuf,
     pub targets: Vec<Target>,
     pub is_member: bool,
@@ -85,12 +100,30 @@ pub struct PackageData {
     pub features: Vec<String>,
     pub out_dir: Option<PathBuf>,
     pub proc_macro_dylib_path: Option<PathBuf>,
+    /// Cfgs set by `cargo:rustc-cfg` in the build script.
+    pub cfgs: Vec<String>,
+    /// Env vars set by `cargo:rustc-env` in the build script.
+    pub envs: Vec<(String, String)>,
 }
 
 #[derive(Debug, Clone)]
 pub struct PackageDependency {
     pub pkg: Package,
+    /// The name the dependency is imported with, which is the new name of a
+    /// renamed dependency.
     pub name: String,
+    pub kind: DepKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepKind {
+    /// Available to all targets, except the build script.
+    Normal,
+    /// Available to tests, benches and examples, and to the other targets
+    /// when they are compiled with `cfg(test)`.
+    Dev,
+    /// Only available to the build script.
+    Build,
 }
 
 #[derive(Debug, Clone)]
@@ -110,6 +143,7 @@ pub enum TargetKind {
     Example,
     Test,
     Bench,
+    BuildScript,
     Other,
 }
 
@@ -121,6 +155,7 @@ impl TargetKind {
                 "test" => TargetKind::Test,
                 "bench" => TargetKind::Bench,
                 "example" => TargetKind::Example,
+                "custom-build" => TargetKind::BuildScript,
                 "proc-macro" => TargetKind::Lib,
                 _ if kind.contains("lib") => TargetKind::Lib,
                 _ => continue,
@@ -134,6 +169,33 @@ impl PackageData {
     pub fn root(&self) -> &Path {
         self.manifest.parent().unwrap()
     }
+
+    /// The `CARGO_MANIFEST_DIR` and `CARGO_PKG_*` env vars Cargo sets when
+    /// compiling the crates of this package.
+    pub fn cargo_env(&self) -> Vec<(String, String)> {
+        // Build metadata (`+foo`) is not part of any of the version parts.
+        let version = self.version.splitn(2, '+').next().unwrap_or_default();
+        let mut version_and_pre = version.splitn(2, '-');
+        let mut parts = version_and_pre.next().unwrap_or_default().split('.');
+        let pre = version_and_pre.next().unwrap_or_default();
+
+        let mut res = vec![
+            // FIXME: We probably mangle non UTF-8 paths here, figure out a better solution
+            ("CARGO_MANIFEST_DIR", self.root().to_string_lossy().to_string()),
+            ("CARGO_PKG_NAME", self.name.clone()),
+            ("CARGO_PKG_VERSION", self.version.clone()),
+        ];
+        for &key in
+            ["CARGO_PKG_VERSION_MAJOR", "CARGO_PKG_VERSION_MINOR", "CARGO_PKG_VERSION_PATCH"].iter()
+        {
+            res.push((key, parts.next().unwrap_or_default().to_string()));
+        }
+        res.push(("CARGO_PKG_VERSION_PRE", pre.to_string()));
+        res.push(("CARGO_PKG_AUTHORS", self.authors.join(":")));
+        res.push(("CARGO_PKG_DESCRIPTION", self.description.clone().unwrap_or_default()));
+        res.push(("CARGO_PKG_REPOSITORY", self.repository.clone().unwrap_or_default()));
+        res.into_iter().map(|(key, value)| (key.to_string(), value)).collect()
+    }
 }
 
 impl CargoWorkspace {
@@ -159,23 +221,34 @@ impl CargoWorkspace {
             format!("Failed to run `cargo metadata --manifest-path {}`", cargo_toml.display())
         })?;
 
-        let mut out_dir_by_id = FxHashMap::default();
-        let mut proc_macro_dylib_paths = FxHashMap::default();
+        let mut resources = ExternResources::default();
         if cargo_features.load_out_dirs_from_check {
-            let resources = load_extern_resources(cargo_toml, cargo_features)?;
-            out_dir_by_id = resources.out_dirs;
-            proc_macro_dylib_paths = resources.proc_dylib_paths;
+            resources = load_extern_resources(cargo_toml, cargo_features)?;
         }
+        CargoWorkspace::new(meta, resources)
+    }
 
+    pub(crate) fn new(meta: Metadata, resources: ExternResources) -> Result<CargoWorkspace> {
         let mut pkg_by_id = FxHashMap::default();
+        let mut dep_kinds = FxHashMap::default();
         let mut packages = Arena::default();
         let mut targets = Arena::default();
 
         let ws_members = &meta.workspace_members;
 
         for meta_pkg in meta.packages {
-            let cargo_metadata::Package { id, edition, name, manifest_path, version, .. } =
-                meta_pkg;
+            let cargo_metadata::Package {
+                id,
+                edition,
+                name,
+                manifest_path,
+                version,
+                authors,
+                description,
+                repository,
+                dependencies,
+                ..
+            } = meta_pkg;
             let is_member = ws_members.contains(&id);
             let edition = edition
                 .parse::<Edition>()
@@ -183,17 +256,39 @@ impl CargoWorkspace {
             let pkg = packages.alloc(PackageData {
                 name,
                 version: version.to_string(),
+                authors,
+                description,
+                repository,
                 manifest: manifest_path,
                 targets: Vec::new(),
                 is_member,
                 edition,
                 dependencies: Vec::new(),
                 features: Vec::new(),
-                out_dir: out_dir_by_id.get(&id).cloned(),
-                proc_macro_dylib_path: proc_macro_dylib_paths.get(&id).cloned(),
+                out_dir: resources.out_dirs.get(&id).cloned(),
+                proc_macro_dylib_path: resources.proc_dylib_paths.get(&id).cloned(),
+                cfgs: resources.cfgs.get(&id).cloned().unwrap_or_default(),
+                envs: resources.envs.get(&id).cloned().unwrap_or_default(),
             });
             let pkg_data = &mut packages[pkg];
             pkg_by_id.insert(id, pkg);
+            // The resolve graph only knows the names the dependencies are
+            // imported with, which are the names of their lib targets unless
+            // they're renamed, so their kinds are looked up by the new name of
+            // a renamed dependency, and by the package otherwise.
+            let kinds = dependencies
+                .iter()
+                .map(|dep| {
+                    let rename = dep.rename.as_ref().map(|it| it.replace('-', "_"));
+                    let kind = match dep.kind {
+                        DependencyKind::Development => DepKind::Dev,
+                        DependencyKind::Build => DepKind::Build,
+                        _ => DepKind::Normal,
+                    };
+                    (dep.name.clone(), rename, kind)
+                })
+                .collect::<Vec<_>>();
+            dep_kinds.insert(pkg, kinds);
             for meta_tgt in meta_pkg.targets {
                 let is_proc_macro = meta_tgt.kind.as_slice() == ["proc-macro"];
                 let tgt = targets.alloc(TargetData {
@@ -229,8 +324,25 @@ impl CargoWorkspace {
                         continue;
                     }
                 };
-                let dep = PackageDependency { name: dep_node.name, pkg };
-                packages[source].dependencies.push(dep);
+                // A package can be several kinds of dependency at once, like a
+                // normal dependency which is a dev-dependency with more features.
+                let mut kinds: Vec<DepKind> = Vec::new();
+                for (package, rename, kind) in dep_kinds.get(&source).into_iter().flatten() {
+                    let is_same = match rename {
+                        Some(rename) => *rename == dep_node.name,
+                        None => *package == packages[pkg].name,
+                    };
+                    if is_same && !kinds.contains(kind) {
+                        kinds.push(*kind);
+                    }
+                }
+                if kinds.is_empty() {
+                    kinds.push(DepKind::Normal);
+                }
+                for kind in kinds {
+                    let dep = PackageDependency { name: dep_node.name.clone(), pkg, kind };
+                    packages[source].dependencies.push(dep);
+                }
             }
             packages[source].features.extend(node.features);
         }
@@ -270,6 +382,28 @@ impl CargoWorkspace {
 pub struct ExternResources {
     out_dirs: FxHashMap<PackageId, PathBuf>,
     proc_dylib_paths: FxHashMap<PackageId, PathBuf>,
+    cfgs: FxHashMap<PackageId, Vec<String>>,
+    envs: FxHashMap<PackageId, Vec<(String, String)>>,
+}
+
+/// The messages of `cargo check --message-format=json` we are interested in.
+///
+/// `cargo_metadata` doesn't expose the env vars set by build scripts, so the
+/// messages are parsed here.
+#[derive(Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum CheckMessage {
+    BuildScriptExecuted {
+        package_id: PackageId,
+        out_dir: PathBuf,
+        #[serde(default)]
+        cfgs: Vec<String>,
+        #[serde(default)]
+        env: Vec<(String, String)>,
+    },
+    CompilerArtifact(Artifact),
+    #[serde(other)]
+    Other,
 }
 
 pub fn load_extern_resources(
@@ -278,44 +412,53 @@ pub fn load_extern_resources(
 ) -> Result<ExternResources> {
     let mut cmd = Command::new(cargo_binary());
     cmd.args(&["check", "--message-format=json", "--manifest-path"]).arg(cargo_toml);
+    if let Some(target) = &cargo_features.target {
+        cmd.args(&["--target", target]);
+    }
     if cargo_features.all_features {
         cmd.arg("--all-features");
-    } else if cargo_features.no_default_features {
-        // FIXME: `NoDefaultFeatures` is mutual exclusive with `SomeFeatures`
-        // https://github.com/oli-obk/cargo_metadata/issues/79
-        cmd.arg("--no-default-features");
     } else {
-        cmd.args(&cargo_features.features);
+        if cargo_features.no_default_features {
+            cmd.arg("--no-default-features");
+        }
+        if !cargo_features.features.is_empty() {
+            cmd.arg("--features").arg(cargo_features.features.join(" "));
+        }
     }
 
     let output = cmd.output()?;
+    Ok(parse_check_output(&String::from_utf8_lossy(&output.stdout)))
+}
 
+fn parse_check_output(stdout: &str) -> ExternResources {
     let mut res = ExternResources::default();
 
-    for message in cargo_metadata::parse_messages(output.stdout.as_slice()) {
-        if let Ok(message) = message {
-            match message {
-                Message::BuildScriptExecuted(BuildScript { package_id, out_dir, .. }) => {
-                    res.out_dirs.insert(package_id, out_dir);
-                }
-
-                Message::CompilerArtifact(message) => {
-                    if message.target.kind.contains(&"proc-macro".to_string()) {
-                        let package_id = message.package_id;
-                        // Skip rmeta file
-                        if let Some(filename) =
-                            message.filenames.iter().filter(|name| is_dylib(name)).next()
-                        {
-                            res.proc_dylib_paths.insert(package_id, filename.clone());
-                        }
+    for line in stdout.lines() {
+        let message = match serde_json::from_str::<CheckMessage>(line) {
+            Ok(it) => it,
+            Err(_) => continue,
+        };
+        match message {
+            CheckMessage::BuildScriptExecuted { package_id, out_dir, cfgs, env } => {
+                res.out_dirs.insert(package_id.clone(), out_dir);
+                res.cfgs.insert(package_id.clone(), cfgs);
+                res.envs.insert(package_id, env);
+            }
+            CheckMessage::CompilerArtifact(message) => {
+                if message.target.kind.contains(&"proc-macro".to_string()) {
+                    let package_id = message.package_id;
+                    // Skip rmeta file
+                    if let Some(filename) =
+                        message.filenames.iter().filter(|name| is_dylib(name)).next()
+                    {
+                        res.proc_dylib_paths.insert(package_id, filename.clone());
                     }
                 }
-                Message::CompilerMessage(_) => (),
-                Message::Unknown => (),
             }
+            CheckMessage::Other => (),
         }
     }
-    Ok(res)
+    res
 }
 
 // FIXME: File a better way to know if it is a dylib
@@ -329,3 +472,146 @@ fn is_dylib(path: &Path) -> bool {
 fn cargo_binary() -> String {
     env::var("CARGO").unwrap_or_else(|_| "cargo".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_check_output() {
+        let stdout = r#"{"reason":"build-script-executed","package_id":"foo 0.1.0 (path+file:///foo)","linked_libs":[],"linked_paths":[],"cfgs":["has_foo","foo_version=\"2\""],"env":[["FOO_GEN","1"]],"out_dir":"/foo/target/debug/build/foo-1234/out"}
+{"reason":"compiler-message","package_id":"foo 0.1.0 (path+file:///foo)","message":{}}
+Compiling foo v0.1.0"#;
+        let res = parse_check_output(stdout);
+        let id = PackageId { repr: "foo 0.1.0 (path+file:///foo)".to_string() };
+        assert_eq!(res.out_dirs[&id], Path::new("/foo/target/debug/build/foo-1234/out"));
+        assert_eq!(res.cfgs[&id], vec!["has_foo".to_string(), "foo_version=\"2\"".to_string()]);
+        assert_eq!(res.envs[&id], vec![("FOO_GEN".to_string(), "1".to_string())]);
+        assert!(res.proc_dylib_paths.is_empty());
+    }
+
+    #[test]
+    fn test_cargo_env() {
+        let pkg = PackageData {
+            version: "1.2.3-beta.1+build.5".to_string(),
+            name: "foo".to_string(),
+            authors: vec!["A <a@example.com>".to_string(), "B".to_string()],
+            description: Some("Foo things".to_string()),
+            repository: None,
+            manifest: PathBuf::from("/foo/Cargo.toml"),
+            targets: Vec::new(),
+            is_member: true,
+            dependencies: Vec::new(),
+            edition: Edition::Edition2018,
+            features: Vec::new(),
+            out_dir: None,
+            proc_macro_dylib_path: None,
+            cfgs: Vec::new(),
+            envs: Vec::new(),
+        };
+        let env: FxHashMap<String, String> = pkg.cargo_env().into_iter().collect();
+        assert_eq!(env["CARGO_MANIFEST_DIR"], "/foo");
+        assert_eq!(env["CARGO_PKG_NAME"], "foo");
+        assert_eq!(env["CARGO_PKG_VERSION"], "1.2.3-beta.1+build.5");
+        assert_eq!(env["CARGO_PKG_VERSION_MAJOR"], "1");
+        assert_eq!(env["CARGO_PKG_VERSION_MINOR"], "2");
+        assert_eq!(env["CARGO_PKG_VERSION_PATCH"], "3");
+        assert_eq!(env["CARGO_PKG_VERSION_PRE"], "beta.1");
+        assert_eq!(env["CARGO_PKG_AUTHORS"], "A <a@example.com>:B");
+        assert_eq!(env["CARGO_PKG_DESCRIPTION"], "Foo things");
+        assert_eq!(env["CARGO_PKG_REPOSITORY"], "");
+    }
+
+    #[test]
+    fn test_dependency_kinds() {
+        // `bar` has a lib target named `baz`, and `qux` is renamed to `quux`.
+        let id = |name: &str| format!("{} 0.1.0", name);
+        let dep = |name: &str, kind: &str, rename: Option<&str>| {
+            json!({
+                "name": name,
+                "source": null,
+                "req": "*",
+                "kind": kind,
+                "rename": rename,
+                "optional": false,
+                "uses_default_features": true,
+                "features": [],
+                "target": null,
+                "registry": null,
+            })
+        };
+        let package = |name: &str, lib: &str, deps: Vec<Value>| {
+            json!({
+                "name": name,
+                "version": "0.1.0",
+                "authors": [],
+                "id": id(name),
+                "source": null,
+                "description": null,
+                "dependencies": deps,
+                "license": null,
+                "license_file": null,
+                "targets": [{
+                    "name": lib,
+                    "kind": ["lib"],
+                    "crate_types": ["lib"],
+                    "required-features": [],
+                    "src_path": format!("/ws/{}/src/lib.rs", name),
+                    "edition": "2018",
+                    "doctest": true,
+                }],
+                "features": {},
+                "manifest_path": format!("/ws/{}/Cargo.toml", name),
+                "categories": [],
+                "keywords": [],
+                "readme": null,
+                "repository": null,
+                "edition": "2018",
+                "metadata": null,
+                "links": null,
+                "publish": null,
+            })
+        };
+        let node = |name: &str, deps: &[(&str, &str)]| {
+            let node_deps: Vec<Value> =
+                deps.iter().map(|&(pkg, name)| json!({ "name": name, "pkg": id(pkg) })).collect();
+            json!({
+                "id": id(name),
+                "dependencies": deps.iter().map(|&(pkg, _)| id(pkg)).collect::<Vec<_>>(),
+                "deps": node_deps,
+                "features": [],
+            })
+        };
+        let metadata = json!({
+            "packages": [
+                package("foo", "foo", vec![
+                    dep("bar", "dev", None),
+                    dep("qux", "build", Some("quux")),
+                ]),
+                package("bar", "baz", Vec::new()),
+                package("qux", "qux", Vec::new()),
+            ],
+            "workspace_members": ["foo 0.1.0"],
+            "resolve": {
+                "nodes": [
+                    node("foo", &[("bar", "baz"), ("qux", "quux")]),
+                    node("bar", &[]),
+                    node("qux", &[]),
+                ],
+                "root": "foo 0.1.0",
+            },
+            "workspace_root": "/ws",
+            "target_directory": "/ws/target",
+            "version": 1,
+        });
+        let metadata = serde_json::from_value(metadata).unwrap();
+        let cargo = CargoWorkspace::new(metadata, ExternResources::default()).unwrap();
+
+        let foo = cargo.packages().find(|&it| cargo[it].name == "foo").unwrap();
+        let deps: Vec<(&str, DepKind)> =
+            cargo[foo].dependencies.iter().map(|it| (it.name.as_str(), it.kind)).collect();
+        assert_eq!(deps, [("baz", DepKind::Dev), ("quux", DepKind::Build)]);
+    }
+}
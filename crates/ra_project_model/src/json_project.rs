@@ -1,10 +1,21 @@
-//! FIXME: write short doc here
+//! `rust-project.json` describes the crates of a project which isn't built with
+//! Cargo, so that build systems like Buck, Bazel or make can provide the same
+//! information as `cargo metadata`.
+//!
+//! Relative paths are resolved against the directory of the file.
 
-use std::path::PathBuf;
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
 
+use anyhow::{Context, Result};
 use rustc_hash::{FxHashMap, FxHashSet};
 use serde::Deserialize;
 
+use crate::split_cfg;
+
 /// A root points to the directory which contains Rust crates. rust-analyzer watches all files in
 /// all roots. Roots might be nested.
 #[derive(Clone, Debug, Deserialize)]
@@ -17,10 +28,17 @@ pub struct Root {
 /// useful in creating the crate graph.
 #[derive(Clone, Debug, Deserialize)]
 pub struct Crate {
+    pub(crate) display_name: Option<String>,
     pub(crate) root_module: PathBuf,
     pub(crate) edition: Edition,
+    #[serde(default)]
     pub(crate) deps: Vec<Dep>,
+    /// Cfgs in the `rustc --cfg` syntax, like `unix` or `feature="foo"`.
+    #[serde(default)]
+    pub(crate) cfg: Vec<String>,
+    #[serde(default)]
     pub(crate) atom_cfgs: FxHashSet<String>,
+    #[serde(default)]
     pub(crate) key_value_cfgs: FxHashMap<String, String>,
     pub(crate) out_dir: Option<PathBuf>,
     pub(crate) proc_macro_dylib_path: Option<PathBuf>,
@@ -33,6 +51,8 @@ pub enum Edition {
     Edition2015,
     #[serde(rename = "2018")]
     Edition2018,
+    #[serde(rename = "2021")]
+    Edition2021,
 }
 
 /// Identifies a crate by position in the crates array.
@@ -51,6 +71,90 @@ pub struct Dep {
 /// Roots and crates that compose this Rust project.
 #[derive(Clone, Debug, Deserialize)]
 pub struct JsonProject {
+    /// The source of the standard library, `rustc --print sysroot` is used to
+    /// find it if this is missing.
+    pub(crate) sysroot_src: Option<PathBuf>,
     pub(crate) roots: Vec<Root>,
     pub(crate) crates: Vec<Crate>,
 }
+
+impl JsonProject {
+    pub fn load(path: &Path) -> Result<JsonProject> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open json file {}", path.display()))?;
+        let reader = BufReader::new(file);
+        let mut project: JsonProject = serde_json::from_reader(reader)
+            .with_context(|| format!("Failed to deserialize json file {}", path.display()))?;
+        if let Some(base) = path.parent() {
+            project.resolve_paths(base);
+        }
+        Ok(project)
+    }
+
+    fn resolve_paths(&mut self, base: &Path) {
+        let resolve = |path: &mut PathBuf| *path = base.join(&*path);
+        self.sysroot_src.iter_mut().for_each(resolve);
+        self.roots.iter_mut().for_each(|root| resolve(&mut root.path));
+        for krate in self.crates.iter_mut() {
+            resolve(&mut krate.root_module);
+            krate.out_dir.iter_mut().for_each(resolve);
+            krate.proc_macro_dylib_path.iter_mut().for_each(resolve);
+        }
+    }
+}
+
+impl Crate {
+    /// Returns the atom cfgs and the key-value ones, from both the `cfg` list
+    /// and the separate fields.
+    pub(crate) fn cfgs(&self) -> (Vec<&str>, Vec<(&str, &str)>) {
+        let mut atoms: Vec<&str> = self.atom_cfgs.iter().map(String::as_str).collect();
+        let mut key_values: Vec<(&str, &str)> =
+            self.key_value_cfgs.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        for cfg in self.cfg.iter() {
+            match split_cfg(cfg) {
+                (key, None) => atoms.push(key),
+                (key, Some(value)) => key_values.push((key, value)),
+            }
+        }
+        (atoms, key_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_project() {
+        let mut project: JsonProject = serde_json::from_str(
+            r#"{
+                "roots": ["src", "/abs/vendor"],
+                "crates": [
+                    {
+                        "display_name": "foo",
+                        "root_module": "src/lib.rs",
+                        "edition": "2018",
+                        "cfg": ["unix", "feature=\"bar\""]
+                    },
+                    {
+                        "root_module": "src/main.rs",
+                        "edition": "2015",
+                        "deps": [{ "crate": 0, "name": "foo" }],
+                        "atom_cfgs": ["test"]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        project.resolve_paths(Path::new("/project"));
+
+        assert_eq!(project.sysroot_src, None);
+        assert_eq!(project.roots[0].path, Path::new("/project/src"));
+        assert_eq!(project.roots[1].path, Path::new("/abs/vendor"));
+        assert_eq!(project.crates[0].root_module, Path::new("/project/src/lib.rs"));
+        assert_eq!(project.crates[0].display_name.as_deref(), Some("foo"));
+        assert_eq!(project.crates[0].cfgs(), (vec!["unix"], vec![("feature", "bar")]));
+        assert_eq!(project.crates[1].cfgs(), (vec!["test"], vec![]));
+        assert_eq!(project.crates[1].deps[0].krate, CrateId(0));
+    }
+}
@@ -6,8 +6,7 @@ mod sysroot;
 
 use std::{
     error::Error,
-    fs::{read_dir, File, ReadDir},
-    io::BufReader,
+    fs::{read_dir, ReadDir},
     path::{Path, PathBuf},
     process::Command,
 };
@@ -16,10 +15,9 @@ use anyhow::{bail, Context, Result};
 use ra_cfg::CfgOptions;
 use ra_db::{CrateGraph, CrateName, Edition, Env, ExternSource, ExternSourceId, FileId};
 use rustc_hash::FxHashMap;
-use serde_json::from_reader;
 
 pub use crate::{
-    cargo_workspace::{CargoConfig, CargoWorkspace, Package, Target, TargetKind},
+    cargo_workspace::{CargoConfig, CargoWorkspace, DepKind, Package, Target, TargetKind},
     json_project::JsonProject,
     sysroot::Sysroot,
 };
@@ -49,7 +47,7 @@ pub enum ProjectWorkspace {
     /// Project workspace was discovered by running `cargo metadata` and `rustc --print sysroot`.
     Cargo { cargo: CargoWorkspace, sysroot: Sysroot },
     /// Project workspace was manually specified using a `rust-project.json` file.
-    Json { project: JsonProject },
+    Json { project: JsonProject, sysroot: Sysroot },
 }
 
 /// `PackageRoot` describes a package root folder.
@@ -89,14 +87,20 @@ impl ProjectWorkspace {
     ) -> Result<ProjectWorkspace> {
         match find_rust_project_json(path) {
             Some(json_path) => {
-                let file = File::open(&json_path)
-                    .with_context(|| format!("Failed to open json file {}", json_path.display()))?;
-                let reader = BufReader::new(file);
-                Ok(ProjectWorkspace::Json {
-                    project: from_reader(reader).with_context(|| {
-                        format!("Failed to deserialize json file {}", json_path.display())
+                let project = JsonProject::load(&json_path)?;
+                let sysroot_src =
+                    cargo_features.sysroot_src.as_ref().or(project.sysroot_src.as_ref());
+                let sysroot = match sysroot_src {
+                    Some(src) => Sysroot::load(src)?,
+                    None if with_sysroot => Sysroot::discover(&json_path).with_context(|| {
+                        format!(
+                            "Failed to find sysroot for json file {}. Is rust-src installed?",
+                            json_path.display()
+                        )
                     })?,
-                })
+                    None => Sysroot::default(),
+                };
+                Ok(ProjectWorkspace::Json { project, sysroot })
             }
             None => {
                 let cargo_toml = find_cargo_toml(path).with_context(|| {
@@ -109,15 +113,15 @@ impl ProjectWorkspace {
                             cargo_toml.display()
                         )
                     })?;
-                let sysroot = if with_sysroot {
-                    Sysroot::discover(&cargo_toml).with_context(|| {
+                let sysroot = match &cargo_features.sysroot_src {
+                    Some(src) if with_sysroot => Sysroot::load(src)?,
+                    None if with_sysroot => Sysroot::discover(&cargo_toml).with_context(|| {
                         format!(
                             "Failed to find sysroot for Cargo.toml file {}. Is rust-src installed?",
                             cargo_toml.display()
                         )
-                    })?
-                } else {
-                    Sysroot::default()
+                    })?,
+                    _ => Sysroot::default(),
                 };
                 Ok(ProjectWorkspace::Cargo { cargo, sysroot })
             }
@@ -129,9 +133,14 @@ impl ProjectWorkspace {
     /// the root is a member of the current workspace
     pub fn to_roots(&self) -> Vec<PackageRoot> {
         match self {
-            ProjectWorkspace::Json { project } => {
-                project.roots.iter().map(|r| PackageRoot::new_member(r.path.clone())).collect()
-            }
+            ProjectWorkspace::Json { project, sysroot } => project
+                .roots
+                .iter()
+                .map(|r| PackageRoot::new_member(r.path.clone()))
+                .chain(sysroot.crates().map(|krate| {
+                    PackageRoot::new_non_member(sysroot[krate].root_dir().to_path_buf())
+                }))
+                .collect(),
             ProjectWorkspace::Cargo { cargo, sysroot } => cargo
                 .packages()
                 .map(|pkg| PackageRoot {
@@ -147,7 +156,7 @@ impl ProjectWorkspace {
 
     pub fn out_dirs(&self) -> Vec<PathBuf> {
         match self {
-            ProjectWorkspace::Json { project } => {
+            ProjectWorkspace::Json { project, sysroot: _ } => {
                 project.crates.iter().filter_map(|krate| krate.out_dir.as_ref()).cloned().collect()
             }
             ProjectWorkspace::Cargo { cargo, sysroot: _ } => {
@@ -158,7 +167,7 @@ impl ProjectWorkspace {
 
     pub fn proc_macro_dylib_paths(&self) -> Vec<PathBuf> {
         match self {
-            ProjectWorkspace::Json { project } => project
+            ProjectWorkspace::Json { project, sysroot: _ } => project
                 .crates
                 .iter()
                 .filter_map(|krate| krate.proc_macro_dylib_path.as_ref())
@@ -174,7 +183,9 @@ impl ProjectWorkspace {
 
     pub fn n_packages(&self) -> usize {
         match self {
-            ProjectWorkspace::Json { project } => project.crates.len(),
+            ProjectWorkspace::Json { project, sysroot } => {
+                project.crates.len() + sysroot.crates().len()
+            }
             ProjectWorkspace::Cargo { cargo, sysroot } => {
                 cargo.packages().len() + sysroot.crates().len()
             }
@@ -190,7 +201,10 @@ impl ProjectWorkspace {
     ) -> CrateGraph {
         let mut crate_graph = CrateGraph::default();
         match self {
-            ProjectWorkspace::Json { project } => {
+            ProjectWorkspace::Json { project, sysroot } => {
+                let (sysroot_deps, libproc_macro) =
+                    sysroot_to_crate_graph(&mut crate_graph, sysroot, default_cfg_options, load);
+
                 let crates: FxHashMap<_, _> = project
                     .crates
                     .iter()
@@ -200,13 +214,16 @@ impl ProjectWorkspace {
                         let edition = match krate.edition {
                             json_project::Edition::Edition2015 => Edition::Edition2015,
                             json_project::Edition::Edition2018 => Edition::Edition2018,
+                            json_project::Edition::Edition2021 => Edition::Edition2021,
                         };
                         let cfg_options = {
                             let mut opts = default_cfg_options.clone();
-                            for name in &krate.atom_cfgs {
+                            opts.insert_atom("test".into());
+                            let (atoms, key_values) = krate.cfgs();
+                            for name in atoms {
                                 opts.insert_atom(name.into());
                             }
-                            for (key, value) in &krate.key_value_cfgs {
+                            for (key, value) in key_values {
                                 opts.insert_key_value(key.into(), value.into());
                             }
                             opts
@@ -225,14 +242,12 @@ impl ProjectWorkspace {
                             .proc_macro_dylib_path
                             .clone()
                             .map(|it| proc_macro_client.by_dylib_path(&it));
-                        // FIXME: No crate name in json definition such that we cannot add OUT_DIR to env
                         Some((
                             json_project::CrateId(seq_index),
                             crate_graph.add_crate_root(
                                 file_id,
                                 edition,
-                                // FIXME json definitions can store the crate name
-                                None,
+                                krate.display_name.as_deref().map(CrateName::normalize_dashes),
                                 cfg_options,
                                 env,
                                 extern_source,
@@ -243,14 +258,16 @@ impl ProjectWorkspace {
                     .collect();
 
                 for (id, krate) in project.crates.iter().enumerate() {
+                    let from_crate_id = json_project::CrateId(id);
+                    let from = match crates.get(&from_crate_id) {
+                        Some(&it) => it,
+                        None => continue,
+                    };
                     for dep in &krate.deps {
-                        let from_crate_id = json_project::CrateId(id);
                         let to_crate_id = dep.krate;
-                        if let (Some(&from), Some(&to)) =
-                            (crates.get(&from_crate_id), crates.get(&to_crate_id))
-                        {
+                        if let Some(&to) = crates.get(&to_crate_id) {
                             if crate_graph
-                                .add_dep(from, CrateName::new(&dep.name).unwrap(), to)
+                                .add_dep(from, CrateName::normalize_dashes(&dep.name), to)
                                 .is_err()
                             {
                                 log::error!(
@@ -261,59 +278,26 @@ impl ProjectWorkspace {
                             }
                         }
                     }
-                }
-            }
-            ProjectWorkspace::Cargo { cargo, sysroot } => {
-                let sysroot_crates: FxHashMap<_, _> = sysroot
-                    .crates()
-                    .filter_map(|krate| {
-                        let file_id = load(&sysroot[krate].root)?;
-
-                        // Crates from sysroot have `cfg(test)` disabled
-                        let cfg_options = {
-                            let mut opts = default_cfg_options.clone();
-                            opts.remove_atom("test");
-                            opts
-                        };
-
-                        let env = Env::default();
-                        let extern_source = ExternSource::default();
-                        let proc_macro = vec![];
-                        let crate_name = CrateName::new(&sysroot[krate].name)
-                            .expect("Sysroot crate names should not contain dashes");
-
-                        let crate_id = crate_graph.add_crate_root(
-                            file_id,
-                            Edition::Edition2018,
-                            Some(crate_name),
-                            cfg_options,
-                            env,
-                            extern_source,
-                            proc_macro,
-                        );
-                        Some((krate, crate_id))
-                    })
-                    .collect();
 
-                for from in sysroot.crates() {
-                    for &to in sysroot[from].deps.iter() {
-                        let name = &sysroot[to].name;
-                        if let (Some(&from), Some(&to)) =
-                            (sysroot_crates.get(&from), sysroot_crates.get(&to))
-                        {
-                            if crate_graph.add_dep(from, CrateName::new(name).unwrap(), to).is_err()
-                            {
-                                log::error!("cyclic dependency between sysroot crates")
-                            }
+                    // The sysroot crates can be overridden by the explicit
+                    // dependencies, e.g. for `#![no_std]` crates.
+                    let has_dep = |name: &str| krate.deps.iter().any(|dep| dep.name == name);
+                    let proc_macro_dep = libproc_macro
+                        .filter(|_| krate.proc_macro_dylib_path.is_some())
+                        .map(|it| (CrateName::new("proc_macro").unwrap(), it));
+                    for (name, to) in sysroot_deps.iter().cloned().chain(proc_macro_dep) {
+                        if has_dep(&name.to_string()) {
+                            continue;
+                        }
+                        if crate_graph.add_dep(from, name.clone(), to).is_err() {
+                            log::error!("cyclic dependency on {} for {:?}", name, from_crate_id)
                         }
                     }
                 }
-
-                let libcore = sysroot.core().and_then(|it| sysroot_crates.get(&it).copied());
-                let liballoc = sysroot.alloc().and_then(|it| sysroot_crates.get(&it).copied());
-                let libstd = sysroot.std().and_then(|it| sysroot_crates.get(&it).copied());
-                let libproc_macro =
-                    sysroot.proc_macro().and_then(|it| sysroot_crates.get(&it).copied());
+            }
+            ProjectWorkspace::Cargo { cargo, sysroot } => {
+                let (sysroot_deps, libproc_macro) =
+                    sysroot_to_crate_graph(&mut crate_graph, sysroot, default_cfg_options, load);
 
                 let mut pkg_to_lib_crate = FxHashMap::default();
                 let mut pkg_crates = FxHashMap::default();
@@ -322,14 +306,36 @@ impl ProjectWorkspace {
                     let mut lib_tgt = None;
                     for &tgt in cargo[pkg].targets.iter() {
                         let root = cargo[tgt].root.as_path();
-                        if let Some(file_id) = load(root) {
+                        let file_id = match load(root) {
+                            Some(it) => it,
+                            None => continue,
+                        };
+                        let kind = cargo[tgt].kind;
+                        for &is_test in test_configs(kind, cargo[pkg].is_member) {
                             let edition = cargo[pkg].edition;
                             let cfg_options = {
                                 let mut opts = default_cfg_options.clone();
+                                if is_test {
+                                    opts.insert_atom("test".into());
+                                }
                                 opts.insert_features(cargo[pkg].features.iter().map(Into::into));
+                                for cfg in cargo[pkg].cfgs.iter() {
+                                    match split_cfg(cfg) {
+                                        (key, None) => opts.insert_atom(key.into()),
+                                        (key, Some(value)) => {
+                                            opts.insert_key_value(key.into(), value.into())
+                                        }
+                                    }
+                                }
                                 opts
                             };
                             let mut env = Env::default();
+                            for (key, value) in cargo[pkg].cargo_env() {
+                                env.set(&key, value);
+                            }
+                            for (key, value) in cargo[pkg].envs.iter() {
+                                env.set(key, value.clone());
+                            }
                             let mut extern_source = ExternSource::default();
                             if let Some(out_dir) = &cargo[pkg].out_dir {
                                 // FIXME: We probably mangle non UTF-8 paths here, figure out a better solution
@@ -351,9 +357,11 @@ impl ProjectWorkspace {
                                 cfg_options,
                                 env,
                                 extern_source,
-                                proc_macro.clone(),
+                                proc_macro,
                             );
-                            if cargo[tgt].kind == TargetKind::Lib {
+                            if is_test {
+                                crate_graph.set_test(crate_id);
+                            } else if kind == TargetKind::Lib {
                                 lib_tgt = Some((crate_id, cargo[tgt].name.clone()));
                                 pkg_to_lib_crate.insert(pkg, crate_id);
                             }
@@ -375,14 +383,19 @@ impl ProjectWorkspace {
                                 }
                             }
 
-                            pkg_crates.entry(pkg).or_insert_with(Vec::new).push(crate_id);
+                            pkg_crates
+                                .entry(pkg)
+                                .or_insert_with(Vec::new)
+                                .push((crate_id, kind, is_test));
                         }
                     }
 
                     // Set deps to the core, std and to the lib target of the current package
-                    for &from in pkg_crates.get(&pkg).into_iter().flatten() {
+                    for &(from, kind, _) in pkg_crates.get(&pkg).into_iter().flatten() {
                         if let Some((to, name)) = lib_tgt.clone() {
-                            if to != from
+                            // The test crate of the lib contains the lib itself.
+                            if kind != TargetKind::Lib
+                                && kind != TargetKind::BuildScript
                                 && crate_graph
                                     .add_dep(
                                         from,
@@ -402,30 +415,13 @@ impl ProjectWorkspace {
                                 }
                             }
                         }
-                        // core is added as a dependency before std in order to
-                        // mimic rustcs dependency order
-                        if let Some(core) = libcore {
-                            if crate_graph
-                                .add_dep(from, CrateName::new("core").unwrap(), core)
-                                .is_err()
-                            {
-                                log::error!("cyclic dependency on core for {}", &cargo[pkg].name)
-                            }
-                        }
-                        if let Some(alloc) = liballoc {
-                            if crate_graph
-                                .add_dep(from, CrateName::new("alloc").unwrap(), alloc)
-                                .is_err()
-                            {
-                                log::error!("cyclic dependency on alloc for {}", &cargo[pkg].name)
-                            }
-                        }
-                        if let Some(std) = libstd {
-                            if crate_graph
-                                .add_dep(from, CrateName::new("std").unwrap(), std)
-                                .is_err()
-                            {
-                                log::error!("cyclic dependency on std for {}", &cargo[pkg].name)
+                        for (name, to) in sysroot_deps.iter().cloned() {
+                            if crate_graph.add_dep(from, name.clone(), to).is_err() {
+                                log::error!(
+                                    "cyclic dependency on {} for {}",
+                                    name,
+                                    &cargo[pkg].name
+                                )
                             }
                         }
                     }
@@ -436,7 +432,16 @@ impl ProjectWorkspace {
                 for pkg in cargo.packages() {
                     for dep in cargo[pkg].dependencies.iter() {
                         if let Some(&to) = pkg_to_lib_crate.get(&dep.pkg) {
-                            for &from in pkg_crates.get(&pkg).into_iter().flatten() {
+                            for &(from, kind, is_test) in pkg_crates.get(&pkg).into_iter().flatten()
+                            {
+                                if !is_dep_visible(dep.kind, kind, is_test, cargo[pkg].is_member) {
+                                    continue;
+                                }
+                                // A dev-dependency can be a normal one as well.
+                                if crate_graph[from].dependencies.iter().any(|it| it.crate_id == to)
+                                {
+                                    continue;
+                                }
                                 if crate_graph
                                     .add_dep(from, CrateName::new(&dep.name).unwrap(), to)
                                     .is_err()
@@ -461,7 +466,7 @@ impl ProjectWorkspace {
             ProjectWorkspace::Cargo { cargo, .. } => {
                 Some(cargo.workspace_root()).filter(|root| path.starts_with(root))
             }
-            ProjectWorkspace::Json { project: JsonProject { roots, .. } } => roots
+            ProjectWorkspace::Json { project: JsonProject { roots, .. }, .. } => roots
                 .iter()
                 .find(|root| path.starts_with(&root.path))
                 .map(|root| root.path.as_ref()),
@@ -469,6 +474,105 @@ impl ProjectWorkspace {
     }
 }
 
+/// The configurations in which the crates of a target are analyzed, as
+/// whether each one is a test crate. Like `cargo test`, which compiles the
+/// libs and bins of workspace members a second time with `cfg(test)`, these
+/// get a test crate besides the normal one, so that test modules and their
+/// imports resolve without changing what dependents of the lib see.
+fn test_configs(kind: TargetKind, is_member: bool) -> &'static [bool] {
+    match kind {
+        TargetKind::Lib | TargetKind::Bin if is_member => &[false, true],
+        TargetKind::Test | TargetKind::Bench if is_member => &[true],
+        _ => &[false],
+    }
+}
+
+/// Whether a dependency of the given kind is visible from a crate of a
+/// target. Dev-dependencies are only visible from the test crates and the
+/// examples of workspace members.
+fn is_dep_visible(dep: DepKind, target: TargetKind, is_test: bool, is_member: bool) -> bool {
+    match (dep, target) {
+        (DepKind::Build, TargetKind::BuildScript) => true,
+        (DepKind::Build, _) | (_, TargetKind::BuildScript) => false,
+        (DepKind::Normal, _) => true,
+        (DepKind::Dev, TargetKind::Example) => is_member,
+        (DepKind::Dev, _) => is_test,
+    }
+}
+
+/// Splits a cfg in the `rustc --cfg` syntax, like `unix` or `feature="foo"`,
+/// into the key and the value.
+pub(crate) fn split_cfg(cfg: &str) -> (&str, Option<&str>) {
+    match cfg.find('=') {
+        None => (cfg, None),
+        Some(idx) => (cfg[..idx].trim(), Some(cfg[idx + 1..].trim().trim_matches('"'))),
+    }
+}
+
+/// Adds the sysroot crates to the `crate_graph`. Returns the crates every
+/// crate depends on, in the order rustc uses, and `proc_macro`.
+fn sysroot_to_crate_graph(
+    crate_graph: &mut CrateGraph,
+    sysroot: &Sysroot,
+    default_cfg_options: &CfgOptions,
+    load: &mut dyn FnMut(&Path) -> Option<FileId>,
+) -> (Vec<(CrateName, CrateId)>, Option<CrateId>) {
+    let sysroot_crates: FxHashMap<_, _> = sysroot
+        .crates()
+        .filter_map(|krate| {
+            let file_id = load(&sysroot[krate].root)?;
+
+            // Crates from sysroot have `cfg(test)` disabled
+            let cfg_options = {
+                let mut opts = default_cfg_options.clone();
+                opts.remove_atom("test");
+                opts
+            };
+
+            let env = Env::default();
+            let extern_source = ExternSource::default();
+            let proc_macro = vec![];
+            let crate_name = CrateName::new(&sysroot[krate].name)
+                .expect("Sysroot crate names should not contain dashes");
+
+            let crate_id = crate_graph.add_crate_root(
+                file_id,
+                Edition::Edition2018,
+                Some(crate_name),
+                cfg_options,
+                env,
+                extern_source,
+                proc_macro,
+            );
+            Some((krate, crate_id))
+        })
+        .collect();
+
+    for from in sysroot.crates() {
+        for &to in sysroot[from].deps.iter() {
+            let name = &sysroot[to].name;
+            if let (Some(&from), Some(&to)) = (sysroot_crates.get(&from), sysroot_crates.get(&to)) {
+                if crate_graph.add_dep(from, CrateName::new(name).unwrap(), to).is_err() {
+                    log::error!("cyclic dependency between sysroot crates")
+                }
+            }
+        }
+    }
+
+    // core is added as a dependency before std in order to mimic rustcs
+    // dependency order
+    let public_deps =
+        [("core", sysroot.core()), ("alloc", sysroot.alloc()), ("std", sysroot.std())]
+            .iter()
+            .filter_map(|&(name, krate)| {
+                let crate_id = *sysroot_crates.get(&krate?)?;
+                Some((CrateName::new(name).unwrap(), crate_id))
+            })
+            .collect();
+    let libproc_macro = sysroot.proc_macro().and_then(|it| sysroot_crates.get(&it).copied());
+    (public_deps, libproc_macro)
+}
+
 fn find_rust_project_json(path: &Path) -> Option<PathBuf> {
     if path.ends_with("rust-project.json") {
         return Some(path.to_path_buf());
@@ -550,7 +654,9 @@ fn find_cargo_toml(path: &Path) -> Result<PathBuf> {
     }
 }
 
-pub fn get_rustc_cfg_options() -> CfgOptions {
+/// Returns the cfgs rustc sets for the `target` triple, or for the host if it's
+/// `None`.
+pub fn get_rustc_cfg_options(target: Option<&str>) -> CfgOptions {
     let mut cfg_options = CfgOptions::default();
 
     // Some nightly-only cfgs, which are required for stdlib
@@ -565,10 +671,12 @@ pub fn get_rustc_cfg_options() -> CfgOptions {
 
     match (|| -> Result<String> {
         // `cfg(test)` and `cfg(debug_assertion)` are handled outside, so we suppress them here.
-        let output = Command::new("rustc")
-            .args(&["--print", "cfg", "-O"])
-            .output()
-            .context("Failed to get output from rustc --print cfg -O")?;
+        let mut cmd = Command::new("rustc");
+        cmd.args(&["--print", "cfg", "-O"]);
+        if let Some(target) = target {
+            cmd.args(&["--target", target]);
+        }
+        let output = cmd.output().context("Failed to get output from rustc --print cfg -O")?;
         if !output.status.success() {
             bail!(
                 "rustc --print cfg -O exited with exit code ({})",
@@ -592,8 +700,217 @@ pub fn get_rustc_cfg_options() -> CfgOptions {
                 }
             }
         }
-        Err(e) => log::error!("failed to get rustc cfgs: {}", e),
+        Err(e) => {
+            log::error!("failed to get rustc cfgs: {}", e);
+            // At least the most common cfgs can be derived from the name of
+            // the target.
+            for (key, value) in target.map(target_cfgs).into_iter().flatten() {
+                cfg_options.insert_key_value(key.into(), value.into());
+            }
+        }
     }
 
     cfg_options
 }
+
+/// Guesses `target_arch`, `target_os` and `target_pointer_width` from a target
+/// triple, like `thumbv7em-none-eabihf`.
+fn target_cfgs(target: &str) -> Vec<(&'static str, &str)> {
+    let mut parts = target.split('-');
+    let arch = parts.next().unwrap_or_default();
+    let (arch, pointer_width) = match arch {
+        "x86_64" => ("x86_64", "64"),
+        "aarch64" => ("aarch64", "64"),
+        "wasm32" => ("wasm32", "32"),
+        "msp430" => ("msp430", "16"),
+        "avr" => ("avr", "16"),
+        _ if arch.starts_with("thumb") || arch.starts_with("arm") => ("arm", "32"),
+        _ if arch.starts_with("riscv64") => ("riscv64", "64"),
+        _ if arch.starts_with("riscv32") => ("riscv32", "32"),
+        _ if arch.starts_with('i') && arch.ends_with("86") => ("x86", "32"),
+        _ => return Vec::new(),
+    };
+    let os = parts
+        .filter_map(|part| match part {
+            // `android` comes after `linux` in the triple, but takes precedence.
+            "android" => Some((0, "android")),
+            "darwin" => Some((1, "macos")),
+            "ios" | "linux" | "windows" | "freebsd" | "netbsd" | "openbsd" | "wasi" | "none" => {
+                Some((1, part))
+            }
+            _ => None,
+        })
+        .min()
+        .map_or("unknown", |(_, os)| os);
+    vec![("target_arch", arch), ("target_os", os), ("target_pointer_width", pointer_width)]
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_cfg::CfgExpr;
+    use serde_json::{json, Value};
+
+    use crate::cargo_workspace::ExternResources;
+
+    use super::*;
+
+    fn package(name: &str, targets: &[(&str, &str)], deps: &[(&str, Option<&str>)]) -> Value {
+        let targets: Vec<Value> = targets
+            .iter()
+            .map(|&(kind, path)| {
+                let target_name = if kind == "lib" { name.replace('-', "_") } else { kind.into() };
+                json!({
+                    "name": target_name,
+                    "kind": [kind],
+                    "crate_types": [kind],
+                    "required-features": [],
+                    "src_path": format!("/ws/{}/{}", name, path),
+                    "edition": "2018",
+                    "doctest": kind == "lib",
+                })
+            })
+            .collect();
+        let deps: Vec<Value> = deps
+            .iter()
+            .map(|&(dep, kind)| {
+                json!({
+                    "name": dep,
+                    "source": null,
+                    "req": "*",
+                    "kind": kind,
+                    "rename": null,
+                    "optional": false,
+                    "uses_default_features": true,
+                    "features": [],
+                    "target": null,
+                    "registry": null,
+                })
+            })
+            .collect();
+        json!({
+            "name": name,
+            "version": "0.1.0",
+            "authors": [],
+            "id": format!("{} 0.1.0", name),
+            "source": null,
+            "description": null,
+            "dependencies": deps,
+            "license": null,
+            "license_file": null,
+            "targets": targets,
+            "features": {},
+            "manifest_path": format!("/ws/{}/Cargo.toml", name),
+            "categories": [],
+            "keywords": [],
+            "readme": null,
+            "repository": null,
+            "edition": "2018",
+            "metadata": null,
+            "links": null,
+            "publish": null,
+        })
+    }
+
+    fn node(name: &str, deps: &[&str]) -> Value {
+        let id = |name: &str| format!("{} 0.1.0", name);
+        let node_deps: Vec<Value> = deps
+            .iter()
+            .map(|&dep| json!({ "name": dep.replace('-', "_"), "pkg": id(dep) }))
+            .collect();
+        json!({
+            "id": id(name),
+            "dependencies": deps.iter().map(|&dep| id(dep)).collect::<Vec<_>>(),
+            "deps": node_deps,
+            "features": [],
+        })
+    }
+
+    /// Renders the crates of a workspace with a member `foo`, which has a
+    /// dev-dependency on `foo-test`, which depends on `foo` in turn.
+    fn check_crate_graph(expected: &[&str]) {
+        let metadata = json!({
+            "packages": [
+                package("foo", &[("lib", "src/lib.rs"), ("test", "tests/it.rs")], &[
+                    ("bar", None),
+                    ("foo-test", Some("dev")),
+                ]),
+                package("foo-test", &[("lib", "src/lib.rs")], &[("foo", None)]),
+                package("bar", &[("lib", "src/lib.rs")], &[]),
+            ],
+            "workspace_members": ["foo 0.1.0"],
+            "resolve": {
+                "nodes": [
+                    node("foo", &["bar", "foo-test"]),
+                    node("foo-test", &["foo"]),
+                    node("bar", &[]),
+                ],
+                "root": "foo 0.1.0",
+            },
+            "workspace_root": "/ws",
+            "target_directory": "/ws/target",
+            "version": 1,
+        });
+        let metadata = serde_json::from_value(metadata).unwrap();
+        let cargo = CargoWorkspace::new(metadata, ExternResources::default()).unwrap();
+        let workspace = ProjectWorkspace::Cargo { cargo, sysroot: Sysroot::default() };
+
+        let mut paths = Vec::new();
+        let crate_graph = workspace.to_crate_graph(
+            &CfgOptions::default(),
+            &FxHashMap::default(),
+            &ProcMacroClient::dummy(),
+            &mut |path| {
+                paths.push(path.to_path_buf());
+                Some(FileId(paths.len() as u32 - 1))
+            },
+        );
+
+        let mut actual: Vec<String> = crate_graph
+            .iter()
+            .map(|krate| {
+                let data = &crate_graph[krate];
+                let mut deps: Vec<String> = data
+                    .dependencies
+                    .iter()
+                    .map(|dep| {
+                        let test = if crate_graph[dep.crate_id].is_test { " (test)" } else { "" };
+                        format!("{}{}", dep.name, test)
+                    })
+                    .collect();
+                deps.sort();
+                let has_test_cfg = data.cfg_options.check(&CfgExpr::Atom("test".into()));
+                format!(
+                    "{}{}{} -> [{}]",
+                    paths[data.root_file_id.0 as usize].display(),
+                    if data.is_test { " (test)" } else { "" },
+                    if has_test_cfg == Some(true) { " cfg(test)" } else { "" },
+                    deps.join(", ")
+                )
+            })
+            .collect();
+        actual.sort();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn dev_dependencies_are_only_visible_from_test_crates() {
+        check_crate_graph(&[
+            "/ws/bar/src/lib.rs -> []",
+            "/ws/foo-test/src/lib.rs -> [foo]",
+            "/ws/foo/src/lib.rs (test) cfg(test) -> [bar, foo_test]",
+            "/ws/foo/src/lib.rs -> [bar]",
+            "/ws/foo/tests/it.rs (test) cfg(test) -> [bar, foo, foo_test]",
+        ]);
+    }
+
+    #[test]
+    fn test_target_cfgs() {
+        let cfgs = |target| target_cfgs(target).into_iter().map(|(_, it)| it).collect::<Vec<_>>();
+        assert_eq!(cfgs("thumbv7em-none-eabihf"), ["arm", "none", "32"]);
+        assert_eq!(cfgs("wasm32-unknown-unknown"), ["wasm32", "unknown", "32"]);
+        assert_eq!(cfgs("x86_64-apple-darwin"), ["x86_64", "macos", "64"]);
+        assert_eq!(cfgs("aarch64-linux-android"), ["aarch64", "android", "64"]);
+        assert_eq!(cfgs("i686-pc-windows-msvc"), ["x86", "windows", "32"]);
+        assert!(cfgs("sparc64-unknown-linux-gnu").is_empty());
+    }
+}
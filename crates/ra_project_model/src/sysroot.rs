@@ -1,4 +1,4 @@
-//! FIXME: write short doc here
+//! Loads the crates of the standard library from the sources in the sysroot.
 
 use anyhow::{bail, Context, Result};
 use std::{
@@ -51,12 +51,31 @@ impl Sysroot {
         self.crates.iter().map(|(id, _data)| id)
     }
 
-    pub fn discover(cargo_toml: &Path) -> Result<Sysroot> {
-        let src = get_or_install_rust_src(cargo_toml)?;
+    /// Finds the standard library sources for the project at `project_file`,
+    /// a `Cargo.toml` or a `rust-project.json`.
+    ///
+    /// `RUST_SRC_PATH` takes precedence over the sysroot of `rustc --print
+    /// sysroot`, which is run in the directory of the `project_file`.
+    pub fn discover(project_file: &Path) -> Result<Sysroot> {
+        let src = get_or_install_rust_src(project_file)?;
+        Sysroot::load(&src)
+    }
+
+    /// Loads the standard library crates from the `src` directory, like
+    /// `lib/rustlib/src/rust/src` of the sysroot.
+    pub fn load(src: &Path) -> Result<Sysroot> {
         let mut sysroot = Sysroot { crates: Arena::default() };
-        for name in SYSROOT_CRATES.trim().lines() {
-            let root = src.join(format!("lib{}", name)).join("lib.rs");
-            if root.exists() {
+        for &(name, _deps) in SYSROOT_CRATES {
+            // Newer toolchains use `library/core/src/lib.rs` instead of
+            // `src/libcore/lib.rs`.
+            let root = [
+                src.join(format!("lib{}", name)).join("lib.rs"),
+                src.join(name).join("src/lib.rs"),
+            ]
+            .iter()
+            .find(|it| it.exists())
+            .cloned();
+            if let Some(root) = root {
                 sysroot.crates.alloc(SysrootCrateData {
                     name: name.into(),
                     root,
@@ -64,17 +83,23 @@ impl Sysroot {
                 });
             }
         }
-        if let Some(std) = sysroot.std() {
-            for dep in STD_DEPS.trim().lines() {
+        for &(name, deps) in SYSROOT_CRATES {
+            let krate = match sysroot.by_name(name) {
+                Some(it) => it,
+                None => continue,
+            };
+            for dep in deps {
                 if let Some(dep) = sysroot.by_name(dep) {
-                    sysroot.crates[std].deps.push(dep)
+                    sysroot.crates[krate].deps.push(dep)
                 }
             }
         }
-        if let Some(alloc) = sysroot.alloc() {
-            if let Some(core) = sysroot.core() {
-                sysroot.crates[alloc].deps.push(core);
-            }
+        if sysroot.core().is_none() {
+            bail!(
+                "can't load standard library from {}, `core` is missing\n\
+                try running `rustup component add rust-src` or set `RUST_SRC_PATH`",
+                src.display()
+            )
         }
         Ok(sysroot)
     }
@@ -110,28 +135,40 @@ fn run_command_in_cargo_dir(cargo_toml: &Path, program: &str, args: &[&str]) ->
     Ok(output)
 }
 
-fn get_or_install_rust_src(cargo_toml: &Path) -> Result<PathBuf> {
+fn get_or_install_rust_src(project_file: &Path) -> Result<PathBuf> {
     if let Ok(path) = env::var("RUST_SRC_PATH") {
         return Ok(path.into());
     }
-    let rustc_output = run_command_in_cargo_dir(cargo_toml, "rustc", &["--print", "sysroot"])?;
+    let rustc_output = run_command_in_cargo_dir(project_file, "rustc", &["--print", "sysroot"])?;
     let stdout = String::from_utf8(rustc_output.stdout)?;
     let sysroot_path = Path::new(stdout.trim());
-    let src_path = sysroot_path.join("lib/rustlib/src/rust/src");
 
-    if !src_path.exists() {
-        run_command_in_cargo_dir(cargo_toml, "rustup", &["component", "add", "rust-src"])?;
+    if let Some(src_path) = find_rust_src(sysroot_path) {
+        return Ok(src_path);
     }
-    if !src_path.exists() {
-        bail!(
+    // The sysroot might not be managed by rustup, so this is allowed to fail.
+    if let Err(err) =
+        run_command_in_cargo_dir(project_file, "rustup", &["component", "add", "rust-src"])
+    {
+        log::error!("failed to install rust-src: {:?}", err);
+    }
+    match find_rust_src(sysroot_path) {
+        Some(src_path) => Ok(src_path),
+        None => bail!(
             "can't load standard library from sysroot\n\
             {}\n\
             (discovered via `rustc --print sysroot`)\n\
             try running `rustup component add rust-src` or set `RUST_SRC_PATH`",
-            src_path.display(),
-        )
+            sysroot_path.display(),
+        ),
     }
-    Ok(src_path)
+}
+
+fn find_rust_src(sysroot_path: &Path) -> Option<PathBuf> {
+    ["lib/rustlib/src/rust/library", "lib/rustlib/src/rust/src"]
+        .iter()
+        .map(|it| sysroot_path.join(it))
+        .find(|it| it.exists())
 }
 
 impl SysrootCrateData {
@@ -140,44 +177,16 @@ impl SysrootCrateData {
     }
 }
 
-const SYSROOT_CRATES: &str = "
-std
-core
-alloc
-collections
-libc
-panic_unwind
-proc_macro
-rustc_unicode
-std_unicode
-test
-alloc_jemalloc
-alloc_system
-compiler_builtins
-getopts
-panic_unwind
-panic_abort
-rand
-term
-unwind
-build_helper
-rustc_asan
-rustc_lsan
-rustc_msan
-rustc_tsan
-syntax";
-
-const STD_DEPS: &str = "
-alloc
-alloc_jemalloc
-alloc_system
-core
-panic_abort
-rand
-compiler_builtins
-unwind
-rustc_asan
-rustc_lsan
-rustc_msan
-rustc_tsan
-build_helper";
+/// The crates of the standard library, with their dependencies on each other.
+const SYSROOT_CRATES: &[(&str, &[&str])] = &[
+    ("core", &[]),
+    ("alloc", &["core"]),
+    ("std", &["alloc", "core", "panic_abort", "panic_unwind", "profiler_builtins", "unwind"]),
+    ("panic_abort", &["core"]),
+    ("panic_unwind", &["alloc", "core", "unwind"]),
+    ("profiler_builtins", &["core"]),
+    ("unwind", &["core"]),
+    ("proc_macro", &["std"]),
+    ("term", &["std"]),
+    ("test", &["std", "term", "proc_macro"]),
+];
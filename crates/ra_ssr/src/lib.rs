@@ -0,0 +1,141 @@
+//! Structural Search Replace
+//!
+//! Allows searching the AST for code that matches one or more patterns and
+//! then replacing that code based on a template. A rule looks like this:
+//!
+//! ```text
+//! foo($a:expr, $b:expr) ==>> bar($b, $a)
+//! ```
+//!
+//! Matching is done on syntax trees, so whitespace and comments don't matter.
+//! The paths of the pattern are resolved at a position in the code, the lookup
+//! context, and paths in the code match them if they resolve to the same item.
+//! So the rule above also matches `m::foo(1, 2)` or an imported `foo`, if `foo`
+//! resolves to the same function at the lookup context.
+
+mod matching;
+mod parsing;
+mod replacing;
+#[cfg(test)]
+mod tests;
+
+use hir::{ModuleDef, PathResolution, Semantics, SemanticsScope};
+use ra_db::{FileId, FilePosition};
+use ra_ide_db::RootDatabase;
+use ra_syntax::{ast, AstNode, SyntaxNode};
+use ra_text_edit::TextEdit;
+use rustc_hash::FxHashMap;
+
+/// Represents an `$var` in an SSR rule.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Var(pub(crate) String);
+
+/// A single rule, like `foo($a:expr) ==>> bar($a)`. Rules are created by
+/// parsing them from strings.
+#[derive(Debug)]
+pub struct SsrRule {
+    pattern: SsrPattern,
+    template: SsrTemplate,
+}
+
+#[derive(Debug)]
+pub(crate) struct SsrPattern {
+    pub(crate) pattern: SyntaxNode,
+    pub(crate) vars: Vec<Var>,
+}
+
+#[derive(Debug)]
+pub(crate) struct SsrTemplate {
+    pub(crate) template: SyntaxNode,
+    pub(crate) placeholders: FxHashMap<SyntaxNode, Var>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SsrError(String);
+
+impl std::fmt::Display for SsrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Parse error: {}", self.0)
+    }
+}
+
+impl std::error::Error for SsrError {}
+
+/// Searches for matches of a set of rules and computes the replacements.
+pub struct MatchFinder<'db> {
+    sema: Semantics<'db, RootDatabase>,
+    lookup_context: FilePosition,
+    rules: Vec<ResolvedRule>,
+}
+
+/// A rule with the items which the paths of its pattern resolve to.
+struct ResolvedRule {
+    rule: SsrRule,
+    resolved_paths: FxHashMap<SyntaxNode, ModuleDef>,
+}
+
+impl<'db> MatchFinder<'db> {
+    /// Creates a finder which resolves the paths of the patterns at
+    /// `lookup_context`, usually the position of the cursor.
+    pub fn in_context(db: &'db RootDatabase, lookup_context: FilePosition) -> MatchFinder<'db> {
+        MatchFinder { sema: Semantics::new(db), lookup_context, rules: Vec::new() }
+    }
+
+    pub fn add_rule(&mut self, rule: SsrRule) {
+        let resolved_paths = match self.lookup_scope() {
+            Some(scope) => resolve_paths(&scope, &rule.pattern.pattern),
+            None => FxHashMap::default(),
+        };
+        self.rules.push(ResolvedRule { rule, resolved_paths });
+    }
+
+    fn lookup_scope(&self) -> Option<SemanticsScope<'db, RootDatabase>> {
+        let file = self.sema.parse(self.lookup_context.file_id);
+        let token = file.syntax().token_at_offset(self.lookup_context.offset).left_biased()?;
+        Some(self.sema.scope(&token.parent()))
+    }
+
+    /// Returns an edit which applies all the rules to the given file, or
+    /// `None` if nothing in the file matches.
+    pub fn edits_for_file(&self, file_id: FileId) -> Option<TextEdit> {
+        let file = self.sema.parse(file_id);
+        let mut matches = Vec::new();
+        for ResolvedRule { rule, resolved_paths } in &self.rules {
+            matches.extend(
+                matching::find_matches(&self.sema, &rule.pattern, resolved_paths, file.syntax())
+                    .into_iter()
+                    .map(|it| (it, &rule.template)),
+            );
+        }
+        if matches.is_empty() {
+            return None;
+        }
+        Some(replacing::replace(matches))
+    }
+}
+
+/// Resolves the outermost paths of the pattern without generic arguments.
+/// Paths which don't resolve to an item, like placeholders, are matched by how
+/// they're written instead.
+fn resolve_paths(
+    scope: &SemanticsScope<RootDatabase>,
+    pattern: &SyntaxNode,
+) -> FxHashMap<SyntaxNode, ModuleDef> {
+    let mut res = FxHashMap::default();
+    for path in pattern.descendants().filter_map(ast::Path::cast) {
+        let is_outermost = path.syntax().parent().and_then(ast::Path::cast).is_none();
+        let has_generic_args = path
+            .syntax()
+            .descendants()
+            .filter_map(ast::PathSegment::cast)
+            .any(|it| it.type_arg_list().is_some());
+        if !is_outermost || has_generic_args {
+            continue;
+        }
+        let def = hir::Path::from_ast(path.clone()).and_then(|it| scope.resolve_hir_path(&it));
+        if let Some(PathResolution::Def(def)) = def {
+            res.insert(path.syntax().clone(), def);
+        }
+    }
+    res
+}
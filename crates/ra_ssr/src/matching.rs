@@ -0,0 +1,243 @@
+//! Finds the places in the code which match an SSR pattern.
+
+use std::{collections::HashMap, iter::once};
+
+use hir::{ModuleDef, PathResolution, Semantics};
+use ra_ide_db::RootDatabase;
+use ra_syntax::{
+    ast::{
+        self, ArgList, AstToken, CallExpr, Comment, Expr, MethodCallExpr, RecordField, RecordLit,
+    },
+    AstNode, SyntaxElement, SyntaxKind, SyntaxNode,
+};
+use rustc_hash::FxHashMap;
+
+use crate::{SsrPattern, Var};
+
+pub(crate) type Binding = HashMap<Var, SyntaxNode>;
+
+#[derive(Debug)]
+pub(crate) struct Match {
+    pub(crate) place: SyntaxNode,
+    pub(crate) binding: Binding,
+    pub(crate) ignored_comments: Vec<Comment>,
+}
+
+pub(crate) fn find_matches(
+    sema: &Semantics<RootDatabase>,
+    pattern: &SsrPattern,
+    resolved_paths: &FxHashMap<SyntaxNode, ModuleDef>,
+    code: &SyntaxNode,
+) -> Vec<Match> {
+    let matcher = Matcher { sema, placeholders: &pattern.vars, resolved_paths };
+    let kind = pattern.pattern.kind();
+    code.descendants()
+        .filter(|n| {
+            n.kind() == kind
+                || (kind == SyntaxKind::CALL_EXPR && n.kind() == SyntaxKind::METHOD_CALL_EXPR)
+                || (kind == SyntaxKind::METHOD_CALL_EXPR && n.kind() == SyntaxKind::CALL_EXPR)
+        })
+        .filter_map(|code| {
+            let match_ =
+                Match { place: code.clone(), binding: HashMap::new(), ignored_comments: vec![] };
+            matcher.check(&pattern.pattern.clone().into(), &code.into(), match_)
+        })
+        .collect()
+}
+
+struct Matcher<'a, 'db> {
+    sema: &'a Semantics<'db, RootDatabase>,
+    placeholders: &'a [Var],
+    resolved_paths: &'a FxHashMap<SyntaxNode, ModuleDef>,
+}
+
+impl Matcher<'_, '_> {
+    fn check(&self, pattern: &SyntaxElement, code: &SyntaxElement, match_: Match) -> Option<Match> {
+        match (&pattern, &code) {
+            (SyntaxElement::Token(pattern), SyntaxElement::Token(code)) => {
+                if pattern.text() == code.text() {
+                    Some(match_)
+                } else {
+                    None
+                }
+            }
+            (SyntaxElement::Node(pattern), SyntaxElement::Node(code)) => {
+                self.check_node(pattern, code, match_)
+            }
+            _ => None,
+        }
+    }
+
+    fn check_node(
+        &self,
+        pattern: &SyntaxNode,
+        code: &SyntaxNode,
+        mut match_: Match,
+    ) -> Option<Match> {
+        if self.placeholders.iter().any(|n| n.0.as_str() == pattern.text()) {
+            match_.binding.insert(Var(pattern.text().to_string()), code.clone());
+            return Some(match_);
+        }
+        if let (Some(pattern), Some(code)) =
+            (RecordLit::cast(pattern.clone()), RecordLit::cast(code.clone()))
+        {
+            self.check_record_lit(pattern, code, match_)
+        } else if let (Some(pattern), Some(code)) =
+            (CallExpr::cast(pattern.clone()), MethodCallExpr::cast(code.clone()))
+        {
+            self.check_call_and_method_call(pattern, code, match_)
+        } else if let (Some(pattern), Some(code)) =
+            (MethodCallExpr::cast(pattern.clone()), CallExpr::cast(code.clone()))
+        {
+            self.check_method_call_and_call(pattern, code, match_)
+        } else if let (Some(pattern), Some(code)) =
+            (ast::Path::cast(pattern.clone()), ast::Path::cast(code.clone()))
+        {
+            self.check_path(pattern, code, match_)
+        } else {
+            self.check_children(pattern, code, match_)
+        }
+    }
+
+    fn check_children(
+        &self,
+        pattern: &SyntaxNode,
+        code: &SyntaxNode,
+        mut match_: Match,
+    ) -> Option<Match> {
+        let mut pattern_children =
+            pattern.children_with_tokens().filter(|element| !element.kind().is_trivia());
+        let mut code_children =
+            code.children_with_tokens().filter(|element| !element.kind().is_trivia());
+        let new_ignored_comments = code.children_with_tokens().filter_map(|element| {
+            element.as_token().and_then(|token| Comment::cast(token.clone()))
+        });
+        match_.ignored_comments.extend(new_ignored_comments);
+        pattern_children
+            .by_ref()
+            .zip(code_children.by_ref())
+            .fold(Some(match_), |accum, (a, b)| accum.and_then(|match_| self.check(&a, &b, match_)))
+            .filter(|_| pattern_children.next().is_none() && code_children.next().is_none())
+    }
+
+    /// Paths match if they resolve to the same item. Paths of the pattern
+    /// which don't resolve at the lookup context are compared by how they're
+    /// written.
+    fn check_path(&self, pattern: ast::Path, code: ast::Path, match_: Match) -> Option<Match> {
+        let pattern_def = match self.resolved_paths.get(pattern.syntax()) {
+            Some(&it) => it,
+            None => return self.check_children(pattern.syntax(), code.syntax(), match_),
+        };
+        match self.sema.resolve_path(&code)? {
+            PathResolution::Def(def) if def == pattern_def => Some(match_),
+            _ => None,
+        }
+    }
+
+    fn check_record_lit(
+        &self,
+        pattern: RecordLit,
+        code: RecordLit,
+        match_: Match,
+    ) -> Option<Match> {
+        let match_ = self.check_opt_nodes(pattern.path(), code.path(), match_)?;
+
+        let mut pattern_fields =
+            pattern.record_field_list().map(|x| x.fields().collect()).unwrap_or(vec![]);
+        let mut code_fields =
+            code.record_field_list().map(|x| x.fields().collect()).unwrap_or(vec![]);
+
+        if pattern_fields.len() != code_fields.len() {
+            return None;
+        }
+
+        let by_name = |a: &RecordField, b: &RecordField| {
+            a.name_ref()
+                .map(|x| x.syntax().text().to_string())
+                .cmp(&b.name_ref().map(|x| x.syntax().text().to_string()))
+        };
+        pattern_fields.sort_by(by_name);
+        code_fields.sort_by(by_name);
+
+        pattern_fields
+            .into_iter()
+            .zip(code_fields.into_iter())
+            .fold(Some(match_), |accum, (a, b)| {
+                accum.and_then(|match_| self.check_opt_nodes(Some(a), Some(b), match_))
+            })
+    }
+
+    fn check_call_and_method_call(
+        &self,
+        pattern: CallExpr,
+        code: MethodCallExpr,
+        match_: Match,
+    ) -> Option<Match> {
+        let (pattern_name, pattern_type_args) = if let Some(Expr::PathExpr(path_exr)) =
+            pattern.expr()
+        {
+            let segment = path_exr.path().and_then(|p| p.segment());
+            (segment.as_ref().and_then(|s| s.name_ref()), segment.and_then(|s| s.type_arg_list()))
+        } else {
+            (None, None)
+        };
+        let match_ = self.check_opt_nodes(pattern_name, code.name_ref(), match_)?;
+        let match_ = self.check_opt_nodes(pattern_type_args, code.type_arg_list(), match_)?;
+        let pattern_args = pattern.syntax().children().find_map(ArgList::cast)?.args();
+        let code_args = code.syntax().children().find_map(ArgList::cast)?.args();
+        let code_args = once(code.expr()?).chain(code_args);
+        self.check_iter(pattern_args, code_args, match_)
+    }
+
+    fn check_method_call_and_call(
+        &self,
+        pattern: MethodCallExpr,
+        code: CallExpr,
+        match_: Match,
+    ) -> Option<Match> {
+        let (code_name, code_type_args) = if let Some(Expr::PathExpr(path_exr)) = code.expr() {
+            let segment = path_exr.path().and_then(|p| p.segment());
+            (segment.as_ref().and_then(|s| s.name_ref()), segment.and_then(|s| s.type_arg_list()))
+        } else {
+            (None, None)
+        };
+        let match_ = self.check_opt_nodes(pattern.name_ref(), code_name, match_)?;
+        let match_ = self.check_opt_nodes(pattern.type_arg_list(), code_type_args, match_)?;
+        let code_args = code.syntax().children().find_map(ArgList::cast)?.args();
+        let pattern_args = pattern.syntax().children().find_map(ArgList::cast)?.args();
+        let pattern_args = once(pattern.expr()?).chain(pattern_args);
+        self.check_iter(pattern_args, code_args, match_)
+    }
+
+    fn check_opt_nodes(
+        &self,
+        pattern: Option<impl AstNode>,
+        code: Option<impl AstNode>,
+        match_: Match,
+    ) -> Option<Match> {
+        match (pattern, code) {
+            (Some(pattern), Some(code)) => {
+                self.check(&pattern.syntax().clone().into(), &code.syntax().clone().into(), match_)
+            }
+            (None, None) => Some(match_),
+            _ => None,
+        }
+    }
+
+    fn check_iter<T, I1, I2>(&self, mut pattern: I1, mut code: I2, match_: Match) -> Option<Match>
+    where
+        T: AstNode,
+        I1: Iterator<Item = T>,
+        I2: Iterator<Item = T>,
+    {
+        pattern
+            .by_ref()
+            .zip(code.by_ref())
+            .fold(Some(match_), |accum, (a, b)| {
+                accum.and_then(|match_| {
+                    self.check(&a.syntax().clone().into(), &b.syntax().clone().into(), match_)
+                })
+            })
+            .filter(|_| pattern.next().is_none() && code.next().is_none())
+    }
+}
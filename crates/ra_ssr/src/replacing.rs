@@ -0,0 +1,74 @@
+//! Renders the replacements for the found matches.
+
+use ra_syntax::{
+    ast::{AstToken, Comment},
+    SyntaxNode, TextRange,
+};
+use ra_text_edit::{TextEdit, TextEditBuilder};
+
+use crate::{matching::Match, SsrTemplate};
+
+/// Replaces all the matches with the corresponding templates. Matches inside
+/// the code bound to a placeholder of another match, like nested calls, are
+/// replaced first.
+pub(crate) fn replace(matches: Vec<(Match, &SsrTemplate)>) -> TextEdit {
+    let all: Vec<&(Match, &SsrTemplate)> = matches.iter().collect();
+    let mut builder = TextEditBuilder::default();
+    for (match_, template) in outermost(all.clone()) {
+        builder.replace(match_.place.text_range(), render_replace(match_, template, &all));
+    }
+    builder.finish()
+}
+
+/// Returns the matches which aren't inside of another one, in order.
+fn outermost<'a, 'b>(
+    mut matches: Vec<&'a (Match, &'b SsrTemplate)>,
+) -> Vec<&'a (Match, &'b SsrTemplate)> {
+    matches.sort_by_key(|(match_, _)| {
+        let range = match_.place.text_range();
+        (range.start(), std::cmp::Reverse(range.end()))
+    });
+    let mut last_end = None;
+    matches.retain(|(match_, _)| {
+        let range = match_.place.text_range();
+        if last_end.map_or(false, |end| range.start() < end) {
+            return false;
+        }
+        last_end = Some(range.end());
+        true
+    });
+    matches
+}
+
+fn render_replace(
+    match_: &Match,
+    template: &SsrTemplate,
+    all: &[&(Match, &SsrTemplate)],
+) -> String {
+    // A placeholder alone can match the code it binds.
+    let others: Vec<&(Match, &SsrTemplate)> =
+        all.iter().copied().filter(|(it, _)| it.place != match_.place).collect();
+    let mut builder = TextEditBuilder::default();
+    for element in template.template.descendants() {
+        if let Some(var) = template.placeholders.get(&element) {
+            builder.replace(element.text_range(), render_node(&match_.binding[var], &others))
+        }
+    }
+    for comment in &match_.ignored_comments {
+        builder.insert(template.template.text_range().end(), comment.syntax().to_string())
+    }
+    builder.finish().apply(&template.template.text().to_string())
+}
+
+/// Renders the code of the node, with the matches inside of it replaced.
+fn render_node(node: &SyntaxNode, all: &[&(Match, &SsrTemplate)]) -> String {
+    let range = node.text_range();
+    let inner = all.iter().copied().filter(|(it, _)| it.place.text_range().is_subrange(&range));
+    let mut builder = TextEditBuilder::default();
+    for (match_, template) in outermost(inner.collect()) {
+        let place = match_.place.text_range();
+        let place = TextRange::from_to(place.start() - range.start(), place.end() - range.start());
+        builder.replace(place, render_replace(match_, template, all));
+    }
+    builder.finish().apply(&node.text().to_string())
+}
@@ -0,0 +1,182 @@
+use ra_db::{fixture::WithFixture, FilePosition, SourceDatabaseExt};
+use ra_ide_db::RootDatabase;
+
+use crate::{MatchFinder, SsrRule};
+
+/// Applies the rule to the file, with the paths of the rule resolved at the
+/// start of the file.
+fn apply_ssr_rule(query: &str, input: &str) -> Option<String> {
+    let rule: SsrRule = query.parse().unwrap();
+    let (db, file_id) = RootDatabase::with_single_file(input);
+    let lookup_context = FilePosition { file_id, offset: 0.into() };
+    let mut match_finder = MatchFinder::in_context(&db, lookup_context);
+    match_finder.add_rule(rule);
+    match_finder.edits_for_file(file_id).map(|edit| edit.apply(input))
+}
+
+fn assert_ssr_transform(query: &str, input: &str, result: &str) {
+    let actual = apply_ssr_rule(query, input).expect("no matches");
+    assert_eq!(actual, result);
+}
+
+fn assert_no_match(query: &str, input: &str) {
+    assert_eq!(apply_ssr_rule(query, input), None);
+}
+
+#[test]
+fn ssr_match_replace() {
+    assert_ssr_transform(
+        "foo($x:expr) ==>> bar($x)",
+        "fn main() { foo(1+2); }",
+        "fn main() { bar(1+2); }",
+    );
+}
+
+#[test]
+fn ssr_function_to_method() {
+    assert_ssr_transform(
+        "my_function($a:expr, $b:expr) ==>> ($a).my_method($b)",
+        "loop { my_function( other_func(x, y), z + w) }",
+        "loop { (other_func(x, y)).my_method(z + w) }",
+    )
+}
+
+#[test]
+fn ssr_nested_function() {
+    assert_ssr_transform(
+        "foo($a:expr, $b:expr, $c:expr) ==>> bar($c, baz($a, $b))",
+        "fn main { foo  (x + value.method(b), x+y-z, true && false) }",
+        "fn main { bar(true && false, baz(x + value.method(b), x+y-z)) }",
+    )
+}
+
+#[test]
+fn ssr_expected_spacing() {
+    assert_ssr_transform(
+        "foo($x:expr) + bar() ==>> bar($x)",
+        "fn main() { foo(5) + bar() }",
+        "fn main() { bar(5) }",
+    );
+}
+
+#[test]
+fn ssr_with_extra_space() {
+    assert_ssr_transform(
+        "foo($x:expr  ) +    bar() ==>> bar($x)",
+        "fn main() { foo(  5 )  +bar(   ) }",
+        "fn main() { bar(5) }",
+    );
+}
+
+#[test]
+fn ssr_keeps_nested_comment() {
+    assert_ssr_transform(
+        "foo($x:expr) ==>> bar($x)",
+        "fn main() { foo(other(5 /* using 5 */)) }",
+        "fn main() { bar(other(5 /* using 5 */)) }",
+    )
+}
+
+#[test]
+fn ssr_keeps_comment() {
+    assert_ssr_transform(
+        "foo($x:expr) ==>> bar($x)",
+        "fn main() { foo(5 /* using 5 */) }",
+        "fn main() { bar(5)/* using 5 */ }",
+    )
+}
+
+#[test]
+fn ssr_struct_lit() {
+    assert_ssr_transform(
+        "foo{a: $a:expr, b: $b:expr} ==>> foo::new($a, $b)",
+        "fn main() { foo{b:2, a:1} }",
+        "fn main() { foo::new(1, 2) }",
+    )
+}
+
+#[test]
+fn ssr_call_and_method_call() {
+    assert_ssr_transform(
+        "foo::<'a>($a:expr, $b:expr)) ==>> foo2($a, $b)",
+        "fn main() { get().bar.foo::<'a>(1); }",
+        "fn main() { foo2(get().bar, 1); }",
+    )
+}
+
+#[test]
+fn ssr_method_call_and_call() {
+    assert_ssr_transform(
+        "$o:expr.foo::<i32>($a:expr)) ==>> $o.foo2($a)",
+        "fn main() { X::foo::<i32>(x, 1); }",
+        "fn main() { x.foo2(1); }",
+    )
+}
+
+#[test]
+fn ssr_resolves_imported_path() {
+    assert_ssr_transform(
+        "foo::bar($a:expr) ==>> foo::baz($a)",
+        "mod foo { pub fn bar(_: i32) {} pub fn baz(_: i32) {} } use foo::bar; fn main() { bar(1); }",
+        "mod foo { pub fn bar(_: i32) {} pub fn baz(_: i32) {} } use foo::bar; fn main() { foo::baz(1); }",
+    )
+}
+
+#[test]
+fn ssr_resolves_qualified_path() {
+    assert_ssr_transform(
+        "bar($a:expr) ==>> baz($a)",
+        "use foo::{bar, baz}; mod foo { pub fn bar(_: i32) {} pub fn baz(_: i32) {} } fn main() { foo::bar(1); }",
+        "use foo::{bar, baz}; mod foo { pub fn bar(_: i32) {} pub fn baz(_: i32) {} } fn main() { baz(1); }",
+    )
+}
+
+#[test]
+fn ssr_doesnt_match_path_which_doesnt_resolve_to_pattern_item() {
+    assert_no_match(
+        "bar($a:expr) ==>> baz($a)",
+        "use foo::bar; mod foo { pub fn bar(_: i32) {} } mod m { fn bar(_: i32) {} fn f() { bar(1); } }",
+    )
+}
+
+#[test]
+fn ssr_doesnt_match_other_item_with_same_name() {
+    assert_no_match(
+        "foo::bar($a:expr) ==>> foo::baz($a)",
+        "mod foo { pub fn bar(_: i32) {} } fn bar(_: i32) {} fn main() { bar(1); }",
+    )
+}
+
+#[test]
+fn ssr_replaces_nested_matches() {
+    assert_ssr_transform(
+        "foo($a:expr) ==>> bar($a)",
+        "fn main() { foo(foo(1)); }",
+        "fn main() { bar(bar(1)); }",
+    );
+    assert_ssr_transform(
+        "foo($a:expr, $b:expr) ==>> bar($b, $a)",
+        "fn main() { foo(foo(1, 2), x + foo(3, 4)); }",
+        "fn main() { bar(x + bar(4, 3), bar(2, 1)); }",
+    );
+}
+
+#[test]
+fn ssr_resolves_path_through_renamed_crate() {
+    let (db, position) = RootDatabase::with_position(
+        r#"
+//- /main.rs crate:main deps:renamed=dep
+use renamed::bar;
+fn main() { bar(1); }<|>
+
+//- /dep.rs crate:dep
+pub fn bar(_: i32) {}
+pub fn baz(_: i32) {}
+"#,
+    );
+    let mut match_finder = MatchFinder::in_context(&db, position);
+    match_finder.add_rule("renamed::bar($a:expr) ==>> renamed::baz($a)".parse().unwrap());
+    let text = db.file_text(position.file_id);
+    let actual = match_finder.edits_for_file(position.file_id).expect("no matches").apply(&text);
+    assert_eq!(actual, "use renamed::bar;\nfn main() { renamed::baz(1); }\n\n");
+}
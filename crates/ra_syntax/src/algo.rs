@@ -98,62 +98,104 @@ pub enum InsertPosition<T> {
     After(T),
 }
 
+#[derive(Debug, Default)]
 pub struct TreeDiff {
     replacements: FxHashMap<SyntaxElement, SyntaxElement>,
+    deletions: Vec<SyntaxElement>,
+    /// Elements of the new tree to insert at an offset of the old one.
+    insertions: Vec<(TextUnit, Vec<SyntaxElement>)>,
 }
 
 impl TreeDiff {
     pub fn into_text_edit(&self, builder: &mut TextEditBuilder) {
+        for (offset, elements) in self.insertions.iter() {
+            builder.insert(*offset, elements.iter().map(|it| it.to_string()).collect())
+        }
         for (from, to) in self.replacements.iter() {
             builder.replace(from.text_range(), to.to_string())
         }
+        for element in self.deletions.iter() {
+            builder.delete(element.text_range())
+        }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.replacements.is_empty()
+        self.replacements.is_empty() && self.deletions.is_empty() && self.insertions.is_empty()
     }
 }
 
 /// Finds minimal the diff, which, applied to `from`, will result in `to`.
 ///
 /// Specifically, returns a map whose keys are descendants of `from` and values
-/// are descendants of `to`, such that  `replace_descendants(from, map) == to`.
+/// are descendants of `to`, such that  `replace_descendants(from, map) == to`,
+/// together with the children which were deleted from or inserted into a node.
 ///
 /// A trivial solution is a singleton map `{ from: to }`, but this function
-/// tries to find a more fine-grained diff.
+/// tries to find a more fine-grained diff. The children which are the same at
+/// the start and at the end of a node are left alone, so that the trivia around
+/// an inserted or removed node is preserved.
 pub fn diff(from: &SyntaxNode, to: &SyntaxNode) -> TreeDiff {
-    let mut buf = FxHashMap::default();
+    let mut diff = TreeDiff::default();
     // FIXME: this is both horrible inefficient and gives larger than
     // necessary diff. I bet there's a cool algorithm to diff trees properly.
-    go(&mut buf, from.clone().into(), to.clone().into());
-    return TreeDiff { replacements: buf };
+    go(&mut diff, from.clone().into(), to.clone().into());
+    return diff;
 
-    fn go(
-        buf: &mut FxHashMap<SyntaxElement, SyntaxElement>,
-        lhs: SyntaxElement,
-        rhs: SyntaxElement,
-    ) {
-        if lhs.kind() == rhs.kind()
+    fn syntax_element_eq(lhs: &SyntaxElement, rhs: &SyntaxElement) -> bool {
+        lhs.kind() == rhs.kind()
             && lhs.text_range().len() == rhs.text_range().len()
-            && match (&lhs, &rhs) {
+            && match (lhs, rhs) {
                 (NodeOrToken::Node(lhs), NodeOrToken::Node(rhs)) => {
                     lhs.green() == rhs.green() || lhs.text() == rhs.text()
                 }
                 (NodeOrToken::Token(lhs), NodeOrToken::Token(rhs)) => lhs.text() == rhs.text(),
                 _ => false,
             }
-        {
+    }
+
+    fn go(diff: &mut TreeDiff, lhs: SyntaxElement, rhs: SyntaxElement) {
+        if syntax_element_eq(&lhs, &rhs) {
             return;
         }
-        if let (Some(lhs), Some(rhs)) = (lhs.as_node(), rhs.as_node()) {
-            if lhs.children_with_tokens().count() == rhs.children_with_tokens().count() {
-                for (lhs, rhs) in lhs.children_with_tokens().zip(rhs.children_with_tokens()) {
-                    go(buf, lhs, rhs)
-                }
+        let (lhs_node, rhs_node) = match (lhs.as_node(), rhs.as_node()) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => {
+                diff.replacements.insert(lhs, rhs);
                 return;
             }
+        };
+        let lhs_children = lhs_node.children_with_tokens().collect::<Vec<_>>();
+        let rhs_children = rhs_node.children_with_tokens().collect::<Vec<_>>();
+        if lhs_children.len() == rhs_children.len() {
+            for (lhs, rhs) in lhs_children.into_iter().zip(rhs_children) {
+                go(diff, lhs, rhs)
+            }
+            return;
+        }
+
+        let prefix = lhs_children
+            .iter()
+            .zip(rhs_children.iter())
+            .take_while(|(lhs, rhs)| syntax_element_eq(lhs, rhs))
+            .count();
+        let suffix = lhs_children[prefix..]
+            .iter()
+            .rev()
+            .zip(rhs_children[prefix..].iter().rev())
+            .take_while(|(lhs, rhs)| syntax_element_eq(lhs, rhs))
+            .count();
+        let deleted = &lhs_children[prefix..lhs_children.len() - suffix];
+        let inserted = &rhs_children[prefix..rhs_children.len() - suffix];
+
+        let offset = match (deleted.first(), prefix.checked_sub(1)) {
+            (Some(first), _) => first.text_range().start(),
+            (None, Some(last_kept)) => lhs_children[last_kept].text_range().end(),
+            (None, None) => lhs_node.text_range().start(),
+        };
+        diff.deletions.extend(deleted.iter().cloned());
+        if !inserted.is_empty() {
+            diff.insertions.push((offset, inserted.to_vec()));
         }
-        buf.insert(lhs, rhs);
     }
 }
 
@@ -240,17 +282,25 @@ pub struct SyntaxRewriter<'a> {
     f: Option<Box<dyn Fn(&SyntaxElement) -> Option<SyntaxElement> + 'a>>,
     //FIXME: add debug_assertions that all elements are in fact from the same file.
     replacements: FxHashMap<SyntaxElement, Replacement>,
+    insertions: FxHashMap<InsertPos, Vec<SyntaxElement>>,
 }
 
 impl fmt::Debug for SyntaxRewriter<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("SyntaxRewriter").field("replacements", &self.replacements).finish()
+        f.debug_struct("SyntaxRewriter")
+            .field("replacements", &self.replacements)
+            .field("insertions", &self.insertions)
+            .finish()
     }
 }
 
 impl<'a> SyntaxRewriter<'a> {
     pub fn from_fn(f: impl Fn(&SyntaxElement) -> Option<SyntaxElement> + 'a) -> SyntaxRewriter<'a> {
-        SyntaxRewriter { f: Some(Box::new(f)), replacements: FxHashMap::default() }
+        SyntaxRewriter {
+            f: Some(Box::new(f)),
+            replacements: FxHashMap::default(),
+            insertions: FxHashMap::default(),
+        }
     }
     pub fn delete<T: Clone + Into<SyntaxElement>>(&mut self, what: &T) {
         let what = what.clone().into();
@@ -262,12 +312,59 @@ impl<'a> SyntaxRewriter<'a> {
         let replacement = Replacement::Single(with.clone().into());
         self.replacements.insert(what, replacement);
     }
+    pub fn replace_with_many<T: Clone + Into<SyntaxElement>>(
+        &mut self,
+        what: &T,
+        with: Vec<SyntaxElement>,
+    ) {
+        let what = what.clone().into();
+        let replacement = Replacement::Many(with);
+        self.replacements.insert(what, replacement);
+    }
     pub fn replace_ast<T: AstNode>(&mut self, what: &T, with: &T) {
         self.replace(what.syntax(), with.syntax())
     }
+    pub fn insert_before<T: Clone + Into<SyntaxElement>, U: Clone + Into<SyntaxElement>>(
+        &mut self,
+        before: &T,
+        what: &U,
+    ) {
+        self.insert_many_before(before, Some(what.clone().into()))
+    }
+    pub fn insert_many_before<T: Clone + Into<SyntaxElement>>(
+        &mut self,
+        before: &T,
+        what: impl IntoIterator<Item = SyntaxElement>,
+    ) {
+        self.insert(InsertPos::Before(before.clone().into()), what)
+    }
+    pub fn insert_after<T: Clone + Into<SyntaxElement>, U: Clone + Into<SyntaxElement>>(
+        &mut self,
+        after: &T,
+        what: &U,
+    ) {
+        self.insert_many_after(after, Some(what.clone().into()))
+    }
+    pub fn insert_many_after<T: Clone + Into<SyntaxElement>>(
+        &mut self,
+        after: &T,
+        what: impl IntoIterator<Item = SyntaxElement>,
+    ) {
+        self.insert(InsertPos::After(after.clone().into()), what)
+    }
+    pub fn insert_as_first_child<U: Clone + Into<SyntaxElement>>(
+        &mut self,
+        parent: &SyntaxNode,
+        what: &U,
+    ) {
+        self.insert(InsertPos::FirstChildOf(parent.clone()), Some(what.clone().into()))
+    }
+    fn insert(&mut self, pos: InsertPos, what: impl IntoIterator<Item = SyntaxElement>) {
+        self.insertions.entry(pos).or_insert_with(Vec::new).extend(what)
+    }
 
     pub fn rewrite(&self, node: &SyntaxNode) -> SyntaxNode {
-        if self.f.is_none() && self.replacements.is_empty() {
+        if self.f.is_none() && self.replacements.is_empty() && self.insertions.is_empty() {
             return node.clone();
         }
         self.rewrite_children(node)
@@ -279,13 +376,19 @@ impl<'a> SyntaxRewriter<'a> {
 
     pub fn rewrite_root(&self) -> Option<SyntaxNode> {
         assert!(self.f.is_none());
-        self.replacements
-            .keys()
-            .map(|element| match element {
-                SyntaxElement::Node(it) => it.clone(),
-                SyntaxElement::Token(it) => it.parent(),
-            })
-            .fold1(|a, b| least_common_ancestor(&a, &b).unwrap())
+        let parent = |element: &SyntaxElement| match element {
+            SyntaxElement::Node(it) => it.parent().unwrap_or_else(|| it.clone()),
+            SyntaxElement::Token(it) => it.parent(),
+        };
+        let replaced = self.replacements.keys().map(|element| match element {
+            SyntaxElement::Node(it) => it.clone(),
+            SyntaxElement::Token(it) => it.parent(),
+        });
+        let inserted_into = self.insertions.keys().map(|pos| match pos {
+            InsertPos::FirstChildOf(it) => it.clone(),
+            InsertPos::Before(it) | InsertPos::After(it) => parent(it),
+        });
+        replaced.chain(inserted_into).fold1(|a, b| least_common_ancestor(&a, &b).unwrap())
     }
 
     fn replacement(&self, element: &SyntaxElement) -> Option<Replacement> {
@@ -296,40 +399,50 @@ impl<'a> SyntaxRewriter<'a> {
         self.replacements.get(element).cloned()
     }
 
+    fn insertions(&self, pos: &InsertPos) -> impl Iterator<Item = SyntaxElement> + '_ {
+        assert!(self.f.is_none() || self.insertions.is_empty());
+        self.insertions.get(pos).into_iter().flatten().cloned()
+    }
+
     fn rewrite_children(&self, node: &SyntaxNode) -> SyntaxNode {
         //  FIXME: this could be made much faster.
-        let new_children =
-            node.children_with_tokens().flat_map(|it| self.rewrite_self(&it)).collect::<Vec<_>>();
+        let mut new_children = Vec::new();
+        new_children
+            .extend(self.insertions(&InsertPos::FirstChildOf(node.clone())).map(to_green_element));
+        for child in node.children_with_tokens() {
+            self.rewrite_self(&mut new_children, &child);
+        }
         with_children(node, new_children)
     }
 
     fn rewrite_self(
         &self,
+        acc: &mut Vec<NodeOrToken<rowan::GreenNode, rowan::GreenToken>>,
         element: &SyntaxElement,
-    ) -> Option<NodeOrToken<rowan::GreenNode, rowan::GreenToken>> {
-        if let Some(replacement) = self.replacement(&element) {
-            return match replacement {
-                Replacement::Single(NodeOrToken::Node(it)) => {
-                    Some(NodeOrToken::Node(it.green().clone()))
-                }
-                Replacement::Single(NodeOrToken::Token(it)) => {
-                    Some(NodeOrToken::Token(it.green().clone()))
+    ) {
+        acc.extend(self.insertions(&InsertPos::Before(element.clone())).map(to_green_element));
+        match self.replacement(&element) {
+            Some(Replacement::Single(it)) => acc.push(to_green_element(it)),
+            Some(Replacement::Many(it)) => acc.extend(it.into_iter().map(to_green_element)),
+            Some(Replacement::Delete) => (),
+            None => acc.push(match element {
+                NodeOrToken::Token(it) => NodeOrToken::Token(it.green().clone()),
+                NodeOrToken::Node(it) => {
+                    NodeOrToken::Node(self.rewrite_children(it).green().clone())
                 }
-                Replacement::Delete => None,
-            };
+            }),
         }
-        let res = match element {
-            NodeOrToken::Token(it) => NodeOrToken::Token(it.green().clone()),
-            NodeOrToken::Node(it) => NodeOrToken::Node(self.rewrite_children(it).green().clone()),
-        };
-        Some(res)
+        acc.extend(self.insertions(&InsertPos::After(element.clone())).map(to_green_element));
     }
 }
 
 impl ops::AddAssign for SyntaxRewriter<'_> {
     fn add_assign(&mut self, rhs: SyntaxRewriter) {
         assert!(rhs.f.is_none());
-        self.replacements.extend(rhs.replacements)
+        self.replacements.extend(rhs.replacements);
+        for (pos, elements) in rhs.insertions {
+            self.insert(pos, elements)
+        }
     }
 }
 
@@ -337,6 +450,14 @@ impl ops::AddAssign for SyntaxRewriter<'_> {
 enum Replacement {
     Delete,
     Single(SyntaxElement),
+    Many(Vec<SyntaxElement>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum InsertPos {
+    FirstChildOf(SyntaxNode),
+    Before(SyntaxElement),
+    After(SyntaxElement),
 }
 
 fn with_children(
@@ -368,3 +489,59 @@ fn to_green_element(element: SyntaxElement) -> NodeOrToken<rowan::GreenNode, row
         NodeOrToken::Token(it) => it.green().clone().into(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{self, make},
+        SourceFile,
+    };
+
+    fn check_diff(from: &SyntaxNode, to: &SyntaxNode, expected: &str) {
+        let mut builder = TextEditBuilder::default();
+        diff(from, to).into_text_edit(&mut builder);
+        let edit = builder.finish();
+        assert_eq!(edit.apply(&from.to_string()), to.to_string());
+        assert_eq!(format!("{:?}", edit.as_atoms()), expected);
+    }
+
+    #[test]
+    fn diff_inserts_only_the_new_children() {
+        let from = SourceFile::parse("fn f() {\n    // keep me\n    foo();\n}\n").tree();
+        let to = SourceFile::parse("fn f() {\n    // keep me\n    foo();\n    bar();\n}\n").tree();
+        check_diff(
+            from.syntax(),
+            to.syntax(),
+            r#"[AtomTextEdit { delete: [34; 34), insert: "\n    bar();" }]"#,
+        );
+        check_diff(
+            to.syntax(),
+            from.syntax(),
+            r#"[AtomTextEdit { delete: [34; 39), insert: "" }, AtomTextEdit { delete: [39; 45), insert: "" }]"#,
+        );
+    }
+
+    #[test]
+    fn rewriter_inserts_and_preserves_trivia() {
+        let file = SourceFile::parse("fn f() {\n    foo(); // keep me\n}\n").tree();
+        let stmt = file.syntax().descendants().find_map(ast::ExprStmt::cast).unwrap();
+        let bar = make::path_unqualified(make::path_segment(make::name_ref("bar")));
+        let call = make::expr_call(make::expr_path(bar), make::arg_list(None));
+        let new_stmt = make::expr_stmt(call);
+
+        let mut rewriter = SyntaxRewriter::default();
+        rewriter.insert_many_before(
+            stmt.syntax(),
+            vec![new_stmt.syntax().clone().into(), make::tokens::whitespace("\n    ").into()],
+        );
+        assert_eq!(rewriter.rewrite_root(), stmt.syntax().parent());
+        let new = rewriter.rewrite(file.syntax());
+        assert_eq!(new.to_string(), "fn f() {\n    bar();\n    foo(); // keep me\n}\n");
+        check_diff(
+            file.syntax(),
+            &new,
+            r#"[AtomTextEdit { delete: [13; 13), insert: "bar();\n    " }]"#,
+        );
+    }
+}
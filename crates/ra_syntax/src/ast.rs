@@ -6,6 +6,7 @@ mod tokens;
 mod extensions;
 mod expr_extensions;
 pub mod edit;
+pub mod lifetimes;
 pub mod make;
 
 use std::marker::PhantomData;
@@ -310,3 +311,67 @@ where
     assert_eq!("for<'a> F", pred.type_ref().unwrap().syntax().text().to_string());
     assert_bound("Fn(&'a str)", bounds.next());
 }
+
+#[test]
+fn test_docs_include_doc_attributes_and_map_to_source() {
+    let text = r#"
+/// Call [`foo`]
+#[doc = "with \"bar\""]
+#[doc(hidden)]
+#[doc = r"and [`baz`]"]
+fn foo() {}
+"#;
+    let file = SourceFile::parse(text).ok().unwrap();
+    let func = file.syntax().descendants().find_map(FnDef::cast).unwrap();
+    let docs = func.docs().unwrap();
+    assert_eq!(docs.text(), "Call [`foo`]\nwith \"bar\"\nand [`baz`]");
+    assert_eq!(func.doc_comment_text().unwrap(), docs.text());
+
+    let foo_in_docs = crate::TextUnit::of_str("Call [`");
+    let foo_in_source = docs.map_offset_up(foo_in_docs).unwrap();
+    assert_eq!(&text[foo_in_source.to_usize()..][..3], "foo");
+    assert_eq!(docs.map_offset_down(foo_in_source), Some(foo_in_docs));
+
+    let baz_in_docs = crate::TextUnit::from_usize(docs.text().find("baz").unwrap());
+    let baz_in_source = docs.map_offset_up(baz_in_docs).unwrap();
+    assert_eq!(&text[baz_in_source.to_usize()..][..3], "baz");
+
+    // The escaped string only maps to its start.
+    let bar_in_docs = crate::TextUnit::from_usize(docs.text().find("bar").unwrap());
+    let bar_in_source = docs.map_offset_up(bar_in_docs).unwrap();
+    assert_eq!(&text[bar_in_source.to_usize()..][..4], "with");
+    assert_eq!(docs.map_offset_down(bar_in_source), None);
+}
+
+#[test]
+fn test_literal_values() {
+    fn token<T: AstToken>(text: &str) -> T {
+        let file = SourceFile::parse(&format!("const _: () = {};", text)).tree();
+        let literal = file.syntax().descendants().find_map(Literal::cast).unwrap();
+        T::cast(literal.token()).unwrap()
+    }
+
+    let int: IntNumber = token("0x_ff_u8");
+    assert_eq!(int.radix(), Radix::Hexadecimal);
+    assert_eq!(int.split_into_parts(), ("0x", "_ff_", "u8"));
+    assert_eq!(int.value(), Some(255));
+    assert_eq!(token::<IntNumber>("1_000").value(), Some(1000));
+    assert_eq!(token::<IntNumber>("0b102").value(), None);
+
+    let float: FloatNumber = token("1.5e-3f32");
+    assert_eq!(float.split_into_parts(), ("1.5e-3", "f32"));
+    assert_eq!(float.value(), Some(1.5e-3));
+    assert_eq!(token::<FloatNumber>("2.").suffix(), None);
+
+    assert_eq!(token::<Char>(r"'\u{1F600}'").value(), Some('\u{1F600}'));
+    assert_eq!(token::<Byte>(r"b'\x7f'").value(), Some(0x7f));
+    assert_eq!(token::<RawByteString>(r##"br#"\n"#"##).value(), Some(br"\n".to_vec()));
+    assert_eq!(token::<ByteString>(r#"b"a\tb""#).unescape().unwrap().value, b"a\tb".to_vec());
+
+    let string: String = token(r#""a\qb\n""#);
+    let unescaped = string.unescape().unwrap();
+    assert_eq!(unescaped.value, "ab\n");
+    assert_eq!(unescaped.errors.len(), 1);
+    assert_eq!(unescaped.errors[0].range(), crate::TextRange::from_to(16.into(), 18.into()));
+    assert_eq!(string.value(), None);
+}
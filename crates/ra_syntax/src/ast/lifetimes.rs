@@ -0,0 +1,80 @@
+//! Finds the lifetimes in the signature of an item, and picks names for new
+//! lifetime parameters, for the features which spell out elided lifetimes.
+
+use crate::{
+    ast::{self, AstNode, TypeParamsOwner},
+    SyntaxKind, SyntaxNode, SyntaxToken, TextRange,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lifetime {
+    /// The range of the `'_`, or the empty range after the `&`.
+    Elided(TextRange),
+    Named(String),
+}
+
+/// Finds the lifetimes of the references and generic arguments in a type, in
+/// the order in which they appear.
+pub fn lifetimes(ty: &SyntaxNode) -> Vec<Lifetime> {
+    ty.descendants()
+        .filter(|it| !is_in_nested_scope(it, ty))
+        .filter_map(|it| {
+            if let Some(reference) = ast::ReferenceType::cast(it.clone()) {
+                lifetime_of(reference.amp_token(), reference.lifetime_token())
+            } else {
+                lifetime_of(None, ast::LifetimeArg::cast(it)?.lifetime_token())
+            }
+        })
+        .collect()
+}
+
+/// The lifetime of a reference or of a `&self` parameter.
+pub fn lifetime_of(amp: Option<SyntaxToken>, lifetime: Option<SyntaxToken>) -> Option<Lifetime> {
+    match lifetime {
+        Some(it) if it.text() == "'_" => Some(Lifetime::Elided(it.text_range())),
+        Some(it) => Some(Lifetime::Named(it.text().to_string())),
+        None => {
+            let offset = amp?.text_range().end();
+            Some(Lifetime::Elided(TextRange::offset_len(offset, 0.into())))
+        }
+    }
+}
+
+/// Whether `node` is in a `fn` pointer, `Fn` trait or higher-ranked type
+/// within `root`, which have their own lifetime scope.
+pub fn is_in_nested_scope(node: &SyntaxNode, root: &SyntaxNode) -> bool {
+    node.ancestors().take_while(|it| it != root).any(|it| match it.kind() {
+        SyntaxKind::FN_POINTER_TYPE | SyntaxKind::FOR_TYPE => true,
+        SyntaxKind::PARAM_LIST | SyntaxKind::RET_TYPE => {
+            it.parent().map_or(false, |it| it.kind() == SyntaxKind::PATH_SEGMENT)
+        }
+        _ => false,
+    })
+}
+
+/// The lifetime parameters declared by a function and by its impl or trait.
+pub fn declared_lifetimes(fn_def: &ast::FnDef) -> Vec<String> {
+    let container = fn_def.syntax().parent().and_then(|it| it.parent());
+    let container_params = container.and_then(|it| match ast::ImplDef::cast(it.clone()) {
+        Some(impl_def) => impl_def.type_param_list(),
+        None => ast::TraitDef::cast(it)?.type_param_list(),
+    });
+    fn_def
+        .type_param_list()
+        .into_iter()
+        .chain(container_params)
+        .flat_map(|it| it.lifetime_params())
+        .filter_map(|it| Some(it.lifetime_token()?.text().to_string()))
+        .collect()
+}
+
+/// Picks the first of `'a`, `'b`, ... which isn't in `taken`.
+pub fn fresh_lifetime(taken: &[String]) -> String {
+    (0..)
+        .map(|i| match i {
+            0..=25 => format!("'{}", (b'a' + i as u8) as char),
+            _ => format!("'l{}", i),
+        })
+        .find(|it| !taken.contains(it))
+        .unwrap()
+}
@@ -125,6 +125,19 @@ pub fn expr_prefix(op: SyntaxKind, expr: ast::Expr) -> ast::Expr {
     let token = token(op);
     expr_from_text(&format!("{}{}", token, expr))
 }
+pub fn expr_call(f: ast::Expr, arg_list: ast::ArgList) -> ast::Expr {
+    expr_from_text(&format!("{}{}", f, arg_list))
+}
+pub fn expr_method_call(
+    receiver: ast::Expr,
+    method: ast::NameRef,
+    arg_list: ast::ArgList,
+) -> ast::Expr {
+    expr_from_text(&format!("{}.{}{}", receiver, method, arg_list))
+}
+pub fn expr_paren(expr: ast::Expr) -> ast::Expr {
+    expr_from_text(&format!("({})", expr))
+}
 fn expr_from_text(text: &str) -> ast::Expr {
     ast_from_text(&format!("const C: () = {};", text))
 }
@@ -133,6 +146,11 @@ pub fn try_expr_from_text(text: &str) -> Option<ast::Expr> {
     try_ast_from_text(&format!("const C: () = {};", text))
 }
 
+pub fn arg_list(args: impl IntoIterator<Item = ast::Expr>) -> ast::ArgList {
+    let args = args.into_iter().join(", ");
+    ast_from_text(&format!("fn main() {{ ()({}) }}", args))
+}
+
 pub fn condition(expr: ast::Expr, pattern: Option<ast::Pat>) -> ast::Condition {
     match pattern {
         None => ast_from_text(&format!("const _: () = while {} {{}};", expr)),
@@ -158,6 +176,14 @@ pub fn placeholder_pat() -> ast::PlaceholderPat {
     }
 }
 
+pub fn dot_dot_pat() -> ast::DotDotPat {
+    return from_text("..");
+
+    fn from_text(text: &str) -> ast::DotDotPat {
+        ast_from_text(&format!("fn f(S({}): ())", text))
+    }
+}
+
 /// Creates a tuple of patterns from an interator of patterns.
 ///
 /// Invariant: `pats` must be length > 1
@@ -277,8 +303,19 @@ pub fn param(name: String, ty: String) -> ast::Param {
     ast_from_text(&format!("fn f({}: {}) {{ }}", name, ty))
 }
 
-pub fn param_list(pats: impl IntoIterator<Item = ast::Param>) -> ast::ParamList {
-    let args = pats.into_iter().join(", ");
+pub fn self_param() -> ast::SelfParam {
+    ast_from_text("fn f(&self) { }")
+}
+
+pub fn param_list(
+    self_param: Option<ast::SelfParam>,
+    pats: impl IntoIterator<Item = ast::Param>,
+) -> ast::ParamList {
+    let args = self_param
+        .map(|it| it.to_string())
+        .into_iter()
+        .chain(pats.into_iter().map(|it| it.to_string()))
+        .join(", ");
     ast_from_text(&format!("fn f({}) {{ }}", args))
 }
 
@@ -1,8 +1,14 @@
 //! There are many AstNodes, but only a few tokens, so we hand-write them here.
 
+use rustc_lexer::unescape;
+
 use crate::{
-    ast::{AstToken, Comment, RawString, String, Whitespace},
-    TextRange, TextUnit,
+    ast::{
+        AstToken, Byte, ByteString, Char, Comment, FloatNumber, IntNumber, RawByteString,
+        RawString, String, Whitespace,
+    },
+    validation::rustc_unescape_error_to_string,
+    SyntaxError, SyntaxErrorCode, TextRange, TextUnit,
 };
 
 impl Comment {
@@ -133,6 +139,8 @@ pub trait HasQuotes: AstToken {
 
 impl HasQuotes for String {}
 impl HasQuotes for RawString {}
+impl HasQuotes for ByteString {}
+impl HasQuotes for RawByteString {}
 
 pub trait HasStringValue: HasQuotes {
     fn value(&self) -> Option<std::string::String>;
@@ -140,20 +148,11 @@ pub trait HasStringValue: HasQuotes {
 
 impl HasStringValue for String {
     fn value(&self) -> Option<std::string::String> {
-        let text = self.text().as_str();
-        let text = &text[self.text_range_between_quotes()? - self.syntax().text_range().start()];
-
-        let mut buf = std::string::String::with_capacity(text.len());
-        let mut has_error = false;
-        rustc_lexer::unescape::unescape_str(text, &mut |_, unescaped_char| match unescaped_char {
-            Ok(c) => buf.push(c),
-            Err(_) => has_error = true,
-        });
-
-        if has_error {
+        let unescaped = self.unescape()?;
+        if !unescaped.errors.is_empty() {
             return None;
         }
-        Some(buf)
+        Some(unescaped.value)
     }
 }
 
@@ -172,3 +171,161 @@ impl RawString {
         Some(range + contents_range.start())
     }
 }
+
+/// The value of a string or byte string literal with its escapes resolved.
+/// Escapes which can't be decoded are left out of `value` and reported in
+/// `errors`, with ranges covering the escape in the source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unescaped<T> {
+    pub value: T,
+    pub errors: Vec<SyntaxError>,
+}
+
+impl String {
+    pub fn unescape(&self) -> Option<Unescaped<std::string::String>> {
+        let contents = self.text_range_between_quotes()?;
+        let text = &self.text().as_str()[contents - self.syntax().text_range().start()];
+
+        let mut res =
+            Unescaped { value: std::string::String::with_capacity(text.len()), errors: Vec::new() };
+        unescape::unescape_str(text, &mut |range, unescaped_char| match unescaped_char {
+            Ok(c) => res.value.push(c),
+            Err(err) => res.errors.push(escape_error(err, range, contents.start())),
+        });
+        Some(res)
+    }
+}
+
+impl ByteString {
+    pub fn unescape(&self) -> Option<Unescaped<Vec<u8>>> {
+        let contents = self.text_range_between_quotes()?;
+        let text = &self.text().as_str()[contents - self.syntax().text_range().start()];
+
+        let mut res = Unescaped { value: Vec::with_capacity(text.len()), errors: Vec::new() };
+        unescape::unescape_byte_str(text, &mut |range, unescaped_byte| match unescaped_byte {
+            Ok(b) => res.value.push(b),
+            Err(err) => res.errors.push(escape_error(err, range, contents.start())),
+        });
+        Some(res)
+    }
+}
+
+impl RawByteString {
+    pub fn value(&self) -> Option<Vec<u8>> {
+        let contents = self.text_range_between_quotes()?;
+        let text = &self.text().as_str()[contents - self.syntax().text_range().start()];
+        Some(text.as_bytes().to_vec())
+    }
+}
+
+fn escape_error(
+    err: unescape::EscapeError,
+    range: std::ops::Range<usize>,
+    offset: TextUnit,
+) -> SyntaxError {
+    let range = TextRange::from_to(
+        offset + TextUnit::from_usize(range.start),
+        offset + TextUnit::from_usize(range.end),
+    );
+    SyntaxError::new(rustc_unescape_error_to_string(err), range)
+        .with_code(SyntaxErrorCode::InvalidEscape)
+}
+
+impl Char {
+    pub fn value(&self) -> Option<char> {
+        let text = self.text().as_str();
+        let contents = text.get(1..text.rfind('\'')?)?;
+        unescape::unescape_char(contents).ok()
+    }
+}
+
+impl Byte {
+    pub fn value(&self) -> Option<u8> {
+        let text = self.text().as_str();
+        let contents = text.get(2..text.rfind('\'')?)?;
+        unescape::unescape_byte(contents).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Binary = 2,
+    Octal = 8,
+    Decimal = 10,
+    Hexadecimal = 16,
+}
+
+impl IntNumber {
+    pub fn radix(&self) -> Radix {
+        match self.text().get(..2).unwrap_or_default() {
+            "0b" => Radix::Binary,
+            "0o" => Radix::Octal,
+            "0x" => Radix::Hexadecimal,
+            _ => Radix::Decimal,
+        }
+    }
+
+    /// Splits the literal into the radix prefix, the digits and the suffix,
+    /// any of which may be empty.
+    pub fn split_into_parts(&self) -> (&str, &str, &str) {
+        let radix = self.radix();
+        let (prefix, rest) = self.text().split_at(if radix == Radix::Decimal { 0 } else { 2 });
+        let is_digit = |c: char| {
+            c.is_ascii_digit() || c == '_' || (radix == Radix::Hexadecimal && c.is_ascii_hexdigit())
+        };
+        let (digits, suffix) =
+            rest.split_at(rest.find(|c: char| !is_digit(c)).unwrap_or(rest.len()));
+        (prefix, digits, suffix)
+    }
+
+    /// The value of the literal, or `None` if it has invalid digits or
+    /// doesn't fit into `u128`.
+    pub fn value(&self) -> Option<u128> {
+        let (_, digits, _) = self.split_into_parts();
+        u128::from_str_radix(&digits.replace('_', ""), self.radix() as u32).ok()
+    }
+
+    pub fn suffix(&self) -> Option<&str> {
+        let (_, _, suffix) = self.split_into_parts();
+        if suffix.is_empty() {
+            None
+        } else {
+            Some(suffix)
+        }
+    }
+}
+
+impl FloatNumber {
+    /// Splits the literal into the number, including the exponent, and the
+    /// suffix, which may be empty.
+    pub fn split_into_parts(&self) -> (&str, &str) {
+        let text = self.text().as_str();
+        let mut chars = text.char_indices().peekable();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '0'..='9' | '_' | '.' => (),
+                'e' | 'E' => {
+                    if let Some((_, '+')) | Some((_, '-')) = chars.peek() {
+                        chars.next();
+                    }
+                }
+                _ => return text.split_at(idx),
+            }
+        }
+        (text, "")
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        let (number, _) = self.split_into_parts();
+        number.replace('_', "").parse().ok()
+    }
+
+    pub fn suffix(&self) -> Option<&str> {
+        let (_, suffix) = self.split_into_parts();
+        if suffix.is_empty() {
+            None
+        } else {
+            Some(suffix)
+        }
+    }
+}
@@ -1,12 +1,10 @@
 //! Various traits that are implemented by ast nodes.
 //!
 //! The implementations are usually trivial, and live in generated.rs
-use stdx::SepBy;
-
 use crate::{
-    ast::{self, support, AstChildren, AstNode, AstToken},
+    ast::{self, support, AstChildren, AstNode, AstToken, HasQuotes, HasStringValue},
     syntax_node::SyntaxElementChildren,
-    SyntaxToken, T,
+    NodeOrToken, SyntaxNode, SyntaxToken, TextRange, TextUnit, T,
 };
 
 pub trait TypeAscriptionOwner: AstNode {
@@ -87,43 +85,143 @@ pub trait DocCommentsOwner: AstNode {
     /// That is, strips leading `///` (+ optional 1 character of whitespace),
     /// trailing `*/`, trailing whitespace and then joins the lines.
     fn doc_comment_text(&self) -> Option<String> {
-        let mut has_comments = false;
-        let docs = self
-            .doc_comments()
-            .filter(|comment| comment.kind().doc.is_some())
-            .map(|comment| {
-                has_comments = true;
-                let prefix_len = comment.prefix().len();
-
-                let line: &str = comment.text().as_str();
-
-                // Determine if the prefix or prefix + 1 char is stripped
-                let pos =
-                    if let Some(ws) = line.chars().nth(prefix_len).filter(|c| c.is_whitespace()) {
-                        prefix_len + ws.len_utf8()
-                    } else {
-                        prefix_len
-                    };
-
-                let end = if comment.kind().shape.is_block() && line.ends_with("*/") {
-                    line.len() - 2
-                } else {
-                    line.len()
-                };
-
-                // Note that we do not trim the end of the line here
-                // since whitespace can have special meaning at the end
-                // of a line in markdown.
-                line[pos..end].to_owned()
-            })
-            .sep_by("\n")
-            .to_string();
-
-        if has_comments {
-            Some(docs)
+        self.docs().map(|it| it.text)
+    }
+
+    /// Like `doc_comment_text`, but also includes `#[doc = "..."]` attributes
+    /// and remembers where each line of the text comes from.
+    fn docs(&self) -> Option<Docs> {
+        Docs::from_node(self.syntax())
+    }
+}
+
+/// Documentation of a node, collected from its doc comments and
+/// `#[doc = "..."]` attributes and joined into markdown, with a map back to
+/// the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Docs {
+    text: String,
+    /// Pairs of a range in `text` and the source range it comes from, in
+    /// source order. The ranges in `text` are separated by newlines.
+    source_map: Vec<(TextRange, TextRange)>,
+}
+
+impl Docs {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Maps an offset in the docs text to an offset in the source file.
+    /// Offsets inside of an attribute with escapes map to the start of the
+    /// attribute's string, as the mapping is not linear there.
+    pub fn map_offset_up(&self, offset: TextUnit) -> Option<TextUnit> {
+        let (text_range, source_range) =
+            self.source_map.iter().find(|(it, _)| it.contains_inclusive(offset))?;
+        if text_range.len() != source_range.len() {
+            return Some(source_range.start());
+        }
+        Some(source_range.start() + (offset - text_range.start()))
+    }
+
+    pub fn map_range_up(&self, range: TextRange) -> Option<TextRange> {
+        let start = self.map_offset_up(range.start())?;
+        let end = self.map_offset_up(range.end())?;
+        if start > end {
+            return None;
+        }
+        Some(TextRange::from_to(start, end))
+    }
+
+    /// Maps an offset in the source file to an offset in the docs text.
+    pub fn map_offset_down(&self, offset: TextUnit) -> Option<TextUnit> {
+        let (text_range, source_range) =
+            self.source_map.iter().find(|(_, it)| it.contains_inclusive(offset))?;
+        if text_range.len() != source_range.len() {
+            return None;
+        }
+        Some(text_range.start() + (offset - source_range.start()))
+    }
+
+    /// Collects the docs of any node, for callers which don't know the kind
+    /// of the node statically.
+    pub fn from_node(node: &SyntaxNode) -> Option<Docs> {
+        let mut res = Docs { text: String::new(), source_map: Vec::new() };
+        for element in node.children_with_tokens() {
+            match element {
+                NodeOrToken::Token(token) => {
+                    if let Some(comment) = ast::Comment::cast(token) {
+                        res.push_comment(&comment);
+                    }
+                }
+                NodeOrToken::Node(node) => {
+                    if let Some(attr) = ast::Attr::cast(node) {
+                        res.push_attr(&attr);
+                    }
+                }
+            }
+        }
+        if res.source_map.is_empty() {
+            return None;
+        }
+        Some(res)
+    }
+
+    fn push_comment(&mut self, comment: &ast::Comment) {
+        if comment.kind().doc.is_none() {
+            return;
+        }
+        let prefix_len = comment.prefix().len();
+        let line: &str = comment.text().as_str();
+
+        // Determine if the prefix or prefix + 1 char is stripped
+        let pos = if let Some(ws) = line.chars().nth(prefix_len).filter(|c| c.is_whitespace()) {
+            prefix_len + ws.len_utf8()
+        } else {
+            prefix_len
+        };
+
+        let end = if comment.kind().shape.is_block() && line.ends_with("*/") {
+            line.len() - 2
         } else {
-            None
+            line.len()
+        };
+
+        // Note that we do not trim the end of the line here
+        // since whitespace can have special meaning at the end
+        // of a line in markdown.
+        let start = comment.syntax().text_range().start();
+        let source_range = TextRange::from_to(
+            start + TextUnit::from_usize(pos),
+            start + TextUnit::from_usize(end),
+        );
+        self.push(&line[pos..end], source_range);
+    }
+
+    fn push_attr(&mut self, attr: &ast::Attr) -> Option<()> {
+        if attr.simple_name()?.as_str() != "doc" {
+            return None;
+        }
+        let token = match attr.input()? {
+            ast::AttrInput::Literal(it) => it.token(),
+            ast::AttrInput::TokenTree(_) => return None,
+        };
+        let (value, source_range) = if let Some(string) = ast::String::cast(token.clone()) {
+            (string.value()?, string.text_range_between_quotes()?)
+        } else {
+            let string = ast::RawString::cast(token)?;
+            (string.value()?, string.text_range_between_quotes()?)
+        };
+        self.push(&value, source_range);
+        Some(())
+    }
+
+    fn push(&mut self, text: &str, source_range: TextRange) {
+        if !self.source_map.is_empty() {
+            self.text.push('\n');
         }
+        let start = TextUnit::of_str(&self.text);
+        self.text.push_str(text);
+        self.source_map.push((TextRange::offset_len(start, TextUnit::of_str(text)), source_range));
     }
 }
 
@@ -49,7 +49,7 @@ pub use crate::{
     ast::{AstNode, AstToken},
     parsing::{lex_single_syntax_kind, lex_single_valid_syntax_kind, tokenize, Token},
     ptr::{AstPtr, SyntaxNodePtr},
-    syntax_error::SyntaxError,
+    syntax_error::{SyntaxError, SyntaxErrorCode},
     syntax_node::{
         Direction, NodeOrToken, SyntaxElement, SyntaxNode, SyntaxToken, SyntaxTreeBuilder,
     },
@@ -127,7 +127,23 @@ impl Parse<SourceFile> {
     }
 
     pub fn reparse(&self, edit: &AtomTextEdit) -> Parse<SourceFile> {
-        self.incremental_reparse(edit).unwrap_or_else(|| self.full_reparse(edit))
+        let reparsed = match self.incremental_reparse(edit) {
+            Some(it) => it,
+            None => return self.full_reparse(edit),
+        };
+        if cfg!(debug_assertions) {
+            // Incremental reparsing must be indistinguishable from parsing the
+            // whole file again.
+            let fully_reparsed = self.full_reparse(edit);
+            assert!(
+                reparsed.green == fully_reparsed.green,
+                "incremental reparse differs from the full one\nedit: {:?}\n\nincremental:\n{:#?}\n\nfull:\n{:#?}",
+                edit,
+                reparsed.tree().syntax(),
+                fully_reparsed.tree().syntax(),
+            );
+        }
+        reparsed
     }
 
     fn incremental_reparse(&self, edit: &AtomTextEdit) -> Option<Parse<SourceFile>> {
@@ -2,9 +2,11 @@
 //!
 //! We use two simple strategies for this:
 //!   - if the edit modifies only a single token (like changing an identifier's
-//!     letter), we replace only this token.
+//!     letter or a character inside a string literal), we replace only this
+//!     token.
 //!   - otherwise, we search for the nearest `{}` block which contains the edit
-//!     and try to parse only this block.
+//!     and try to parse only this block. If the edit breaks the braces of the
+//!     block, we try the enclosing blocks.
 
 use ra_parser::Reparser;
 use ra_text_edit::AtomTextEdit;
@@ -16,7 +18,7 @@ use crate::{
         text_token_source::TextTokenSource,
         text_tree_sink::TextTreeSink,
     },
-    syntax_node::{GreenNode, GreenToken, NodeOrToken, SyntaxElement, SyntaxNode},
+    syntax_node::{GreenNode, GreenToken, NodeOrToken, SyntaxElement, SyntaxNode, SyntaxToken},
     SyntaxError,
     SyntaxKind::*,
     TextRange, TextUnit, T,
@@ -44,7 +46,8 @@ fn reparse_token<'node>(
     let prev_token = algo::find_covering_element(root, edit.delete).as_token()?.clone();
     let prev_token_kind = prev_token.kind();
     match prev_token_kind {
-        WHITESPACE | COMMENT | IDENT | STRING | RAW_STRING => {
+        WHITESPACE | COMMENT | IDENT | STRING | RAW_STRING | BYTE_STRING | RAW_BYTE_STRING
+        | CHAR | BYTE | INT_NUMBER => {
             if prev_token_kind == WHITESPACE || prev_token_kind == COMMENT {
                 // removing a new line may extends previous token
                 let deleted_range = edit.delete - prev_token.text_range().start();
@@ -55,6 +58,9 @@ fn reparse_token<'node>(
 
             let mut new_text = get_text_after_edit(prev_token.clone().into(), &edit);
             let (new_token_kind, new_err) = lex_single_syntax_kind(&new_text)?;
+            if changes_comment_attachment(&prev_token, &new_text) {
+                return None;
+            }
 
             if new_token_kind != prev_token_kind
                 || (new_token_kind == IDENT && is_contextual_kw(&new_text))
@@ -90,13 +96,15 @@ fn reparse_block<'node>(
     root: &'node SyntaxNode,
     edit: &AtomTextEdit,
 ) -> Option<(GreenNode, Vec<SyntaxError>, TextRange)> {
-    let (node, reparser) = find_reparsable_node(root, edit.delete)?;
-    let text = get_text_after_edit(node.clone().into(), edit);
-
-    let (tokens, new_lexer_errors) = tokenize(&text);
-    if !is_balanced(&tokens) {
-        return None;
-    }
+    let (node, reparser, text, tokens, new_lexer_errors) = reparsable_nodes(root, edit.delete)
+        .find_map(|(node, reparser)| {
+            let text = get_text_after_edit(node.clone().into(), edit);
+            let (tokens, new_lexer_errors) = tokenize(&text);
+            if !is_balanced(&tokens) {
+                return None;
+            }
+            Some((node, reparser, text, tokens, new_lexer_errors))
+        })?;
 
     let mut token_source = TextTokenSource::new(&text, &tokens);
     let mut tree_sink = TextTreeSink::new(&text, &tokens);
@@ -126,14 +134,33 @@ fn is_contextual_kw(text: &str) -> bool {
     }
 }
 
-fn find_reparsable_node(node: &SyntaxNode, range: TextRange) -> Option<(SyntaxNode, Reparser)> {
+/// Comments are attached to the following item unless there's a blank line
+/// between them, which a doc comment overrides. Such edits can move a comment
+/// in or out of the item, so they can't be handled by replacing the token.
+fn changes_comment_attachment(token: &SyntaxToken, new_text: &str) -> bool {
+    match token.kind() {
+        WHITESPACE => {
+            let is_comment = |it: Option<SyntaxToken>| it.map_or(false, |it| it.kind() == COMMENT);
+            (is_comment(token.prev_token()) || is_comment(token.next_token()))
+                && token.text().contains("\n\n") != new_text.contains("\n\n")
+        }
+        COMMENT => token.text().starts_with("///") != new_text.starts_with("///"),
+        _ => false,
+    }
+}
+
+/// Returns the reparsable nodes containing the `range`, innermost first.
+fn reparsable_nodes(
+    node: &SyntaxNode,
+    range: TextRange,
+) -> impl Iterator<Item = (SyntaxNode, Reparser)> {
     let node = algo::find_covering_element(node, range);
 
-    let mut ancestors = match node {
+    let ancestors = match node {
         NodeOrToken::Token(it) => it.parent().ancestors(),
         NodeOrToken::Node(it) => it.ancestors(),
     };
-    ancestors.find_map(|node| {
+    ancestors.filter_map(|node| {
         let first_child = node.first_child_or_token().map(|it| it.kind());
         let parent = node.parent().map(|it| it.kind());
         Reparser::for_node(node.kind(), first_child, parent).map(|r| (node, r))
@@ -255,7 +282,7 @@ fn foo {
 }
 ",
             "62",
-            31, // FIXME: reparse only int literal here
+            2,
         );
         do_check(
             r"
@@ -414,6 +441,44 @@ enum Foo {
         );
     }
 
+    #[test]
+    fn reparse_byte_string_token() {
+        do_check(
+            r#"
+fn -> &[u8] { b"Hello<|><|>" }
+"#,
+            ", world",
+            8,
+        );
+    }
+
+    #[test]
+    fn reparse_enclosing_block_when_braces_change() {
+        do_check(
+            r"
+fn foo() {
+    let x = <|>{ 1 }<|>;
+}
+",
+            "( 1 )",
+            22,
+        );
+    }
+
+    #[test]
+    fn dont_reparse_whitespace_detaching_comment() {
+        let (range, before) = extract_range(
+            r"
+// comment
+<|> <|>
+fn foo() {}
+",
+        );
+        let edit = AtomTextEdit::replace(range, String::new());
+        let before = SourceFile::parse(&before);
+        assert!(incremental_reparse(before.tree().syntax(), &edit, Vec::new()).is_none());
+    }
+
     #[test]
     fn reparse_str_token_with_error_unchanged() {
         do_check(r#""<|>Unclosed<|> string literal"#, "Still unclosed", 24);
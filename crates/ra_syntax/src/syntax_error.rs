@@ -7,34 +7,59 @@ use crate::{TextRange, TextUnit};
 /// Represents the result of unsuccessful tokenization, parsing
 /// or tree validation.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct SyntaxError(String, TextRange);
-
-// FIXME: there was an unused SyntaxErrorKind previously (before this enum was removed)
-// It was introduced in this PR: https://github.com/rust-analyzer/rust-analyzer/pull/846/files#diff-827da9b03b8f9faa1bade5cdd44d5dafR95
-// but it was not removed by a mistake.
-//
-// So, we need to find a place where to stick validation for attributes in match clauses.
-// Code before refactor:
-// InvalidMatchInnerAttr => {
-//    write!(f, "Inner attributes are only allowed directly after the opening brace of the match expression")
-// }
+pub struct SyntaxError(String, TextRange, Option<SyntaxErrorCode>);
+
+/// Identifies errors reported by validation, so that clients can offer
+/// fixes without matching on the message text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxErrorCode {
+    InvalidEscape,
+    InvalidLiteralSuffix,
+    InvalidTupleIndex,
+    UnnecessaryVisibility,
+    InnerAttrNotAllowed,
+    DocCommentDocumentsNothing,
+    InclusiveRangeWithoutEnd,
+}
+
+impl SyntaxErrorCode {
+    /// The matching `rustc` error code, if there is one.
+    pub fn rustc_code(self) -> Option<&'static str> {
+        let code = match self {
+            SyntaxErrorCode::UnnecessaryVisibility => "E0449",
+            SyntaxErrorCode::DocCommentDocumentsNothing => "E0585",
+            SyntaxErrorCode::InclusiveRangeWithoutEnd => "E0586",
+            _ => return None,
+        };
+        Some(code)
+    }
+}
 
 impl SyntaxError {
     pub fn new(message: impl Into<String>, range: TextRange) -> Self {
-        Self(message.into(), range)
+        Self(message.into(), range, None)
     }
     pub fn new_at_offset(message: impl Into<String>, offset: TextUnit) -> Self {
-        Self(message.into(), TextRange::offset_len(offset, 0.into()))
+        Self(message.into(), TextRange::offset_len(offset, 0.into()), None)
     }
 
     pub fn range(&self) -> TextRange {
         self.1
     }
 
+    pub fn code(&self) -> Option<SyntaxErrorCode> {
+        self.2
+    }
+
     pub fn with_range(mut self, range: TextRange) -> Self {
         self.1 = range;
         self
     }
+
+    pub fn with_code(mut self, code: SyntaxErrorCode) -> Self {
+        self.2 = Some(code);
+        self
+    }
 }
 
 impl fmt::Display for SyntaxError {
@@ -2,15 +2,17 @@
 
 mod block;
 
+use std::iter::successors;
+
 use rustc_lexer::unescape;
 
 use crate::{
-    ast, match_ast, AstNode, SyntaxError,
-    SyntaxKind::{BYTE, BYTE_STRING, CHAR, CONST_DEF, FN_DEF, INT_NUMBER, STRING, TYPE_ALIAS_DEF},
-    SyntaxNode, SyntaxToken, TextUnit, T,
+    ast, match_ast, AstNode, AstToken, NodeOrToken, SyntaxError, SyntaxErrorCode,
+    SyntaxKind::{self, *},
+    SyntaxNode, SyntaxToken, TextRange, TextUnit, T,
 };
 
-fn rustc_unescape_error_to_string(err: unescape::EscapeError) -> &'static str {
+pub(crate) fn rustc_unescape_error_to_string(err: unescape::EscapeError) -> &'static str {
     use unescape::EscapeError as EE;
 
     #[rustfmt::skip]
@@ -81,11 +83,19 @@ pub(crate) fn validate(root: &SyntaxNode) -> Vec<SyntaxError> {
     // FIXME:
     // * Add validation of character literal containing only a single char
     // * Add validation of `crate` keyword not appearing in the middle of the symbol path
-    // * Add validation of doc comments are being attached to nodes
     // * Remove validation of unterminated literals (it is already implemented in `tokenize()`)
 
     let mut errors = Vec::new();
-    for node in root.descendants() {
+    for element in root.descendants_with_tokens() {
+        let node = match element {
+            NodeOrToken::Node(it) => it,
+            NodeOrToken::Token(it) => {
+                if let Some(comment) = ast::Comment::cast(it) {
+                    validate_doc_comment(comment, &mut errors);
+                }
+                continue;
+            }
+        };
         match_ast! {
             match node {
                 ast::Literal(it) => validate_literal(it, &mut errors),
@@ -94,6 +104,7 @@ pub(crate) fn validate(root: &SyntaxNode) -> Vec<SyntaxError> {
                 ast::RecordField(it) => validate_numeric_name(it.name_ref(), &mut errors),
                 ast::Visibility(it) => validate_visibility(it, &mut errors),
                 ast::RangeExpr(it) => validate_range_expr(it, &mut errors),
+                ast::Attr(it) => validate_inner_attr(it, &mut errors),
                 _ => (),
             }
         }
@@ -113,7 +124,10 @@ fn validate_literal(literal: ast::Literal, acc: &mut Vec<SyntaxError>) {
     // FIXME: lift this lambda refactor to `fn` (https://github.com/rust-analyzer/rust-analyzer/pull/2834#discussion_r366199205)
     let mut push_err = |prefix_len, (off, err): (usize, unescape::EscapeError)| {
         let off = token.text_range().start() + TextUnit::from_usize(off + prefix_len);
-        acc.push(SyntaxError::new_at_offset(rustc_unescape_error_to_string(err), off));
+        acc.push(
+            SyntaxError::new_at_offset(rustc_unescape_error_to_string(err), off)
+                .with_code(SyntaxErrorCode::InvalidEscape),
+        );
     };
 
     match token.kind() {
@@ -128,25 +142,97 @@ fn validate_literal(literal: ast::Literal, acc: &mut Vec<SyntaxError>) {
             }
         }
         BYTE_STRING => {
-            if let Some(without_quotes) = unquote(text, 2, '"') {
-                unescape::unescape_byte_str(without_quotes, &mut |range, char| {
-                    if let Err(err) = char {
-                        push_err(2, (range.start, err));
-                    }
-                })
+            if let Some(unescaped) =
+                ast::ByteString::cast(token.clone()).and_then(|it| it.unescape())
+            {
+                acc.extend(unescaped.errors);
             }
         }
         STRING => {
-            if let Some(without_quotes) = unquote(text, 1, '"') {
-                unescape::unescape_str(without_quotes, &mut |range, char| {
-                    if let Err(err) = char {
-                        push_err(1, (range.start, err));
-                    }
-                })
+            if let Some(unescaped) = ast::String::cast(token.clone()).and_then(|it| it.unescape()) {
+                acc.extend(unescaped.errors);
             }
         }
         _ => (),
     }
+
+    validate_literal_suffix(&token, acc);
+}
+
+const INT_SUFFIXES: &[&str] =
+    &["i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize"];
+const FLOAT_SUFFIXES: &[&str] = &["f32", "f64"];
+
+fn validate_literal_suffix(token: &SyntaxToken, acc: &mut Vec<SyntaxError>) {
+    let text = token.text().as_str();
+    let kind = token.kind();
+    let suffix_start = match kind {
+        INT_NUMBER => {
+            let int = ast::IntNumber::cast(token.clone()).unwrap();
+            text.len() - int.suffix().map_or(0, str::len)
+        }
+        FLOAT_NUMBER => {
+            let float = ast::FloatNumber::cast(token.clone()).unwrap();
+            text.len() - float.suffix().map_or(0, str::len)
+        }
+        CHAR | BYTE | STRING | BYTE_STRING | RAW_STRING | RAW_BYTE_STRING => {
+            match quoted_suffix_start(text) {
+                Some(it) => it,
+                // Unterminated literals are reported by the lexer.
+                None => return,
+            }
+        }
+        _ => return,
+    };
+    let suffix = &text[suffix_start..];
+    if suffix.is_empty() {
+        return;
+    }
+
+    let is_valid = match kind {
+        // `1f32` is a float literal, but there are no binary or octal floats.
+        INT_NUMBER => {
+            INT_SUFFIXES.contains(&suffix)
+                || (FLOAT_SUFFIXES.contains(&suffix)
+                    && !text.starts_with("0b")
+                    && !text.starts_with("0o"))
+        }
+        FLOAT_NUMBER => FLOAT_SUFFIXES.contains(&suffix),
+        _ => false,
+    };
+    if !is_valid {
+        let range = TextRange::from_to(
+            token.text_range().start() + TextUnit::from_usize(suffix_start),
+            token.text_range().end(),
+        );
+        acc.push(
+            SyntaxError::new(
+                format!("Invalid suffix `{}` for {} literal", suffix, literal_kind_name(kind)),
+                range,
+            )
+            .with_code(SyntaxErrorCode::InvalidLiteralSuffix),
+        );
+    }
+}
+
+fn quoted_suffix_start(text: &str) -> Option<usize> {
+    let open = text.find(|c: char| c == '\'' || c == '"')?;
+    let quote = text[open..].chars().next()?;
+    let close = open + 1 + text[open + 1..].rfind(quote)?;
+    let hashes = text[close + 1..].chars().take_while(|&c| c == '#').count();
+    Some(close + 1 + hashes)
+}
+
+fn literal_kind_name(kind: SyntaxKind) -> &'static str {
+    match kind {
+        INT_NUMBER => "integer",
+        FLOAT_NUMBER => "float",
+        CHAR => "char",
+        BYTE => "byte",
+        STRING | RAW_STRING => "string",
+        BYTE_STRING | RAW_BYTE_STRING => "byte string",
+        _ => "unknown",
+    }
 }
 
 pub(crate) fn validate_block_structure(root: &SyntaxNode) {
@@ -180,11 +266,14 @@ pub(crate) fn validate_block_structure(root: &SyntaxNode) {
 fn validate_numeric_name(name_ref: Option<ast::NameRef>, errors: &mut Vec<SyntaxError>) {
     if let Some(int_token) = int_token(name_ref) {
         if int_token.text().chars().any(|c| !c.is_digit(10)) {
-            errors.push(SyntaxError::new(
-                "Tuple (struct) field access is only allowed through \
-                decimal integers with no underscores or suffix",
-                int_token.text_range(),
-            ));
+            errors.push(
+                SyntaxError::new(
+                    "Tuple (struct) field access is only allowed through \
+                    decimal integers with no underscores or suffix",
+                    int_token.text_range(),
+                )
+                .with_code(SyntaxErrorCode::InvalidTupleIndex),
+            );
         }
     }
 
@@ -208,15 +297,71 @@ fn validate_visibility(vis: ast::Visibility, errors: &mut Vec<SyntaxError>) {
         None => return,
     };
     if impl_def.target_trait().is_some() {
-        errors.push(SyntaxError::new("Unnecessary visibility qualifier", vis.syntax.text_range()));
+        errors.push(
+            SyntaxError::new("Unnecessary visibility qualifier", vis.syntax.text_range())
+                .with_code(SyntaxErrorCode::UnnecessaryVisibility),
+        );
     }
 }
 
 fn validate_range_expr(expr: ast::RangeExpr, errors: &mut Vec<SyntaxError>) {
     if expr.op_kind() == Some(ast::RangeOp::Inclusive) && expr.end().is_none() {
-        errors.push(SyntaxError::new(
-            "An inclusive range must have an end expression",
-            expr.syntax().text_range(),
-        ));
+        errors.push(
+            SyntaxError::new(
+                "An inclusive range must have an end expression",
+                expr.syntax().text_range(),
+            )
+            .with_code(SyntaxErrorCode::InclusiveRangeWithoutEnd),
+        );
+    }
+}
+
+fn validate_inner_attr(attr: ast::Attr, errors: &mut Vec<SyntaxError>) {
+    if attr.kind() != ast::AttrKind::Inner {
+        return;
+    }
+    let parent = match attr.syntax().parent() {
+        Some(it) => it,
+        None => return,
+    };
+    // Inner attributes of blocks are checked by `block::validate_block_expr`.
+    let message = match parent.kind() {
+        SOURCE_FILE | ITEM_LIST | EXTERN_ITEM_LIST | MATCH_ARM_LIST | BLOCK => return,
+        MATCH_ARM => {
+            "Inner attributes are only allowed directly after the opening brace of the match expression"
+        }
+        _ => "Inner attributes are not allowed in this position",
+    };
+    errors.push(
+        SyntaxError::new(message, attr.syntax().text_range())
+            .with_code(SyntaxErrorCode::InnerAttrNotAllowed),
+    );
+}
+
+fn validate_doc_comment(comment: ast::Comment, errors: &mut Vec<SyntaxError>) {
+    if comment.kind().doc != Some(ast::CommentPlacement::Outer) {
+        return;
+    }
+    // `////`, `/***` and `/**/` are regular comments.
+    let text = comment.text().as_str();
+    if text.starts_with("////") || text.starts_with("/***") || text == "/**/" {
+        return;
+    }
+    // Token trees are free to contain anything.
+    if comment.syntax().parent().ancestors().any(|it| it.kind() == TOKEN_TREE) {
+        return;
+    }
+
+    let next = successors(comment.syntax().next_token(), |it| it.next_token())
+        .find(|it| !it.kind().is_trivia());
+    match next {
+        Some(it) if it.kind() != T!['}'] => (),
+        _ => errors.push(
+            SyntaxError::new(
+                "Found a documentation comment that doesn't document anything",
+                comment.syntax().text_range(),
+            )
+            .with_code(SyntaxErrorCode::DocCommentDocumentsNothing),
+        ),
     }
 }
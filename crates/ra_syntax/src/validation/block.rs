@@ -2,7 +2,7 @@
 
 use crate::{
     ast::{self, AstNode, AttrsOwner},
-    SyntaxError,
+    SyntaxError, SyntaxErrorCode,
     SyntaxKind::*,
 };
 
@@ -19,6 +19,7 @@ pub(crate) fn validate_block_expr(expr: ast::BlockExpr, errors: &mut Vec<SyntaxE
                 "A block in this position cannot accept inner attributes",
                 attr.syntax().text_range(),
             )
+            .with_code(SyntaxErrorCode::InnerAttrNotAllowed)
         }))
     }
 }
@@ -0,0 +1,5 @@
+fn main() {
+    let _ = 1u3;
+    let _ = 0b1f32;
+    let _ = 1.0i32;
+}
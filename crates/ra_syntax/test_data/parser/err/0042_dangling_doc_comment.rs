@@ -0,0 +1,7 @@
+struct S {
+    a: u32,
+    /// Nothing to document
+}
+
+fn foo() {}
+/// Nor here
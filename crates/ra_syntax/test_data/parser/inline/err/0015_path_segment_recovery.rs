@@ -0,0 +1,7 @@
+fn foo() {
+    foo::;
+    bar(baz::);
+    match x {
+        Foo::=> (),
+    }
+}
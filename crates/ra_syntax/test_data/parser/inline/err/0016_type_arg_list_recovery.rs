@@ -0,0 +1,4 @@
+fn foo() {
+    let v: Vec<
+    let m: HashMap<String, = HashMap::new();
+}
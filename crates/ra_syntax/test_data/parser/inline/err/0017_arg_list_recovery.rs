@@ -0,0 +1,4 @@
+fn foo() {
+    foo(bar, ;
+    baz.qux(;
+}
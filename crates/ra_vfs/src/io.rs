@@ -0,0 +1,287 @@
+//! The background thread of the VFS, which loads the roots and watches them for
+//! changes.
+
+use std::{
+    fmt, fs, iter,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    time::Duration,
+};
+
+use crossbeam_channel::{never, select, unbounded, Receiver, RecvError, Sender};
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher as _};
+use parking_lot::RwLock;
+use relative_path::RelativePathBuf;
+use walkdir::WalkDir;
+
+use crate::{roots::Roots, VfsRoot, Watch};
+
+const WATCHER_DELAY: Duration = Duration::from_millis(250);
+
+pub(crate) enum Task {
+    AddRoot {
+        root: VfsRoot,
+    },
+    /// Reloads the file at the path, as reported by the client's watcher.
+    LoadPath(PathBuf),
+}
+
+/// The result of a background IO task, which should be fed back to
+/// `Vfs::handle_task`.
+pub struct VfsTask(pub(crate) TaskResult);
+
+pub(crate) enum TaskResult {
+    /// The initial contents of a root.
+    BulkLoadRoot { root: VfsRoot, files: Vec<(RelativePathBuf, String)> },
+    /// A batch of changes, `None` means the file or directory was removed.
+    FileChanges(Vec<(VfsRoot, RelativePathBuf, Option<String>)>),
+}
+
+impl fmt::Debug for VfsTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            TaskResult::BulkLoadRoot { root, files } => f
+                .debug_struct("BulkLoadRoot")
+                .field("root", root)
+                .field("n_files", &files.len())
+                .finish(),
+            TaskResult::FileChanges(changes) => {
+                f.debug_struct("FileChanges").field("n_changes", &changes.len()).finish()
+            }
+        }
+    }
+}
+
+pub(crate) struct Worker {
+    // XXX: drop order is significant
+    pub(crate) sender: Sender<Task>,
+    _thread: jod_thread::JoinHandle<()>,
+}
+
+pub(crate) fn start(
+    roots: Arc<RwLock<Roots>>,
+    mut on_task: Box<dyn FnMut(VfsTask) + Send>,
+    watch: Watch,
+) -> Worker {
+    let (sender, receiver) = unbounded::<Task>();
+    let thread = jod_thread::Builder::new()
+        .name("vfs".to_string())
+        .spawn(move || run(&roots, receiver, &mut *on_task, watch))
+        .expect("failed to spawn the vfs thread");
+    Worker { sender, _thread: thread }
+}
+
+struct Watcher {
+    // XXX: drop order is significant, dropping the watcher stops the forwarder
+    inner: RecommendedWatcher,
+    _forwarder: jod_thread::JoinHandle<()>,
+}
+
+impl Watcher {
+    /// Starts the OS watcher. Its events are forwarded to a crossbeam channel,
+    /// so that they can be `select`ed together with the tasks.
+    fn start() -> Option<(Watcher, Receiver<DebouncedEvent>)> {
+        let (notify_sender, notify_receiver) = mpsc::channel();
+        let inner = match notify::watcher(notify_sender, WATCHER_DELAY) {
+            Ok(it) => it,
+            Err(err) => {
+                log::error!("failed to start the file watcher: {}", err);
+                return None;
+            }
+        };
+        let (sender, receiver) = unbounded();
+        let forwarder = jod_thread::Builder::new()
+            .name("vfs-watcher".to_string())
+            .spawn(move || {
+                notify_receiver.into_iter().try_for_each(|event| sender.send(event)).ok();
+            })
+            .expect("failed to spawn the vfs watcher thread");
+        Some((Watcher { inner, _forwarder: forwarder }, receiver))
+    }
+
+    fn watch(&mut self, dir: &Path) {
+        if let Err(err) = self.inner.watch(dir, RecursiveMode::NonRecursive) {
+            log::warn!("failed to watch {}: {}", dir.display(), err);
+        }
+    }
+}
+
+fn run(
+    roots: &RwLock<Roots>,
+    receiver: Receiver<Task>,
+    on_task: &mut dyn FnMut(VfsTask),
+    watch: Watch,
+) {
+    let watcher = if watch.0 { Watcher::start() } else { None };
+    let (mut watcher, mut watcher_receiver) = match watcher {
+        Some((watcher, receiver)) => (Some(watcher), receiver),
+        None => (None, never()),
+    };
+    loop {
+        select! {
+            recv(receiver) -> task => match task {
+                Ok(task) => handle_task(&roots.read(), task, watcher.as_mut(), on_task),
+                Err(RecvError) => break,
+            },
+            recv(watcher_receiver) -> event => match event {
+                Ok(event) => {
+                    // Batch the events which are already available, so that
+                    // a `git checkout` results in a single change.
+                    let roots = roots.read();
+                    let mut changes = Vec::new();
+                    for event in iter::once(event).chain(watcher_receiver.try_iter()) {
+                        handle_watcher_event(&roots, event, watcher.as_mut(), &mut changes);
+                    }
+                    if !changes.is_empty() {
+                        on_task(VfsTask(TaskResult::FileChanges(changes)));
+                    }
+                }
+                Err(RecvError) => {
+                    // The watcher died, replace it with a never channel to
+                    // avoid busy-waiting.
+                    watcher_receiver = never();
+                }
+            },
+        }
+    }
+}
+
+fn handle_task(
+    roots: &Roots,
+    task: Task,
+    mut watcher: Option<&mut Watcher>,
+    on_task: &mut dyn FnMut(VfsTask),
+) {
+    match task {
+        Task::AddRoot { root } => {
+            let mut files = Vec::new();
+            load_dir(
+                roots,
+                root,
+                roots.path(root),
+                watcher.as_deref_mut(),
+                &mut |rel_path, text| files.push((rel_path, text)),
+            );
+            log::debug!("loaded {} files from {}", files.len(), roots.path(root).display());
+            on_task(VfsTask(TaskResult::BulkLoadRoot { root, files }));
+        }
+        Task::LoadPath(path) => {
+            let mut changes = Vec::new();
+            load_path(roots, &path, watcher, &mut changes);
+            if !changes.is_empty() {
+                on_task(VfsTask(TaskResult::FileChanges(changes)));
+            }
+        }
+    }
+}
+
+fn handle_watcher_event(
+    roots: &Roots,
+    event: DebouncedEvent,
+    watcher: Option<&mut Watcher>,
+    changes: &mut Vec<(VfsRoot, RelativePathBuf, Option<String>)>,
+) {
+    match event {
+        DebouncedEvent::NoticeWrite(_)
+        | DebouncedEvent::NoticeRemove(_)
+        | DebouncedEvent::Chmod(_) => (),
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) => {
+            load_path(roots, &path, watcher, changes)
+        }
+        DebouncedEvent::Remove(path) => remove_path(roots, &path, changes),
+        DebouncedEvent::Rename(src, dst) => {
+            remove_path(roots, &src, changes);
+            load_path(roots, &dst, watcher, changes);
+        }
+        DebouncedEvent::Rescan => {
+            log::warn!("file watcher lost some events, reloading all roots");
+            let mut watcher = watcher;
+            for root in roots.iter() {
+                load_dir(
+                    roots,
+                    root,
+                    roots.path(root),
+                    watcher.as_deref_mut(),
+                    &mut |path, text| changes.push((root, path, Some(text))),
+                );
+            }
+        }
+        DebouncedEvent::Error(err, path) => {
+            log::warn!("file watcher error {:?}: {}", path, err);
+        }
+    }
+}
+
+/// Loads a created or changed file, or all the files of a created directory.
+fn load_path(
+    roots: &Roots,
+    path: &Path,
+    watcher: Option<&mut Watcher>,
+    changes: &mut Vec<(VfsRoot, RelativePathBuf, Option<String>)>,
+) {
+    if path.is_dir() {
+        if let Some((root, _)) = roots.find_any(path) {
+            load_dir(roots, root, path, watcher, &mut |rel_path, text| {
+                changes.push((root, rel_path, Some(text)))
+            });
+        }
+        return;
+    }
+    if let Some((root, rel_path)) = roots.find(path) {
+        // The file may have been removed since the event was sent.
+        changes.push((root, rel_path, read_to_string(path)));
+    }
+}
+
+fn remove_path(
+    roots: &Roots,
+    path: &Path,
+    changes: &mut Vec<(VfsRoot, RelativePathBuf, Option<String>)>,
+) {
+    if let Some((root, rel_path)) = roots.find_any(path) {
+        changes.push((root, rel_path, None));
+    }
+}
+
+/// Loads the files of the `dir` which belong to the `root`, watching the
+/// included directories.
+fn load_dir(
+    roots: &Roots,
+    root: VfsRoot,
+    dir: &Path,
+    mut watcher: Option<&mut Watcher>,
+    acc: &mut dyn FnMut(RelativePathBuf, String),
+) {
+    let root_path = roots.path(root);
+    let rel_path = |path: &Path| {
+        path.strip_prefix(root_path).ok().and_then(|it| RelativePathBuf::from_path(it).ok())
+    };
+    let entries = WalkDir::new(dir).follow_links(true).into_iter().filter_entry(|entry| {
+        if !entry.file_type().is_dir() || entry.path() == root_path {
+            return true;
+        }
+        rel_path(entry.path()).map_or(false, |it| roots.include_dir(root, &it))
+    });
+    for entry in entries.filter_map(|it| it.ok()) {
+        let path = entry.path();
+        if entry.file_type().is_dir() {
+            if let Some(watcher) = watcher.as_deref_mut() {
+                watcher.watch(path);
+            }
+            continue;
+        }
+        let rel_path = match rel_path(path) {
+            Some(it) if roots.include_file(root, &it) => it,
+            _ => continue,
+        };
+        if let Some(text) = read_to_string(path) {
+            acc(rel_path, text);
+        }
+    }
+}
+
+fn read_to_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .map_err(|err| log::debug!("failed to read {}: {}", path.display(), err))
+        .ok()
+}
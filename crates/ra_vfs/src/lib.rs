@@ -0,0 +1,479 @@
+//! VFS stands for Virtual File System.
+//!
+//! When doing analysis, we don't want to do any IO, we want to keep all source
+//! code in memory. However, the actual source code is stored on disk, so you
+//! need to get it into the memory in the first place somehow. VFS is the
+//! component which does this.
+//!
+//! It is also responsible for watching the disk for changes, and for merging
+//! the editor state (modified, unsaved files) with the disk state.
+//!
+//! The VFS is created with a set of roots. A root is a directory together with
+//! a `Filter`, which decides which of its files and directories are loaded.
+//! Roots can be nested, in which case a file belongs to the innermost root.
+//!
+//! The roots are loaded and watched by a background thread. Its results are
+//! passed to the `on_task` callback, and should be fed back to
+//! `Vfs::handle_task`. All the changes, from the disk and from the editor, are
+//! accumulated and handed out in a batch by `Vfs::commit_changes`.
+mod io;
+mod roots;
+
+use std::{
+    fmt, fs, mem,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use parking_lot::RwLock;
+use rustc_hash::FxHashMap;
+
+use crate::{
+    io::{TaskResult, Worker},
+    roots::Roots,
+};
+
+pub use crate::{io::VfsTask, roots::VfsRoot};
+pub use relative_path::{RelativePath, RelativePathBuf};
+
+/// Decides which files and directories of a root are loaded. The paths are
+/// relative to the root.
+pub trait Filter: Send + Sync {
+    fn include_dir(&self, dir_path: &RelativePath) -> bool;
+    fn include_file(&self, file_path: &RelativePath) -> bool;
+}
+
+pub struct RootEntry {
+    path: PathBuf,
+    filter: Box<dyn Filter>,
+}
+
+impl RootEntry {
+    pub fn new(path: PathBuf, filter: Box<dyn Filter>) -> RootEntry {
+        RootEntry { path, filter }
+    }
+}
+
+/// Whether the roots should be watched for changes with the OS file watcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watch(pub bool);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VfsFile(pub u32);
+
+/// The line endings of a file on disk. The texts stored in the VFS always use
+/// `\n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEndings {
+    Unix,
+    Dos,
+}
+
+impl Default for LineEndings {
+    fn default() -> LineEndings {
+        LineEndings::Unix
+    }
+}
+
+impl LineEndings {
+    fn normalize(text: String) -> (String, LineEndings) {
+        if text.contains("\r\n") {
+            (text.replace("\r\n", "\n"), LineEndings::Dos)
+        } else {
+            (text, LineEndings::Unix)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum VfsChange {
+    AddRoot { root: VfsRoot, files: Vec<(VfsFile, RelativePathBuf, Arc<String>)> },
+    AddFile { root: VfsRoot, file: VfsFile, path: RelativePathBuf, text: Arc<String> },
+    RemoveFile { root: VfsRoot, file: VfsFile, path: RelativePathBuf },
+    ChangeFile { file: VfsFile, text: Arc<String> },
+}
+
+struct VfsFileData {
+    root: VfsRoot,
+    path: RelativePathBuf,
+    is_overlayed: bool,
+    text: Arc<String>,
+    line_endings: LineEndings,
+}
+
+pub struct Vfs {
+    roots: Arc<RwLock<Roots>>,
+    files: Vec<VfsFileData>,
+    root2files: FxHashMap<VfsRoot, FxHashMap<RelativePathBuf, VfsFile>>,
+    pending_changes: Vec<VfsChange>,
+    worker: Worker,
+}
+
+impl fmt::Debug for Vfs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Vfs")
+            .field("n_roots", &self.roots.read().len())
+            .field("n_files", &self.files.len())
+            .field("n_pending_changes", &self.pending_changes.len())
+            .finish()
+    }
+}
+
+impl Vfs {
+    pub fn new(
+        roots: Vec<RootEntry>,
+        on_task: Box<dyn FnMut(VfsTask) + Send>,
+        watch: Watch,
+    ) -> (Vfs, Vec<VfsRoot>) {
+        let roots = Arc::new(RwLock::new(Roots::new(roots)));
+        let worker = io::start(Arc::clone(&roots), on_task, watch);
+        let vfs_roots: Vec<VfsRoot> = roots.read().iter().collect();
+        let mut root2files = FxHashMap::default();
+        for &root in vfs_roots.iter() {
+            root2files.insert(root, FxHashMap::default());
+            worker.sender.send(io::Task::AddRoot { root }).unwrap();
+        }
+        let res = Vfs { roots, files: Vec::new(), root2files, pending_changes: Vec::new(), worker };
+        (res, vfs_roots)
+    }
+
+    /// Adds the roots which are not present yet, and starts loading them like
+    /// the initial ones. Returns the new roots.
+    pub fn add_roots(&mut self, roots: Vec<RootEntry>) -> Vec<VfsRoot> {
+        let new_roots = self.roots.write().add(roots);
+        for &root in new_roots.iter() {
+            self.root2files.insert(root, FxHashMap::default());
+        }
+
+        // The files of the existing roots might belong to the new nested ones.
+        let moved_files: Vec<VfsFile> = self
+            .root2files
+            .values()
+            .flat_map(|it| it.values().copied())
+            .filter(|&file| {
+                let path = self.file2path(file);
+                let root = self.roots.read().find(&path).map(|it| it.0);
+                root != Some(self.file(file).root)
+            })
+            .collect();
+        for file in moved_files {
+            self.move_file(file);
+        }
+
+        for &root in new_roots.iter() {
+            self.worker.sender.send(io::Task::AddRoot { root }).unwrap();
+        }
+        new_roots
+    }
+
+    /// Removes the roots which are not in `roots`, along with their files.
+    /// The files of a removed nested root go back to the enclosing one.
+    /// Returns the removed roots.
+    pub fn retain_roots(&mut self, roots: &[RootEntry]) -> Vec<VfsRoot> {
+        let paths: Vec<&Path> = roots.iter().map(|it| it.path.as_path()).collect();
+        let removed = self.roots.write().retain(&paths);
+        for &root in removed.iter() {
+            let files: Vec<VfsFile> = self.root2files[&root].values().copied().collect();
+            for file in files {
+                self.move_file(file);
+            }
+            // Picks up the files on disk if there is an enclosing root.
+            self.notify_changed(self.root2path(root));
+        }
+        removed
+    }
+
+    /// The number of the roots ever added, including the removed ones.
+    pub fn n_roots(&self) -> usize {
+        self.roots.read().len()
+    }
+
+    pub fn root2path(&self, root: VfsRoot) -> PathBuf {
+        self.roots.read().path(root).to_path_buf()
+    }
+
+    pub fn path2file(&self, path: &Path) -> Option<VfsFile> {
+        let (root, rel_path) = self.roots.read().find(path)?;
+        self.find_file(root, &rel_path)
+    }
+
+    pub fn file2path(&self, file: VfsFile) -> PathBuf {
+        let data = self.file(file);
+        data.path.to_path(self.roots.read().path(data.root))
+    }
+
+    pub fn file_line_endings(&self, file: VfsFile) -> LineEndings {
+        self.file(file).line_endings
+    }
+
+    /// Loads the file at `path` right away, without waiting for its root to
+    /// be scanned.
+    pub fn load(&mut self, path: &Path) -> Option<VfsFile> {
+        let (root, rel_path) = self.roots.read().find(path)?;
+        if let Some(file) = self.find_file(root, &rel_path) {
+            return Some(file);
+        }
+        let text = fs::read_to_string(path).unwrap_or_default();
+        let (text, line_endings) = LineEndings::normalize(text);
+        Some(self.add_file(root, rel_path, Arc::new(text), line_endings, false))
+    }
+
+    /// Asks the background thread to reload the file at `path`, for example
+    /// when the client reports a change.
+    pub fn notify_changed(&self, path: PathBuf) {
+        if self.roots.read().find_any(&path).is_some() {
+            self.worker.sender.send(io::Task::LoadPath(path)).unwrap();
+        }
+    }
+
+    pub fn add_file_overlay(&mut self, path: &Path, text: String) -> Option<VfsFile> {
+        let (root, rel_path) = self.roots.read().find(path)?;
+        let (text, line_endings) = LineEndings::normalize(text);
+        let text = Arc::new(text);
+        let file = match self.find_file(root, &rel_path) {
+            Some(file) => {
+                self.change_file(file, text, line_endings);
+                file
+            }
+            None => self.add_file(root, rel_path, text, line_endings, true),
+        };
+        self.files[file.0 as usize].is_overlayed = true;
+        Some(file)
+    }
+
+    pub fn change_file_overlay(&mut self, path: &Path, new_text: String) {
+        let file = match self.path2file(path) {
+            Some(it) => it,
+            None => return,
+        };
+        let (text, line_endings) = LineEndings::normalize(new_text);
+        self.change_file(file, Arc::new(text), line_endings);
+    }
+
+    /// Drops the editor's version of the file, falling back to the disk one.
+    pub fn remove_file_overlay(&mut self, path: &Path) -> Option<VfsFile> {
+        let file = self.path2file(path)?;
+        self.files[file.0 as usize].is_overlayed = false;
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let (text, line_endings) = LineEndings::normalize(text);
+                self.change_file(file, Arc::new(text), line_endings);
+            }
+            Err(_) => self.remove_file(file),
+        }
+        Some(file)
+    }
+
+    pub fn handle_task(&mut self, task: VfsTask) {
+        match task.0 {
+            TaskResult::BulkLoadRoot { root, mut files } => {
+                // The root might have been removed while it was being loaded.
+                if self.roots.read().is_removed(root) {
+                    files.clear();
+                }
+                let mut cur_files = Vec::new();
+                for (path, text) in files {
+                    let (text, line_endings) = LineEndings::normalize(text);
+                    let text = Arc::new(text);
+                    // The file may have been loaded with `load` already.
+                    let file = match self.find_file(root, &path) {
+                        Some(file) => {
+                            let data = &mut self.files[file.0 as usize];
+                            if !data.is_overlayed {
+                                data.text = Arc::clone(&text);
+                                data.line_endings = line_endings;
+                            }
+                            file
+                        }
+                        None => {
+                            self.raw_add_file(root, path.clone(), Arc::clone(&text), line_endings)
+                        }
+                    };
+                    let text = Arc::clone(&self.files[file.0 as usize].text);
+                    cur_files.push((file, path, text));
+                }
+                self.pending_changes.push(VfsChange::AddRoot { root, files: cur_files });
+            }
+            TaskResult::FileChanges(changes) => {
+                for (root, path, text) in changes {
+                    if self.roots.read().is_removed(root) {
+                        continue;
+                    }
+                    self.do_change(root, path, text);
+                }
+            }
+        }
+    }
+
+    /// Returns all the changes since the last call.
+    pub fn commit_changes(&mut self) -> Vec<VfsChange> {
+        mem::replace(&mut self.pending_changes, Vec::new())
+    }
+
+    fn do_change(&mut self, root: VfsRoot, path: RelativePathBuf, text: Option<String>) {
+        let file = self.find_file(root, &path);
+        // The editor is the source of truth for the overlayed files.
+        if file.map_or(false, |it| self.file(it).is_overlayed) {
+            return;
+        }
+        match (file, text) {
+            (Some(file), Some(text)) => {
+                let (text, line_endings) = LineEndings::normalize(text);
+                if self.file(file).text.as_str() != text {
+                    self.change_file(file, Arc::new(text), line_endings);
+                }
+            }
+            (Some(file), None) => self.remove_file(file),
+            (None, Some(text)) => {
+                let (text, line_endings) = LineEndings::normalize(text);
+                self.add_file(root, path, Arc::new(text), line_endings, false);
+            }
+            (None, None) => {
+                // A directory was removed
+                let removed: Vec<VfsFile> = self.root2files[&root]
+                    .iter()
+                    .filter(|(it, _)| it.starts_with(&path))
+                    .map(|(_, &file)| file)
+                    .filter(|&file| !self.file(file).is_overlayed)
+                    .collect();
+                for file in removed {
+                    self.remove_file(file);
+                }
+            }
+        }
+    }
+
+    fn add_file(
+        &mut self,
+        root: VfsRoot,
+        path: RelativePathBuf,
+        text: Arc<String>,
+        line_endings: LineEndings,
+        is_overlayed: bool,
+    ) -> VfsFile {
+        let file = self.raw_add_file(root, path.clone(), Arc::clone(&text), line_endings);
+        self.files[file.0 as usize].is_overlayed = is_overlayed;
+        self.pending_changes.push(VfsChange::AddFile { root, file, path, text });
+        file
+    }
+
+    fn raw_add_file(
+        &mut self,
+        root: VfsRoot,
+        path: RelativePathBuf,
+        text: Arc<String>,
+        line_endings: LineEndings,
+    ) -> VfsFile {
+        let file = VfsFile(self.files.len() as u32);
+        self.root2files.get_mut(&root).unwrap().insert(path.clone(), file);
+        self.files.push(VfsFileData { root, path, is_overlayed: false, text, line_endings });
+        file
+    }
+
+    fn change_file(&mut self, file: VfsFile, text: Arc<String>, line_endings: LineEndings) {
+        let data = &mut self.files[file.0 as usize];
+        data.text = Arc::clone(&text);
+        data.line_endings = line_endings;
+        self.pending_changes.push(VfsChange::ChangeFile { file, text });
+    }
+
+    fn remove_file(&mut self, file: VfsFile) {
+        let data = &self.files[file.0 as usize];
+        let (root, path) = (data.root, data.path.clone());
+        self.root2files.get_mut(&root).unwrap().remove(&path);
+        self.pending_changes.push(VfsChange::RemoveFile { root, file, path });
+    }
+
+    /// Removes the file from its old root after the roots have changed.
+    fn move_file(&mut self, file: VfsFile) {
+        self.remove_file(file);
+        // The overlays are not on disk, so they won't be picked up by the
+        // scan of the new root.
+        if self.file(file).is_overlayed {
+            let path = self.file2path(file);
+            let new_place = self.roots.read().find(&path);
+            if let Some((root, path)) = new_place {
+                let data = self.file(file);
+                let (text, line_endings) = (Arc::clone(&data.text), data.line_endings);
+                self.add_file(root, path, text, line_endings, true);
+            }
+        }
+    }
+
+    fn find_file(&self, root: VfsRoot, path: &RelativePathBuf) -> Option<VfsFile> {
+        self.root2files[&root].get(path).copied()
+    }
+
+    fn file(&self, file: VfsFile) -> &VfsFileData {
+        &self.files[file.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam_channel::unbounded;
+
+    use super::*;
+
+    struct RustFiles;
+
+    impl Filter for RustFiles {
+        fn include_dir(&self, _dir_path: &RelativePath) -> bool {
+            true
+        }
+
+        fn include_file(&self, file_path: &RelativePath) -> bool {
+            file_path.extension() == Some("rs")
+        }
+    }
+
+    #[test]
+    fn test_vfs_overlays() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_path = dir.path().canonicalize().unwrap();
+        fs::create_dir(root_path.join("src")).unwrap();
+        fs::write(root_path.join("src/lib.rs"), "mod a;\r\n").unwrap();
+        fs::write(root_path.join("Cargo.toml"), "").unwrap();
+
+        let (sender, receiver) = unbounded();
+        let (mut vfs, roots) = Vfs::new(
+            vec![RootEntry::new(root_path.clone(), Box::new(RustFiles))],
+            Box::new(move |task| sender.send(task).unwrap()),
+            Watch(false),
+        );
+        assert_eq!(roots.len(), 1);
+        vfs.handle_task(receiver.recv().unwrap());
+        let lib = match vfs.commit_changes().as_slice() {
+            [VfsChange::AddRoot { files, .. }] => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].1.as_str(), "src/lib.rs");
+                assert_eq!(files[0].2.as_str(), "mod a;\n");
+                files[0].0
+            }
+            changes => panic!("unexpected changes: {:?}", changes),
+        };
+        assert_eq!(vfs.file_line_endings(lib), LineEndings::Dos);
+        assert_eq!(vfs.path2file(&root_path.join("src/lib.rs")), Some(lib));
+        assert_eq!(vfs.path2file(&root_path.join("Cargo.toml")), None);
+
+        let a_path = root_path.join("src/a.rs");
+        let a = vfs.add_file_overlay(&a_path, "fn foo() {}".to_string()).unwrap();
+        vfs.change_file_overlay(&a_path, "fn bar() {}".to_string());
+        assert_eq!(vfs.file2path(a), a_path);
+        match vfs.commit_changes().as_slice() {
+            [VfsChange::AddFile { file, .. }, VfsChange::ChangeFile { text, .. }] => {
+                assert_eq!(*file, a);
+                assert_eq!(text.as_str(), "fn bar() {}");
+            }
+            changes => panic!("unexpected changes: {:?}", changes),
+        }
+
+        // The file doesn't exist on disk, so closing it removes it.
+        assert_eq!(vfs.remove_file_overlay(&a_path), Some(a));
+        match vfs.commit_changes().as_slice() {
+            [VfsChange::RemoveFile { file, .. }] => assert_eq!(*file, a),
+            changes => panic!("unexpected changes: {:?}", changes),
+        }
+        assert_eq!(vfs.path2file(&a_path), None);
+    }
+}
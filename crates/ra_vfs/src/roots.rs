@@ -0,0 +1,217 @@
+//! Roots are the directories the VFS loads files from.
+//!
+//! Roots can be nested: a file belongs to the innermost root which contains
+//! it, so the directories of the inner roots are excluded from the outer ones.
+
+use std::{
+    iter,
+    path::{Path, PathBuf},
+};
+
+use relative_path::{RelativePath, RelativePathBuf};
+
+use crate::{Filter, RootEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VfsRoot(pub u32);
+
+pub(crate) struct Roots {
+    roots: Vec<RootData>,
+}
+
+struct RootData {
+    path: PathBuf,
+    filter: Box<dyn Filter>,
+    /// Directories of the nested roots.
+    excluded_dirs: Vec<RelativePathBuf>,
+    /// The removed roots keep their ids, but no longer contain any files.
+    is_removed: bool,
+}
+
+impl Roots {
+    pub(crate) fn new(entries: Vec<RootEntry>) -> Roots {
+        let mut roots = Roots { roots: Vec::new() };
+        roots.add(entries);
+        roots
+    }
+
+    /// Adds the roots which are not present yet, returning the new ones.
+    pub(crate) fn add(&mut self, mut entries: Vec<RootEntry>) -> Vec<VfsRoot> {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries.dedup_by(|a, b| a.path == b.path);
+        entries.retain(|entry| self.iter().all(|it| self.path(it) != entry.path));
+
+        let start = self.roots.len();
+        self.roots.extend(entries.into_iter().map(|entry| RootData {
+            path: entry.path,
+            filter: entry.filter,
+            excluded_dirs: Vec::new(),
+            is_removed: false,
+        }));
+        self.update_excluded_dirs();
+        (start..self.roots.len()).map(|idx| VfsRoot(idx as u32)).collect()
+    }
+
+    /// Removes the roots whose paths are not in `paths`, returning them.
+    pub(crate) fn retain(&mut self, paths: &[&Path]) -> Vec<VfsRoot> {
+        let removed: Vec<VfsRoot> =
+            self.iter().filter(|&it| !paths.contains(&self.path(it))).collect();
+        for root in removed.iter() {
+            self.roots[root.0 as usize].is_removed = true;
+        }
+        self.update_excluded_dirs();
+        removed
+    }
+
+    /// The number of the roots ever added, including the removed ones.
+    pub(crate) fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Iterates over the roots which are not removed.
+    pub(crate) fn iter<'a>(&'a self) -> impl Iterator<Item = VfsRoot> + 'a {
+        (0..self.roots.len()).map(|idx| VfsRoot(idx as u32)).filter(move |&it| !self.is_removed(it))
+    }
+
+    pub(crate) fn is_removed(&self, root: VfsRoot) -> bool {
+        self.root(root).is_removed
+    }
+
+    pub(crate) fn path(&self, root: VfsRoot) -> &Path {
+        &self.root(root).path
+    }
+
+    /// Finds the root containing the file at `path`.
+    pub(crate) fn find(&self, path: &Path) -> Option<(VfsRoot, RelativePathBuf)> {
+        self.iter().find_map(|root| {
+            let rel_path = self.contains(root, path)?;
+            Some((root, rel_path))
+        })
+    }
+
+    /// Finds the root containing `path`, which may be a directory or a file
+    /// which no longer exists.
+    pub(crate) fn find_any(&self, path: &Path) -> Option<(VfsRoot, RelativePathBuf)> {
+        self.iter().find_map(|root| {
+            let rel_path = self.relative_path(root, path)?;
+            Some((root, rel_path))
+        })
+    }
+
+    /// Checks if the file at `path` belongs to the `root`.
+    pub(crate) fn contains(&self, root: VfsRoot, path: &Path) -> Option<RelativePathBuf> {
+        let rel_path = self.relative_path(root, path)?;
+        if self.include_file(root, &rel_path) {
+            Some(rel_path)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn include_dir(&self, root: VfsRoot, rel_path: &RelativePath) -> bool {
+        let data = self.root(root);
+        !data.excluded_dirs.iter().any(|it| it == rel_path) && data.filter.include_dir(rel_path)
+    }
+
+    pub(crate) fn include_file(&self, root: VfsRoot, rel_path: &RelativePath) -> bool {
+        self.root(root).filter.include_file(rel_path)
+    }
+
+    /// Returns the path relative to the `root` if neither it nor any of its
+    /// parent directories are excluded.
+    fn relative_path(&self, root: VfsRoot, path: &Path) -> Option<RelativePathBuf> {
+        let rel_path = relative_path(self.path(root), path)?;
+        let is_included = iter::successors(rel_path.parent(), |it| it.parent())
+            .filter(|it| !it.as_str().is_empty())
+            .all(|it| self.include_dir(root, it));
+        if is_included && !self.root(root).excluded_dirs.iter().any(|it| it == &rel_path) {
+            Some(rel_path)
+        } else {
+            None
+        }
+    }
+
+    fn update_excluded_dirs(&mut self) {
+        let live: Vec<VfsRoot> = self.iter().collect();
+        for &root in live.iter() {
+            let excluded_dirs = live
+                .iter()
+                .filter(|&&it| it != root)
+                .filter_map(|&it| relative_path(self.path(root), self.path(it)))
+                .collect();
+            self.roots[root.0 as usize].excluded_dirs = excluded_dirs;
+        }
+    }
+
+    fn root(&self, root: VfsRoot) -> &RootData {
+        &self.roots[root.0 as usize]
+    }
+}
+
+fn relative_path(base: &Path, path: &Path) -> Option<RelativePathBuf> {
+    let path = path.strip_prefix(base).ok()?;
+    RelativePathBuf::from_path(path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct RustFiles;
+
+    impl Filter for RustFiles {
+        fn include_dir(&self, dir_path: &RelativePath) -> bool {
+            dir_path.as_str() != "target"
+        }
+
+        fn include_file(&self, file_path: &RelativePath) -> bool {
+            file_path.extension() == Some("rs")
+        }
+    }
+
+    #[test]
+    fn test_nested_roots() {
+        let entry = |path: &str| RootEntry::new(PathBuf::from(path), Box::new(RustFiles));
+        let roots = Roots::new(vec![entry("/ws"), entry("/ws/crates/foo"), entry("/ws")]);
+        assert_eq!(roots.len(), 2);
+
+        let find = |path: &str| {
+            roots.find(Path::new(path)).map(|(root, rel_path)| {
+                (roots.path(root).to_path_buf(), rel_path.as_str().to_string())
+            })
+        };
+        assert_eq!(find("/ws/src/lib.rs"), Some((PathBuf::from("/ws"), "src/lib.rs".to_string())));
+        assert_eq!(
+            find("/ws/crates/foo/src/lib.rs"),
+            Some((PathBuf::from("/ws/crates/foo"), "src/lib.rs".to_string()))
+        );
+        assert_eq!(find("/ws/Cargo.toml"), None);
+        assert_eq!(find("/ws/target/debug/build.rs"), None);
+        assert_eq!(find("/other/lib.rs"), None);
+    }
+
+    #[test]
+    fn test_add_nested_root() {
+        let entry = |path: &str| RootEntry::new(PathBuf::from(path), Box::new(RustFiles));
+        let mut roots = Roots::new(vec![entry("/ws")]);
+        let new_roots = roots.add(vec![entry("/ws"), entry("/ws/vendor/bar")]);
+        assert_eq!(new_roots, vec![VfsRoot(1)]);
+        assert_eq!(roots.find(Path::new("/ws/vendor/bar/lib.rs")).map(|it| it.0), Some(VfsRoot(1)));
+        assert_eq!(roots.find(Path::new("/ws/vendor/baz/lib.rs")).map(|it| it.0), Some(VfsRoot(0)));
+    }
+
+    #[test]
+    fn test_remove_nested_root() {
+        let entry = |path: &str| RootEntry::new(PathBuf::from(path), Box::new(RustFiles));
+        let mut roots = Roots::new(vec![entry("/ws"), entry("/ws/vendor/bar")]);
+        let removed = roots.retain(&[Path::new("/ws")]);
+        assert_eq!(removed, vec![VfsRoot(1)]);
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots.iter().collect::<Vec<_>>(), vec![VfsRoot(0)]);
+        assert_eq!(roots.find(Path::new("/ws/vendor/bar/lib.rs")).map(|it| it.0), Some(VfsRoot(0)));
+
+        let new_roots = roots.add(vec![entry("/ws/vendor/bar")]);
+        assert_eq!(new_roots, vec![VfsRoot(2)]);
+        assert_eq!(roots.find(Path::new("/ws/vendor/bar/lib.rs")).map(|it| it.0), Some(VfsRoot(2)));
+    }
+}
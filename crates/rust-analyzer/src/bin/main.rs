@@ -3,7 +3,7 @@
 //! Based on cli flags, either spawns an LSP server, or runs a batch analysis
 mod args;
 
-use lsp_server::Connection;
+use lsp_server::{Connection, ErrorCode, Message, RequestId, Response};
 use rust_analyzer::{cli, config::Config, from_json, Result};
 
 use crate::args::HelpPrinted;
@@ -38,6 +38,12 @@ fn main() -> Result<()> {
         args::Command::Bench { path, what, load_output_dirs } => {
             cli::analysis_bench(args.verbosity, path.as_ref(), what, load_output_dirs)?
         }
+        args::Command::Diagnostics { path, load_output_dirs, with_deps } => {
+            cli::diagnostics(path.as_ref(), load_output_dirs, with_deps)?
+        }
+        args::Command::DumpSymbols { path, load_output_dirs, with_deps } => {
+            cli::dump_symbols(path.as_ref(), load_output_dirs, with_deps)?
+        }
 
         args::Command::RunServer => run_server()?,
         args::Command::Version => println!("rust-analyzer {}", env!("REV")),
@@ -56,9 +62,13 @@ fn run_server() -> Result<()> {
     log::info!("lifecycle: server started");
 
     let (connection, io_threads) = Connection::stdio();
-    let server_capabilities = serde_json::to_value(rust_analyzer::server_capabilities()).unwrap();
+    let mut server_capabilities =
+        serde_json::to_value(rust_analyzer::server_capabilities()).unwrap();
+    // `lsp_types` doesn't know about `codeAction/resolve` yet.
+    server_capabilities["codeActionProvider"] = serde_json::json!({ "resolveProvider": true });
 
-    let initialize_params = connection.initialize(server_capabilities)?;
+    let (initialize_id, initialize_params) = initialize_start(&connection)?;
+    let raw_capabilities = initialize_params.get("capabilities").cloned().unwrap_or_default();
     let initialize_params =
         from_json::<lsp_types::InitializeParams>("InitializeParams", initialize_params)?;
 
@@ -82,12 +92,17 @@ fn run_server() -> Result<()> {
         if let Some(value) = &initialize_params.initialization_options {
             config.update(value);
         }
-        if let Some(caps) = &initialize_params.capabilities.text_document {
-            config.update_caps(caps);
-        }
+        config.update_caps(&initialize_params.capabilities);
+        config.update_raw_caps(&raw_capabilities);
         config
     };
 
+    let initialize_result = serde_json::json!({
+        "capabilities": server_capabilities,
+        "offsetEncoding": config.client_caps.offset_encoding.name(),
+    });
+    initialize_finish(&connection, initialize_id, initialize_result)?;
+
     rust_analyzer::main_loop(workspace_roots, config, connection)?;
 
     log::info!("shutting down IO...");
@@ -95,3 +110,35 @@ fn run_server() -> Result<()> {
     log::info!("... IO is down");
     Ok(())
 }
+
+/// Waits for the `initialize` request. Unlike `Connection::initialize`, this
+/// doesn't reply right away, as the reply depends on the client capabilities.
+fn initialize_start(connection: &Connection) -> Result<(RequestId, serde_json::Value)> {
+    loop {
+        match connection.receiver.recv()? {
+            Message::Request(req) if req.method == "initialize" => return Ok((req.id, req.params)),
+            Message::Request(req) => {
+                let resp = Response::new_err(
+                    req.id,
+                    ErrorCode::ServerNotInitialized as i32,
+                    format!("expected initialize request, got {:?}", req.method),
+                );
+                connection.sender.send(resp.into()).unwrap();
+            }
+            msg => return Err(format!("expected initialize request, got {:?}", msg).into()),
+        }
+    }
+}
+
+fn initialize_finish(
+    connection: &Connection,
+    initialize_id: RequestId,
+    initialize_result: serde_json::Value,
+) -> Result<()> {
+    let resp = Response::new_ok(initialize_id, initialize_result);
+    connection.sender.send(resp.into()).unwrap();
+    match connection.receiver.recv()? {
+        Message::Notification(not) if not.method == "initialized" => Ok(()),
+        msg => Err(format!("expected initialized notification, got {:?}", msg).into()),
+    }
+}
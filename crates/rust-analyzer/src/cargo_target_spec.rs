@@ -1,5 +1,7 @@
 //! See `CargoTargetSpec`
 
+use std::path::PathBuf;
+
 use ra_ide::{FileId, RunnableKind, TestId};
 use ra_project_model::{self, ProjectWorkspace, TargetKind};
 
@@ -13,10 +15,12 @@ pub(crate) struct CargoTargetSpec {
     pub(crate) package: String,
     pub(crate) target: String,
     pub(crate) target_kind: TargetKind,
+    pub(crate) cargo_toml: PathBuf,
 }
 
 impl CargoTargetSpec {
     pub(crate) fn runnable_args(
+        world: &WorldSnapshot,
         spec: Option<CargoTargetSpec>,
         kind: &RunnableKind,
     ) -> Result<(Vec<String>, Vec<String>)> {
@@ -53,6 +57,17 @@ impl CargoTargetSpec {
                 }
                 extra_args.push("--nocapture".to_string());
             }
+            RunnableKind::DocTest { test_id } => {
+                args.push("test".to_string());
+                if let Some(spec) = spec {
+                    // `--doc` can't be combined with target selection flags
+                    args.push("--package".to_string());
+                    args.push(spec.package);
+                }
+                args.push("--doc".to_string());
+                extra_args.push(test_id.to_string());
+                extra_args.push("--nocapture".to_string());
+            }
             RunnableKind::Bin => {
                 args.push("run".to_string());
                 if let Some(spec) = spec {
@@ -60,6 +75,19 @@ impl CargoTargetSpec {
                 }
             }
         }
+
+        let cargo_config = &world.config.cargo;
+        if cargo_config.all_features {
+            args.push("--all-features".to_string());
+        } else {
+            if cargo_config.no_default_features {
+                args.push("--no-default-features".to_string());
+            }
+            if !cargo_config.features.is_empty() {
+                args.push("--features".to_string());
+                args.push(cargo_config.features.join(" "));
+            }
+        }
         Ok((args, extra_args))
     }
 
@@ -80,6 +108,7 @@ impl CargoTargetSpec {
                     package: cargo.package_flag(&cargo[cargo[tgt].package]),
                     target: cargo[tgt].name.clone(),
                     target_kind: cargo[tgt].kind,
+                    cargo_toml: cargo[cargo[tgt].package].manifest.clone(),
                 })
             }
             ProjectWorkspace::Json { .. } => None,
@@ -110,7 +139,7 @@ impl CargoTargetSpec {
             TargetKind::Lib => {
                 buf.push("--lib".to_string());
             }
-            TargetKind::Other => (),
+            TargetKind::BuildScript | TargetKind::Other => (),
         }
     }
 }
@@ -3,6 +3,8 @@
 mod load_cargo;
 mod analysis_stats;
 mod analysis_bench;
+mod diagnostics;
+mod dump_symbols;
 mod progress_report;
 
 use std::io::Read;
@@ -62,6 +64,8 @@ pub fn highlight(rainbow: bool) -> Result<()> {
 
 pub use analysis_bench::{analysis_bench, BenchWhat, Position};
 pub use analysis_stats::analysis_stats;
+pub use diagnostics::diagnostics;
+pub use dump_symbols::dump_symbols;
 
 fn file() -> Result<SourceFile> {
     let text = read_stdin()?;
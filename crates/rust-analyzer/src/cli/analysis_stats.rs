@@ -1,7 +1,11 @@
 //! Fully type-check project and print various stats, like the number of type
 //! errors.
 
-use std::{collections::HashSet, path::Path, time::Instant};
+use std::{
+    collections::HashSet,
+    path::Path,
+    time::{Duration, Instant},
+};
 
 use hir::{
     db::{AstDatabase, DefDatabase, HirDatabase},
@@ -108,6 +112,7 @@ pub fn analysis_stats(
     let mut num_exprs_unknown = 0;
     let mut num_exprs_partially_unknown = 0;
     let mut num_type_mismatches = 0;
+    let mut infer_times: Vec<(Duration, String)> = Vec::new();
     for f in funcs {
         let name = f.name(db);
         let full_name = f
@@ -136,8 +141,10 @@ pub fn analysis_stats(
         }
         bar.set_message(&msg);
         let f_id = FunctionId::from(f);
+        let infer_start = Instant::now();
         let body = db.body(f_id.into());
         let inference_result = db.infer(f_id.into());
+        infer_times.push((infer_start.elapsed(), full_name.clone()));
         let (previous_exprs, previous_unknown, previous_partially_unknown) =
             (num_exprs, num_exprs_unknown, num_exprs_partially_unknown);
         for (expr_id, _) in body.exprs.iter() {
@@ -245,6 +252,18 @@ pub fn analysis_stats(
     );
     println!("Type mismatches: {}", num_type_mismatches);
     println!("Inference: {:?}, {}", inference_time.elapsed(), ra_prof::memory_usage());
+    infer_times.sort();
+    if let Some((max, slowest)) = infer_times.last() {
+        let percentile = |p: usize| infer_times[(infer_times.len() - 1) * p / 100].0;
+        println!(
+            "Inference per function: p50 {:?}, p90 {:?}, p99 {:?}, max {:?} ({})",
+            percentile(50),
+            percentile(90),
+            percentile(99),
+            max,
+            slowest
+        );
+    }
     println!("Total: {:?}, {}", analysis_time.elapsed(), ra_prof::memory_usage());
 
     if memory_usage {
@@ -0,0 +1,63 @@
+//! Analyzes all the files of a project and prints the diagnostics, failing if
+//! there are errors, so that it can be used on CI.
+
+use std::path::Path;
+
+use anyhow::bail;
+use ra_db::SourceDatabaseExt;
+use ra_ide::Severity;
+
+use crate::cli::{load_cargo::load_cargo, Result};
+
+pub fn diagnostics(path: &Path, load_output_dirs: bool, with_deps: bool) -> Result<()> {
+    let (host, roots) = load_cargo(path, load_output_dirs)?;
+    let db = host.raw_database();
+    let analysis = host.analysis();
+
+    let mut roots: Vec<_> =
+        roots.into_iter().filter(|(_, root)| with_deps || root.is_member()).collect();
+    roots.sort_by(|(_, a), (_, b)| a.path().cmp(b.path()));
+
+    let mut num_errors = 0;
+    for (source_root_id, root) in roots {
+        let mut files: Vec<_> = db.source_root(source_root_id).walk().collect();
+        files.sort_by_key(|&file_id| db.file_relative_path(file_id));
+        for file_id in files {
+            let path = db.file_relative_path(file_id).to_path(root.path());
+            let line_index = analysis.file_line_index(file_id)?;
+            for diagnostic in analysis.diagnostics(file_id, false)? {
+                if let Severity::Error = diagnostic.severity {
+                    num_errors += 1;
+                }
+                let start = line_index.line_col(diagnostic.range.start());
+                println!(
+                    "{}:{}:{}: {:?}: {}",
+                    path.display(),
+                    start.line + 1,
+                    start.col_utf16 + 1,
+                    diagnostic.severity,
+                    diagnostic.message
+                );
+                for label in diagnostic.labels {
+                    let file_id = label.range.file_id;
+                    let path = db.file_relative_path(file_id).to_path(root.path());
+                    let start =
+                        analysis.file_line_index(file_id)?.line_col(label.range.range.start());
+                    println!(
+                        "    {}:{}:{}: note: {}",
+                        path.display(),
+                        start.line + 1,
+                        start.col_utf16 + 1,
+                        label.message
+                    );
+                }
+            }
+        }
+    }
+
+    if num_errors > 0 {
+        bail!("found {} errors", num_errors);
+    }
+    println!("no errors found");
+    Ok(())
+}
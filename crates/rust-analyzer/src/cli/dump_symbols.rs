@@ -0,0 +1,48 @@
+//! Prints the symbols of all the files of a project as JSON, for scripts which
+//! need an index of a project without running an editor.
+
+use std::path::Path;
+
+use ra_db::SourceDatabaseExt;
+use serde_json::json;
+
+use crate::cli::{load_cargo::load_cargo, Result};
+
+pub fn dump_symbols(path: &Path, load_output_dirs: bool, with_deps: bool) -> Result<()> {
+    let (host, roots) = load_cargo(path, load_output_dirs)?;
+    let db = host.raw_database();
+    let analysis = host.analysis();
+
+    let mut roots: Vec<_> =
+        roots.into_iter().filter(|(_, root)| with_deps || root.is_member()).collect();
+    roots.sort_by(|(_, a), (_, b)| a.path().cmp(b.path()));
+
+    let mut symbols = Vec::new();
+    for (source_root_id, root) in roots {
+        let mut files: Vec<_> = db.source_root(source_root_id).walk().collect();
+        files.sort_by_key(|&file_id| db.file_relative_path(file_id));
+        for file_id in files {
+            let path = db.file_relative_path(file_id).to_path(root.path());
+            let line_index = analysis.file_line_index(file_id)?;
+            let structure = analysis.file_structure(file_id)?;
+            for node in structure.iter() {
+                let start = line_index.line_col(node.navigation_range.start());
+                let end = line_index.line_col(node.navigation_range.end());
+                symbols.push(json!({
+                    "name": node.label,
+                    "kind": format!("{:?}", node.kind),
+                    "container": node.parent.map(|idx| &structure[idx].label),
+                    "detail": node.detail,
+                    "file": path,
+                    "range": {
+                        "start": { "line": start.line, "character": start.col_utf16 },
+                        "end": { "line": end.line, "character": end.col_utf16 },
+                    },
+                }));
+            }
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&symbols)?);
+    Ok(())
+}
@@ -141,8 +141,7 @@ pub(crate) fn load(
 
     // FIXME: cfg options?
     let default_cfg_options = {
-        let mut opts = get_rustc_cfg_options();
-        opts.insert_atom("test".into());
+        let mut opts = get_rustc_cfg_options(None);
         opts.insert_atom("debug_assertion".into());
         opts
     };
@@ -1,26 +1,36 @@
 //! Config used by the language server.
 //!
-//! We currently get this config from `initialize` LSP request, which is not the
-//! best way to do it, but was the simplest thing we could implement.
+//! The initial config comes from the `initialize` LSP request, and is updated
+//! with `workspace/configuration` when the client sends
+//! `workspace/didChangeConfiguration`.
 //!
 //! Of particular interest is the `feature_flags` hash map: while other fields
 //! configure the server itself, feature flags are passed into analysis, and
 //! tweak things like automatic insertion of `()` in completions.
 
-use lsp_types::TextDocumentClientCapabilities;
+use std::path::{Path, PathBuf};
+
+use globset::{GlobSet, GlobSetBuilder};
+use lsp_types::{ClientCapabilities, DiagnosticSeverity};
 use ra_flycheck::FlycheckConfig;
-use ra_ide::{CompletionConfig, InlayHintsConfig};
+use ra_ide::{AssistConfig, CompletionConfig, ImportGranularity, InlayHintsConfig, SearchMode};
 use ra_project_model::CargoConfig;
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::Deserialize;
 
+use crate::vfs_glob::Glob;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub client_caps: ClientCapsConfig,
 
     pub with_sysroot: bool,
     pub publish_diagnostics: bool,
+    pub diagnostics: DiagnosticsConfig,
     pub lru_capacity: Option<usize>,
     pub proc_macro_srv: Option<String>,
+    /// Where to save the symbol indices of the libraries between the runs.
+    pub cache_dir: Option<PathBuf>,
     pub files: FilesConfig,
     pub notifications: NotificationsConfig,
 
@@ -30,12 +40,111 @@ pub struct Config {
 
     pub inlay_hints: InlayHintsConfig,
     pub completion: CompletionConfig,
+    pub assist: AssistConfig,
     pub call_info_full: bool,
+    /// How references, highlights, code lenses and incoming calls match calls
+    /// of trait methods.
+    pub search_mode: SearchMode,
+    /// Whether hover and signature help spell out the elided lifetimes of
+    /// function signatures.
+    pub show_elided_lifetimes: bool,
+    /// Macros whose input is written in another language, mapped to the id
+    /// of that language.
+    pub injection_macros: FxHashMap<String, String>,
+}
+
+/// Tweaks the native diagnostics, which are identified by their code, like
+/// `unresolved-module`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiagnosticsConfig {
+    pub disabled: FxHashSet<String>,
+    pub severity_overrides: FxHashMap<String, DiagnosticLevel>,
+    /// Globs of the files which get no native diagnostics, like generated code.
+    /// Relative globs are matched against the path within the workspace.
+    pub exclude: ExcludeGlobs,
+    /// Enables the experimental diagnostics, which approximate the borrow
+    /// checker and may be wrong.
+    pub enable_experimental: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl DiagnosticsConfig {
+    pub fn is_disabled(&self, code: &str) -> bool {
+        self.disabled.contains(code)
+    }
+
+    /// The severity to report a diagnostic with `code` with, if the user
+    /// overrides the default one.
+    pub fn severity(&self, code: &str) -> Option<DiagnosticSeverity> {
+        let severity = match self.severity_overrides.get(code)? {
+            DiagnosticLevel::Error => DiagnosticSeverity::Error,
+            DiagnosticLevel::Warning => DiagnosticSeverity::Warning,
+            DiagnosticLevel::Information => DiagnosticSeverity::Information,
+            DiagnosticLevel::Hint => DiagnosticSeverity::Hint,
+        };
+        Some(severity)
+    }
+
+    pub fn is_excluded(&self, path: &Path, workspace_root: Option<&Path>) -> bool {
+        let relative_path = workspace_root.and_then(|root| path.strip_prefix(root).ok());
+        let set = &self.exclude.set;
+        set.is_match(path) || relative_path.map_or(false, |it| set.is_match(it))
+    }
+}
+
+/// Exclusion globs, compiled once when the config is updated. They compare
+/// equal if they were compiled from the same globs.
+#[derive(Debug, Clone)]
+pub struct ExcludeGlobs {
+    globs: Vec<String>,
+    set: GlobSet,
+}
+
+impl ExcludeGlobs {
+    /// Invalid globs are logged and skipped.
+    pub fn new(globs: Vec<String>) -> ExcludeGlobs {
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs.iter() {
+            match Glob::new(glob) {
+                Ok(it) => {
+                    builder.add(it);
+                }
+                Err(e) => log::error!("invalid exclude glob `{}`: {}", glob, e),
+            }
+        }
+        let set = builder.build().unwrap_or_else(|e| {
+            log::error!("failed to compile exclude globs: {}", e);
+            GlobSetBuilder::new().build().unwrap()
+        });
+        ExcludeGlobs { globs, set }
+    }
+}
+
+impl Default for ExcludeGlobs {
+    fn default() -> ExcludeGlobs {
+        ExcludeGlobs::new(Vec::new())
+    }
+}
+
+impl PartialEq for ExcludeGlobs {
+    fn eq(&self, other: &ExcludeGlobs) -> bool {
+        self.globs == other.globs
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct FilesConfig {
     pub watcher: FilesWatcher,
+    /// Globs of the directories to load even if they are skipped by default.
+    pub include: Vec<String>,
     pub exclude: Vec<String>,
 }
 
@@ -67,6 +176,40 @@ pub enum RustfmtConfig {
 pub struct ClientCapsConfig {
     pub location_link: bool,
     pub line_folding_only: bool,
+    /// The client supports `window/workDoneProgress/create` and `$/progress`.
+    pub work_done_progress: bool,
+    /// The client can lazily compute the edits of code actions with
+    /// `codeAction/resolve`.
+    pub code_action_resolve: bool,
+    /// The client handles `rust-analyzer/statusNotification`.
+    pub status_notification: bool,
+    /// The client can apply `SourceChange`s which contain snippets.
+    pub snippet_text_edit: bool,
+    /// The encoding of the columns in LSP positions, negotiated with the
+    /// `offsetEncoding` capability.
+    pub offset_encoding: OffsetEncoding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetEncoding {
+    Utf8,
+    Utf16,
+}
+
+impl Default for OffsetEncoding {
+    fn default() -> Self {
+        // The only encoding in the LSP spec
+        OffsetEncoding::Utf16
+    }
+}
+
+impl OffsetEncoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            OffsetEncoding::Utf8 => "utf-8",
+            OffsetEncoding::Utf16 => "utf-16",
+        }
+    }
 }
 
 impl Default for Config {
@@ -76,9 +219,15 @@ impl Default for Config {
 
             with_sysroot: true,
             publish_diagnostics: true,
+            diagnostics: DiagnosticsConfig::default(),
             lru_capacity: None,
             proc_macro_srv: None,
-            files: FilesConfig { watcher: FilesWatcher::Notify, exclude: Vec::new() },
+            cache_dir: None,
+            files: FilesConfig {
+                watcher: FilesWatcher::Notify,
+                include: Vec::new(),
+                exclude: Vec::new(),
+            },
             notifications: NotificationsConfig {
                 workspace_loaded: true,
                 cargo_toml_not_found: true,
@@ -89,6 +238,10 @@ impl Default for Config {
             check: Some(FlycheckConfig::CargoCommand {
                 command: "check".to_string(),
                 all_targets: true,
+                all_features: true,
+                no_default_features: false,
+                features: Vec::new(),
+                target: None,
                 extra_args: Vec::new(),
             }),
 
@@ -96,6 +249,7 @@ impl Default for Config {
                 type_hints: true,
                 parameter_hints: true,
                 chaining_hints: true,
+                drop_hints: false,
                 max_length: None,
             },
             completion: CompletionConfig {
@@ -103,7 +257,11 @@ impl Default for Config {
                 add_call_parenthesis: true,
                 add_call_argument_snippets: true,
             },
+            assist: AssistConfig::default(),
             call_info_full: true,
+            search_mode: SearchMode::WithDispatch,
+            show_elided_lifetimes: false,
+            injection_macros: FxHashMap::default(),
         }
     }
 }
@@ -119,11 +277,20 @@ impl Config {
 
         set(value, "/withSysroot", &mut self.with_sysroot);
         set(value, "/featureFlags/lsp.diagnostics", &mut self.publish_diagnostics);
+        set(value, "/diagnostics/disabled", &mut self.diagnostics.disabled);
+        set(value, "/diagnostics/severityOverrides", &mut self.diagnostics.severity_overrides);
+        if let Some(globs) = get(value, "/diagnostics/exclude") {
+            self.diagnostics.exclude = ExcludeGlobs::new(globs);
+        }
+        set(value, "/diagnostics/enableExperimental", &mut self.diagnostics.enable_experimental);
         set(value, "/lruCapacity", &mut self.lru_capacity);
+        set(value, "/cacheDirectory", &mut self.cache_dir);
         self.files.watcher = match get(value, "/files/watcher") {
             Some("client") => FilesWatcher::Client,
             Some("notify") | _ => FilesWatcher::Notify
         };
+        set(value, "/files/include", &mut self.files.include);
+        set(value, "/files/exclude", &mut self.files.exclude);
         set(value, "/notifications/workspaceLoaded", &mut self.notifications.workspace_loaded);
         set(value, "/notifications/cargoTomlNotFound", &mut self.notifications.cargo_toml_not_found);
 
@@ -131,6 +298,8 @@ impl Config {
         set(value, "/cargo/allFeatures", &mut self.cargo.all_features);
         set(value, "/cargo/features", &mut self.cargo.features);
         set(value, "/cargo/loadOutDirsFromCheck", &mut self.cargo.load_out_dirs_from_check);
+        set(value, "/cargo/sysrootSrc", &mut self.cargo.sysroot_src);
+        set(value, "/cargo/target", &mut self.cargo.target);
         match get::<Vec<String>>(value, "/rustfmt/overrideCommand") {
             Some(mut args) if !args.is_empty() => {
                 let command = args.remove(0);
@@ -162,12 +331,24 @@ impl Config {
                 }
                 // otherwise configure command customizations
                 _ => {
-                    if let Some(FlycheckConfig::CargoCommand { command, extra_args, all_targets })
-                        = &mut self.check
+                    if let Some(FlycheckConfig::CargoCommand {
+                        command,
+                        all_targets,
+                        all_features,
+                        no_default_features,
+                        features,
+                        target,
+                        extra_args,
+                    }) = &mut self.check
                     {
                         set(value, "/checkOnSave/extraArgs", extra_args);
                         set(value, "/checkOnSave/command", command);
                         set(value, "/checkOnSave/allTargets", all_targets);
+                        // Check the same features as the ones used for analysis.
+                        *all_features = self.cargo.all_features;
+                        *no_default_features = self.cargo.no_default_features;
+                        *features = self.cargo.features.clone();
+                        *target = self.cargo.target.clone();
                     }
                 }
             };
@@ -176,11 +357,23 @@ impl Config {
         set(value, "/inlayHints/typeHints", &mut self.inlay_hints.type_hints);
         set(value, "/inlayHints/parameterHints", &mut self.inlay_hints.parameter_hints);
         set(value, "/inlayHints/chainingHints", &mut self.inlay_hints.chaining_hints);
+        set(value, "/inlayHints/dropHints", &mut self.inlay_hints.drop_hints);
         set(value, "/inlayHints/maxLength", &mut self.inlay_hints.max_length);
         set(value, "/completion/postfix/enable", &mut self.completion.enable_postfix_completions);
         set(value, "/completion/addCallParenthesis", &mut self.completion.add_call_parenthesis);
         set(value, "/completion/addCallArgumentSnippets", &mut self.completion.add_call_argument_snippets);
+        self.assist.insert_use.granularity = match get(value, "/assist/importGranularity") {
+            Some("module") => ImportGranularity::Module,
+            Some("item") => ImportGranularity::Item,
+            Some("crate") | _ => ImportGranularity::Crate,
+        };
         set(value, "/callInfo/full", &mut self.call_info_full);
+        self.search_mode = match get(value, "/references/exactMethodCalls") {
+            Some(true) => SearchMode::Exact,
+            Some(false) | None => SearchMode::WithDispatch,
+        };
+        set(value, "/signatures/showElidedLifetimes", &mut self.show_elided_lifetimes);
+        set(value, "/highlighting/injectionMacros", &mut self.injection_macros);
 
         log::info!("Config::update() = {:#?}", self);
 
@@ -195,12 +388,96 @@ impl Config {
         }
     }
 
-    pub fn update_caps(&mut self, caps: &TextDocumentClientCapabilities) {
-        if let Some(value) = caps.definition.as_ref().and_then(|it| it.link_support) {
-            self.client_caps.location_link = value;
+    pub fn update_caps(&mut self, caps: &ClientCapabilities) {
+        if let Some(caps) = &caps.text_document {
+            if let Some(value) = caps.definition.as_ref().and_then(|it| it.link_support) {
+                self.client_caps.location_link = value;
+            }
+            if let Some(value) = caps.folding_range.as_ref().and_then(|it| it.line_folding_only) {
+                self.client_caps.line_folding_only = value
+            }
         }
-        if let Some(value) = caps.folding_range.as_ref().and_then(|it| it.line_folding_only) {
-            self.client_caps.line_folding_only = value
+    }
+
+    /// Reads the LSP 3.16 capabilities which are not yet supported by
+    /// `lsp_types` from the raw `initialize` params.
+    pub fn update_raw_caps(&mut self, caps: &serde_json::Value) {
+        if let Some(value) = caps.pointer("/window/workDoneProgress").and_then(|it| it.as_bool()) {
+            self.client_caps.work_done_progress = value;
         }
+        if let Some(value) =
+            caps.pointer("/experimental/statusNotification").and_then(|it| it.as_bool())
+        {
+            self.client_caps.status_notification = value;
+        }
+        if let Some(value) =
+            caps.pointer("/experimental/snippetTextEdit").and_then(|it| it.as_bool())
+        {
+            self.client_caps.snippet_text_edit = value;
+        }
+        // Pick UTF-8 if the client supports it, as that's what we use
+        // internally, so positions don't have to be converted.
+        let offset_encodings = caps.pointer("/offsetEncoding").and_then(|it| it.as_array());
+        if let Some(encodings) = offset_encodings {
+            if encodings.iter().any(|it| it.as_str() == Some(OffsetEncoding::Utf8.name())) {
+                self.client_caps.offset_encoding = OffsetEncoding::Utf8;
+            }
+        }
+        let resolve_properties = caps
+            .pointer("/textDocument/codeAction/resolveSupport/properties")
+            .and_then(|it| it.as_array());
+        if let Some(properties) = resolve_properties {
+            self.client_caps.code_action_resolve =
+                properties.iter().any(|it| it.as_str() == Some("edit"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn diagnostics_config() {
+        let mut config = Config::default();
+        config.update(&json!({
+            "diagnostics": {
+                "disabled": ["unresolved-module"],
+                "severityOverrides": { "unused-mut": "warning" },
+                "exclude": ["**/generated/**", "/tmp/**"],
+                "enableExperimental": true
+            }
+        }));
+        let diagnostics = &config.diagnostics;
+        assert!(diagnostics.is_disabled("unresolved-module"));
+        assert!(!diagnostics.is_disabled("unused-mut"));
+        assert_eq!(diagnostics.severity("unused-mut"), Some(DiagnosticSeverity::Warning));
+        assert_eq!(diagnostics.severity("needs-mut"), None);
+        assert!(diagnostics.enable_experimental);
+
+        let root = Path::new("/home/user/project");
+        assert!(
+            diagnostics.is_excluded(Path::new("/home/user/project/src/generated/a.rs"), Some(root))
+        );
+        assert!(diagnostics.is_excluded(Path::new("/tmp/a.rs"), None));
+        assert!(!diagnostics.is_excluded(Path::new("/home/user/project/src/lib.rs"), Some(root)));
+
+        // Settings missing from an update go back to their defaults.
+        config.update(&json!({}));
+        assert!(!config.diagnostics.is_disabled("unresolved-module"));
+        assert!(!config.diagnostics.enable_experimental);
+        assert!(!config.diagnostics.is_excluded(Path::new("/tmp/a.rs"), None));
+    }
+
+    #[test]
+    fn search_mode_config() {
+        let mut config = Config::default();
+        assert_eq!(config.search_mode, SearchMode::WithDispatch);
+        config.update(&json!({ "references": { "exactMethodCalls": true } }));
+        assert_eq!(config.search_mode, SearchMode::Exact);
+        config.update(&json!({}));
+        assert_eq!(config.search_mode, SearchMode::WithDispatch);
     }
 }
@@ -1,6 +1,8 @@
 //! Convenience module responsible for translating between rust-analyzer's types
 //! and LSP types.
 
+use std::sync::Arc;
+
 use lsp_types::{
     self, CreateFile, DiagnosticSeverity, DocumentChangeOperation, DocumentChanges, Documentation,
     Location, LocationLink, MarkupContent, MarkupKind, ParameterInformation, ParameterLabel,
@@ -11,7 +13,7 @@ use lsp_types::{
 use ra_ide::{
     translate_offset_with_edit, CompletionItem, CompletionItemKind, FileId, FilePosition,
     FileRange, FileSystemEdit, Fold, FoldKind, Highlight, HighlightModifier, HighlightTag,
-    InlayHint, InlayKind, InsertTextFormat, LineCol, LineIndex, NavigationTarget, RangeInfo,
+    InlayHint, InlayKind, InsertTextFormat, LineCol, LineColUtf8, NavigationTarget, RangeInfo,
     ReferenceAccess, Severity, SourceChange, SourceFileEdit,
 };
 use ra_syntax::{SyntaxKind, TextRange, TextUnit};
@@ -19,12 +21,18 @@ use ra_text_edit::{AtomTextEdit, TextEdit};
 use ra_vfs::LineEndings;
 
 use crate::{
+    config::OffsetEncoding,
     req,
-    semantic_tokens::{self, ModifierSet, CONSTANT, CONTROL_FLOW, MUTABLE, UNSAFE},
+    semantic_tokens::{
+        self, ModifierSet, CONSTANT, CONTROL_FLOW, MACRO_EXPANSION, MUTABLE, UNSAFE,
+    },
     world::WorldSnapshot,
     Result,
 };
-use semantic_tokens::{ATTRIBUTE, BUILTIN_TYPE, ENUM_MEMBER, LIFETIME, TYPE_ALIAS, UNION};
+use semantic_tokens::{
+    ATTRIBUTE, BUILTIN_TYPE, ENUM_MEMBER, ESCAPE_SEQUENCE, FORMAT_SPECIFIER, LIFETIME, TYPE_ALIAS,
+    UNION,
+};
 
 pub trait Conv {
     type Output;
@@ -176,12 +184,27 @@ impl ConvWith<(&LineIndex, LineEndings)> for CompletionItem {
     }
 }
 
+/// `ra_ide::LineIndex` together with the offset encoding of the client, which
+/// is all that's needed to convert between LSP positions and offsets.
+#[derive(Debug, Clone)]
+pub(crate) struct LineIndex {
+    pub(crate) index: Arc<ra_ide::LineIndex>,
+    pub(crate) encoding: OffsetEncoding,
+}
+
 impl ConvWith<&LineIndex> for Position {
     type Output = TextUnit;
 
     fn conv_with(self, line_index: &LineIndex) -> TextUnit {
-        let line_col = LineCol { line: self.line as u32, col_utf16: self.character as u32 };
-        line_index.offset(line_col)
+        let line = self.line as u32;
+        match line_index.encoding {
+            OffsetEncoding::Utf8 => {
+                line_index.index.offset_utf8(LineColUtf8 { line, col: self.character as u32 })
+            }
+            OffsetEncoding::Utf16 => {
+                line_index.index.offset(LineCol { line, col_utf16: self.character as u32 })
+            }
+        }
     }
 }
 
@@ -189,8 +212,17 @@ impl ConvWith<&LineIndex> for TextUnit {
     type Output = Position;
 
     fn conv_with(self, line_index: &LineIndex) -> Position {
-        let line_col = line_index.line_col(self);
-        Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16))
+        let (line, col) = match line_index.encoding {
+            OffsetEncoding::Utf8 => {
+                let line_col = line_index.index.line_col_utf8(self);
+                (line_col.line, line_col.col)
+            }
+            OffsetEncoding::Utf16 => {
+                let line_col = line_index.index.line_col(self);
+                (line_col.line, line_col.col_utf16)
+            }
+        };
+        Position::new(u64::from(line), u64::from(col))
     }
 }
 
@@ -223,21 +255,31 @@ impl Conv for ra_ide::Documentation {
 impl ConvWith<bool> for ra_ide::FunctionSignature {
     type Output = lsp_types::SignatureInformation;
     fn conv_with(self, concise: bool) -> Self::Output {
-        let (label, documentation, params) = if concise {
+        let (label, documentation, params, param_docs) = if concise {
             let mut params = self.parameters;
+            let mut param_docs = self.parameter_docs;
             if self.has_self_param {
                 params.remove(0);
+                if !param_docs.is_empty() {
+                    param_docs.remove(0);
+                }
             }
-            (params.join(", "), None, params)
+            (params.join(", "), None, params, param_docs)
         } else {
-            (self.to_string(), self.doc.map(|it| it.conv()), self.parameters)
+            (self.to_string(), self.doc.map(|it| it.conv()), self.parameters, self.parameter_docs)
         };
 
+        let mut param_docs = param_docs.into_iter();
         let parameters: Vec<ParameterInformation> = params
             .into_iter()
             .map(|param| ParameterInformation {
                 label: ParameterLabel::Simple(param),
-                documentation: None,
+                documentation: param_docs.next().flatten().map(|doc| {
+                    Documentation::MarkupContent(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: doc,
+                    })
+                }),
             })
             .collect();
 
@@ -281,8 +323,8 @@ impl ConvWith<&FoldConvCtx<'_>> for Fold {
         let kind = match self.kind {
             FoldKind::Comment => Some(lsp_types::FoldingRangeKind::Comment),
             FoldKind::Imports => Some(lsp_types::FoldingRangeKind::Imports),
-            FoldKind::Mods => None,
-            FoldKind::Block => None,
+            FoldKind::Region => Some(lsp_types::FoldingRangeKind::Region),
+            FoldKind::Mods | FoldKind::Block | FoldKind::ArgList => None,
         };
 
         let range = self.range.conv_with(&ctx.line_index);
@@ -333,6 +375,7 @@ impl ConvWith<&LineIndex> for InlayHint {
                 InlayKind::ParameterHint => req::InlayKind::ParameterHint,
                 InlayKind::TypeHint => req::InlayKind::TypeHint,
                 InlayKind::ChainingHint => req::InlayKind::ChainingHint,
+                InlayKind::DropHint => req::InlayKind::DropHint,
             },
         }
     }
@@ -372,7 +415,10 @@ impl Conv for Highlight {
             HighlightTag::CharLiteral | HighlightTag::StringLiteral => SemanticTokenType::STRING,
             HighlightTag::Comment => SemanticTokenType::COMMENT,
             HighlightTag::Attribute => ATTRIBUTE,
+            HighlightTag::EscapeSequence => ESCAPE_SEQUENCE,
+            HighlightTag::FormatSpecifier => FORMAT_SPECIFIER,
             HighlightTag::Keyword => SemanticTokenType::KEYWORD,
+            HighlightTag::Operator => SemanticTokenType::OPERATOR,
         };
 
         for modifier in self.modifiers.iter() {
@@ -381,6 +427,7 @@ impl Conv for Highlight {
                 HighlightModifier::ControlFlow => CONTROL_FLOW,
                 HighlightModifier::Mutable => MUTABLE,
                 HighlightModifier::Unsafe => UNSAFE,
+                HighlightModifier::MacroExpansion => MACRO_EXPANSION,
             };
             mods |= modifier;
         }
@@ -436,7 +483,7 @@ impl TryConvWith<&WorldSnapshot> for &TextDocumentPositionParams {
     type Output = FilePosition;
     fn try_conv_with(self, world: &WorldSnapshot) -> Result<FilePosition> {
         let file_id = self.text_document.try_conv_with(world)?;
-        let line_index = world.analysis().file_line_index(file_id)?;
+        let line_index = world.file_line_index(file_id)?;
         let offset = self.position.conv_with(&line_index);
         Ok(FilePosition { file_id, offset })
     }
@@ -446,7 +493,7 @@ impl TryConvWith<&WorldSnapshot> for (&TextDocumentIdentifier, Range) {
     type Output = FileRange;
     fn try_conv_with(self, world: &WorldSnapshot) -> Result<FileRange> {
         let file_id = self.0.try_conv_with(world)?;
-        let line_index = world.analysis().file_line_index(file_id)?;
+        let line_index = world.file_line_index(file_id)?;
         let range = self.1.conv_with(&line_index);
         Ok(FileRange { file_id, range })
     }
@@ -466,39 +513,61 @@ impl<T: TryConvWith<CTX>, CTX: Copy> TryConvWith<CTX> for Vec<T> {
 impl TryConvWith<&WorldSnapshot> for SourceChange {
     type Output = req::SourceChange;
     fn try_conv_with(self, world: &WorldSnapshot) -> Result<req::SourceChange> {
-        let cursor_position = match self.cursor_position {
+        let change =
+            if world.config.client_caps.snippet_text_edit { self } else { self.without_snippets() };
+        let cursor_position = match change.cursor_position {
             None => None,
             Some(pos) => {
-                let line_index = world.analysis().file_line_index(pos.file_id)?;
+                let line_index = world.file_line_index(pos.file_id)?;
                 let edit = self
                     .source_file_edits
                     .iter()
                     .find(|it| it.file_id == pos.file_id)
                     .map(|it| &it.edit);
-                let line_col = match edit {
-                    Some(edit) => translate_offset_with_edit(&*line_index, pos.offset, edit),
-                    None => line_index.line_col(pos.offset),
+                let position = match (edit, line_index.encoding) {
+                    (Some(edit), OffsetEncoding::Utf16) => {
+                        let line_col =
+                            translate_offset_with_edit(&line_index.index, pos.offset, edit);
+                        Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16))
+                    }
+                    // `translate_offset_with_edit` only knows about UTF-16, so
+                    // index the edited text instead.
+                    (Some(edit), OffsetEncoding::Utf8) => {
+                        let text = edit.apply(&world.analysis().file_text(pos.file_id)?);
+                        let index = Arc::new(ra_ide::LineIndex::new(&text));
+                        pos.offset.conv_with(&LineIndex { index, encoding: OffsetEncoding::Utf8 })
+                    }
+                    (None, _) => pos.offset.conv_with(&line_index),
                 };
-                let position =
-                    Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16));
                 Some(TextDocumentPositionParams {
                     text_document: TextDocumentIdentifier::new(pos.file_id.try_conv_with(world)?),
                     position,
                 })
             }
         };
+        // Files are created before they are edited, but moved only after all
+        // the edits were applied, as the edits refer to the old file names.
+        let (create_ops, move_ops): (Vec<_>, Vec<_>) =
+            change.file_system_edits.try_conv_with(world)?.into_iter().partition(|it| match it {
+                ResourceOp::Create(_) => true,
+                _ => false,
+            });
         let mut document_changes: Vec<DocumentChangeOperation> = Vec::new();
-        for resource_op in self.file_system_edits.try_conv_with(world)? {
-            document_changes.push(DocumentChangeOperation::Op(resource_op));
-        }
-        for text_document_edit in self.source_file_edits.try_conv_with(world)? {
+        document_changes.extend(create_ops.into_iter().map(DocumentChangeOperation::Op));
+        for text_document_edit in change.source_file_edits.try_conv_with(world)? {
             document_changes.push(DocumentChangeOperation::Edit(text_document_edit));
         }
+        document_changes.extend(move_ops.into_iter().map(DocumentChangeOperation::Op));
         let workspace_edit = WorkspaceEdit {
             changes: None,
             document_changes: Some(DocumentChanges::Operations(document_changes)),
         };
-        Ok(req::SourceChange { label: self.label, workspace_edit, cursor_position })
+        Ok(req::SourceChange {
+            label: change.label,
+            workspace_edit,
+            cursor_position,
+            is_snippet: change.is_snippet,
+        })
     }
 }
 
@@ -509,7 +578,7 @@ impl TryConvWith<&WorldSnapshot> for SourceFileEdit {
             uri: self.file_id.try_conv_with(world)?,
             version: None,
         };
-        let line_index = world.analysis().file_line_index(self.file_id)?;
+        let line_index = world.file_line_index(self.file_id)?;
         let line_endings = world.file_line_endings(self.file_id);
         let edits =
             self.edit.as_atoms().iter().map_conv_with((&line_index, line_endings)).collect();
@@ -538,7 +607,7 @@ impl TryConvWith<&WorldSnapshot> for FileSystemEdit {
 impl TryConvWith<&WorldSnapshot> for &NavigationTarget {
     type Output = Location;
     fn try_conv_with(self, world: &WorldSnapshot) -> Result<Location> {
-        let line_index = world.analysis().file_line_index(self.file_id())?;
+        let line_index = world.file_line_index(self.file_id())?;
         let range = self.range();
         to_location(self.file_id(), range, &world, &line_index)
     }
@@ -550,8 +619,8 @@ impl TryConvWith<&WorldSnapshot> for (FileId, RangeInfo<NavigationTarget>) {
         let (src_file_id, target) = self;
 
         let target_uri = target.info.file_id().try_conv_with(world)?;
-        let src_line_index = world.analysis().file_line_index(src_file_id)?;
-        let tgt_line_index = world.analysis().file_line_index(target.info.file_id())?;
+        let src_line_index = world.file_line_index(src_file_id)?;
+        let tgt_line_index = world.file_line_index(target.info.file_id())?;
 
         let target_range = target.info.full_range().conv_with(&tgt_line_index);
 
@@ -592,20 +661,18 @@ impl TryConvWith<&WorldSnapshot> for (FileId, RangeInfo<Vec<NavigationTarget>>)
 }
 
 pub fn to_call_hierarchy_item(
-    file_id: FileId,
-    range: TextRange,
     world: &WorldSnapshot,
-    line_index: &LineIndex,
     nav: NavigationTarget,
 ) -> Result<lsp_types::CallHierarchyItem> {
+    let line_index = world.file_line_index(nav.file_id())?;
     Ok(lsp_types::CallHierarchyItem {
         name: nav.name().to_string(),
         kind: nav.kind().conv(),
         tags: None,
         detail: nav.description().map(|it| it.to_string()),
-        uri: file_id.try_conv_with(&world)?,
+        uri: nav.file_id().try_conv_with(&world)?,
         range: nav.range().conv_with(&line_index),
-        selection_range: range.conv_with(&line_index),
+        selection_range: nav.focus_range().unwrap_or_else(|| nav.range()).conv_with(&line_index),
     })
 }
 
@@ -678,6 +745,20 @@ mod tests {
     use super::*;
     use test_utils::extract_ranges;
 
+    #[test]
+    fn conv_position_with_offset_encoding() {
+        let text = "fn main() {\n    let s = \"メ\";\n}";
+        let index = Arc::new(ra_ide::LineIndex::new(text));
+        let utf8 = LineIndex { index: index.clone(), encoding: OffsetEncoding::Utf8 };
+        let utf16 = LineIndex { index, encoding: OffsetEncoding::Utf16 };
+
+        let offset = TextUnit::of_str("fn main() {\n    let s = \"メ\"");
+        assert_eq!(offset.conv_with(&utf8), Position::new(1, 17));
+        assert_eq!(offset.conv_with(&utf16), Position::new(1, 15));
+        assert_eq!(Position::new(1, 17).conv_with(&utf8), offset);
+        assert_eq!(Position::new(1, 15).conv_with(&utf16), offset);
+    }
+
     #[test]
     fn conv_fold_line_folding_only_fixup() {
         let text = r#"<fold>mod a;
@@ -701,7 +782,10 @@ fn main() <fold>{
             Fold { range: ranges[3], kind: FoldKind::Block },
         ];
 
-        let line_index = LineIndex::new(&text);
+        let line_index = LineIndex {
+            index: Arc::new(ra_ide::LineIndex::new(&text)),
+            encoding: OffsetEncoding::Utf16,
+        };
         let ctx = FoldConvCtx { text: &text, line_index: &line_index, line_folding_only: true };
         let converted: Vec<_> = folds.into_iter().map_conv_with(&ctx).collect();
 
@@ -0,0 +1,171 @@
+//! Builds the binary of a runnable with `cargo build --message-format=json`,
+//! so that clients can start it under a debugger directly.
+
+use std::{
+    env,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+
+use crate::{
+    req::{DebugConfig, Runnable},
+    test_runner::read_stderr,
+    Result,
+};
+
+/// The build of a runnable's binary, and how to run it afterwards.
+#[derive(Debug)]
+pub(crate) struct DebugBuild {
+    label: String,
+    /// `cargo build` (or `cargo test --no-run`) arguments.
+    args: Vec<String>,
+    /// Arguments for the binary itself.
+    program_args: Vec<String>,
+    env: FxHashMap<String, String>,
+    cwd: Option<String>,
+}
+
+impl DebugBuild {
+    pub(crate) fn new(runnable: Runnable) -> Result<DebugBuild> {
+        let mut args = runnable.args;
+        match args.first().map(String::as_str) {
+            Some("run") => args[0] = "build".to_string(),
+            // Doctests are compiled by rustdoc, there is no binary to debug.
+            Some("test") if args.iter().any(|it| it == "--doc") => {
+                return Err("doctests can't be debugged".into())
+            }
+            Some("test") | Some("bench") => args.push("--no-run".to_string()),
+            _ => return Err(format!("can't debug `{} {}`", runnable.bin, args.join(" ")).into()),
+        }
+        args.push("--message-format=json".to_string());
+        Ok(DebugBuild {
+            label: runnable.label,
+            args,
+            program_args: runnable.extra_args,
+            env: runnable.env,
+            cwd: runnable.cwd,
+        })
+    }
+
+    /// Adds env vars the binary expects from `cargo run` or `cargo test`.
+    pub(crate) fn extend_env(&mut self, env: impl IntoIterator<Item = (String, String)>) {
+        self.env.extend(env)
+    }
+
+    /// Builds the binary, blocking until the build has finished.
+    pub(crate) fn run(self) -> Result<DebugConfig> {
+        let mut cmd = Command::new(cargo_binary());
+        cmd.args(&self.args);
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        let mut child =
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).stdin(Stdio::null()).spawn()?;
+        let stderr = read_stderr(&mut child);
+
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut executables = Vec::new();
+        for line in stdout.lines() {
+            if let Some(it) = parse_executable(&line?) {
+                executables.push(it);
+            }
+        }
+
+        let exit_status = child.wait()?;
+        let stderr = stderr.join();
+        if !exit_status.success() {
+            return Err(format!(
+                "`cargo {}` failed ({})\n{}",
+                self.args.join(" "),
+                exit_status,
+                stderr
+            )
+            .into());
+        }
+        let program = match executables.as_slice() {
+            [program] => program.to_string_lossy().to_string(),
+            [] => return Err(format!("`cargo {}` built no binary", self.args.join(" ")).into()),
+            _ => {
+                return Err(format!("`cargo {}` built several binaries", self.args.join(" ")).into())
+            }
+        };
+        Ok(DebugConfig {
+            name: self.label,
+            program,
+            args: self.program_args,
+            env: self.env,
+            cwd: self.cwd,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    executable: Option<PathBuf>,
+}
+
+fn parse_executable(line: &str) -> Option<PathBuf> {
+    let msg = serde_json::from_str::<CargoMessage>(line).ok()?;
+    if msg.reason != "compiler-artifact" {
+        return None;
+    }
+    msg.executable
+}
+
+fn cargo_binary() -> String {
+    env::var("CARGO").unwrap_or_else(|_| "cargo".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runnable(args: &[&str]) -> Runnable {
+        Runnable {
+            range: Default::default(),
+            label: "test foo".to_string(),
+            bin: "cargo".to_string(),
+            args: args.iter().map(|it| it.to_string()).collect(),
+            extra_args: vec!["foo".to_string(), "--exact".to_string()],
+            env: FxHashMap::default(),
+            cwd: None,
+        }
+    }
+
+    #[test]
+    fn test_build_args() {
+        let build =
+            DebugBuild::new(runnable(&["run", "--package", "foo", "--bin", "foo"])).unwrap();
+        assert_eq!(
+            build.args,
+            ["build", "--package", "foo", "--bin", "foo", "--message-format=json"]
+        );
+
+        let build = DebugBuild::new(runnable(&["test", "--package", "foo", "--lib"])).unwrap();
+        assert_eq!(
+            build.args,
+            ["test", "--package", "foo", "--lib", "--no-run", "--message-format=json"]
+        );
+        assert_eq!(build.program_args, ["foo", "--exact"]);
+
+        assert!(DebugBuild::new(runnable(&["test", "--package", "foo", "--doc"])).is_err());
+        assert!(DebugBuild::new(runnable(&["check", "--all"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_executable() {
+        let lines = [
+            r#"{"reason":"compiler-artifact","package_id":"foo 0.1.0 (path+file:///foo)","executable":null}"#,
+            r#"{"reason":"compiler-artifact","package_id":"foo 0.1.0 (path+file:///foo)","executable":"/foo/target/debug/deps/foo-1234"}"#,
+            r#"{"reason":"build-finished","success":true}"#,
+        ];
+        let executables: Vec<PathBuf> =
+            lines.iter().filter_map(|it| parse_executable(it)).collect();
+        assert_eq!(executables, vec![PathBuf::from("/foo/target/debug/deps/foo-1234")]);
+    }
+}
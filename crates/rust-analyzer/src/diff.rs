@@ -0,0 +1,131 @@
+//! Line-based diffing, used to turn the output of rustfmt into small edits
+//! instead of a whole-file replacement.
+
+use ra_syntax::{TextRange, TextUnit};
+use ra_text_edit::{TextEdit, TextEditBuilder};
+
+/// Bounds the size of the longest common subsequence table. If the changed
+/// part of the file is larger than that, it is replaced as a whole.
+const MAX_LCS_CELLS: usize = 1 << 22;
+
+/// Returns a `TextEdit` which turns `old` into `new`, consisting of one atom
+/// per changed run of lines.
+pub(crate) fn diff(old: &str, new: &str) -> TextEdit {
+    let old_lines = lines_with_endings(old);
+    let new_lines = lines_with_endings(new);
+
+    let prefix = old_lines.iter().zip(new_lines.iter()).take_while(|(l, r)| l == r).count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(l, r)| l == r)
+        .count();
+    let start = TextUnit::from_usize(old_lines[..prefix].iter().map(|it| it.len()).sum());
+    let old_lines = &old_lines[prefix..old_lines.len() - suffix];
+    let new_lines = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut builder = TextEditBuilder::default();
+    if old_lines.is_empty() && new_lines.is_empty() {
+        return builder.finish();
+    }
+    if old_lines.len() * new_lines.len() > MAX_LCS_CELLS {
+        let len = TextUnit::from_usize(old_lines.iter().map(|it| it.len()).sum());
+        builder.replace(TextRange::offset_len(start, len), new_lines.concat());
+        return builder.finish();
+    }
+
+    // `lcs[i * width + j]` is the length of the longest common subsequence of
+    // `old_lines[i..]` and `new_lines[j..]`.
+    let width = new_lines.len() + 1;
+    let mut lcs = vec![0u32; (old_lines.len() + 1) * width];
+    for i in (0..old_lines.len()).rev() {
+        for j in (0..new_lines.len()).rev() {
+            lcs[i * width + j] = if old_lines[i] == new_lines[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut offset = start;
+    while i < old_lines.len() || j < new_lines.len() {
+        if i < old_lines.len() && j < new_lines.len() && old_lines[i] == new_lines[j] {
+            offset += TextUnit::of_str(old_lines[i]);
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        let hunk_start = offset;
+        let mut inserted = String::new();
+        while i < old_lines.len() || j < new_lines.len() {
+            if i < old_lines.len() && j < new_lines.len() && old_lines[i] == new_lines[j] {
+                break;
+            }
+            let delete = j == new_lines.len()
+                || (i < old_lines.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]);
+            if delete {
+                offset += TextUnit::of_str(old_lines[i]);
+                i += 1;
+            } else {
+                inserted.push_str(new_lines[j]);
+                j += 1;
+            }
+        }
+        builder.replace(TextRange::from_to(hunk_start, offset), inserted);
+    }
+    builder.finish()
+}
+
+fn lines_with_endings(text: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut start = 0;
+    for (idx, _) in text.match_indices('\n') {
+        res.push(&text[start..=idx]);
+        start = idx + 1;
+    }
+    if start < text.len() {
+        res.push(&text[start..]);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(old: &str, new: &str, expected_atoms: usize) {
+        let edit = diff(old, new);
+        assert_eq!(edit.apply(old), new);
+        assert_eq!(edit.as_atoms().len(), expected_atoms, "{:?}", edit);
+    }
+
+    #[test]
+    fn diff_identical_texts_is_empty() {
+        check("fn main() {}\n", "fn main() {}\n", 0);
+    }
+
+    #[test]
+    fn diff_touches_only_changed_lines() {
+        check(
+            "fn a() {}\nfn b( ) {}\nfn c() {}\nfn d( ) {}\nfn e() {}\n",
+            "fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}\nfn e() {}\n",
+            2,
+        );
+        let edit = diff("a\nb \nc\n", "a\nb\nc\n");
+        let atom = &edit.as_atoms()[0];
+        assert_eq!(atom.delete, TextRange::from_to(2.into(), 5.into()));
+        assert_eq!(atom.insert, "b\n");
+    }
+
+    #[test]
+    fn diff_handles_insertions_deletions_and_missing_final_newline() {
+        check("use a;\nuse b;\n", "use a;\n\nuse b;\n", 1);
+        check("fn f() {\n\n\n}\n", "fn f() {}\n", 1);
+        check("fn f() {}", "fn f() {}\n", 1);
+        check("", "fn f() {}\n", 1);
+    }
+}
@@ -21,13 +21,16 @@ mod vfs_glob;
 mod caps;
 mod cargo_target_spec;
 mod conv;
+mod debug_config;
 mod main_loop;
 mod markdown;
 pub mod req;
 pub mod config;
 mod world;
 mod diagnostics;
+mod diff;
 mod semantic_tokens;
+mod test_runner;
 
 use serde::de::DeserializeOwned;
 
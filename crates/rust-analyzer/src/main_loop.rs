@@ -9,23 +9,23 @@ use std::{
     env,
     error::Error,
     fmt, panic,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
+    thread,
     time::{Duration, Instant},
 };
 
-use crossbeam_channel::{never, select, unbounded, RecvError, Sender};
+use crossbeam_channel::{at, never, select, unbounded, RecvError, Sender};
 use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
 use lsp_types::{
     NumberOrString, WorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressCreateParams,
     WorkDoneProgressEnd, WorkDoneProgressReport,
 };
 use ra_flycheck::{url_from_path_with_drive_lowercasing, CheckTask};
-use ra_ide::{Canceled, FileId, LibraryData, SourceRootId};
+use ra_ide::{Canceled, FileId, PrimeCachesProgress};
 use ra_prof::profile;
 use ra_project_model::{PackageRoot, ProjectWorkspace};
 use ra_vfs::{VfsFile, VfsTask, Watch};
-use relative_path::RelativePathBuf;
 use rustc_hash::FxHashSet;
 use serde::{de::DeserializeOwned, Serialize};
 use threadpool::ThreadPool;
@@ -88,74 +88,35 @@ pub fn main_loop(ws_roots: Vec<PathBuf>, config: Config, connection: Connection)
 
     let mut loop_state = LoopState::default();
     let mut world_state = {
-        // FIXME: support dynamic workspace loading.
-        let workspaces = {
-            let mut loaded_workspaces = Vec::new();
-            for ws_root in &ws_roots {
-                let workspace = ra_project_model::ProjectWorkspace::discover_with_sysroot(
-                    ws_root.as_path(),
-                    config.with_sysroot,
-                    &config.cargo,
-                );
-                match workspace {
-                    Ok(workspace) => loaded_workspaces.push(workspace),
-                    Err(e) => {
-                        log::error!("loading workspace failed: {:?}", e);
-
-                        if let Some(ra_project_model::CargoTomlNotFoundError { .. }) =
-                            e.downcast_ref()
-                        {
-                            if !config.notifications.cargo_toml_not_found {
-                                continue;
-                            }
-                        }
+        let (workspaces, loading_errors) = load_workspaces(&ws_roots, &config, &connection.sender);
 
-                        show_message(
-                            req::MessageType::Error,
-                            format!("rust-analyzer failed to load workspace: {:?}", e),
-                            &connection.sender,
-                        );
-                    }
-                }
-            }
-            loaded_workspaces
+        let globs = |globs: &[String]| {
+            globs
+                .iter()
+                .map(|glob| crate::vfs_glob::Glob::new(glob))
+                .collect::<std::result::Result<Vec<_>, _>>()
         };
-
-        let globs = config
-            .files
-            .exclude
-            .iter()
-            .map(|glob| crate::vfs_glob::Glob::new(glob))
-            .collect::<std::result::Result<Vec<_>, _>>()?;
-
-        if let FilesWatcher::Client = config.files.watcher {
-            let registration_options = req::DidChangeWatchedFilesRegistrationOptions {
-                watchers: workspaces
-                    .iter()
-                    .flat_map(ProjectWorkspace::to_roots)
-                    .filter(PackageRoot::is_member)
-                    .map(|root| format!("{}/**/*.rs", root.path().display()))
-                    .map(|glob_pattern| req::FileSystemWatcher { glob_pattern, kind: None })
-                    .collect(),
-            };
-            let registration = req::Registration {
-                id: "file-watcher".to_string(),
-                method: "workspace/didChangeWatchedFiles".to_string(),
-                register_options: Some(serde_json::to_value(registration_options).unwrap()),
-            };
-            let params = req::RegistrationParams { registrations: vec![registration] };
-            let request =
-                request_new::<req::RegisterCapability>(loop_state.next_request_id(), params);
-            connection.sender.send(request.into()).unwrap();
-        }
+        let include_globs = globs(&config.files.include)?;
+        let exclude_globs = globs(&config.files.exclude)?;
+
+        let watch_rust_files = matches!(config.files.watcher, FilesWatcher::Client);
+        register_file_watchers(
+            &ws_roots,
+            &workspaces,
+            watch_rust_files,
+            &mut loop_state,
+            &connection.sender,
+        );
 
         WorldState::new(
             ws_roots,
             workspaces,
             config.lru_capacity,
-            &globs,
+            &include_globs,
+            &exclude_globs,
             Watch(matches!(config.files.watcher, FilesWatcher::Notify)),
             config,
+            loading_errors,
         )
     };
 
@@ -163,15 +124,17 @@ pub fn main_loop(ws_roots: Vec<PathBuf>, config: Config, connection: Connection)
     loop_state.roots_scanned = 0;
 
     let pool = ThreadPool::default();
+    // Completion, hover and friends get a pool of their own, so that they
+    // don't wait behind diagnostics and library indexing.
+    let latency_pool = ThreadPool::with_name("latency-sensitive".to_string(), 2);
     let (task_sender, task_receiver) = unbounded::<Task>();
-    let (libdata_sender, libdata_receiver) = unbounded::<LibraryData>();
 
     log::info!("server initialized, serving requests");
     {
         let task_sender = task_sender;
-        let libdata_sender = libdata_sender;
         loop {
             log::trace!("selecting");
+            let reload_timer = loop_state.workspace_reload_deadline.map_or_else(never, at);
             let event = select! {
                 recv(&connection.receiver) -> msg => match msg {
                     Ok(msg) => Event::Msg(msg),
@@ -182,7 +145,7 @@ pub fn main_loop(ws_roots: Vec<PathBuf>, config: Config, connection: Connection)
                     Ok(task) => Event::Vfs(task),
                     Err(RecvError) => return Err("vfs died".into()),
                 },
-                recv(libdata_receiver) -> data => Event::Lib(data.unwrap()),
+                recv(reload_timer) -> _ => Event::ReloadWorkspaces,
                 recv(world_state.flycheck.as_ref().map_or(&never(), |it| &it.task_recv)) -> task => match task {
                     Ok(task) => Event::CheckWatcher(task),
                     Err(RecvError) => return Err("check watcher died".into()),
@@ -195,8 +158,8 @@ pub fn main_loop(ws_roots: Vec<PathBuf>, config: Config, connection: Connection)
             }
             loop_turn(
                 &pool,
+                &latency_pool,
                 &task_sender,
-                &libdata_sender,
                 &connection,
                 &mut world_state,
                 &mut loop_state,
@@ -209,10 +172,10 @@ pub fn main_loop(ws_roots: Vec<PathBuf>, config: Config, connection: Connection)
     task_receiver.into_iter().for_each(|task| {
         on_task(task, &connection.sender, &mut loop_state.pending_requests, &mut world_state)
     });
-    libdata_receiver.into_iter().for_each(drop);
     log::info!("...tasks have finished");
     log::info!("joining threadpool...");
     drop(pool);
+    drop(latency_pool);
     log::info!("...threadpool has finished");
 
     let vfs = Arc::try_unwrap(world_state.vfs).expect("all snapshots should be dead");
@@ -221,19 +184,66 @@ pub fn main_loop(ws_roots: Vec<PathBuf>, config: Config, connection: Connection)
     Ok(())
 }
 
+fn load_workspaces(
+    ws_roots: &[PathBuf],
+    config: &Config,
+    sender: &Sender<Message>,
+) -> (Vec<ProjectWorkspace>, Vec<String>) {
+    let mut loaded_workspaces = Vec::new();
+    let mut loading_errors = Vec::new();
+    for ws_root in ws_roots {
+        let workspace = ra_project_model::ProjectWorkspace::discover_with_sysroot(
+            ws_root.as_path(),
+            config.with_sysroot,
+            &config.cargo,
+        );
+        match workspace {
+            Ok(workspace) => loaded_workspaces.push(workspace),
+            Err(e) => {
+                log::error!("loading workspace failed: {:?}", e);
+                loading_errors.push(format!("{}: {}", ws_root.display(), e));
+
+                if let Some(ra_project_model::CargoTomlNotFoundError { .. }) = e.downcast_ref() {
+                    if !config.notifications.cargo_toml_not_found {
+                        continue;
+                    }
+                }
+
+                show_message(
+                    req::MessageType::Error,
+                    format!("rust-analyzer failed to load workspace: {:?}", e),
+                    sender,
+                );
+            }
+        }
+    }
+    (loaded_workspaces, loading_errors)
+}
+
 #[derive(Debug)]
 enum Task {
     Respond(Response),
+    /// The request was canceled by a change, and should be dispatched again
+    /// against the new state unless the client canceled it as well.
+    Retry(Request),
     Notify(Notification),
     Diagnostic(DiagnosticTask),
+    /// The workspaces rediscovered by the `generation`th reload.
+    Workspaces {
+        generation: u64,
+        workspaces: Vec<ProjectWorkspace>,
+        loading_errors: Vec<String>,
+    },
 }
 
 enum Event {
     Msg(Message),
     Task(Task),
     Vfs(VfsTask),
-    Lib(LibraryData),
     CheckWatcher(CheckTask),
+    /// The manifests were changed and haven't been touched since
+    /// `WORKSPACE_RELOAD_DELAY`.
+    ReloadWorkspaces,
 }
 
 impl fmt::Debug for Event {
@@ -255,6 +265,9 @@ impl fmt::Debug for Event {
                     return debug_verbose_not(not, f);
                 }
             }
+            Event::Task(Task::Workspaces { generation, .. }) => {
+                return f.debug_struct("Workspaces").field("generation", generation).finish();
+            }
             Event::Task(Task::Respond(resp)) => {
                 return f
                     .debug_struct("Response")
@@ -268,8 +281,8 @@ impl fmt::Debug for Event {
             Event::Msg(it) => fmt::Debug::fmt(it, f),
             Event::Task(it) => fmt::Debug::fmt(it, f),
             Event::Vfs(it) => fmt::Debug::fmt(it, f),
-            Event::Lib(it) => fmt::Debug::fmt(it, f),
             Event::CheckWatcher(it) => fmt::Debug::fmt(it, f),
+            Event::ReloadWorkspaces => f.write_str("ReloadWorkspaces"),
         }
     }
 }
@@ -280,15 +293,20 @@ struct LoopState {
     pending_responses: FxHashSet<RequestId>,
     pending_requests: PendingRequests,
     subscriptions: Subscriptions,
-    // We try not to index more than MAX_IN_FLIGHT_LIBS libraries at the same
-    // time to always have a thread ready to react to input.
-    in_flight_libraries: usize,
-    pending_libraries: Vec<(SourceRootId, Vec<(FileId, RelativePathBuf, Arc<String>)>)>,
     workspace_loaded: bool,
     roots_progress_reported: Option<usize>,
     roots_scanned: usize,
     roots_total: usize,
     configuration_request_id: Option<RequestId>,
+    /// The number of `cargo check` runs which reported progress, every run
+    /// gets a new progress token.
+    flycheck_progress_id: u64,
+    /// When to reload the workspaces after a change to a `Cargo.toml` or
+    /// similar, postponed by every other change.
+    workspace_reload_deadline: Option<Instant>,
+    /// The number of workspace reloads started, so that the results of all
+    /// but the latest one are dropped.
+    workspace_reload_generation: u64,
 }
 
 impl LoopState {
@@ -303,8 +321,8 @@ impl LoopState {
 
 fn loop_turn(
     pool: &ThreadPool,
+    latency_pool: &ThreadPool,
     task_sender: &Sender<Task>,
-    libdata_sender: &Sender<LibraryData>,
     connection: &Connection,
     world_state: &mut WorldState,
     loop_state: &mut LoopState,
@@ -321,6 +339,32 @@ fn loop_turn(
     }
 
     match event {
+        Event::Task(Task::Retry(req)) => {
+            if let Some(request_received) = loop_state.pending_requests.retry(&req.id) {
+                log::info!("retrying req#{}", req.id);
+                on_request(
+                    world_state,
+                    &mut loop_state.pending_requests,
+                    pool,
+                    latency_pool,
+                    task_sender,
+                    &connection.sender,
+                    request_received,
+                    req,
+                )?;
+            }
+        }
+        Event::Task(Task::Workspaces { generation, workspaces, loading_errors }) => {
+            if generation == loop_state.workspace_reload_generation {
+                apply_workspaces(
+                    world_state,
+                    loop_state,
+                    &connection.sender,
+                    workspaces,
+                    loading_errors,
+                );
+            }
+        }
         Event::Task(task) => {
             on_task(task, &connection.sender, &mut loop_state.pending_requests, world_state);
             world_state.maybe_collect_garbage();
@@ -328,25 +372,33 @@ fn loop_turn(
         Event::Vfs(task) => {
             world_state.vfs.write().handle_task(task);
         }
-        Event::Lib(lib) => {
-            world_state.add_lib(lib);
-            world_state.maybe_collect_garbage();
-            loop_state.in_flight_libraries -= 1;
-            loop_state.roots_scanned += 1;
+        Event::CheckWatcher(task) => {
+            on_check_task(task, world_state, loop_state, task_sender, &connection.sender)?
+        }
+        Event::ReloadWorkspaces => {
+            loop_state.workspace_reload_deadline = None;
+            reload_workspaces(world_state, loop_state, pool, task_sender, &connection.sender);
         }
-        Event::CheckWatcher(task) => on_check_task(task, world_state, task_sender)?,
         Event::Msg(msg) => match msg {
             Message::Request(req) => on_request(
                 world_state,
                 &mut loop_state.pending_requests,
                 pool,
+                latency_pool,
                 task_sender,
                 &connection.sender,
                 loop_start,
                 req,
             )?,
             Message::Notification(not) => {
-                on_notification(&connection.sender, world_state, loop_state, not)?;
+                on_notification(
+                    pool,
+                    task_sender,
+                    &connection.sender,
+                    world_state,
+                    loop_state,
+                    not,
+                )?;
             }
             Message::Response(resp) => {
                 let removed = loop_state.pending_responses.remove(&resp.id);
@@ -367,7 +419,25 @@ fn loop_turn(
                             if let Some(new_config) = configs.get(0) {
                                 let mut config = world_state.config.clone();
                                 config.update(&new_config);
-                                world_state.update_configuration(config);
+                                let diagnostics_changed =
+                                    config.diagnostics != world_state.config.diagnostics;
+                                if world_state.update_configuration(config) {
+                                    reload_workspaces(
+                                        world_state,
+                                        loop_state,
+                                        pool,
+                                        task_sender,
+                                        &connection.sender,
+                                    );
+                                }
+                                if diagnostics_changed {
+                                    update_file_notifications_on_threadpool(
+                                        pool,
+                                        world_state.snapshot(),
+                                        task_sender.clone(),
+                                        loop_state.subscriptions.subscriptions(),
+                                    );
+                                }
                             }
                         }
                         (None, None) => {
@@ -379,42 +449,42 @@ fn loop_turn(
         },
     };
 
-    let mut state_changed = false;
-    if let Some(changes) = world_state.process_changes(&mut loop_state.roots_scanned) {
-        state_changed = true;
-        loop_state.pending_libraries.extend(changes);
-    }
-
-    let max_in_flight_libs = pool.max_count().saturating_sub(2).max(1);
-    while loop_state.in_flight_libraries < max_in_flight_libs
-        && !loop_state.pending_libraries.is_empty()
-    {
-        let (root, files) = loop_state.pending_libraries.pop().unwrap();
-        loop_state.in_flight_libraries += 1;
-        let sender = libdata_sender.clone();
-        pool.execute(move || {
-            log::info!("indexing {:?} ... ", root);
-            let data = LibraryData::prepare(root, files);
-            sender.send(data).unwrap();
-        });
-    }
+    let state_changed = world_state.process_changes(&mut loop_state.roots_scanned);
 
-    let show_progress =
-        !loop_state.workspace_loaded && world_state.config.notifications.workspace_loaded;
+    let show_progress = !loop_state.workspace_loaded
+        && world_state.config.notifications.workspace_loaded
+        && world_state.config.client_caps.work_done_progress;
 
-    if !loop_state.workspace_loaded
-        && loop_state.roots_scanned == loop_state.roots_total
-        && loop_state.pending_libraries.is_empty()
-        && loop_state.in_flight_libraries == 0
-    {
+    if !loop_state.workspace_loaded && loop_state.roots_scanned == loop_state.roots_total {
         loop_state.workspace_loaded = true;
         if let Some(flycheck) = &world_state.flycheck {
             flycheck.update();
         }
+        let report_progress = world_state.config.client_caps.work_done_progress;
+        if report_progress {
+            let work_done_progress_create = request_new::<req::WorkDoneProgressCreate>(
+                loop_state.next_request_id(),
+                WorkDoneProgressCreateParams { token: prime_caches_progress_token() },
+            );
+            connection.sender.send(work_done_progress_create.into()).unwrap();
+        }
         pool.execute({
-            let subs = loop_state.subscriptions.subscriptions();
             let snap = world_state.snapshot();
-            move || snap.analysis().prime_caches(subs).unwrap_or_else(|_: Canceled| ())
+            let task_sender = task_sender.clone();
+            move || {
+                let sender = &task_sender;
+                let res = snap.analysis().prime_caches(|progress| {
+                    if report_progress {
+                        send_prime_caches_progress(sender, progress)
+                    }
+                });
+                if res.is_err() && report_progress {
+                    let end = WorkDoneProgress::End(WorkDoneProgressEnd {
+                        message: Some("canceled".to_string()),
+                    });
+                    send_prime_caches_progress_notif(sender, end);
+                }
+            }
         });
     }
 
@@ -422,6 +492,15 @@ fn loop_turn(
         send_startup_progress(&connection.sender, loop_state);
     }
 
+    let status = server_status(world_state, loop_state);
+    if status != *world_state.status {
+        if world_state.config.client_caps.status_notification {
+            let not = notification_new::<req::ServerStatusNotification>(status.clone());
+            connection.sender.send(not.into()).unwrap();
+        }
+        world_state.status = Arc::new(status);
+    }
+
     if state_changed {
         update_file_notifications_on_threadpool(
             pool,
@@ -446,6 +525,117 @@ fn loop_turn(
     Ok(())
 }
 
+fn server_status(world_state: &WorldState, loop_state: &LoopState) -> req::ServerStatus {
+    req::ServerStatus {
+        workspace_loaded: loop_state.workspace_loaded,
+        roots_scanned: loop_state.roots_scanned,
+        roots_total: loop_state.roots_total,
+        packages: world_state.workspaces.iter().map(ProjectWorkspace::n_packages).sum(),
+        proc_macro_srv: world_state.config.proc_macro_srv.clone(),
+        load_out_dirs_from_check: world_state.config.cargo.load_out_dirs_from_check,
+        loading_errors: world_state.loading_errors.clone(),
+        analysis: None,
+    }
+}
+
+/// Rediscovers the workspaces with the current config and workspace folders,
+/// e.g. after the cargo features were changed. `cargo metadata` can take a
+/// while, so this runs on the pool.
+fn reload_workspaces(
+    world_state: &WorldState,
+    loop_state: &mut LoopState,
+    pool: &ThreadPool,
+    task_sender: &Sender<Task>,
+    sender: &Sender<Message>,
+) {
+    log::info!("reloading workspaces");
+    loop_state.workspace_reload_generation += 1;
+    let generation = loop_state.workspace_reload_generation;
+    let roots = world_state.roots.clone();
+    let config = world_state.config.clone();
+    let task_sender = task_sender.clone();
+    let sender = sender.clone();
+    pool.execute(move || {
+        let (workspaces, loading_errors) = load_workspaces(&roots, &config, &sender);
+        task_sender.send(Task::Workspaces { generation, workspaces, loading_errors }).unwrap();
+    });
+}
+
+/// Switches to the workspaces loaded by `reload_workspaces`.
+fn apply_workspaces(
+    world_state: &mut WorldState,
+    loop_state: &mut LoopState,
+    sender: &Sender<Message>,
+    workspaces: Vec<ProjectWorkspace>,
+    loading_errors: Vec<String>,
+) {
+    let params = req::UnregistrationParams {
+        unregisterations: vec![req::Unregistration {
+            id: FILE_WATCHER_REGISTRATION_ID.to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+        }],
+    };
+    let request = request_new::<req::UnregisterCapability>(loop_state.next_request_id(), params);
+    sender.send(request.into()).unwrap();
+    let watch_rust_files = matches!(world_state.config.files.watcher, FilesWatcher::Client);
+    register_file_watchers(&world_state.roots, &workspaces, watch_rust_files, loop_state, sender);
+    world_state.reload_workspaces(workspaces, loading_errors);
+    loop_state.roots_total = world_state.vfs.read().n_roots();
+}
+
+const FILE_WATCHER_REGISTRATION_ID: &str = "file-watcher";
+
+/// How long to wait for the manifests to settle before reloading the
+/// workspaces, as `cargo` updates `Cargo.lock` after `Cargo.toml` is saved.
+const WORKSPACE_RELOAD_DELAY: Duration = Duration::from_millis(500);
+
+const MANIFEST_FILE_NAMES: &[&str] = &["Cargo.toml", "Cargo.lock", "rust-project.json"];
+
+fn is_manifest(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|it| it.to_str())
+        .map_or(false, |it| MANIFEST_FILE_NAMES.contains(&it))
+}
+
+/// Asks the client to watch the manifests of the workspaces and, if
+/// `watch_rust_files` is set, the `.rs` files of the workspace members.
+fn register_file_watchers(
+    ws_roots: &[PathBuf],
+    workspaces: &[ProjectWorkspace],
+    watch_rust_files: bool,
+    loop_state: &mut LoopState,
+    sender: &Sender<Message>,
+) {
+    let member_roots = workspaces
+        .iter()
+        .flat_map(ProjectWorkspace::to_roots)
+        .filter(PackageRoot::is_member)
+        .map(|root| root.path().to_path_buf())
+        .collect::<Vec<_>>();
+    let manifest_globs = ws_roots
+        .iter()
+        .chain(member_roots.iter())
+        .map(|root| format!("{}/**/{{{}}}", root.display(), MANIFEST_FILE_NAMES.join(",")));
+    let rust_file_globs = member_roots
+        .iter()
+        .filter(|_| watch_rust_files)
+        .map(|root| format!("{}/**/*.rs", root.display()));
+    let registration_options = req::DidChangeWatchedFilesRegistrationOptions {
+        watchers: manifest_globs
+            .chain(rust_file_globs)
+            .map(|glob_pattern| req::FileSystemWatcher { glob_pattern, kind: None })
+            .collect(),
+    };
+    let registration = req::Registration {
+        id: FILE_WATCHER_REGISTRATION_ID.to_string(),
+        method: "workspace/didChangeWatchedFiles".to_string(),
+        register_options: Some(serde_json::to_value(registration_options).unwrap()),
+    };
+    let params = req::RegistrationParams { registrations: vec![registration] };
+    let request = request_new::<req::RegisterCapability>(loop_state.next_request_id(), params);
+    sender.send(request.into()).unwrap();
+}
+
 fn on_task(
     task: Task,
     msg_sender: &Sender<Message>,
@@ -464,6 +654,9 @@ fn on_task(
             msg_sender.send(n.into()).unwrap();
         }
         Task::Diagnostic(task) => on_diagnostic_task(task, msg_sender, state),
+        // Retries and workspaces are handled by the `loop_turn`, and dropped
+        // on shutdown.
+        Task::Retry(_) | Task::Workspaces { .. } => (),
     }
 }
 
@@ -471,6 +664,7 @@ fn on_request(
     world: &mut WorldState,
     pending_requests: &mut PendingRequests,
     pool: &ThreadPool,
+    latency_pool: &ThreadPool,
     task_sender: &Sender<Task>,
     msg_sender: &Sender<Message>,
     request_received: Instant,
@@ -479,6 +673,7 @@ fn on_request(
     let mut pool_dispatcher = PoolDispatcher {
         req: Some(req),
         pool,
+        latency_pool,
         world,
         task_sender,
         msg_sender,
@@ -487,16 +682,27 @@ fn on_request(
     };
     pool_dispatcher
         .on_sync::<req::CollectGarbage>(|s, ()| Ok(s.collect_garbage()))?
+        .on_sync::<req::MemoryUsage>(|s, ()| Ok(s.memory_usage()))?
         .on_sync::<req::JoinLines>(|s, p| handlers::handle_join_lines(s.snapshot(), p))?
         .on_sync::<req::OnEnter>(|s, p| handlers::handle_on_enter(s.snapshot(), p))?
         .on_sync::<req::SelectionRangeRequest>(|s, p| {
             handlers::handle_selection_range(s.snapshot(), p)
         })?
+        .on_sync::<req::ExtendSelection>(|s, p| handlers::handle_extend_selection(s.snapshot(), p))?
+        .on_sync::<req::ShrinkSelection>(|s, p| handlers::handle_shrink_selection(s.snapshot(), p))?
         .on_sync::<req::FindMatchingBrace>(|s, p| {
             handlers::handle_find_matching_brace(s.snapshot(), p)
         })?
+        .on_latency_sensitive::<req::Completion>(handlers::handle_completion)?
+        .on_latency_sensitive::<req::SignatureHelpRequest>(handlers::handle_signature_help)?
+        .on_latency_sensitive::<req::HoverRequest>(handlers::handle_hover)?
+        .on_latency_sensitive::<req::DocumentHighlightRequest>(handlers::handle_document_highlight)?
         .on::<req::AnalyzerStatus>(handlers::handle_analyzer_status)?
+        .on::<req::ServerStatusRequest>(handlers::handle_server_status)?
+        .on::<req::StartProfiling>(handlers::handle_start_profiling)?
+        .on::<req::StopProfiling>(handlers::handle_stop_profiling)?
         .on::<req::SyntaxTree>(handlers::handle_syntax_tree)?
+        .on::<req::ViewHir>(handlers::handle_view_hir)?
         .on::<req::ExpandMacro>(handlers::handle_expand_macro)?
         .on::<req::OnTypeFormatting>(handlers::handle_on_type_formatting)?
         .on::<req::DocumentSymbolRequest>(handlers::handle_document_symbol)?
@@ -505,31 +711,42 @@ fn on_request(
         .on::<req::GotoImplementation>(handlers::handle_goto_implementation)?
         .on::<req::GotoTypeDefinition>(handlers::handle_goto_type_definition)?
         .on::<req::ParentModule>(handlers::handle_parent_module)?
+        .on::<req::SuperMethod>(handlers::handle_super_method)?
+        .on::<req::OpenCargoToml>(handlers::handle_open_cargo_toml)?
         .on::<req::Runnables>(handlers::handle_runnables)?
-        .on::<req::Completion>(handlers::handle_completion)?
+        .on::<req::TestTree>(handlers::handle_test_tree)?
+        .on_process::<req::RunTests, _>(handlers::handle_run_tests, handlers::run_tests)?
+        .on_process::<req::DebugRunnable, _>(
+            handlers::handle_debug_runnable,
+            handlers::build_debug_runnable,
+        )?
         .on::<req::CodeActionRequest>(handlers::handle_code_action)?
+        .on::<req::CodeActionResolveRequest>(handlers::handle_code_action_resolve)?
         .on::<req::CodeLensRequest>(handlers::handle_code_lens)?
         .on::<req::CodeLensResolve>(handlers::handle_code_lens_resolve)?
         .on::<req::FoldingRangeRequest>(handlers::handle_folding_range)?
-        .on::<req::SignatureHelpRequest>(handlers::handle_signature_help)?
-        .on::<req::HoverRequest>(handlers::handle_hover)?
         .on::<req::PrepareRenameRequest>(handlers::handle_prepare_rename)?
         .on::<req::Rename>(handlers::handle_rename)?
         .on::<req::References>(handlers::handle_references)?
         .on::<req::Formatting>(handlers::handle_formatting)?
-        .on::<req::DocumentHighlightRequest>(handlers::handle_document_highlight)?
+        .on::<req::RangeFormatting>(handlers::handle_range_formatting)?
         .on::<req::InlayHints>(handlers::handle_inlay_hints)?
+        .on::<req::InjectedLanguages>(handlers::handle_injected_languages)?
         .on::<req::CallHierarchyPrepare>(handlers::handle_call_hierarchy_prepare)?
         .on::<req::CallHierarchyIncomingCalls>(handlers::handle_call_hierarchy_incoming)?
         .on::<req::CallHierarchyOutgoingCalls>(handlers::handle_call_hierarchy_outgoing)?
         .on::<req::SemanticTokensRequest>(handlers::handle_semantic_tokens)?
+        .on::<req::SemanticTokensEditsRequest>(handlers::handle_semantic_tokens_edits)?
         .on::<req::SemanticTokensRangeRequest>(handlers::handle_semantic_tokens_range)?
+        .on::<req::SafeDelete>(handlers::handle_safe_delete)?
         .on::<req::Ssr>(handlers::handle_ssr)?
         .finish();
     Ok(())
 }
 
 fn on_notification(
+    pool: &ThreadPool,
+    task_sender: &Sender<Task>,
     msg_sender: &Sender<Message>,
     state: &mut WorldState,
     loop_state: &mut LoopState,
@@ -593,6 +810,7 @@ fn on_notification(
             if let Some(file_id) = state.vfs.write().remove_file_overlay(path.as_path()) {
                 loop_state.subscriptions.remove_sub(FileId(file_id.0));
             }
+            state.semantic_tokens_cache.lock().remove(&uri);
             let params =
                 req::PublishDiagnosticsParams { uri, diagnostics: Vec::new(), version: None };
             let not = notification_new::<req::PublishDiagnostics>(params);
@@ -622,13 +840,31 @@ fn on_notification(
         }
         Err(not) => not,
     };
+    let not = match notification_cast::<req::DidChangeWorkspaceFolders>(not) {
+        Ok(params) => {
+            let to_paths = |folders: Vec<req::WorkspaceFolder>| {
+                folders.into_iter().filter_map(|it| it.uri.to_file_path().ok()).collect()
+            };
+            let added = to_paths(params.event.added);
+            let removed = to_paths(params.event.removed);
+            state.change_workspace_folders(added, removed);
+            reload_workspaces(state, loop_state, pool, task_sender, msg_sender);
+            return Ok(());
+        }
+        Err(not) => not,
+    };
     let not = match notification_cast::<req::DidChangeWatchedFiles>(not) {
         Ok(params) => {
             let mut vfs = state.vfs.write();
             for change in params.changes {
                 let uri = change.uri;
                 let path = uri.to_file_path().map_err(|()| format!("invalid uri: {}", uri))?;
-                vfs.notify_changed(path)
+                if is_manifest(&path) {
+                    loop_state.workspace_reload_deadline =
+                        Some(Instant::now() + WORKSPACE_RELOAD_DELAY);
+                } else {
+                    vfs.notify_changed(path)
+                }
             }
             return Ok(());
         }
@@ -644,7 +880,9 @@ fn on_notification(
 fn on_check_task(
     task: CheckTask,
     world_state: &mut WorldState,
+    loop_state: &mut LoopState,
     task_sender: &Sender<Task>,
+    msg_sender: &Sender<Message>,
 ) -> Result<()> {
     match task {
         CheckTask::ClearDiagnostics => {
@@ -666,10 +904,25 @@ fn on_check_task(
         }
 
         CheckTask::Status(progress) => {
-            let params = req::ProgressParams {
-                token: req::ProgressToken::String("rustAnalyzer/cargoWatcher".to_string()),
-                value: req::ProgressParamsValue::WorkDone(progress),
-            };
+            if !world_state.config.client_caps.work_done_progress {
+                return Ok(());
+            }
+            if let WorkDoneProgress::Begin(_) = &progress {
+                loop_state.flycheck_progress_id += 1;
+            }
+            let token = req::ProgressToken::String(format!(
+                "rustAnalyzer/cargoWatcher/{}",
+                loop_state.flycheck_progress_id
+            ));
+            if let WorkDoneProgress::Begin(_) = &progress {
+                let work_done_progress_create = request_new::<req::WorkDoneProgressCreate>(
+                    loop_state.next_request_id(),
+                    WorkDoneProgressCreateParams { token: token.clone() },
+                );
+                msg_sender.send(work_done_progress_create.into()).unwrap();
+            }
+            let params =
+                req::ProgressParams { token, value: req::ProgressParamsValue::WorkDone(progress) };
             let not = notification_new::<req::Progress>(params);
             task_sender.send(Task::Notify(not)).unwrap();
         }
@@ -749,9 +1002,44 @@ fn send_startup_progress(sender: &Sender<Message>, loop_state: &mut LoopState) {
     }
 }
 
+fn prime_caches_progress_token() -> req::ProgressToken {
+    req::ProgressToken::String("rustAnalyzer/primeCaches".to_string())
+}
+
+fn send_prime_caches_progress(sender: &Sender<Task>, progress: PrimeCachesProgress) {
+    let work_done_progress = match progress {
+        PrimeCachesProgress::Started => WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: "indexing".to_string(),
+            cancellable: None,
+            message: None,
+            percentage: Some(0.0),
+        }),
+        PrimeCachesProgress::CrateDone { name, n_done, n_total } => {
+            WorkDoneProgress::Report(WorkDoneProgressReport {
+                cancellable: None,
+                message: Some(format!("{}/{} ({})", n_done, n_total, name)),
+                percentage: Some(100.0 * n_done as f64 / n_total as f64),
+            })
+        }
+        PrimeCachesProgress::Finished => {
+            WorkDoneProgress::End(WorkDoneProgressEnd { message: None })
+        }
+    };
+    send_prime_caches_progress_notif(sender, work_done_progress);
+}
+
+fn send_prime_caches_progress_notif(sender: &Sender<Task>, work_done_progress: WorkDoneProgress) {
+    let notif = notification_new::<req::Progress>(req::ProgressParams {
+        token: prime_caches_progress_token(),
+        value: req::ProgressParamsValue::WorkDone(work_done_progress),
+    });
+    sender.send(Task::Notify(notif)).unwrap();
+}
+
 struct PoolDispatcher<'a> {
     req: Option<Request>,
     pool: &'a ThreadPool,
+    latency_pool: &'a ThreadPool,
     world: &'a mut WorldState,
     pending_requests: &'a mut PendingRequests,
     msg_sender: &'a Sender<Message>,
@@ -778,6 +1066,7 @@ impl<'a> PoolDispatcher<'a> {
         };
         let world = panic::AssertUnwindSafe(&mut *self.world);
         let task = panic::catch_unwind(move || {
+            let _p = profile("request").detail(|| R::METHOD.to_string());
             let result = f(world.0, params);
             result_to_task::<R>(id, result)
         })
@@ -793,6 +1082,40 @@ impl<'a> PoolDispatcher<'a> {
         R::Params: DeserializeOwned + Send + 'static,
         R::Result: Serialize + 'static,
     {
+        let pool = self.pool;
+        self.on_pool::<R>(pool, f)
+    }
+
+    /// Dispatches the request onto the thread pool reserved for the requests
+    /// which the user is actively waiting for, like completion.
+    fn on_latency_sensitive<R>(
+        &mut self,
+        f: fn(WorldSnapshot, R::Params) -> Result<R::Result>,
+    ) -> Result<&mut Self>
+    where
+        R: req::Request + 'static,
+        R::Params: DeserializeOwned + Send + 'static,
+        R::Result: Serialize + 'static,
+    {
+        let pool = self.latency_pool;
+        self.on_pool::<R>(pool, f)
+    }
+
+    fn on_pool<R>(
+        &mut self,
+        pool: &ThreadPool,
+        f: fn(WorldSnapshot, R::Params) -> Result<R::Result>,
+    ) -> Result<&mut Self>
+    where
+        R: req::Request + 'static,
+        R::Params: DeserializeOwned + Send + 'static,
+        R::Result: Serialize + 'static,
+    {
+        // Keep the original request around to retry it if it gets canceled.
+        let req = match &self.req {
+            Some(req) if req.method == R::METHOD => req.clone(),
+            _ => return Ok(self),
+        };
         let (id, params) = match self.parse::<R>() {
             Some(it) => it,
             None => {
@@ -800,12 +1123,15 @@ impl<'a> PoolDispatcher<'a> {
             }
         };
 
-        self.pool.execute({
+        pool.execute({
             let world = self.world.snapshot();
             let sender = self.task_sender.clone();
             move || {
-                let result = f(world, params);
-                let task = result_to_task::<R>(id, result);
+                let _p = profile("request").detail(|| R::METHOD.to_string());
+                let task = match f(world, params) {
+                    Err(e) if is_canceled(&e) => Task::Retry(req),
+                    result => result_to_task::<R>(id, result),
+                };
                 sender.send(task).unwrap();
             }
         });
@@ -813,6 +1139,59 @@ impl<'a> PoolDispatcher<'a> {
         Ok(self)
     }
 
+    /// Dispatches a request which runs an external process, like `cargo test`.
+    /// `prepare` runs on the thread pool, and `run` on a thread of its own,
+    /// so that a slow process neither occupies the pool nor holds on to a
+    /// snapshot. `run` can send notifications while the process is running.
+    fn on_process<R, P>(
+        &mut self,
+        prepare: fn(WorldSnapshot, R::Params) -> Result<P>,
+        run: fn(P, &dyn Fn(Notification)) -> Result<R::Result>,
+    ) -> Result<&mut Self>
+    where
+        R: req::Request + 'static,
+        R::Params: DeserializeOwned + Send + 'static,
+        R::Result: Serialize + 'static,
+        P: Send + 'static,
+    {
+        let req = match &self.req {
+            Some(req) if req.method == R::METHOD => req.clone(),
+            _ => return Ok(self),
+        };
+        let (id, params) = match self.parse::<R>() {
+            Some(it) => it,
+            None => {
+                return Ok(self);
+            }
+        };
+
+        self.pool.execute({
+            let world = self.world.snapshot();
+            let sender = self.task_sender.clone();
+            move || {
+                let prepared = match prepare(world, params) {
+                    Ok(it) => it,
+                    Err(e) if is_canceled(&e) => {
+                        sender.send(Task::Retry(req)).unwrap();
+                        return;
+                    }
+                    Err(e) => {
+                        sender.send(result_to_task::<R>(id, Err(e))).unwrap();
+                        return;
+                    }
+                };
+                thread::spawn(move || {
+                    let _p = profile("process").detail(|| R::METHOD.to_string());
+                    let notify = |not| sender.send(Task::Notify(not)).unwrap();
+                    let result = run(prepared, &notify);
+                    sender.send(result_to_task::<R>(id, result)).unwrap();
+                });
+            }
+        });
+
+        Ok(self)
+    }
+
     fn parse<R>(&mut self) -> Option<(RequestId, R::Params)>
     where
         R: req::Request + 'static,
@@ -3,27 +3,33 @@
 //! `ra_ide` crate.
 
 use std::{
-    io::Write as _,
+    io::{self, Write as _},
     process::{self, Stdio},
 };
 
-use lsp_server::ErrorCode;
+use lsp_server::{ErrorCode, Notification};
 use lsp_types::{
     CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams, CallHierarchyItem,
     CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
-    CodeAction, CodeActionResponse, CodeLens, Command, CompletionItem, Diagnostic,
-    DocumentFormattingParams, DocumentHighlight, DocumentSymbol, FoldingRange, FoldingRangeParams,
-    Hover, HoverContents, Location, MarkupContent, MarkupKind, Position, PrepareRenameResponse,
-    Range, RenameParams, SemanticTokensParams, SemanticTokensRangeParams,
-    SemanticTokensRangeResult, SemanticTokensResult, SymbolInformation, TextDocumentIdentifier,
-    TextEdit, WorkspaceEdit,
+    CodeLens, Command, CompletionItem, Diagnostic, DiagnosticRelatedInformation,
+    DocumentFormattingParams, DocumentHighlight, DocumentRangeFormattingParams, DocumentSymbol,
+    FoldingRange, FoldingRangeParams, Hover, HoverContents, Location, MarkupContent, MarkupKind,
+    NumberOrString, Position, PrepareRenameResponse, Range, RenameParams, SemanticTokens,
+    SemanticTokensEditResult, SemanticTokensEdits, SemanticTokensEditsParams, SemanticTokensParams,
+    SemanticTokensRangeParams, SemanticTokensRangeResult, SemanticTokensResult, SymbolInformation,
+    SymbolKind, TextDocumentIdentifier, TextEdit, Url, WorkspaceEdit,
 };
+use ra_flycheck::url_from_path_with_drive_lowercasing;
 use ra_ide::{
-    Assist, AssistId, FileId, FilePosition, FileRange, Query, RangeInfo, Runnable, RunnableKind,
-    SearchScope,
+    Assist, AssistId, Cancelable, FileId, FilePosition, FileRange, Query, RangeInfo, Runnable,
+    RunnableKind, SearchScope, TestId, TestItem, TestItemKind,
 };
 use ra_prof::profile;
-use ra_syntax::{AstNode, SyntaxKind, TextRange, TextUnit};
+use ra_project_model::ProjectWorkspace;
+use ra_syntax::{
+    algo::find_covering_element, AstNode, NodeOrToken, SourceFile, SyntaxKind, TextRange, TextUnit,
+};
+use ra_text_edit::TextEditBuilder;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use serde_json::to_value;
@@ -36,10 +42,14 @@ use crate::{
         to_call_hierarchy_item, to_location, Conv, ConvWith, FoldConvCtx, MapConvWith, TryConvWith,
         TryConvWithToVec,
     },
+    debug_config::DebugBuild,
     diagnostics::DiagnosticTask,
+    diff::diff,
     from_json,
-    req::{self, InlayHint, InlayHintsParams},
-    semantic_tokens::SemanticTokensBuilder,
+    main_loop::notification_new,
+    req::{self, InjectedLanguage, InjectedLanguagesParams, InlayHint, InlayHintsParams},
+    semantic_tokens::{self, SemanticTokensBuilder},
+    test_runner::TestRun,
     world::WorldSnapshot,
     LspError, Result,
 };
@@ -56,22 +66,55 @@ pub fn handle_analyzer_status(world: WorldSnapshot, _: ()) -> Result<String> {
     Ok(buf)
 }
 
+pub fn handle_server_status(world: WorldSnapshot, _: ()) -> Result<req::ServerStatus> {
+    let _p = profile("handle_server_status");
+    let mut status = (*world.status).clone();
+    status.analysis = Some(world.analysis().status()?);
+    Ok(status)
+}
+
+pub fn handle_start_profiling(_: WorldSnapshot, params: req::StartProfilingParams) -> Result<()> {
+    // Each request is profiled under a `request` scope, so this filters out
+    // the other scopes like the turns of the main loop.
+    ra_prof::set_filter(ra_prof::Filter::from_spec("request"));
+    ra_prof::capture(params.requests);
+    Ok(())
+}
+
+pub fn handle_stop_profiling(_: WorldSnapshot, _: ()) -> Result<String> {
+    let profiles = ra_prof::take_captured();
+    ra_prof::capture(0);
+    // Go back to the filter set by `RA_PROFILE`.
+    ra_prof::init();
+    Ok(profiles.join("\n"))
+}
+
 pub fn handle_syntax_tree(world: WorldSnapshot, params: req::SyntaxTreeParams) -> Result<String> {
     let _p = profile("handle_syntax_tree");
     let id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(id)?;
+    let line_index = world.file_line_index(id)?;
     let text_range = params.range.map(|p| p.conv_with(&line_index));
     let res = world.analysis().syntax_tree(id, text_range)?;
     Ok(res)
 }
 
+pub fn handle_view_hir(
+    world: WorldSnapshot,
+    params: req::TextDocumentPositionParams,
+) -> Result<String> {
+    let _p = profile("handle_view_hir");
+    let position = params.try_conv_with(&world)?;
+    let res = world.analysis().view_hir(position)?;
+    Ok(res)
+}
+
 pub fn handle_expand_macro(
     world: WorldSnapshot,
     params: req::ExpandMacroParams,
 ) -> Result<Option<req::ExpandedMacro>> {
     let _p = profile("handle_expand_macro");
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
     let offset = params.position.map(|p| p.conv_with(&line_index));
 
     match offset {
@@ -89,7 +132,7 @@ pub fn handle_selection_range(
 ) -> Result<Option<Vec<req::SelectionRange>>> {
     let _p = profile("handle_selection_range");
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
     let res: Result<Vec<req::SelectionRange>> = params
         .positions
         .into_iter()
@@ -126,13 +169,47 @@ pub fn handle_selection_range(
     Ok(Some(res?))
 }
 
+pub fn handle_extend_selection(
+    world: WorldSnapshot,
+    params: req::ExtendSelectionParams,
+) -> Result<req::ExtendSelectionResult> {
+    let _p = profile("handle_extend_selection");
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let line_index = world.file_line_index(file_id)?;
+    let selections = params
+        .selections
+        .into_iter()
+        .map_conv_with(&line_index)
+        .map(|range| FileRange { file_id, range })
+        .map(|frange| world.analysis().extend_selection(frange).map(|it| it.conv_with(&line_index)))
+        .collect::<Cancelable<Vec<_>>>()?;
+    Ok(req::ExtendSelectionResult { selections })
+}
+
+pub fn handle_shrink_selection(
+    world: WorldSnapshot,
+    params: req::ExtendSelectionParams,
+) -> Result<req::ExtendSelectionResult> {
+    let _p = profile("handle_shrink_selection");
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let line_index = world.file_line_index(file_id)?;
+    let selections = params
+        .selections
+        .into_iter()
+        .map_conv_with(&line_index)
+        .map(|range| FileRange { file_id, range })
+        .map(|frange| world.analysis().shrink_selection(frange).map(|it| it.conv_with(&line_index)))
+        .collect::<Cancelable<Vec<_>>>()?;
+    Ok(req::ExtendSelectionResult { selections })
+}
+
 pub fn handle_find_matching_brace(
     world: WorldSnapshot,
     params: req::FindMatchingBraceParams,
 ) -> Result<Vec<Position>> {
     let _p = profile("handle_find_matching_brace");
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
     let res = params
         .offsets
         .into_iter()
@@ -179,7 +256,7 @@ pub fn handle_on_type_formatting(
 ) -> Result<Option<Vec<TextEdit>>> {
     let _p = profile("handle_on_type_formatting");
     let mut position = params.text_document_position.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(position.file_id)?;
+    let line_index = world.file_line_index(position.file_id)?;
     let line_endings = world.file_line_endings(position.file_id);
 
     // in `ra_ide`, the `on_type` invariant is that
@@ -218,15 +295,23 @@ pub fn handle_document_symbol(
 ) -> Result<Option<req::DocumentSymbolResponse>> {
     let _p = profile("handle_document_symbol");
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
 
+    let structure = world.analysis().file_structure(file_id)?;
     let mut parents: Vec<(DocumentSymbol, Option<usize>)> = Vec::new();
 
-    for symbol in world.analysis().file_structure(file_id)? {
+    for symbol in structure.iter() {
+        // Functions inside of impls and traits are shown as methods
+        let is_method = symbol.kind == SyntaxKind::FN_DEF
+            && symbol.parent.map_or(false, |it| match structure[it].kind {
+                SyntaxKind::IMPL_DEF | SyntaxKind::TRAIT_DEF => true,
+                _ => false,
+            });
+        let kind = if is_method { SymbolKind::Method } else { symbol.kind.conv() };
         let doc_symbol = DocumentSymbol {
-            name: symbol.label,
-            detail: symbol.detail,
-            kind: symbol.kind.conv(),
+            name: symbol.label.clone(),
+            detail: symbol.detail.clone(),
+            kind,
             deprecated: Some(symbol.deprecated),
             range: symbol.node_range.conv_with(&line_index),
             selection_range: symbol.navigation_range.conv_with(&line_index),
@@ -234,8 +319,13 @@ pub fn handle_document_symbol(
         };
         parents.push((doc_symbol, symbol.parent));
     }
+    // Children are collected from the last one to the first one, so the
+    // lists are reversed afterwards.
     let mut res = Vec::new();
-    while let Some((node, parent)) = parents.pop() {
+    while let Some((mut node, parent)) = parents.pop() {
+        if let Some(children) = &mut node.children {
+            children.reverse();
+        }
         match parent {
             None => res.push(node),
             Some(i) => {
@@ -247,6 +337,7 @@ pub fn handle_document_symbol(
             }
         }
     }
+    res.reverse();
 
     Ok(Some(res.into()))
 }
@@ -256,26 +347,9 @@ pub fn handle_workspace_symbol(
     params: req::WorkspaceSymbolParams,
 ) -> Result<Option<Vec<SymbolInformation>>> {
     let _p = profile("handle_workspace_symbol");
-    let all_symbols = params.query.contains('#');
-    let libs = params.query.contains('*');
-    let query = {
-        let query: String = params.query.chars().filter(|&c| c != '#' && c != '*').collect();
-        let mut q = Query::new(query);
-        if !all_symbols {
-            q.only_types();
-        }
-        if libs {
-            q.libs();
-        }
-        q.limit(128);
-        q
-    };
-    let mut res = exec_query(&world, query)?;
-    if res.is_empty() && !all_symbols {
-        let mut query = Query::new(params.query);
-        query.limit(128);
-        res = exec_query(&world, query)?;
-    }
+    let mut query = Query::parse(&params.query);
+    query.limit(128);
+    let res = exec_query(&world, query)?;
 
     return Ok(Some(res));
 
@@ -343,7 +417,41 @@ pub fn handle_parent_module(
 ) -> Result<Vec<Location>> {
     let _p = profile("handle_parent_module");
     let position = params.try_conv_with(&world)?;
-    world.analysis().parent_module(position)?.iter().try_conv_with_to_vec(&world)
+    let navs = world.analysis().parent_module(position)?;
+    if navs.is_empty() {
+        // Crate roots don't have a parent module, so go to the `Cargo.toml`
+        // which declares the crate instead.
+        let params = req::OpenCargoTomlParams { text_document: params.text_document };
+        return Ok(handle_open_cargo_toml(world, params)?.into_iter().collect());
+    }
+    navs.iter().try_conv_with_to_vec(&world)
+}
+
+pub fn handle_super_method(
+    world: WorldSnapshot,
+    params: req::TextDocumentPositionParams,
+) -> Result<Vec<Location>> {
+    let _p = profile("handle_super_method");
+    let position = params.try_conv_with(&world)?;
+    let navs = match world.analysis().goto_super_method(position)? {
+        None => return Ok(Vec::new()),
+        Some(it) => it.info,
+    };
+    navs.iter().try_conv_with_to_vec(&world)
+}
+
+pub fn handle_open_cargo_toml(
+    world: WorldSnapshot,
+    params: req::OpenCargoTomlParams,
+) -> Result<Option<Location>> {
+    let _p = profile("handle_open_cargo_toml");
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let spec = match CargoTargetSpec::for_file(&world, file_id)? {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let uri = url_from_path_with_drive_lowercasing(&spec.cargo_toml)?;
+    Ok(Some(Location::new(uri, Range::default())))
 }
 
 pub fn handle_runnables(
@@ -352,7 +460,7 @@ pub fn handle_runnables(
 ) -> Result<Vec<req::Runnable>> {
     let _p = profile("handle_runnables");
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
     let offset = params.position.map(|it| it.conv_with(&line_index));
     let mut res = Vec::new();
     let workspace_root = world.workspace_root_for(file_id);
@@ -389,6 +497,121 @@ pub fn handle_runnables(
     Ok(res)
 }
 
+pub fn handle_test_tree(world: WorldSnapshot, _: ()) -> Result<Vec<req::TestItem>> {
+    let _p = profile("handle_test_tree");
+    let mut res = Vec::new();
+    for ws in world.workspaces.iter() {
+        let cargo = match ws {
+            ProjectWorkspace::Cargo { cargo, .. } => cargo,
+            ProjectWorkspace::Json { .. } => continue,
+        };
+        for pkg in cargo.packages().filter(|&pkg| cargo[pkg].is_member) {
+            for &tgt in cargo[pkg].targets.iter() {
+                let crate_uri = url_from_path_with_drive_lowercasing(&cargo[tgt].root)?;
+                let file_id = match world.uri_to_file_id(&crate_uri) {
+                    Ok(it) => it,
+                    Err(_) => continue,
+                };
+                let &crate_id = match world.analysis().crate_for(file_id)?.first() {
+                    Some(it) => it,
+                    None => continue,
+                };
+                if let Some(tree) = world.analysis().test_tree(crate_id)? {
+                    let mut item = to_lsp_test_item(&world, crate_uri.as_str(), &tree)?;
+                    item.label = cargo[tgt].name.clone();
+                    res.push(item);
+                }
+            }
+        }
+    }
+    Ok(res)
+}
+
+pub(crate) fn handle_run_tests(
+    world: WorldSnapshot,
+    params: req::RunTestsParams,
+) -> Result<TestRun> {
+    let _p = profile("handle_run_tests");
+    let (crate_id, path) = match params.id.find('#') {
+        Some(idx) => (&params.id[..idx], &params.id[idx + 1..]),
+        None => (params.id.as_str(), ""),
+    };
+    let file_id = world.uri_to_file_id(&Url::parse(crate_id)?)?;
+    let &krate = world
+        .analysis()
+        .crate_for(file_id)?
+        .first()
+        .ok_or_else(|| format!("no crate for {}", crate_id))?;
+    let tree =
+        world.analysis().test_tree(krate)?.ok_or_else(|| format!("no tests in {}", crate_id))?;
+    let kind = match find_test_item(&tree, path).map(|it| it.kind) {
+        Some(TestItemKind::Test) => RunnableKind::Test { test_id: TestId::Path(path.to_string()) },
+        Some(_) => RunnableKind::TestMod { path: path.to_string() },
+        None => return Err(format!("unknown test: {}", params.id).into()),
+    };
+    let spec = CargoTargetSpec::for_file(&world, file_id)?
+        .ok_or_else(|| format!("{} is not a Cargo target", crate_id))?;
+    let (mut args, mut extra_args) = CargoTargetSpec::runnable_args(&world, Some(spec), &kind)?;
+    args.push("--message-format=json".to_string());
+    // Running a whole crate leaves an empty filter, and libtest only reports
+    // the output of a failed test if it captures it.
+    extra_args.retain(|it| !it.is_empty() && it != "--nocapture");
+    extra_args
+        .extend(["-Z", "unstable-options", "--format", "json"].iter().map(|it| it.to_string()));
+    Ok(TestRun {
+        crate_id: crate_id.to_string(),
+        args,
+        extra_args,
+        cwd: world.workspace_root_for(file_id).map(|it| it.to_path_buf()),
+    })
+}
+
+pub(crate) fn run_tests(
+    run: TestRun,
+    notify: &dyn Fn(Notification),
+) -> Result<req::TestRunSummary> {
+    run.run(&mut |params| notify(notification_new::<req::TestResult>(params)))
+}
+
+pub(crate) fn handle_debug_runnable(
+    world: WorldSnapshot,
+    params: req::Runnable,
+) -> Result<DebugBuild> {
+    let _p = profile("handle_debug_runnable");
+    let package = params.args.iter().skip_while(|it| *it != "--package").nth(1).cloned();
+    let mut build = DebugBuild::new(params)?;
+    // Cargo sets these when running the binary, and tests often read
+    // `CARGO_MANIFEST_DIR` at runtime.
+    if let Some(package) = package {
+        for ws in world.workspaces.iter() {
+            let cargo = match ws {
+                ProjectWorkspace::Cargo { cargo, .. } => cargo,
+                ProjectWorkspace::Json { .. } => continue,
+            };
+            if let Some(pkg) =
+                cargo.packages().find(|&pkg| cargo.package_flag(&cargo[pkg]) == package)
+            {
+                build.extend_env(cargo[pkg].cargo_env());
+            }
+        }
+    }
+    Ok(build)
+}
+
+pub(crate) fn build_debug_runnable(
+    build: DebugBuild,
+    _: &dyn Fn(Notification),
+) -> Result<req::DebugConfig> {
+    build.run()
+}
+
+fn find_test_item<'a>(item: &'a TestItem, path: &str) -> Option<&'a TestItem> {
+    if item.path == path {
+        return Some(item);
+    }
+    item.children.iter().find_map(|it| find_test_item(it, path))
+}
+
 pub fn handle_completion(
     world: WorldSnapshot,
     params: req::CompletionParams,
@@ -421,7 +644,7 @@ pub fn handle_completion(
         None => return Ok(None),
         Some(items) => items,
     };
-    let line_index = world.analysis().file_line_index(position.file_id)?;
+    let line_index = world.file_line_index(position.file_id)?;
     let line_endings = world.file_line_endings(position.file_id);
     let items: Vec<CompletionItem> =
         items.into_iter().map(|item| item.conv_with((&line_index, line_endings))).collect();
@@ -437,7 +660,7 @@ pub fn handle_folding_range(
     let file_id = params.text_document.try_conv_with(&world)?;
     let folds = world.analysis().folding_ranges(file_id)?;
     let text = world.analysis().file_text(file_id)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
     let ctx = FoldConvCtx {
         text: &text,
         line_index: &line_index,
@@ -453,7 +676,9 @@ pub fn handle_signature_help(
 ) -> Result<Option<req::SignatureHelp>> {
     let _p = profile("handle_signature_help");
     let position = params.try_conv_with(&world)?;
-    if let Some(call_info) = world.analysis().call_info(position)? {
+    if let Some(call_info) =
+        world.analysis().call_info(position, world.config.show_elided_lifetimes)?
+    {
         let concise = !world.config.call_info_full;
         let mut active_parameter = call_info.active_parameter.map(|it| it as i64);
         if concise && call_info.signature.has_self_param {
@@ -477,11 +702,11 @@ pub fn handle_hover(
 ) -> Result<Option<Hover>> {
     let _p = profile("handle_hover");
     let position = params.try_conv_with(&world)?;
-    let info = match world.analysis().hover(position)? {
+    let info = match world.analysis().hover(position, world.config.show_elided_lifetimes)? {
         None => return Ok(None),
         Some(info) => info,
     };
-    let line_index = world.analysis.file_line_index(position.file_id)?;
+    let line_index = world.file_line_index(position.file_id)?;
     let range = info.range.conv_with(&line_index);
     let res = Hover {
         contents: HoverContents::Markup(MarkupContent {
@@ -507,7 +732,7 @@ pub fn handle_prepare_rename(
     };
 
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
     let range = range.conv_with(&line_index);
     Ok(Some(PrepareRenameResponse::Range(range)))
 }
@@ -535,6 +760,33 @@ pub fn handle_rename(world: WorldSnapshot, params: RenameParams) -> Result<Optio
     Ok(Some(source_change_req.workspace_edit))
 }
 
+pub fn handle_safe_delete(
+    world: WorldSnapshot,
+    params: req::TextDocumentPositionParams,
+) -> Result<Option<req::SafeDeleteResult>> {
+    let _p = profile("handle_safe_delete");
+    let position = params.try_conv_with(&world)?;
+    let res = match world.analysis().safe_delete(position)? {
+        None => return Ok(None),
+        Some(it) => it.info,
+    };
+    let res = match res {
+        Ok(change) => {
+            req::SafeDeleteResult { change: Some(change.try_conv_with(&world)?), blockers: vec![] }
+        }
+        Err(references) => {
+            let mut blockers = Vec::new();
+            for reference in references {
+                let FileRange { file_id, range } = reference.file_range;
+                let line_index = world.file_line_index(file_id)?;
+                blockers.push(to_location(file_id, range, &world, &line_index)?);
+            }
+            req::SafeDeleteResult { change: None, blockers }
+        }
+    };
+    Ok(Some(res))
+}
+
 pub fn handle_references(
     world: WorldSnapshot,
     params: req::ReferenceParams,
@@ -542,16 +794,18 @@ pub fn handle_references(
     let _p = profile("handle_references");
     let position = params.text_document_position.try_conv_with(&world)?;
 
-    let refs = match world.analysis().find_all_refs(position, None)? {
+    let refs = match world.analysis().find_all_refs(position, None, world.config.search_mode)? {
         None => return Ok(None),
         Some(refs) => refs,
     };
+    if refs.is_truncated() {
+        log::info!("references search was canceled, returning {} references", refs.len());
+    }
 
     let locations = if params.context.include_declaration {
         refs.into_iter()
             .filter_map(|reference| {
-                let line_index =
-                    world.analysis().file_line_index(reference.file_range.file_id).ok()?;
+                let line_index = world.file_line_index(reference.file_range.file_id).ok()?;
                 to_location(
                     reference.file_range.file_id,
                     reference.file_range.range,
@@ -566,8 +820,7 @@ pub fn handle_references(
         refs.references()
             .iter()
             .filter_map(|reference| {
-                let line_index =
-                    world.analysis().file_line_index(reference.file_range.file_id).ok()?;
+                let line_index = world.file_line_index(reference.file_range.file_id).ok()?;
                 to_location(
                     reference.file_range.file_id,
                     reference.file_range.range,
@@ -589,12 +842,53 @@ pub fn handle_formatting(
     let _p = profile("handle_formatting");
     let file_id = params.text_document.try_conv_with(&world)?;
     let file = world.analysis().file_text(file_id)?;
-    let crate_ids = world.analysis().crate_for(file_id)?;
+    let formatted = match run_rustfmt(&world, &params.text_document, file_id, &file)? {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+
+    let line_index = world.file_line_index(file_id)?;
+    let line_endings = world.file_line_endings(file_id);
+    Ok(Some(diff(&file, &formatted).conv_with((&line_index, line_endings))))
+}
 
-    let file_line_index = world.analysis().file_line_index(file_id)?;
-    let end_position = TextUnit::of_str(&file).conv_with(&file_line_index);
+pub fn handle_range_formatting(
+    world: WorldSnapshot,
+    params: DocumentRangeFormattingParams,
+) -> Result<Option<Vec<TextEdit>>> {
+    let _p = profile("handle_range_formatting");
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let file = world.analysis().file_text(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
+    let line_endings = world.file_line_endings(file_id);
+    let range = params.range.conv_with(&line_index);
+
+    // `rustfmt` can only format a range on nightly, so the whole file is formatted
+    // and only the changes touching the range are kept.
+    let formatted = match run_rustfmt(&world, &params.text_document, file_id, &file)? {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let mut builder = TextEditBuilder::default();
+    for atom in diff(&file, &formatted).as_atoms() {
+        if atom.delete.start() <= range.end() && range.start() <= atom.delete.end() {
+            builder.replace(atom.delete, atom.insert.clone());
+        }
+    }
+    Ok(Some(builder.finish().conv_with((&line_index, line_endings))))
+}
 
-    let mut rustfmt = match &world.config.rustfmt {
+/// Returns the formatted `text` of the file, or `None` if rustfmt failed to
+/// parse it.
+fn run_rustfmt(
+    world: &WorldSnapshot,
+    text_document: &TextDocumentIdentifier,
+    file_id: FileId,
+    text: &str,
+) -> Result<Option<String>> {
+    let crate_ids = world.analysis().crate_for(file_id)?;
+
+    let (mut rustfmt, command) = match &world.config.rustfmt {
         RustfmtConfig::Rustfmt { extra_args } => {
             let mut cmd = process::Command::new("rustfmt");
             cmd.args(extra_args);
@@ -604,23 +898,34 @@ pub fn handle_formatting(
                 cmd.arg("--edition");
                 cmd.arg(edition.to_string());
             }
-            cmd
+            (cmd, "rustfmt")
         }
         RustfmtConfig::CustomCommand { command, args } => {
             let mut cmd = process::Command::new(command);
             cmd.args(args);
-            cmd
+            (cmd, command.as_str())
         }
     };
 
-    if let Ok(path) = params.text_document.uri.to_file_path() {
+    // `rustfmt` looks for `rustfmt.toml` starting from the working directory.
+    if let Ok(path) = text_document.uri.to_file_path() {
         if let Some(parent) = path.parent() {
             rustfmt.current_dir(parent);
         }
     }
-    let mut rustfmt = rustfmt.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+    let mut rustfmt = match rustfmt.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn() {
+        Ok(it) => it,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let mut message = format!("failed to run `{}`: it is not installed", command);
+            if command == "rustfmt" {
+                message.push_str(", try `rustup component add rustfmt`");
+            }
+            return Err(LspError::new(-32900, message).into());
+        }
+        Err(e) => return Err(e.into()),
+    };
 
-    rustfmt.stdin.as_mut().unwrap().write_all(file.as_bytes())?;
+    rustfmt.stdin.as_mut().unwrap().write_all(text.as_bytes())?;
 
     let output = rustfmt.wait_with_output()?;
     let captured_stdout = String::from_utf8(output.stdout)?;
@@ -637,7 +942,7 @@ pub fn handle_formatting(
                 return Ok(None);
             }
             _ => {
-                // Something else happened - e.g. `rustfmt` is missing or caught a signal
+                // Something else happened - e.g. `rustfmt` caught a signal
                 return Err(LspError::new(
                     -32900,
                     format!(
@@ -652,13 +957,10 @@ pub fn handle_formatting(
         }
     }
 
-    Ok(Some(vec![TextEdit {
-        range: Range::new(Position::new(0, 0), end_position),
-        new_text: captured_stdout,
-    }]))
+    Ok(Some(captured_stdout))
 }
 
-fn create_single_code_action(assist: Assist, world: &WorldSnapshot) -> Result<CodeAction> {
+fn create_single_code_action(assist: Assist, world: &WorldSnapshot) -> Result<req::CodeAction> {
     let arg = to_value(assist.source_change.try_conv_with(world)?)?;
     let title = assist.label;
     let command = Command {
@@ -667,37 +969,42 @@ fn create_single_code_action(assist: Assist, world: &WorldSnapshot) -> Result<Co
         arguments: Some(vec![arg]),
     };
 
-    let kind = match assist.id {
-        AssistId("introduce_variable") => Some("refactor.extract.variable".to_string()),
-        AssistId("add_custom_impl") => Some("refactor.rewrite.add_custom_impl".to_string()),
-        _ => None,
-    };
-
-    Ok(CodeAction {
+    Ok(req::CodeAction {
         title,
-        kind,
-        diagnostics: None,
-        edit: None,
+        kind: assist_kind(assist.id),
         command: Some(command),
-        is_preferred: None,
+        ..req::CodeAction::default()
     })
 }
 
+fn assist_kind(id: AssistId) -> Option<String> {
+    match id {
+        AssistId("introduce_variable") => Some("refactor.extract.variable".to_string()),
+        AssistId("add_custom_impl") => Some("refactor.rewrite.add_custom_impl".to_string()),
+        _ => None,
+    }
+}
+
 pub fn handle_code_action(
     world: WorldSnapshot,
     params: req::CodeActionParams,
-) -> Result<Option<CodeActionResponse>> {
+) -> Result<Option<Vec<req::CodeAction>>> {
     let _p = profile("handle_code_action");
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
     let range = params.range.conv_with(&line_index);
 
-    let diagnostics = world.analysis().diagnostics(file_id)?;
-    let mut res = CodeActionResponse::default();
+    let enable_experimental = world.config.diagnostics.enable_experimental;
+    let diagnostics = world.analysis().diagnostics(file_id, enable_experimental)?;
+    let mut res: Vec<req::CodeAction> = Vec::new();
 
     let fixes_from_diagnostics = diagnostics
         .into_iter()
-        .filter_map(|d| Some((d.range, d.fix?)))
+        .filter(|d| !world.config.diagnostics.is_disabled(d.code))
+        .flat_map(|d| {
+            let range = d.range;
+            d.fixes.into_iter().map(move |fix| (range, fix))
+        })
         .filter(|(diag_range, _fix)| diag_range.intersection(&range).is_some())
         .map(|(_range, fix)| fix);
 
@@ -710,15 +1017,11 @@ pub fn handle_code_action(
             command: "rust-analyzer.applySourceChange".to_string(),
             arguments: Some(vec![to_value(edit).unwrap()]),
         };
-        let action = CodeAction {
+        res.push(req::CodeAction {
             title: command.title.clone(),
-            kind: None,
-            diagnostics: None,
-            edit: None,
             command: Some(command),
-            is_preferred: None,
-        };
-        res.push(action.into());
+            ..req::CodeAction::default()
+        });
     }
 
     for fix in world.check_fixes.get(&file_id).into_iter().flatten() {
@@ -726,32 +1029,52 @@ pub fn handle_code_action(
         if fix_range.intersection(&range).is_none() {
             continue;
         }
-        res.push(fix.action.clone());
+        res.push(fix.action.clone().into());
+    }
+
+    // Clients supporting `codeAction/resolve` get the assists without the
+    // edits, which are computed only for the assist that is picked.
+    if world.config.client_caps.code_action_resolve {
+        for assist in world
+            .analysis()
+            .unresolved_assists(FileRange { file_id, range }, &world.config.assist)?
+        {
+            res.push(req::CodeAction {
+                title: assist.label,
+                kind: assist_kind(assist.id),
+                data: Some(req::CodeActionData {
+                    code_action_params: params.clone(),
+                    id: assist.id.0.to_string(),
+                }),
+                ..req::CodeAction::default()
+            });
+        }
+        return Ok(Some(res));
     }
 
     let mut grouped_assists: FxHashMap<String, (usize, Vec<Assist>)> = FxHashMap::default();
-    for assist in world.analysis().assists(FileRange { file_id, range })?.into_iter() {
+    for assist in
+        world.analysis().assists(FileRange { file_id, range }, &world.config.assist)?.into_iter()
+    {
         match &assist.group_label {
             Some(label) => grouped_assists
                 .entry(label.to_owned())
                 .or_insert_with(|| {
                     let idx = res.len();
-                    let dummy = Command::new(String::new(), String::new(), None);
-                    res.push(dummy.into());
+                    res.push(req::CodeAction::default());
                     (idx, Vec::new())
                 })
                 .1
                 .push(assist),
             None => {
-                res.push(create_single_code_action(assist, &world)?.into());
+                res.push(create_single_code_action(assist, &world)?);
             }
         }
     }
 
     for (group_label, (idx, assists)) in grouped_assists {
         if assists.len() == 1 {
-            res[idx] =
-                create_single_code_action(assists.into_iter().next().unwrap(), &world)?.into();
+            res[idx] = create_single_code_action(assists.into_iter().next().unwrap(), &world)?;
         } else {
             let title = group_label;
 
@@ -765,28 +1088,60 @@ pub fn handle_code_action(
                 command: "rust-analyzer.selectAndApplySourceChange".to_string(),
                 arguments: Some(vec![serde_json::Value::Array(arguments)]),
             });
-            res[idx] = CodeAction {
-                title,
-                kind: None,
-                diagnostics: None,
-                edit: None,
-                command,
-                is_preferred: None,
-            }
-            .into();
+            res[idx] = req::CodeAction { title, command, ..req::CodeAction::default() };
         }
     }
 
     Ok(Some(res))
 }
 
+pub fn handle_code_action_resolve(
+    world: WorldSnapshot,
+    mut code_action: req::CodeAction,
+) -> Result<req::CodeAction> {
+    let _p = profile("handle_code_action_resolve");
+    let data = match code_action.data.take() {
+        Some(it) => it,
+        None => {
+            return Err(LspError::new(
+                ErrorCode::InvalidParams as i32,
+                "code action without data can't be resolved".into(),
+            )
+            .into())
+        }
+    };
+
+    let file_id = data.code_action_params.text_document.try_conv_with(&world)?;
+    let line_index = world.file_line_index(file_id)?;
+    let range = data.code_action_params.range.conv_with(&line_index);
+
+    let assists = world.analysis().assists(FileRange { file_id, range }, &world.config.assist)?;
+    let assist =
+        assists.into_iter().find(|it| it.id.0 == data.id.as_str() && it.label == code_action.title);
+    let assist = match assist {
+        Some(it) => it,
+        None => {
+            return Err(LspError::new(
+                ErrorCode::InvalidParams as i32,
+                format!("assist `{}` is no longer applicable", data.id),
+            )
+            .into())
+        }
+    };
+    // The client applies the edit as a plain `WorkspaceEdit`.
+    let source_change: req::SourceChange =
+        assist.source_change.without_snippets().try_conv_with(&world)?;
+    code_action.edit = Some(source_change.workspace_edit);
+    Ok(code_action)
+}
+
 pub fn handle_code_lens(
     world: WorldSnapshot,
     params: req::CodeLensParams,
 ) -> Result<Option<Vec<CodeLens>>> {
     let _p = profile("handle_code_lens");
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
 
     let mut lenses: Vec<CodeLens> = Default::default();
 
@@ -795,10 +1150,12 @@ pub fn handle_code_lens(
         let title = match &runnable.kind {
             RunnableKind::Test { .. } | RunnableKind::TestMod { .. } => "▶️\u{fe0e}Run Test",
             RunnableKind::Bench { .. } => "Run Bench",
+            RunnableKind::DocTest { .. } => "▶️\u{fe0e}Run Doctest",
             RunnableKind::Bin => "Run",
         }
         .to_string();
-        let mut r = to_lsp_runnable(&world, file_id, runnable)?;
+        let is_doc_test = matches!(runnable.kind, RunnableKind::DocTest { .. });
+        let r = to_lsp_runnable(&world, file_id, runnable)?;
         let lens = CodeLens {
             range: r.range,
             command: Some(Command {
@@ -810,10 +1167,9 @@ pub fn handle_code_lens(
         };
         lenses.push(lens);
 
-        if r.args[0] == "run" {
-            r.args[0] = "build".into();
-        } else {
-            r.args.push("--no-run".into());
+        // Doctests are compiled by rustdoc, there is no binary to debug
+        if is_doc_test {
+            continue;
         }
         let debug_lens = CodeLens {
             range: r.range,
@@ -827,12 +1183,12 @@ pub fn handle_code_lens(
         lenses.push(debug_lens);
     }
 
+    let structure = world.analysis().file_structure(file_id)?;
+
     // Handle impls
     lenses.extend(
-        world
-            .analysis()
-            .file_structure(file_id)?
-            .into_iter()
+        structure
+            .iter()
             .filter(|it| match it.kind {
                 SyntaxKind::TRAIT_DEF | SyntaxKind::STRUCT_DEF | SyntaxKind::ENUM_DEF => true,
                 _ => false,
@@ -850,13 +1206,53 @@ pub fn handle_code_lens(
             }),
     );
 
+    // Handle references, only for the items which are visible outside of
+    // their module. Counting references is expensive, so it's done lazily in
+    // `handle_code_lens_resolve`.
+    let source_file = world.analysis().parse(file_id)?;
+    lenses.extend(
+        structure
+            .iter()
+            .filter(|it| match it.kind {
+                SyntaxKind::FN_DEF
+                | SyntaxKind::STRUCT_DEF
+                | SyntaxKind::ENUM_DEF
+                | SyntaxKind::UNION_DEF
+                | SyntaxKind::TRAIT_DEF
+                | SyntaxKind::TYPE_ALIAS_DEF
+                | SyntaxKind::CONST_DEF
+                | SyntaxKind::STATIC_DEF => true,
+                _ => false,
+            })
+            .filter(|it| has_visibility(&source_file, it.node_range))
+            .map(|it| {
+                let range = it.node_range.conv_with(&line_index);
+                let pos = it.navigation_range.start().conv_with(&line_index);
+                let lens_params =
+                    req::TextDocumentPositionParams::new(params.text_document.clone(), pos);
+                CodeLens {
+                    range,
+                    command: None,
+                    data: Some(to_value(CodeLensResolveData::References(lens_params)).unwrap()),
+                }
+            }),
+    );
+
     Ok(Some(lenses))
 }
 
+fn has_visibility(source_file: &SourceFile, range: TextRange) -> bool {
+    match find_covering_element(source_file.syntax(), range) {
+        NodeOrToken::Node(node) => node.children().any(|it| it.kind() == SyntaxKind::VISIBILITY),
+        NodeOrToken::Token(_) => false,
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 enum CodeLensResolveData {
     Impls(req::TextDocumentPositionParams),
+    References(req::TextDocumentPositionParams),
 }
 
 pub fn handle_code_lens_resolve(world: WorldSnapshot, code_lens: CodeLens) -> Result<CodeLens> {
@@ -896,6 +1292,36 @@ pub fn handle_code_lens_resolve(world: WorldSnapshot, code_lens: CodeLens) -> Re
             };
             Ok(CodeLens { range: code_lens.range, command: Some(cmd), data: None })
         }
+        Some(CodeLensResolveData::References(lens_params)) => {
+            let position = lens_params.try_conv_with(&world)?;
+            let refs = world.analysis().find_all_refs(position, None, world.config.search_mode)?;
+            let truncated = refs.as_ref().map_or(false, |it| it.is_truncated());
+            let locations: Vec<Location> = match refs {
+                Some(refs) => refs
+                    .references()
+                    .iter()
+                    .filter_map(|reference| {
+                        let file_id = reference.file_range.file_id;
+                        let line_index = world.file_line_index(file_id).ok()?;
+                        to_location(file_id, reference.file_range.range, &world, &line_index).ok()
+                    })
+                    .collect(),
+                None => vec![],
+            };
+
+            let title = references_title(locations.len(), truncated);
+
+            let cmd = Command {
+                title,
+                command: "rust-analyzer.showReferences".into(),
+                arguments: Some(vec![
+                    to_value(&lens_params.text_document.uri).unwrap(),
+                    to_value(code_lens.range.start).unwrap(),
+                    to_value(locations).unwrap(),
+                ]),
+            };
+            Ok(CodeLens { range: code_lens.range, command: Some(cmd), data: None })
+        }
         None => Ok(CodeLens {
             range: code_lens.range,
             command: Some(Command { title: "Error".into(), ..Default::default() }),
@@ -904,21 +1330,45 @@ pub fn handle_code_lens_resolve(world: WorldSnapshot, code_lens: CodeLens) -> Re
     }
 }
 
+/// The title of a references code lens. A `+` marks a search which was canceled
+/// before all the references were found.
+fn references_title(count: usize, truncated: bool) -> String {
+    match (count, truncated) {
+        (1, false) => "1 reference".into(),
+        (_, false) => format!("{} references", count),
+        (_, true) => format!("{}+ references", count),
+    }
+}
+
 pub fn handle_document_highlight(
     world: WorldSnapshot,
     params: req::TextDocumentPositionParams,
 ) -> Result<Option<Vec<DocumentHighlight>>> {
     let _p = profile("handle_document_highlight");
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
+    let position = params.try_conv_with(&world)?;
 
-    let refs = match world
-        .analysis()
-        .find_all_refs(params.try_conv_with(&world)?, Some(SearchScope::single_file(file_id)))?
-    {
+    if let Some(ranges) = world.analysis().highlight_related(position)? {
+        return Ok(Some(
+            ranges
+                .into_iter()
+                .map(|range| DocumentHighlight { range: range.conv_with(&line_index), kind: None })
+                .collect(),
+        ));
+    }
+
+    let refs = match world.analysis().find_all_refs(
+        position,
+        Some(SearchScope::single_file(file_id)),
+        world.config.search_mode,
+    )? {
         None => return Ok(None),
         Some(refs) => refs,
     };
+    if refs.is_truncated() {
+        log::info!("highlight search was canceled, returning {} references", refs.len());
+    }
 
     Ok(Some(
         refs.into_iter()
@@ -933,29 +1383,47 @@ pub fn handle_document_highlight(
 
 pub fn handle_ssr(world: WorldSnapshot, params: req::SsrParams) -> Result<req::SourceChange> {
     let _p = profile("handle_ssr");
+    let position = params.position.try_conv_with(&world)?;
     world
         .analysis()
-        .structural_search_replace(&params.query, params.parse_only)??
+        .structural_search_replace(&params.query, params.parse_only, position)??
         .try_conv_with(&world)
 }
 
 pub fn publish_diagnostics(world: &WorldSnapshot, file_id: FileId) -> Result<DiagnosticTask> {
     let _p = profile("publish_diagnostics");
-    let line_index = world.analysis().file_line_index(file_id)?;
-    let diagnostics: Vec<Diagnostic> = world
-        .analysis()
-        .diagnostics(file_id)?
-        .into_iter()
-        .map(|d| Diagnostic {
+    let config = &world.config.diagnostics;
+    let path = world.file_id_to_path(file_id);
+    if config.is_excluded(&path, world.workspace_root_for(file_id)) {
+        return Ok(DiagnosticTask::SetNative(file_id, Vec::new()));
+    }
+    let line_index = world.file_line_index(file_id)?;
+    let mut diagnostics = Vec::new();
+    for d in world.analysis().diagnostics(file_id, config.enable_experimental)? {
+        if config.is_disabled(d.code) {
+            continue;
+        }
+        let mut related_information = Vec::new();
+        for label in d.labels {
+            let FileRange { file_id, range } = label.range;
+            let location = to_location(file_id, range, world, &world.file_line_index(file_id)?)?;
+            related_information
+                .push(DiagnosticRelatedInformation { location, message: label.message });
+        }
+        diagnostics.push(Diagnostic {
             range: d.range.conv_with(&line_index),
-            severity: Some(d.severity.conv()),
-            code: None,
+            severity: Some(config.severity(d.code).unwrap_or_else(|| d.severity.conv())),
+            code: Some(NumberOrString::String(d.code.to_string())),
             source: Some("rust-analyzer".to_string()),
             message: d.message,
-            related_information: None,
+            related_information: if related_information.is_empty() {
+                None
+            } else {
+                Some(related_information)
+            },
             tags: None,
-        })
-        .collect();
+        });
+    }
     Ok(DiagnosticTask::SetNative(file_id, diagnostics))
 }
 
@@ -965,12 +1433,13 @@ fn to_lsp_runnable(
     runnable: Runnable,
 ) -> Result<req::Runnable> {
     let spec = CargoTargetSpec::for_file(world, file_id)?;
-    let (args, extra_args) = CargoTargetSpec::runnable_args(spec, &runnable.kind)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let (args, extra_args) = CargoTargetSpec::runnable_args(world, spec, &runnable.kind)?;
+    let line_index = world.file_line_index(file_id)?;
     let label = match &runnable.kind {
         RunnableKind::Test { test_id } => format!("test {}", test_id),
         RunnableKind::TestMod { path } => format!("test-mod {}", path),
         RunnableKind::Bench { test_id } => format!("bench {}", test_id),
+        RunnableKind::DocTest { test_id } => format!("doctest {}", test_id),
         RunnableKind::Bin => "run binary".to_string(),
     };
     Ok(req::Runnable {
@@ -988,6 +1457,34 @@ fn to_lsp_runnable(
     })
 }
 
+fn to_lsp_test_item(
+    world: &WorldSnapshot,
+    crate_id: &str,
+    item: &TestItem,
+) -> Result<req::TestItem> {
+    let id = match item.kind {
+        TestItemKind::Crate => crate_id.to_string(),
+        TestItemKind::Module | TestItemKind::Test => format!("{}#{}", crate_id, item.path),
+    };
+    let kind = match item.kind {
+        TestItemKind::Crate => req::TestItemKind::Crate,
+        TestItemKind::Module => req::TestItemKind::Module,
+        TestItemKind::Test => req::TestItemKind::Test,
+    };
+    let children = item
+        .children
+        .iter()
+        .map(|it| to_lsp_test_item(world, crate_id, it))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(req::TestItem {
+        id,
+        label: item.name.clone(),
+        kind,
+        location: (&item.nav).try_conv_with(world)?,
+        children,
+    })
+}
+
 pub fn handle_inlay_hints(
     world: WorldSnapshot,
     params: InlayHintsParams,
@@ -995,7 +1492,7 @@ pub fn handle_inlay_hints(
     let _p = profile("handle_inlay_hints");
     let file_id = params.text_document.try_conv_with(&world)?;
     let analysis = world.analysis();
-    let line_index = analysis.file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
     Ok(analysis
         .inlay_hints(file_id, &world.config.inlay_hints)?
         .into_iter()
@@ -1003,25 +1500,42 @@ pub fn handle_inlay_hints(
         .collect())
 }
 
+pub fn handle_injected_languages(
+    world: WorldSnapshot,
+    params: InjectedLanguagesParams,
+) -> Result<Vec<InjectedLanguage>> {
+    let _p = profile("handle_injected_languages");
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let line_index = world.file_line_index(file_id)?;
+    let res = world
+        .analysis()
+        .injected_languages(file_id, &world.config.injection_macros)?
+        .into_iter()
+        .map(|it| InjectedLanguage {
+            range: it.range.conv_with(&line_index),
+            language: it.language,
+        })
+        .collect();
+    Ok(res)
+}
+
 pub fn handle_call_hierarchy_prepare(
     world: WorldSnapshot,
     params: CallHierarchyPrepareParams,
 ) -> Result<Option<Vec<CallHierarchyItem>>> {
     let _p = profile("handle_call_hierarchy_prepare");
     let position = params.text_document_position_params.try_conv_with(&world)?;
-    let file_id = position.file_id;
 
     let nav_info = match world.analysis().call_hierarchy(position)? {
         None => return Ok(None),
         Some(it) => it,
     };
 
-    let line_index = world.analysis().file_line_index(file_id)?;
-    let RangeInfo { range, info: navs } = nav_info;
+    let RangeInfo { range: _, info: navs } = nav_info;
     let res = navs
         .into_iter()
         .filter(|it| it.kind() == SyntaxKind::FN_DEF)
-        .filter_map(|it| to_call_hierarchy_item(file_id, range, &world, &line_index, it).ok())
+        .filter_map(|it| to_call_hierarchy_item(&world, it).ok())
         .collect();
 
     Ok(Some(res))
@@ -1035,10 +1549,10 @@ pub fn handle_call_hierarchy_incoming(
     let item = params.item;
 
     let doc = TextDocumentIdentifier::new(item.uri);
-    let frange: FileRange = (&doc, item.range).try_conv_with(&world)?;
+    let frange: FileRange = (&doc, item.selection_range).try_conv_with(&world)?;
     let fpos = FilePosition { file_id: frange.file_id, offset: frange.range.start() };
 
-    let call_items = match world.analysis().incoming_calls(fpos)? {
+    let call_items = match world.analysis().incoming_calls(fpos, world.config.search_mode)? {
         None => return Ok(None),
         Some(it) => it,
     };
@@ -1046,14 +1560,10 @@ pub fn handle_call_hierarchy_incoming(
     let mut res = vec![];
 
     for call_item in call_items.into_iter() {
-        let file_id = call_item.target.file_id();
-        let line_index = world.analysis().file_line_index(file_id)?;
-        let range = call_item.target.range();
-        let item = to_call_hierarchy_item(file_id, range, &world, &line_index, call_item.target)?;
-        res.push(CallHierarchyIncomingCall {
-            from: item,
-            from_ranges: call_item.ranges.iter().map(|it| it.conv_with(&line_index)).collect(),
-        });
+        let line_index = world.file_line_index(call_item.target.file_id())?;
+        let from_ranges = call_item.ranges.iter().map(|it| it.conv_with(&line_index)).collect();
+        let item = to_call_hierarchy_item(&world, call_item.target)?;
+        res.push(CallHierarchyIncomingCall { from: item, from_ranges });
     }
 
     Ok(Some(res))
@@ -1067,7 +1577,7 @@ pub fn handle_call_hierarchy_outgoing(
     let item = params.item;
 
     let doc = TextDocumentIdentifier::new(item.uri);
-    let frange: FileRange = (&doc, item.range).try_conv_with(&world)?;
+    let frange: FileRange = (&doc, item.selection_range).try_conv_with(&world)?;
     let fpos = FilePosition { file_id: frange.file_id, offset: frange.range.start() };
 
     let call_items = match world.analysis().outgoing_calls(fpos)? {
@@ -1075,17 +1585,14 @@ pub fn handle_call_hierarchy_outgoing(
         Some(it) => it,
     };
 
+    // The ranges of outgoing calls are in the file of the caller
+    let line_index = world.file_line_index(fpos.file_id)?;
     let mut res = vec![];
 
     for call_item in call_items.into_iter() {
-        let file_id = call_item.target.file_id();
-        let line_index = world.analysis().file_line_index(file_id)?;
-        let range = call_item.target.range();
-        let item = to_call_hierarchy_item(file_id, range, &world, &line_index, call_item.target)?;
-        res.push(CallHierarchyOutgoingCall {
-            to: item,
-            from_ranges: call_item.ranges.iter().map(|it| it.conv_with(&line_index)).collect(),
-        });
+        let from_ranges = call_item.ranges.iter().map(|it| it.conv_with(&line_index)).collect();
+        let item = to_call_hierarchy_item(&world, call_item.target)?;
+        res.push(CallHierarchyOutgoingCall { to: item, from_ranges });
     }
 
     Ok(Some(res))
@@ -1098,14 +1605,47 @@ pub fn handle_semantic_tokens(
     let _p = profile("handle_semantic_tokens");
 
     let file_id = params.text_document.try_conv_with(&world)?;
+    let tokens = semantic_tokens_for_file(&world, file_id)?;
+    world.semantic_tokens_cache.lock().insert(params.text_document.uri, tokens.clone());
+
+    Ok(Some(tokens.into()))
+}
+
+pub fn handle_semantic_tokens_edits(
+    world: WorldSnapshot,
+    params: SemanticTokensEditsParams,
+) -> Result<Option<SemanticTokensEditResult>> {
+    let _p = profile("handle_semantic_tokens_edits");
+
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let tokens = semantic_tokens_for_file(&world, file_id)?;
+
+    let mut cache = world.semantic_tokens_cache.lock();
+    let previous = cache.insert(params.text_document.uri, tokens.clone());
+    match previous {
+        Some(previous) if previous.result_id.as_ref() == Some(&params.previous_result_id) => {
+            let edits = semantic_tokens::diff_tokens(&previous.data, &tokens.data);
+            let edits = SemanticTokensEdits { result_id: tokens.result_id, edits };
+            Ok(Some(SemanticTokensEditResult::TokensEdits(edits)))
+        }
+        // The client is out of sync with us, send all the tokens instead
+        _ => Ok(Some(SemanticTokensEditResult::Tokens(tokens))),
+    }
+}
+
+fn semantic_tokens_for_file(world: &WorldSnapshot, file_id: FileId) -> Result<SemanticTokens> {
     let text = world.analysis().file_text(file_id)?;
-    let line_index = world.analysis().file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
 
-    let mut builder = SemanticTokensBuilder::default();
+    let mut builder = SemanticTokensBuilder::with_result_id(semantic_tokens::next_result_id());
 
+    let injected = injected_ranges(world, file_id)?;
     for highlight_range in world.analysis().highlight(file_id)?.into_iter() {
+        if injected.iter().any(|it| highlight_range.range.is_subrange(it)) {
+            continue;
+        }
         let (token_index, modifier_bitset) = highlight_range.highlight.conv();
-        for mut range in line_index.lines(highlight_range.range) {
+        for mut range in line_index.index.lines(highlight_range.range) {
             if text[range].ends_with('\n') {
                 range = TextRange::from_to(range.start(), range.end() - TextUnit::of_char('\n'));
             }
@@ -1114,9 +1654,7 @@ pub fn handle_semantic_tokens(
         }
     }
 
-    let tokens = builder.build();
-
-    Ok(Some(tokens.into()))
+    Ok(builder.build())
 }
 
 pub fn handle_semantic_tokens_range(
@@ -1126,11 +1664,15 @@ pub fn handle_semantic_tokens_range(
     let _p = profile("handle_semantic_tokens_range");
 
     let frange = (&params.text_document, params.range).try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(frange.file_id)?;
+    let line_index = world.file_line_index(frange.file_id)?;
 
     let mut builder = SemanticTokensBuilder::default();
 
+    let injected = injected_ranges(&world, frange.file_id)?;
     for highlight_range in world.analysis().highlight_range(frange)?.into_iter() {
+        if injected.iter().any(|it| highlight_range.range.is_subrange(it)) {
+            continue;
+        }
         let (token_type, token_modifiers) = highlight_range.highlight.conv();
         builder.push(highlight_range.range.conv_with(&line_index), token_type, token_modifiers);
     }
@@ -1139,3 +1681,28 @@ pub fn handle_semantic_tokens_range(
 
     Ok(Some(tokens.into()))
 }
+
+/// The inputs of the configured injection macros get no semantic tokens, so
+/// that the client's injection grammar for their language applies there.
+fn injected_ranges(world: &WorldSnapshot, file_id: FileId) -> Result<Vec<TextRange>> {
+    let res = world
+        .analysis()
+        .injected_languages(file_id, &world.config.injection_macros)?
+        .into_iter()
+        .map(|it| it.range)
+        .collect();
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::references_title;
+
+    #[test]
+    fn references_title_marks_truncated_searches() {
+        assert_eq!(references_title(1, false), "1 reference");
+        assert_eq!(references_title(3, false), "3 references");
+        assert_eq!(references_title(1, true), "1+ references");
+        assert_eq!(references_title(0, true), "0+ references");
+    }
+}
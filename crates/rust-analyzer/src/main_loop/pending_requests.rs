@@ -43,6 +43,12 @@ impl PendingRequests {
     pub(crate) fn cancel(&mut self, id: &RequestId) -> bool {
         self.map.remove(id).is_some()
     }
+    /// Removes the request so that it can be dispatched again, returning the
+    /// time it was originally received. Returns `None` if the request was
+    /// canceled in the meantime.
+    pub(crate) fn retry(&mut self, id: &RequestId) -> Option<Instant> {
+        self.map.remove(id).map(|request| request.received)
+    }
     pub(crate) fn finish(&mut self, id: &RequestId) -> Option<CompletedRequest> {
         self.map.remove(id).map(CompletedRequest::from)
     }
@@ -60,7 +66,9 @@ pub struct LatestRequests {
 impl LatestRequests {
     pub(crate) fn record(&mut self, request: CompletedRequest) {
         // special case: don't track status request itself
-        if request.method == "rust-analyzer/analyzerStatus" {
+        if request.method == "rust-analyzer/analyzerStatus"
+            || request.method == "rust-analyzer/status"
+        {
             return;
         }
         let idx = self.idx;
@@ -15,7 +15,8 @@ pub use lsp_types::{
     SelectionRangeParams, SemanticTokensParams, SemanticTokensRangeParams,
     SemanticTokensRangeResult, SemanticTokensResult, ServerCapabilities, ShowMessageParams,
     SignatureHelp, SymbolKind, TextDocumentEdit, TextDocumentPositionParams, TextEdit,
-    WorkDoneProgressParams, WorkspaceEdit, WorkspaceSymbolParams,
+    Unregistration, UnregistrationParams, WorkDoneProgressParams, WorkspaceEdit, WorkspaceFolder,
+    WorkspaceSymbolParams,
 };
 
 pub enum AnalyzerStatus {}
@@ -26,6 +27,43 @@ impl Request for AnalyzerStatus {
     const METHOD: &'static str = "rust-analyzer/analyzerStatus";
 }
 
+pub enum ServerStatusRequest {}
+
+impl Request for ServerStatusRequest {
+    type Params = ();
+    type Result = ServerStatus;
+    const METHOD: &'static str = "rust-analyzer/status";
+}
+
+/// Sent whenever the status changes, if the client opts in with the
+/// `experimental.statusNotification` capability.
+pub enum ServerStatusNotification {}
+
+impl Notification for ServerStatusNotification {
+    type Params = ServerStatus;
+    const METHOD: &'static str = "rust-analyzer/statusNotification";
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    /// Whether all the source roots were scanned and indexed.
+    pub workspace_loaded: bool,
+    pub roots_scanned: usize,
+    pub roots_total: usize,
+    /// The number of packages in all the loaded workspaces.
+    pub packages: usize,
+    /// The proc-macro server, if the expansion of proc-macros is enabled.
+    pub proc_macro_srv: Option<String>,
+    /// Whether the `OUT_DIR`s of build scripts are loaded with `cargo check`.
+    pub load_out_dirs_from_check: bool,
+    pub loading_errors: Vec<String>,
+    /// File, syntax tree and memory statistics of the analysis. Too expensive
+    /// to compute for the notifications, so only set in the responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<String>,
+}
+
 pub enum CollectGarbage {}
 
 impl Request for CollectGarbage {
@@ -34,6 +72,41 @@ impl Request for CollectGarbage {
     const METHOD: &'static str = "rust-analyzer/collectGarbage";
 }
 
+/// Reports the memory used by each query. The caches are emptied to measure
+/// it, so the next requests are slower.
+pub enum MemoryUsage {}
+
+impl Request for MemoryUsage {
+    type Params = ();
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/memoryUsage";
+}
+
+/// Starts keeping the profiles of the last few requests, see `ra_prof`.
+pub enum StartProfiling {}
+
+impl Request for StartProfiling {
+    type Params = StartProfilingParams;
+    type Result = ();
+    const METHOD: &'static str = "rust-analyzer/startProfiling";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StartProfilingParams {
+    /// How many of the last requests to keep.
+    pub requests: usize,
+}
+
+/// Stops the profiling and returns the kept profiles.
+pub enum StopProfiling {}
+
+impl Request for StopProfiling {
+    type Params = ();
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/stopProfiling";
+}
+
 pub enum SyntaxTree {}
 
 impl Request for SyntaxTree {
@@ -49,6 +122,14 @@ pub struct SyntaxTreeParams {
     pub range: Option<Range>,
 }
 
+pub enum ViewHir {}
+
+impl Request for ViewHir {
+    type Params = TextDocumentPositionParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/viewHir";
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ExpandedMacro {
@@ -71,6 +152,35 @@ pub struct ExpandMacroParams {
     pub position: Option<Position>,
 }
 
+pub enum ExtendSelection {}
+
+impl Request for ExtendSelection {
+    type Params = ExtendSelectionParams;
+    type Result = ExtendSelectionResult;
+    const METHOD: &'static str = "rust-analyzer/extendSelection";
+}
+
+pub enum ShrinkSelection {}
+
+impl Request for ShrinkSelection {
+    type Params = ExtendSelectionParams;
+    type Result = ExtendSelectionResult;
+    const METHOD: &'static str = "rust-analyzer/shrinkSelection";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtendSelectionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub selections: Vec<Range>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtendSelectionResult {
+    pub selections: Vec<Range>,
+}
+
 pub enum FindMatchingBrace {}
 
 impl Request for FindMatchingBrace {
@@ -94,6 +204,28 @@ impl Request for ParentModule {
     const METHOD: &'static str = "rust-analyzer/parentModule";
 }
 
+pub enum SuperMethod {}
+
+impl Request for SuperMethod {
+    type Params = TextDocumentPositionParams;
+    type Result = Vec<Location>;
+    const METHOD: &'static str = "rust-analyzer/superMethod";
+}
+
+pub enum OpenCargoToml {}
+
+impl Request for OpenCargoToml {
+    type Params = OpenCargoTomlParams;
+    type Result = Option<Location>;
+    const METHOD: &'static str = "rust-analyzer/openCargoToml";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenCargoTomlParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
 pub enum JoinLines {}
 
 impl Request for JoinLines {
@@ -144,12 +276,116 @@ pub struct Runnable {
     pub cwd: Option<String>,
 }
 
+/// Builds the binary of a runnable, and returns how to start it under a
+/// debugger.
+pub enum DebugRunnable {}
+
+impl Request for DebugRunnable {
+    type Params = Runnable;
+    type Result = DebugConfig;
+    const METHOD: &'static str = "rust-analyzer/debugRunnable";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugConfig {
+    pub name: String,
+    /// The path of the built binary.
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: FxHashMap<String, String>,
+    pub cwd: Option<String>,
+}
+
+pub enum TestTree {}
+
+impl Request for TestTree {
+    type Params = ();
+    type Result = Vec<TestItem>;
+    const METHOD: &'static str = "rust-analyzer/testTree";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TestItem {
+    /// The URI of the crate root, followed by `#` and the path of the module
+    /// or test for items inside of the crate.
+    pub id: String,
+    pub label: String,
+    pub kind: TestItemKind,
+    pub location: Location,
+    pub children: Vec<TestItem>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TestItemKind {
+    Crate,
+    Module,
+    Test,
+}
+
+/// Runs the tests of a crate, module or single test, reporting their
+/// results with `TestResult` notifications. The response is sent once all
+/// tests have finished.
+pub enum RunTests {}
+
+impl Request for RunTests {
+    type Params = RunTestsParams;
+    type Result = TestRunSummary;
+    const METHOD: &'static str = "rust-analyzer/runTests";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RunTestsParams {
+    /// The id of a `TestItem`.
+    pub id: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TestRunSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+}
+
+pub enum TestResult {}
+
+impl Notification for TestResult {
+    type Params = TestResultParams;
+    const METHOD: &'static str = "rust-analyzer/testResult";
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TestResultParams {
+    /// The id of the test, in the same format as `TestItem::id`.
+    pub id: String,
+    pub status: TestStatus,
+    /// The captured output of a failed test.
+    pub output: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TestStatus {
+    Started,
+    Passed,
+    Failed,
+    Ignored,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SourceChange {
     pub label: String,
     pub workspace_edit: WorkspaceEdit,
     pub cursor_position: Option<TextDocumentPositionParams>,
+    /// The inserted text uses the snippet syntax, which is only the case if
+    /// the client has the `snippetTextEdit` capability.
+    pub is_snippet: bool,
 }
 
 pub enum InlayHints {}
@@ -171,6 +407,7 @@ pub enum InlayKind {
     TypeHint,
     ParameterHint,
     ChainingHint,
+    DropHint,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -180,6 +417,45 @@ pub struct InlayHint {
     pub label: String,
 }
 
+pub enum InjectedLanguages {}
+
+impl Request for InjectedLanguages {
+    type Params = InjectedLanguagesParams;
+    type Result = Vec<InjectedLanguage>;
+    const METHOD: &'static str = "rust-analyzer/injectedLanguages";
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InjectedLanguagesParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// A range written in another language, which the client can highlight with
+/// an injection grammar.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InjectedLanguage {
+    pub range: Range,
+    pub language: String,
+}
+
+pub enum SafeDelete {}
+
+impl Request for SafeDelete {
+    type Params = TextDocumentPositionParams;
+    type Result = Option<SafeDeleteResult>;
+    const METHOD: &'static str = "rust-analyzer/safeDelete";
+}
+
+/// Either the change deleting the item, or the references which keep it from
+/// being deleted.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeDeleteResult {
+    pub change: Option<SourceChange>,
+    pub blockers: Vec<Location>,
+}
+
 pub enum Ssr {}
 
 impl Request for Ssr {
@@ -193,4 +469,68 @@ impl Request for Ssr {
 pub struct SsrParams {
     pub query: String,
     pub parse_only: bool,
+    /// The position at which the paths of the query are resolved.
+    #[serde(flatten)]
+    pub position: TextDocumentPositionParams,
+}
+
+/// `textDocument/codeAction` with the `data` field of LSP 3.16 code actions,
+/// which are not yet supported by `lsp_types`. Shadows the request from
+/// `lsp_types::request`.
+pub enum CodeActionRequest {}
+
+impl Request for CodeActionRequest {
+    type Params = CodeActionParams;
+    type Result = Option<Vec<CodeAction>>;
+    const METHOD: &'static str = "textDocument/codeAction";
+}
+
+pub enum CodeActionResolveRequest {}
+
+impl Request for CodeActionResolveRequest {
+    type Params = CodeAction;
+    type Result = CodeAction;
+    const METHOD: &'static str = "codeAction/resolve";
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeAction {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<lsp_types::Command>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edit: Option<WorkspaceEdit>,
+    /// Set for the assists whose `edit` is computed lazily by
+    /// `codeAction/resolve`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<CodeActionData>,
+}
+
+impl From<lsp_types::CodeActionOrCommand> for CodeAction {
+    fn from(action: lsp_types::CodeActionOrCommand) -> CodeAction {
+        match action {
+            lsp_types::CodeActionOrCommand::Command(command) => CodeAction {
+                title: command.title.clone(),
+                command: Some(command),
+                ..CodeAction::default()
+            },
+            lsp_types::CodeActionOrCommand::CodeAction(action) => CodeAction {
+                title: action.title,
+                kind: action.kind,
+                command: action.command,
+                edit: action.edit,
+                data: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeActionData {
+    pub code_action_params: CodeActionParams,
+    pub id: String,
 }
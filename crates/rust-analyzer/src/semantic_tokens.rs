@@ -1,12 +1,20 @@
 //! Semantic Tokens helpers
 
-use std::ops;
+use std::{
+    ops,
+    sync::atomic::{AtomicU32, Ordering},
+};
 
-use lsp_types::{Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens};
+use lsp_types::{
+    Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens,
+    SemanticTokensEdit,
+};
 
 pub(crate) const ATTRIBUTE: SemanticTokenType = SemanticTokenType::new("attribute");
 pub(crate) const BUILTIN_TYPE: SemanticTokenType = SemanticTokenType::new("builtinType");
 pub(crate) const ENUM_MEMBER: SemanticTokenType = SemanticTokenType::new("enumMember");
+pub(crate) const ESCAPE_SEQUENCE: SemanticTokenType = SemanticTokenType::new("escapeSequence");
+pub(crate) const FORMAT_SPECIFIER: SemanticTokenType = SemanticTokenType::new("formatSpecifier");
 pub(crate) const LIFETIME: SemanticTokenType = SemanticTokenType::new("lifetime");
 pub(crate) const TYPE_ALIAS: SemanticTokenType = SemanticTokenType::new("typeAlias");
 pub(crate) const UNION: SemanticTokenType = SemanticTokenType::new("union");
@@ -15,6 +23,8 @@ pub(crate) const CONSTANT: SemanticTokenModifier = SemanticTokenModifier::new("c
 pub(crate) const CONTROL_FLOW: SemanticTokenModifier = SemanticTokenModifier::new("controlFlow");
 pub(crate) const MUTABLE: SemanticTokenModifier = SemanticTokenModifier::new("mutable");
 pub(crate) const UNSAFE: SemanticTokenModifier = SemanticTokenModifier::new("unsafe");
+pub(crate) const MACRO_EXPANSION: SemanticTokenModifier =
+    SemanticTokenModifier::new("macroExpansion");
 
 pub(crate) const SUPPORTED_TYPES: &[SemanticTokenType] = &[
     SemanticTokenType::COMMENT,
@@ -40,6 +50,8 @@ pub(crate) const SUPPORTED_TYPES: &[SemanticTokenType] = &[
     ATTRIBUTE,
     BUILTIN_TYPE,
     ENUM_MEMBER,
+    ESCAPE_SEQUENCE,
+    FORMAT_SPECIFIER,
     LIFETIME,
     TYPE_ALIAS,
     UNION,
@@ -57,6 +69,7 @@ pub(crate) const SUPPORTED_MODIFIERS: &[SemanticTokenModifier] = &[
     MUTABLE,
     UNSAFE,
     CONTROL_FLOW,
+    MACRO_EXPANSION,
 ];
 
 #[derive(Default)]
@@ -74,12 +87,19 @@ impl ops::BitOrAssign<SemanticTokenModifier> for ModifierSet {
 /// This is a direct port of https://github.com/microsoft/vscode-languageserver-node/blob/f425af9de46a0187adb78ec8a46b9b2ce80c5412/server/src/sematicTokens.proposed.ts#L45
 #[derive(Default)]
 pub(crate) struct SemanticTokensBuilder {
+    result_id: Option<String>,
     prev_line: u32,
     prev_char: u32,
     data: Vec<SemanticToken>,
 }
 
 impl SemanticTokensBuilder {
+    /// Creates a builder for tokens which the client can later request a
+    /// delta against.
+    pub fn with_result_id(result_id: String) -> SemanticTokensBuilder {
+        SemanticTokensBuilder { result_id: Some(result_id), ..SemanticTokensBuilder::default() }
+    }
+
     /// Push a new token onto the builder
     pub fn push(&mut self, range: Range, token_index: u32, modifier_bitset: u32) {
         let mut push_line = range.start.line as u32;
@@ -110,10 +130,80 @@ impl SemanticTokensBuilder {
     }
 
     pub fn build(self) -> SemanticTokens {
-        SemanticTokens { result_id: None, data: self.data }
+        SemanticTokens { result_id: self.result_id, data: self.data }
     }
 }
 
+pub fn next_result_id() -> String {
+    static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+    NEXT_ID.fetch_add(1, Ordering::SeqCst).to_string()
+}
+
+/// Computes the edit which turns `old` into `new`.
+///
+/// Tokens are compared as a whole, so the edit only covers the tokens between
+/// the longest common prefix and suffix. Note that `start` and `delete_count`
+/// are indices into the flattened array of integers, where each token takes up
+/// five elements.
+pub fn diff_tokens(old: &[SemanticToken], new: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix = old.iter().zip(new.iter()).take_while(|(old, new)| old == new).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old.iter().rev().zip(new.iter().rev()).take_while(|(old, new)| old == new).count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    if old.is_empty() && new.is_empty() {
+        return Vec::new();
+    }
+    vec![SemanticTokensEdit {
+        start: 5 * prefix as u32,
+        delete_count: 5 * old.len() as u32,
+        data: Some(new.to_vec()),
+    }]
+}
+
 pub fn type_index(type_: SemanticTokenType) -> u32 {
     SUPPORTED_TYPES.iter().position(|it| *it == type_).unwrap() as u32
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(delta_line: u32, delta_start: u32) -> SemanticToken {
+        SemanticToken {
+            delta_line,
+            delta_start,
+            length: 1,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    #[test]
+    fn diff_of_equal_tokens_is_empty() {
+        let tokens = vec![token(0, 1), token(1, 2)];
+        assert!(diff_tokens(&tokens, &tokens).is_empty());
+    }
+
+    #[test]
+    fn diff_covers_changed_tokens_only() {
+        let old = vec![token(0, 1), token(1, 2), token(1, 3), token(2, 0)];
+        let new = vec![token(0, 1), token(1, 5), token(2, 0)];
+        let edits = diff_tokens(&old, &new);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].start, 5);
+        assert_eq!(edits[0].delete_count, 10);
+        assert_eq!(edits[0].data, Some(vec![token(1, 5)]));
+    }
+
+    #[test]
+    fn diff_of_appended_tokens() {
+        let old = vec![token(0, 1)];
+        let new = vec![token(0, 1), token(1, 2)];
+        let edits = diff_tokens(&old, &new);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].start, 5);
+        assert_eq!(edits[0].delete_count, 0);
+        assert_eq!(edits[0].data, Some(vec![token(1, 2)]));
+    }
+}
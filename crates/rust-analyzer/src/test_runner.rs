@@ -0,0 +1,160 @@
+//! Runs tests with `cargo test` and turns libtest's JSON output into
+//! `TestResult` notifications.
+//!
+//! libtest only prints JSON with `-Z unstable-options --format json`, so
+//! this requires a nightly toolchain.
+
+use std::{
+    env,
+    io::{BufRead, BufReader, Read},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    req::{TestResultParams, TestRunSummary, TestStatus},
+    Result,
+};
+
+/// A `cargo test` invocation for a single crate.
+#[derive(Debug)]
+pub(crate) struct TestRun {
+    /// The id of the crate's `TestItem`, which prefixes the ids of its tests.
+    pub(crate) crate_id: String,
+    pub(crate) args: Vec<String>,
+    /// Arguments for the test binary, passed after `--`.
+    pub(crate) extra_args: Vec<String>,
+    pub(crate) cwd: Option<PathBuf>,
+}
+
+impl TestRun {
+    /// Runs the tests, blocking until all of them have finished.
+    pub(crate) fn run(
+        &self,
+        on_result: &mut dyn FnMut(TestResultParams),
+    ) -> Result<TestRunSummary> {
+        let mut cmd = Command::new(cargo_binary());
+        cmd.args(&self.args).arg("--").args(&self.extra_args);
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        let mut child =
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).stdin(Stdio::null()).spawn()?;
+        let stderr = read_stderr(&mut child);
+
+        // Cargo's own messages are interleaved with libtest's ones, and are
+        // skipped like any other line which isn't a test event.
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut summary = TestRunSummary::default();
+        for line in stdout.lines() {
+            let result = match parse_test_result(&self.crate_id, &line?) {
+                Some(it) => it,
+                None => continue,
+            };
+            match result.status {
+                TestStatus::Started => (),
+                TestStatus::Passed => summary.passed += 1,
+                TestStatus::Failed => summary.failed += 1,
+                TestStatus::Ignored => summary.ignored += 1,
+            }
+            on_result(result);
+        }
+
+        let exit_status = child.wait()?;
+        let stderr = stderr.join();
+        // Failing tests make cargo fail as well, so only a run without any
+        // results is an error.
+        if !exit_status.success() && summary == TestRunSummary::default() {
+            return Err(
+                format!("`cargo test` failed ({}): {:?}\n{}", exit_status, cmd, stderr).into()
+            );
+        }
+        if !stderr.is_empty() {
+            log::debug!("`cargo test` stderr:\n{}", stderr);
+        }
+        Ok(summary)
+    }
+}
+
+#[derive(Deserialize)]
+struct LibtestMessage {
+    #[serde(rename = "type")]
+    kind: String,
+    event: String,
+    name: Option<String>,
+    stdout: Option<String>,
+}
+
+fn parse_test_result(crate_id: &str, line: &str) -> Option<TestResultParams> {
+    let msg = serde_json::from_str::<LibtestMessage>(line).ok()?;
+    if msg.kind != "test" {
+        return None;
+    }
+    let status = match msg.event.as_str() {
+        "started" => TestStatus::Started,
+        "ok" => TestStatus::Passed,
+        "failed" => TestStatus::Failed,
+        "ignored" => TestStatus::Ignored,
+        // `timeout` only warns about a test which is still running.
+        _ => return None,
+    };
+    let output = if status == TestStatus::Failed { msg.stdout } else { None };
+    Some(TestResultParams { id: format!("{}#{}", crate_id, msg.name?), status, output })
+}
+
+/// Reads the stderr of the child on a separate thread, so that neither of
+/// the pipes fills up while the other one is being read.
+pub(crate) fn read_stderr(child: &mut Child) -> jod_thread::JoinHandle<String> {
+    let mut stderr = child.stderr.take().unwrap();
+    jod_thread::spawn(move || {
+        let mut res = String::new();
+        if let Err(err) = stderr.read_to_string(&mut res) {
+            log::error!("failed to read the stderr of cargo: {}", err);
+        }
+        res
+    })
+}
+
+fn cargo_binary() -> String {
+    env::var("CARGO").unwrap_or_else(|_| "cargo".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_test_result() {
+        let crate_id = "file:///foo/src/lib.rs";
+        let results: Vec<TestResultParams> = [
+            r#"{"reason":"compiler-artifact","package_id":"foo 0.1.0 (path+file:///foo)"}"#,
+            r#"{ "type": "suite", "event": "started", "test_count": 3 }"#,
+            r#"{ "type": "test", "event": "started", "name": "tests::bar" }"#,
+            r#"{ "type": "test", "name": "tests::bar", "event": "ok" }"#,
+            r#"{ "type": "test", "name": "tests::baz", "event": "failed", "stdout": "panicked\n" }"#,
+            r#"{ "type": "test", "name": "quux", "event": "ignored" }"#,
+            r#"{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 1 }"#,
+            "running 3 tests",
+        ]
+        .iter()
+        .filter_map(|line| parse_test_result(crate_id, line))
+        .collect();
+
+        let result = |name: &str, status, output: Option<&str>| TestResultParams {
+            id: format!("{}#{}", crate_id, name),
+            status,
+            output: output.map(|it| it.to_string()),
+        };
+        assert_eq!(
+            results,
+            vec![
+                result("tests::bar", TestStatus::Started, None),
+                result("tests::bar", TestStatus::Passed, None),
+                result("tests::baz", TestStatus::Failed, Some("panicked\n")),
+                result("quux", TestStatus::Ignored, None),
+            ]
+        );
+    }
+}
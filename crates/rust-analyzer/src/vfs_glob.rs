@@ -3,7 +3,8 @@
 //! By default, we include only `.rs` files, and skip some know offenders like
 //! `/target` or `/node_modules` altogether.
 //!
-//! It's also possible to add custom exclusion globs.
+//! It's also possible to add custom exclusion globs, and inclusion globs for
+//! the directories which are skipped by default. Custom exclusions win.
 
 use globset::{GlobSet, GlobSetBuilder};
 use ra_vfs::{Filter, RelativePath};
@@ -15,12 +16,17 @@ const IGNORED_FOR_NON_MEMBERS: &[&str] = &["examples/**", "tests/**", "benches/*
 
 pub struct RustPackageFilterBuilder {
     is_member: bool,
+    include: GlobSetBuilder,
     exclude: GlobSetBuilder,
 }
 
 impl Default for RustPackageFilterBuilder {
     fn default() -> RustPackageFilterBuilder {
-        RustPackageFilterBuilder { is_member: false, exclude: GlobSetBuilder::new() }
+        RustPackageFilterBuilder {
+            is_member: false,
+            include: GlobSetBuilder::new(),
+            exclude: GlobSetBuilder::new(),
+        }
     }
 }
 
@@ -30,6 +36,13 @@ impl RustPackageFilterBuilder {
         self
     }
 
+    pub fn include(mut self, globs: impl IntoIterator<Item = Glob>) -> RustPackageFilterBuilder {
+        for glob in globs.into_iter() {
+            self.include.add(glob);
+        }
+        self
+    }
+
     pub fn exclude(mut self, globs: impl IntoIterator<Item = Glob>) -> RustPackageFilterBuilder {
         for glob in globs.into_iter() {
             self.exclude.add(glob);
@@ -38,26 +51,38 @@ impl RustPackageFilterBuilder {
     }
 
     pub fn into_vfs_filter(self) -> Box<dyn Filter> {
-        let RustPackageFilterBuilder { is_member, mut exclude } = self;
+        let RustPackageFilterBuilder { is_member, include, exclude } = self;
+        let mut ignore = GlobSetBuilder::new();
         for &glob in ALWAYS_IGNORED {
-            exclude.add(Glob::new(glob).unwrap());
+            ignore.add(Glob::new(glob).unwrap());
         }
         if !is_member {
             for &glob in IGNORED_FOR_NON_MEMBERS {
-                exclude.add(Glob::new(glob).unwrap());
+                ignore.add(Glob::new(glob).unwrap());
             }
         }
-        Box::new(RustPackageFilter { exclude: exclude.build().unwrap() })
+        Box::new(RustPackageFilter {
+            include: include.build().unwrap(),
+            exclude: exclude.build().unwrap(),
+            ignore: ignore.build().unwrap(),
+        })
     }
 }
 
 struct RustPackageFilter {
+    include: GlobSet,
     exclude: GlobSet,
+    /// The directories skipped by default.
+    ignore: GlobSet,
 }
 
 impl Filter for RustPackageFilter {
     fn include_dir(&self, dir_path: &RelativePath) -> bool {
-        !self.exclude.is_match(dir_path.as_str())
+        let dir_path = dir_path.as_str();
+        if self.exclude.is_match(dir_path) {
+            return false;
+        }
+        self.include.is_match(dir_path) || !self.ignore.is_match(dir_path)
     }
 
     fn include_file(&self, file_path: &RelativePath) -> bool {
@@ -95,4 +120,16 @@ fn test_globs() {
         .into_vfs_filter();
 
     assert!(!filter.include_dir(RelativePath::new("src/llvm-project/clang")));
+
+    let filter = RustPackageFilterBuilder::default()
+        .set_member(false)
+        .include(std::iter::once(Glob::new("tests/**").unwrap()))
+        .exclude(std::iter::once(Glob::new("tests/data/**").unwrap()))
+        .into_vfs_filter();
+
+    assert!(filter.include_dir(RelativePath::new("tests")));
+    assert!(filter.include_dir(RelativePath::new("tests/ui")));
+    assert!(!filter.include_dir(RelativePath::new("tests/data")));
+    assert!(!filter.include_dir(RelativePath::new("target/debug")));
+    assert!(!filter.include_dir(RelativePath::new("benches")));
 }
@@ -9,11 +9,11 @@ use std::{
 };
 
 use crossbeam_channel::{unbounded, Receiver};
-use lsp_types::Url;
-use parking_lot::RwLock;
+use lsp_types::{SemanticTokens, Url};
+use parking_lot::{Mutex, RwLock};
 use ra_flycheck::{url_from_path_with_drive_lowercasing, Flycheck, FlycheckConfig};
 use ra_ide::{
-    Analysis, AnalysisChange, AnalysisHost, CrateGraph, FileId, LibraryData, SourceRootId,
+    Analysis, AnalysisChange, AnalysisHost, CrateGraph, FileId, SourceRootId, SymbolCache,
 };
 use ra_project_model::{get_rustc_cfg_options, ProcMacroClient, ProjectWorkspace};
 use ra_vfs::{LineEndings, RootEntry, Vfs, VfsChange, VfsFile, VfsRoot, VfsTask, Watch};
@@ -22,14 +22,31 @@ use stdx::format_to;
 
 use crate::{
     config::Config,
+    conv::LineIndex,
     diagnostics::{CheckFixes, DiagnosticCollection},
     main_loop::pending_requests::{CompletedRequest, LatestRequests},
+    req,
     vfs_glob::{Glob, RustPackageFilterBuilder},
     LspError, Result,
 };
-use ra_db::ExternSourceId;
+use ra_db::{ExternSourceId, SourceDatabase};
 use rustc_hash::{FxHashMap, FxHashSet};
 
+fn symbol_cache(config: &Config) -> Option<SymbolCache> {
+    let dir = config.cache_dir.as_ref()?;
+    Some(SymbolCache::new(dir.join("symbols"), build_id()?))
+}
+
+/// Identifies the build of the server for the caches. The git revision alone
+/// doesn't, as dev builds can have local changes or no revision at all, so
+/// the modification time of the executable is added to it.
+fn build_id() -> Option<String> {
+    let exe = std::env::current_exe().ok()?;
+    let modified = std::fs::metadata(exe).ok()?.modified().ok()?;
+    let modified = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(format!("{}-{}", env!("REV"), modified.as_nanos()))
+}
+
 fn create_flycheck(workspaces: &[ProjectWorkspace], config: &FlycheckConfig) -> Option<Flycheck> {
     // FIXME: Figure out the multi-workspace situation
     workspaces
@@ -48,6 +65,105 @@ fn create_flycheck(workspaces: &[ProjectWorkspace], config: &FlycheckConfig) ->
         })
 }
 
+fn create_crate_graph(
+    workspaces: &[ProjectWorkspace],
+    vfs: &mut Vfs,
+    config: &Config,
+    loading_errors: &mut Vec<String>,
+) -> CrateGraph {
+    let extern_dirs: FxHashSet<_> =
+        workspaces.iter().flat_map(ProjectWorkspace::out_dirs).collect();
+    let mut extern_source_roots = FxHashMap::default();
+    for r in (0..vfs.n_roots()).map(|it| VfsRoot(it as u32)) {
+        let vfs_root_path = vfs.root2path(r);
+        // FIXME: add path2root in vfs to simpily this logic
+        if extern_dirs.contains(&vfs_root_path) {
+            extern_source_roots.insert(vfs_root_path, ExternSourceId(r.0));
+        }
+    }
+
+    // FIXME: Read default cfgs from config
+    let default_cfg_options = {
+        let mut opts = get_rustc_cfg_options(config.cargo.target.as_deref());
+        opts.insert_atom("debug_assertion".into());
+        opts
+    };
+
+    // Create crate graph from all the workspaces
+    let mut crate_graph = CrateGraph::default();
+    let mut load = |path: &std::path::Path| {
+        // Some path from metadata will be non canonicalized, e.g. /foo/../bar/lib.rs
+        let path = path.canonicalize().ok()?;
+        let vfs_file = vfs.load(&path);
+        vfs_file.map(|f| FileId(f.0))
+    };
+
+    let proc_macro_client = match &config.proc_macro_srv {
+        None => ProcMacroClient::dummy(),
+        Some(srv) => {
+            let path = Path::new(&srv);
+            match ProcMacroClient::extern_process(path) {
+                Ok(it) => it,
+                Err(err) => {
+                    log::error!(
+                        "Fail to run ra_proc_macro_srv from path {}, error : {}",
+                        path.to_string_lossy(),
+                        err
+                    );
+                    loading_errors.push(format!(
+                        "failed to run the proc-macro server {}: {}",
+                        path.display(),
+                        err
+                    ));
+                    ProcMacroClient::dummy()
+                }
+            }
+        }
+    };
+
+    workspaces
+        .iter()
+        .map(|ws| {
+            ws.to_crate_graph(
+                &default_cfg_options,
+                &extern_source_roots,
+                &proc_macro_client,
+                &mut load,
+            )
+        })
+        .for_each(|graph| {
+            crate_graph.extend(graph);
+        });
+    crate_graph
+}
+
+/// Returns the VFS roots for the workspace folders, the packages and their
+/// `OUT_DIR`s.
+fn root_entries(
+    folder_roots: &[PathBuf],
+    workspaces: &[ProjectWorkspace],
+    include_globs: &[Glob],
+    exclude_globs: &[Glob],
+) -> Vec<RootEntry> {
+    let extern_dirs: FxHashSet<_> =
+        workspaces.iter().flat_map(ProjectWorkspace::out_dirs).collect();
+    let create_filter = |is_member| {
+        RustPackageFilterBuilder::default()
+            .set_member(is_member)
+            .include(include_globs.iter().cloned())
+            .exclude(exclude_globs.iter().cloned())
+            .into_vfs_filter()
+    };
+    folder_roots
+        .iter()
+        .map(|path| RootEntry::new(path.clone(), create_filter(true)))
+        .chain(workspaces.iter().flat_map(ProjectWorkspace::to_roots).map(|pkg_root| {
+            RootEntry::new(pkg_root.path().to_owned(), create_filter(pkg_root.is_member()))
+        }))
+        .chain(extern_dirs.iter().map(|path| RootEntry::new(path.to_owned(), create_filter(false))))
+        .collect()
+}
+
 /// `WorldState` is the primary mutable state of the language server
 ///
 /// The most interesting components are `vfs`, which stores a consistent
@@ -64,6 +180,14 @@ pub struct WorldState {
     pub latest_requests: Arc<RwLock<LatestRequests>>,
     pub flycheck: Option<Flycheck>,
     pub diagnostics: DiagnosticCollection,
+    /// The last semantic tokens sent for each file, used to compute deltas.
+    pub semantic_tokens_cache: Arc<Mutex<FxHashMap<Url, SemanticTokens>>>,
+    /// Errors from discovering the workspaces and building the crate graph.
+    pub loading_errors: Vec<String>,
+    /// The last status reported to the client, see `req::ServerStatus`.
+    pub status: Arc<req::ServerStatus>,
+    include_globs: Vec<Glob>,
+    exclude_globs: Vec<Glob>,
 }
 
 /// An immutable snapshot of the world's state at a point in time.
@@ -73,6 +197,8 @@ pub struct WorldSnapshot {
     pub analysis: Analysis,
     pub latest_requests: Arc<RwLock<LatestRequests>>,
     pub check_fixes: CheckFixes,
+    pub semantic_tokens_cache: Arc<Mutex<FxHashMap<Url, SemanticTokens>>>,
+    pub status: Arc<req::ServerStatus>,
     vfs: Arc<RwLock<Vfs>>,
 }
 
@@ -81,102 +207,34 @@ impl WorldState {
         folder_roots: Vec<PathBuf>,
         workspaces: Vec<ProjectWorkspace>,
         lru_capacity: Option<usize>,
+        include_globs: &[Glob],
         exclude_globs: &[Glob],
         watch: Watch,
         config: Config,
+        mut loading_errors: Vec<String>,
     ) -> WorldState {
         let mut change = AnalysisChange::new();
 
-        let extern_dirs: FxHashSet<_> =
-            workspaces.iter().flat_map(ProjectWorkspace::out_dirs).collect();
-
-        let roots: Vec<_> = {
-            let create_filter = |is_member| {
-                RustPackageFilterBuilder::default()
-                    .set_member(is_member)
-                    .exclude(exclude_globs.iter().cloned())
-                    .into_vfs_filter()
-            };
-            folder_roots
-                .iter()
-                .map(|path| RootEntry::new(path.clone(), create_filter(true)))
-                .chain(workspaces.iter().flat_map(ProjectWorkspace::to_roots).map(|pkg_root| {
-                    RootEntry::new(pkg_root.path().to_owned(), create_filter(pkg_root.is_member()))
-                }))
-                .chain(
-                    extern_dirs
-                        .iter()
-                        .map(|path| RootEntry::new(path.to_owned(), create_filter(false))),
-                )
-                .collect()
-        };
+        let roots = root_entries(&folder_roots, &workspaces, include_globs, exclude_globs);
 
         let (task_sender, task_receiver) = unbounded();
         let task_sender = Box::new(move |t| task_sender.send(t).unwrap());
         let (mut vfs, vfs_roots) = Vfs::new(roots, task_sender, watch);
 
-        let mut extern_source_roots = FxHashMap::default();
         for r in vfs_roots {
             let vfs_root_path = vfs.root2path(r);
             let is_local = folder_roots.iter().any(|it| vfs_root_path.starts_with(it));
             change.add_root(SourceRootId(r.0), is_local);
             change.set_debug_root_path(SourceRootId(r.0), vfs_root_path.display().to_string());
-
-            // FIXME: add path2root in vfs to simpily this logic
-            if extern_dirs.contains(&vfs_root_path) {
-                extern_source_roots.insert(vfs_root_path, ExternSourceId(r.0));
-            }
         }
 
-        // FIXME: Read default cfgs from config
-        let default_cfg_options = {
-            let mut opts = get_rustc_cfg_options();
-            opts.insert_atom("test".into());
-            opts.insert_atom("debug_assertion".into());
-            opts
-        };
-
-        // Create crate graph from all the workspaces
-        let mut crate_graph = CrateGraph::default();
-        let mut load = |path: &std::path::Path| {
-            // Some path from metadata will be non canonicalized, e.g. /foo/../bar/lib.rs
-            let path = path.canonicalize().ok()?;
-            let vfs_file = vfs.load(&path);
-            vfs_file.map(|f| FileId(f.0))
-        };
-
-        let proc_macro_client = match &config.proc_macro_srv {
-            None => ProcMacroClient::dummy(),
-            Some(srv) => {
-                let path = Path::new(&srv);
-                match ProcMacroClient::extern_process(path) {
-                    Ok(it) => it,
-                    Err(err) => {
-                        log::error!(
-                            "Fail to run ra_proc_macro_srv from path {}, error : {}",
-                            path.to_string_lossy(),
-                            err
-                        );
-                        ProcMacroClient::dummy()
-                    }
-                }
-            }
-        };
-
-        workspaces
-            .iter()
-            .map(|ws| {
-                ws.to_crate_graph(
-                    &default_cfg_options,
-                    &extern_source_roots,
-                    &proc_macro_client,
-                    &mut load,
-                )
-            })
-            .for_each(|graph| {
-                crate_graph.extend(graph);
-            });
-        change.set_crate_graph(crate_graph);
+        change.set_crate_graph(create_crate_graph(
+            &workspaces,
+            &mut vfs,
+            &config,
+            &mut loading_errors,
+        ));
+        change.set_symbol_cache(symbol_cache(&config));
 
         let flycheck = config.check.as_ref().and_then(|c| create_flycheck(&workspaces, c));
 
@@ -192,47 +250,96 @@ impl WorldState {
             latest_requests: Default::default(),
             flycheck,
             diagnostics: Default::default(),
+            semantic_tokens_cache: Default::default(),
+            loading_errors,
+            status: Default::default(),
+            include_globs: include_globs.to_vec(),
+            exclude_globs: exclude_globs.to_vec(),
         }
     }
 
-    pub fn update_configuration(&mut self, config: Config) {
+    /// Applies the new `config`, returning `true` if the workspaces should be
+    /// reloaded for it to take effect.
+    pub fn update_configuration(&mut self, config: Config) -> bool {
         self.analysis_host.update_lru_capacity(config.lru_capacity);
+        if config.cache_dir != self.config.cache_dir {
+            let mut change = AnalysisChange::new();
+            change.set_symbol_cache(symbol_cache(&config));
+            self.analysis_host.apply_change(change);
+        }
         if config.check != self.config.check {
             self.flycheck =
                 config.check.as_ref().and_then(|it| create_flycheck(&self.workspaces, it));
         }
+        let reload_workspaces = config.cargo != self.config.cargo
+            || config.with_sysroot != self.config.with_sysroot
+            || config.proc_macro_srv != self.config.proc_macro_srv;
 
         self.config = config;
+        reload_workspaces
     }
 
-    /// Returns a vec of libraries
-    /// FIXME: better API here
-    pub fn process_changes(
+    /// Replaces the crate graph with the one of the reloaded `workspaces`.
+    ///
+    /// The roots of the new workspace folders and packages are added to the
+    /// VFS, and the ones which are no longer used, like the roots of the
+    /// removed workspace folders, are unloaded.
+    pub fn reload_workspaces(
         &mut self,
-        roots_scanned: &mut usize,
-    ) -> Option<Vec<(SourceRootId, Vec<(FileId, RelativePathBuf, Arc<String>)>)>> {
+        workspaces: Vec<ProjectWorkspace>,
+        mut loading_errors: Vec<String>,
+    ) {
+        let mut change = AnalysisChange::new();
+        let roots =
+            root_entries(&self.roots, &workspaces, &self.include_globs, &self.exclude_globs);
+        let mut vfs = self.vfs.write();
+        vfs.retain_roots(&roots);
+        for r in vfs.add_roots(roots) {
+            let vfs_root_path = vfs.root2path(r);
+            let is_local = self.roots.iter().any(|it| vfs_root_path.starts_with(it));
+            change.add_root(SourceRootId(r.0), is_local);
+            change.set_debug_root_path(SourceRootId(r.0), vfs_root_path.display().to_string());
+        }
+        let crate_graph =
+            create_crate_graph(&workspaces, &mut vfs, &self.config, &mut loading_errors);
+        drop(vfs);
+        // Setting an equal crate graph would still invalidate all the
+        // analysis, e.g. when only a comment in `Cargo.toml` was changed.
+        if *self.analysis_host.raw_database().crate_graph() != crate_graph {
+            change.set_crate_graph(crate_graph);
+        }
+
+        self.analysis_host.apply_change(change);
+        self.flycheck = self.config.check.as_ref().and_then(|it| create_flycheck(&workspaces, it));
+        self.workspaces = Arc::new(workspaces);
+        self.loading_errors = loading_errors;
+    }
+
+    /// Updates the workspace folders, the workspaces should be reloaded
+    /// afterwards.
+    pub fn change_workspace_folders(&mut self, added: Vec<PathBuf>, removed: Vec<PathBuf>) {
+        self.roots.retain(|it| !removed.contains(it));
+        for path in added {
+            if !self.roots.contains(&path) {
+                self.roots.push(path);
+            }
+        }
+    }
+
+    /// Applies the changes of the VFS to the analysis, returning `false` if
+    /// there were none.
+    pub fn process_changes(&mut self, roots_scanned: &mut usize) -> bool {
         let changes = self.vfs.write().commit_changes();
         if changes.is_empty() {
-            return None;
+            return false;
         }
-        let mut libs = Vec::new();
         let mut change = AnalysisChange::new();
         for c in changes {
             match c {
                 VfsChange::AddRoot { root, files } => {
-                    let root_path = self.vfs.read().root2path(root);
-                    let is_local = self.roots.iter().any(|r| root_path.starts_with(r));
-                    if is_local {
-                        *roots_scanned += 1;
-                        for (file, path, text) in files {
-                            change.add_file(SourceRootId(root.0), FileId(file.0), path, text);
-                        }
-                    } else {
-                        let files = files
-                            .into_iter()
-                            .map(|(vfsfile, path, text)| (FileId(vfsfile.0), path, text))
-                            .collect();
-                        libs.push((SourceRootId(root.0), files));
+                    *roots_scanned += 1;
+                    for (file, path, text) in files {
+                        change.add_file(SourceRootId(root.0), FileId(file.0), path, text);
                     }
                 }
                 VfsChange::AddFile { root, file, path, text } => {
@@ -247,13 +354,7 @@ impl WorldState {
             }
         }
         self.analysis_host.apply_change(change);
-        Some(libs)
-    }
-
-    pub fn add_lib(&mut self, data: LibraryData) {
-        let mut change = AnalysisChange::new();
-        change.add_library(data);
-        self.analysis_host.apply_change(change);
+        true
     }
 
     pub fn snapshot(&self) -> WorldSnapshot {
@@ -264,6 +365,8 @@ impl WorldState {
             vfs: Arc::clone(&self.vfs),
             latest_requests: Arc::clone(&self.latest_requests),
             check_fixes: Arc::clone(&self.diagnostics.check_fixes),
+            semantic_tokens_cache: Arc::clone(&self.semantic_tokens_cache),
+            status: Arc::clone(&self.status),
         }
     }
 
@@ -275,6 +378,18 @@ impl WorldState {
         self.analysis_host.collect_garbage()
     }
 
+    /// Measures the memory used by each query by evicting its values, the
+    /// numbers are only available with the `jemalloc` feature.
+    pub fn memory_usage(&mut self) -> String {
+        let mut mem = self.analysis_host.per_query_memory_usage();
+        mem.push(("Remaining".to_string(), ra_prof::memory_usage().allocated));
+        let mut buf = String::new();
+        for (name, bytes) in mem {
+            format_to!(buf, "{:>8} {}\n", bytes.to_string(), name);
+        }
+        buf
+    }
+
     pub fn complete_request(&mut self, request: CompletedRequest) {
         self.latest_requests.write().record(request)
     }
@@ -309,6 +424,11 @@ impl WorldSnapshot {
         self.vfs.read().file2path(VfsFile(id.0))
     }
 
+    pub(crate) fn file_line_index(&self, file_id: FileId) -> Result<LineIndex> {
+        let index = self.analysis.file_line_index(file_id)?;
+        Ok(LineIndex { index, encoding: self.config.client_caps.offset_encoding })
+    }
+
     pub fn file_line_endings(&self, id: FileId) -> LineEndings {
         self.vfs.read().file_line_endings(VfsFile(id.0))
     }
@@ -331,6 +451,12 @@ impl WorldSnapshot {
                 format_to!(buf, "{} packages loaded\n", w.n_packages());
             }
         }
+        if !self.status.loading_errors.is_empty() {
+            buf.push_str("\nloading errors:\n");
+            for error in self.status.loading_errors.iter() {
+                format_to!(buf, "{}\n", error);
+            }
+        }
         buf.push_str("\nanalysis:\n");
         buf.push_str(
             &self
@@ -3,13 +3,13 @@ mod support;
 use std::{collections::HashMap, time::Instant};
 
 use lsp_types::{
-    CodeActionContext, DidOpenTextDocumentParams, DocumentFormattingParams, FormattingOptions,
-    PartialResultParams, Position, Range, TextDocumentItem, TextDocumentPositionParams,
-    WorkDoneProgressParams,
+    CodeActionContext, DidOpenTextDocumentParams, DocumentFormattingParams,
+    DocumentRangeFormattingParams, FormattingOptions, PartialResultParams, Position, Range,
+    TextDocumentItem, TextDocumentPositionParams, WorkDoneProgressParams,
 };
 use rust_analyzer::req::{
     CodeActionParams, CodeActionRequest, Completion, CompletionParams, DidOpenTextDocument,
-    Formatting, GotoDefinition, OnEnter, Runnables, RunnablesParams,
+    Formatting, GotoDefinition, OnEnter, RangeFormatting, Runnables, RunnablesParams,
 };
 use serde_json::json;
 use tempfile::TempDir;
@@ -224,12 +224,20 @@ pub use std::collections::HashMap;
         },
         json!([
             {
-                "newText": r#"mod bar;
-
-fn main() {}
-
-pub use std::collections::HashMap;
-"#,
+                "newText": "fn main() {}\n",
+                "range": {
+                    "end": {
+                        "character": 0,
+                        "line": 4
+                    },
+                    "start": {
+                        "character": 0,
+                        "line": 2
+                    }
+                }
+            },
+            {
+                "newText": "",
                 "range": {
                     "end": {
                         "character": 0,
@@ -237,7 +245,7 @@ pub use std::collections::HashMap;
                     },
                     "start": {
                         "character": 0,
-                        "line": 0
+                        "line": 6
                     }
                 }
             }
@@ -288,22 +296,96 @@ pub use std::collections::HashMap;
         },
         json!([
             {
-                "newText": r#"mod bar;
+                "newText": "async fn test() {}\n",
+                "range": {
+                    "end": {
+                        "character": 0,
+                        "line": 4
+                    },
+                    "start": {
+                        "character": 0,
+                        "line": 2
+                    }
+                }
+            },
+            {
+                "newText": "fn main() {}\n",
+                "range": {
+                    "end": {
+                        "character": 0,
+                        "line": 7
+                    },
+                    "start": {
+                        "character": 0,
+                        "line": 5
+                    }
+                }
+            },
+            {
+                "newText": "",
+                "range": {
+                    "end": {
+                        "character": 0,
+                        "line": 10
+                    },
+                    "start": {
+                        "character": 0,
+                        "line": 9
+                    }
+                }
+            }
+        ]),
+    );
+}
 
-async fn test() {}
+#[test]
+fn test_format_range() {
+    if skip_slow_tests() {
+        return;
+    }
 
-fn main() {}
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
 
-pub use std::collections::HashMap;
+//- src/lib.rs
+fn foo() {
+}
+
+fn main() {
+}
 "#,
+    );
+    server.wait_until_workspace_is_loaded();
+
+    server.request::<RangeFormatting>(
+        DocumentRangeFormattingParams {
+            text_document: server.doc_id("src/lib.rs"),
+            range: Range::new(Position::new(3, 0), Position::new(4, 1)),
+            options: FormattingOptions {
+                tab_size: 4,
+                insert_spaces: false,
+                properties: HashMap::new(),
+                insert_final_newline: None,
+                trim_final_newlines: None,
+                trim_trailing_whitespace: None,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        },
+        json!([
+            {
+                "newText": "fn main() {}\n",
                 "range": {
                     "end": {
                         "character": 0,
-                        "line": 10
+                        "line": 5
                     },
                     "start": {
                         "character": 0,
-                        "line": 0
+                        "line": 3
                     }
                 }
             }
@@ -346,6 +428,7 @@ fn main() {}
               "arguments": [
                 {
                   "cursorPosition": null,
+                  "isSnippet": false,
                   "label": "create module",
                   "workspaceEdit": {
                     "documentChanges": [
@@ -429,6 +512,7 @@ fn main() {{}}
               "arguments": [
                 {
                   "cursorPosition": null,
+                  "isSnippet": false,
                   "label": "create module",
                   "workspaceEdit": {
                     "documentChanges": [
@@ -509,6 +593,7 @@ fn main() {{}}
             "position": { "character": 4, "line": 1 },
             "textDocument": { "uri": "file:///[..]src/m0.rs" }
           },
+          "isSnippet": false,
           "label": "on enter",
           "workspaceEdit": {
             "documentChanges": [
@@ -561,6 +646,7 @@ version = \"0.0.0\"
             "position": { "line": 1, "character": 4 },
             "textDocument": { "uri": "file:///[..]src/main.rs" }
           },
+          "isSnippet": false,
           "label": "on enter",
           "workspaceEdit": {
             "documentChanges": [
@@ -78,6 +78,22 @@ export async function createClient(serverPath: string, cwd: string): Promise<lc.
     // implementations are still in the "proposed" category for 3.16.
     res.registerFeature(new CallHierarchyFeature(res));
     res.registerFeature(new SemanticTokensFeature(res));
+    res.registerFeature(new ExperimentalFeatures());
 
     return res;
 }
+
+/**
+ * Advertises the rust-analyzer specific extensions the client handles:
+ * `rust-analyzer/statusNotification`, and `SourceChange`s with snippets.
+ */
+class ExperimentalFeatures implements lc.StaticFeature {
+    fillClientCapabilities(capabilities: lc.ClientCapabilities): void {
+        capabilities.experimental = {
+            ...capabilities.experimental,
+            statusNotification: true,
+            snippetTextEdit: true,
+        };
+    }
+    initialize(): void { }
+}
@@ -6,20 +6,45 @@ import { Ctx, Cmd } from '../ctx';
 import * as sourceChange from '../source_change';
 
 export * from './analyzer_status';
+export * from './memory_usage';
 export * from './matching_brace';
 export * from './join_lines';
 export * from './on_enter';
 export * from './parent_module';
+export * from './super_method';
+export * from './open_cargo_toml';
 export * from './syntax_tree';
+export * from './view_hir';
 export * from './expand_macro';
 export * from './runnables';
 export * from './ssr';
+export * from './safe_delete';
 export * from './server_version';
 
 export function collectGarbage(ctx: Ctx): Cmd {
     return async () => ctx.client.sendRequest(ra.collectGarbage, null);
 }
 
+export function startProfiling(ctx: Ctx): Cmd {
+    return async () => {
+        const input = await vscode.window.showInputBox({
+            prompt: 'Number of requests to profile',
+            value: '10',
+            validateInput: (value) => /^[1-9][0-9]*$/.test(value) ? null : 'Enter a positive number',
+        });
+        if (input === undefined) return;
+        await ctx.client.sendRequest(ra.startProfiling, { requests: parseInt(input, 10) });
+    };
+}
+
+export function stopProfiling(ctx: Ctx): Cmd {
+    return async () => {
+        const profiles = await ctx.client.sendRequest(ra.stopProfiling, null);
+        const document = await vscode.workspace.openTextDocument({ content: profiles });
+        return vscode.window.showTextDocument(document, vscode.ViewColumn.Two, true);
+    };
+}
+
 export function showReferences(ctx: Ctx): Cmd {
     return (uri: string, position: lc.Position, locations: lc.Location[]) => {
         const client = ctx.client;
@@ -0,0 +1,41 @@
+import * as vscode from 'vscode';
+
+import * as ra from '../rust-analyzer-api';
+import { Ctx, Cmd } from '../ctx';
+
+// Shows the memory used by each query. Measuring it clears the caches of the
+// server, so the document is only refreshed when the command is invoked.
+export function memoryUsage(ctx: Ctx): Cmd {
+    const tdcp = new TextDocumentContentProvider(ctx);
+
+    ctx.pushCleanup(
+        vscode.workspace.registerTextDocumentContentProvider(
+            'rust-analyzer-memory',
+            tdcp,
+        ),
+    );
+
+    return async () => {
+        tdcp.eventEmitter.fire(tdcp.uri);
+        const document = await vscode.workspace.openTextDocument(tdcp.uri);
+        return vscode.window.showTextDocument(document, vscode.ViewColumn.Two, true);
+    };
+}
+
+class TextDocumentContentProvider implements vscode.TextDocumentContentProvider {
+    readonly uri = vscode.Uri.parse('rust-analyzer-memory://memory');
+    readonly eventEmitter = new vscode.EventEmitter<vscode.Uri>();
+
+    constructor(private readonly ctx: Ctx) {
+    }
+
+    provideTextDocumentContent(_uri: vscode.Uri): vscode.ProviderResult<string> {
+        return this.ctx.client.sendRequest(ra.memoryUsage, null).then((mem) => {
+            return 'Per-query memory usage:\n' + mem + '\n(note: the caches were cleared)';
+        });
+    }
+
+    get onDidChange(): vscode.Event<vscode.Uri> {
+        return this.eventEmitter.event;
+    }
+}
@@ -63,26 +63,51 @@ export function runSingle(ctx: Ctx): Cmd {
 }
 
 export function debugSingle(ctx: Ctx): Cmd {
-    return async (config: ra.Runnable) => {
+    return async (runnable: ra.Runnable) => {
         const editor = ctx.activeRustEditor;
-        if (!editor) return;
-        if (!vscode.extensions.getExtension("vadimcn.vscode-lldb")) {
-            vscode.window.showErrorMessage("Install `vadimcn.vscode-lldb` extension for debugging");
+        const client = ctx.client;
+        if (!editor || !client) return;
+
+        const debugEngine = [
+            { extension: "vadimcn.vscode-lldb", create: lldbConfig },
+            { extension: "ms-vscode.cpptools", create: cppdbgConfig },
+        ].find(it => vscode.extensions.getExtension(it.extension));
+        if (!debugEngine) {
+            vscode.window.showErrorMessage("Install `vadimcn.vscode-lldb` or `ms-vscode.cpptools` extension for debugging");
             return;
         }
 
-        const debugConfig = {
-            type: "lldb",
-            request: "launch",
-            name: config.label,
-            cargo: {
-                args: config.args,
-            },
-            args: config.extraArgs,
-            cwd: config.cwd
-        };
+        const config = await vscode.window.withProgress({
+            location: vscode.ProgressLocation.Window,
+            title: `Building ${runnable.label}`,
+        }, () => client.sendRequest(ra.debugRunnable, runnable));
+
+        return vscode.debug.startDebugging(undefined, debugEngine.create(config));
+    };
+}
+
+function lldbConfig(config: ra.DebugConfig): vscode.DebugConfiguration {
+    return {
+        type: "lldb",
+        request: "launch",
+        name: config.name,
+        program: config.program,
+        args: config.args,
+        env: config.env,
+        cwd: config.cwd,
+    };
+}
 
-        return vscode.debug.startDebugging(undefined, debugConfig);
+function cppdbgConfig(config: ra.DebugConfig): vscode.DebugConfiguration {
+    return {
+        type: "cppdbg",
+        request: "launch",
+        name: config.name,
+        program: config.program,
+        args: config.args,
+        environment: Object.entries(config.env).map(([name, value]) => ({ name, value })),
+        cwd: config.cwd ?? ".",
+        MIMode: "gdb",
     };
 }
 
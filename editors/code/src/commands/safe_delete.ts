@@ -0,0 +1,37 @@
+import * as vscode from 'vscode';
+import * as ra from '../rust-analyzer-api';
+
+import { Ctx, Cmd } from '../ctx';
+import { applySourceChange } from '../source_change';
+
+export function safeDelete(ctx: Ctx): Cmd {
+    return async () => {
+        const editor = ctx.activeRustEditor;
+        const client = ctx.client;
+        if (!editor || !client) return;
+
+        const position = client.code2ProtocolConverter.asPosition(editor.selection.active);
+        const result = await client.sendRequest(ra.safeDelete, {
+            textDocument: { uri: editor.document.uri.toString() },
+            position,
+        });
+        if (!result) {
+            vscode.window.showInformationMessage("Nothing to delete at the cursor");
+            return;
+        }
+        if (result.change) {
+            await applySourceChange(ctx, result.change);
+            return;
+        }
+
+        vscode.window.showWarningMessage(
+            `Can't delete, ${result.blockers.length} reference(s) remain`,
+        );
+        await vscode.commands.executeCommand(
+            'editor.action.showReferences',
+            editor.document.uri,
+            editor.selection.active,
+            result.blockers.map(client.protocol2CodeConverter.asLocation),
+        );
+    };
+}
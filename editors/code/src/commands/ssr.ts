@@ -6,15 +6,20 @@ import { applySourceChange } from '../source_change';
 
 export function ssr(ctx: Ctx): Cmd {
     return async () => {
+        const editor = ctx.activeRustEditor;
         const client = ctx.client;
-        if (!client) return;
+        if (!editor || !client) return;
+
+        // Paths in the query are resolved at the cursor.
+        const textDocument = { uri: editor.document.uri.toString() };
+        const position = client.code2ProtocolConverter.asPosition(editor.selection.active);
 
         const options: vscode.InputBoxOptions = {
             value: "() ==>> ()",
             prompt: "EnteR request, for example 'Foo($a:expr) ==> Foo::new($a)' ",
             validateInput: async (x: string) => {
                 try {
-                    await client.sendRequest(ra.ssr, { query: x, parseOnly: true });
+                    await client.sendRequest(ra.ssr, { query: x, parseOnly: true, textDocument, position });
                 } catch (e) {
                     return e.toString();
                 }
@@ -25,7 +30,7 @@ export function ssr(ctx: Ctx): Cmd {
 
         if (!request) return;
 
-        const change = await client.sendRequest(ra.ssr, { query: request, parseOnly: false });
+        const change = await client.sendRequest(ra.ssr, { query: request, parseOnly: false, textDocument, position });
 
         await applySourceChange(ctx, change);
     };
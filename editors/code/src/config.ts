@@ -11,7 +11,6 @@ export class Config {
     private readonly rootSection = "rust-analyzer";
     private readonly requiresReloadOpts = [
         "serverPath",
-        "cargo",
         "files",
         "highlighting",
         "updates.channel",
@@ -97,6 +96,7 @@ export class Config {
             typeHints: this.get<boolean>("inlayHints.typeHints"),
             parameterHints: this.get<boolean>("inlayHints.parameterHints"),
             chainingHints: this.get<boolean>("inlayHints.chainingHints"),
+            dropHints: this.get<boolean>("inlayHints.dropHints"),
             maxLength: this.get<null | number>("inlayHints.maxLength"),
         };
     }
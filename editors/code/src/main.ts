@@ -5,7 +5,7 @@ import { promises as fs } from "fs";
 
 import * as commands from './commands';
 import { activateInlayHints } from './inlay_hints';
-import { activateStatusDisplay } from './status_display';
+import { activateStatusBar } from './status';
 import { Ctx } from './ctx';
 import { Config, NIGHTLY_TAG } from './config';
 import { log, assert } from './util';
@@ -71,11 +71,17 @@ export async function activate(context: vscode.ExtensionContext) {
     });
 
     ctx.registerCommand('analyzerStatus', commands.analyzerStatus);
+    ctx.registerCommand('memoryUsage', commands.memoryUsage);
     ctx.registerCommand('collectGarbage', commands.collectGarbage);
+    ctx.registerCommand('startProfiling', commands.startProfiling);
+    ctx.registerCommand('stopProfiling', commands.stopProfiling);
     ctx.registerCommand('matchingBrace', commands.matchingBrace);
     ctx.registerCommand('joinLines', commands.joinLines);
     ctx.registerCommand('parentModule', commands.parentModule);
+    ctx.registerCommand('superMethod', commands.superMethod);
+    ctx.registerCommand('openCargoToml', commands.openCargoToml);
     ctx.registerCommand('syntaxTree', commands.syntaxTree);
+    ctx.registerCommand('viewHir', commands.viewHir);
     ctx.registerCommand('expandMacro', commands.expandMacro);
     ctx.registerCommand('run', commands.run);
 
@@ -83,6 +89,7 @@ export async function activate(context: vscode.ExtensionContext) {
     ctx.registerCommand('onEnter', commands.onEnter);
 
     ctx.registerCommand('ssr', commands.ssr);
+    ctx.registerCommand('safeDelete', commands.safeDelete);
     ctx.registerCommand('serverVersion', commands.serverVersion);
 
     // Internal commands which are invoked by the server.
@@ -94,9 +101,8 @@ export async function activate(context: vscode.ExtensionContext) {
 
     ctx.pushCleanup(activateTaskProvider(workspaceFolder));
 
-    activateStatusDisplay(ctx);
-
     activateInlayHints(ctx);
+    activateStatusBar(ctx);
 
     vscode.workspace.onDidChangeConfiguration(
         _ => ctx?.client?.sendNotification('workspace/didChangeConfiguration', { settings: "" }),
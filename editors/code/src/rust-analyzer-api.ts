@@ -19,6 +19,26 @@ function notification<TParam>(method: string) {
 export const analyzerStatus = request<null, string>("analyzerStatus");
 
 
+export interface ServerStatus {
+    workspaceLoaded: boolean;
+    rootsScanned: number;
+    rootsTotal: number;
+    packages: number;
+    procMacroSrv: Option<string>;
+    loadOutDirsFromCheck: boolean;
+    loadingErrors: Vec<string>;
+    analysis?: string;
+}
+export const status = request<null, ServerStatus>("status");
+export const serverStatus = notification<ServerStatus>("rust-analyzer/statusNotification");
+
+
+export const memoryUsage = request<null, string>("memoryUsage");
+export interface StartProfilingParams {
+    requests: number;
+}
+export const startProfiling = request<StartProfilingParams, null>("startProfiling");
+export const stopProfiling = request<null, string>("stopProfiling");
 export const collectGarbage = request<null, null>("collectGarbage");
 
 
@@ -29,6 +49,9 @@ export interface SyntaxTreeParams {
 export const syntaxTree = request<SyntaxTreeParams, string>("syntaxTree");
 
 
+export const viewHir = request<lc.TextDocumentPositionParams, string>("viewHir");
+
+
 export interface ExpandMacroParams {
     textDocument: lc.TextDocumentIdentifier;
     position: Option<lc.Position>;
@@ -61,6 +84,14 @@ export const decorationsRequest = request<lc.TextDocumentIdentifier, Vec<Decorat
 
 export const parentModule = request<lc.TextDocumentPositionParams, Vec<lc.Location>>("parentModule");
 
+export const superMethod = request<lc.TextDocumentPositionParams, Vec<lc.Location>>("superMethod");
+
+
+export interface OpenCargoTomlParams {
+    textDocument: lc.TextDocumentIdentifier;
+}
+export const openCargoToml = request<OpenCargoTomlParams, Option<lc.Location>>("openCargoToml");
+
 
 export interface JoinLinesParams {
     textDocument: lc.TextDocumentIdentifier;
@@ -86,13 +117,49 @@ export interface Runnable {
 }
 export const runnables = request<RunnablesParams, Vec<Runnable>>("runnables");
 
-export type InlayHint = InlayHint.TypeHint | InlayHint.ParamHint | InlayHint.ChainingHint;
+export interface DebugConfig {
+    name: string;
+    program: string;
+    args: Vec<string>;
+    env: FxHashMap<string, string>;
+    cwd: Option<string>;
+}
+export const debugRunnable = request<Runnable, DebugConfig>("debugRunnable");
+
+export interface TestItem {
+    id: string;
+    label: string;
+    kind: "crate" | "module" | "test";
+    location: lc.Location;
+    children: Vec<TestItem>;
+}
+export const testTree = request<null, Vec<TestItem>>("testTree");
+
+export interface RunTestsParams {
+    id: string;
+}
+export interface TestRunSummary {
+    passed: number;
+    failed: number;
+    ignored: number;
+}
+export const runTests = request<RunTestsParams, TestRunSummary>("runTests");
+
+export interface TestResultParams {
+    id: string;
+    status: "started" | "passed" | "failed" | "ignored";
+    output: Option<string>;
+}
+export const testResult = notification<TestResultParams>("rust-analyzer/testResult");
+
+export type InlayHint = InlayHint.TypeHint | InlayHint.ParamHint | InlayHint.ChainingHint | InlayHint.DropHint;
 
 export namespace InlayHint {
     export const enum Kind {
         TypeHint = "TypeHint",
         ParamHint = "ParameterHint",
         ChainingHint = "ChainingHint",
+        DropHint = "DropHint",
     }
     interface Common {
         range: lc.Range;
@@ -101,6 +168,7 @@ export namespace InlayHint {
     export type TypeHint = Common & { kind: Kind.TypeHint };
     export type ParamHint = Common & { kind: Kind.ParamHint };
     export type ChainingHint = Common & { kind: Kind.ChainingHint };
+    export type DropHint = Common & { kind: Kind.DropHint };
 }
 export interface InlayHintsParams {
     textDocument: lc.TextDocumentIdentifier;
@@ -108,12 +176,18 @@ export interface InlayHintsParams {
 export const inlayHints = request<InlayHintsParams, Vec<InlayHint>>("inlayHints");
 
 
-export interface SsrParams {
+export interface SsrParams extends lc.TextDocumentPositionParams {
     query: string;
     parseOnly: boolean;
 }
 export const ssr = request<SsrParams, SourceChange>("ssr");
 
+export interface SafeDeleteResult {
+    change: Option<SourceChange>;
+    blockers: Vec<lc.Location>;
+}
+export const safeDelete = request<lc.TextDocumentPositionParams, Option<SafeDeleteResult>>("safeDelete");
+
 
 export const publishDecorations = notification<PublishDecorationsParams>("publishDecorations");
 
@@ -122,4 +196,5 @@ export interface SourceChange {
     label: string;
     workspaceEdit: lc.WorkspaceEdit;
     cursorPosition: Option<lc.TextDocumentPositionParams>;
+    isSnippet: boolean;
 }
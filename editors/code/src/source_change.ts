@@ -8,6 +8,11 @@ export async function applySourceChange(ctx: Ctx, change: ra.SourceChange) {
     const client = ctx.client;
     if (!client) return;
 
+    if (change.isSnippet) {
+        await applySnippetChange(client, change);
+        return;
+    }
+
     const wsEdit = client.protocol2CodeConverter.asWorkspaceEdit(
         change.workspaceEdit,
     );
@@ -50,3 +55,65 @@ export async function applySourceChange(ctx: Ctx, change: ra.SourceChange) {
         );
     }
 }
+
+/**
+ * Applies a change whose text edits use the snippet syntax. The change is
+ * applied as a workspace edit, including any file operations, with the edits
+ * which have tab stops left out. Those are then inserted as snippets, so that
+ * the editor ends up with the cursor at the `$0` tab stop.
+ */
+async function applySnippetChange(client: lc.LanguageClient, change: ra.SourceChange) {
+    const snippets: { uri: vscode.Uri; position: vscode.Position; text: string }[] = [];
+    const documentChanges = (change.workspaceEdit.documentChanges ?? []).map(docChange => {
+        if (!lc.TextDocumentEdit.is(docChange)) return docChange;
+
+        const uri = client.protocol2CodeConverter.asUri(docChange.textDocument.uri);
+        // Every edit uses the snippet syntax, but only some have tab stops.
+        const edits = docChange.edits.map(edit =>
+            hasTabStop(edit.newText)
+                ? { range: edit.range, newText: '' }
+                : { range: edit.range, newText: unescapeSnippet(edit.newText) },
+        );
+        docChange.edits.forEach((edit, idx) => {
+            if (!hasTabStop(edit.newText)) return;
+            // The server sends the edits sorted and without overlaps.
+            const position = mapPosition(edit.range.start, edits.slice(0, idx));
+            snippets.push({ uri, position, text: edit.newText });
+        });
+        return { textDocument: docChange.textDocument, edits };
+    });
+    const wsEdit = client.protocol2CodeConverter.asWorkspaceEdit({ documentChanges });
+    await vscode.workspace.applyEdit(wsEdit);
+
+    // Later snippets go first, so that the positions of the earlier ones stay valid.
+    for (const snippet of snippets.reverse()) {
+        const doc = await vscode.workspace.openTextDocument(snippet.uri);
+        const editor = await vscode.window.showTextDocument(doc);
+        await editor.insertSnippet(new vscode.SnippetString(snippet.text), snippet.position);
+    }
+}
+
+/** Returns where `position` ends up after applying `edits`, which precede it. */
+function mapPosition(position: lc.Position, edits: lc.TextEdit[]): vscode.Position {
+    let { line, character } = position;
+    // Going backwards keeps the ranges of the remaining edits valid.
+    for (const edit of [...edits].reverse()) {
+        const { start, end } = edit.range;
+        const lines = edit.newText.split('\n');
+        if (end.line === line) {
+            const lastLine = lines[lines.length - 1];
+            const newEnd = lines.length === 1 ? start.character + lastLine.length : lastLine.length;
+            character = newEnd + character - end.character;
+        }
+        line += lines.length - 1 - (end.line - start.line);
+    }
+    return new vscode.Position(line, character);
+}
+
+function hasTabStop(snippet: string): boolean {
+    return /(^|[^\\])(\\\\)*\$(\d|\{)/.test(snippet);
+}
+
+function unescapeSnippet(snippet: string): string {
+    return snippet.replace(/\\([\\$}])/g, '$1');
+}
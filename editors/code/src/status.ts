@@ -0,0 +1,25 @@
+import * as vscode from 'vscode';
+import * as ra from './rust-analyzer-api';
+
+import { Ctx } from './ctx';
+
+export function activateStatusBar(ctx: Ctx) {
+    const statusBarItem = vscode.window.createStatusBarItem(vscode.StatusBarAlignment.Left, 10);
+    statusBarItem.command = "rust-analyzer.analyzerStatus";
+    ctx.pushCleanup(statusBarItem);
+
+    ctx.client.onNotification(ra.serverStatus, status => {
+        if (!status.workspaceLoaded) {
+            statusBarItem.text = `$(sync~spin) rust-analyzer: ${status.rootsScanned}/${status.rootsTotal}`;
+        } else if (status.loadingErrors.length > 0) {
+            statusBarItem.text = "$(warning) rust-analyzer";
+        } else {
+            statusBarItem.text = "rust-analyzer";
+        }
+        statusBarItem.tooltip = [
+            `${status.packages} packages loaded`,
+            ...status.loadingErrors,
+        ].join("\n");
+        statusBarItem.show();
+    });
+}
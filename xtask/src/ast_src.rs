@@ -304,7 +304,18 @@ macro_rules! ast_enums {
 }
 
 pub(crate) const AST_SRC: AstSrc = AstSrc {
-    tokens: &["Whitespace", "Comment", "String", "RawString"],
+    tokens: &[
+        "Whitespace",
+        "Comment",
+        "String",
+        "RawString",
+        "ByteString",
+        "RawByteString",
+        "Char",
+        "Byte",
+        "IntNumber",
+        "FloatNumber",
+    ],
     nodes: &ast_nodes! {
         struct SourceFile: ModuleItemOwner, AttrsOwner {
             modules: [Module],
@@ -451,7 +462,7 @@ pub(crate) const AST_SRC: AstSrc = AstSrc {
         struct ContinueExpr: AttrsOwner { T![continue], T![lifetime] }
         struct BreakExpr: AttrsOwner { T![break], T![lifetime], Expr }
         struct Label { T![lifetime] }
-        struct BlockExpr: AttrsOwner { Label, T![unsafe], Block  }
+        struct BlockExpr: AttrsOwner { Label, T![unsafe], T![async], Block  }
         struct ReturnExpr: AttrsOwner { Expr }
         struct CallExpr: ArgListOwner { Expr }
         struct MethodCallExpr: AttrsOwner, ArgListOwner {